@@ -1,44 +1,50 @@
 use arc_swap::{access::Map, ArcSwap};
+use async_openai::types::ChatCompletionMessageToolCall;
 use futures_util::Stream;
 use helix_core::{diagnostic::Severity, syntax};
 use helix_lsp::{
   lsp::{self, notification::Notification},
-  Call, LspProgressMap,
+  Call, LspProgressMap, OffsetEncoding,
 };
 use helix_stdx::path::get_relative_path;
 use helix_view::{
-  document::DocumentSavedEventResult,
+  document::{DocumentSavedEventResult, Mode},
   editor::{ConfigEvent, EditorEvent},
   graphics::Rect,
+  input::KeyEvent,
   theme, Editor,
 };
 use sazid::{
-  action::{ChatToolAction, LsiAction, SessionAction},
+  action::{ChatToolAction, LsiAction, SessionAction, ToolType},
   app::{
-    lsi::interface::LanguageServerInterface, model_tools::tool_call::ChatTools,
+    input_history::InputHistory, lsi::interface::LanguageServerInterface, mentions::MentionScope,
+    model_tools::tool_call::ChatTools, plan::PlanState, prompt_profiles::DEFAULT_PROMPT_PROFILE,
     session_config::WorkspaceParams,
   },
   components::session::Session,
 };
 use serde_json::json;
 use tokio::sync::mpsc;
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamMap};
 use tui::backend::Backend;
 
 use crate::{
   args::Args,
-  commands::ChatMessageItem,
+  commands::{self, open_session_store, ChatMessageItem},
   compositor::{self, Compositor, ContextFocus, Event},
   config::Config,
-  handlers,
-  job::Jobs,
-  ui::{self, EditorView},
+  ctrl, handlers,
+  job::{Callback, Jobs},
+  key,
+  keymap::Keymaps,
+  ui::{self, overlay::overlaid, EditorView, PromptEvent},
+  widgets::diff::DiffStatus,
 };
 
 use log::{debug, error, info, warn};
 #[cfg(not(feature = "integration"))]
 use std::io::stdout;
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Instant};
 
 use anyhow::{Context, Error, Result};
 
@@ -70,7 +76,9 @@ pub struct Application {
   pub editor: Editor,
 
   session: Session,
-  session_events: UnboundedReceiverStream<SessionAction>,
+  // the actions of every open session, by session id. the sessions in background tabs are kept
+  // by `ui::SessionTabs`
+  session_events: StreamMap<i64, UnboundedReceiverStream<SessionAction>>,
 
   language_server_interface: LanguageServerInterface,
   language_server_interface_events: UnboundedReceiverStream<LsiAction>,
@@ -78,6 +86,9 @@ pub struct Application {
   chat_tools: ChatTools,
   chat_tools_events: UnboundedReceiverStream<ChatToolAction>,
 
+  // shared with the completion handler, see `MentionScope`
+  mention_scope: MentionScope,
+
   config: Arc<ArcSwap<Config>>,
 
   #[allow(dead_code)]
@@ -88,6 +99,10 @@ pub struct Application {
   signals: Signals,
   jobs: Jobs,
   lsp_progress: LspProgressMap,
+
+  last_render: Instant,
+  /// set when a render was skipped to coalesce redraws, see `RenderPerf::redraw_interval`
+  deferred_render_at: Option<Instant>,
 }
 
 #[cfg(feature = "integration")]
@@ -152,7 +167,9 @@ impl Application {
     let area = terminal.size().expect("couldn't get terminal size");
     let mut compositor = Compositor::new(area);
     let config = Arc::new(ArcSwap::from_pointee(config));
-    let handlers = handlers::setup(config.clone());
+    // the completion of mentions sees the workspace of the session shown and the symbol index
+    let mention_scope = MentionScope::default();
+    let handlers = handlers::setup(config.clone(), mention_scope.clone());
     let mut editor = Editor::new(
       area,
       theme_loader.clone(),
@@ -161,7 +178,8 @@ impl Application {
       handlers,
     );
 
-    let _keys = Box::new(Map::new(Arc::clone(&config), |config: &Config| &config.keys));
+    // the keymap follows the config, so bindings changed in the config file apply on reload
+    let keys = Box::new(Map::new(Arc::clone(&config), |config: &Config| &config.keys));
 
     editor.new_file(Action::VerticalSplit);
     editor.set_theme(theme);
@@ -169,11 +187,11 @@ impl Application {
     // Language Server Interface Configuration
     let (lsi_tx, lsi_rx) = mpsc::unbounded_channel();
     let language_server_interface_events = UnboundedReceiverStream::new(lsi_rx);
-    let language_server_interface = LanguageServerInterface::new(syn_loader.clone(), lsi_tx);
+    let mut language_server_interface = LanguageServerInterface::new(syn_loader.clone(), lsi_tx);
+    language_server_interface.symbol_index = mention_scope.symbols.clone();
 
     // Session Configuration
     let (session_tx, session_rx) = mpsc::unbounded_channel();
-    let session_events = UnboundedReceiverStream::new(session_rx);
     let mut session_config = config.load().session.clone();
 
     match (args.workspace, args.language) {
@@ -183,6 +201,7 @@ impl Application {
           language,
           language_server: args.language_server.unwrap_or("rust-analyzer".to_string()),
           doc_path: None,
+          name: None,
         });
         log::debug!("workspace: {:#?}", session_config.workspace);
       },
@@ -195,21 +214,73 @@ impl Application {
       },
     }
 
+    if args.openai_org_id.is_some() {
+      session_config.openai.organization_id = args.openai_org_id;
+    }
+    if args.openai_project_id.is_some() {
+      session_config.openai.project_id = args.openai_project_id;
+    }
+    if args.openai_profile.is_some() {
+      session_config.openai.profile = args.openai_profile;
+    }
+
+    if session_config.audit_dir.is_none() {
+      session_config.audit_dir = Some(helix_loader::data_dir().join("audit"));
+    }
     let mut session = Session::new(session_tx, Some(session_config));
-    session.set_system_prompt("you are an expert programming assistant");
+    session.symbol_index = mention_scope.symbols.clone();
+    let history_path = helix_loader::data_dir().join("input_history.jsonl");
+    match InputHistory::load(&history_path) {
+      Ok(input_history) => session.input_history = input_history,
+      Err(e) => log::warn!("unable to load input history {}: {}", history_path.display(), e),
+    }
+
+    // a session named on the command line is opened instead of the new one
+    let startup_session_id = match (args.session_id, args.resume) {
+      (Some(session_id), _) => Some(session_id),
+      (None, true) => {
+        let sessions = open_session_store()
+          .and_then(|store| store.list_sessions())
+          .map_err(|e| anyhow::anyhow!("unable to list sessions: {}", e))?;
+        if sessions.is_empty() {
+          editor.set_status("no saved session to resume");
+        }
+        sessions.first().map(|session| session.id.clone())
+      },
+      (None, false) => None,
+    };
+    match &startup_session_id {
+      Some(session_id) => open_session_store()
+        .and_then(|store| {
+          session.load_session(&store, session_id, Some(ui::session::SESSION_LOAD_MESSAGE_LIMIT))
+        })
+        .map_err(|e| anyhow::anyhow!("unable to load session {}: {}", session_id, e))?,
+      // an opened session keeps the system message it was saved with
+      None => {
+        let prompt_profile =
+          session.config.prompt_profile.clone().unwrap_or(DEFAULT_PROMPT_PROFILE.to_string());
+        session.select_prompt_profile(&prompt_profile).map_err(|e| anyhow::anyhow!("{}", e))?;
+      },
+    }
+    let startup_picker_sessions =
+      if startup_session_id.is_none() && !args.resume && session.config.startup_session_picker {
+        open_session_store().and_then(|store| store.list_sessions()).unwrap_or_else(|e| {
+          log::warn!("unable to list sessions for the startup picker: {}", e);
+          vec![]
+        })
+      } else {
+        vec![]
+      };
 
     // Tool Configuration
     let (tool_tx, tool_rx) = mpsc::unbounded_channel();
     let chat_tools: ChatTools = ChatTools::new(tool_tx, session.id, session.config.clone());
     let chat_tools_events = UnboundedReceiverStream::new(tool_rx);
+    let mut session_events = StreamMap::new();
+    session_events.insert(session.id, UnboundedReceiverStream::new(session_rx));
 
     // Load existing messages
-    let messages = session
-      .messages
-      .clone()
-      .iter()
-      .map(|message| ChatMessageItem::new_chat(message.message_id, message.message.clone()))
-      .collect::<Vec<_>>();
+    let messages = session.messages.iter().map(ChatMessageItem::from_container).collect::<Vec<_>>();
 
     let session_callback = |_context: &mut compositor::Context,
                             _message: &ChatMessageItem,
@@ -256,12 +327,23 @@ impl Application {
 
     editor.switch(doc_id, Action::Replace);
 
-    let mut input = EditorView::new(crate::keymap::minimal_keymap());
+    let mut input = EditorView::new(Keymaps::new(keys));
     input.override_height(markdown_session.input_height, ui::editor::VerticalAlign::Bottom);
+    let statusline = ui::SessionStatusline::new(markdown_session.input_height);
+    let tabs = ui::SessionTabs::new(session.id, mention_scope.symbols.clone());
 
     // session must be pushed after input in order for input not to overwrite style changes made in session
     compositor.push(Box::new(markdown_session));
     compositor.push(Box::new(input));
+    compositor.push(Box::new(tabs));
+    compositor.push(Box::new(statusline));
+    if !startup_picker_sessions.is_empty() {
+      compositor.push(Box::new(overlaid(ui::session::session_picker(startup_picker_sessions))));
+    }
+    // without an api key the sessions can still be browsed, the setup screen says how to add one
+    if !session.provider_configured() {
+      compositor.push(Box::new(ui::ProviderSetup));
+    }
 
     #[cfg(windows)]
     let signals = futures_util::stream::empty();
@@ -275,6 +357,9 @@ impl Application {
     ])
     .context("build signal handler")?;
 
+    #[cfg(not(feature = "integration"))]
+    crate::config::watch_config_files(editor.config_events.0.clone());
+
     let app = Self {
       compositor,
       terminal,
@@ -291,12 +376,17 @@ impl Application {
       chat_tools,
       chat_tools_events,
 
+      mention_scope,
+
       theme_loader,
       syn_loader,
 
       signals,
       jobs: Jobs::new(),
       lsp_progress: LspProgressMap::new(),
+
+      last_render: Instant::now(),
+      deferred_render_at: None,
     };
 
     Ok(app)
@@ -329,12 +419,38 @@ impl Application {
   }
 
   // end test helper functions
+  fn statusline(&mut self) -> &mut ui::SessionStatusline {
+    self.compositor.find_id(ui::SessionStatusline::ID).expect("expected the session statusline")
+  }
+
   async fn render(&mut self) {
+    let redraw_interval = self
+      .compositor
+      .find::<ui::SessionView<ChatMessageItem>>()
+      .map_or(Default::default(), |session_view| session_view.perf.redraw_interval());
+    let next_render_at = self.last_render + redraw_interval;
+    if Instant::now() < next_render_at {
+      self.deferred_render_at = Some(next_render_at);
+      return;
+    }
+    self.deferred_render_at = None;
+
     if self.compositor.full_redraw {
       self.terminal.clear().expect("Cannot clear the terminal");
       self.compositor.full_redraw = false;
     }
 
+    if let Some(panel) =
+      self.compositor.find_id::<ui::LanguageServerPanel>(ui::LanguageServerPanel::ID)
+    {
+      panel.servers = self.language_server_interface.language_server_statuses(&self.lsp_progress);
+    }
+
+    let workspace_root = self.session.workspace_root();
+    if **self.mention_scope.workspace_root.load() != workspace_root {
+      self.mention_scope.workspace_root.store(Arc::new(workspace_root));
+    }
+
     let mut cx = crate::compositor::Context {
       focus: &mut self.focus,
       session: &mut self.session,
@@ -358,7 +474,24 @@ impl Application {
     self.editor.cursor_cache.set(None);
 
     let pos = pos.map(|pos| (pos.col as u16, pos.row as u16));
+    let draw_start = Instant::now();
     self.terminal.draw(pos, kind).unwrap();
+    self.last_render = Instant::now();
+
+    // images are drawn straight to the terminal, over the session and under nothing else, so
+    // they are cleared while a picker, popup or prompt is open
+    let covered = self.compositor.layers_in_front::<ui::SessionStatusline>() != Some(0);
+    if let Some(session_view) = self.compositor.find::<ui::SessionView<ChatMessageItem>>() {
+      if let Err(e) = session_view.draw_diagrams(covered, &mut std::io::stdout()) {
+        log::error!("unable to draw diagrams: {}", e);
+      }
+    }
+
+    if let Some(session_view) = self.compositor.find::<ui::SessionView<ChatMessageItem>>() {
+      if session_view.perf.record_frame(self.last_render - draw_start) {
+        helix_event::request_redraw();
+      }
+    }
   }
 
   pub async fn event_loop<S>(&mut self, input_stream: &mut S)
@@ -384,9 +517,11 @@ impl Application {
       }
 
       use futures_util::StreamExt;
+      self.watch_opened_sessions();
       let session_tx = self.session.action_tx.clone().unwrap();
       let lsi_tx = self.language_server_interface.tx.clone();
       let chat_tool_tx = self.chat_tools.tx.clone();
+      let deferred_render_at = self.deferred_render_at;
 
       tokio::select! {
           biased;
@@ -400,7 +535,7 @@ impl Application {
               self.handle_terminal_events(event).await;
           }
           Some(callback) = self.jobs.callbacks.recv() => {
-              self.jobs.handle_callback(&mut self.editor, &mut self.compositor, Ok(Some(callback)));
+              self.handle_job_callback(Ok(Some(callback))).await;
               self.render().await;
           }
           Some(msg) = self.jobs.status_messages.recv() => {
@@ -417,77 +552,132 @@ impl Application {
 
           Some(callback) = self.jobs.wait_futures.next() => {
               log::debug!("received callback " );
-              self.jobs.handle_callback(&mut self.editor, &mut self.compositor, callback);
+              self.handle_job_callback(callback).await;
               self.render().await;
           }
 
 
-          Some(action) = self.session_events.next() => {
-                  match action.clone() {
-                      SessionAction::SaveSession => {
-                        let data_folder = helix_loader::data_dir().join("session_history");
-                        if !data_folder.exists() {
-                            if let Err(e) = std::fs::create_dir_all(&data_folder) {
-                                self.editor.set_error(format!("error creating data directory: {}", e));
-                            }
-                        }
-                        let save_path = data_folder.join(self.session.config.title.clone()).with_extension("szd");
-                        log::info!("saving session history to: {:#?}", save_path );
-                        match self.session.save_session(save_path.clone()) {
-                        Ok(_) => self.editor.set_status(format!("session saved to: {:?}", save_path)),
-                        Err(e) => {
-                            log::error!("error saving session: {}", e);
-                            self.editor.set_error(format!("error saving session: {}", e));
+          Some((session_id, action)) = self.session_events.next() => {
+              if session_id != self.session.id {
+                  self.handle_background_session_action(session_id, action).await;
+              } else {
+                    match action.clone() {
+                        SessionAction::SaveSession => {
+                          log::info!("saving session: {}", self.session.config.title);
+                          match crate::commands::open_session_store()
+                            .and_then(|mut store| self.session.save_session(&mut store))
+                          {
+                          Ok(_) => self.editor.set_status(format!("session saved: {}", self.session.config.title)),
+                          Err(e) => {
+                              log::error!("error saving session: {}", e);
+                              self.editor.set_error(format!("error saving session: {}", e));
+                          },
+                          };
+                        },
+                        SessionAction::ChatToolAction(event) => {
+                            chat_tool_tx.send(event).unwrap();
                         },
-                        };
+                        SessionAction::LsiAction(event) => {
+                            lsi_tx.send(event).unwrap();
+                        },
+
+                        SessionAction::UpdateStatus(status) => {
+                          self.statusline().set_activity(status);
+                          self.render().await;
+                        }
+                        SessionAction::ReloadMessages(messages) => {
+                            let messages = messages.iter().map(|(id, m)|{
+                                 ChatMessageItem::from_session(
+                                     &self.session,
+                                     *id,
+                                     m.clone())
+                           }).collect();
+
+                          let session = self.compositor.find::<ui::SessionView<ChatMessageItem>>()
+                          .unwrap();
+                            session.reload_messages(messages );
+                          self.render().await;
+                        }
+                        SessionAction::UpdateMessage(message, id) => {
+                         let message = ChatMessageItem::from_session(&self.session, id, message);
+                         self.compositor
+                             .find::<ui::SessionView<ChatMessageItem>>()
+                             .unwrap()
+                             .upsert_message(message);
+                          self.render().await;
                       },
-                      SessionAction::ChatToolAction(event) => {
-                          chat_tool_tx.send(event).unwrap();
+                      SessionAction::FileEdited(session_id, edit) => {
+                         if session_id == self.session.id {
+                             let path = edit.path.clone();
+                             self.compositor
+                                 .find::<ui::SessionView<ChatMessageItem>>()
+                                 .unwrap()
+                                 .add_file_edit(edit);
+                             // open views of the file show the change, and the file beside the
+                             // session follows the files tools change
+                             ui::split_view::reload_changed_file(&mut self.editor, &path);
+                             if let Some(view_id) = ui::split_view::split_view(&mut self.compositor) {
+                                 if let Err(e) = ui::split_view::show_in_split_view(&mut self.editor, Some(view_id), &path) {
+                                     self.editor.set_error(format!("unable to open \"{}\" {}", path.display(), e));
+                                 }
+                             }
+                         }
+                         // the session keeps the edit in its journal so it can be reverted
+                         match self.session.update(action) {
+                             Ok(Some(action)) => session_tx.send(action).unwrap(),
+                             Ok(None) => {},
+                             Err(err) => log::debug!("session update error: {:#?}", err),
+                         }
+                          self.render().await;
                       },
-                      SessionAction::LsiAction(event) => {
-                          lsi_tx.send(event).unwrap();
+                      SessionAction::IndexProgress(_, done, total) => {
+                          self.editor.set_status(format!("indexing workspace {}/{}", done, total));
+                          self.render().await;
                       },
-
-                      SessionAction::UpdateStatus(Some(status)) => {
-                        self.editor.set_status(status);
-                        self.render().await;
-                      }
-                      SessionAction::ReloadMessages(mut messages) => {
-                          messages.sort_unstable_by_key(|k| k.0);
-                          let messages = messages.iter().map(|(id, m)|{
-                               ChatMessageItem::new_chat(
-                                   *id,
-                                   m.clone())
-                         }).collect();
-
-                        let session = self.compositor.find::<ui::SessionView<ChatMessageItem>>()
-                        .unwrap();
-                          session.reload_messages(messages );
-                        self.render().await;
-                      }
-                      SessionAction::UpdateMessage(message, id) => {
-                       self.compositor
-                           .find::<ui::SessionView<ChatMessageItem>>()
-                           .unwrap()
-                           .upsert_message(
-                               ChatMessageItem::new_chat(
-                                   id,
-                                   message
-                                   ));
-                        self.render().await;
-                    },
-                    SessionAction::Error(error) => {
-                      self.editor.set_error(error.to_string());
-                        self.compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap().upsert_message(ChatMessageItem::new_error(error));
-                                    self.render().await;
-                                },
-                        _ => match self.session.update(action) {
-                                Ok(Some(action)) => {session_tx.send(action).unwrap()},
-                                Ok(None) => {},
-                                Err(err) => log::debug!("session update error: {:#?}", err),
-                            }
-                    };
-
+                      SessionAction::IndexComplete(_, indexed, failed) => {
+                          if failed > 0 {
+                              self.editor.set_error(format!(
+                                  "indexed {} changed files, {} could not be indexed", indexed, failed
+                              ));
+                          } else {
+                              self.editor.set_status(format!("indexed {} changed files", indexed));
+                          }
+                          if let Err(err) = self.session.update(action) {
+                              log::debug!("session update error: {:#?}", err);
+                          }
+                          self.render().await;
+                      },
+                      SessionAction::FileEditReverted(tool_call_id) => {
+                         self.compositor
+                             .find::<ui::SessionView<ChatMessageItem>>()
+                             .unwrap()
+                             .set_file_diff_status(&tool_call_id, DiffStatus::Reverted);
+                          // the journal is saved quietly, the status shows the reverted files
+                          if let Err(e) = open_session_store().and_then(|mut store| self.session.save_session(&mut store)) {
+                              log::error!("error saving session: {}", e);
+                          }
+                          self.render().await;
+                      },
+                      SessionAction::Error(error) => {
+                        self.editor.set_error(error.to_string());
+                        self.statusline().set_activity(None);
+                          self.compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap().upsert_message(ChatMessageItem::new_error(error));
+                                      self.render().await;
+                                  },
+                          _ => {
+                              let drafting = self.session.plan.as_ref().is_some_and(|plan| plan.state == PlanState::Drafting);
+                              match self.session.update(action) {
+                                  Ok(Some(action)) => {session_tx.send(action).unwrap()},
+                                  Ok(None) => {},
+                                  Err(err) => log::debug!("session update error: {:#?}", err),
+                              }
+                              // the plan drafted is shown for review as soon as the reply completes
+                              if drafting && self.session.plan.as_ref().is_some_and(|plan| plan.state == PlanState::Review) {
+                                  self.compositor.push(Box::new(ui::PlanEditor::default()));
+                              }
+                          },
+                      };
+              }
           }
 
           Some((id, call)) = self.language_server_interface.language_servers.incoming.next() => {
@@ -501,7 +691,67 @@ impl Application {
           }
 
           Some(action) = self.language_server_interface_events.next() => {
-              if self.language_server_interface.language_servers.iter_clients().all(|client| {
+              let deadlines = &self.language_server_interface.deadlines;
+              if action.query().is_some_and(|query| deadlines.timed_out(query)) {
+                // the model was told the request timed out, it is not run
+                log::info!("dropping a request that timed out: {:?}", action);
+              } else if let LsiAction::RespawnLanguageServer(..)
+                | LsiAction::RestartLanguageServer(_)
+                | LsiAction::StopLanguageServer(_) = action
+              {
+                // servers are started and stopped without waiting for the others to settle
+                self.language_server_interface.handle_action(action);
+              } else if let LsiAction::LanguageServerStartFailed(name, workspaces, error) = action {
+                // a server that did not start is started again like one that crashed
+                match self.language_server_interface.language_server_start_failed(&name, workspaces) {
+                  Some(delay) => self.editor.set_error(format!(
+                    "{} did not start: {}, retrying in {}s",
+                    name,
+                    error,
+                    delay.as_secs()
+                  )),
+                  None => self.editor.set_error(format!("{} did not start: {}", name, error)),
+                }
+                helix_event::request_redraw();
+              } else if let LsiAction::UpdateWorkspaceFileSymbols(..) = action {
+                // symbols are stored as the sync receives them, a server still indexing would
+                // hold them back until it is done
+                self.language_server_interface.handle_action(action);
+              } else if let LsiAction::SymbolSyncProgress(progress) = action {
+                if progress.is_complete() {
+                  self.language_server_interface.refresh_symbol_index();
+                }
+                // the error of each file is in the log
+                if progress.is_complete() && progress.failed > 0 {
+                  self.editor.set_error(progress.message());
+                } else {
+                  self.editor.set_status(progress.message());
+                }
+                helix_event::request_redraw();
+              } else if let LsiAction::SearchSymbols(query) = action {
+                // the symbols are searched as they are stored, without waiting for a sync
+                self.show_symbol_search(&query);
+                self.render().await;
+              } else if let LsiAction::ShowOutline(file_path) = action {
+                self.show_outline(file_path);
+                self.render().await;
+              } else if let LsiAction::SessionAction(action) = action {
+                // answers are not held back by a server that is still busy
+                let late = match action.as_ref() {
+                  SessionAction::ToolCallComplete(ToolType::LsiQuery(query), _) => {
+                    let late = deadlines.timed_out(query);
+                    deadlines.finish(&query.tool_call_id);
+                    late
+                  },
+                  _ => false,
+                };
+                match self.session_tx_for(&action) {
+                  // the model was told the request timed out
+                  _ if late => log::info!("dropping a late answer: {:?}", action),
+                  Some(tx) => tx.send(*action).unwrap(),
+                  None => log::debug!("dropping an action of a closed session: {:?}", action),
+                }
+              } else if self.language_server_interface.language_servers.iter_clients().all(|client| {
                   client.is_initialized() && !self.lsp_progress.is_progressing(client.id())
               }) {
                 match self.language_server_interface.synchronize_workspace_file_changes() {
@@ -511,9 +761,6 @@ impl Application {
                     Ok(false) => {
                         log::debug!("running workspace action: {:?}", action);
                         match action {
-                            LsiAction::SessionAction(action) => {
-                                session_tx.send(*action).unwrap();
-                            },
                             LsiAction::ChatToolResponse(action) => {
                                 chat_tool_tx.send(*action).unwrap();
                             }
@@ -536,11 +783,21 @@ impl Application {
             log::debug!("chat tool action: {:#?}", action);
               match action  {
                 ChatToolAction::SessionAction(action) => {
-                    session_tx.send(*action).unwrap();
+                    match self.session_tx_for(&action) {
+                      Some(tx) => tx.send(*action).unwrap(),
+                      None => log::debug!("dropping an action of a closed session: {:?}", action),
+                    }
                 },
                 ChatToolAction::LsiRequest(action) => {
+                    if let Some(query) = action.query() {
+                      self.language_server_interface.deadlines.start(query);
+                    }
                     lsi_tx.send(*action).unwrap();
                 },
+                ChatToolAction::ConfirmToolCall(tool_call, session_id) => {
+                    self.confirm_tool_call(tool_call, session_id);
+                    self.render().await;
+                },
                 _ => {
                   match self.chat_tools.handle_action(action) {
                       Ok(Some(action)) => {
@@ -557,6 +814,11 @@ impl Application {
               }
 
 
+          _ = tokio::time::sleep_until(deferred_render_at.unwrap_or_else(Instant::now).into()),
+            if deferred_render_at.is_some() => {
+              self.render().await;
+          }
+
           event = self.editor.wait_event() => {
               let _idle_handled = self.handle_editor_event(event).await;
 
@@ -578,6 +840,154 @@ impl Application {
     }
   }
 
+  // listens to the actions of the sessions opened in new tabs, and stops listening to those of
+  // closed tabs, whose tool calls may still be sending
+  fn watch_opened_sessions(&mut self) {
+    let Some(tabs) = self.compositor.find::<ui::SessionTabs>() else {
+      return;
+    };
+    for (session_id, rx) in tabs.take_opened() {
+      self.session_events.insert(session_id, UnboundedReceiverStream::new(rx));
+    }
+    for session_id in tabs.take_closed() {
+      self.session_events.remove(&session_id);
+    }
+  }
+
+  // answers the `:symbols` command with a picker of the symbols of every workspace that match
+  fn show_symbol_search(&mut self, query: &str) {
+    let lsi = &self.language_server_interface;
+    let symbols = match lsi.search_symbols(query) {
+      Ok(symbols) => symbols,
+      Err(e) => {
+        self.editor.set_error(format!("invalid symbol query: {}", e));
+        return;
+      },
+    };
+    if symbols.is_empty() {
+      self.editor.set_status(format!("no symbols match `{}`", query));
+      return;
+    }
+    self.editor.set_status(format!("{} symbols match `{}`", symbols.len(), query));
+    let symbols = symbols
+      .into_iter()
+      .map(|symbol| {
+        let workspace = lsi.workspaces.iter().find(|w| w.workspace_path == symbol.workspace_path);
+        let offset_encoding = workspace
+          .map_or(OffsetEncoding::Utf16, |workspace| workspace.language_server.offset_encoding());
+        (symbol, offset_encoding)
+      })
+      .collect();
+    self.compositor.push(Box::new(commands::source_symbol_picker(symbols)));
+  }
+
+  // answers the `:outline` command with the symbol tree of the file beside its text
+  fn show_outline(&mut self, file_path: PathBuf) {
+    let symbols = match self.language_server_interface.file_outline(&file_path) {
+      Ok(symbols) => symbols,
+      Err(e) => {
+        self.editor.set_error(format!("no outline of {}: {}", file_path.display(), e));
+        return;
+      },
+    };
+    let text = match std::fs::read_to_string(&file_path) {
+      Ok(text) => text,
+      Err(e) => {
+        self.editor.set_error(format!("error reading {}: {}", file_path.display(), e));
+        return;
+      },
+    };
+    self.compositor.remove(ui::FileOutline::ID);
+    self.compositor.push(Box::new(ui::FileOutline::new(file_path, &text, &symbols)));
+  }
+
+  // asks the user whether a tool call with the `confirm` permission runs, escape declines it
+  fn confirm_tool_call(&mut self, tool_call: ChatCompletionMessageToolCall, session_id: i64) {
+    let chat_tool_tx = self.chat_tools.tx.clone();
+    let arguments = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments)
+      .and_then(|arguments| serde_json::to_string_pretty(&arguments))
+      .unwrap_or_else(|_| tool_call.function.arguments.clone());
+    let mut prompt = ui::Prompt::new(
+      format!("run {}? (y/n): ", tool_call.function.name).into(),
+      None,
+      ui::completers::none,
+      move |_cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+        let allowed = match event {
+          PromptEvent::Validate => matches!(input.trim(), "y" | "yes"),
+          PromptEvent::Abort => false,
+          PromptEvent::Update => return,
+        };
+        let answer = ChatToolAction::ToolCallConfirmed(tool_call.clone(), session_id, allowed);
+        chat_tool_tx.send(answer).unwrap();
+      },
+    );
+    prompt.doc_fn = Box::new(move |_| Some(arguments.clone().into()));
+    self.compositor.push(Box::new(prompt));
+  }
+
+  // the channel of the session an action is for, the active session's when it names none. an
+  // action of a session that was closed has nowhere to go
+  fn session_tx_for(
+    &mut self,
+    action: &SessionAction,
+  ) -> Option<mpsc::UnboundedSender<SessionAction>> {
+    match action.session_id() {
+      Some(id) if id != self.session.id => {
+        let tabs = self.compositor.find::<ui::SessionTabs>()?;
+        tabs.background_tab(id)?.session.as_ref()?.action_tx.clone()
+      },
+      _ => self.session.action_tx.clone(),
+    }
+  }
+
+  // a session in a background tab is updated without drawing anything, the screen shows the
+  // active session. its tab is marked once its response is finished or fails
+  async fn handle_background_session_action(&mut self, session_id: i64, action: SessionAction) {
+    let Some(tab) =
+      self.compositor.find::<ui::SessionTabs>().and_then(|tabs| tabs.background_tab(session_id))
+    else {
+      return;
+    };
+    let Some(session) = tab.session.as_mut() else {
+      return;
+    };
+    let mut unread = false;
+    match action {
+      SessionAction::SaveSession => {
+        if let Err(e) = open_session_store().and_then(|mut store| session.save_session(&mut store))
+        {
+          log::error!("error saving session {}: {}", session.config.title, e);
+        }
+      },
+      SessionAction::ChatToolAction(action) => self.chat_tools.tx.send(action).unwrap(),
+      SessionAction::LsiAction(action) => self.language_server_interface.tx.send(action).unwrap(),
+      SessionAction::UpdateStatus(_)
+      | SessionAction::ReloadMessages(_)
+      | SessionAction::UpdateMessage(..)
+      | SessionAction::FileEditReverted(_) => {},
+      SessionAction::Error(error) => {
+        self.editor.set_error(format!("{}: {}", session.config.title, error));
+        unread = true;
+      },
+      action => {
+        if let SessionAction::FileEdited(_, edit) = &action {
+          ui::split_view::reload_changed_file(&mut self.editor, &edit.path);
+        }
+        let answered = matches!(action, SessionAction::AddMessage(..));
+        match session.update(action) {
+          Ok(Some(action)) => session.action_tx.as_ref().unwrap().send(action).unwrap(),
+          Ok(None) => {},
+          Err(err) => log::debug!("session update error: {:#?}", err),
+        }
+        unread = answered && session.turn_complete();
+      },
+    }
+    if unread {
+      tab.unread = true;
+      helix_event::request_redraw();
+    }
+  }
+
   pub fn handle_config_events(&mut self, config_event: ConfigEvent) {
     match config_event {
       ConfigEvent::Refresh => self.refresh_config(),
@@ -653,6 +1063,14 @@ impl Application {
       self.refresh_language_config()?;
       self.refresh_theme(&default_config)?;
       self.terminal.reconfigure(default_config.editor.clone().into())?;
+      // the sessions take the settings that can change while they run
+      let mut session_txs = self.session.action_tx.iter().cloned().collect::<Vec<_>>();
+      if let Some(tabs) = self.compositor.find::<ui::SessionTabs>() {
+        session_txs.extend(tabs.background_sessions().filter_map(|s| s.action_tx.clone()));
+      }
+      for tx in session_txs {
+        tx.send(SessionAction::ConfigReloaded(Box::new(default_config.session.clone())))?;
+      }
       // Store new config
       self.config.store(Arc::new(default_config));
       Ok(())
@@ -781,6 +1199,7 @@ impl Application {
     let bytes = doc_save_event.text.len_bytes();
 
     self.editor.set_doc_path(doc_save_event.doc_id, &doc_save_event.path);
+    self.session.reindex_file(&doc_save_event.path);
     // TODO: fix being overwritten by lsp
     self.editor.set_status(format!(
       "'{}' written, {}L {}B",
@@ -855,6 +1274,17 @@ impl Application {
         kind: crossterm::event::KeyEventKind::Release,
         ..
       }) => false,
+      // ctrl-c, or esc outside insert mode, stops a response while nothing is open in front of
+      // the session
+      CrosstermEvent::Key(key)
+        if is_interrupt_key(key.into(), cx.editor.mode())
+          && cx.session.is_busy()
+          && self.compositor.layers_in_front::<ui::SessionStatusline>() == Some(0) =>
+      {
+        cx.session.interrupt();
+        cx.editor.set_status("response interrupted");
+        true
+      },
       event => self.compositor.handle_event(&event.into(), &mut cx),
     };
 
@@ -993,19 +1423,15 @@ impl Application {
               }) => (None, message, percentage),
               lsp::WorkDoneProgress::End(lsp::WorkDoneProgressEnd { message }) => {
                 // log::error!("UNKNOWN MESSAGE: {:#?}", message);
-                let editor_view = self
-                  .compositor
-                  .find::<ui::EditorView>()
-                  .expect("expected at least one EditorView");
                 //           let lsp::ProgressParams { token, value } = params;
                 if message.is_some() {
                   (None, message, &None)
                 } else {
                   self.lsp_progress.end_progress(server_id, &token);
                   if !self.lsp_progress.is_progressing(server_id) {
-                    editor_view.spinners_mut().get_or_create(server_id).stop();
+                    let name = language_server!().name().to_string();
+                    self.statusline().set_lsp_progress(server_id, &name, None);
                   }
-                  self.editor.clear_status();
 
                   // we want to render to clear any leftover spinners or messages
                   return;
@@ -1045,25 +1471,31 @@ impl Application {
               },
             };
 
+            let name = language_server!().name().to_string();
             if let lsp::WorkDoneProgress::End(_a) = work {
               let _res = self.lsp_progress.end_progress(server_id, &token);
               // log::info!("end progress: {:#?} {:#?}", res, a);
-              // if !self.lsp_progress.is_progressing(server_id) {
-              // editor_view.spinners_mut().get_or_create(server_id).stop();
-              // }
             } else {
               self.lsp_progress.update(server_id, token, work);
             }
-            //
-            // self.lsp_progress.update(server_id, token, work);
-            // if self.config.load().editor.lsp.display_messages {
-            // log::debug!("status: {}", status);
-            self.editor.set_status(status);
+            let progress = self.lsp_progress.is_progressing(server_id).then_some(status);
+            self.statusline().set_lsp_progress(server_id, &name, progress);
             self.render().await;
-            // }
           },
           Notification::Exit => {
-            self.editor.set_status("Language server exited");
+            // a stopped server is gone from the registry by the time it exits
+            let name = self
+              .language_server_interface
+              .language_server_by_id(server_id)
+              .map_or("Language server".to_string(), |client| client.name().to_string());
+            match self.language_server_interface.language_server_exited(server_id) {
+              Some(delay) => self.editor.set_status(format!(
+                "{} exited unexpectedly, restarting it in {}s",
+                name,
+                delay.as_secs()
+              )),
+              None => self.editor.set_status(format!("{} exited", name)),
+            }
 
             // LSPs may produce diagnostics for files that haven't been opened in helix,
             // we need to clear those and remove the entries from the list if this leads to
@@ -1078,9 +1510,6 @@ impl Application {
             // for doc in self.editor.documents_mut() {
             //   doc.clear_diagnostics(Some(server_id));
             // }
-
-            // Remove the language server from the registry.
-            self.language_server_interface.language_servers.remove_by_id(server_id);
           },
         }
       },
@@ -1699,6 +2128,23 @@ impl Application {
   //   lsp::ShowDocumentResult { success: true }
   // }
 
+  /// runs a job's callback, handing the terminal to the shell while those that need it run
+  async fn handle_job_callback(&mut self, call: anyhow::Result<Option<Callback>>) {
+    match call {
+      Ok(Some(Callback::Terminal(call))) => {
+        if let Err(e) = self.restore_term() {
+          log::error!("unable to restore the terminal: {}", e);
+        }
+        call(&mut self.editor, &mut self.compositor);
+        if let Err(e) = self.claim_term().await {
+          log::error!("unable to claim the terminal: {}", e);
+        }
+        self.compositor.need_full_redraw();
+      },
+      call => self.jobs.handle_callback(&mut self.editor, &mut self.compositor, call),
+    }
+  }
+
   async fn claim_term(&mut self) -> std::io::Result<()> {
     let terminal_config = self.config.load().editor.clone().into();
     self.terminal.claim(terminal_config)
@@ -1765,3 +2211,8 @@ impl Application {
     errs
   }
 }
+
+// the keys that interrupt a response. esc only does outside insert mode, where it leaves the mode
+fn is_interrupt_key(key: KeyEvent, mode: Mode) -> bool {
+  key == ctrl!('c') || (key == key!(Esc) && mode != Mode::Insert)
+}
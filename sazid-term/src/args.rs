@@ -20,6 +20,15 @@ pub struct Args {
   pub workspace: Option<PathBuf>,
   pub language: Option<String>,
   pub language_server: Option<String>,
+  pub openai_org_id: Option<String>,
+  pub openai_project_id: Option<String>,
+  pub openai_profile: Option<String>,
+  /// `login` or `logout` and the provider of `sazid auth`
+  pub auth: Option<(String, String)>,
+  /// open the most recently updated session
+  pub resume: bool,
+  /// the id of the session to open
+  pub session_id: Option<String>,
 }
 
 impl Args {
@@ -33,6 +42,14 @@ impl Args {
     while let Some(arg) = argv.next() {
       match arg.as_str() {
         "--" => break, // stop parsing at this point treat the remaining as files
+        "auth" => {
+          let action = match argv.next().as_deref() {
+            Some(action @ ("login" | "logout")) => action.to_string(),
+            _ => anyhow::bail!("auth must be followed by either 'login' or 'logout'"),
+          };
+          let provider = argv.next().unwrap_or("openai".to_string());
+          args.auth = Some((action, provider));
+        },
         "--version" => args.display_version = true,
         // "--help" => args.display_help = true,
         // "--tutor" => args.load_tutor = true,
@@ -83,6 +100,23 @@ impl Args {
             args.language_server = Some(language_server.into());
           }
         },
+        "--org-id" => match argv.next().as_deref() {
+          Some(org_id) => args.openai_org_id = Some(org_id.into()),
+          None => anyhow::bail!("--org-id must specify an organization id"),
+        },
+        "--project-id" => match argv.next().as_deref() {
+          Some(project_id) => args.openai_project_id = Some(project_id.into()),
+          None => anyhow::bail!("--project-id must specify a project id"),
+        },
+        "--openai-profile" => match argv.next().as_deref() {
+          Some(profile) => args.openai_profile = Some(profile.into()),
+          None => anyhow::bail!("--openai-profile must specify a profile name"),
+        },
+        "--resume" => args.resume = true,
+        "--session" => match argv.next().as_deref() {
+          Some(id) => args.session_id = Some(id.into()),
+          None => anyhow::bail!("--session must specify a session id"),
+        },
         "-w" | "--workspace" => match argv.next().as_deref() {
           Some(path) => {
             args.workspace = if Path::new(path).is_dir() {
@@ -115,6 +149,10 @@ impl Args {
       }
     }
 
+    if args.resume && args.session_id.is_some() {
+      anyhow::bail!("--resume can not be used with --session");
+    }
+
     Ok(args)
   }
 }
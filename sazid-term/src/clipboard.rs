@@ -0,0 +1,32 @@
+use std::io::Write;
+
+use base64::Engine;
+
+/// true when running over ssh, where the clipboard providers found on the remote host can not
+/// reach the clipboard of the local terminal
+pub fn is_ssh_session() -> bool {
+  ["SSH_TTY", "SSH_CONNECTION", "SSH_CLIENT"].iter().any(|var| std::env::var_os(var).is_some())
+}
+
+/// the OSC 52 escape sequence that asks the terminal to set its clipboard to `text`
+pub fn osc52_sequence(text: &str) -> String {
+  format!("\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(text))
+}
+
+/// copies `text` to the clipboard of the terminal sazid is displayed in, which works across ssh
+/// as long as the terminal supports OSC 52
+pub fn copy_osc52(text: &str) -> std::io::Result<()> {
+  let mut stdout = std::io::stdout();
+  stdout.write_all(osc52_sequence(text).as_bytes())?;
+  stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_osc52_sequence() {
+    assert_eq!(osc52_sequence("fn main() {}"), "\x1b]52;c;Zm4gbWFpbigpIHt9\x07");
+  }
+}
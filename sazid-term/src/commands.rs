@@ -4,10 +4,14 @@ pub(crate) mod lsp;
 pub(crate) mod typed;
 
 pub use dap::*;
+use helix_lsp::lsp::DiagnosticSeverity;
 use helix_stdx::rope::{self, RopeSliceExt};
 use helix_vcs::Hunk;
 pub use llm::*;
 pub use lsp::*;
+use sazid::app::prompt_templates::{
+  expand_template, load_templates, parse_template_command, TemplateVariables,
+};
 use sazid::components::session::Session;
 use tui::widgets::Row;
 pub use typed::*;
@@ -19,7 +23,9 @@ use helix_core::{
   history::UndoKind,
   increment, indent,
   indent::IndentStyle,
-  line_ending::{get_line_ending_of_str, line_end_char_index, str_is_line_ending},
+  line_ending::{
+    get_line_ending_of_str, line_end_char_index, rope_end_without_line_ending, str_is_line_ending,
+  },
   match_brackets,
   movement::{self, move_horizontally, move_vertically, move_vertically_visual, Direction},
   object, pos_at_coords,
@@ -398,6 +404,7 @@ impl MappableCommand {
         yank_main_selection_to_clipboard, "Yank main selection to clipboard",
         yank_joined_to_primary_clipboard, "Join and yank selections to primary clipboard",
         yank_main_selection_to_primary_clipboard, "Yank main selection to primary clipboard",
+        yank_code_block, "Yank code block under cursor to clipboard",
         replace_with_yanked, "Replace with yanked text",
         replace_selections_with_clipboard, "Replace selections by clipboard content",
         replace_selections_with_primary_clipboard, "Replace selections by primary clipboard",
@@ -477,6 +484,8 @@ impl MappableCommand {
         goto_prev_test, "Goto previous test",
         goto_next_paragraph, "Goto next paragraph",
         goto_prev_paragraph, "Goto previous paragraph",
+        goto_next_message, "Goto next message",
+        goto_prev_message, "Goto previous message",
         dap_launch, "Launch debug target",
         dap_restart, "Restart debugging session",
         dap_toggle_breakpoint, "Toggle breakpoint",
@@ -508,6 +517,9 @@ impl MappableCommand {
         // sazid specific commands
         quit, "Exit application",
         submit_input_to_session, "Submit Chat Completion Request",
+        input_history_previous, "Recall the previous input on the first line, else move up",
+        input_history_next, "Recall the next input on the last line, else move down",
+        edit_input_in_external_editor, "Edit the input in $EDITOR",
         save_session, "save session to file",
         session_view_scroll_up, "scroll session text up",
         session_view_scroll_down, "scroll session text down",
@@ -515,10 +527,22 @@ impl MappableCommand {
         session_page_cursor_half_down, "scroll session cursor half page down",
         load_session_picker, "show saved session",
         toggle_layer_order, "toggle focus between session and editor",
-        new_session, "create a new session",
+        new_session, "open a new session in a tab",
+        close_session_tab, "close the session tab, saving the session",
+        goto_next_session_tab, "show the next session tab",
+        goto_previous_session_tab, "show the previous session tab",
         add_session_workspace_folder, "add a workspace folder to this session",
         remove_session_workspace_folder, "remove a workspace folder from current session",
         modify_system_prompt, "modify the system prompt",
+        regenerate_response, "regenerate the last assistant response",
+        message_actions, "show actions for the message under the cursor",
+        toggle_message_fold, "fold or unfold the message under the cursor",
+        delete_message, "delete the message under the cursor from the context",
+        undo_delete_message, "restore the most recently deleted messages",
+        accept_file_edit, "accept the file change under the cursor",
+        revert_file_edit, "revert the file change under the cursor",
+        toggle_split_view, "show the file the session is working on beside it",
+        select_line_mode, "Enter selection extend mode with whole lines selected",
     );
 }
 
@@ -556,11 +580,16 @@ impl std::str::FromStr for MappableCommand {
         })
         .ok_or_else(|| anyhow!("No TypableCommand named '{}'", s))
     } else {
-      MappableCommand::STATIC_COMMAND_LIST
-        .iter()
-        .find(|cmd| cmd.name() == s)
-        .cloned()
-        .ok_or_else(|| anyhow!("No command named '{}'", s))
+      MappableCommand::STATIC_COMMAND_LIST.iter().find(|cmd| cmd.name() == s).cloned().ok_or_else(
+        || {
+          // a misspelled command in a keymap is pointed at the closest command name
+          let names = MappableCommand::STATIC_COMMAND_LIST.iter().map(|cmd| cmd.name());
+          match helix_core::fuzzy::fuzzy_match(s, names, false).first() {
+            Some((name, _)) => anyhow!("No command named '{}', did you mean '{}'?", s, name),
+            None => anyhow!("No command named '{}'", s),
+          }
+        },
+      )
     }
   }
 }
@@ -592,8 +621,57 @@ impl PartialEq for MappableCommand {
 }
 
 // Sazid Custom Commands
-fn new_session(_cx: &mut Context) {
-  log::info!("new_session");
+fn new_session(cx: &mut Context) {
+  cx.callback.push(Box::new(|compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let Some(tabs) = compositor.find::<ui::SessionTabs>() else {
+      return;
+    };
+    let config = cx.session.config.for_new_session();
+    tabs.open(cx.session, config);
+    ui::session_tabs::sync_tab_bar(compositor);
+    ui::session_tabs::show_active_session(compositor, cx.session);
+  }));
+}
+
+fn close_session_tab(cx: &mut Context) {
+  cx.callback.push(Box::new(|compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let Some(tabs) = compositor.find::<ui::SessionTabs>() else {
+      return;
+    };
+    let Some(mut closed) = tabs.close(cx.session) else {
+      cx.editor.set_status("the last session tab can not be closed");
+      return;
+    };
+    closed.interrupt();
+    if !closed.messages.is_empty() {
+      if let Err(e) = open_session_store().and_then(|mut store| closed.save_session(&mut store)) {
+        cx.editor.set_error(format!("error saving session: {}", e));
+      }
+    }
+    ui::session_tabs::sync_tab_bar(compositor);
+    ui::session_tabs::show_active_session(compositor, cx.session);
+  }));
+}
+
+fn goto_next_session_tab(cx: &mut Context) {
+  let count = cx.count() as isize;
+  cycle_session_tabs(cx, count);
+}
+
+fn goto_previous_session_tab(cx: &mut Context) {
+  let count = cx.count() as isize;
+  cycle_session_tabs(cx, -count);
+}
+
+fn cycle_session_tabs(cx: &mut Context, offset: isize) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let Some(tabs) = compositor.find::<ui::SessionTabs>().filter(|tabs| tabs.height() > 0) else {
+      cx.editor.set_status("no other sessions are open");
+      return;
+    };
+    tabs.cycle(cx.session, offset);
+    ui::session_tabs::show_active_session(compositor, cx.session);
+  }));
 }
 
 fn add_session_workspace_folder(_cx: &mut Context) {
@@ -617,6 +695,8 @@ fn modify_system_prompt(cx: &mut Context) {
       (range.from(), range.to())
     });
     cx.session.set_system_prompt(&input);
+    // a written prompt is not any profile's
+    cx.session.config.prompt_profile = None;
     doc.apply(&transaction, view_id);
     cx.editor.set_status("system prompt updated");
   } else {
@@ -624,6 +704,13 @@ fn modify_system_prompt(cx: &mut Context) {
   }
 }
 
+fn regenerate_response(cx: &mut Context) {
+  match cx.session.regenerate_last_response(None, None) {
+    Ok(_) => cx.editor.set_status("regenerating response"),
+    Err(e) => cx.editor.set_error(format!("error regenerating response: {}", e)),
+  }
+}
+
 fn quit(cx: &mut Context) {
   log::debug!("quitting...");
 
@@ -707,27 +794,20 @@ fn session_view_scroll_down(cx: &mut Context) {
 }
 
 fn save_session(cx: &mut Context) {
-  let data_folder = helix_loader::data_dir().join("sessions");
-  if !data_folder.exists() {
-    if let Err(e) = std::fs::create_dir_all(&data_folder) {
-      cx.editor.set_error(format!("error creating data directory: {}", e));
-      return;
-    }
-  }
-
-  let date_and_time_string = chrono::Utc::now().to_rfc3339();
+  let title = cx.session.config.title.clone();
   ui::prompt_with_input(
     cx,
     "save-session:".into(),
-    date_and_time_string,
+    title,
     Some('|'),
     ui::completers::none,
     move |cx, input: &str, event: PromptEvent| match event {
       PromptEvent::Validate => {
-        let save_path = data_folder.join(input).with_extension("szd");
-        log::info!("saving session to: {:#?}\nevent: {:#?}", save_path, event);
-        match cx.session.save_session(save_path.clone()) {
-          Ok(_) => cx.editor.set_status(format!("session saved to: {:?}", save_path)),
+        cx.session.config.title = input.to_string();
+        cx.session.config.title_set = true;
+        log::info!("saving session: {}", input);
+        match open_session_store().and_then(|mut store| cx.session.save_session(&mut store)) {
+          Ok(_) => cx.editor.set_status(format!("session saved: {}", input)),
           Err(e) => {
             log::error!("error saving session: {}", e);
             cx.editor.set_error(format!("error saving session: {}", e));
@@ -741,12 +821,14 @@ fn save_session(cx: &mut Context) {
 }
 
 fn load_session_picker(cx: &mut Context) {
-  let root = helix_loader::data_dir().join("session_history");
-  if !root.exists() {
-    cx.editor.set_error("data directory does not exist");
-    return;
-  }
-  let picker = ui::session::session_picker(root, &cx.editor.config());
+  let sessions = match open_session_store().and_then(|store| store.list_sessions()) {
+    Ok(sessions) => sessions,
+    Err(e) => {
+      cx.editor.set_error(format!("error listing sessions: {}", e));
+      return;
+    },
+  };
+  let picker = ui::session::session_picker(sessions);
   cx.push_layer(Box::new(overlaid(picker)));
 }
 
@@ -760,11 +842,6 @@ fn submit_input_to_session(cx: &mut Context) {
     return;
   }
 
-  if cx.session.is_receiving() {
-    cx.editor.set_error("still receiving");
-    return;
-  }
-
   // let message = async_openai::types::ChatCompletionRequestUserMessage {
   //   content: async_openai::types::ChatCompletionRequestUserMessageContent::Text(
   //     text.into(),
@@ -774,7 +851,33 @@ fn submit_input_to_session(cx: &mut Context) {
   // };
   // cx.session.add_message(sazid::app::messages::ChatMessage::User(message));
 
-  cx.session.submit_chat_completion_request(input.into());
+  let input = input.to_string();
+  if let Err(e) = cx.session.input_history.add(&cx.session.config.id, &input) {
+    log::warn!("unable to save input history: {}", e);
+  }
+  if let Some((name, file)) = parse_template_command(&input) {
+    let (name, file) = (name.to_string(), file.to_string());
+    // the selection in the session is only reachable through the compositor
+    cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+      let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+      let range = session.selection.primary();
+      let selection = (range.len() > 1)
+        .then(|| session.get_messages_plaintext().slice(range.from()..range.to()).to_string());
+      match expand_prompt_template(cx.editor, &name, &file, selection) {
+        Ok(prompt) => {
+          if cx.session.submit_or_queue(prompt) {
+            set_queued_status(cx.editor, cx.session.queued_inputs.len());
+          }
+          replace_input(cx.editor, "");
+        },
+        Err(e) => cx.editor.set_error(e.to_string()),
+      }
+    }));
+    return;
+  }
+  if cx.session.submit_or_queue(input) {
+    set_queued_status(cx.editor, cx.session.queued_inputs.len());
+  }
 
   log::debug!("submitting input to session... {}", cx.session.messages.len());
 
@@ -785,6 +888,141 @@ fn submit_input_to_session(cx: &mut Context) {
   doc.apply(&transaction, view.id);
 }
 
+fn set_queued_status(editor: &mut Editor, queued: usize) {
+  editor.set_status(format!("queued, sent when the response completes ({} waiting)", queued));
+}
+
+/// the prompt the template `name` in the config's templates directory expands to, with `file`
+/// and the workspace diagnostics filled in
+fn expand_prompt_template(
+  editor: &Editor,
+  name: &str,
+  file: &str,
+  selection: Option<String>,
+) -> anyhow::Result<String> {
+  let templates =
+    load_templates(&helix_loader::config_dir().join("templates")).map_err(|e| anyhow!("{}", e))?;
+  let Some(template) = templates.get(name) else {
+    let names = templates.keys().map(String::as_str).collect::<Vec<_>>();
+    bail!("no template named '{}', the templates are: {}", name, names.join(", "));
+  };
+  let file = match file {
+    "" => None,
+    file => {
+      let content = std::fs::read_to_string(file).with_context(|| format!("reading {}", file))?;
+      let language = Path::new(file).extension().and_then(|e| e.to_str()).unwrap_or_default();
+      Some(format!("{}\n```{}\n{}\n```", file, language, content.trim_end()))
+    },
+  };
+  let cwd = helix_stdx::env::current_working_dir();
+  let mut diagnostics = vec![];
+  for (path, diags) in editor.diagnostics.iter() {
+    let path = path.strip_prefix(&cwd).unwrap_or(path).display();
+    for (diag, _) in diags {
+      let severity = match diag.severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        _ => "hint",
+      };
+      let start = diag.range.start;
+      diagnostics.push(format!(
+        "{}:{}:{} {}: {}",
+        path,
+        start.line + 1,
+        start.character + 1,
+        severity,
+        diag.message
+      ));
+    }
+  }
+  if diagnostics.is_empty() {
+    diagnostics.push("there are no diagnostics".to_string());
+  }
+  let variables = TemplateVariables { selection, file, diagnostics: Some(diagnostics.join("\n")) };
+  expand_template(template, &variables).map_err(|e| anyhow!("{}", e))
+}
+
+// the line the cursor of the input is on and the last line with text on it
+fn input_cursor_lines(cx: &mut Context) -> (usize, usize) {
+  let (view, doc) = current_ref!(cx.editor);
+  let text = doc.text().slice(..);
+  let cursor = doc.selection(view.id).primary().cursor(text);
+  (text.char_to_line(cursor), text.char_to_line(rope_end_without_line_ending(&text)))
+}
+
+/// replaces the input with recalled or edited text, with the cursor at its end
+fn replace_input(editor: &mut Editor, input: &str) {
+  let (view, doc) = current!(editor);
+  let end = input.chars().count();
+  let transaction = Transaction::change(
+    doc.text(),
+    std::iter::once((0, doc.text().len_chars(), Some(input.into()))),
+  )
+  .with_selection(Selection::point(end));
+  doc.apply(&transaction, view.id);
+}
+
+fn input_history_previous(cx: &mut Context) {
+  let (line, _) = input_cursor_lines(cx);
+  if !matches!(cx.focus, ContextFocus::EditorView) || line > 0 {
+    return move_visual_line_up(cx);
+  }
+  let input = doc!(cx.editor).text().to_string();
+  match cx.session.input_history.recall_previous(&cx.session.config.id, &input) {
+    Some(recalled) => replace_input(cx.editor, &recalled),
+    None => move_visual_line_up(cx),
+  }
+}
+
+fn input_history_next(cx: &mut Context) {
+  let (line, last_line) = input_cursor_lines(cx);
+  if !matches!(cx.focus, ContextFocus::EditorView) || line < last_line {
+    return move_visual_line_down(cx);
+  }
+  match cx.session.input_history.recall_next() {
+    Some(recalled) => replace_input(cx.editor, &recalled),
+    None => move_visual_line_down(cx),
+  }
+}
+
+/// opens the input in $VISUAL or $EDITOR and replaces it with what was saved
+fn edit_input_in_external_editor(cx: &mut Context) {
+  let input = doc!(cx.editor).text().to_string();
+  cx.jobs.callback(async move {
+    let call = move |editor: &mut Editor, _compositor: &mut Compositor| {
+      match edit_in_external_editor(&input) {
+        Ok(edited) => replace_input(editor, edited.trim_end()),
+        Err(e) => editor.set_error(format!("unable to edit the input: {}", e)),
+      }
+    };
+    Ok(Callback::Terminal(Box::new(call)))
+  });
+}
+
+// writes `text` to a temporary file, opens it in $VISUAL or $EDITOR and returns its contents
+// once the editor exits. the editor needs the terminal, so this runs in a terminal callback
+fn edit_in_external_editor(text: &str) -> anyhow::Result<String> {
+  let command = std::env::var("VISUAL")
+    .or_else(|_| std::env::var("EDITOR"))
+    .map_err(|_| anyhow!("$EDITOR is not set"))?;
+  let shellwords = shellwords::Shellwords::from(command.as_str());
+  let Some((program, args)) = shellwords.words().split_first() else {
+    bail!("$EDITOR is empty");
+  };
+  let path = std::env::temp_dir().join(format!("sazid-input-{}.md", std::process::id()));
+  std::fs::write(&path, text)?;
+  let status = std::process::Command::new(program.to_string())
+    .args(args.iter().map(|arg| arg.to_string()))
+    .arg(&path)
+    .status();
+  let edited = std::fs::read_to_string(&path);
+  let _ = std::fs::remove_file(&path);
+  let status = status.with_context(|| format!("unable to run {}", program))?;
+  ensure!(status.success(), "{} exited with {}", program, status);
+  Ok(edited?)
+}
+
 fn no_op(_cx: &mut Context) {}
 
 type MoveFn =
@@ -839,14 +1077,26 @@ fn session_move_impl_callback(
   let text = Rope::from(session.get_messages_plaintext());
   let mut annotations = TextAnnotations::default();
   // log::warn!("text: {:#?}", text);
-  session.selection = session.selection.clone().transform(|range| {
+  let selection = session.selection.clone().transform(|range| {
     move_fn(text.slice(..), range, dir, count, behaviour, &TextFormat::default(), &mut annotations)
   });
+  session_set_selection(session, text.slice(..), selection);
+  // .ensure_invariants(text.slice(..));
+  // .into_single();
+  // log::info!("move_impl callback: session view {:?}", session.selection);
+}
 
+/// replaces the session selection and scrolls the session cursor into view
+fn session_set_selection(
+  session: &mut ui::SessionView<ChatMessageItem>,
+  text: RopeSlice,
+  selection: Selection,
+) {
+  session.selection = selection;
   let session_cursor = session.selection.primary().head;
 
   let (scroll_by, direction, _) = crate::movement::translate_char_index_to_viewport_pos(
-    &text.slice(..),
+    &text,
     session.chat_viewport,
     session.state.vertical_scroll,
     session_cursor,
@@ -856,9 +1106,58 @@ fn session_move_impl_callback(
   if let Some(direction) = direction {
     session.state.scroll_by(scroll_by, direction);
   }
-  // .ensure_invariants(text.slice(..));
-  // .into_single();
-  // log::info!("move_impl callback: session view {:?}", session.selection);
+}
+
+/// applies a selection transform to the session view from a command
+fn session_transform_selection(cx: &mut Context, f: impl Fn(RopeSlice, Range) -> Range + 'static) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, _cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    let text = Rope::from(session.get_messages_plaintext());
+    let selection = session.selection.clone().transform(|range| f(text.slice(..), range));
+    session_set_selection(session, text.slice(..), selection);
+  }));
+}
+
+/// moves the session cursor to the message start that `target` picks from the message char
+/// ranges and the cursor position, extending the selection in select mode
+fn session_goto_message(
+  cx: &mut Context,
+  target: impl Fn(&[std::ops::Range<usize>], usize) -> Option<usize> + 'static,
+) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    let text = Rope::from(session.get_messages_plaintext());
+    let text = text.slice(..);
+    let messages = session.message_char_ranges();
+    let extend = cx.editor.mode == Mode::Select;
+    let selection =
+      session.selection.clone().transform(|range| match target(&messages, range.cursor(text)) {
+        Some(pos) => crate::movement::put_cursor(range, text, pos, extend),
+        None => range,
+      });
+    session_set_selection(session, text, selection);
+  }));
+}
+
+/// moves the session cursor to the start of the message at `idx`, or the last message
+fn session_goto_nth_message(cx: &mut Context, idx: usize) {
+  session_goto_message(cx, move |messages, _| {
+    messages.get(idx.min(messages.len().saturating_sub(1))).map(|m| m.start)
+  });
+}
+
+fn goto_next_message(cx: &mut Context) {
+  let count = cx.count();
+  session_goto_message(cx, move |messages, cursor| {
+    crate::movement::message_start(messages, cursor, Direction::Forward, count)
+  });
+}
+
+fn goto_prev_message(cx: &mut Context) {
+  let count = cx.count();
+  session_goto_message(cx, move |messages, cursor| {
+    crate::movement::message_start(messages, cursor, Direction::Backward, count)
+  });
 }
 
 fn session_move_impl(
@@ -1351,9 +1650,13 @@ fn goto_window_bottom(cx: &mut Context) {
 
 fn move_word_impl<F>(cx: &mut Context, move_fn: F)
 where
-  F: Fn(RopeSlice, Range, usize) -> Range,
+  F: Fn(RopeSlice, Range, usize) -> Range + 'static,
 {
   let count = cx.count();
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    session_transform_selection(cx, move |text, range| move_fn(text, range, count));
+    return;
+  }
   let (view, doc) = current!(cx.editor);
   let text = doc.text().slice(..);
 
@@ -1419,6 +1722,11 @@ fn goto_next_paragraph(cx: &mut Context) {
 }
 
 fn goto_file_start(cx: &mut Context) {
+  // in the session a count picks a message, like it picks a line in a document
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    session_goto_nth_message(cx, cx.count.map_or(0, |count| count.get() - 1));
+    return;
+  }
   if cx.count.is_some() {
     goto_line(cx);
   } else {
@@ -1538,17 +1846,22 @@ fn open_url(cx: &mut Context, url: Url, action: Action) {
 
 fn extend_word_impl<F>(cx: &mut Context, extend_fn: F)
 where
-  F: Fn(RopeSlice, Range, usize) -> Range,
+  F: Fn(RopeSlice, Range, usize) -> Range + 'static,
 {
   let count = cx.count();
-  let (view, doc) = current!(cx.editor);
-  let text = doc.text().slice(..);
-
-  let selection = doc.selection(view.id).clone().transform(|range| {
+  let extend = move |text: RopeSlice, range: Range| {
     let word = extend_fn(text, range, count);
     let pos = word.cursor(text);
     range.put_cursor(text, pos, true)
-  });
+  };
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    session_transform_selection(cx, extend);
+    return;
+  }
+  let (view, doc) = current!(cx.editor);
+  let text = doc.text().slice(..);
+
+  let selection = doc.selection(view.id).clone().transform(|range| extend(text, range));
   doc.set_selection(view.id, selection);
 }
 
@@ -3381,6 +3694,10 @@ fn push_jump(view: &mut View, doc: &Document) {
 }
 
 fn goto_line(cx: &mut Context) {
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    session_goto_nth_message(cx, cx.count.map_or(usize::MAX, |count| count.get() - 1));
+    return;
+  }
   if cx.count.is_some() {
     let (view, doc) = current!(cx.editor);
     push_jump(view, doc);
@@ -3411,6 +3728,10 @@ fn goto_line_without_jumplist(editor: &mut Editor, count: Option<NonZeroUsize>)
 }
 
 fn goto_last_line(cx: &mut Context) {
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    session_goto_nth_message(cx, usize::MAX);
+    return;
+  }
   let (view, doc) = current!(cx.editor);
   let text = doc.text().slice(..);
   let line_idx = if text.line(text.len_lines() - 1).len_chars() == 0 {
@@ -3462,6 +3783,11 @@ fn goto_last_modified_file(cx: &mut Context) {
 }
 
 fn select_mode(cx: &mut Context) {
+  // the session view has no end-of-document cursor to widen
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    cx.editor.mode = Mode::Select;
+    return;
+  }
   let (view, doc) = current!(cx.editor);
   let text = doc.text().slice(..);
 
@@ -3479,6 +3805,16 @@ fn select_mode(cx: &mut Context) {
   cx.editor.mode = Mode::Select;
 }
 
+fn select_line_mode(cx: &mut Context) {
+  match cx.focus {
+    ContextFocus::SessionView => {
+      session_transform_selection(cx, crate::movement::session_extend_to_line_bounds)
+    },
+    ContextFocus::EditorView => extend_to_line_bounds(cx),
+  }
+  cx.editor.mode = Mode::Select;
+}
+
 fn exit_select_mode_from_callback(editor: &mut Editor) {
   if editor.mode == Mode::Select {
     editor.mode = Mode::Normal;
@@ -4050,6 +4386,33 @@ fn yank_session_impl(
   }
 }
 
+fn yank_code_block(cx: &mut Context) {
+  let register = cx.register.unwrap_or('+');
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    let cursor = session.selection.primary().cursor(session.get_messages_plaintext());
+    let Some(code) = session.code_block_at(cursor).map(|block| block.code) else {
+      cx.editor.set_error("no code block under the cursor");
+      return;
+    };
+    if let Err(err) = cx.editor.registers.write(register, vec![code.clone()]) {
+      cx.editor.set_error(err.to_string());
+      return;
+    }
+    // clipboard providers on a remote host can not reach the local clipboard
+    if crate::clipboard::is_ssh_session() {
+      if let Err(err) = crate::clipboard::copy_osc52(&code) {
+        log::error!("error copying code block with osc 52: {}", err);
+      }
+    }
+    let lines = code.lines().count();
+    cx.editor.set_status(format!(
+      "yanked code block ({lines} line{}) to register {register}",
+      if lines == 1 { "" } else { "s" }
+    ));
+  }));
+}
+
 fn yank_impl(editor: &mut Editor, register: char) {
   let (view, doc) = current!(editor);
   let text = doc.text().slice(..);
@@ -5174,6 +5537,10 @@ fn select_textobject_inner(cx: &mut Context) {
 }
 
 fn select_textobject(cx: &mut Context, objtype: textobject::TextObject) {
+  if matches!(cx.focus, ContextFocus::SessionView) {
+    session_select_textobject(cx, objtype);
+    return;
+  }
   let count = cx.count();
 
   cx.on_next_key(move |cx, event| {
@@ -5266,6 +5633,53 @@ fn select_textobject(cx: &mut Context, objtype: textobject::TextObject) {
   cx.editor.autoinfo = Some(Info::new(title, &help_text));
 }
 
+fn session_select_textobject(cx: &mut Context, objtype: textobject::TextObject) {
+  let count = cx.count();
+
+  cx.on_next_key(move |cx, event| {
+    cx.editor.autoinfo = None;
+    if let Some(ch) = event.char() {
+      cx.callback.push(Box::new(
+        move |compositor: &mut Compositor, _cx: &mut compositor::Context| {
+          let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+          let text = Rope::from(session.get_messages_plaintext());
+          let text = text.slice(..);
+          let messages = session.message_char_ranges();
+          let code_blocks = session.code_block_char_ranges();
+          let selection = session.selection.clone().transform(|range| match ch {
+            'm' => crate::movement::textobject_message(text, range, objtype, &messages),
+            'c' => crate::movement::textobject_code_block(text, range, objtype, &code_blocks),
+            'w' => textobject::textobject_word(text, range, objtype, count, false),
+            'W' => textobject::textobject_word(text, range, objtype, count, true),
+            'p' => textobject::textobject_paragraph(text, range, objtype, count),
+            ch if !ch.is_ascii_alphanumeric() => {
+              textobject::textobject_pair_surround(text, range, objtype, ch, count)
+            },
+            _ => range,
+          });
+          session_set_selection(session, text, selection);
+        },
+      ));
+    }
+  });
+
+  let title = match objtype {
+    textobject::TextObject::Inside => "Match inside",
+    textobject::TextObject::Around => "Match around",
+    _ => return,
+  };
+  let help_text = [
+    ("m", "Message"),
+    ("c", "Code block"),
+    ("w", "Word"),
+    ("W", "WORD"),
+    ("p", "Paragraph"),
+    (" ", "... or any character acting as a pair"),
+  ];
+
+  cx.editor.autoinfo = Some(Info::new(title, &help_text));
+}
+
 fn surround_add(cx: &mut Context) {
   cx.on_next_key(move |cx, event| {
     let (view, doc) = current!(cx.editor);
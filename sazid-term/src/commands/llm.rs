@@ -1,28 +1,51 @@
-use std::sync::Arc;
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
 
 use super::Context;
 use crate::{
   compositor::{self, Compositor},
   job::Callback,
-  ui::{self, overlay::overlaid},
-  widgets::{paragraph::Wrap, table::MessageCell},
+  ui::{
+    self,
+    overlay::overlaid,
+    split_view::{set_split_view, show_in_split_view, split_view},
+    ChatRole, Picker,
+  },
+  widgets::{
+    diff::{DiffStatus, FileDiff},
+    paragraph::Wrap,
+    table::MessageCell,
+  },
 };
 
-use crate::ui::MarkdownRenderer;
+use crate::ui::{IncrementalMarkdown, MarkdownRenderer};
 use arc_swap::ArcSwap;
 use async_openai::types::ChatCompletionRequestMessage;
 use helix_lsp::lsp::Range;
 use helix_view::{
+  editor::Action,
   graphics::Rect,
   theme::{Color, Style},
   Editor, Theme,
 };
+use sazid::app::errors::SazidError;
+use sazid::app::export::{export_session, ExportFormat};
+use sazid::app::file_patch::FileEdit;
 use sazid::app::messages::{
-  chat_completion_request_message_content_as_str, chat_completion_request_message_tool_calls_as_str,
+  chat_completion_request_message_content_as_str,
+  chat_completion_request_message_tool_calls_as_str, MessageContainer,
 };
+use sazid::app::session_config::{GutterConfig, GutterRole};
+use sazid::app::session_store::SessionStore;
+use sazid::app::snippets::snippet_text;
+use sazid::components::session::Session;
 use tui::{
   buffer::Buffer,
   text::{Span, Spans, Text},
+  widgets::Row,
 };
 
 use helix_core::{syntax, Rope};
@@ -45,9 +68,28 @@ pub struct ChatMessageItem {
   pub formatted_line_widths: Vec<(usize, String)>,
   pub plaintext_line_widths: Vec<(usize, String)>,
   pub rendered_area: Option<Rect>,
-  pub start_idx: usize,
+  /// the one line summary shown in place of the message while it is folded
+  pub folded: Option<String>,
+  /// the rendered content, updated a block at a time while the message streams in
+  pub markdown: IncrementalMarkdown,
+  /// chars of each line of the message once wrapped, so only changed lines are wrapped again
+  pub wrapped_line_chars: Vec<usize>,
+  /// the plain text and line chars of the message wrapped to other widths, kept until the
+  /// message changes so resizing back to a width does not wrap it again
+  pub wrap_cache: HashMap<u16, (Rope, Vec<usize>)>,
+  /// diffs of the files changed by the message's tool calls
+  pub file_diffs: Vec<FileDiff>,
+  /// when the message was sent, in seconds since the epoch
+  pub timestamp: Option<i64>,
+  /// the model that wrote the message
+  pub model: Option<String>,
 }
 
+/// tool results with more lines than this are folded when they are added to the session
+const FOLD_TOOL_RESULT_LINES: usize = 20;
+// characters of a message's first line shown in its fold summary
+const FOLD_SUMMARY_CHARS: usize = 60;
+
 impl ChatMessageItem {
   pub fn new_chat(id: i64, message: ChatCompletionRequestMessage) -> Self {
     let id = Some(id);
@@ -65,10 +107,34 @@ impl ChatMessageItem {
       formatted_line_widths: vec![],
       plaintext_line_widths: vec![],
       rendered_area: None,
-      start_idx: 0,
+      folded: None,
+      markdown: IncrementalMarkdown::default(),
+      wrapped_line_chars: vec![],
+      wrap_cache: HashMap::new(),
+      file_diffs: vec![],
+      timestamp: None,
+      model: None,
     }
   }
 
+  /// a message of the session, with when it was sent and the model that wrote it
+  pub fn from_container(container: &MessageContainer) -> Self {
+    let mut item = Self::new_chat(container.message_id, container.message.clone());
+    item.timestamp = Some(container.timestamp);
+    item.model = container.model().map(String::from);
+    item
+  }
+
+  /// `message` as the message of the session with `id`, which it was sent with, if there is one
+  pub fn from_session(session: &Session, id: i64, message: ChatCompletionRequestMessage) -> Self {
+    let mut item = Self::new_chat(id, message);
+    if let Some(container) = session.messages.iter().find(|m| m.message_id == id) {
+      item.timestamp = Some(container.timestamp);
+      item.model = container.model().map(String::from);
+    }
+    item
+  }
+
   pub fn new_error(message: String) -> Self {
     let id = None;
     let message = ChatMessageType::Error(message);
@@ -85,10 +151,93 @@ impl ChatMessageItem {
       formatted_line_widths: vec![],
       plaintext_line_widths: vec![],
       rendered_area: None,
-      start_idx: 0,
+      folded: None,
+      markdown: IncrementalMarkdown::default(),
+      wrapped_line_chars: vec![],
+      wrap_cache: HashMap::new(),
+      file_diffs: vec![],
+      timestamp: None,
+      model: None,
+    }
+  }
+
+  /// true for messages that start out folded, long tool results and pasted snippets
+  pub fn folds_by_default(&self) -> bool {
+    let long_tool_result =
+      matches!(self.chat_message, ChatMessageType::Chat(ChatCompletionRequestMessage::Tool(_)))
+        && self.content().lines().count() > FOLD_TOOL_RESULT_LINES;
+    long_tool_result || self.snippet().is_some()
+  }
+
+  /// the pasted text of a snippet the user attached, none for other messages
+  pub fn snippet(&self) -> Option<&str> {
+    match self.role() {
+      ChatRole::User => snippet_text(self.content()),
+      _ => None,
+    }
+  }
+
+  /// a one line summary of the message, e.g. "tool: read_file — 412 lines". tool results are
+  /// named after the tool call, looked up in `messages`
+  pub fn fold_summary(&self, messages: &[ChatMessageItem]) -> String {
+    let snippet = self.snippet();
+    let line_count = snippet.unwrap_or(self.content()).lines().count();
+    let lines = format!("{} line{}", line_count, if line_count == 1 { "" } else { "s" });
+    let (role, description) = match &self.chat_message {
+      ChatMessageType::Chat(ChatCompletionRequestMessage::Tool(tool)) => {
+        let tool_name = messages.iter().find_map(|m| match &m.chat_message {
+          ChatMessageType::Chat(ChatCompletionRequestMessage::Assistant(assistant)) => assistant
+            .tool_calls
+            .iter()
+            .flatten()
+            .find(|call| call.id == tool.tool_call_id)
+            .map(|call| call.function.name.clone()),
+          _ => None,
+        });
+        ("tool", tool_name.unwrap_or_else(|| "result".to_string()))
+      },
+      _ => {
+        let text = snippet.unwrap_or(self.content());
+        let first_line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or_default();
+        let role = if snippet.is_some() { "snippet" } else { self.role().name() };
+        (role, first_line.trim().chars().take(FOLD_SUMMARY_CHARS).collect())
+      },
+    };
+    format!("{}: {} — {}", role, description, lines)
+  }
+
+  pub fn role(&self) -> ChatRole {
+    match &self.chat_message {
+      ChatMessageType::Chat(ChatCompletionRequestMessage::System(_)) => ChatRole::System,
+      ChatMessageType::Chat(ChatCompletionRequestMessage::User(_)) => ChatRole::User,
+      ChatMessageType::Chat(ChatCompletionRequestMessage::Assistant(_)) => ChatRole::Assistant,
+      ChatMessageType::Chat(ChatCompletionRequestMessage::Tool(_)) => ChatRole::Tool,
+      ChatMessageType::Chat(ChatCompletionRequestMessage::Function(_)) => ChatRole::Function,
+      ChatMessageType::Error(_) => ChatRole::Error,
     }
   }
 
+  /// the lines of the column beside the message, its role and `index`, when it was sent and
+  /// the model that wrote it
+  pub fn gutter_lines(&self, index: usize, config: &GutterConfig) -> Vec<String> {
+    let role = self.role();
+    let mut lines = vec![match config.role {
+      GutterRole::None => index.to_string(),
+      GutterRole::Icon => format!("{} {}", role.icon(), index),
+      GutterRole::Name => role.name().to_string(),
+    }];
+    if let Some(timestamp) = self.timestamp.filter(|_| config.timestamp) {
+      let sent = chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string());
+      lines.extend(sent);
+    }
+    if let Some(model) = self.model.as_ref().filter(|_| config.model && role == ChatRole::Assistant)
+    {
+      lines.push(model.clone());
+    }
+    lines
+  }
+
   pub fn get_wrapped_height(&self, width: u16) -> usize {
     if self.plaintext_wrapped_width == width {
       self.plain_text.len_lines()
@@ -114,39 +263,84 @@ impl ChatMessageItem {
   //   }
   // }
 
+  /// wraps the message to `width` for the plain text used to place the session cursor. lines
+  /// before the first one that changed since the last call are kept as they are
   pub fn cache_wrapped_plain_text(
     &mut self,
     width: u16,
+    theme: Option<&Theme>,
     config_loader: &Arc<ArcSwap<syntax::Loader>>,
   ) {
-    let text = self.format_to_text(None, config_loader.clone());
+    let content = self.content().to_string();
+    let changed = self.folded.is_some() || !self.markdown.is_current(&content, theme);
+    // line chars are cleared while folded, so only unfolded layouts are cached
+    if width != self.plaintext_wrapped_width && !self.wrapped_line_chars.is_empty() {
+      let wrapped = (self.plain_text.clone(), std::mem::take(&mut self.wrapped_line_chars));
+      self.wrap_cache.insert(self.plaintext_wrapped_width, wrapped);
+    }
+    if changed {
+      self.wrap_cache.clear();
+    } else if let Some((plain_text, line_chars)) = self.wrap_cache.remove(&width) {
+      self.plain_text = plain_text;
+      self.wrapped_line_chars = line_chars;
+      self.plaintext_wrapped_width = width;
+      return;
+    }
 
-    let style = Style::default();
+    // the header line is never changed by an update
+    let first_changed = match self.folded {
+      Some(_) => 0,
+      None => 1 + self.markdown.update(&content, theme, config_loader.clone()),
+    };
+    let first_changed = match self.plaintext_wrapped_width == width && self.folded.is_none() {
+      true => first_changed.min(self.wrapped_line_chars.len()),
+      false => 0,
+    };
     let area = Rect::new(0, 0, width, 0);
     let buf = &mut Buffer::empty(area);
-    self.plain_text = if let Some(plain_text) = MessageCell::format_text(
-      buf,
-      true,
-      false,
-      &text,
-      style,
-      Some(Wrap { trim: false }),
-      area,
-      tui::layout::Alignment::Left,
-      None,
-      0,
-      None,
-      None,
-    ) {
-      plain_text
-    } else {
-      self.plain_text.clone()
-    };
+    let wrapped_lines = self
+      .format_to_text(theme, config_loader.clone())
+      .lines
+      .into_iter()
+      .skip(first_changed)
+      .map(|line| {
+        MessageCell::format_text(
+          buf,
+          true,
+          false,
+          &Text::from(vec![line]),
+          Style::default(),
+          Some(Wrap { trim: false }),
+          area,
+          tui::layout::Alignment::Left,
+          None,
+          0,
+          None,
+          None,
+        )
+        .unwrap_or_default()
+      })
+      .collect::<Vec<_>>();
 
-    // log::warn!("plain_text: {}", self.plain_text);
+    let kept_chars = match first_changed {
+      0 => 0,
+      // each kept line is followed by the newline that separates it from the next
+      kept => self.wrapped_line_chars[..kept].iter().sum::<usize>() + kept - 1,
+    };
+    self.plain_text.remove(kept_chars.min(self.plain_text.len_chars())..);
+    self.wrapped_line_chars.truncate(first_changed);
+    for (idx, wrapped) in wrapped_lines.into_iter().enumerate() {
+      if first_changed + idx > 0 {
+        self.plain_text.insert_char(self.plain_text.len_chars(), '\n');
+      }
+      self.wrapped_line_chars.push(wrapped.len_chars());
+      self.plain_text.append(wrapped);
+    }
+    if self.folded.is_some() {
+      // the summary line shares nothing with the unfolded lines
+      self.wrapped_line_chars.clear();
+    }
     self.plaintext_wrapped_width = width;
-    // self.line_widths =
-    //   self.plain_text.lines().map(|l| l.len_chars() as u16).collect();
   }
 
   pub fn format_to_text(
@@ -154,86 +348,38 @@ impl ChatMessageItem {
     theme: Option<&Theme>,
     config_loader: Arc<ArcSwap<syntax::Loader>>,
   ) -> tui::text::Text {
-    let (style, header) = match self.chat_message {
-      ChatMessageType::Chat(ChatCompletionRequestMessage::System(_)) => {
-        (
-          Style::default()
-        .fg(Color::Magenta)
-        // .add_modifier(helix_view::theme::Modifier::ITALIC)
-        .add_modifier(helix_view::theme::Modifier::BOLD),
-          "System".to_string(),
-        )
-      },
-      ChatMessageType::Chat(ChatCompletionRequestMessage::User(_)) => {
-        (
-          Style::default()
-        .fg(Color::Green)
-        // .add_modifier(helix_view::theme::Modifier::ITALIC)
-        .add_modifier(helix_view::theme::Modifier::BOLD),
-          "User".to_string(),
-        )
-      },
-      ChatMessageType::Chat(ChatCompletionRequestMessage::Assistant(_)) => {
-        (
-          Style::default()
-        .fg(Color::Blue)
-        // .add_modifier(helix_view::theme::Modifier::ITALIC)
-        .add_modifier(helix_view::theme::Modifier::BOLD),
-          "Assistant".to_string(),
-        )
-      },
-      ChatMessageType::Chat(ChatCompletionRequestMessage::Tool(_)) => {
-        (
-          Style::default()
-        .fg(Color::Yellow)
-        // .add_modifier(helix_view::theme::Modifier::ITALIC)
-        .add_modifier(helix_view::theme::Modifier::BOLD),
-          "Tool".to_string(),
-        )
-      },
-      ChatMessageType::Chat(ChatCompletionRequestMessage::Function(_)) => {
-        (
-          Style::default()
-        .fg(Color::LightYellow)
-        // .add_modifier(helix_view::theme::Modifier::ITALIC)
-        .add_modifier(helix_view::theme::Modifier::BOLD),
-          "Function".to_string(),
-        )
-      },
-      ChatMessageType::Error(_) => (
-        Style::default().fg(Color::Red).add_modifier(helix_view::theme::Modifier::BOLD),
-        "ERROR".to_string(),
-      ),
-    };
+    let role = self.role();
+    let style = role.header_style(theme);
+
+    if let Some(summary) = &self.folded {
+      return Text::from(Spans::from(vec![Span::styled(format!("▸ {}", summary), style)]));
+    }
 
     // log::warn!("content: {}\nheader: {}", self.content(), header);
-    let header = Spans::from(vec![Span::styled(header, style)]);
+    let header = Spans::from(vec![Span::styled(role.header(), style)]);
     let mut lines = vec![header];
-    let content =
-      if let ChatMessageType::Chat(ChatCompletionRequestMessage::Tool(_)) = &self.chat_message {
-        if self.content().lines().count() > 1 {
-          "tool call response content"
-          //self.content()
-        } else {
-          self.content()
-        }
-      } else {
-        self.content()
-      };
 
-    let text = MarkdownRenderer::parse(content, theme, config_loader.clone());
-    lines.extend(text);
+    // the cached lines are used when they are up to date, rendering highlights code blocks
+    if self.markdown.is_current(self.content(), theme) {
+      lines.extend(self.markdown.lines().cloned());
+    } else {
+      lines.extend(MarkdownRenderer::parse(self.content(), theme, config_loader.clone()));
+    }
 
     if let Some(tool_calls) = self.tool_calls() {
-      tool_calls.iter().for_each(|(tool_name, tool_args)| {
+      tool_calls.iter().zip(self.tool_call_ids()).for_each(|((tool_name, tool_args), id)| {
         lines.extend(Text::from(Spans::from(vec![
           Span::styled("   Tool Call: ", Style::default().fg(Color::White)),
           Span::styled(*tool_name, Style::default().fg(Color::Cyan)),
         ])));
-        lines.extend(Text::from(Spans::from(vec![
-          Span::styled("   Arguments: ", Style::default().fg(Color::White)),
-          Span::styled(*tool_args, Style::default().fg(Color::Cyan)),
-        ])));
+        // a file the call changed is shown as a diff rather than the contents it was called with
+        match self.file_diffs.iter().find(|diff| diff.edit.tool_call_id == id) {
+          Some(diff) => lines.extend(diff.lines(theme)),
+          None => lines.extend(Text::from(Spans::from(vec![
+            Span::styled("   Arguments: ", Style::default().fg(Color::White)),
+            Span::styled(*tool_args, Style::default().fg(Color::Cyan)),
+          ]))),
+        }
       })
     }
     lines.into()
@@ -245,6 +391,16 @@ impl ChatMessageItem {
       ChatMessageType::Error(error) => error,
     }
   }
+  /// ids of the tool calls the message made, in the same order as `tool_calls`
+  pub fn tool_call_ids(&self) -> Vec<&str> {
+    match &self.chat_message {
+      ChatMessageType::Chat(ChatCompletionRequestMessage::Assistant(assistant)) => {
+        assistant.tool_calls.iter().flatten().map(|call| call.id.as_str()).collect()
+      },
+      _ => vec![],
+    }
+  }
+
   pub fn tool_calls(&self) -> Option<Vec<(&str, &str)>> {
     match &self.chat_message {
       ChatMessageType::Chat(message) => chat_completion_request_message_tool_calls_as_str(message),
@@ -267,12 +423,7 @@ pub fn session_messages(cx: &mut Context) {
   let (_view, _doc) = current!(cx.editor);
 
   let messages_fut = futures_util::future::ready(
-    cx.session
-      .messages
-      .clone()
-      .iter()
-      .map(|message| ChatMessageItem::new_chat(message.message_id, message.message.clone()))
-      .collect::<Vec<_>>(),
+    cx.session.messages.iter().map(ChatMessageItem::from_container).collect::<Vec<_>>(),
   );
 
   let session_callback = |_context: &mut compositor::Context,
@@ -314,3 +465,385 @@ pub fn session_messages(cx: &mut Context) {
     Ok(Callback::EditorCompositor(Box::new(call)))
   });
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageAction {
+  Copy,
+  Rerun,
+  Fork,
+  Collapse,
+  Expand,
+  Export,
+  Delete,
+  OpenFile(PathBuf),
+}
+
+impl ui::menu::Item for MessageAction {
+  type Data = ();
+
+  fn format(&self, _data: &Self::Data) -> Row {
+    match self {
+      MessageAction::Copy => "copy message to clipboard".into(),
+      MessageAction::Rerun => "re-run from this message".into(),
+      MessageAction::Fork => "fork session from this message".into(),
+      MessageAction::Collapse => "collapse message to one line".into(),
+      MessageAction::Expand => "expand message".into(),
+      MessageAction::Export => "export message to a file".into(),
+      MessageAction::Delete => "delete message from the context".into(),
+      MessageAction::OpenFile(path) => format!("open {}", path.display()).into(),
+    }
+  }
+}
+
+/// paths mentioned in the message text that exist relative to the working directory
+pub fn referenced_files(text: &str) -> Vec<PathBuf> {
+  let cwd = helix_stdx::env::current_working_dir();
+  let mut files: Vec<PathBuf> = Vec::new();
+  text
+    .split(|c: char| c.is_whitespace() || matches!(c, '`' | '"' | '\'' | '(' | ')' | '[' | ']'))
+    .map(|word| word.trim_end_matches(|c: char| matches!(c, '.' | ',' | ':' | ';')))
+    // strip line and column suffixes, e.g. src/main.rs:10:4
+    .map(|word| word.split(':').next().unwrap_or(word))
+    .filter(|word| word.contains('.') || word.contains('/'))
+    .for_each(|word| {
+      let path = PathBuf::from(word);
+      let path = if path.is_absolute() { path } else { cwd.join(path) };
+      if path.is_file() && !files.contains(&path) {
+        files.push(path);
+      }
+    });
+  files
+}
+
+/// folds the message under the session cursor to a one line summary, or unfolds it
+pub fn toggle_message_fold(cx: &mut Context) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    match session.toggle_fold_at_cursor() {
+      Some(true) => cx.editor.set_status("message folded"),
+      Some(false) => cx.editor.set_status("message unfolded"),
+      None => cx.editor.set_status("no message under cursor"),
+    }
+  }))
+}
+
+/// removes the message under the session cursor from the context, see Session::delete_message
+pub fn delete_message(cx: &mut Context) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    match session.message_at_cursor().map(|message| message.id) {
+      Some(Some(message_id)) => set_delete_status(cx.editor, cx.session.delete_message(message_id)),
+      Some(None) => cx.editor.set_status("error messages are not part of the context"),
+      None => cx.editor.set_status("no message under cursor"),
+    }
+  }))
+}
+
+/// restores the most recently deleted messages
+pub fn undo_delete_message(cx: &mut Context) {
+  match cx.session.undo_delete_message() {
+    Ok(count) => cx.editor.set_status(format!("restored {} message(s)", count)),
+    Err(e) => cx.editor.set_error(format!("error restoring messages: {}", e)),
+  }
+}
+
+/// keeps the change a tool made to a file in the message under the cursor
+pub fn accept_file_edit(cx: &mut Context) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    let Some(edit) = session.pending_file_diff_at_cursor().map(|diff| diff.edit.clone()) else {
+      cx.editor.set_status("no pending file edit under cursor");
+      return;
+    };
+    session.set_file_diff_status(&edit.tool_call_id, DiffStatus::Accepted);
+    cx.editor.set_status(format!("accepted {}", edit.path.display()));
+  }))
+}
+
+/// restores the file a tool changed in the message under the cursor
+pub fn revert_file_edit(cx: &mut Context) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    let Some(tool_call_id) =
+      session.pending_file_diff_at_cursor().map(|diff| diff.edit.tool_call_id.clone())
+    else {
+      cx.editor.set_status("no pending file edit under cursor");
+      return;
+    };
+    set_revert_status(cx.editor, cx.session.revert_file_edit(&tool_call_id));
+  }))
+}
+
+/// shows the file the session is working on in a view beside it, or closes that view
+pub fn toggle_split_view(cx: &mut Context) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    if let Some(view_id) = split_view(compositor) {
+      if cx.editor.tree.contains(view_id) {
+        cx.editor.close(view_id);
+      }
+      set_split_view(compositor, None);
+      return;
+    }
+    let workspace_root = match &cx.session.config.workspace {
+      Some(workspace) => workspace.workspace_path.clone(),
+      None => helix_stdx::env::current_working_dir(),
+    };
+    let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+    let Some(path) = session.working_file(&workspace_root) else {
+      cx.editor.set_status("no file has been changed or mentioned in this session");
+      return;
+    };
+    match show_in_split_view(cx.editor, None, &path) {
+      Ok(view_id) => set_split_view(compositor, Some(view_id)),
+      Err(e) => cx.editor.set_error(format!("unable to open \"{}\" {}", path.display(), e)),
+    }
+  }))
+}
+
+pub fn set_revert_status(editor: &mut Editor, reverted: Result<Vec<FileEdit>, SazidError>) {
+  match reverted {
+    Ok(edits) => {
+      let paths = edits.iter().map(|edit| edit.path.display().to_string()).collect::<Vec<_>>();
+      editor.set_status(format!("reverted {}", paths.join(", ")))
+    },
+    Err(e) => editor.set_error(format!("error reverting file edit: {}", e)),
+  }
+}
+
+fn set_delete_status(editor: &mut Editor, deleted: Result<usize, SazidError>) {
+  match deleted {
+    Ok(count) => editor.set_status(format!("deleted {} message(s), space u to undo", count)),
+    Err(e) => editor.set_error(format!("error deleting message: {}", e)),
+  }
+}
+
+/// opens a picker listing the actions available for the message under the session cursor
+pub fn message_actions(cx: &mut Context) {
+  cx.callback.push(Box::new(move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+    let Some(message) =
+      compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap().message_at_cursor().cloned()
+    else {
+      cx.editor.set_status("no message under cursor");
+      return;
+    };
+    let Some(message_id) = message.id else {
+      cx.editor.set_status("no actions available for error messages");
+      return;
+    };
+
+    let fold = match message.folded {
+      Some(_) => MessageAction::Expand,
+      None => MessageAction::Collapse,
+    };
+    let mut actions = vec![
+      MessageAction::Copy,
+      MessageAction::Rerun,
+      MessageAction::Fork,
+      fold,
+      MessageAction::Export,
+      MessageAction::Delete,
+    ];
+    actions.extend(referenced_files(message.content()).into_iter().map(MessageAction::OpenFile));
+
+    let picker = Picker::new(actions, (), move |cx, action, _| match action {
+      MessageAction::Copy => {
+        match cx.editor.registers.write('+', vec![message.content().to_string()]) {
+          Ok(_) => cx.editor.set_status("message copied to clipboard"),
+          Err(e) => cx.editor.set_error(format!("error copying message: {}", e)),
+        }
+      },
+      MessageAction::Rerun => match cx.session.rerun_from_message(message_id) {
+        Ok(_) => cx.editor.set_status("re-running from message"),
+        Err(e) => cx.editor.set_error(format!("error re-running message: {}", e)),
+      },
+      MessageAction::Fork => {
+        let saved = open_session_store().and_then(|mut store| cx.session.save_session(&mut store));
+        if let Err(e) = saved {
+          cx.editor.set_error(format!("error saving session before fork: {}", e));
+          return;
+        }
+        match cx.session.fork_from_message(message_id) {
+          Ok(_) => cx.editor.set_status(format!("forked session: {}", cx.session.config.title)),
+          Err(e) => cx.editor.set_error(format!("error forking session: {}", e)),
+        }
+      },
+      // the picker has closed by the time the action runs, the session view is reached through
+      // the compositor once it is back on top
+      MessageAction::Collapse | MessageAction::Expand => {
+        cx.jobs.callback(async move {
+          let call = move |editor: &mut Editor, compositor: &mut Compositor| {
+            let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+            match session.toggle_fold_at_cursor() {
+              Some(true) => editor.set_status("message collapsed"),
+              Some(false) => editor.set_status("message expanded"),
+              None => editor.set_status("no message under cursor"),
+            }
+          };
+          Ok(Callback::EditorCompositor(Box::new(call)))
+        });
+      },
+      MessageAction::Export => {
+        cx.jobs.callback(async move {
+          let call = move |_editor: &mut Editor, compositor: &mut Compositor| {
+            let prompt = ui::Prompt::new(
+              "export message to:".into(),
+              None,
+              ui::completers::filename,
+              move |cx, input, event| {
+                if event == ui::PromptEvent::Validate && !input.is_empty() {
+                  export_message(cx, message_id, input);
+                }
+              },
+            );
+            compositor.push(Box::new(prompt));
+          };
+          Ok(Callback::EditorCompositor(Box::new(call)))
+        });
+      },
+      MessageAction::Delete => set_delete_status(cx.editor, cx.session.delete_message(message_id)),
+      MessageAction::OpenFile(path) => {
+        if let Err(e) = cx.editor.open(path, Action::Replace) {
+          cx.editor.set_error(format!("unable to open \"{}\" {}", path.display(), e));
+        }
+      },
+    });
+    compositor.push(Box::new(overlaid(picker)));
+  }))
+}
+
+/// writes the message with `message_id` to `path`, as html if the path ends in .html and as
+/// markdown otherwise
+fn export_message(cx: &mut compositor::Context, message_id: i64, path: &str) {
+  let path = helix_stdx::path::expand_tilde(Path::new(path));
+  let format = path
+    .extension()
+    .and_then(|extension| extension.to_str()?.parse::<ExportFormat>().ok())
+    .unwrap_or(ExportFormat::Markdown);
+  let Some(message) = cx.session.messages.iter().find(|m| m.message_id == message_id) else {
+    cx.editor.set_error("the message is no longer in the session");
+    return;
+  };
+  let title = format!("{} message {}", cx.session.config.title, message_id);
+  match export_session(&title, std::slice::from_ref(message), format, &path) {
+    Ok(()) => cx.editor.set_status(format!("exported message to {}", path.display())),
+    Err(e) => cx.editor.set_error(format!("error exporting message: {}", e)),
+  }
+}
+
+/// opens the session store in the data directory. sessions that earlier versions saved as json
+/// files are imported into it the first time
+pub fn open_session_store() -> Result<SessionStore, SazidError> {
+  let data_dir = helix_loader::data_dir();
+  let mut store = SessionStore::open(&data_dir.join("sessions.sqlite"))?;
+  for legacy_dir in ["session_history", "sessions"] {
+    let migrated = store.migrate_json_sessions(&data_dir.join(legacy_dir))?;
+    if migrated > 0 {
+      log::info!("imported {} sessions from {}", migrated, legacy_dir);
+    }
+  }
+  Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+    ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent, ChatCompletionToolType, FunctionCall, Role,
+  };
+  use sazid::app::snippets::snippet_message;
+
+  #[test]
+  fn test_fold_summary_names_tool() {
+    let assistant = ChatMessageItem::new_chat(
+      1,
+      ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+        name: None,
+        role: Role::Assistant,
+        content: None,
+        function_call: None,
+        tool_calls: Some(vec![ChatCompletionMessageToolCall {
+          id: "call_1".to_string(),
+          r#type: ChatCompletionToolType::Function,
+          function: FunctionCall { name: "read_file".to_string(), arguments: "{}".to_string() },
+        }]),
+      }),
+    );
+    let tool = ChatMessageItem::new_chat(
+      2,
+      ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+        role: Role::Tool,
+        content: "line\n".repeat(412),
+        tool_call_id: "call_1".to_string(),
+      }),
+    );
+    assert!(tool.folds_by_default());
+    assert_eq!(
+      tool.fold_summary(&[assistant.clone(), tool.clone()]),
+      "tool: read_file — 412 lines"
+    );
+    assert!(!assistant.folds_by_default());
+  }
+
+  #[test]
+  fn test_snippet_folds_to_a_chip() {
+    let log = (1..=120).map(|i| format!("[{}] request handled\n", i)).collect::<String>();
+    let snippet = ChatMessageItem::new_chat(
+      1,
+      ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+        role: Role::User,
+        name: None,
+        content: ChatCompletionRequestUserMessageContent::Text(snippet_message(&log)),
+      }),
+    );
+    assert!(snippet.folds_by_default());
+    assert_eq!(snippet.fold_summary(&[]), "snippet: [1] request handled — 120 lines");
+  }
+
+  #[test]
+  fn test_gutter_lines() {
+    let mut assistant = ChatMessageItem::new_chat(
+      1,
+      ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+        name: None,
+        role: Role::Assistant,
+        content: Some("hello".to_string()),
+        function_call: None,
+        tool_calls: None,
+      }),
+    );
+    assistant.timestamp = Some(1_700_000_000);
+    assistant.model = Some("gpt-4o".to_string());
+    let lines = assistant.gutter_lines(3, &GutterConfig::default());
+    assert_eq!(lines.len(), 3);
+    assert_eq!((lines[0].as_str(), lines[2].as_str()), ("◀ 3", "gpt-4o"));
+    assert!(lines[1].len() == 5 && lines[1].chars().nth(2) == Some(':'));
+
+    let config = GutterConfig { role: GutterRole::Name, timestamp: false, model: true };
+    assert_eq!(assistant.gutter_lines(3, &config), vec!["assistant", "gpt-4o"]);
+    // only assistant messages show the model that wrote them
+    let mut error = ChatMessageItem::new_error("failed".into());
+    error.model = Some("gpt-4o".to_string());
+    assert_eq!(error.gutter_lines(4, &config), vec!["error"]);
+  }
+
+  #[test]
+  fn test_wrap_cache_is_kept_until_message_changes() {
+    let syn_loader = Arc::new(ArcSwap::from_pointee(helix_core::config::default_lang_loader()));
+    let mut message = ChatMessageItem::new_error("a few words that wrap at narrow widths".into());
+    message.cache_wrapped_plain_text(40, None, &syn_loader);
+    let wide = message.plain_text.clone();
+    message.cache_wrapped_plain_text(10, None, &syn_loader);
+    assert!(message.plain_text.len_lines() > wide.len_lines());
+    assert!(message.wrap_cache.contains_key(&40));
+
+    message.cache_wrapped_plain_text(40, None, &syn_loader);
+    assert_eq!(message.plain_text, wide);
+    assert!(message.wrap_cache.contains_key(&10));
+
+    message.update_message(ChatMessageType::Error("changed".into()));
+    message.cache_wrapped_plain_text(40, None, &syn_loader);
+    assert!(message.wrap_cache.is_empty());
+  }
+}
@@ -27,8 +27,10 @@ use helix_view::{
   Document, View,
 };
 
+use sazid::app::lsi::symbol_types::SourceSymbol;
+
 use crate::{
-  compositor::{self, Compositor},
+  compositor::{self, Component, Compositor},
   job::Callback,
   ui::{self, overlay::overlaid, DynamicPicker, FileLocation, Picker, Popup, PromptEvent},
 };
@@ -39,6 +41,7 @@ use std::{
   fmt::Write,
   future::Future,
   path::{Path, PathBuf},
+  sync::Arc,
 };
 
 /// Gets the first language server that is attached to a document which supports a specific feature.
@@ -241,6 +244,28 @@ fn sym_picker(symbols: Vec<SymbolInformationItem>, current_path: Option<lsp::Url
   .truncate_start(false)
 }
 
+/// a picker of symbols from the language server interface, each with the offset encoding of the
+/// server that sent it
+pub fn source_symbol_picker(symbols: Vec<(Arc<SourceSymbol>, OffsetEncoding)>) -> impl Component {
+  #[allow(deprecated)]
+  let symbols = symbols
+    .into_iter()
+    .filter_map(|(symbol, offset_encoding)| {
+      let uri = lsp::Url::from_file_path(&symbol.file_path).ok()?;
+      let symbol = lsp::SymbolInformation {
+        name: symbol.name.clone(),
+        kind: symbol.kind,
+        tags: symbol.tags.clone(),
+        deprecated: None,
+        location: lsp::Location::new(uri, *symbol.range.lock().unwrap()),
+        container_name: symbol.parent.lock().unwrap().upgrade().map(|parent| parent.name.clone()),
+      };
+      Some(SymbolInformationItem { symbol, offset_encoding })
+    })
+    .collect();
+  overlaid(sym_picker(symbols, None))
+}
+
 #[derive(Copy, Clone, PartialEq)]
 enum DiagnosticsFormat {
   ShowSourcePath,
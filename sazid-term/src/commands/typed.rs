@@ -2,6 +2,7 @@ use std::fmt::Write;
 use std::ops::Deref;
 
 use crate::job::Job;
+use crate::movement::CodeBlock;
 
 use super::*;
 
@@ -10,8 +11,14 @@ use helix_core::indent::MAX_INDENT;
 use helix_core::{encoding, line_ending, shellwords::Shellwords};
 use helix_view::document::DEFAULT_LANGUAGE_NAME;
 use helix_view::editor::{Action, CloseError, ConfigEvent};
+use sazid::action::{LsiAction, SessionAction};
+use sazid::app::{
+  audit_log, commit_message, credentials, file_patch,
+  session_config::{WorkspaceParams, SESSION_OPTIONS},
+};
 use serde_json::Value;
 use ui::completers::{self, Completer};
+use ui::render_perf::PerfMode;
 
 #[derive(Clone)]
 pub struct TypableCommand {
@@ -1782,6 +1789,13 @@ fn set_option(
   }
   let (key, arg) = (&args[0].to_lowercase(), &args[1]);
 
+  // model settings belong to the session, not the editor config
+  if SESSION_OPTIONS.contains(&key.as_str()) {
+    cx.session.set_option(key, arg).map_err(|e| anyhow!("{}", e))?;
+    cx.editor.set_status(format!("{} set to {}", key, arg));
+    return Ok(());
+  }
+
   let key_error = || anyhow::anyhow!("Unknown key `{}`", key);
   let field_error = |_| anyhow::anyhow!("Could not parse field `{}`", arg);
 
@@ -2126,26 +2140,822 @@ fn pipe_to(
   pipe_impl(cx, args, event, &ShellBehavior::Ignore)
 }
 
-fn pipe(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
-  pipe_impl(cx, args, event, &ShellBehavior::Replace)
+fn pipe(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+  pipe_impl(cx, args, event, &ShellBehavior::Replace)
+}
+
+fn pipe_impl(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+  behavior: &ShellBehavior,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(!args.is_empty(), "Shell command required");
+  shell(cx, &args.join(" "), behavior);
+  Ok(())
+}
+
+fn run_shell_command(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let shell = cx.editor.config().shell.clone();
+  let args = args.join(" ");
+
+  let callback = async move {
+    let (output, success) = shell_impl_async(&shell, &args, None).await?;
+    let call: job::Callback = Callback::EditorCompositor(Box::new(
+      move |editor: &mut Editor, compositor: &mut Compositor| {
+        if !output.is_empty() {
+          let contents =
+            ui::Markdown::new(format!("```sh\n{}\n```", output), editor.syn_loader.clone());
+          let popup = Popup::new("shell", contents).position(Some(helix_core::Position::new(
+            editor.cursor().0.unwrap_or_default().row,
+            2,
+          )));
+          compositor.replace_or_push("shell", popup);
+        }
+        if success {
+          editor.set_status("Command succeeded");
+        } else {
+          editor.set_error("Command failed");
+        }
+      },
+    ));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+
+  Ok(())
+}
+
+fn reset_diff_change(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+  ensure!(args.is_empty(), ":reset-diff-change takes no arguments");
+
+  let editor = &mut cx.editor;
+  let scrolloff = editor.config().scrolloff;
+
+  let (view, doc) = current!(editor);
+  let Some(handle) = doc.diff_handle() else {
+    bail!("Diff is not available in the current buffer")
+  };
+
+  let diff = handle.load();
+  let doc_text = doc.text().slice(..);
+  let line = doc.selection(view.id).primary().cursor_line(doc_text);
+
+  let Some(hunk_idx) = diff.hunk_at(line as u32, true) else {
+    bail!("There is no change at the cursor")
+  };
+  let hunk = diff.nth_hunk(hunk_idx);
+  let diff_base = diff.diff_base();
+  let before_start = diff_base.line_to_char(hunk.before.start as usize);
+  let before_end = diff_base.line_to_char(hunk.before.end as usize);
+  let text: Tendril = diff.diff_base().slice(before_start..before_end).chunks().collect();
+  let anchor = doc_text.line_to_char(hunk.after.start as usize);
+  let transaction = Transaction::change(
+    doc.text(),
+    [(anchor, doc_text.line_to_char(hunk.after.end as usize), (!text.is_empty()).then_some(text))]
+      .into_iter(),
+  );
+  drop(diff); // make borrow check happy
+  doc.apply(&transaction, view.id);
+  // select inserted text
+  let text_len = before_end - before_start;
+  doc.set_selection(view.id, Selection::single(anchor, anchor + text_len));
+  doc.append_changes_to_history(view);
+  view.ensure_cursor_in_view(doc, scrolloff);
+  Ok(())
+}
+
+fn clear_register(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 1, ":clear-register takes at most 1 argument");
+  if args.is_empty() {
+    cx.editor.registers.clear();
+    cx.editor.set_status("All registers cleared");
+    return Ok(());
+  }
+
+  ensure!(args[0].chars().count() == 1, format!("Invalid register {}", args[0]));
+  let register = args[0].chars().next().unwrap_or_default();
+  if cx.editor.registers.remove(register) {
+    cx.editor.set_status(format!("Register {} cleared", register));
+  } else {
+    cx.editor.set_error(format!("Register {} not found", register));
+  }
+  Ok(())
+}
+
+fn redraw(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let callback = Box::pin(async move {
+    let call: job::Callback = job::Callback::EditorCompositor(Box::new(|_editor, compositor| {
+      compositor.need_full_redraw();
+    }));
+
+    Ok(call)
+  });
+
+  cx.jobs.callback(callback);
+
+  Ok(())
+}
+
+fn move_buffer(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() == 1, format!(":move takes one argument"));
+  let doc = doc!(cx.editor);
+  let old_path = doc.path().context("Scratch buffer cannot be moved. Use :write instead")?.clone();
+  let new_path = args.first().unwrap().to_string();
+  if let Err(err) = cx.editor.move_path(&old_path, new_path.as_ref()) {
+    bail!("Could not move file: {err}");
+  }
+  Ok(())
+}
+
+fn regenerate(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 2, ":regenerate takes at most two arguments");
+  let mut model = None;
+  let mut temperature_bump = None;
+  for arg in args {
+    match arg.parse::<f32>() {
+      Ok(bump) => temperature_bump = Some(bump),
+      Err(_) => {
+        model = Some(
+          sazid::app::types::Model::from_name(arg)
+            .ok_or_else(|| anyhow!("unknown model: {}", arg))?,
+        )
+      },
+    }
+  }
+
+  cx.session.regenerate_last_response(model, temperature_bump).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status("regenerating response");
+  Ok(())
+}
+
+fn usage(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.is_empty(), ":usage takes no arguments");
+  let usage = &cx.session.usage;
+  let mut readout = usage
+    .by_model
+    .iter()
+    .map(|(model, u)| {
+      format!(
+        "{}: {} prompt, {} completion, ${:.4}",
+        model, u.prompt_tokens, u.completion_tokens, u.cost
+      )
+    })
+    .collect::<Vec<String>>();
+  readout.push(format!("total: {}", usage.summary()));
+  cx.editor.set_status(readout.join(" | "));
+  Ok(())
+}
+
+fn export(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() == 2, ":export takes two arguments: <markdown|html> <path>");
+  let format = args[0].parse::<sazid::app::export::ExportFormat>().map_err(|e| anyhow!("{}", e))?;
+  let path = helix_stdx::path::expand_tilde(Path::new(args[1].as_ref()));
+  sazid::app::export::export_session(&cx.session.config.title, &cx.session.messages, format, &path)
+    .map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("exported session to {}", path.display()));
+  Ok(())
+}
+
+fn import(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(
+    !args.is_empty() && args.len() <= 2,
+    ":import takes a path and optionally a conversation index or title"
+  );
+  let path = helix_stdx::path::expand_tilde(Path::new(args[0].as_ref()));
+  let mut conversations =
+    sazid::app::import::import_conversations(&path).map_err(|e| anyhow!("{}", e))?;
+  let count = conversations.len();
+  let index = match args.get(1) {
+    None => 0,
+    Some(selector) => match selector.parse::<usize>() {
+      Ok(index) => index,
+      Err(_) => conversations
+        .iter()
+        .position(|c| c.title.to_lowercase().contains(&selector.to_lowercase()))
+        .ok_or_else(|| anyhow!("no conversation titled {}", selector))?,
+    },
+  };
+  ensure!(index < count, "{} contains {} conversations", path.display(), count);
+
+  let conversation = conversations.swap_remove(index);
+  let title = conversation.title.clone();
+  cx.session.import_conversation(conversation).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("imported {} ({} of {})", title, index + 1, count));
+  Ok(())
+}
+
+/// the path given to :pin, :unpin or :outline, or the path of the current document
+fn pin_path_arg(cx: &mut compositor::Context, args: &[Cow<str>]) -> anyhow::Result<PathBuf> {
+  match args.first() {
+    Some(path) => Ok(helix_stdx::path::canonicalize(Path::new(path.as_ref()))),
+    None => doc!(cx.editor).path().cloned().ok_or_else(|| anyhow!("the buffer has no path")),
+  }
+}
+
+fn pin(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 1, ":pin takes at most one path");
+  let path = pin_path_arg(cx, args)?;
+  cx.session.pin_file(path.clone()).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("pinned {}", path.display()));
+  Ok(())
+}
+
+fn unpin(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 1, ":unpin takes at most one path");
+  let path = pin_path_arg(cx, args)?;
+  cx.session.unpin_file(&path).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("unpinned {}", path.display()));
+  Ok(())
+}
+
+/// holds the replies of the session to a json schema, validating each and asking again when one
+/// does not match
+fn schema(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  if args.is_empty() {
+    match &cx.session.response_schema {
+      Some(schema) => cx.editor.set_status(format!("replies follow {}", schema.path.display())),
+      None => cx.editor.set_status("no schema is attached"),
+    }
+    return Ok(());
+  }
+  ensure!(args.len() == 1, ":schema takes one path");
+  let path = helix_stdx::path::expand_tilde(Path::new(args[0].as_ref()));
+  cx.session.attach_schema(&path).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("replies follow {}", path.display()));
+  Ok(())
+}
+
+fn schema_clear(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(cx.session.response_schema.take().is_some(), "no schema is attached");
+  cx.editor.set_status("replies are no longer held to a schema");
+  Ok(())
+}
+
+fn undo_edit(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  set_revert_status(cx.editor, cx.session.undo_file_edit());
+  Ok(())
+}
+
+// the queued input an argument numbers, counting from 1, or the last one without an argument
+fn queued_input_arg(cx: &compositor::Context, args: &[Cow<str>]) -> anyhow::Result<usize> {
+  let queued = cx.session.queued_inputs.len();
+  ensure!(queued > 0, "no inputs are queued");
+  match args.first() {
+    Some(arg) => match arg.parse::<usize>() {
+      Ok(n) if (1..=queued).contains(&n) => Ok(n - 1),
+      _ => bail!("expected a queued input from 1 to {}", queued),
+    },
+    None => Ok(queued - 1),
+  }
+}
+
+fn queue_edit(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 1, ":queue-edit takes at most one number");
+  let index = queued_input_arg(cx, args)?;
+  ensure!(
+    doc!(cx.editor).text().chars().all(char::is_whitespace),
+    "the input is not empty, submit or clear it first"
+  );
+  let input = cx.session.queued_inputs.remove(index);
+  replace_input(cx.editor, &input);
+  cx.editor.set_status(format!("editing queued input {}, submit it to queue it again", index + 1));
+  Ok(())
+}
+
+fn queue_drop(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 1, ":queue-drop takes at most one number");
+  if args.is_empty() {
+    let dropped = std::mem::take(&mut cx.session.queued_inputs).len();
+    cx.editor.set_status(format!("dropped {} queued inputs", dropped));
+    return Ok(());
+  }
+  let index = queued_input_arg(cx, args)?;
+  cx.session.queued_inputs.remove(index);
+  cx.editor.set_status(format!("dropped queued input {}", index + 1));
+  Ok(())
+}
+
+fn snippet_drop(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(args.len() <= 1, ":snippet-drop takes at most one number");
+  let snippets = cx.session.snippets.len();
+  ensure!(snippets > 0, "no snippets are attached");
+  let Some(arg) = args.first() else {
+    cx.session.snippets.clear();
+    cx.editor.set_status(format!("dropped {} snippets", snippets));
+    return Ok(());
+  };
+  match arg.parse::<usize>() {
+    Ok(n) if (1..=snippets).contains(&n) => {
+      cx.session.snippets.remove(n - 1);
+      cx.editor.set_status(format!("dropped snippet {}", n));
+      Ok(())
+    },
+    _ => bail!("expected a snippet from 1 to {}", snippets),
+  }
+}
+
+/// hands a goal to a new session in a background tab, which works towards it on its own while
+/// the current session stays in front
+fn agent(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(!args.is_empty(), ":agent needs a goal");
+  let goal = args.join(" ");
+  let config = cx.session.config.for_new_session();
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(move |editor, compositor| {
+      let Some(tabs) = compositor.find::<ui::SessionTabs>() else {
+        return;
+      };
+      tabs.open_agent(config, &goal);
+      ui::session_tabs::sync_tab_bar(compositor);
+      editor.set_status("started the agent task in a new tab");
+    }));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+  Ok(())
+}
+
+/// asks the model to draft a plan for the goal, which is shown as a checklist to review
+fn plan(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(!args.is_empty(), ":plan needs a goal");
+  ensure!(!cx.session.is_busy(), "cannot draft a plan while a response is in progress");
+  cx.session.start_plan(&args.join(" "));
+  cx.editor.set_status("drafting a plan, it is shown for review once the reply completes");
+  Ok(())
+}
+
+/// shows the checklist of the session's plan
+fn plan_review(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  ensure!(cx.session.plan.is_some(), "the session has no plan, :plan drafts one");
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(move |_editor, compositor| {
+      if compositor.find::<ui::PlanEditor>().is_none() {
+        compositor.push(Box::new(ui::PlanEditor::default()));
+      }
+    }));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+  Ok(())
+}
+
+/// shows or hides the panel tailing the log file
+fn log_viewer(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(move |_editor, compositor| {
+      if compositor.find::<ui::LogViewer>().is_some() {
+        compositor.remove(ui::LogViewer::ID);
+      } else {
+        compositor.push(Box::new(ui::LogViewer::new(helix_loader::log_file())));
+      }
+    }));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+  Ok(())
+}
+
+/// searches the symbols of the workspaces with a symbol query, such as
+/// `kind:function path:src/** parent:App handle`, and shows the matches in a picker
+fn symbols(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+  let Some(tx) = &cx.session.action_tx else {
+    bail!("no session is open");
+  };
+  tx.send(SessionAction::LsiAction(LsiAction::SearchSymbols(args.join(" "))))?;
+  Ok(())
+}
+
+/// shows the symbol tree of a file, or of the current buffer, beside its text
+fn outline(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+  ensure!(args.len() <= 1, ":outline takes at most one path");
+  let path = pin_path_arg(cx, args)?;
+  let Some(tx) = &cx.session.action_tx else {
+    bail!("no session is open");
+  };
+  tx.send(SessionAction::LsiAction(LsiAction::ShowOutline(path)))?;
+  Ok(())
+}
+
+/// shows the language servers in the registry, to restart or stop them or read their stderr
+fn lsp_panel(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(move |_editor, compositor| {
+      if compositor.find::<ui::LanguageServerPanel>().is_none() {
+        compositor.push(Box::new(ui::LanguageServerPanel::default()));
+      }
+    }));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+  Ok(())
+}
+
+/// asks for an api key and stores it in the os keyring
+fn login(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let provider = args.first().map(|provider| provider.as_ref()).unwrap_or("openai");
+  let providers = credentials::PROVIDERS;
+  let provider = *providers.iter().find(|name| **name == provider).ok_or_else(|| {
+    anyhow!("unknown provider {}, the providers are: {}", provider, providers.join(", "))
+  })?;
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(move |_editor, compositor| {
+      compositor.push(Box::new(ui::provider_setup::api_key_prompt(provider)));
+    }));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+  Ok(())
+}
+
+/// proposes a commit message for the staged changes in an editable buffer. run from that
+/// buffer, commits the staged changes with the message in it
+fn commit_msg(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let dir = match &cx.session.config.workspace {
+    Some(workspace) => workspace.workspace_path.clone(),
+    None => helix_stdx::env::current_working_dir(),
+  };
+  let message_path = helix_stdx::path::canonicalize(commit_message::commit_message_path(&dir)?);
+
+  let doc = doc!(cx.editor);
+  if doc.path() == Some(&message_path) {
+    std::fs::write(&message_path, doc.text().to_string())?;
+    let output = commit_message::commit(&dir, &message_path)?;
+    cx.editor.set_status(output.lines().next().unwrap_or("committed").to_string());
+    return Ok(());
+  }
+
+  let diff = commit_message::staged_diff(&dir)?;
+  let request = cx.session.request_commit_message(&diff);
+  cx.editor.set_status("writing a commit message for the staged changes");
+  let callback = async move {
+    let message = request.await?;
+    let call: job::Callback = Callback::EditorCompositor(Box::new(
+      move |editor: &mut Editor, _compositor: &mut Compositor| {
+        if let Err(e) = editor.open(&message_path, Action::Replace) {
+          editor.set_error(format!("error opening {}: {}", message_path.display(), e));
+          return;
+        }
+        // the buffer may still hold a message proposed before
+        let (view, doc) = current!(editor);
+        let transaction = Transaction::change(
+          doc.text(),
+          std::iter::once((0, doc.text().len_chars(), Some(message.into()))),
+        );
+        doc.apply(&transaction, view.id);
+        doc.append_changes_to_history(view);
+        editor.set_status("edit the commit message, then run :commit-msg from it to commit");
+      },
+    ));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
+  Ok(())
+}
+
+fn rollback_all(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let count = cx.session.rollback_file_edits().map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("reverted {} file edit(s)", count));
+  Ok(())
+}
+
+/// indexes the workspace files the file picker lists for semantic search, in the background
+fn index_workspace(
+  cx: &mut compositor::Context,
+  _args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  let root = match &cx.session.config.workspace {
+    Some(workspace) => workspace.workspace_path.clone(),
+    None => bail!("indexing needs a workspace"),
+  };
+  let files = ui::workspace_walk_builder(&root, &cx.editor.config())
+    .build()
+    .filter_map(|entry| entry.ok())
+    .filter(|entry| entry.file_type().map_or(false, |file_type| file_type.is_file()))
+    .map(|entry| entry.into_path())
+    .collect::<Vec<_>>();
+  let count = files.len();
+  cx.session.index_workspace_files(files).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!("indexing {} workspace files", count));
+  Ok(())
+}
+
+/// without arguments lists the registered workspaces, with a name scopes tool calls to that one
+fn workspace(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  match args.first() {
+    Some(name) => {
+      cx.session.select_workspace(name).map_err(|e| anyhow!("{}", e))?;
+      cx.editor.set_status(format!("tool calls now run in workspace {}", name));
+    },
+    None => {
+      let active = cx.session.config.workspace.as_ref().map(|w| w.workspace_path.clone());
+      let workspaces = cx
+        .session
+        .config
+        .all_workspaces()
+        .iter()
+        .map(|w| {
+          if Some(&w.workspace_path) == active.as_ref() {
+            format!("*{}", w.name())
+          } else {
+            w.name()
+          }
+        })
+        .collect::<Vec<_>>();
+      ensure!(!workspaces.is_empty(), "no workspaces are registered");
+      cx.editor.set_status(workspaces.join(" "));
+    },
+  }
+  Ok(())
+}
+
+/// without arguments lists the prompt profiles, with a name makes that one the system prompt
+fn profile(
+  cx: &mut compositor::Context,
+  args: &[Cow<str>],
+  event: PromptEvent,
+) -> anyhow::Result<()> {
+  if event != PromptEvent::Validate {
+    return Ok(());
+  }
+
+  match args.first() {
+    Some(name) => {
+      cx.session.select_prompt_profile(name).map_err(|e| anyhow!("{}", e))?;
+      cx.editor.set_status(format!("system prompt set to the {} profile", name));
+    },
+    None => {
+      let active = cx.session.config.prompt_profile.clone();
+      let profiles = cx
+        .session
+        .config
+        .all_prompt_profiles()
+        .into_keys()
+        .map(|name| if Some(&name) == active.as_ref() { format!("*{}", name) } else { name })
+        .collect::<Vec<_>>();
+      cx.editor.set_status(profiles.join(" "));
+    },
+  }
+  Ok(())
 }
 
-fn pipe_impl(
+fn workspace_add(
   cx: &mut compositor::Context,
   args: &[Cow<str>],
   event: PromptEvent,
-  behavior: &ShellBehavior,
 ) -> anyhow::Result<()> {
   if event != PromptEvent::Validate {
     return Ok(());
   }
 
-  ensure!(!args.is_empty(), "Shell command required");
-  shell(cx, &args.join(" "), behavior);
+  ensure!(
+    (2..=4).contains(&args.len()),
+    ":workspace-add takes a directory, a language, and optionally a language server and a name"
+  );
+  let path =
+    helix_stdx::path::canonicalize(helix_stdx::path::expand_tilde(Path::new(args[0].as_ref())));
+  ensure!(path.is_dir(), "{} is not a directory", path.display());
+  let workspace_params = WorkspaceParams {
+    workspace_path: path,
+    language: args[1].to_string(),
+    language_server: args.get(2).map_or("rust-analyzer".to_string(), |s| s.to_string()),
+    doc_path: None,
+    name: args.get(3).map(|s| s.to_string()),
+  };
+  let name = workspace_params.name();
+  cx.session.add_workspace(workspace_params).map_err(|e| anyhow!("{}", e))?;
+  cx.editor.set_status(format!(
+    "added workspace {}, start a message with @workspace:{} or run :workspace {} to use it",
+    name, name, name
+  ));
   Ok(())
 }
 
-fn run_shell_command(
+const SESSION_SEARCH_RESULT_LIMIT: usize = 200;
+
+fn search_sessions(
   cx: &mut compositor::Context,
   args: &[Cow<str>],
   event: PromptEvent,
@@ -2154,37 +2964,26 @@ fn run_shell_command(
     return Ok(());
   }
 
-  let shell = cx.editor.config().shell.clone();
-  let args = args.join(" ");
+  ensure!(!args.is_empty(), ":search-sessions takes the text to search for");
+  let text = args.join(" ");
+  let results = open_session_store()
+    .and_then(|store| store.search_messages(&text, SESSION_SEARCH_RESULT_LIMIT))
+    .map_err(|e| anyhow!("{}", e))?;
+  ensure!(!results.is_empty(), "no messages found containing {}", text);
 
   let callback = async move {
-    let (output, success) = shell_impl_async(&shell, &args, None).await?;
     let call: job::Callback = Callback::EditorCompositor(Box::new(
-      move |editor: &mut Editor, compositor: &mut Compositor| {
-        if !output.is_empty() {
-          let contents =
-            ui::Markdown::new(format!("```sh\n{}\n```", output), editor.syn_loader.clone());
-          let popup = Popup::new("shell", contents).position(Some(helix_core::Position::new(
-            editor.cursor().0.unwrap_or_default().row,
-            2,
-          )));
-          compositor.replace_or_push("shell", popup);
-        }
-        if success {
-          editor.set_status("Command succeeded");
-        } else {
-          editor.set_error("Command failed");
-        }
+      move |_editor: &mut Editor, compositor: &mut Compositor| {
+        compositor.push(Box::new(overlaid(ui::session::message_search_picker(results))));
       },
     ));
     Ok(call)
   };
   cx.jobs.callback(callback);
-
   Ok(())
 }
 
-fn reset_diff_change(
+fn audit(
   cx: &mut compositor::Context,
   args: &[Cow<str>],
   event: PromptEvent,
@@ -2192,45 +2991,28 @@ fn reset_diff_change(
   if event != PromptEvent::Validate {
     return Ok(());
   }
-  ensure!(args.is_empty(), ":reset-diff-change takes no arguments");
 
-  let editor = &mut cx.editor;
-  let scrolloff = editor.config().scrolloff;
-
-  let (view, doc) = current!(editor);
-  let Some(handle) = doc.diff_handle() else {
-    bail!("Diff is not available in the current buffer")
+  ensure!(args.is_empty(), ":audit takes no arguments");
+  let Some(dir) = &cx.session.config.audit_dir else {
+    bail!("the tool calls of this session are not logged, set audit_dir in the config");
   };
+  let path = audit_log::audit_log_path(dir, &cx.session.config.id);
+  let entries = audit_log::load(&path).map_err(|e| anyhow!("{}", e))?;
+  ensure!(!entries.is_empty(), "this session has not called any tools");
 
-  let diff = handle.load();
-  let doc_text = doc.text().slice(..);
-  let line = doc.selection(view.id).primary().cursor_line(doc_text);
-
-  let Some(hunk_idx) = diff.hunk_at(line as u32, true) else {
-    bail!("There is no change at the cursor")
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(
+      move |_editor: &mut Editor, compositor: &mut Compositor| {
+        compositor.push(Box::new(overlaid(ui::session::audit_picker(entries))));
+      },
+    ));
+    Ok(call)
   };
-  let hunk = diff.nth_hunk(hunk_idx);
-  let diff_base = diff.diff_base();
-  let before_start = diff_base.line_to_char(hunk.before.start as usize);
-  let before_end = diff_base.line_to_char(hunk.before.end as usize);
-  let text: Tendril = diff.diff_base().slice(before_start..before_end).chunks().collect();
-  let anchor = doc_text.line_to_char(hunk.after.start as usize);
-  let transaction = Transaction::change(
-    doc.text(),
-    [(anchor, doc_text.line_to_char(hunk.after.end as usize), (!text.is_empty()).then_some(text))]
-      .into_iter(),
-  );
-  drop(diff); // make borrow check happy
-  doc.apply(&transaction, view.id);
-  // select inserted text
-  let text_len = before_end - before_start;
-  doc.set_selection(view.id, Selection::single(anchor, anchor + text_len));
-  doc.append_changes_to_history(view);
-  view.ensure_cursor_in_view(doc, scrolloff);
+  cx.jobs.callback(callback);
   Ok(())
 }
 
-fn clear_register(
+fn perf_mode(
   cx: &mut compositor::Context,
   args: &[Cow<str>],
   event: PromptEvent,
@@ -2239,62 +3021,115 @@ fn clear_register(
     return Ok(());
   }
 
-  ensure!(args.len() <= 1, ":clear-register takes at most 1 argument");
-  if args.is_empty() {
-    cx.editor.registers.clear();
-    cx.editor.set_status("All registers cleared");
-    return Ok(());
-  }
-
-  ensure!(args[0].chars().count() == 1, format!("Invalid register {}", args[0]));
-  let register = args[0].chars().next().unwrap_or_default();
-  if cx.editor.registers.remove(register) {
-    cx.editor.set_status(format!("Register {} cleared", register));
-  } else {
-    cx.editor.set_error(format!("Register {} not found", register));
-  }
+  ensure!(args.len() <= 1, ":perf-mode takes on, off or auto");
+  let mode = args.first().map(|mode| mode.parse::<PerfMode>()).transpose()?;
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(
+      move |editor: &mut Editor, compositor: &mut Compositor| {
+        let perf = &mut compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap().perf;
+        perf.mode = mode.unwrap_or(match perf.is_degraded() {
+          true => PerfMode::Normal,
+          false => PerfMode::Degraded,
+        });
+        editor.set_status(format!(
+          "perf mode {:?}, degraded rendering {}",
+          perf.mode,
+          if perf.is_degraded() { "on" } else { "off" }
+        ));
+        helix_event::request_redraw();
+      },
+    ));
+    Ok(call)
+  };
+  cx.jobs.callback(callback);
   Ok(())
 }
 
-fn redraw(
+fn block_save(
   cx: &mut compositor::Context,
-  _args: &[Cow<str>],
+  args: &[Cow<str>],
   event: PromptEvent,
 ) -> anyhow::Result<()> {
   if event != PromptEvent::Validate {
     return Ok(());
   }
 
-  let callback = Box::pin(async move {
-    let call: job::Callback = job::Callback::EditorCompositor(Box::new(|_editor, compositor| {
-      compositor.need_full_redraw();
-    }));
-
+  ensure!(args.len() <= 1, ":block-save takes the path to write the code block to");
+  let path = args.first().map(|path| helix_stdx::path::expand_tilde(Path::new(path.as_ref())));
+  let path = path.map(|path| path.to_path_buf());
+  let callback = async move {
+    let call: job::Callback = Callback::EditorCompositor(Box::new(
+      move |editor: &mut Editor, compositor: &mut Compositor| {
+        let session = compositor.find::<ui::SessionView<ChatMessageItem>>().unwrap();
+        let cursor = session.selection.primary().cursor(session.get_messages_plaintext());
+        let block = session.code_block_at(cursor);
+        let (path, original, contents) = match block_save_change(block, path) {
+          Ok(change) => change,
+          Err(e) => {
+            editor.set_error(e.to_string());
+            return;
+          },
+        };
+        let preview = match original == contents {
+          true => "no changes".to_string(),
+          false => file_patch::preview_diff(&original, &contents),
+        };
+        let mut prompt = Prompt::new(
+          format!("write {}? (y/n): ", path.display()).into(),
+          None,
+          ui::completers::none,
+          move |cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+            if event != PromptEvent::Validate {
+              return;
+            }
+            if !matches!(input.trim(), "y" | "yes") {
+              cx.editor.set_status("code block was not saved");
+              return;
+            }
+            let written = path
+              .parent()
+              .map_or(Ok(()), std::fs::create_dir_all)
+              .and_then(|_| std::fs::write(&path, &contents));
+            match written {
+              Ok(()) => cx.editor.set_status(format!("wrote {}", path.display())),
+              Err(e) => cx.editor.set_error(format!("error writing {}: {}", path.display(), e)),
+            }
+          },
+        );
+        prompt.doc_fn = Box::new(move |_| Some(preview.clone().into()));
+        compositor.push(Box::new(prompt));
+      },
+    ));
     Ok(call)
-  });
-
+  };
   cx.jobs.callback(callback);
-
   Ok(())
 }
 
-fn move_buffer(
-  cx: &mut compositor::Context,
-  args: &[Cow<str>],
-  event: PromptEvent,
-) -> anyhow::Result<()> {
-  if event != PromptEvent::Validate {
-    return Ok(());
-  }
-
-  ensure!(args.len() == 1, format!(":move takes one argument"));
-  let doc = doc!(cx.editor);
-  let old_path = doc.path().context("Scratch buffer cannot be moved. Use :write instead")?.clone();
-  let new_path = args.first().unwrap().to_string();
-  if let Err(err) = cx.editor.move_path(&old_path, new_path.as_ref()) {
-    bail!("Could not move file: {err}");
-  }
-  Ok(())
+/// the path a code block is saved to, with the file's current contents and its contents once
+/// the block is saved. blocks holding a diff are applied as a patch, to the path in the diff
+/// header unless a path is given
+fn block_save_change(
+  block: Option<CodeBlock>,
+  path: Option<PathBuf>,
+) -> anyhow::Result<(PathBuf, String, String)> {
+  let block = block.ok_or_else(|| anyhow!("no code block under the cursor"))?;
+  let is_diff = file_patch::is_diff(&block.language, &block.code);
+  let path = match path {
+    Some(path) => path,
+    None if is_diff => file_patch::patch_target(&block.code)
+      .ok_or_else(|| anyhow!("the diff does not name a file, :block-save takes a path"))?,
+    None => bail!(":block-save takes the path to write the code block to"),
+  };
+  let original = match path.exists() {
+    true => std::fs::read_to_string(&path)?,
+    false => String::new(),
+  };
+  let contents = match is_diff {
+    true => file_patch::apply_patch(&original, &block.code)?,
+    false => block.code,
+  };
+  Ok((path, original, contents))
 }
 
 pub const TYPABLE_COMMAND_LIST: &[TypableCommand] = &[
@@ -2658,6 +3493,27 @@ pub const TYPABLE_COMMAND_LIST: &[TypableCommand] = &[
         fun: update,
         signature: CommandSignature::none(),
     },
+    TypableCommand {
+        name: "lsp",
+        aliases: &[],
+        doc: "Show the language servers of the workspaces, with keys to restart or stop one or read its stderr.",
+        fun: lsp_panel,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "symbols",
+        aliases: &[],
+        doc: "Search the symbols of the workspaces, e.g. `:symbols kind:function,method path:src/** name:^handle_ parent:App key`. Other words are fuzzy matched against the names.",
+        fun: symbols,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "outline",
+        aliases: &[],
+        doc: "Show the symbol tree of a file, or the current buffer. Tab folds a symbol and Enter scrolls the preview to its lines.",
+        fun: outline,
+        signature: CommandSignature::positional(&[completers::filename]),
+    },
     TypableCommand {
         name: "lsp-workspace-command",
         aliases: &[],
@@ -2766,7 +3622,7 @@ pub const TYPABLE_COMMAND_LIST: &[TypableCommand] = &[
     TypableCommand {
         name: "set-option",
         aliases: &["set"],
-        doc: "Set a config option at runtime.\nFor example to disable smart case search, use `:set search.smart-case false`.",
+        doc: "Set a config option at runtime.\nFor example to disable smart case search, use `:set search.smart-case false`.\n`model`, `temperature` and `max-tokens` set the model settings of the session, for example `:set temperature 0.2`.",
         fun: set_option,
         // TODO: Add support for completion of the options value(s), when appropriate.
         signature: CommandSignature::positional(&[completers::setting]),
@@ -2904,6 +3760,195 @@ pub const TYPABLE_COMMAND_LIST: &[TypableCommand] = &[
         fun: move_buffer,
         signature: CommandSignature::positional(&[completers::filename]),
     },
+    TypableCommand {
+        name: "regenerate",
+        aliases: &["regen"],
+        doc: "Drop the last assistant response and request it again. Optionally takes a model name and/or a temperature increase, e.g. `:regenerate gpt-4 0.2`.",
+        fun: regenerate,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "usage",
+        aliases: &[],
+        doc: "Show token usage and cost for the current session, per model.",
+        fun: usage,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "export",
+        aliases: &[],
+        doc: "Export the session transcript as markdown or html. Tool calls and results are collapsed in html.",
+        fun: export,
+        signature: CommandSignature::positional(&[completers::none, completers::filename]),
+    },
+    TypableCommand {
+        name: "import",
+        aliases: &[],
+        doc: "Import a ChatGPT export or a JSONL file of role/content messages into the session. Exports with several conversations take a conversation index or title as the second argument.",
+        fun: import,
+        signature: CommandSignature::positional(&[completers::filename, completers::none]),
+    },
+    TypableCommand {
+        name: "search-sessions",
+        aliases: &[],
+        doc: "Search the messages of all saved sessions, and load the session of the selected message.",
+        fun: search_sessions,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "audit",
+        aliases: &[],
+        doc: "List the tool calls of the session in the order they ran, and jump to the message of the selected call.",
+        fun: audit,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "perf-mode",
+        aliases: &[],
+        doc: "Set degraded rendering for slow terminals: on, off or auto. auto degrades when frames are slow to draw. Without an argument, toggles degraded rendering.",
+        fun: perf_mode,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "block-save",
+        aliases: &[],
+        doc: "Write the code block under the cursor to a file, after confirming a preview of the changes. Code blocks holding a diff are applied as a patch, to the file named in the diff unless a path is given.",
+        fun: block_save,
+        signature: CommandSignature::positional(&[completers::filename]),
+    },
+    TypableCommand {
+        name: "pin",
+        aliases: &[],
+        doc: "Pin a file, or the current buffer, into the session context. Its current contents are sent with every request.",
+        fun: pin,
+        signature: CommandSignature::positional(&[completers::filename]),
+    },
+    TypableCommand {
+        name: "unpin",
+        aliases: &[],
+        doc: "Remove a pinned file, or the current buffer, from the session context.",
+        fun: unpin,
+        signature: CommandSignature::positional(&[completers::filename]),
+    },
+    TypableCommand {
+        name: "schema",
+        aliases: &[],
+        doc: "Hold replies to the JSON schema in a file. Requests ask for a JSON object, each reply is validated against the schema and asked for again when it does not match. Without a path, shows the schema attached.",
+        fun: schema,
+        signature: CommandSignature::positional(&[completers::filename]),
+    },
+    TypableCommand {
+        name: "schema-clear",
+        aliases: &[],
+        doc: "Stop holding replies to the attached JSON schema.",
+        fun: schema_clear,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "undo-edit",
+        aliases: &[],
+        doc: "Revert the most recent change a tool made to a file.",
+        fun: undo_edit,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "agent",
+        aliases: &[],
+        doc: "Start an agent task: a new session in a background tab works towards the goal on its own, calling tools and checking the results, while its progress is listed below the tabs.",
+        fun: agent,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "plan",
+        aliases: &[],
+        doc: "Ask the model to draft a plan for the goal. The steps are shown as a checklist to edit, reorder and approve, then carried out one reply at a time.",
+        fun: plan,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "plan-review",
+        aliases: &[],
+        doc: "Show the checklist of the session's plan, with the tool calls of the steps carried out.",
+        fun: plan_review,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "log-viewer",
+        aliases: &["logs"],
+        doc: "Toggle a panel tailing the log file. 1-5 filter by severity, / searches.",
+        fun: log_viewer,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "login",
+        aliases: &[],
+        doc: "Enter an api key for a provider, openai if none is given, and store it in the os keyring.",
+        fun: login,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "queue-edit",
+        aliases: &[],
+        doc: "Move a message queued behind the response, the last one unless a number is given, back into the input to edit it.",
+        fun: queue_edit,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "queue-drop",
+        aliases: &[],
+        doc: "Drop a message queued behind the response by its number, or every queued message without one.",
+        fun: queue_drop,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "snippet-drop",
+        aliases: &[],
+        doc: "Drop a pasted snippet attached to the next message by its number, or every snippet without one.",
+        fun: snippet_drop,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "rollback-all",
+        aliases: &[],
+        doc: "Revert every change tools made to files this session, most recent first.",
+        fun: rollback_all,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "index-workspace",
+        aliases: &[],
+        doc: "Index the workspace files for semantic search in the background. Files saved afterwards are indexed again.",
+        fun: index_workspace,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "workspace",
+        aliases: &[],
+        doc: "List the registered workspaces, the active one marked with *, or make the named workspace the one tool calls run in. A message starting with @workspace:<name> does the same.",
+        fun: workspace,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "workspace-add",
+        aliases: &[],
+        doc: "Register another workspace: its directory, language, and optionally its language server and name.",
+        fun: workspace_add,
+        signature: CommandSignature::positional(&[completers::directory, completers::language]),
+    },
+    TypableCommand {
+        name: "profile",
+        aliases: &[],
+        doc: "List the prompt profiles, the active one marked with *, or make the named profile the system prompt.",
+        fun: profile,
+        signature: CommandSignature::none(),
+    },
+    TypableCommand {
+        name: "commit-msg",
+        aliases: &[],
+        doc: "Propose a commit message for the staged changes in a buffer. Run it again from that buffer to commit with the message.",
+        fun: commit_msg,
+        signature: CommandSignature::none(),
+    },
 ];
 
 pub static TYPABLE_COMMAND_MAP: Lazy<HashMap<&'static str, &'static TypableCommand>> =
@@ -217,6 +217,13 @@ impl Compositor {
       .and_then(|component| component.as_any_mut().downcast_mut())
   }
 
+  /// the number of layers in front of the `T` layer, none if there is no `T` layer
+  pub fn layers_in_front<T: 'static>(&self) -> Option<usize> {
+    let type_name = std::any::type_name::<T>();
+    let idx = self.layers.iter().position(|component| component.type_name() == type_name)?;
+    Some(self.layers.len() - idx - 1)
+  }
+
   pub fn find_id<T: 'static>(&mut self, id: &'static str) -> Option<&mut T> {
     self
       .layers
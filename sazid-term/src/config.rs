@@ -2,13 +2,19 @@ use crate::keymap;
 use crate::keymap::{merge_keys, KeyTrie};
 use helix_loader::merge_toml_values;
 use helix_view::document::Mode;
+use helix_view::editor::ConfigEvent;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs;
 use std::io::Error as IOError;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::UnboundedSender;
 use toml::de::Error as TomlError;
 
+// how often the config files are checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Config {
   pub theme: Option<String>,
@@ -30,7 +36,7 @@ impl Default for Config {
   fn default() -> Config {
     let mut config = Config {
       theme: None,
-      keys: keymap::default(),
+      keys: keymap::minimal(),
       editor: helix_view::editor::Config::default(),
       session: sazid::app::session_config::SessionConfig::default(),
     };
@@ -71,7 +77,7 @@ impl Config {
 
     let res = match (global_config, local_config) {
       (Ok(global), Ok(local)) => {
-        let mut keys = keymap::default();
+        let mut keys = keymap::minimal();
         if let Some(global_keys) = global.keys {
           merge_keys(&mut keys, global_keys)
         }
@@ -106,7 +112,7 @@ impl Config {
         return Err(ConfigLoadError::BadConfig(err))
       },
       (Ok(config), Err(_)) | (Err(_), Ok(config)) => {
-        let mut keys = keymap::default();
+        let mut keys = keymap::minimal();
         if let Some(keymap) = config.keys {
           merge_keys(&mut keys, keymap);
         }
@@ -139,6 +145,38 @@ impl Config {
   }
 }
 
+// the modified times of the files a config refresh reads, none for files that do not exist
+fn config_file_times() -> Vec<Option<SystemTime>> {
+  [
+    helix_loader::config_file(),
+    helix_loader::workspace_config_file(),
+    helix_loader::lang_config_file(),
+  ]
+  .iter()
+  .map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+  .collect()
+}
+
+/// sends a config refresh whenever a config file is written, created or removed, so theme,
+/// keymap and session settings apply without restarting
+pub fn watch_config_files(config_events: UnboundedSender<ConfigEvent>) {
+  tokio::spawn(async move {
+    let mut times = config_file_times();
+    let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+    loop {
+      interval.tick().await;
+      let current = config_file_times();
+      if current == times {
+        continue;
+      }
+      times = current;
+      if config_events.send(ConfigEvent::Refresh).is_err() {
+        break;
+      }
+    }
+  });
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -163,7 +201,7 @@ mod tests {
             A-F12 = "move_next_word_end"
         "#;
 
-    let mut keys = keymap::default();
+    let mut keys = keymap::minimal();
     merge_keys(
       &mut keys,
       hashmap! {
@@ -184,10 +222,48 @@ mod tests {
   fn keys_resolve_to_correct_defaults() {
     // From serde default
     let default_keys = Config::load_test("").keys;
-    assert_eq!(default_keys, keymap::default());
+    assert_eq!(default_keys, keymap::minimal());
 
     // From the Default trait
     let default_keys = Config::default().keys;
-    assert_eq!(default_keys, keymap::default());
+    assert_eq!(default_keys, keymap::minimal());
+  }
+
+  #[test]
+  fn chat_keys_from_config() {
+    use crate::keymap;
+    use helix_core::hashmap;
+    use helix_view::document::Mode;
+
+    let chat_keymaps = r#"
+            [keys.normal]
+            C-y = "yank_code_block"
+            C-g = "regenerate_response"
+            A-z = "toggle_message_fold"
+        "#;
+
+    let mut keys = keymap::minimal();
+    merge_keys(
+      &mut keys,
+      hashmap! {
+          Mode::Normal => keymap!({ "Normal mode"
+              "C-y" => yank_code_block,
+              "C-g" => regenerate_response,
+              "A-z" => toggle_message_fold,
+          }),
+      },
+    );
+    assert_eq!(Config::load_test(chat_keymaps).keys, keys);
+
+    let misspelled = Config::load(
+      Ok("[keys.normal]\nC-y = \"yank_code_blok\"".to_owned()),
+      Err(ConfigLoadError::default()),
+    );
+    match misspelled {
+      Err(ConfigLoadError::BadConfig(err)) => {
+        assert!(err.to_string().contains("did you mean 'yank_code_block'?"), "{}", err)
+      },
+      _ => panic!("a misspelled command should not load"),
+    }
   }
 }
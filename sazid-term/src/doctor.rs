@@ -0,0 +1,284 @@
+use std::io::Write;
+use std::time::Duration;
+
+use async_openai::error::OpenAIError;
+use async_openai::Client;
+use crossterm::style::Stylize;
+use crossterm::tty::IsTty;
+use helix_core::config::{default_lang_config, user_lang_config};
+use sazid::app::credentials;
+use sazid::app::database::vector_store::{open_vector_store, VectorStoreConfig};
+use sazid::app::provider_fixtures::ProviderMode;
+use sazid::app::rate_limit;
+use sazid::app::session_config::SessionConfig;
+
+use crate::commands::open_session_store;
+use crate::config::Config;
+use crate::ui::diagrams::GraphicsProtocol;
+
+// how long the api and the database have to answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+  Ok,
+  /// works, with a feature missing
+  Warn,
+  Fail,
+}
+
+/// the outcome of one check, with what to do about it when it did not pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+  pub name: String,
+  pub status: Status,
+  pub detail: String,
+  pub fix: Option<String>,
+}
+
+impl Check {
+  fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+    Check { name: name.into(), status: Status::Ok, detail: detail.into(), fix: None }
+  }
+
+  fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+    Check { name: name.into(), status: Status::Warn, detail: detail.into(), fix: Some(fix.into()) }
+  }
+
+  fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+    Check { name: name.into(), status: Status::Fail, detail: detail.into(), fix: Some(fix.into()) }
+  }
+}
+
+/// runs every check and prints them to `out`, returns false if one failed
+pub async fn doctor(out: &mut impl Write) -> std::io::Result<bool> {
+  let (mut checks, config) = config_checks();
+  checks.extend(api_checks(&config.session).await);
+  checks.extend(language_server_checks(&config.session));
+  checks.extend(database_checks(&config.session).await);
+  checks.extend(terminal_checks(|name| std::env::var(name).ok()));
+  report(&checks, std::io::stdout().is_tty(), out)?;
+  Ok(checks.iter().all(|check| check.status != Status::Fail))
+}
+
+/// writes the checks to `out`, colored when `color` is set
+pub fn report(checks: &[Check], color: bool, out: &mut impl Write) -> std::io::Result<()> {
+  for check in checks {
+    let icon = match (check.status, color) {
+      (Status::Ok, true) => "✓".green().to_string(),
+      (Status::Warn, true) => "!".yellow().to_string(),
+      (Status::Fail, true) => "✘".red().to_string(),
+      (Status::Ok, false) => "✓".to_string(),
+      (Status::Warn, false) => "!".to_string(),
+      (Status::Fail, false) => "✘".to_string(),
+    };
+    writeln!(out, "{} {}: {}", icon, check.name, check.detail)?;
+    if let Some(fix) = &check.fix {
+      writeln!(out, "    fix: {}", fix)?;
+    }
+  }
+  let failed = checks.iter().filter(|check| check.status == Status::Fail).count();
+  let warned = checks.iter().filter(|check| check.status == Status::Warn).count();
+  writeln!(out, "\n{} checks, {} failed, {} with warnings", checks.len(), failed, warned)
+}
+
+// the config file and the language config. the checks after these use the defaults when the
+// config file does not load
+fn config_checks() -> (Vec<Check>, Config) {
+  let config_file = helix_loader::config_file();
+  let mut checks = vec![];
+  let config = match Config::load_default() {
+    Ok(config) => {
+      checks.push(Check::ok("config", config_file.display().to_string()));
+      config
+    },
+    Err(crate::config::ConfigLoadError::Error(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+      checks.push(Check::ok("config", "no config file, using the defaults"));
+      Config::default()
+    },
+    Err(e) => {
+      let fix = format!("correct {}, the checks below use the defaults", config_file.display());
+      checks.push(Check::fail("config", e.to_string(), fix));
+      Config::default()
+    },
+  };
+  checks.push(match user_lang_config() {
+    Ok(_) => Check::ok("language config", "loaded"),
+    Err(e) => Check::fail(
+      "language config",
+      e.to_string(),
+      format!("correct {}", helix_loader::config_dir().join("languages.toml").display()),
+    ),
+  });
+  (checks, config)
+}
+
+// an api key for each provider, and whether the api answers with it
+async fn api_checks(config: &SessionConfig) -> Vec<Check> {
+  let mut checks = vec![];
+  for provider in credentials::PROVIDERS {
+    let name = format!("{} api", provider);
+    if config.provider != ProviderMode::Live {
+      checks.push(Check::ok(name, "answered from fixtures or a mock, no api key is needed"));
+      continue;
+    }
+    let client_config = config.openai.client_config();
+    if !client_config.is_configured() {
+      let fix = format!(
+        "set {}, or run `sazid auth login {}` to store a key in the os keyring",
+        credentials::provider_env_var(provider).unwrap_or_default(),
+        provider
+      );
+      checks.push(Check::fail(name, "no api key is configured", fix));
+      continue;
+    }
+    let api_base = async_openai::config::Config::api_base(&client_config).to_string();
+    let client = Client::with_config(client_config).with_backoff(rate_limit::without_retries());
+    checks.push(match tokio::time::timeout(CONNECT_TIMEOUT, client.models().list()).await {
+      Ok(Ok(models)) => {
+        Check::ok(name, format!("{} answered, {} models", api_base, models.data.len()))
+      },
+      Ok(Err(OpenAIError::ApiError(e))) => Check::fail(
+        name,
+        format!("{} refused the request: {}", api_base, e.message),
+        format!("check the key, `sazid auth login {}` stores a new one", provider),
+      ),
+      Ok(Err(e)) => Check::fail(
+        name,
+        format!("{} could not be reached: {}", api_base, e),
+        "check the network connection and session.openai.api_base",
+      ),
+      Err(_) => Check::fail(
+        name,
+        format!("{} did not answer within {:?}", api_base, CONNECT_TIMEOUT),
+        "check the network connection and session.openai.api_base",
+      ),
+    });
+  }
+  checks
+}
+
+// the language server of each workspace is on the PATH
+fn language_server_checks(config: &SessionConfig) -> Vec<Check> {
+  let lang_config = user_lang_config().unwrap_or_else(|_| default_lang_config());
+  let workspaces = config.all_workspaces();
+  if workspaces.is_empty() {
+    return vec![Check::ok("language servers", "no workspace is configured")];
+  }
+  let mut checks = vec![];
+  for workspace in workspaces {
+    let name = format!("workspace {}", workspace.name());
+    if !workspace.workspace_path.is_dir() {
+      let fix = "correct workspace_path in the config, or pass an existing --workspace";
+      let detail = format!("{} is not a directory", workspace.workspace_path.display());
+      checks.push(Check::fail(name.clone(), detail, fix));
+    }
+    let server = &workspace.language_server;
+    let command = lang_config
+      .language_server
+      .get(server)
+      .map(|server| server.command.clone())
+      .unwrap_or_else(|| server.clone());
+    checks.push(match helix_stdx::env::which(&command) {
+      Ok(path) => Check::ok(name, format!("{} is {}", server, path.display())),
+      Err(_) => Check::fail(
+        name,
+        format!("{} is not on the PATH", command),
+        format!("install {} or set language_server of the workspace to one that is", command),
+      ),
+    });
+  }
+  checks
+}
+
+// the session store and the vector store retrieval reads from
+async fn database_checks(config: &SessionConfig) -> Vec<Check> {
+  let mut checks = vec![match open_session_store() {
+    Ok(_) => Check::ok("session store", "opened"),
+    Err(e) => Check::fail(
+      "session store",
+      e.to_string(),
+      format!("check the permissions of {}", helix_loader::data_dir().display()),
+    ),
+  }];
+  let sqlite_fix = "set session.database_url, or set session.vector_store.kind = \"sqlite\"";
+  let store = match open_vector_store(&config.vector_store, &config.database_url) {
+    Ok(store) => store,
+    Err(e) => {
+      // workspace retrieval is off without a vector store, everything else works
+      checks.push(Check::warn("vector store", e.to_string(), sqlite_fix));
+      return checks;
+    },
+  };
+  let kind = match config.vector_store {
+    VectorStoreConfig::Postgres => "postgres",
+    VectorStoreConfig::Sqlite { .. } => "sqlite",
+  };
+  checks.push(match tokio::time::timeout(CONNECT_TIMEOUT, store.files()).await {
+    Ok(Ok(files)) => Check::ok("vector store", format!("{}, {} files indexed", kind, files.len())),
+    Ok(Err(e)) => Check::fail("vector store", format!("{}: {}", kind, e), sqlite_fix),
+    Err(_) => Check::fail(
+      "vector store",
+      format!("{} did not answer within {:?}", kind, CONNECT_TIMEOUT),
+      sqlite_fix,
+    ),
+  });
+  checks
+}
+
+// truecolor for themes, and a graphics protocol for diagrams. `var` reads the environment
+fn terminal_checks(var: impl Fn(&str) -> Option<String>) -> Vec<Check> {
+  let colorterm = var("COLORTERM").unwrap_or_default();
+  let truecolor = if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+    Check::ok("truecolor", format!("COLORTERM={}", colorterm))
+  } else {
+    Check::warn(
+      "truecolor",
+      "COLORTERM does not announce truecolor, themes are drawn with 256 colors",
+      "set COLORTERM=truecolor if the terminal supports it, or enable editor.true-color",
+    )
+  };
+  let graphics = match GraphicsProtocol::detect() {
+    Some(protocol) => Check::ok("graphics", format!("{:?} images", protocol)),
+    None if var("TMUX").is_some() => Check::warn(
+      "graphics",
+      "tmux does not pass images through, diagrams are shown as source",
+      "run sazid outside of tmux to see diagrams",
+    ),
+    None => Check::warn(
+      "graphics",
+      "the terminal has no known image protocol, diagrams are shown as source",
+      "use a terminal with the kitty or iterm2 image protocol, like kitty, wezterm or iterm2",
+    ),
+  };
+  vec![truecolor, graphics]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_report() {
+    let checks = vec![
+      Check::ok("config", "loaded"),
+      Check::fail("openai api", "no api key is configured", "run `sazid auth login openai`"),
+      Check::warn("graphics", "no image protocol", "use kitty"),
+    ];
+    let mut out = vec![];
+    report(&checks, false, &mut out).unwrap();
+    assert_eq!(
+      String::from_utf8(out).unwrap(),
+      "✓ config: loaded\n✘ openai api: no api key is configured\n    fix: run `sazid auth login \
+       openai`\n! graphics: no image protocol\n    fix: use kitty\n\n3 checks, 1 failed, 1 with \
+       warnings\n"
+    );
+
+    let truecolor = |value: &str| {
+      let value = value.to_string();
+      terminal_checks(move |name| (name == "COLORTERM").then(|| value.clone())).remove(0).status
+    };
+    assert_eq!(truecolor("truecolor"), Status::Ok);
+    assert_eq!(truecolor("8bit"), Status::Warn);
+  }
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use arc_swap::ArcSwap;
 use helix_event::AsyncHook;
+use sazid::app::mentions::MentionScope;
 
 use crate::config::Config;
 use crate::events;
@@ -14,10 +15,10 @@ pub use helix_view::handlers::Handlers;
 mod completion;
 mod signature_help;
 
-pub fn setup(config: Arc<ArcSwap<Config>>) -> Handlers {
+pub fn setup(config: Arc<ArcSwap<Config>>, mentions: MentionScope) -> Handlers {
   events::register();
 
-  let completions = CompletionHandler::new(config).spawn();
+  let completions = CompletionHandler::new(config, mentions).spawn();
   let signature_hints = SignatureHelpHandler::new().spawn();
   let handlers = Handlers { completions, signature_hints };
   completion::register_hooks(&handlers);
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -6,15 +7,20 @@ use arc_swap::ArcSwap;
 use futures_util::stream::FuturesUnordered;
 use helix_core::chars::char_is_word;
 use helix_core::syntax::LanguageServerFeature;
+use helix_core::RopeSlice;
 use helix_event::{
   cancelable_future, cancelation, register_hook, send_blocking, CancelRx, CancelTx,
 };
-use helix_lsp::lsp;
 use helix_lsp::util::pos_to_lsp_pos;
+use helix_lsp::{lsp, OffsetEncoding};
 use helix_stdx::rope::RopeSliceExt;
 use helix_view::document::{Mode, SavePoint};
 use helix_view::handlers::lsp::CompletionEvent;
 use helix_view::{DocumentId, Editor, ViewId};
+use sazid::app::mentions::{
+  complete_mention, completed_segment, partial_mention, IndexedSymbol, MentionCompletionKind,
+  MentionScope,
+};
 use tokio::sync::mpsc::Sender;
 use tokio::time::Instant;
 use tokio_stream::StreamExt;
@@ -58,11 +64,13 @@ pub(super) struct CompletionHandler {
   /// request (by dropping the handle)
   request: Option<CancelTx>,
   config: Arc<ArcSwap<Config>>,
+  /// the workspace and symbols @-mentions are completed with
+  mentions: MentionScope,
 }
 
 impl CompletionHandler {
-  pub fn new(config: Arc<ArcSwap<Config>>) -> CompletionHandler {
-    Self { config, request: None, trigger: None }
+  pub fn new(config: Arc<ArcSwap<Config>>, mentions: MentionScope) -> CompletionHandler {
+    Self { config, mentions, request: None, trigger: None }
   }
 }
 
@@ -125,8 +133,9 @@ impl helix_event::AsyncHook for CompletionHandler {
     let trigger = self.trigger.take().expect("debounce always has a trigger");
     let (tx, rx) = cancelation();
     self.request = Some(tx);
+    let mentions = self.mentions.clone();
     dispatch_blocking(move |editor, compositor| {
-      request_completion(trigger, rx, editor, compositor)
+      request_completion(trigger, rx, &mentions, editor, compositor)
     });
   }
 }
@@ -134,6 +143,7 @@ impl helix_event::AsyncHook for CompletionHandler {
 fn request_completion(
   mut trigger: Trigger,
   cancel: CancelRx,
+  mentions: &MentionScope,
   editor: &mut Editor,
   compositor: &mut Compositor,
 ) {
@@ -165,6 +175,19 @@ fn request_completion(
   trigger.pos = cursor;
   let trigger_text = text.slice(..cursor);
 
+  // @-mentions are completed from the session's workspace and the symbol index, not by the
+  // language servers of the document
+  let mention_items = typed_mention(text.slice(..), cursor).map(|partial| {
+    let start = cursor - completed_segment(&partial).chars().count();
+    let range = lsp::Range::new(
+      pos_to_lsp_pos(text, start, OffsetEncoding::Utf8),
+      pos_to_lsp_pos(text, cursor, OffsetEncoding::Utf8),
+    );
+    let root = mentions.workspace_root.load_full();
+    let symbols = mentions.symbols.load_full();
+    tokio::task::spawn_blocking(move || mention_completion_items(&partial, &root, &symbols, range))
+  });
+
   let mut seen_language_servers = HashSet::new();
   let mut futures: FuturesUnordered<_> = doc
     .language_servers_with_feature(LanguageServerFeature::Completion)
@@ -215,7 +238,11 @@ fn request_completion(
           None => Vec::new(),
         }
         .into_iter()
-        .map(|item| CompletionItem { item, language_server_id, resolved: false })
+        .map(|item| CompletionItem {
+          item,
+          language_server_id: Some(language_server_id),
+          resolved: false,
+        })
         .collect();
         anyhow::Ok(items)
       }
@@ -224,6 +251,12 @@ fn request_completion(
 
   let future = async move {
     let mut items = Vec::new();
+    if let Some(mention_items) = mention_items {
+      match mention_items.await {
+        Ok(mut mention_items) => items.append(&mut mention_items),
+        Err(err) => log::debug!("mention completion failed: {err:?}"),
+      }
+    }
     while let Some(lsp_items) = futures.next().await {
       match lsp_items {
         Ok(mut lsp_items) => items.append(&mut lsp_items),
@@ -251,6 +284,40 @@ fn request_completion(
   });
 }
 
+// the @-mention being typed before the cursor, without its `@`
+fn typed_mention(text: RopeSlice, cursor: usize) -> Option<String> {
+  let word =
+    text.chars_at(cursor).reversed().take_while(|c| !c.is_whitespace()).collect::<Vec<_>>();
+  partial_mention(&word.into_iter().rev().collect::<String>()).map(String::from)
+}
+
+// completions replacing the part of the mention in `range` with directory entries or symbols
+fn mention_completion_items(
+  partial: &str,
+  root: &Path,
+  symbols: &[IndexedSymbol],
+  range: lsp::Range,
+) -> Vec<CompletionItem> {
+  complete_mention(partial, root, symbols)
+    .into_iter()
+    .map(|completion| {
+      let kind = match completion.kind {
+        MentionCompletionKind::File => lsp::CompletionItemKind::FILE,
+        MentionCompletionKind::Directory => lsp::CompletionItemKind::FOLDER,
+        MentionCompletionKind::Symbol => lsp::CompletionItemKind::REFERENCE,
+      };
+      let item = lsp::CompletionItem {
+        label: completion.text.clone(),
+        kind: Some(kind),
+        detail: completion.detail,
+        text_edit: Some(lsp::CompletionTextEdit::Edit(lsp::TextEdit::new(range, completion.text))),
+        ..Default::default()
+      };
+      CompletionItem { item, language_server_id: None, resolved: true }
+    })
+    .collect()
+}
+
 fn show_completion(
   editor: &mut Editor,
   compositor: &mut Compositor,
@@ -298,6 +365,12 @@ pub fn trigger_auto_completion(
   let cursor = doc.selection(view.id).primary().cursor(text);
   text = doc.text().slice(..cursor);
 
+  // mentions are completed from their @ on, and again once a directory is entered with /
+  if typed_mention(text, cursor).is_some() {
+    send_blocking(tx, CompletionEvent::TriggerChar { cursor, doc: doc.id(), view: view.id });
+    return;
+  }
+
   let is_trigger_char =
     doc.language_servers_with_feature(LanguageServerFeature::Completion).any(|ls| {
       matches!(&ls.capabilities().completion_provider, Some(lsp::CompletionOptions {
@@ -32,6 +32,8 @@ pub fn dispatch_blocking(job: impl FnOnce(&mut Editor, &mut Compositor) + Send +
 pub enum Callback {
   EditorCompositor(EditorCompositorCallback),
   Editor(EditorCallback),
+  /// runs with the terminal handed back to the shell, for programs that draw to it like $EDITOR
+  Terminal(EditorCompositorCallback),
 }
 
 pub type JobFuture = BoxFuture<'static, anyhow::Result<Option<Callback>>>;
@@ -94,6 +96,8 @@ impl Jobs {
       Ok(Some(call)) => match call {
         Callback::EditorCompositor(call) => call(editor, compositor),
         Callback::Editor(call) => call(editor),
+        // only the application can hand over the terminal, see Application::handle_job_callback
+        Callback::Terminal(_) => log::warn!("skipping a callback that needs the terminal"),
       },
       Err(e) => {
         editor.set_error(format!("Async job failed: {}", e));
@@ -18,7 +18,7 @@ use std::{
 
 pub use default::default;
 use macros::key;
-pub use minimal::minimal_keymap;
+pub use minimal::{minimal, minimal_keymap};
 
 #[derive(Debug, Clone, Default)]
 pub struct KeyTrieNode {
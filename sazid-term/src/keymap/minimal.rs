@@ -12,12 +12,15 @@ pub fn minimal_keymap() -> Keymaps {
 
 pub fn minimal() -> HashMap<Mode, KeyTrie> {
   let normal = keymap!({ "Normal mode"
-      "C-ret" => submit_input_to_session,
+      "C-ret" | "A-ret" => submit_input_to_session,
+      "C-e" => edit_input_in_external_editor,
       "C-c" => quit,
 
       "h" | "left" => move_char_left,
-      "j" | "down" => move_visual_line_down,
-      "k" | "up" => move_visual_line_up,
+      "j" => move_visual_line_down,
+      "k" => move_visual_line_up,
+      "down" => input_history_next,
+      "up" => input_history_previous,
       "l" | "right" => move_char_right,
 
       "t" => find_till_char,
@@ -44,6 +47,7 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
       "E" => move_next_long_word_end,
 
       "v" => select_mode,
+      "V" => select_line_mode,
       "G" => goto_line,
       "g" => { "Goto"
           "g" => goto_file_start,
@@ -57,7 +61,10 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
           "y" => goto_type_definition,
           "r" => goto_reference,
           "i" => goto_implementation,
-          "t" => goto_window_top,
+          "t" => goto_next_session_tab,
+          "T" => goto_previous_session_tab,
+          "N" => new_session,
+          "X" => close_session_tab,
           "c" => goto_window_center,
           "b" => goto_window_bottom,
           "a" => goto_last_accessed_file,
@@ -124,6 +131,7 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
           "c" => goto_prev_comment,
           "T" => goto_prev_test,
           "p" => goto_prev_paragraph,
+          "m" => goto_prev_message,
           "space" => add_newline_above,
       },
       "]" => { "Right bracket"
@@ -137,6 +145,7 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
           "c" => goto_next_comment,
           "T" => goto_next_test,
           "p" => goto_next_paragraph,
+          "m" => goto_next_message,
           "space" => add_newline_below,
       },
 
@@ -230,7 +239,17 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
           "a" => add_session_workspace_folder,
           "r" => remove_session_workspace_folder,
           "p" => modify_system_prompt,
+          "g" => regenerate_response,
+          "m" => message_actions,
+          "c" => yank_code_block,
+          "d" => delete_message,
+          "u" => undo_delete_message,
+          "e" => { "File edit"
+              "a" => accept_file_edit,
+              "r" => revert_file_edit,
+          },
           "t" => toggle_layer_order,
+          "v" => toggle_split_view,
           "q" => quit,
 
       //     "F" => file_picker_in_current_directory,
@@ -302,6 +321,7 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
       },
       "z" => { "View"
           "z" | "c" => align_view_center,
+          "a" => toggle_message_fold,
           "t" => align_view_top,
           "b" => align_view_bottom,
           "m" => align_view_middle,
@@ -380,13 +400,15 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
       "esc" => exit_select_mode,
 
       "v" => normal_mode,
+      "V" => select_line_mode,
       "g" => { "Goto"
           "k" => extend_line_up,
           "j" => extend_line_down,
       },
   }));
   let insert = keymap!({ "Insert mode"
-      "C-ret" => submit_input_to_session,
+      "C-ret" | "A-ret" => submit_input_to_session,
+      "C-e" => edit_input_in_external_editor,
       "C-c" => quit,
 
       "esc" => normal_mode,
@@ -405,8 +427,8 @@ pub fn minimal() -> HashMap<Mode, KeyTrie> {
       "tab" => smart_tab,
       "S-tab" => insert_tab,
 
-      "up" => move_visual_line_up,
-      "down" => move_visual_line_down,
+      "up" | "C-p" => input_history_previous,
+      "down" | "C-n" => input_history_next,
       "left" => move_char_left,
       "right" => move_char_right,
       "pageup" => page_up,
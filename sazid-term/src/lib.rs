@@ -3,9 +3,11 @@ extern crate helix_view;
 
 pub mod application;
 pub mod args;
+pub mod clipboard;
 pub mod commands;
 pub mod compositor;
 pub mod config;
+pub mod doctor;
 pub mod events;
 pub mod health;
 pub mod job;
@@ -14,12 +16,10 @@ pub mod movement;
 pub mod ui;
 pub mod widgets;
 
-use std::path::Path;
-
 use futures_util::Future;
 mod handlers;
 
-use ignore::DirEntry;
+use sazid::app::workspace_walk::filter_picker_entry;
 use url::Url;
 
 #[cfg(windows)]
@@ -46,24 +46,6 @@ fn true_color() -> bool {
   }
 }
 
-/// Function used for filtering dir entries in the various file pickers.
-fn filter_picker_entry(entry: &DirEntry, root: &Path, dedup_symlinks: bool) -> bool {
-  // We always want to ignore the .git directory, otherwise if
-  // `ignore` is turned off, we end up with a lot of noise
-  // in our picker.
-  if entry.file_name() == ".git" {
-    return false;
-  }
-
-  // We also ignore symlinks that point inside the current directory
-  // if `dedup_links` is enabled.
-  if dedup_symlinks && entry.path_is_symlink() {
-    return entry.path().canonicalize().ok().map_or(false, |path| !path.starts_with(root));
-  }
-
-  true
-}
-
 /// Opens URL in external program.
 fn open_external_url_callback(
   url: Url,
@@ -1,11 +1,15 @@
+use std::io::{IsTerminal, Read};
 use std::path::Path;
 
 use anyhow::{Context, Error, Result};
+use clap::Parser;
 use crossterm::event::EventStream;
 use helix_loader::VERSION_AND_GIT_HASH;
 use sazid::app::errors::SazidError;
+use sazid::cli::{HeadlessCli, HeadlessCommand, HEADLESS_COMMANDS};
 use sazid_term::application::Application;
 use sazid_term::args::Args;
+use sazid_term::commands::open_session_store;
 
 use sazid_term::config::{Config, ConfigLoadError};
 use tracing_error::ErrorLayer;
@@ -41,8 +45,14 @@ pub fn setup_tracing_logging(verbosity: u64, log_path: &Path) -> Result<()> {
     .with_target(false)
    .with_filter(tracing_subscriber::filter::EnvFilter::from_default_env());
 
+  #[cfg(feature = "otel")]
+  let otlp_subscriber = otlp_layer()?;
+  #[cfg(not(feature = "otel"))]
+  let otlp_subscriber = None::<tracing_subscriber::layer::Identity>;
+
   tracing_subscriber::registry()
     .with(file_subscriber)
+    .with(otlp_subscriber)
     .with(console_subscriber::ConsoleLayer::builder().with_default_env().spawn())
     .with(ErrorLayer::default())
     .init();
@@ -52,6 +62,31 @@ pub fn setup_tracing_logging(verbosity: u64, log_path: &Path) -> Result<()> {
   Ok(())
 }
 
+/// exports the spans of the chat requests, tool calls and language server requests to the
+/// collector at OTEL_EXPORTER_OTLP_ENDPOINT, none if it is not set
+#[cfg(feature = "otel")]
+fn otlp_layer<S>() -> Result<Option<impl tracing_subscriber::Layer<S>>>
+where
+  S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+  use opentelemetry::KeyValue;
+  use opentelemetry_sdk::{runtime, trace, Resource};
+
+  if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+    return Ok(None);
+  }
+  let resource = Resource::new(vec![KeyValue::new("service.name", "sazid")]);
+  let tracer = opentelemetry_otlp::new_pipeline()
+    .tracing()
+    .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+    .with_trace_config(trace::config().with_resource(resource))
+    .install_batch(runtime::Tokio)?;
+  let targets = tracing_subscriber::filter::Targets::new()
+    .with_target("sazid", tracing::Level::INFO)
+    .with_target("sazid_term", tracing::Level::INFO);
+  Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(targets)))
+}
+
 fn setup_logging(verbosity: u64) -> Result<()> {
   let mut base_config = fern::Dispatch::new();
 
@@ -80,6 +115,64 @@ fn setup_logging(verbosity: u64) -> Result<()> {
   Ok(())
 }
 
+/// runs a command without the terminal interface. errors are printed and exit with 1
+async fn run_headless(cli: HeadlessCli) -> Result<i32> {
+  helix_loader::initialize_config_file(None);
+  // reports a bad config instead of stopping at it
+  if let HeadlessCommand::Doctor = cli.command {
+    let passed = sazid_term::doctor::doctor(&mut std::io::stdout()).await?;
+    return Ok(if passed { 0 } else { 1 });
+  }
+  let config = match Config::load_default() {
+    Ok(config) => config,
+    Err(ConfigLoadError::Error(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+      Config::default()
+    },
+    Err(err) => {
+      eprintln!("Bad config: {}", err);
+      return Ok(1);
+    },
+  };
+  let result = match cli.command {
+    HeadlessCommand::Ask(args) => {
+      // text piped in, like `cat error.log | sazid ask "explain this"`, is sent with the prompt
+      let mut stdin = std::io::stdin();
+      let piped = if stdin.is_terminal() {
+        None
+      } else {
+        let mut piped = String::new();
+        stdin.read_to_string(&mut piped)?;
+        Some(piped)
+      };
+      sazid::cli::ask(args, config.session, piped, &mut std::io::stdout()).await
+    },
+    HeadlessCommand::Sessions(command) => open_session_store()
+      .and_then(|mut store| sazid::cli::sessions(command, &mut store, &mut std::io::stdout())),
+    HeadlessCommand::Serve(args) => {
+      let socket = args.socket.unwrap_or_else(|| helix_loader::data_dir().join("sazid.sock"));
+      match open_session_store() {
+        Ok(store) => sazid::server::serve(&socket, config.session, store).await,
+        Err(e) => Err(e),
+      }
+    },
+    HeadlessCommand::Acp => match open_session_store() {
+      Ok(store) => {
+        let stdin = tokio::io::stdin();
+        sazid::acp::serve_acp(config.session, store, stdin, &mut tokio::io::stdout()).await
+      },
+      Err(e) => Err(e),
+    },
+    HeadlessCommand::Doctor => unreachable!("doctor runs before the config is loaded"),
+  };
+  match result {
+    Ok(()) => Ok(0),
+    Err(err) => {
+      eprintln!("{}", err);
+      Ok(1)
+    },
+  }
+}
+
 fn main() -> Result<()> {
   let exit_code = main_impl()?;
   std::process::exit(exit_code);
@@ -114,6 +207,21 @@ FLAGS:
     --vsplit                       Splits all given files vertically into different windows
     --hsplit                       Splits all given files horizontally into different windows
     -w, --working-dir <path>       Specify an initial working directory
+    --org-id <id>                  OpenAI organization id (default: $OPENAI_ORG_ID)
+    --project-id <id>              OpenAI project id (default: $OPENAI_PROJECT_ID)
+    --openai-profile <name>        Use the named profile from [session.openai.profiles]
+    --resume                       Opens the most recently updated session
+    --session <id>                 Opens the saved session with the given id
+    auth {{login|logout}} [provider]  Stores or removes a provider's api key in the os keyring
+                                   (default provider: openai)
+    ask <prompt>                   Sends one prompt and prints the answer, with any text piped
+                                   to stdin, see `ask --help`
+    sessions {{list|show|export|delete}}  Lists, prints, exports or deletes saved sessions,
+                                   see `sessions --help`
+    serve [--socket <path>]        Answers json-rpc requests from editor plugins on a unix socket
+    acp                            Runs as an agent client protocol agent on stdin and stdout
+    doctor                         Checks the config, api keys, language servers, databases and
+                                   terminal, and suggests fixes
     +N                             Open the first given file at line number N
 ",
     env!("CARGO_PKG_NAME"),
@@ -123,6 +231,10 @@ FLAGS:
     helix_loader::default_log_file().display(),
   );
 
+  if std::env::args().nth(1).is_some_and(|arg| HEADLESS_COMMANDS.contains(&arg.as_str())) {
+    return run_headless(HeadlessCli::parse()).await;
+  }
+
   let mut args = Args::parse_args().context("could not parse arguments")?;
 
   helix_loader::initialize_config_file(args.config_file.clone());
@@ -151,6 +263,19 @@ FLAGS:
     std::process::exit(0);
   }
 
+  if let Some((action, provider)) = &args.auth {
+    let result = match action.as_str() {
+      "login" => sazid::app::credentials::login(provider),
+      _ => sazid::app::credentials::logout(provider),
+    };
+    if let Err(err) = result {
+      eprintln!("{}", err);
+      return Ok(1);
+    }
+    println!("{} api key {}", provider, if action == "login" { "stored" } else { "removed" });
+    return Ok(0);
+  }
+
   if args.fetch_grammars {
     helix_loader::grammar::fetch_grammars()?;
     return Ok(0);
@@ -209,5 +334,9 @@ FLAGS:
 
   let exit_code = app.run(&mut EventStream::new()).await?;
 
+  // the spans still batched are sent before exiting
+  #[cfg(feature = "otel")]
+  opentelemetry::global::shutdown_tracer_provider();
+
   Ok(exit_code)
 }
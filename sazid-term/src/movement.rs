@@ -1,3 +1,4 @@
+use helix_core::textobject::TextObject;
 use helix_core::{
   doc_formatter::TextFormat,
   movement::{Direction, Movement},
@@ -5,6 +6,7 @@ use helix_core::{
   Position, Range, RopeSlice,
 };
 use helix_view::graphics::Rect;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
 
 pub fn translate_pos_to_char_index(
   text: &RopeSlice<'_>,
@@ -344,3 +346,204 @@ pub fn session_move_vertically(
   range.put_cursor(all_messages_text, new_pos, behaviour == Movement::Extend)
       */
 }
+
+/// selects the whole lines spanned by the range
+pub fn session_extend_to_line_bounds(text: RopeSlice, range: Range) -> Range {
+  let (start_line, end_line) = range.line_range(text);
+  let start = text.line_to_char(start_line);
+  let end = text.line_to_char((end_line + 1).min(text.len_lines()));
+  Range::new(start, end).with_direction(range.direction())
+}
+
+/// the start of the message `count` messages away from the one containing `cursor`, clamped
+/// to the first and last message. moving backward from inside a message first goes to its own
+/// start, like paragraph motions do
+pub fn message_start(
+  messages: &[std::ops::Range<usize>],
+  cursor: usize,
+  dir: Direction,
+  count: usize,
+) -> Option<usize> {
+  let last = messages.len().checked_sub(1)?;
+  let current = messages.iter().rposition(|m| m.start <= cursor).unwrap_or(0);
+  let idx = match dir {
+    Direction::Forward => (current + count).min(last),
+    Direction::Backward if messages[current].start < cursor => (current + 1).saturating_sub(count),
+    Direction::Backward => current.saturating_sub(count),
+  };
+  Some(messages[idx].start)
+}
+
+/// `messages` are the char ranges of each message's plain text in the transcript. around
+/// includes the header line and the separator after the message, inside is only the content
+pub fn textobject_message(
+  text: RopeSlice,
+  range: Range,
+  objtype: TextObject,
+  messages: &[std::ops::Range<usize>],
+) -> Range {
+  let cursor = range.cursor(text);
+  let Some(idx) = messages.iter().rposition(|m| m.start <= cursor) else {
+    return range;
+  };
+  let message = &messages[idx];
+  let (start, end) = match objtype {
+    TextObject::Around => {
+      (message.start, messages.get(idx + 1).map_or(text.len_chars(), |next| next.start))
+    },
+    _ => {
+      let header_line = text.char_to_line(message.start);
+      let content_start = text.line_to_char((header_line + 1).min(text.len_lines()));
+      (content_start.min(message.end), message.end)
+    },
+  };
+  Range::new(start, end.min(text.len_chars())).with_direction(range.direction())
+}
+
+/// `code_blocks` are the char ranges of rendered code blocks in the transcript. inside is the
+/// lines of the block, around also takes the blank line the renderer adds after it
+pub fn textobject_code_block(
+  text: RopeSlice,
+  range: Range,
+  objtype: TextObject,
+  code_blocks: &[std::ops::Range<usize>],
+) -> Range {
+  let cursor = range.cursor(text);
+  let Some(block) = code_blocks.iter().find(|b| b.start <= cursor && cursor < b.end) else {
+    return range;
+  };
+  let start = text.line_to_char(text.char_to_line(block.start));
+  let mut end_line = text.char_to_line(block.end.saturating_sub(1)) + 1;
+  if objtype == TextObject::Around
+    && text.get_line(end_line).map_or(false, |line| line.chars().all(char::is_whitespace))
+  {
+    end_line += 1;
+  }
+  let end = text.line_to_char(end_line.min(text.len_lines()));
+  Range::new(start, end).with_direction(range.direction())
+}
+
+/// char ranges of the code blocks of markdown `content` within `plain_text`, its rendered and
+/// wrapped form
+pub fn code_block_char_ranges(plain_text: RopeSlice, content: &str) -> Vec<std::ops::Range<usize>> {
+  code_blocks(plain_text, content).into_iter().map(|block| block.range).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+  /// char range of the rendered block
+  pub range: std::ops::Range<usize>,
+  /// the info string of a fenced block, empty for indented blocks
+  pub language: String,
+  /// the source of the block, without its fences
+  pub code: String,
+}
+
+/// the code blocks of markdown `content`, located within `plain_text`. fences are not rendered
+/// and wrapping adds line breaks, so blocks are located by matching their non-whitespace
+/// characters
+pub fn code_blocks(plain_text: RopeSlice, content: &str) -> Vec<CodeBlock> {
+  let mut code_blocks: Vec<(String, String)> = Vec::new();
+  let mut in_code_block = false;
+  for event in Parser::new_ext(content, Options::all()) {
+    match event {
+      Event::Start(Tag::CodeBlock(kind)) => {
+        in_code_block = true;
+        let language = match kind {
+          CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+          CodeBlockKind::Indented => String::new(),
+        };
+        code_blocks.push((language, String::new()));
+      },
+      Event::End(TagEnd::CodeBlock) => in_code_block = false,
+      Event::Text(text) if in_code_block => code_blocks.last_mut().unwrap().1.push_str(&text),
+      _ => {},
+    }
+  }
+
+  let significant = plain_text
+    .chars()
+    .enumerate()
+    .filter(|(_, c)| !c.is_whitespace())
+    .map(|(idx, c)| (c, idx))
+    .collect::<Vec<(char, usize)>>();
+
+  let mut search_from = 0;
+  let mut blocks = Vec::new();
+  for (language, code) in code_blocks {
+    let needle = code.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    if needle.is_empty() || significant.len() < needle.len() {
+      continue;
+    }
+    let found = (search_from..=significant.len() - needle.len())
+      .find(|&i| significant[i..i + needle.len()].iter().map(|(c, _)| c).eq(needle.iter()));
+    if let Some(i) = found {
+      let end = i + needle.len();
+      blocks.push(CodeBlock {
+        range: significant[i].1..significant[end - 1].1 + 1,
+        language,
+        code,
+      });
+      search_from = end;
+    }
+  }
+  blocks
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use helix_core::Rope;
+
+  #[test]
+  fn test_code_block_char_ranges_ignores_wrapping() {
+    let content = "look:\n\n```rust\nfn main() { println!(\"hi\"); }\n```\n\nand `inline`";
+    let plain_text =
+      Rope::from("Assistant\nlook:\n\nfn main() { \nprintln!(\"hi\"); }\n\nand inline");
+    let ranges = code_block_char_ranges(plain_text.slice(..), content);
+    assert_eq!(ranges.len(), 1);
+    let code = plain_text.slice(ranges[0].clone()).to_string();
+    assert_eq!(code, "fn main() { \nprintln!(\"hi\"); }");
+    let blocks = code_blocks(plain_text.slice(..), content);
+    assert_eq!(blocks[0].language, "rust");
+    assert_eq!(blocks[0].code, "fn main() { println!(\"hi\"); }\n");
+  }
+
+  #[test]
+  fn test_textobject_message() {
+    let text = Rope::from("User\nhello\n\nAssistant\nhi there\n\n");
+    let text = text.slice(..);
+    let messages = vec![0..10, 12..30];
+    let cursor = Range::point(14);
+
+    let inside = textobject_message(text, cursor, TextObject::Inside, &messages);
+    assert_eq!(text.slice(inside.from()..inside.to()).to_string(), "hi there");
+    let around = textobject_message(text, Range::point(3), TextObject::Around, &messages);
+    assert_eq!(text.slice(around.from()..around.to()).to_string(), "User\nhello\n\n");
+  }
+
+  #[test]
+  fn test_message_start() {
+    let messages = vec![0..10, 12..30, 32..40];
+    assert_eq!(message_start(&messages, 3, Direction::Forward, 1), Some(12));
+    assert_eq!(message_start(&messages, 3, Direction::Forward, 5), Some(32));
+    assert_eq!(message_start(&messages, 14, Direction::Backward, 1), Some(12));
+    assert_eq!(message_start(&messages, 12, Direction::Backward, 1), Some(0));
+    assert_eq!(message_start(&messages, 35, Direction::Backward, 2), Some(12));
+    assert_eq!(message_start(&[], 0, Direction::Forward, 1), None);
+  }
+
+  #[test]
+  fn test_textobject_code_block() {
+    let text = Rope::from("Assistant\nlook:\n\nlet a = 1;\nlet b = 2;\n\ndone");
+    let text = text.slice(..);
+    let code_blocks = vec![17..38];
+
+    let inside = textobject_code_block(text, Range::point(30), TextObject::Inside, &code_blocks);
+    assert_eq!(text.slice(inside.from()..inside.to()).to_string(), "let a = 1;\nlet b = 2;\n");
+    let around = textobject_code_block(text, Range::point(30), TextObject::Around, &code_blocks);
+    assert_eq!(text.slice(around.from()..around.to()).to_string(), "let a = 1;\nlet b = 2;\n\n");
+    let outside = textobject_code_block(text, Range::point(3), TextObject::Inside, &code_blocks);
+    assert_eq!(outside, Range::point(3));
+  }
+}
@@ -0,0 +1,139 @@
+use helix_view::{
+  graphics::{Color, Modifier, Style},
+  Theme,
+};
+
+/// who a message in a session is from, each styled with its own `ui.chat.*` theme scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatRole {
+  System,
+  User,
+  Assistant,
+  Tool,
+  Function,
+  Error,
+}
+
+impl ChatRole {
+  /// the lowercase name of the role, as shown in fold summaries
+  pub fn name(self) -> &'static str {
+    match self {
+      ChatRole::System => "system",
+      ChatRole::User => "user",
+      ChatRole::Assistant => "assistant",
+      ChatRole::Tool => "tool",
+      ChatRole::Function => "function",
+      ChatRole::Error => "error",
+    }
+  }
+
+  /// the header shown above the role's messages
+  pub fn header(self) -> &'static str {
+    match self {
+      ChatRole::System => "System",
+      ChatRole::User => "User",
+      ChatRole::Assistant => "Assistant",
+      ChatRole::Tool => "Tool",
+      ChatRole::Function => "Function",
+      ChatRole::Error => "ERROR",
+    }
+  }
+
+  /// the icon shown in the gutter of the role's messages
+  pub fn icon(self) -> &'static str {
+    match self {
+      ChatRole::System => "◆",
+      ChatRole::User => "▶",
+      ChatRole::Assistant => "◀",
+      ChatRole::Tool | ChatRole::Function => "■",
+      ChatRole::Error => "✗",
+    }
+  }
+
+  /// the theme scope of the role. functions are the tool calls of older models and share the
+  /// scope of tools
+  pub fn scope(self) -> &'static str {
+    match self {
+      ChatRole::System => "ui.chat.system",
+      ChatRole::User => "ui.chat.user",
+      ChatRole::Assistant => "ui.chat.assistant",
+      ChatRole::Tool | ChatRole::Function => "ui.chat.tool",
+      ChatRole::Error => "ui.chat.error",
+    }
+  }
+
+  // the bundled themes have no chat scopes, the roles take their colour from a syntax scope
+  // every one of them sets instead, so each theme gets its own set of role colours
+  fn fallback_scope(self) -> &'static str {
+    match self {
+      ChatRole::System => "keyword",
+      ChatRole::User => "string",
+      ChatRole::Assistant => "function",
+      ChatRole::Tool => "type",
+      ChatRole::Function => "constant",
+      ChatRole::Error => "error",
+    }
+  }
+
+  // the colour used when there is no theme or it sets neither scope
+  fn default_color(self) -> Color {
+    match self {
+      ChatRole::System => Color::Magenta,
+      ChatRole::User => Color::Green,
+      ChatRole::Assistant => Color::Blue,
+      ChatRole::Tool => Color::Yellow,
+      ChatRole::Function => Color::LightYellow,
+      ChatRole::Error => Color::Red,
+    }
+  }
+
+  /// the style of the role's message gutter and border
+  pub fn style(self, theme: Option<&Theme>) -> Style {
+    theme
+      .and_then(|theme| {
+        theme.try_get(self.scope()).or_else(|| {
+          let fg = theme.try_get(self.fallback_scope())?.fg?;
+          Some(Style::default().fg(fg))
+        })
+      })
+      .unwrap_or_else(|| Style::default().fg(self.default_color()))
+  }
+
+  /// the style of the role's message header, its style in bold
+  pub fn header_style(self, theme: Option<&Theme>) -> Style {
+    self.style(theme).add_modifier(Modifier::BOLD)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_chat_role_styles() {
+    let theme: Theme = toml::from_str::<toml::Value>(
+      r#"
+      "ui.chat.user" = { fg = "red", modifiers = ["italic"] }
+      string = "cyan"
+      type = { bg = "gray" }
+      error = "light-red"
+      "#,
+    )
+    .unwrap()
+    .into();
+    let theme = Some(&theme);
+
+    assert_eq!(
+      ChatRole::User.style(theme),
+      Style::default().fg(Color::Red).add_modifier(Modifier::ITALIC)
+    );
+    assert_eq!(ChatRole::Error.style(theme), Style::default().fg(Color::LightRed));
+    // a fallback scope without a foreground colour is skipped
+    assert_eq!(ChatRole::Tool.style(theme), Style::default().fg(Color::Yellow));
+    assert_eq!(ChatRole::Assistant.style(None), Style::default().fg(Color::Blue));
+    assert_eq!(
+      ChatRole::System.header_style(theme),
+      Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+    );
+  }
+}
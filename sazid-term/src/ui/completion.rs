@@ -91,7 +91,8 @@ impl menu::Item for CompletionItem {
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct CompletionItem {
   pub item: lsp::CompletionItem,
-  pub language_server_id: usize,
+  /// none for items completed without a language server, like @-mentions
+  pub language_server_id: Option<usize>,
   pub resolved: bool,
 }
 
@@ -202,14 +203,13 @@ impl Completion {
 
       let (view, doc) = current!(editor);
 
-      macro_rules! language_server {
+      macro_rules! offset_encoding {
                 ($item:expr) => {
-                    match editor
-                        .language_servers
-                        .get_by_id($item.language_server_id)
-                    {
-                        Some(ls) => ls,
-                        None => {
+                    match $item.language_server_id.map(|id| editor.language_servers.get_by_id(id)) {
+                        // items made without a language server edit by utf-8 offsets
+                        None => OffsetEncoding::Utf8,
+                        Some(Some(ls)) => ls.offset_encoding(),
+                        Some(None) => {
                             editor.set_error("completions are outdated");
                             // TODO close the completion menu somehow,
                             // currently there is no trivial way to access the EditorView to close the completion menu
@@ -246,7 +246,7 @@ impl Completion {
             doc,
             view.id,
             &item.item,
-            language_server!(item).offset_encoding(),
+            offset_encoding!(item),
             trigger_offset,
             true,
             replace_mode,
@@ -261,13 +261,11 @@ impl Completion {
           // always present here
           let mut item = item.unwrap().clone();
 
-          let language_server = language_server!(item);
-          let offset_encoding = language_server.offset_encoding();
-
-          let language_server = editor.language_servers.get_by_id(item.language_server_id).unwrap();
+          let offset_encoding = offset_encoding!(item);
 
           // resolve item if not yet resolved
-          if !item.resolved {
+          if let (false, Some(id)) = (item.resolved, item.language_server_id) {
+            let language_server = editor.language_servers.get_by_id(id).unwrap();
             if let Some(resolved) =
               Self::resolve_completion_item(language_server, item.item.clone())
             {
@@ -553,7 +551,9 @@ fn resolve_completion_item(
   item: CompletionItem,
   cancel: helix_event::CancelRx,
 ) {
-  let Some(language_server) = editor.language_server_by_id(item.language_server_id) else {
+  let Some(language_server) =
+    item.language_server_id.and_then(|id| editor.language_server_by_id(id))
+  else {
     return;
   };
 
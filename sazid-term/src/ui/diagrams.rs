@@ -0,0 +1,274 @@
+// mermaid and graphviz blocks in replies rendered to pngs by an external command and drawn over
+// their code blocks with the terminal's graphics protocol. terminals without one show the code
+use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use helix_view::graphics::Rect;
+use sazid::app::model_tools::shell_tool::command_words;
+use sazid::app::session_config::DiagramConfig;
+use serde_json::Value;
+
+// the longest a diagram command may run
+const RENDER_TIMEOUT: Duration = Duration::from_secs(30);
+// kitty takes the image data in chunks of at most this many bytes
+const KITTY_CHUNK_BYTES: usize = 4096;
+
+/// the terminal graphics protocols images are drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+  Kitty,
+  ITerm2,
+}
+
+impl GraphicsProtocol {
+  /// the protocol of the terminal sazid runs in, from the variables the terminal sets. none for
+  /// other terminals and under tmux, which does not pass images through
+  pub fn detect() -> Option<Self> {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    if std::env::var_os("TMUX").is_some() {
+      None
+    } else if var("TERM") == "xterm-kitty" || std::env::var_os("KITTY_WINDOW_ID").is_some() {
+      Some(GraphicsProtocol::Kitty)
+    } else if matches!(var("TERM_PROGRAM").as_str(), "iTerm.app" | "WezTerm") {
+      Some(GraphicsProtocol::ITerm2)
+    } else {
+      None
+    }
+  }
+
+  /// the escape sequence drawing `png` at the cursor, scaled to the cells of `area`
+  pub fn image_sequence(self, png: &[u8], area: Rect) -> String {
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    match self {
+      GraphicsProtocol::Kitty => {
+        let chunks = data.as_bytes().chunks(KITTY_CHUNK_BYTES).collect::<Vec<_>>();
+        let mut sequence = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+          let more = u8::from(i + 1 < chunks.len());
+          // base64 is ascii, so every chunk is a str
+          let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+          let control = match i {
+            0 => format!("a=T,f=100,q=2,C=1,c={},r={},m={}", area.width, area.height, more),
+            _ => format!("m={}", more),
+          };
+          sequence.push_str(&format!("\x1b_G{};{}\x1b\\", control, chunk));
+        }
+        sequence
+      },
+      GraphicsProtocol::ITerm2 => format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        area.width,
+        area.height,
+        data
+      ),
+    }
+  }
+
+  // removes the images drawn before. kitty keeps them above the text until they are deleted,
+  // iTerm2 draws them into the cells, which the next change to the cells replaces
+  fn clear_sequence(self) -> &'static str {
+    match self {
+      GraphicsProtocol::Kitty => "\x1b_Ga=d,d=A,q=2\x1b\\",
+      GraphicsProtocol::ITerm2 => "",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagramKind {
+  Mermaid,
+  Graphviz,
+}
+
+impl DiagramKind {
+  /// the kind of diagram in a code block with the info string `language`
+  pub fn from_language(language: &str) -> Option<Self> {
+    match language {
+      "mermaid" => Some(DiagramKind::Mermaid),
+      "dot" | "graphviz" => Some(DiagramKind::Graphviz),
+      _ => None,
+    }
+  }
+
+  fn command(self, config: &DiagramConfig) -> &str {
+    match self {
+      DiagramKind::Mermaid => &config.mermaid_command,
+      DiagramKind::Graphviz => &config.graphviz_command,
+    }
+  }
+
+  fn extension(self) -> &'static str {
+    match self {
+      DiagramKind::Mermaid => "mmd",
+      DiagramKind::Graphviz => "dot",
+    }
+  }
+}
+
+/// a diagram block of a reply and the cells of the screen it is drawn in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagram {
+  pub kind: DiagramKind,
+  pub source: String,
+  pub area: Rect,
+}
+
+impl Diagram {
+  // the same diagram has the same key wherever it is drawn, its png is rendered once
+  fn key(&self) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (self.kind, &self.source).hash(&mut hasher);
+    hasher.finish()
+  }
+}
+
+enum Rendered {
+  Pending,
+  Png(Arc<Vec<u8>>),
+  Failed,
+}
+
+/// draws diagrams over their code blocks once the frame is on the screen. each diagram is
+/// rendered in the background the first time it is seen and shown as code until it is ready
+pub struct InlineDiagrams {
+  protocol: GraphicsProtocol,
+  config: DiagramConfig,
+  rendered: Arc<Mutex<HashMap<u64, Rendered>>>,
+  // the diagrams drawn and where, images are only sent again when these change
+  placed: Vec<(u64, Rect)>,
+}
+
+impl InlineDiagrams {
+  /// none when diagrams are turned off or the terminal can not draw images
+  pub fn new(config: &DiagramConfig) -> Option<Self> {
+    let protocol = GraphicsProtocol::detect().filter(|_| config.enabled)?;
+    Some(InlineDiagrams {
+      protocol,
+      config: config.clone(),
+      rendered: Arc::new(Mutex::new(HashMap::new())),
+      placed: vec![],
+    })
+  }
+
+  pub fn config(&self) -> &DiagramConfig {
+    &self.config
+  }
+
+  /// draws the `diagrams` whose pngs are ready, replacing the images drawn before
+  pub fn draw(&mut self, diagrams: &[Diagram], out: &mut impl Write) -> std::io::Result<()> {
+    let mut images = vec![];
+    {
+      let mut rendered = self.rendered.lock().unwrap();
+      for diagram in diagrams {
+        let key = diagram.key();
+        match rendered.get(&key) {
+          Some(Rendered::Png(png)) => images.push((key, diagram.area, png.clone())),
+          Some(Rendered::Pending | Rendered::Failed) => {},
+          None => {
+            rendered.insert(key, Rendered::Pending);
+            self.render(key, diagram);
+          },
+        }
+      }
+    }
+    let placed = images.iter().map(|(key, area, _)| (*key, *area)).collect::<Vec<_>>();
+    if placed == self.placed {
+      return Ok(());
+    }
+    self.placed = placed;
+    // the cursor is saved and restored around the images so the frame's cursor stays put
+    write!(out, "\x1b7{}", self.protocol.clear_sequence())?;
+    for (_, area, png) in images {
+      let image = self.protocol.image_sequence(&png, area);
+      write!(out, "\x1b[{};{}H{}", area.y + 1, area.x + 1, image)?;
+    }
+    write!(out, "\x1b8")?;
+    out.flush()
+  }
+
+  fn render(&self, key: u64, diagram: &Diagram) {
+    let rendered = self.rendered.clone();
+    let command = diagram.kind.command(&self.config).to_string();
+    let (kind, source) = (diagram.kind, diagram.source.clone());
+    tokio::spawn(async move {
+      let state = match render_png(&command, kind, &source, key).await {
+        Ok(png) => Rendered::Png(Arc::new(png)),
+        Err(e) => {
+          log::warn!("unable to render a {:?} diagram: {}", kind, e);
+          Rendered::Failed
+        },
+      };
+      rendered.lock().unwrap().insert(key, state);
+      helix_event::request_redraw();
+    });
+  }
+}
+
+// the png the diagram command writes for `source`. pngs are kept in the cache directory, so a
+// diagram is only rendered again once the cache is cleared
+async fn render_png(
+  command: &str,
+  kind: DiagramKind,
+  source: &str,
+  key: u64,
+) -> anyhow::Result<Vec<u8>> {
+  let dir = helix_loader::cache_dir().join("diagrams");
+  let output = dir.join(format!("{:x}.png", key));
+  if let Ok(png) = std::fs::read(&output) {
+    return Ok(png);
+  }
+  std::fs::create_dir_all(&dir)?;
+  let input = dir.join(format!("{:x}.{}", key, kind.extension()));
+  std::fs::write(&input, source)?;
+
+  let arguments = HashMap::from([
+    ("input".to_string(), Value::from(input.display().to_string())),
+    ("output".to_string(), Value::from(output.display().to_string())),
+  ]);
+  let words = command_words(command, &arguments).map_err(|e| anyhow!("{}", e))?;
+  let Some((program, args)) = words.split_first() else {
+    bail!("the {:?} diagram command is empty", kind);
+  };
+  let mut process = tokio::process::Command::new(program);
+  process.args(args).stdin(Stdio::null()).kill_on_drop(true);
+  let result = tokio::time::timeout(RENDER_TIMEOUT, process.output())
+    .await
+    .map_err(|_| anyhow!("{} did not finish within {} seconds", program, RENDER_TIMEOUT.as_secs()))?
+    .with_context(|| format!("running {}", program))?;
+  if !result.status.success() {
+    bail!("{} failed: {}", program, String::from_utf8_lossy(&result.stderr).trim());
+  }
+  std::fs::read(&output).with_context(|| format!("reading {}", output.display()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_image_sequences() {
+    let area = Rect::new(4, 2, 30, 6);
+    let png = vec![0u8; 4000];
+    let kitty = GraphicsProtocol::Kitty.image_sequence(&png, area);
+    // 4000 bytes are 5336 base64 chars, sent in two chunks
+    assert!(kitty.starts_with("\x1b_Ga=T,f=100,q=2,C=1,c=30,r=6,m=1;AAAA"));
+    assert!(kitty.contains("\x1b\\\x1b_Gm=0;AAAA"));
+    assert_eq!(kitty.matches("\x1b_G").count(), 2);
+
+    let iterm = GraphicsProtocol::ITerm2.image_sequence(&[1, 2, 3], area);
+    assert_eq!(
+      iterm,
+      "\x1b]1337;File=inline=1;size=3;width=30;height=6;preserveAspectRatio=1:AQID\x07"
+    );
+
+    assert_eq!(DiagramKind::from_language("dot"), Some(DiagramKind::Graphviz));
+    assert_eq!(DiagramKind::from_language("rust"), None);
+  }
+}
@@ -6,6 +6,7 @@ use crate::{
   keymap::{KeymapResult, Keymaps},
   ui::{
     document::{render_document, LinePos, TextRenderer, TranslatedPosition},
+    split_view::split_columns,
     Completion, ProgressSpinners,
   },
 };
@@ -25,8 +26,9 @@ use helix_view::{
   graphics::{Color, CursorKind, Modifier, Rect, Style},
   input::{KeyEvent, MouseButton, MouseEvent, MouseEventKind},
   keyboard::{KeyCode, KeyModifiers},
-  Document, Editor, Theme, View,
+  Document, Editor, Theme, View, ViewId,
 };
+use sazid::app::snippets::is_snippet_paste;
 use std::{mem::take, num::NonZeroUsize, path::PathBuf, rc::Rc, sync::Arc};
 
 use tui::{buffer::Buffer as Surface, text::Span};
@@ -51,6 +53,8 @@ pub struct EditorView {
   /// Tracks if the terminal window is focused by reaction to terminal focus events
   terminal_focused: bool,
   editor_is_focused: bool,
+  /// the view of a file shown beside the session, see `ui::split_view`
+  pub split_view: Option<ViewId>,
 }
 
 #[derive(Debug, Clone)]
@@ -80,6 +84,7 @@ impl EditorView {
       spinners: ProgressSpinners::default(),
       terminal_focused: true,
       editor_is_focused: true,
+      split_view: None,
     }
   }
 
@@ -1181,6 +1186,18 @@ impl Component for EditorView {
     };
 
     match event {
+      Event::Paste(contents)
+        if self.split_view != Some(cx.editor.tree.focus)
+          && is_snippet_paste(contents, cx.session.config.snippet_paste_lines) =>
+      {
+        // a large paste into the input is sent with the next message instead, where it folds
+        cx.session.snippets.push(contents.clone());
+        cx.editor.set_status(format!(
+          "attached a {} line snippet to the next message, :snippet-drop removes it",
+          contents.lines().count()
+        ));
+        EventResult::Consumed(None)
+      },
       Event::Paste(contents) => {
         cx.count = cx.editor.count;
         commands::paste_bracketed_value(&mut cx, contents.clone());
@@ -1375,15 +1392,26 @@ impl Component for EditorView {
     surface.set_style(editor_area, cx.editor.theme.get("ui.background"));
     // if the terminal size suddenly changed, we need to trigger a resize
     cx.editor.resize(editor_area);
+    // the tree lays every view out in the input, the split view is moved beside the session and
+    // the others take the whole input
+    if let Some(split_view) = self.split_view.filter(|id| cx.editor.tree.contains(*id)) {
+      let session_area = area.clip_bottom(self.override_height.unwrap_or(0) + 2);
+      let (_, split_area) = split_columns(session_area);
+      let view_ids = cx.editor.tree.views().map(|(view, _)| view.id).collect::<Vec<_>>();
+      for view_id in view_ids {
+        cx.editor.tree.get_mut(view_id).area =
+          if view_id == split_view { split_area } else { editor_area };
+      }
+    }
 
     if use_bufferline {
       Self::render_bufferline(cx.editor, area.with_height(1), surface);
     }
 
     self.editor_is_focused = matches!(cx.focus, ContextFocus::EditorView);
-    for (view, _focused) in cx.editor.tree.views() {
+    for (view, focused) in cx.editor.tree.views() {
       let doc = cx.editor.document(view.doc).unwrap();
-      self.render_view(cx.editor, doc, view, area, surface, self.editor_is_focused);
+      self.render_view(cx.editor, doc, view, area, surface, self.editor_is_focused && focused);
     }
 
     if config.auto_info {
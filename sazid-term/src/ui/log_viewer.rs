@@ -0,0 +1,335 @@
+use std::collections::VecDeque;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use helix_view::graphics::{Margin, Rect};
+use helix_view::input::{Event, KeyEvent};
+use helix_view::keyboard::KeyCode;
+use log::LevelFilter;
+use tokio::task::AbortHandle;
+use tui::buffer::Buffer as Surface;
+use tui::widgets::{Block, Borders, Widget};
+
+use crate::compositor::{Callback, Component, Compositor, Context, EventResult};
+use crate::{ctrl, key};
+
+// lines kept in memory, older ones are dropped as the log grows
+const MAX_LINES: usize = 5000;
+// how much of an existing log is read when the viewer opens
+const INITIAL_TAIL_BYTES: u64 = 256 * 1024;
+// how often the open viewer checks whether the log was written to
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+const HELP: &str = "1-5 severity · / search · j/k scroll · g/G top/bottom · esc close";
+
+/// a line of the log with the severity it was logged at. lines without one, like the rest of a
+/// multi line message, have the severity of the line before them
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+  pub level: log::Level,
+  pub text: String,
+}
+
+/// the end of a log file, read as it is written
+#[derive(Debug)]
+pub struct LogTail {
+  path: PathBuf,
+  offset: Option<u64>,
+  partial: String,
+  pub lines: VecDeque<LogLine>,
+}
+
+impl LogTail {
+  pub fn new(path: PathBuf) -> Self {
+    LogTail { path, offset: None, partial: String::new(), lines: VecDeque::new() }
+  }
+
+  /// reads what was written since the last poll, returns true if lines were added. a log that
+  /// shrank was started over, and is read from its beginning
+  pub fn poll(&mut self) -> std::io::Result<bool> {
+    let mut file = std::fs::File::open(&self.path)?;
+    let len = file.metadata()?.len();
+    let offset = match self.offset {
+      Some(offset) if offset <= len => offset,
+      Some(_) => {
+        self.lines.clear();
+        self.partial.clear();
+        0
+      },
+      None => len.saturating_sub(INITIAL_TAIL_BYTES),
+    };
+    if offset == len {
+      self.offset = Some(len);
+      return Ok(false);
+    }
+    // the first line of a log read from the middle is cut off
+    let cut_off = self.offset.is_none() && offset > 0;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = vec![];
+    file.take(len - offset).read_to_end(&mut bytes)?;
+    self.offset = Some(offset + bytes.len() as u64);
+    self.partial.push_str(&String::from_utf8_lossy(&bytes));
+    if cut_off {
+      let end = self.partial.find('\n').map_or(self.partial.len(), |end| end + 1);
+      self.partial.drain(..end);
+    }
+
+    let Some(end) = self.partial.rfind('\n') else {
+      return Ok(false);
+    };
+    let complete = self.partial.drain(..=end).collect::<String>();
+    complete.lines().for_each(|line| self.push(line));
+    Ok(true)
+  }
+
+  fn push(&mut self, line: &str) {
+    let text = strip_ansi_escapes::strip_str(line);
+    let previous = self.lines.back().map(|line| line.level).unwrap_or(log::Level::Info);
+    let level = parse_level(&text).unwrap_or(previous);
+    if self.lines.len() == MAX_LINES {
+      self.lines.pop_front();
+    }
+    self.lines.push_back(LogLine { level, text });
+  }
+}
+
+// the severity a line starts with, as the tracing formatter writes it
+fn parse_level(text: &str) -> Option<log::Level> {
+  let word = text.split_whitespace().next()?.trim_matches(|c| c == '[' || c == ']');
+  // a continuation line can start with a word like "info"
+  if !word.chars().all(|c| c.is_ascii_uppercase()) {
+    return None;
+  }
+  word.parse().ok()
+}
+
+/// a panel over the lower half of the screen tailing the log file, filtered by severity and
+/// searched for a string
+pub struct LogViewer {
+  tail: LogTail,
+  max_level: LevelFilter,
+  query: String,
+  searching: bool,
+  // lines scrolled up from the end, 0 follows the log as it is written
+  scroll: usize,
+  error: Option<String>,
+  // redraws the viewer as the log is written, started by the first render
+  watcher: Option<AbortHandle>,
+}
+
+impl LogViewer {
+  pub const ID: &'static str = "log-viewer";
+
+  pub fn new(path: PathBuf) -> Self {
+    LogViewer {
+      tail: LogTail::new(path),
+      max_level: LevelFilter::Trace,
+      query: String::new(),
+      searching: false,
+      scroll: 0,
+      error: None,
+      watcher: None,
+    }
+  }
+
+  /// opens the viewer searching for `query`
+  pub fn with_query(mut self, query: String) -> Self {
+    self.query = query;
+    self
+  }
+
+  fn close() -> Option<Callback> {
+    Some(Box::new(|compositor: &mut Compositor, _| {
+      compositor.remove(Self::ID);
+    }))
+  }
+
+  /// the lines shown with the severity filter and search applied
+  pub fn visible_lines(&self) -> Vec<&LogLine> {
+    let query = self.query.to_lowercase();
+    self
+      .tail
+      .lines
+      .iter()
+      .filter(|line| line.level <= self.max_level)
+      .filter(|line| query.is_empty() || line.text.to_lowercase().contains(&query))
+      .collect()
+  }
+
+  fn handle_search_key(&mut self, key: KeyEvent) {
+    match key {
+      key!(Esc) => {
+        self.query.clear();
+        self.searching = false;
+      },
+      key!(Enter) => self.searching = false,
+      key!(Backspace) => {
+        self.query.pop();
+      },
+      KeyEvent { code: KeyCode::Char(c), .. } => self.query.push(c),
+      _ => {},
+    }
+    self.scroll = 0;
+  }
+}
+
+impl Component for LogViewer {
+  fn handle_event(&mut self, event: &Event, _cx: &mut Context) -> EventResult {
+    let Event::Key(key) = event else {
+      return EventResult::Ignored(None);
+    };
+    if self.searching {
+      self.handle_search_key(*key);
+      return EventResult::Consumed(None);
+    }
+    let last = self.visible_lines().len().saturating_sub(1);
+    match *key {
+      key!(Esc) | key!('q') | ctrl!('c') => return EventResult::Consumed(Self::close()),
+      key!('/') => {
+        self.query.clear();
+        self.searching = true;
+      },
+      key!('1') => self.max_level = LevelFilter::Error,
+      key!('2') => self.max_level = LevelFilter::Warn,
+      key!('3') => self.max_level = LevelFilter::Info,
+      key!('4') => self.max_level = LevelFilter::Debug,
+      key!('5') => self.max_level = LevelFilter::Trace,
+      key!('k') | key!(Up) => self.scroll = (self.scroll + 1).min(last),
+      key!('j') | key!(Down) => self.scroll = self.scroll.saturating_sub(1),
+      ctrl!('u') | key!(PageUp) => self.scroll = (self.scroll + 10).min(last),
+      ctrl!('d') | key!(PageDown) => self.scroll = self.scroll.saturating_sub(10),
+      key!('g') => self.scroll = last,
+      key!('G') => self.scroll = 0,
+      _ => {},
+    }
+    EventResult::Consumed(None)
+  }
+
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    if self.watcher.is_none() {
+      self.watcher = Some(watch_log(self.tail.path.clone()));
+    }
+    let shown = self.visible_lines().len();
+    match self.tail.poll() {
+      Ok(added) => {
+        // keep the lines in view while scrolled up
+        if added && self.scroll > 0 {
+          self.scroll += self.visible_lines().len().saturating_sub(shown);
+        }
+        self.error = None;
+      },
+      Err(e) => self.error = Some(format!("cannot read {}: {}", self.tail.path.display(), e)),
+    }
+
+    let theme = &cx.editor.theme;
+    let text_style = theme.get("ui.text");
+    let help_style = theme.get("ui.text.inactive");
+    let level_style = |level: log::Level| match level {
+      log::Level::Error => theme.get("error"),
+      log::Level::Warn => theme.get("warning"),
+      log::Level::Info => theme.get("info"),
+      log::Level::Debug => theme.get("hint"),
+      log::Level::Trace => help_style,
+    };
+
+    // the lower half of the screen, above the command line
+    let area = area.clip_bottom(1);
+    let area = area.clip_top(area.height / 2);
+    surface.clear_with(area, theme.get("ui.background"));
+    let title = match (&self.query, self.searching) {
+      (query, true) => format!("log ({}) /{}", self.max_level, query),
+      (query, false) if !query.is_empty() => format!("log ({}) matching {}", self.max_level, query),
+      _ => format!("log ({})", self.max_level),
+    };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+    if inner.height < 2 {
+      return;
+    }
+
+    let height = inner.height as usize - 1;
+    if let Some(error) = &self.error {
+      surface.set_stringn(inner.x, inner.y, error, inner.width as usize, theme.get("error"));
+    } else {
+      let lines = self.visible_lines();
+      let end = lines.len().saturating_sub(self.scroll);
+      let start = end.saturating_sub(height);
+      for (y, line) in (inner.y..).zip(&lines[start..end]) {
+        let style = text_style.patch(level_style(line.level));
+        surface.set_stringn(inner.x, y, &line.text, inner.width as usize, style);
+      }
+    }
+    surface.set_stringn(inner.x, inner.bottom() - 1, HELP, inner.width as usize, help_style);
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
+
+impl Drop for LogViewer {
+  fn drop(&mut self) {
+    if let Some(watcher) = &self.watcher {
+      watcher.abort();
+    }
+  }
+}
+
+// requests a redraw whenever the length of the log changes, so lines written while no key is
+// pressed are read by the next render
+fn watch_log(path: PathBuf) -> AbortHandle {
+  let log_len = |path: &Path| std::fs::metadata(path).map(|metadata| metadata.len()).ok();
+  tokio::spawn(async move {
+    let mut len = log_len(&path);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+      interval.tick().await;
+      let current = log_len(&path);
+      if current != len {
+        len = current;
+        helix_event::request_redraw();
+      }
+    }
+  })
+  .abort_handle()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  #[test]
+  fn test_log_is_tailed_and_filtered() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sazid.log");
+    let mut file = std::fs::File::create(&path).unwrap();
+    write!(file, "\x1b[32m INFO\x1b[0m src/lsp.rs:4: started\n WARN src/tools.rs:9: slow\nsec")
+      .unwrap();
+
+    let mut viewer = LogViewer::new(path.clone());
+    assert!(viewer.tail.poll().unwrap());
+    assert_eq!(viewer.tail.lines.len(), 2);
+    assert_eq!(viewer.tail.lines[0].text, " INFO src/lsp.rs:4: started");
+
+    // the line that was being written is read once it is complete
+    writeln!(file, "ond line\nERROR src/tools.rs:12: tool call failed").unwrap();
+    assert!(viewer.tail.poll().unwrap());
+    assert!(!viewer.tail.poll().unwrap());
+    let levels = viewer.tail.lines.iter().map(|line| line.level).collect::<Vec<_>>();
+    assert_eq!(levels, [log::Level::Info, log::Level::Warn, log::Level::Warn, log::Level::Error]);
+
+    viewer.max_level = LevelFilter::Warn;
+    assert_eq!(viewer.visible_lines().len(), 3);
+    viewer.query = "TOOL CALL".to_string();
+    let visible = viewer.visible_lines();
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].level, log::Level::Error);
+
+    // a new run starts the log over
+    std::fs::write(&path, "DEBUG restarted\n").unwrap();
+    assert!(viewer.tail.poll().unwrap());
+    assert_eq!(viewer.tail.lines.len(), 1);
+  }
+}
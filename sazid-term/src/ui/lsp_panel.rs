@@ -0,0 +1,134 @@
+use helix_view::graphics::{Margin, Rect};
+use helix_view::input::Event;
+use sazid::action::{LsiAction, SessionAction};
+use sazid::app::lsi::server_status::LanguageServerStatus;
+use tui::buffer::Buffer as Surface;
+use tui::widgets::{Block, Borders, Widget};
+
+use crate::compositor::{Callback, Component, Compositor, Context, EventResult};
+use crate::ui::LogViewer;
+use crate::{ctrl, key};
+
+// the widest the panel is drawn
+const MAX_WIDTH: u16 = 120;
+const HELP: &str = "r restart · s stop · e stderr · j/k move · esc close";
+
+/// the language servers in the registry, with their process, workspaces and pending progress.
+/// the application refreshes `servers` before each render
+#[derive(Default)]
+pub struct LanguageServerPanel {
+  pub servers: Vec<LanguageServerStatus>,
+  cursor: usize,
+}
+
+impl LanguageServerPanel {
+  pub const ID: &'static str = "lsp-panel";
+
+  fn close() -> Option<Callback> {
+    Some(Box::new(|compositor: &mut Compositor, _| {
+      compositor.remove(Self::ID);
+    }))
+  }
+
+  // the lines of a server, its name and state then its workspaces and progress
+  fn server_lines(server: &LanguageServerStatus) -> Vec<String> {
+    let pid = server.pid.map_or("pid unknown".to_string(), |pid| format!("pid {}", pid));
+    let state = if server.initialized { "running" } else { "initializing" };
+    let mut lines = vec![format!("{} ({}, {})", server.name, pid, state)];
+    lines.extend(server.roots.iter().map(|root| format!("  root {}", root.display())));
+    if !server.progress_tokens.is_empty() {
+      lines.push(format!("  progress {}", server.progress_tokens.join(", ")));
+    }
+    lines
+  }
+}
+
+impl Component for LanguageServerPanel {
+  fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+    let Event::Key(key) = event else {
+      return EventResult::Ignored(None);
+    };
+    let last = self.servers.len().saturating_sub(1);
+    self.cursor = self.cursor.min(last);
+    let selected = self.servers.get(self.cursor);
+    let send = |cx: &mut Context, action: LsiAction| {
+      if let Some(tx) = &cx.session.action_tx {
+        tx.send(SessionAction::LsiAction(action)).unwrap();
+      }
+    };
+    match *key {
+      key!(Esc) | key!('q') | ctrl!('c') => return EventResult::Consumed(Self::close()),
+      key!('j') | key!(Down) => self.cursor = (self.cursor + 1).min(last),
+      key!('k') | key!(Up) => self.cursor = self.cursor.saturating_sub(1),
+      key!('r') => {
+        if let Some(server) = selected {
+          send(cx, LsiAction::RestartLanguageServer(server.id));
+          cx.editor.set_status(format!("restarting {}", server.name));
+        }
+      },
+      key!('s') => {
+        if let Some(server) = selected {
+          send(cx, LsiAction::StopLanguageServer(server.id));
+          cx.editor.set_status(format!("stopped {}, its workspaces are closed", server.name));
+        }
+      },
+      key!('e') => {
+        // helix logs what a server writes to stderr as `<name> err <- <line>`
+        if let Some(query) = selected.map(|server| format!("{} err", server.name)) {
+          return EventResult::Consumed(Some(Box::new(|compositor: &mut Compositor, _| {
+            let viewer = LogViewer::new(helix_loader::log_file()).with_query(query);
+            compositor.push(Box::new(viewer));
+          })));
+        }
+      },
+      _ => {},
+    }
+    EventResult::Consumed(None)
+  }
+
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let theme = &cx.editor.theme;
+    let text_style = theme.get("ui.text");
+    let selected_style = theme.get("ui.menu.selected");
+    let help_style = theme.get("ui.text.inactive");
+
+    let lines = self.servers.iter().map(Self::server_lines).collect::<Vec<_>>();
+    let line_count = lines.iter().map(Vec::len).sum::<usize>().max(1);
+    let width = area.width.saturating_sub(4).min(MAX_WIDTH);
+    let height = (line_count as u16 + 3).min(area.height);
+    let area = Rect::new(
+      area.x + (area.width - width) / 2,
+      area.y + (area.height - height) / 2,
+      width,
+      height,
+    );
+    surface.clear_with(area, theme.get("ui.background"));
+    let block = Block::default().title("language servers").borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+    if inner.height == 0 {
+      return;
+    }
+
+    if self.servers.is_empty() {
+      let message = "no language server is running";
+      surface.set_stringn(inner.x, inner.y, message, inner.width as usize, help_style);
+    }
+    let mut y = inner.y;
+    for (idx, server_lines) in lines.iter().enumerate() {
+      let style = if idx == self.cursor { selected_style } else { text_style };
+      for line in server_lines {
+        if y >= inner.bottom().saturating_sub(1) {
+          break;
+        }
+        surface.set_stringn(inner.x, y, line, inner.width as usize, style);
+        y += 1;
+      }
+    }
+    surface.set_stringn(inner.x, inner.bottom() - 1, HELP, inner.width as usize, help_style);
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
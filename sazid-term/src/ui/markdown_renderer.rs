@@ -306,6 +306,153 @@ impl MarkdownRenderer {
   }
 }
 
+/// markdown rendered a block at a time, so text streamed onto the end of a message is styled
+/// as it arrives without parsing and highlighting everything before it again. blocks that end
+/// before the last block start are final once rendered, only the last block is parsed again
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IncrementalMarkdown {
+  source: String,
+  theme_name: Option<String>,
+  /// byte offset in source of the last block, which may still change
+  open_block_start: usize,
+  closed_lines: Vec<Spans<'static>>,
+  open_lines: Vec<Spans<'static>>,
+}
+
+impl IncrementalMarkdown {
+  /// true if the lines are rendered from `contents` with `theme`
+  pub fn is_current(&self, contents: &str, theme: Option<&Theme>) -> bool {
+    self.theme_name.as_deref() == theme.map(|t| t.name()) && self.source == contents
+  }
+
+  pub fn lines(&self) -> impl Iterator<Item = &Spans<'static>> {
+    self.closed_lines.iter().chain(self.open_lines.iter())
+  }
+
+  /// renders `contents`, reusing the blocks rendered before if it extends the previous
+  /// contents. returns the index of the first line that may have changed
+  pub fn update(
+    &mut self,
+    contents: &str,
+    theme: Option<&Theme>,
+    config_loader: Arc<ArcSwap<syntax::Loader>>,
+  ) -> usize {
+    let theme_name = theme.map(|t| t.name().to_string());
+    if theme_name != self.theme_name || !contents.starts_with(&self.source[..self.open_block_start])
+    {
+      *self = IncrementalMarkdown { theme_name, ..Default::default() };
+    }
+    let first_changed = self.closed_lines.len();
+
+    for block_start in block_starts(contents, self.open_block_start) {
+      let block = &contents[self.open_block_start..block_start];
+      let lines = render_owned(block, theme, config_loader.clone());
+      if !self.closed_lines.is_empty() && !lines.is_empty() {
+        // a block renders without the blank line parsing the whole text ends it with
+        self.closed_lines.push(Spans::default());
+      }
+      self.closed_lines.extend(lines);
+      self.open_block_start = block_start;
+    }
+
+    let open_block = &contents[self.open_block_start..];
+    self.open_lines = render_owned(open_block, theme, config_loader);
+    if !self.closed_lines.is_empty() && !self.open_lines.is_empty() {
+      self.open_lines.insert(0, Spans::default());
+    }
+    self.source = contents.to_string();
+    first_changed
+  }
+}
+
+fn render_owned(
+  contents: &str,
+  theme: Option<&Theme>,
+  config_loader: Arc<ArcSwap<syntax::Loader>>,
+) -> Vec<Spans<'static>> {
+  MarkdownRenderer::parse(contents, theme, config_loader)
+    .lines
+    .into_iter()
+    .map(|spans| {
+      Spans::from(
+        spans
+          .0
+          .into_iter()
+          .map(|span| Span::styled(span.content.into_owned(), span.style))
+          .collect::<Vec<_>>(),
+      )
+    })
+    .collect()
+}
+
+/// byte offsets of the lines in `text` after `from` that start a top level block. only
+/// complete lines that follow a blank line outside a code fence count. list items and
+/// indented lines can continue the block before them, they never start one
+fn block_starts(text: &str, from: usize) -> Vec<usize> {
+  let mut starts = vec![];
+  let mut fence: Option<(char, usize)> = None;
+  let mut after_blank = false;
+  let mut offset = from;
+  for line in text[from..].split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    if !line.ends_with('\n') {
+      break;
+    }
+    let content = line.trim();
+    let indent = line.trim_end().len() - content.len();
+    let fence_len = |ch: char| content.chars().take_while(|c| *c == ch).count();
+
+    if let Some((ch, len)) = fence {
+      if indent < 4 && fence_len(ch) >= len && content.trim_start_matches(ch).is_empty() {
+        fence = None;
+      }
+      continue;
+    }
+    if content.is_empty() {
+      after_blank = true;
+      continue;
+    }
+    if after_blank && indent == 0 && !is_list_item(content) {
+      starts.push(start);
+    }
+    after_blank = false;
+    if let Some(ch @ ('`' | '~')) = content.chars().next() {
+      if indent < 4 && fence_len(ch) >= 3 {
+        fence = Some((ch, fence_len(ch)));
+      }
+    }
+  }
+  starts
+}
+
+fn is_list_item(line: &str) -> bool {
+  let marker = line.split_whitespace().next().unwrap_or_default();
+  let digits = marker.trim_end_matches(['.', ')']);
+  matches!(marker, "-" | "*" | "+")
+    || marker.len() == digits.len() + 1
+      && !digits.is_empty()
+      && digits.len() <= 9
+      && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_block_starts() {
+    let text = "# Title\n\nsome text\n\n```rust\nfn a() {}\n\nfn b() {}\n```\n\n- one\n\n- two\n\n  more\n\ndone\n\npartial";
+    let starts = block_starts(text, 0);
+    let blocks =
+      starts.iter().map(|start| text[*start..].lines().next().unwrap()).collect::<Vec<_>>();
+    assert_eq!(blocks, vec!["some text", "```rust", "done"]);
+    assert_eq!(block_starts(text, starts[1]), starts[2..].to_vec());
+    assert!(is_list_item("12. twelve"));
+    assert!(!is_list_item("2024 was a year"));
+  }
+}
+
 // impl Component for Markdown {
 //   fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
 //     use tui::widgets::{Paragraph, Widget, Wrap};
@@ -1,41 +1,63 @@
+mod chat_theme;
 mod completion;
+pub(crate) mod diagrams;
 mod document;
 pub(crate) mod editor;
 mod info;
+mod log_viewer;
 pub mod lsp;
+mod lsp_panel;
 mod markdown;
 mod markdown_renderer;
 pub mod markdownmenu;
 pub mod menu;
+mod mouse_select;
+mod outline;
 pub mod overlay;
 pub mod picker;
+mod plan_editor;
 pub mod popup;
+pub mod prefix_sums;
 mod prompt;
+pub mod provider_setup;
+pub mod render_perf;
+#[cfg(test)]
+mod render_snapshots;
 pub mod session;
+mod session_statusline;
+pub mod session_tabs;
 mod spinner;
+pub mod split_view;
 mod statusline;
 mod text;
 pub mod textbox;
 
 use crate::compositor::{Component, Compositor};
-use crate::filter_picker_entry;
 use crate::job::{self, Callback};
+pub use chat_theme::ChatRole;
 pub use completion::{Completion, CompletionItem};
 pub use editor::EditorView;
 use helix_stdx::rope;
+pub use log_viewer::LogViewer;
+pub use lsp_panel::LanguageServerPanel;
 pub use markdown::Markdown;
-pub use markdown_renderer::MarkdownRenderer;
+pub use markdown_renderer::{IncrementalMarkdown, MarkdownRenderer};
 pub use menu::Menu;
+pub use outline::FileOutline;
 pub use picker::{DynamicPicker, FileLocation, Picker};
+pub use plan_editor::PlanEditor;
 pub use popup::Popup;
 pub use prompt::{Prompt, PromptEvent};
+pub use provider_setup::ProviderSetup;
 pub use session::SessionView;
+pub use session_statusline::SessionStatusline;
+pub use session_tabs::SessionTabs;
 pub use spinner::{ProgressSpinners, Spinner};
 pub use text::Text;
 
 use helix_view::Editor;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn prompt(
   cx: &mut crate::commands::Context,
@@ -167,39 +189,20 @@ pub fn raw_regex_prompt(
   cx.push_layer(Box::new(prompt));
 }
 
+/// walks the files under `root` the file picker lists, leaving out ignored files and archives
+pub fn workspace_walk_builder(
+  root: &Path,
+  config: &helix_view::editor::Config,
+) -> ignore::WalkBuilder {
+  sazid::app::workspace_walk::workspace_walk_builder(root, &config.file_picker)
+}
+
 pub fn file_picker(root: PathBuf, config: &helix_view::editor::Config) -> Picker<PathBuf> {
-  use ignore::{types::TypesBuilder, WalkBuilder};
   use std::time::Instant;
 
   let now = Instant::now();
 
-  let dedup_symlinks = config.file_picker.deduplicate_links;
-  let absolute_root = root.canonicalize().unwrap_or_else(|_| root.clone());
-
-  let mut walk_builder = WalkBuilder::new(&root);
-  walk_builder
-    .hidden(config.file_picker.hidden)
-    .parents(config.file_picker.parents)
-    .ignore(config.file_picker.ignore)
-    .follow_links(config.file_picker.follow_symlinks)
-    .git_ignore(config.file_picker.git_ignore)
-    .git_global(config.file_picker.git_global)
-    .git_exclude(config.file_picker.git_exclude)
-    .sort_by_file_name(|name1, name2| name1.cmp(name2))
-    .max_depth(config.file_picker.max_depth)
-    .filter_entry(move |entry| filter_picker_entry(entry, &absolute_root, dedup_symlinks));
-
-  walk_builder.add_custom_ignore_filename(helix_loader::config_dir().join("ignore"));
-  walk_builder.add_custom_ignore_filename(".helix/ignore");
-
-  // We want to exclude files that the editor can't handle yet
-  let mut type_builder = TypesBuilder::new();
-  type_builder
-    .add("compressed", "*.{zip,gz,bz2,zst,lzo,sz,tgz,tbz2,lz,lz4,lzma,lzo,z,Z,xz,7z,rar,cab}")
-    .expect("Invalid type definition");
-  type_builder.negate("all");
-  let excluded_types = type_builder.build().expect("failed to build excluded_types");
-  walk_builder.types(excluded_types);
+  let walk_builder = workspace_walk_builder(&root, config);
   let mut files = walk_builder.build().filter_map(|entry| {
     let entry = entry.ok()?;
     if !entry.file_type()?.is_file() {
@@ -306,6 +309,7 @@ pub mod completers {
       let mut keys = Vec::new();
       let json = serde_json::json!(Config::default());
       get_keys(&json, &mut keys, None);
+      keys.extend(sazid::app::session_config::SESSION_OPTIONS.iter().map(|key| key.to_string()));
       keys
     });
 
@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use helix_core::{
+  textobject::{textobject_word, TextObject},
+  Position, Range, RopeSlice, Selection,
+};
+
+/// the longest pause between the clicks of a double or triple click
+pub const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// what a mouse selection grows by, picked by the number of clicks that started it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectUnit {
+  #[default]
+  Char,
+  Word,
+  Line,
+}
+
+impl SelectUnit {
+  /// the unit of `clicks` clicks, a fourth click starting over from a single char
+  pub fn from_clicks(clicks: u8) -> Self {
+    match clicks % 3 {
+      2 => SelectUnit::Word,
+      0 => SelectUnit::Line,
+      _ => SelectUnit::Char,
+    }
+  }
+
+  /// the range of the unit at `char_idx` in `text`
+  pub fn range_at(self, text: RopeSlice, char_idx: usize) -> Range {
+    match self {
+      SelectUnit::Char => Range::new(char_idx, (char_idx + 1).min(text.len_chars())),
+      SelectUnit::Word => {
+        textobject_word(text, Range::point(char_idx), TextObject::Inside, 1, false)
+      },
+      SelectUnit::Line => {
+        let line = text.char_to_line(char_idx);
+        Range::new(text.line_to_char(line), text.line_to_char(line + 1))
+      },
+    }
+  }
+}
+
+/// a left click, kept to count the clicks of a double or triple click
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Click {
+  pub at: Instant,
+  pub pos: Position,
+  pub count: u8,
+}
+
+impl Click {
+  /// a click at `pos`, counted as another click of `last` when it lands in the same cell soon
+  /// after it
+  pub fn after(last: Option<Click>, pos: Position, at: Instant) -> Self {
+    let count = match last {
+      Some(last) if last.pos == pos && at.duration_since(last.at) <= MULTI_CLICK_INTERVAL => {
+        last.count % 3 + 1
+      },
+      _ => 1,
+    };
+    Click { at, pos, count }
+  }
+}
+
+/// the selection from the unit a drag started on, `origin`, to the unit at `char_idx`. the
+/// selection keeps the whole of both, whichever way it was dragged
+pub fn drag_selection(
+  text: RopeSlice,
+  origin: Range,
+  unit: SelectUnit,
+  char_idx: usize,
+) -> Selection {
+  let target = unit.range_at(text, char_idx);
+  if target.from() < origin.from() {
+    Selection::single(origin.to(), target.from())
+  } else {
+    Selection::single(origin.from(), target.to().max(origin.to()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use helix_core::Rope;
+
+  #[test]
+  fn test_drag_selection_by_unit() {
+    let text = Rope::from("one two\nthree four\n");
+    let text = text.slice(..);
+
+    assert_eq!(SelectUnit::Word.range_at(text, 5), Range::new(4, 7));
+    assert_eq!(SelectUnit::Line.range_at(text, 10), Range::new(8, 19));
+
+    let origin = SelectUnit::Word.range_at(text, 5);
+    let forward = drag_selection(text, origin, SelectUnit::Word, 15);
+    assert_eq!(forward.primary(), Range::new(4, 18));
+    let backward = drag_selection(text, origin, SelectUnit::Word, 1);
+    assert_eq!(backward.primary(), Range::new(7, 0));
+
+    let origin = SelectUnit::Char.range_at(text, 2);
+    assert_eq!(drag_selection(text, origin, SelectUnit::Char, 5).primary(), Range::new(2, 6));
+  }
+
+  #[test]
+  fn test_click_counts() {
+    let now = Instant::now();
+    let pos = Position::new(3, 4);
+    let first = Click::after(None, pos, now);
+    let second = Click::after(Some(first), pos, now + Duration::from_millis(100));
+    let third = Click::after(Some(second), pos, now + Duration::from_millis(200));
+    assert_eq!((first.count, second.count, third.count), (1, 2, 3));
+    assert_eq!(SelectUnit::from_clicks(third.count), SelectUnit::Line);
+
+    let elsewhere = Click::after(Some(second), Position::new(3, 5), now);
+    assert_eq!(elsewhere.count, 1);
+    let late = Click::after(Some(first), pos, now + MULTI_CLICK_INTERVAL * 2);
+    assert_eq!(late.count, 1);
+  }
+}
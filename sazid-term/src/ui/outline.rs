@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use helix_lsp::lsp;
+use helix_view::graphics::{Margin, Rect};
+use helix_view::input::Event;
+use sazid::app::lsi::symbol_types::SourceSymbol;
+use tui::buffer::Buffer as Surface;
+use tui::widgets::{Block, Borders, Widget};
+
+use crate::compositor::{Callback, Component, Compositor, Context, EventResult};
+use crate::{ctrl, key};
+
+// the widest the symbol tree is drawn, the preview gets the rest
+const MAX_TREE_WIDTH: u16 = 48;
+const HELP: &str = "enter jump · tab fold · h/l collapse/expand · j/k move · esc close";
+
+// a symbol of the tree, in the order the tree lists them
+#[derive(Debug, Clone, PartialEq)]
+struct OutlineNode {
+  label: String,
+  depth: usize,
+  range: lsp::Range,
+  has_children: bool,
+}
+
+/// the symbol tree of a file beside a preview of its text. symbols with children can be folded,
+/// and enter scrolls the preview to the lines of the selected symbol
+pub struct FileOutline {
+  file_path: PathBuf,
+  lines: Vec<String>,
+  nodes: Vec<OutlineNode>,
+  // the nodes that are folded, by their index in `nodes`
+  collapsed: HashSet<usize>,
+  // the selected node, by its index in the visible nodes
+  cursor: usize,
+  preview_top: usize,
+  jumped: Option<lsp::Range>,
+}
+
+impl FileOutline {
+  pub const ID: &'static str = "file-outline";
+
+  pub fn new(file_path: PathBuf, text: &str, symbols: &[Arc<SourceSymbol>]) -> Self {
+    let mut nodes = vec![];
+    for symbol in symbols {
+      Self::push_node(&mut nodes, symbol, 0);
+    }
+    FileOutline {
+      file_path,
+      lines: text.lines().map(|line| line.replace('\t', "    ")).collect(),
+      nodes,
+      collapsed: HashSet::new(),
+      cursor: 0,
+      preview_top: 0,
+      jumped: None,
+    }
+  }
+
+  fn push_node(nodes: &mut Vec<OutlineNode>, symbol: &SourceSymbol, depth: usize) {
+    let children = symbol.children.lock().unwrap().clone();
+    let kind = format!("{:?}", symbol.kind).to_lowercase();
+    nodes.push(OutlineNode {
+      label: format!("{} {}", kind, symbol.name),
+      depth,
+      range: *symbol.range.lock().unwrap(),
+      has_children: !children.is_empty(),
+    });
+    for child in &children {
+      Self::push_node(nodes, child, depth + 1);
+    }
+  }
+
+  fn close() -> Option<Callback> {
+    Some(Box::new(|compositor: &mut Compositor, _| {
+      compositor.remove(Self::ID);
+    }))
+  }
+
+  // the nodes not inside a folded one, by their index in `nodes`
+  fn visible(&self) -> Vec<usize> {
+    let mut visible = vec![];
+    let mut folded_depth: Option<usize> = None;
+    for (idx, node) in self.nodes.iter().enumerate() {
+      if folded_depth.is_some_and(|depth| node.depth > depth) {
+        continue;
+      }
+      folded_depth = self.collapsed.contains(&idx).then_some(node.depth);
+      visible.push(idx);
+    }
+    visible
+  }
+
+  // h collapses an open symbol, or moves to the symbol it is in
+  fn collapse_or_parent(&mut self, visible: &[usize]) {
+    let Some(&idx) = visible.get(self.cursor) else {
+      return;
+    };
+    let node = &self.nodes[idx];
+    if node.has_children && !self.collapsed.contains(&idx) {
+      self.collapsed.insert(idx);
+    } else if let Some(parent) =
+      visible[..self.cursor].iter().rposition(|&other| self.nodes[other].depth < node.depth)
+    {
+      self.cursor = parent;
+    }
+  }
+
+  fn toggle(&mut self, idx: usize) {
+    if self.nodes[idx].has_children && !self.collapsed.remove(&idx) {
+      self.collapsed.insert(idx);
+    }
+  }
+}
+
+impl Component for FileOutline {
+  fn handle_event(&mut self, event: &Event, _cx: &mut Context) -> EventResult {
+    let Event::Key(key) = event else {
+      return EventResult::Ignored(None);
+    };
+    let visible = self.visible();
+    let last = visible.len().saturating_sub(1);
+    self.cursor = self.cursor.min(last);
+    let selected = visible.get(self.cursor).copied();
+    match *key {
+      key!(Esc) | key!('q') | ctrl!('c') => return EventResult::Consumed(Self::close()),
+      key!('j') | key!(Down) => self.cursor = (self.cursor + 1).min(last),
+      key!('k') | key!(Up) => self.cursor = self.cursor.saturating_sub(1),
+      key!('g') | key!(Home) => self.cursor = 0,
+      key!('G') | key!(End) => self.cursor = last,
+      key!('h') | key!(Left) => self.collapse_or_parent(&visible),
+      key!('l') | key!(Right) => {
+        if let Some(idx) = selected {
+          self.collapsed.remove(&idx);
+        }
+      },
+      key!(Tab) | key!(' ') => {
+        if let Some(idx) = selected {
+          self.toggle(idx);
+        }
+      },
+      key!(Enter) => {
+        if let Some(idx) = selected {
+          let range = self.nodes[idx].range;
+          self.preview_top = range.start.line as usize;
+          self.jumped = Some(range);
+        }
+      },
+      ctrl!('d') => {
+        self.preview_top = (self.preview_top + 10).min(self.lines.len().saturating_sub(1))
+      },
+      ctrl!('u') => self.preview_top = self.preview_top.saturating_sub(10),
+      _ => {},
+    }
+    EventResult::Consumed(None)
+  }
+
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let theme = &cx.editor.theme;
+    let text_style = theme.get("ui.text");
+    let selected_style = theme.get("ui.menu.selected");
+    let help_style = theme.get("ui.text.inactive");
+    let line_number_style = theme.get("ui.linenr");
+    let jumped_style = theme.get("ui.selection");
+
+    let area = area.inner(&Margin::all(2));
+    surface.clear_with(area, theme.get("ui.background"));
+    let title = format!("outline {}", self.file_path.display());
+    let block = Block::default().title(title.as_str()).borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+    if inner.height < 2 {
+      return;
+    }
+    let height = inner.height as usize - 1;
+    let tree_width = (inner.width * 2 / 5).min(MAX_TREE_WIDTH);
+
+    let visible = self.visible();
+    self.cursor = self.cursor.min(visible.len().saturating_sub(1));
+    if visible.is_empty() {
+      let message = "no symbols, the file may not be synced yet";
+      surface.set_stringn(inner.x, inner.y, message, tree_width as usize, help_style);
+    }
+    // the tree scrolls to keep the selected symbol on screen
+    let tree_top = (self.cursor + 1).saturating_sub(height);
+    for (row, &idx) in visible.iter().skip(tree_top).take(height).enumerate() {
+      let node = &self.nodes[idx];
+      let marker = match (node.has_children, self.collapsed.contains(&idx)) {
+        (false, _) => "  ",
+        (true, true) => "▸ ",
+        (true, false) => "▾ ",
+      };
+      let line = format!("{}{}{}", "  ".repeat(node.depth), marker, node.label);
+      let style = if tree_top + row == self.cursor { selected_style } else { text_style };
+      surface.set_stringn(inner.x, inner.y + row as u16, line, tree_width as usize, style);
+    }
+
+    let preview_x = inner.x + tree_width + 1;
+    let preview_width = inner.right().saturating_sub(preview_x) as usize;
+    let number_width = self.lines.len().to_string().len();
+    for (row, line) in self.lines.iter().enumerate().skip(self.preview_top).take(height) {
+      let y = inner.y + (row - self.preview_top) as u16;
+      let in_jumped = self
+        .jumped
+        .is_some_and(|range| (range.start.line..=range.end.line).contains(&(row as u32)));
+      let number = format!("{:>width$} ", row + 1, width = number_width);
+      surface.set_stringn(preview_x, y, &number, preview_width, line_number_style);
+      let text_x = preview_x + number.len() as u16;
+      let text_width = preview_width.saturating_sub(number.len());
+      let style = if in_jumped { text_style.patch(jumped_style) } else { text_style };
+      surface.set_stringn(text_x, y, line, text_width, style);
+    }
+    surface.set_stringn(inner.x, inner.bottom() - 1, HELP, inner.width as usize, help_style);
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_outline_folds_and_moves_to_parents() {
+    let symbol = |name: &str, kind: lsp::SymbolKind, line: u32| {
+      Arc::new(SourceSymbol {
+        name: name.to_string(),
+        kind,
+        range: Arc::new(std::sync::Mutex::new(lsp::Range::new(
+          lsp::Position::new(line, 0),
+          lsp::Position::new(line + 1, 0),
+        ))),
+        ..Default::default()
+      })
+    };
+    let app = symbol("App", lsp::SymbolKind::STRUCT, 0);
+    let run = symbol("run", lsp::SymbolKind::METHOD, 2);
+    app.children.lock().unwrap().push(run);
+    let main = symbol("main", lsp::SymbolKind::FUNCTION, 5);
+    let mut outline = FileOutline::new(PathBuf::from("src/app.rs"), "", &[app, main]);
+
+    let labels = outline.nodes.iter().map(|node| node.label.as_str()).collect::<Vec<_>>();
+    assert_eq!(labels, ["struct App", "method run", "function main"]);
+    assert_eq!(outline.visible(), [0, 1, 2]);
+
+    // h on a nested symbol moves to its parent, then folds it
+    outline.cursor = 1;
+    outline.collapse_or_parent(&[0, 1, 2]);
+    assert_eq!(outline.cursor, 0);
+    outline.collapse_or_parent(&[0, 1, 2]);
+    assert_eq!(outline.visible(), [0, 2]);
+    outline.toggle(0);
+    assert_eq!(outline.visible(), [0, 1, 2]);
+  }
+}
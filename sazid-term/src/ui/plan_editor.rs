@@ -0,0 +1,209 @@
+use async_openai::types::ChatCompletionRequestMessage;
+use helix_view::graphics::{Margin, Rect};
+use helix_view::input::Event;
+use sazid::app::messages::get_chat_message_text;
+use sazid::app::plan::{Plan, PlanState, PlanStep, StepState};
+use sazid::components::session::Session;
+use tui::buffer::Buffer as Surface;
+use tui::widgets::{Block, Borders, Widget};
+
+use crate::compositor::{Callback, Component, Compositor, Context, EventResult};
+use crate::ui::{Prompt, PromptEvent};
+use crate::{ctrl, key};
+
+// the widest the checklist is drawn
+const MAX_WIDTH: u16 = 100;
+// lines of the transcript shown under the selected step
+const MAX_TRANSCRIPT_LINES: usize = 6;
+const HELP: &str =
+  "space check · J/K move · e edit · o add · d delete · enter run the checked steps · esc close";
+
+/// the checklist of the session's plan. steps are checked, edited, added, removed and put in
+/// order before the plan is run, and the tool calls of the steps carried out are listed under
+/// them
+#[derive(Default)]
+pub struct PlanEditor {
+  cursor: usize,
+}
+
+impl PlanEditor {
+  pub const ID: &'static str = "plan-editor";
+
+  fn close() -> Option<Callback> {
+    Some(Box::new(|compositor: &mut Compositor, _| {
+      compositor.remove(Self::ID);
+    }))
+  }
+
+  // a prompt for the text of a step, which `apply` puts into the plan
+  fn step_prompt(
+    title: &'static str,
+    line: String,
+    apply: impl Fn(&mut Plan, String) + 'static,
+  ) -> Option<Callback> {
+    Some(Box::new(move |compositor: &mut Compositor, cx: &mut Context| {
+      let prompt = Prompt::new(
+        title.into(),
+        None,
+        |_, _| vec![],
+        move |cx: &mut Context, input: &str, event: PromptEvent| {
+          if event != PromptEvent::Validate || input.trim().is_empty() {
+            return;
+          }
+          if let Some(plan) = &mut cx.session.plan {
+            apply(plan, input.trim().to_string());
+          }
+        },
+      )
+      .with_line(line, cx.editor);
+      compositor.push(Box::new(prompt));
+    }))
+  }
+
+  // the tool calls a step made and the first line of each of their outputs
+  fn transcript(session: &Session, message_ids: &[i64]) -> Vec<String> {
+    let messages = session.messages.iter().filter(|m| message_ids.contains(&m.message_id));
+    let mut lines = vec![];
+    for message in messages {
+      match &message.message {
+        ChatCompletionRequestMessage::Assistant(assistant) => {
+          let calls = assistant.tool_calls.iter().flatten();
+          lines.extend(calls.map(|call| format!("→ {}", call.function.name)));
+        },
+        ChatCompletionRequestMessage::Tool(tool) => {
+          let output = tool.content.lines().find(|l| !l.trim().is_empty()).unwrap_or_default();
+          lines.push(format!("  {}", output.trim()));
+        },
+        message => {
+          let text = get_chat_message_text(message);
+          lines.extend(text.lines().find(|l| !l.trim().is_empty()).map(str::to_string));
+        },
+      }
+    }
+    lines
+  }
+
+  // handles a key on the checklist, the flag is set when the plan should run
+  fn handle_key(&mut self, event: &Event, plan: &mut Plan) -> (EventResult, bool) {
+    let Event::Key(key) = event else {
+      return (EventResult::Ignored(None), false);
+    };
+    let last = plan.steps.len().saturating_sub(1);
+    self.cursor = self.cursor.min(last);
+    let pending = plan.steps.get(self.cursor).is_some_and(|step| step.state == StepState::Pending);
+    match *key {
+      key!(Esc) | key!('q') | ctrl!('c') => return (EventResult::Consumed(Self::close()), false),
+      key!('j') | key!(Down) => self.cursor = (self.cursor + 1).min(last),
+      key!('k') | key!(Up) => self.cursor = self.cursor.saturating_sub(1),
+      key!('J') => self.cursor = plan.move_step(self.cursor, 1),
+      key!('K') => self.cursor = plan.move_step(self.cursor, -1),
+      key!(' ') if pending => plan.steps[self.cursor].checked ^= true,
+      key!('d') if pending => {
+        plan.steps.remove(self.cursor);
+      },
+      key!('e') if pending => {
+        let idx = self.cursor;
+        let line = plan.steps[idx].text.clone();
+        let edit = move |plan: &mut Plan, text| {
+          if let Some(step) = plan.steps.get_mut(idx) {
+            step.text = text;
+          }
+        };
+        return (EventResult::Consumed(Self::step_prompt("step: ", line, edit)), false);
+      },
+      key!('o') => {
+        let idx = (self.cursor + 1).min(plan.steps.len());
+        let add = move |plan: &mut Plan, text: String| {
+          let idx = idx.min(plan.steps.len());
+          plan.steps.insert(idx, PlanStep::new(&text));
+        };
+        return (EventResult::Consumed(Self::step_prompt("new step: ", String::new(), add)), false);
+      },
+      key!(Enter) if plan.state == PlanState::Review => {
+        return (EventResult::Consumed(Self::close()), true);
+      },
+      _ => {},
+    }
+    (EventResult::Consumed(None), false)
+  }
+}
+
+impl Component for PlanEditor {
+  fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+    let Some(plan) = &mut cx.session.plan else {
+      return EventResult::Consumed(Self::close());
+    };
+    let (result, run) = self.handle_key(event, plan);
+    if run {
+      match cx.session.execute_plan() {
+        Ok(()) => cx.editor.set_status("running the plan, :plan-review shows its progress"),
+        Err(e) => cx.editor.set_error(e.to_string()),
+      }
+    }
+    result
+  }
+
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let Some(plan) = &cx.session.plan else {
+      return;
+    };
+    let theme = &cx.editor.theme;
+    let text_style = theme.get("ui.text");
+    let selected_style = theme.get("ui.menu.selected");
+    let help_style = theme.get("ui.text.inactive");
+
+    let selected = plan.steps.get(self.cursor);
+    let transcript =
+      selected.map(|step| Self::transcript(cx.session, &step.message_ids)).unwrap_or_default();
+    let shown_transcript = transcript.len().min(MAX_TRANSCRIPT_LINES);
+    let width = area.width.saturating_sub(4).min(MAX_WIDTH);
+    let height = (plan.steps.len() + shown_transcript + 4).min(area.height as usize) as u16;
+    let area = Rect::new(
+      area.x + (area.width - width) / 2,
+      area.y + (area.height - height) / 2,
+      width,
+      height,
+    );
+    surface.clear_with(area, theme.get("ui.background"));
+
+    let state = match plan.state {
+      PlanState::Drafting => "drafting",
+      PlanState::Review => "review",
+      PlanState::Executing => "running",
+      PlanState::Done => "done",
+    };
+    let title = format!("plan ({}): {}", state, plan.goal.lines().next().unwrap_or_default());
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+
+    let transcript_width = inner.width.saturating_sub(4) as usize;
+    let mut y = inner.y;
+    for (idx, step) in plan.steps.iter().enumerate() {
+      if y >= inner.bottom().saturating_sub(1) {
+        break;
+      }
+      let mark = match (step.state, step.checked) {
+        (StepState::Done, _) => "✓",
+        (StepState::Running, _) => "▶",
+        (StepState::Pending, true) => "x",
+        (StepState::Pending, false) => " ",
+      };
+      let style = if idx == self.cursor { selected_style } else { text_style };
+      let line = format!("[{}] {}. {}", mark, idx + 1, step.text);
+      surface.set_stringn(inner.x, y, line, inner.width as usize, style);
+      y += 1;
+      if idx == self.cursor {
+        for line in transcript.iter().take(shown_transcript) {
+          surface.set_stringn(inner.x + 4, y, line, transcript_width, help_style);
+          y += 1;
+        }
+      }
+    }
+    surface.set_stringn(inner.x, inner.bottom() - 1, HELP, inner.width as usize, help_style);
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
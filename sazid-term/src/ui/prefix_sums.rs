@@ -0,0 +1,112 @@
+/// a list of lengths that can be changed and summed in O(log n), backed by a fenwick tree. the
+/// session keeps the length of each message's plain text in one, so message offsets in the
+/// transcript do not have to be recomputed when a message grows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrefixSums {
+  values: Vec<usize>,
+  // tree[i - 1] holds the sum of the lowbit(i) values ending at values[i - 1]
+  tree: Vec<usize>,
+}
+
+fn lowbit(i: usize) -> usize {
+  i & i.wrapping_neg()
+}
+
+impl PrefixSums {
+  pub fn from_values(values: Vec<usize>) -> Self {
+    let mut tree = values.clone();
+    for i in 1..=tree.len() {
+      let parent = i + lowbit(i);
+      if parent <= tree.len() {
+        tree[parent - 1] += tree[i - 1];
+      }
+    }
+    PrefixSums { values, tree }
+  }
+
+  pub fn len(&self) -> usize {
+    self.values.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.values.is_empty()
+  }
+
+  pub fn get(&self, idx: usize) -> usize {
+    self.values[idx]
+  }
+
+  pub fn push(&mut self, value: usize) {
+    let i = self.values.len() + 1;
+    // the new node covers the values after the node lowbit(i) before it
+    let covered = self.prefix(i - 1) - self.prefix(i - lowbit(i));
+    self.values.push(value);
+    self.tree.push(value + covered);
+  }
+
+  pub fn set(&mut self, idx: usize, value: usize) {
+    let old = std::mem::replace(&mut self.values[idx], value);
+    let mut i = idx + 1;
+    while i <= self.tree.len() {
+      self.tree[i - 1] = self.tree[i - 1] - old + value;
+      i += lowbit(i);
+    }
+  }
+
+  /// the sum of the first `idx` values
+  pub fn prefix(&self, idx: usize) -> usize {
+    let mut sum = 0;
+    let mut i = idx.min(self.tree.len());
+    while i > 0 {
+      sum += self.tree[i - 1];
+      i -= lowbit(i);
+    }
+    sum
+  }
+
+  pub fn total(&self) -> usize {
+    self.prefix(self.len())
+  }
+
+  /// the index of the value whose range contains `offset`, the last one for offsets past the
+  /// end
+  pub fn position(&self, offset: usize) -> Option<usize> {
+    if self.is_empty() {
+      return None;
+    }
+    // descend the tree, taking every node that still ends at or before offset
+    let mut pos = 0;
+    let mut remaining = offset;
+    let mut step = self.tree.len().next_power_of_two();
+    while step > 0 {
+      if pos + step <= self.tree.len() && self.tree[pos + step - 1] <= remaining {
+        pos += step;
+        remaining -= self.tree[pos - 1];
+      }
+      step /= 2;
+    }
+    Some(pos.min(self.len() - 1))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_prefix_sums() {
+    let mut sums = PrefixSums::from_values(vec![3, 5, 2]);
+    sums.push(4);
+    sums.push(1);
+    assert_eq!((0..=5).map(|i| sums.prefix(i)).collect::<Vec<_>>(), vec![0, 3, 8, 10, 14, 15]);
+
+    sums.set(1, 1);
+    assert_eq!(sums.prefix(2), 4);
+    assert_eq!(sums.total(), 11);
+    assert_eq!(sums.position(0), Some(0));
+    assert_eq!(sums.position(3), Some(1));
+    assert_eq!(sums.position(4), Some(2));
+    assert_eq!(sums.position(100), Some(4));
+    assert_eq!(PrefixSums::default().position(0), None);
+  }
+}
@@ -36,6 +36,8 @@ pub struct Prompt {
   pub doc_fn: DocFn,
   next_char_handler: Option<PromptCharHandler>,
   language: Option<(&'static str, Arc<ArcSwap<syntax::Loader>>)>,
+  // the input is drawn as `*`, for secrets
+  masked: bool,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -88,6 +90,7 @@ impl Prompt {
       doc_fn: Box::new(|_| None),
       next_char_handler: None,
       language: None,
+      masked: false,
     }
   }
 
@@ -108,6 +111,12 @@ impl Prompt {
     self
   }
 
+  /// hides the input, for secrets like api keys
+  pub fn with_masked(mut self) -> Self {
+    self.masked = true;
+    self
+  }
+
   pub fn line(&self) -> &String {
     &self.line
   }
@@ -462,6 +471,9 @@ impl Prompt {
       {
         surface.set_string(line_area.x, line_area.y, suggestion, suggestion_color);
       }
+    } else if self.masked {
+      let masked = "*".repeat(self.line.chars().count());
+      surface.set_string(line_area.x, line_area.y, masked, prompt_color);
     } else if let Some((language, loader)) = self.language.as_ref() {
       let mut text: ui::text::Text = crate::ui::markdown::highlighted_code_block(
         &self.line,
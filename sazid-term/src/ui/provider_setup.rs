@@ -0,0 +1,115 @@
+use helix_view::graphics::{Margin, Rect};
+use helix_view::input::Event;
+use sazid::app::credentials;
+use tui::buffer::Buffer as Surface;
+use tui::widgets::{Block, Borders, Widget};
+
+use crate::compositor::{Callback, Component, Compositor, Context, EventResult};
+use crate::job;
+use crate::ui::{Prompt, PromptEvent, SessionTabs};
+use crate::{ctrl, key};
+
+// the widest the screen is drawn
+const MAX_WIDTH: u16 = 72;
+const LINES: &[&str] = &[
+  "No OpenAI api key is configured, so replies can not be requested.",
+  "",
+  "Set OPENAI_API_KEY, add openai.api_key to the config file, or enter a key",
+  "here to store it in the os keyring.",
+  "",
+  "Without a key, saved sessions can still be browsed and the local tools used.",
+  "",
+  "enter: enter a key · esc: continue offline · :login shows the key prompt",
+];
+
+/// a prompt for a provider's api key, stored in the os keyring. the open sessions look it up
+/// again once it is stored
+pub fn api_key_prompt(provider: &'static str) -> Prompt {
+  Prompt::new(
+    format!("{} api key: ", provider).into(),
+    None,
+    |_, _| vec![],
+    move |cx: &mut Context, input: &str, event: PromptEvent| {
+      if event != PromptEvent::Validate || input.trim().is_empty() {
+        return;
+      }
+      if let Err(e) = credentials::store(provider, input) {
+        cx.editor.set_error(format!("unable to store the {} api key: {}", provider, e));
+        return;
+      }
+      cx.session.reload_credentials();
+      cx.jobs.callback(async move {
+        let call: job::Callback =
+          job::Callback::EditorCompositor(Box::new(|_editor, compositor: &mut Compositor| {
+            if let Some(tabs) = compositor.find::<SessionTabs>() {
+              tabs.background_sessions_mut().for_each(|session| session.reload_credentials());
+            }
+          }));
+        Ok(call)
+      });
+      cx.editor.set_status(format!("stored the {} api key in the os keyring", provider));
+    },
+  )
+  .with_masked()
+}
+
+/// shown at startup when requests can not be sent for want of an api key
+pub struct ProviderSetup;
+
+impl ProviderSetup {
+  pub const ID: &'static str = "provider-setup";
+
+  fn close() -> Callback {
+    Box::new(|compositor: &mut Compositor, _| {
+      compositor.remove(Self::ID);
+    })
+  }
+}
+
+impl Component for ProviderSetup {
+  fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+    let Event::Key(key) = event else {
+      return EventResult::Ignored(None);
+    };
+    match *key {
+      key!(Enter) => EventResult::Consumed(Some(Box::new(|compositor: &mut Compositor, _| {
+        compositor.remove(Self::ID);
+        compositor.push(Box::new(api_key_prompt("openai")));
+      }))),
+      key!(Esc) | key!('q') | ctrl!('c') => {
+        cx.editor.set_status("running offline, :login stores an api key");
+        EventResult::Consumed(Some(Self::close()))
+      },
+      _ => EventResult::Consumed(None),
+    }
+  }
+
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let theme = &cx.editor.theme;
+    let text_style = theme.get("ui.text");
+    let help_style = theme.get("ui.text.inactive");
+
+    let width = area.width.saturating_sub(4).min(MAX_WIDTH + 4);
+    let height = (LINES.len() as u16 + 2).min(area.height);
+    let area = Rect::new(
+      area.x + (area.width - width) / 2,
+      area.y + (area.height - height) / 2,
+      width,
+      height,
+    );
+    surface.clear_with(area, theme.get("ui.background"));
+    let block = Block::default().title("setup").borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+
+    let last = LINES.len() - 1;
+    for (idx, (y, line)) in (inner.y..inner.bottom()).zip(LINES).enumerate() {
+      let style = if idx == last { help_style } else { text_style };
+      surface.set_stringn(inner.x, y, line, inner.width as usize, style);
+    }
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
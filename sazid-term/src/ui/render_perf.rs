@@ -0,0 +1,126 @@
+use std::{collections::VecDeque, str::FromStr, time::Duration};
+
+// frames averaged when deciding whether the terminal is slow
+const SAMPLE_COUNT: usize = 20;
+// draw times above this mean output is backing up, e.g. over a high latency ssh connection
+const SLOW_FRAME: Duration = Duration::from_millis(30);
+// draw times have to fall well below the slow threshold before leaving degraded mode, so the
+// mode does not flap when frames get cheaper because less is being drawn
+const FAST_FRAME: Duration = Duration::from_millis(10);
+const NORMAL_REDRAW_INTERVAL: Duration = Duration::ZERO;
+const DEGRADED_REDRAW_INTERVAL: Duration = Duration::from_millis(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PerfMode {
+  /// degrade when draws are slow
+  #[default]
+  Auto,
+  Degraded,
+  Normal,
+}
+
+impl FromStr for PerfMode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "auto" => Ok(PerfMode::Auto),
+      "on" | "degraded" => Ok(PerfMode::Degraded),
+      "off" | "normal" => Ok(PerfMode::Normal),
+      _ => anyhow::bail!("unknown perf mode {}, expected on, off or auto", s),
+    }
+  }
+}
+
+/// tracks how long frames take to draw, and decides when to reduce rendering work so the ui
+/// stays usable on slow terminals
+#[derive(Debug, Clone, Default)]
+pub struct RenderPerf {
+  pub mode: PerfMode,
+  samples: VecDeque<Duration>,
+  slow_terminal: bool,
+}
+
+impl RenderPerf {
+  /// records the draw time of a frame, returns true if degraded mode was entered or left
+  pub fn record_frame(&mut self, draw_time: Duration) -> bool {
+    if self.samples.len() == SAMPLE_COUNT {
+      self.samples.pop_front();
+    }
+    self.samples.push_back(draw_time);
+    if self.samples.len() < SAMPLE_COUNT {
+      return false;
+    }
+
+    let was_degraded = self.is_degraded();
+    let average = self.samples.iter().sum::<Duration>() / self.samples.len() as u32;
+    if average > SLOW_FRAME {
+      self.slow_terminal = true;
+    } else if average < FAST_FRAME {
+      self.slow_terminal = false;
+    }
+    if was_degraded != self.is_degraded() {
+      log::info!("average frame draw time {:?}, degraded mode: {}", average, self.is_degraded());
+      true
+    } else {
+      false
+    }
+  }
+
+  pub fn is_degraded(&self) -> bool {
+    match self.mode {
+      PerfMode::Auto => self.slow_terminal,
+      PerfMode::Degraded => true,
+      PerfMode::Normal => false,
+    }
+  }
+
+  /// the minimum time between frames, redraws requested sooner are coalesced
+  pub fn redraw_interval(&self) -> Duration {
+    if self.is_degraded() {
+      DEGRADED_REDRAW_INTERVAL
+    } else {
+      NORMAL_REDRAW_INTERVAL
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_degrades_on_slow_frames_and_recovers() {
+    let mut perf = RenderPerf::default();
+    for _ in 0..SAMPLE_COUNT - 1 {
+      assert!(!perf.record_frame(Duration::from_millis(50)));
+    }
+    assert!(perf.record_frame(Duration::from_millis(50)));
+    assert!(perf.is_degraded());
+    assert_eq!(perf.redraw_interval(), DEGRADED_REDRAW_INTERVAL);
+
+    // frames in between the thresholds keep the current mode
+    for _ in 0..SAMPLE_COUNT {
+      perf.record_frame(Duration::from_millis(20));
+    }
+    assert!(perf.is_degraded());
+
+    for _ in 0..SAMPLE_COUNT {
+      perf.record_frame(Duration::from_millis(2));
+    }
+    assert!(!perf.is_degraded());
+  }
+
+  #[test]
+  fn test_manual_mode_overrides_timing() {
+    let mut perf = RenderPerf { mode: PerfMode::Normal, ..Default::default() };
+    for _ in 0..SAMPLE_COUNT {
+      perf.record_frame(Duration::from_millis(50));
+    }
+    assert!(!perf.is_degraded());
+    perf.mode = PerfMode::Degraded;
+    assert!(perf.is_degraded());
+    perf.mode = "auto".parse().unwrap();
+    assert!(perf.is_degraded());
+  }
+}
@@ -0,0 +1,217 @@
+// the session and its table rendered into a buffer at fixed sizes, with the cells snapshotted so
+// layout changes show up as snapshot diffs. new and changed snapshots are reviewed with
+// `cargo insta review`
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use async_openai::types::{
+  ChatCompletionRequestMessage, ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
+  ChatCompletionRequestUserMessageContent, Role,
+};
+use helix_core::{syntax, Selection};
+use helix_view::{graphics::Rect, theme::Style, Theme};
+use insta::assert_snapshot;
+use sazid::components::session::Session;
+use tui::{buffer::Buffer, layout::Constraint};
+
+use super::session::SessionView;
+use super::SessionStatusline;
+use crate::commands::ChatMessageItem;
+use crate::widgets::table::{MessageCell, MessageType, Row, Table, TableState};
+
+const WIDTHS: [Constraint; 2] = [Constraint::Length(3), Constraint::Min(10)];
+
+fn syn_loader() -> Arc<ArcSwap<syntax::Loader>> {
+  Arc::new(ArcSwap::from_pointee(helix_core::config::default_lang_loader()))
+}
+
+// the text of each line of the buffer, followed by a line marking the cells drawn with the
+// background of `highlight` when it has any
+fn buffer_text(buf: &Buffer, highlight: Style) -> String {
+  let mut text = String::new();
+  for y in buf.area.top()..buf.area.bottom() {
+    let (mut line, mut marks) = (String::new(), String::new());
+    for x in buf.area.left()..buf.area.right() {
+      let cell = &buf[(x, y)];
+      line.push_str(&cell.symbol);
+      marks.push(if highlight.bg == Some(cell.bg) { '^' } else { ' ' });
+    }
+    text.push_str(line.trim_end());
+    text.push('\n');
+    if marks.contains('^') {
+      text.push_str(marks.trim_end());
+      text.push('\n');
+    }
+  }
+  text
+}
+
+fn text_rows(texts: &[&str]) -> Vec<Row<'static>> {
+  texts
+    .iter()
+    .enumerate()
+    .map(|(i, text)| {
+      Row::new(vec![
+        MessageCell::new(MessageType::Text(i.to_string())),
+        MessageCell::new(MessageType::Text(text.to_string())),
+      ])
+    })
+    .collect()
+}
+
+fn render_table(table: Table, width: u16, height: u16, state: &mut TableState) -> String {
+  let theme = Theme::default();
+  let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+  table.highlight_style(theme.get("ui.selection")).highlight_symbol(" > ").render_table(
+    buf.area,
+    &mut buf,
+    state,
+    false,
+    &theme,
+    &syn_loader(),
+  );
+  buffer_text(&buf, theme.get("ui.selection"))
+}
+
+fn user_message(id: i64, text: &str) -> ChatMessageItem {
+  ChatMessageItem::new_chat(
+    id,
+    ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+      content: ChatCompletionRequestUserMessageContent::Text(text.to_string()),
+      role: Role::User,
+      name: None,
+    }),
+  )
+}
+
+fn tool_message(id: i64, content: String) -> ChatMessageItem {
+  ChatMessageItem::new_chat(
+    id,
+    ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+      role: Role::Tool,
+      content,
+      tool_call_id: "call_1".to_string(),
+    }),
+  )
+}
+
+fn session(messages: Vec<ChatMessageItem>) -> SessionView<ChatMessageItem> {
+  let mut session =
+    SessionView::new(vec![], Some(Theme::default()), String::new(), syn_loader(), |_, _, _| {});
+  for message in messages {
+    session.upsert_message(message);
+  }
+  session
+}
+
+fn render_session(session: &mut SessionView<ChatMessageItem>, width: u16, height: u16) -> String {
+  let theme = Theme::default();
+  let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+  // the first render lays out the chat column, the messages are wrapped to it for the second
+  session.render_messages(buf.area, &mut buf, &theme);
+  session.wrap_messages();
+  session.render_messages(buf.area, &mut buf, &theme);
+  buffer_text(&buf, theme.get("ui.selection"))
+}
+
+#[test]
+fn table_wraps_long_cells() {
+  let rows = text_rows(&["short", "a cell with more words than fit on one line of the table"]);
+  let mut state = TableState::default();
+  assert_snapshot!(render_table(Table::new(rows).widths(&WIDTHS), 24, 8, &mut state));
+}
+
+#[test]
+fn table_row_spacing() {
+  let rows = text_rows(&["one", "two\nlines", "three"]);
+  let table = Table::new(rows).widths(&WIDTHS).row_spacing(1);
+  assert_snapshot!(render_table(table, 24, 8, &mut TableState::default()));
+}
+
+#[test]
+fn table_scrolled_into_a_row() {
+  let rows = text_rows(&["one\ntwo\nthree", "four\nfive", "six"]);
+  let mut state = TableState { vertical_scroll: 2, ..TableState::default() };
+  assert_snapshot!(render_table(Table::new(rows).widths(&WIDTHS), 24, 4, &mut state));
+}
+
+#[test]
+fn table_selected_row() {
+  let rows = text_rows(&["one", "two", "three"]);
+  let mut state = TableState { selected: Some(1), ..TableState::default() };
+  assert_snapshot!(render_table(Table::new(rows).widths(&WIDTHS), 24, 4, &mut state));
+}
+
+#[test]
+fn session_wraps_messages_to_width() {
+  let text = "a message long enough to wrap over several lines of a narrow session";
+  let mut session = session(vec![user_message(1, text), user_message(2, "short")]);
+  assert_snapshot!("session_wraps_messages_to_width_40", render_session(&mut session, 40, 12));
+  assert_snapshot!("session_wraps_messages_to_width_60", render_session(&mut session, 60, 12));
+}
+
+#[test]
+fn session_row_spacing() {
+  let messages = vec![user_message(1, "one"), user_message(2, "two"), user_message(3, "three")];
+  let mut session = session(messages);
+  session.table_row_spacing = 0;
+  session.rebuild_messages_plaintext();
+  assert_snapshot!("session_row_spacing_0", render_session(&mut session, 40, 8));
+  session.table_row_spacing = 2;
+  session.rebuild_messages_plaintext();
+  assert_snapshot!("session_row_spacing_2", render_session(&mut session, 40, 12));
+}
+
+#[test]
+fn session_scrolling() {
+  let messages = (1..=8).map(|id| user_message(id, &format!("message {}", id))).collect();
+  let mut session = session(messages);
+  // new sessions stick to their last message
+  assert_snapshot!("session_scrolling_bottom", render_session(&mut session, 40, 8));
+  session.state.scroll_top();
+  assert_snapshot!("session_scrolling_top", render_session(&mut session, 40, 8));
+}
+
+#[test]
+fn session_scroll_position() {
+  let messages = (1..=8).map(|id| user_message(id, &format!("message {}", id))).collect();
+  let mut session = session(messages);
+  let first_line = |text: String| text.lines().next().unwrap().to_string();
+  assert!(first_line(render_session(&mut session, 40, 8)).ends_with(" message 8/8 ┐"));
+  session.state.scroll_top();
+  assert!(first_line(render_session(&mut session, 40, 8)).ends_with(" message 3/8 ┐"));
+  session.state.select(Some(4));
+  assert!(first_line(render_session(&mut session, 40, 8)).ends_with(" message 5/8 ┐"));
+}
+
+#[test]
+fn session_folds() {
+  let lines = (1..=30).map(|i| format!("line {}\n", i)).collect();
+  let mut session = session(vec![user_message(1, "read a file"), tool_message(2, lines)]);
+  assert_snapshot!("session_folds_folded", render_session(&mut session, 40, 10));
+  session.selection = Selection::point(session.message_start(1));
+  assert_eq!(session.toggle_fold_at_cursor(), Some(false));
+  session.state.scroll_top();
+  assert_snapshot!("session_folds_unfolded", render_session(&mut session, 40, 10));
+}
+
+#[test]
+fn session_selection_highlight() {
+  let mut session = session(vec![user_message(1, "select some of this text")]);
+  session.selection = Selection::single(7, 11);
+  assert_snapshot!("session_selection_highlight_text", render_session(&mut session, 40, 6));
+  session.state.select(Some(0));
+  assert_snapshot!("session_selection_highlight_row", render_session(&mut session, 40, 6));
+}
+
+#[test]
+fn session_statusline() {
+  let mut session = Session::default();
+  let model = session.config.model.clone();
+  session.usage.record(&model, 12_000, 800);
+  session.context_tokens = 12_000;
+  let theme = Theme::default();
+  let mut buf = Buffer::empty(Rect::new(0, 0, 80, 1));
+  SessionStatusline::new(10).render_statusline(buf.area, &mut buf, &theme, &session);
+  assert_snapshot!(buffer_text(&buf, theme.get("ui.selection")));
+}
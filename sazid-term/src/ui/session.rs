@@ -1,14 +1,16 @@
 use crate::{
-  commands::ChatMessageItem,
+  commands::{open_session_store, ChatMessageItem},
   compositor::{self, Component, Compositor, Context, ContextFocus, Event, EventResult},
-  filter_picker_entry,
-  job::Callback,
+  job::{Callback, Jobs},
   movement::min_width_1,
   ui::{
     document::{render_document, LineDecoration, LinePos, TextRenderer},
-    EditorView,
+    ChatRole, EditorView,
+  },
+  widgets::{
+    diff::{DiffStatus, FileDiff},
+    table::{MessageCell, MessageType, Row, Table, TableState},
   },
-  widgets::table::{MessageCell, MessageType, Row, Table, TableState},
 };
 
 use arc_swap::ArcSwap;
@@ -39,25 +41,52 @@ use helix_core::{
   movement::Direction,
   syntax::{self, Highlight, HighlightEvent},
   text_annotations::TextAnnotations,
+  unicode::width::UnicodeWidthStr,
   Position, Rope, RopeSlice, Selection, Syntax,
 };
 
 use helix_view::{
   document::Mode,
   editor::{Action, CursorShapeConfig},
-  graphics::{CursorKind, Margin, Modifier, Rect, UnderlineStyle},
+  graphics::{CursorKind, Margin, Rect, UnderlineStyle},
   input::{MouseButton, MouseEventKind},
   theme::{Color, Style},
   view::ViewPosition,
-  Document, DocumentId, Editor, Theme,
+  Document, DocumentId, Editor, Theme, ViewId,
 };
 
 pub const ID: &str = "session";
-use super::{markdownmenu::MarkdownItem, overlay::Overlay, Picker};
+use super::{
+  diagrams::{Diagram, DiagramKind, InlineDiagrams},
+  markdownmenu::MarkdownItem,
+  mouse_select::{drag_selection, Click, SelectUnit},
+  overlay::Overlay,
+  prefix_sums::PrefixSums,
+  render_perf::RenderPerf,
+  split_view::split_columns,
+  Picker,
+};
+use sazid::app::audit_log::AuditEntry;
+use sazid::app::file_patch::FileEdit;
+use sazid::app::mentions::{parse_mentions, Mention};
+use sazid::app::session_config::{GutterConfig, GutterRole};
+use sazid::app::session_store::{MessageSearchResult, SessionSummary};
+use sazid::app::snippets::snippet_chip;
 
 pub const MIN_AREA_WIDTH_FOR_PREVIEW: u16 = 72;
 /// Biggest file size to preview in bytes
 pub const MAX_FILE_SIZE_FOR_PREVIEW: u64 = 10 * 1024 * 1024;
+/// the pinned files panel is only shown when the session is wider than this
+pub const MIN_AREA_WIDTH_FOR_PINNED_FILES: u16 = 80;
+pub const MAX_PINNED_FILES_WIDTH: u16 = 40;
+// queued inputs past this many are counted in the title of the queue instead of listed
+const MAX_QUEUED_INPUTS_SHOWN: usize = 3;
+/// Messages outside the viewport that are still rendered on either side of it
+const MESSAGE_OVERSCAN: usize = 2;
+// model names in the gutter are cut off after this many columns
+const MAX_GUTTER_MODEL_WIDTH: usize = 12;
+// the width of a timestamp in the gutter, `%H:%M`
+const GUTTER_TIMESTAMP_WIDTH: usize = 5;
 
 #[derive(PartialEq, Eq, Hash)]
 pub enum PathOrId {
@@ -176,6 +205,8 @@ pub struct SessionView<T: MarkdownItem> {
   terminal_focused: bool,
   session_is_focused: bool,
   selected_option: u32,
+  /// whether the matcher was still matching messages when last ticked
+  matcher_running: bool,
   syn_loader: Arc<ArcSwap<syntax::Loader>>,
   // textbox: ui::textbox::Textbox,
   pub input: EditorView,
@@ -199,7 +230,27 @@ pub struct SessionView<T: MarkdownItem> {
   /// Given an item in the session, return the file path and line number to display.
   file_fn: Option<FileCallback<T>>,
   messages_plaintext: Rope,
+  /// chars each message takes up in messages_plaintext, for the offsets of messages in it
+  message_offsets: PrefixSums,
+  /// the height of each message's row with its spacing, updated with `message_offsets` when a
+  /// message changes or is wrapped again rather than on every render
+  row_heights: Vec<u16>,
   updating_system_prompt: bool,
+  pub perf: RenderPerf,
+  /// the theme messages were last rendered with, they are rendered again when it changes
+  theme: Option<Theme>,
+  /// the width messages are being wrapped to in the background, if a wrap job is running
+  pending_wrap_width: Option<u16>,
+  /// what the column beside the messages shows, taken from the session config
+  pub gutter: GutterConfig,
+  /// the view of a file shown beside the session, see `ui::split_view`
+  pub split_view: Option<ViewId>,
+  /// the rows above the session taken by the tab bar, see `ui::session_tabs`
+  pub tab_bar_height: u16,
+  last_click: Option<Click>,
+  // the unit under the click that started a drag, and what the drag grows the selection by
+  drag_origin: Option<(helix_core::Range, SelectUnit)>,
+  diagrams: Option<InlineDiagrams>,
 }
 
 impl<T: MarkdownItem + 'static> SessionView<T> {
@@ -249,7 +300,7 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
 
   fn with(
     matcher: Nucleo<T>,
-    _theme: Option<Theme>,
+    theme: Option<Theme>,
     editor_data: Arc<T::Data>,
     shutdown: Arc<AtomicBool>,
     syn_loader: Arc<ArcSwap<syntax::Loader>>,
@@ -279,6 +330,7 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
       session_is_focused: false,
       terminal_focused: true,
       selected_option: 0,
+      matcher_running: false,
       line_char_counts: Vec::new(),
       state: tablestate,
       input,
@@ -298,7 +350,18 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
       file_fn: None,
       selection: Selection::point(0),
       messages_plaintext: Rope::new(),
+      message_offsets: PrefixSums::default(),
+      row_heights: Vec::new(),
+      perf: RenderPerf::default(),
+      theme,
+      pending_wrap_width: None,
       updating_system_prompt: false,
+      gutter: GutterConfig::default(),
+      split_view: None,
+      tab_bar_height: 0,
+      last_click: None,
+      drag_origin: None,
+      diagrams: None,
     }
   }
 
@@ -306,63 +369,410 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
     self.messages_plaintext.slice(..)
   }
 
-  pub fn update_messages_plaintext(&mut self) -> Rope {
-    let newlines_per_messages = 1 + self.table_row_spacing as usize;
-    if self.messages_plaintext.len_chars()
-      != self
-        .messages
-        .iter()
-        .map(|m| m.plain_text.len_chars() + newlines_per_messages)
-        .sum::<usize>()
-    {
-      // log::warn!(
-      //   "updating messages plaintext {}  {} {} ",
-      //   self.messages_plaintext.len_chars(),
-      //   self.messages.iter().map(|m| m.plain_text.len_chars()).sum::<usize>(),
-      //   self
-      //     .messages
-      //     .iter()
-      //     .map(|m| m.plain_text.len_chars() + newlines_per_messages)
-      //     .sum::<usize>()
-      // );
-      self.messages_plaintext = Rope::new();
-      self.messages.iter_mut().for_each(|message| {
-        message.start_idx = self.messages_plaintext.len_chars();
-        self.messages_plaintext.append(message.plain_text.clone());
-        self.messages_plaintext.append(Rope::from("\n".repeat(newlines_per_messages)));
-      });
+  /// chars a message takes up in the transcript, its plain text and the rows between messages
+  fn message_plaintext_len(&self, message: &ChatMessageItem) -> usize {
+    message.plain_text.len_chars() + 1 + self.table_row_spacing as usize
+  }
+
+  fn message_row_height(&self, message: &ChatMessageItem) -> u16 {
+    message.plain_text.len_lines() as u16 + self.table_row_spacing
+  }
+
+  /// the char index in the transcript where the message at `idx` starts
+  pub fn message_start(&self, idx: usize) -> usize {
+    self.message_offsets.prefix(idx)
+  }
+
+  /// the index of the message containing `char_idx` in the transcript
+  fn message_idx_at(&self, char_idx: usize) -> Option<usize> {
+    self.message_offsets.position(char_idx)
+  }
+
+  /// the transcript char drawn at a screen position in the chat column. the rows between messages
+  /// belong to the message above them and map to its last char
+  fn char_idx_at(&self, pos: Position) -> Option<usize> {
+    let text = self.get_messages_plaintext();
+    let char_idx = crate::movement::translate_pos_to_char_index(
+      &text,
+      self.chat_viewport,
+      self.state.vertical_scroll,
+      pos,
+    )?;
+    let Some(idx) = self.message_idx_at(char_idx) else {
+      return Some(char_idx);
     };
-    self.messages_plaintext.clone()
+    let text_end = self.message_start(idx) + self.messages[idx].plain_text.len_chars();
+    Some(char_idx.min(text_end.saturating_sub(1).max(self.message_start(idx))))
   }
 
-  pub fn upsert_message(&mut self, message: ChatMessageItem) {
-    if let Some(existing_message) =
-      self.messages.iter_mut().find(|m| m.id.is_some() && m.id == message.id)
-    {
-      existing_message.update_message(message.chat_message);
-      existing_message.cache_wrapped_plain_text(self.chat_viewport.width, &self.syn_loader);
-      self.update_messages_plaintext();
+  /// builds the transcript from every message's plain text
+  pub fn rebuild_messages_plaintext(&mut self) {
+    let separator = Rope::from("\n".repeat(1 + self.table_row_spacing as usize));
+    self.messages_plaintext = Rope::new();
+    for message in self.messages.iter() {
+      self.messages_plaintext.append(message.plain_text.clone());
+      self.messages_plaintext.append(separator.clone());
+    }
+    self.message_offsets = PrefixSums::from_values(
+      self.messages.iter().map(|message| self.message_plaintext_len(message)).collect(),
+    );
+    self.row_heights =
+      self.messages.iter().map(|message| self.message_row_height(message)).collect();
+  }
+
+  /// wraps every message to the chat viewport now, rather than on a worker
+  pub(crate) fn wrap_messages(&mut self) {
+    self.messages.iter_mut().for_each(|message| {
+      message.cache_wrapped_plain_text(
+        self.chat_viewport.width,
+        self.theme.as_ref(),
+        &self.syn_loader,
+      );
+    });
+    self.rebuild_messages_plaintext();
+  }
+
+  /// replaces the transcript text of the message at `idx` with its current plain text, or
+  /// appends it if it is a new last message
+  fn splice_message_plaintext(&mut self, idx: usize) {
+    let start = self.message_start(idx);
+    let old_len = match idx < self.message_offsets.len() {
+      true => self.message_offsets.get(idx),
+      false => 0,
+    };
+    let tail = self.messages_plaintext.split_off(start + old_len);
+    self.messages_plaintext.remove(start..);
+    self.messages_plaintext.append(self.messages[idx].plain_text.clone());
+    self.messages_plaintext.append(Rope::from("\n".repeat(1 + self.table_row_spacing as usize)));
+    self.messages_plaintext.append(tail);
+
+    let len = self.message_plaintext_len(&self.messages[idx]);
+    match idx < self.message_offsets.len() {
+      true => self.message_offsets.set(idx, len),
+      false => self.message_offsets.push(len),
+    }
+    let height = self.message_row_height(&self.messages[idx]);
+    match idx < self.row_heights.len() {
+      true => self.row_heights[idx] = height,
+      false => self.row_heights.push(height),
+    }
+  }
+
+  /// wraps the messages that are not wrapped to `width` on a worker. they are rendered with
+  /// their previous layout until it finishes
+  fn wrap_messages_in_background(&mut self, width: u16, jobs: &mut Jobs) {
+    let messages = self
+      .messages
+      .iter()
+      .enumerate()
+      .filter(|(_, message)| message.plaintext_wrapped_width != width)
+      .map(|(idx, message)| (idx, message.clone()))
+      .collect::<Vec<_>>();
+    if messages.is_empty() {
+      return;
+    }
+    self.pending_wrap_width = Some(width);
+    let theme = self.theme.clone();
+    let syn_loader = self.syn_loader.clone();
+    jobs.callback(async move {
+      let wrapped = tokio::task::spawn_blocking(move || {
+        messages
+          .into_iter()
+          .map(|(idx, mut message)| {
+            message.cache_wrapped_plain_text(width, theme.as_ref(), &syn_loader);
+            (idx, message)
+          })
+          .collect::<Vec<_>>()
+      })
+      .await;
+      let callback = move |_editor: &mut Editor, compositor: &mut Compositor| {
+        let Some(session) = compositor.find::<Self>() else {
+          return;
+        };
+        match wrapped {
+          Ok(wrapped) => session.apply_wrapped_messages(width, wrapped),
+          // the messages are wrapped again by the next render
+          Err(e) => {
+            log::error!("failed to wrap messages to width {}: {}", width, e);
+            if session.pending_wrap_width == Some(width) {
+              session.pending_wrap_width = None;
+            }
+          },
+        }
+      };
+      Ok(Callback::EditorCompositor(Box::new(callback)))
+    });
+  }
+
+  /// replaces messages with their layouts wrapped in the background, unless they changed or the
+  /// session was resized again while they were wrapped
+  fn apply_wrapped_messages(&mut self, width: u16, wrapped: Vec<(usize, ChatMessageItem)>) {
+    if self.pending_wrap_width == Some(width) {
+      self.pending_wrap_width = None;
+    }
+    if width != self.chat_viewport.width {
+      return;
+    }
+    for (idx, message) in wrapped {
+      let Some(current) = self.messages.get_mut(idx) else {
+        continue;
+      };
+      // messages that changed were already wrapped again when they were updated
+      if current.id == message.id
+        && current.chat_message == message.chat_message
+        && current.folded == message.folded
+      {
+        *current = message;
+      }
+    }
+    self.rebuild_messages_plaintext();
+    helix_event::request_redraw();
+  }
+
+  pub fn upsert_message(&mut self, mut message: ChatMessageItem) {
+    if let Some(idx) = self.messages.iter().position(|m| m.id.is_some() && m.id == message.id) {
+      self.messages[idx].update_message(message.chat_message);
+      // an update for a message the session no longer has does not know its model
+      if message.model.is_some() {
+        self.messages[idx].model = message.model;
+      }
+      if self.messages[idx].folded.is_some() {
+        self.messages[idx].folded = Some(self.messages[idx].fold_summary(&self.messages));
+      }
+      self.messages[idx].cache_wrapped_plain_text(
+        self.chat_viewport.width,
+        self.theme.as_ref(),
+        &self.syn_loader,
+      );
+      self.splice_message_plaintext(idx);
     } else {
+      if message.folds_by_default() {
+        message.folded = Some(message.fold_summary(&self.messages));
+      }
       self.messages.push(message);
-      self
-        .messages
-        .iter_mut()
-        .last()
-        .unwrap()
-        .cache_wrapped_plain_text(self.chat_viewport.width, &self.syn_loader);
-      self.update_messages_plaintext();
+      self.messages.iter_mut().last().unwrap().cache_wrapped_plain_text(
+        self.chat_viewport.width,
+        self.theme.as_ref(),
+        &self.syn_loader,
+      );
+      self.splice_message_plaintext(self.messages.len() - 1);
     }
   }
 
-  pub fn reload_messages(&mut self, messages: Vec<ChatMessageItem>) {
+  /// shows a change a tool made to a file as a diff in the message that called the tool
+  pub fn add_file_edit(&mut self, edit: FileEdit) {
+    let Some(idx) =
+      self.messages.iter().position(|m| m.tool_call_ids().contains(&edit.tool_call_id.as_str()))
+    else {
+      log::warn!("no message found for the tool call that edited {}", edit.path.display());
+      return;
+    };
+    self.messages[idx].file_diffs.push(FileDiff::new(edit));
+    self.refresh_message(idx);
+  }
+
+  /// the file the session is working on: the last one a tool changed, or else the last one a
+  /// message mentioned
+  pub fn working_file(&self, workspace_root: &std::path::Path) -> Option<PathBuf> {
+    let edited = self.messages.iter().rev().find_map(|message| message.file_diffs.last());
+    edited.map(|diff| diff.edit.path.clone()).or_else(|| {
+      self.messages.iter().rev().find_map(|message| {
+        parse_mentions(message.content()).into_iter().rev().find_map(|mention| match mention {
+          Mention::File(path) => Some(workspace_root.join(path)),
+          Mention::Symbol(_) => None,
+        })
+      })
+    })
+  }
+
+  /// the first file diff in the message at the cursor that was not accepted or reverted yet
+  pub fn pending_file_diff_at_cursor(&self) -> Option<&FileDiff> {
+    self.message_at_cursor()?.file_diffs.iter().find(|diff| diff.status == DiffStatus::Pending)
+  }
+
+  pub fn set_file_diff_status(&mut self, tool_call_id: &str, status: DiffStatus) {
+    let Some(idx) = self.messages.iter().position(|message| {
+      message.file_diffs.iter().any(|diff| diff.edit.tool_call_id == tool_call_id)
+    }) else {
+      return;
+    };
+    for diff in self.messages[idx].file_diffs.iter_mut() {
+      if diff.edit.tool_call_id == tool_call_id {
+        diff.status = status;
+      }
+    }
+    self.refresh_message(idx);
+  }
+
+  /// wraps the message at `idx` again after something other than its content changed
+  fn refresh_message(&mut self, idx: usize) {
+    self.messages[idx].wrap_cache.clear();
+    self.messages[idx].cache_wrapped_plain_text(
+      self.chat_viewport.width,
+      self.theme.as_ref(),
+      &self.syn_loader,
+    );
+    self.splice_message_plaintext(idx);
+  }
+
+  pub fn reload_messages(&mut self, mut messages: Vec<ChatMessageItem>) {
+    // folds and file diffs outlive a reload, messages not seen before are folded by default
+    for idx in 0..messages.len() {
+      let previous = self.messages.iter().find(|m| m.id.is_some() && m.id == messages[idx].id);
+      if let Some(previous) = previous {
+        messages[idx].file_diffs = previous.file_diffs.clone();
+      }
+      let folded = match previous {
+        Some(previous) => previous.folded.is_some(),
+        None => messages[idx].folds_by_default(),
+      };
+      if folded {
+        messages[idx].folded = Some(messages[idx].fold_summary(&messages));
+      }
+    }
     self.messages = messages;
     self.messages.iter_mut().for_each(|message| {
-      message.cache_wrapped_plain_text(self.chat_viewport.width, &self.syn_loader);
+      message.cache_wrapped_plain_text(
+        self.chat_viewport.width,
+        self.theme.as_ref(),
+        &self.syn_loader,
+      );
     });
-    self.update_messages_plaintext();
+    self.rebuild_messages_plaintext();
     self.state.scroll_top();
   }
 
+  /// folds the message at the cursor to its one line summary, or unfolds it. returns whether
+  /// the message is now folded, or none if there is no message at the cursor
+  pub fn toggle_fold_at_cursor(&mut self) -> Option<bool> {
+    let cursor = self.selection.primary().head;
+    let idx = self.message_idx_at(cursor)?;
+    self.messages[idx].folded = match self.messages[idx].folded {
+      Some(_) => None,
+      None => Some(self.messages[idx].fold_summary(&self.messages)),
+    };
+    self.messages[idx].cache_wrapped_plain_text(
+      self.chat_viewport.width,
+      self.theme.as_ref(),
+      &self.syn_loader,
+    );
+    self.splice_message_plaintext(idx);
+    // the cursor may have been inside the text that was folded away
+    self.selection = Selection::point(self.message_start(idx));
+    Some(self.messages[idx].folded.is_some())
+  }
+
+  /// moves the cursor to the start of the message with `message_id` and scrolls to it. false if
+  /// the message is not in the transcript
+  pub fn goto_message(&mut self, message_id: i64) -> bool {
+    let Some(idx) = self.messages.iter().position(|m| m.id == Some(message_id)) else {
+      return false;
+    };
+    self.selection = Selection::point(self.message_start(idx));
+    let (scroll_by, direction, _) = crate::movement::translate_char_index_to_viewport_pos(
+      &self.get_messages_plaintext(),
+      self.chat_viewport,
+      self.state.vertical_scroll,
+      self.selection.primary().head,
+      true,
+    );
+    if let Some(direction) = direction {
+      self.state.scroll_by(scroll_by, direction);
+    }
+    true
+  }
+
+  /// the message containing the session cursor
+  pub fn message_at_cursor(&self) -> Option<&ChatMessageItem> {
+    self.message_idx_at(self.selection.primary().head).map(|idx| &self.messages[idx])
+  }
+
+  /// char ranges of each message's plain text within the transcript
+  pub fn message_char_ranges(&self) -> Vec<std::ops::Range<usize>> {
+    self
+      .messages
+      .iter()
+      .enumerate()
+      .map(|(idx, m)| self.message_start(idx)..self.message_start(idx) + m.plain_text.len_chars())
+      .collect()
+  }
+
+  /// char ranges of the code blocks of every message within the transcript
+  pub fn code_block_char_ranges(&self) -> Vec<std::ops::Range<usize>> {
+    self
+      .messages
+      .iter()
+      .enumerate()
+      .flat_map(|(idx, m)| {
+        let start_idx = self.message_start(idx);
+        crate::movement::code_block_char_ranges(m.plain_text.slice(..), m.content())
+          .into_iter()
+          .map(move |r| r.start + start_idx..r.end + start_idx)
+      })
+      .collect()
+  }
+
+  /// the code block containing `char_idx` in the transcript. its range is relative to the
+  /// message it is in
+  pub fn code_block_at(&self, char_idx: usize) -> Option<crate::movement::CodeBlock> {
+    let message_idx = self.message_idx_at(char_idx)?;
+    let message = &self.messages[message_idx];
+    let idx = char_idx.saturating_sub(self.message_start(message_idx));
+    crate::movement::code_blocks(message.plain_text.slice(..), message.content())
+      .into_iter()
+      .find(|block| block.range.start <= idx && idx < block.range.end)
+  }
+
+  /// draws the diagrams of the replies in view over the frame the terminal just drew. `covered`
+  /// clears them while a picker or popup is in front of the session
+  pub fn draw_diagrams(
+    &mut self,
+    covered: bool,
+    out: &mut impl std::io::Write,
+  ) -> std::io::Result<()> {
+    let diagrams = match self.diagrams {
+      Some(_) if !covered && !self.perf.is_degraded() => self.visible_diagrams(),
+      Some(_) => vec![],
+      None => return Ok(()),
+    };
+    self.diagrams.as_mut().map_or(Ok(()), |inline| inline.draw(&diagrams, out))
+  }
+
+  // the diagram blocks of the replies that are wholly in view, a block cut off by the edge of
+  // the view is shown as code
+  fn visible_diagrams(&self) -> Vec<Diagram> {
+    let viewport = self.chat_viewport;
+    let top = self.state.vertical_scroll as usize;
+    let bottom = top + viewport.height as usize;
+    let rows = self.state.visible_rows(0);
+    // summed as usize, the rows above the viewport can be taller than u16::MAX together
+    let mut row_top =
+      self.state.row_heights[..rows.start].iter().map(|height| *height as usize).sum::<usize>();
+    let mut diagrams = vec![];
+    for idx in rows {
+      let message = &self.messages[idx];
+      if message.role() == ChatRole::Assistant && message.folded.is_none() {
+        let plain_text = message.plain_text.slice(..);
+        for block in crate::movement::code_blocks(plain_text, message.content()) {
+          let Some(kind) = DiagramKind::from_language(&block.language) else {
+            continue;
+          };
+          let block_top = row_top + plain_text.char_to_line(block.range.start);
+          let block_bottom = row_top + plain_text.char_to_line(block.range.end - 1) + 1;
+          if block_top >= top && block_bottom <= bottom {
+            // inside the viewport, so the offsets fit in u16
+            let y = viewport.y + (block_top - top) as u16;
+            let height = (block_bottom - block_top) as u16;
+            let area = Rect::new(viewport.x, y, viewport.width, height);
+            diagrams.push(Diagram { kind, source: block.code, area });
+          }
+        }
+      }
+      row_top += self.state.row_heights[idx] as usize;
+    }
+    diagrams
+  }
+
   pub fn set_terminal_focused(&mut self, terminal_focused: bool) {
     self.terminal_focused = terminal_focused
   }
@@ -613,65 +1023,87 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
     cx: &mut Context,
     _overlay_highlight_iter: impl Iterator<Item = HighlightEvent>,
   ) {
-    // -- make space for the input bar:
+    // -- make space for the input bar and the statusline above it:
     let input_on_top = false;
     // define input area
     let area = if self.input_hidden || input_on_top {
       area.clip_top(self.input_height)
     } else {
-      area.clip_bottom(self.input_height + 1)
+      area.clip_bottom(self.input_height + 2)
+    };
+
+    // pasted snippets waiting for the next message are shown as chips above the input
+    let area = match cx.session.snippets.is_empty() || area.height < 2 {
+      true => area,
+      false => {
+        self.render_snippet_chips(area.clip_top(area.height - 1), surface, cx);
+        area.clip_bottom(1)
+      },
     };
 
+    // inputs waiting for the response to complete are listed below the messages
+    let area = match cx.session.queued_inputs.len().min(MAX_QUEUED_INPUTS_SHOWN) as u16 {
+      0 => area,
+      shown => {
+        let height = (shown + 2).min(area.height / 2);
+        self.render_queued_inputs(area.clip_top(area.height - height), surface, cx);
+        area.clip_bottom(height)
+      },
+    };
+
+    if self.theme.as_ref().map(|theme| theme.name()) != Some(cx.editor.theme.name()) {
+      self.theme = Some(cx.editor.theme.clone());
+      self.wrap_messages();
+    }
+    self.gutter = cx.session.config.gutter;
+    if self.diagrams.as_ref().map(InlineDiagrams::config) != Some(&cx.session.config.diagrams) {
+      self.diagrams = InlineDiagrams::new(&cx.session.config.diagrams);
+    }
+
     let status = self.matcher.tick(10);
+    self.matcher_running = status.running;
     let snapshot = self.matcher.snapshot();
     if status.changed {
       self.selected_option =
         self.selected_option.min(snapshot.matched_item_count().saturating_sub(1))
     }
 
-    let text_style = cx.editor.theme.get("ui.text");
-    let _cursor_style = cx.editor.theme.get("ui.cursor");
-    let selected = cx.editor.theme.get("ui.selection");
-    let _highlight_style = cx.editor.theme.get("special").add_modifier(Modifier::BOLD);
+    if let (Some(position), _cursor) = self.cursor(self.chat_viewport, cx.editor) {
+      self.state.cursor_position = Some(position);
+    };
+
+    self.render_messages(area, surface, &cx.editor.theme);
+    if self.pending_wrap_width.is_none() {
+      self.wrap_messages_in_background(self.chat_viewport.width, cx.jobs);
+    }
+
+    let mut matcher = MATCHER.lock();
+    matcher.config = Config::DEFAULT;
+    if self.file_fn.is_some() {
+      matcher.config.set_match_paths()
+    }
+  }
+
+  /// draws the frame and the messages in it, it needs no editor so it can be rendered on its own
+  pub(crate) fn render_messages(&mut self, area: Rect, surface: &mut Surface, theme: &Theme) {
+    let text_style = theme.get("ui.text");
+    let selected = theme.get("ui.selection");
 
     // -- Render the frame:
     // clear area
-    let background = cx.editor.theme.get("ui.background");
+    let background = theme.get("ui.background");
     surface.clear_with(area, background);
-    let block = Block::default().borders(Borders::ALL);
+    // borders are dropped in degraded mode, every cell drawn costs bandwidth on slow terminals
+    let degraded = self.perf.is_degraded();
+    let borders = if degraded { Borders::NONE } else { Borders::ALL };
+    let block = Block::default().borders(borders);
 
     // calculate the inner area inside the box
     let table_area = block.inner(area);
 
     block.render(area, surface);
 
-    // -- upper right hand corner readout
-    let count = format!(
-      "{}{}/{}",
-      if status.running { "(running) " } else { "" },
-      snapshot.matched_item_count(),
-      snapshot.item_count(),
-    );
-
-    surface.set_stringn(
-      (area.x + area.width).saturating_sub(count.len() as u16 + 1),
-      area.y,
-      &count,
-      (count.len()).min(area.width as usize),
-      text_style,
-    );
-
     // -- Render the contents:
-    let mut matcher = MATCHER.lock();
-    matcher.config = Config::DEFAULT;
-    if self.file_fn.is_some() {
-      matcher.config.set_match_paths()
-    }
-
-    if let (Some(position), _cursor) = self.cursor(self.chat_viewport, cx.editor) {
-      self.state.cursor_position = Some(position);
-    };
-
     let primary_range = self.selection.primary();
 
     let highlight_range = if primary_range.head < primary_range.anchor {
@@ -682,7 +1114,7 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
     let highlight_style = selected;
 
     // precalculate column areas so plain text messages can be cached
-    self.widths = vec![Constraint::Length(5), Constraint::Percentage(25)];
+    self.widths = vec![Constraint::Length(self.gutter_width()), Constraint::Percentage(25)];
     let highlight_symbol = " > ".to_string();
     let column_areas = Table::calculate_column_areas(
       table_area,
@@ -697,23 +1129,43 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
     // message.update_wrapped_plain_text_if_necessary(self.chat_viewport.width, &self.syn_loader)
     // });
 
+    // only the messages near the viewport are built into rows, the others are placed using the
+    // height of their cached plain text
+    self.state.row_heights.clone_from(&self.row_heights);
+    self.state.viewport_height = table_area.height;
+    self.state.update_sticky_scroll();
+    let visible_messages = self.state.visible_rows(MESSAGE_OVERSCAN);
+    let row_heights = self.state.row_heights.clone();
+
+    let message_offsets = &self.message_offsets;
     Table::new(
-      self
-        .messages
+      self.messages[visible_messages.clone()]
         .iter()
         .enumerate()
-        .map(|(msg_idx, message)| {
-          let message_cell = MessageCell::new(MessageType::Chat(message))
+        .map(|(i, message)| {
+          let msg_idx = visible_messages.start + i;
+          // the cached plain text is already wrapped, degraded mode draws it as is instead of
+          // formatting and wrapping the message again every frame
+          let message_type =
+            if degraded { MessageType::Plain(message) } else { MessageType::Chat(message) };
+          let message_cell = MessageCell::new(message_type)
             // .with_style(style)
             .with_wrap_trim(false)
             .with_highlight(highlight_style, highlight_range.clone())
             .with_block(Block::default())
-            .with_char_index(message.start_idx);
+            .with_char_index(message_offsets.prefix(msg_idx));
 
-          let msg_idx = msg_idx.to_string();
-          let index_cell = MessageCell::new(MessageType::Text(msg_idx))
+          // the gutter and its border take the colour of the message's role
+          let role_style = message.role().style(Some(theme));
+          let gutter = message.gutter_lines(msg_idx, &self.gutter).join("\n");
+          let index_cell = MessageCell::new(MessageType::Text(gutter))
             .centered()
-            .with_block(Block::default().borders(Borders::RIGHT));
+            .with_style(role_style)
+            .with_block(
+              Block::default()
+                .borders(if degraded { Borders::NONE } else { Borders::RIGHT })
+                .border_style(role_style),
+            );
 
           Row::new(vec![index_cell, message_cell]).height(message.plain_text.len_lines() as u16)
         })
@@ -725,14 +1177,87 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
     .column_spacing(self.table_column_spacing)
     .row_spacing(self.table_row_spacing)
     .widths(&self.widths)
+    .row_window(visible_messages.start, row_heights)
     .render_table(
       table_area,
       surface,
       &mut self.state,
       self.truncate_start,
-      &cx.editor.theme,
-      &cx.editor.syn_loader,
+      theme,
+      &self.syn_loader,
     );
+
+    // the scrollbar and readout are drawn over the frame, which degraded mode drops
+    if !degraded {
+      self.render_scroll_position(area, table_area, surface, theme);
+    }
+  }
+
+  // a scrollbar down the right border once the messages no longer fit, and a readout in the top
+  // right corner of "message n/m" beside the count of items the search matches, once it has any.
+  // n is the selected message, or else the one at the bottom of the view
+  fn render_scroll_position(
+    &self,
+    area: Rect,
+    table_area: Rect,
+    surface: &mut Surface,
+    theme: &Theme,
+  ) {
+    let mut readout = vec![];
+    if let Some((thumb_line, thumb_height)) = self.state.scrollbar_thumb() {
+      let scroll_style = theme.get("ui.menu.scroll");
+      let x = area.right().saturating_sub(1);
+      for line in thumb_line..thumb_line + thumb_height {
+        let cell = &mut surface[(x, table_area.top() + line)];
+        cell.set_symbol("▌");
+        cell.set_fg(scroll_style.fg.unwrap_or(Color::Reset));
+      }
+      let bottom_line = self.state.vertical_scroll + self.state.viewport_height.saturating_sub(1);
+      if let Some(current) = self.state.selected.or_else(|| self.state.row_at(bottom_line)) {
+        readout.push(format!("message {}/{}", current + 1, self.messages.len()));
+      }
+    }
+    let snapshot = self.matcher.snapshot();
+    if self.matcher_running || snapshot.item_count() > 0 {
+      readout.push(format!(
+        "{}{}/{} matched",
+        if self.matcher_running { "(running) " } else { "" },
+        snapshot.matched_item_count(),
+        snapshot.item_count(),
+      ));
+    }
+    if readout.is_empty() {
+      return;
+    }
+
+    let readout = format!(" {} ", readout.join(" · "));
+    let width = readout.chars().count() as u16;
+    if width + 2 <= area.width {
+      surface.set_string(area.right() - width - 1, area.top(), readout, theme.get("ui.text"));
+    }
+  }
+
+  // the width of the gutter, its widest line with a column of padding and its border
+  fn gutter_width(&self) -> u16 {
+    let digits = self.messages.len().max(1).to_string().len();
+    let role = match self.gutter.role {
+      GutterRole::None => digits,
+      GutterRole::Icon => digits + 2,
+      GutterRole::Name => "assistant".len(),
+    };
+    let timestamp = if self.gutter.timestamp { GUTTER_TIMESTAMP_WIDTH } else { 0 };
+    let model = match self.gutter.model {
+      true => self
+        .messages
+        .iter()
+        .filter(|message| message.role() == ChatRole::Assistant)
+        .filter_map(|message| Some(message.model.as_ref()?.width()))
+        .max()
+        .unwrap_or(0)
+        .min(MAX_GUTTER_MODEL_WIDTH),
+      false => 0,
+    };
+    (role.max(timestamp).max(model) + 2) as u16
   }
 
   fn viewport_byte_range(
@@ -998,6 +1523,81 @@ impl<T: MarkdownItem + 'static> SessionView<T> {
 
     spans
   }
+  fn render_pinned_files(&self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let background = cx.editor.theme.get("ui.background");
+    let text = cx.editor.theme.get("ui.text");
+    surface.clear_with(area, background);
+
+    let block = Block::default().title("pinned").borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+
+    for (row, path) in cx.session.config.pinned_files.iter().enumerate().take(inner.height as usize)
+    {
+      let name = path
+        .file_name()
+        .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+      // the line count is known once the file has been read for a request
+      let lines = cx
+        .session
+        .pinned_files
+        .iter()
+        .find(|file| &file.path == path)
+        .map(|file| format!(" ({})", file.content.lines().count()))
+        .unwrap_or_default();
+      surface.set_stringn(
+        inner.x,
+        inner.y + row as u16,
+        format!("{}{}", name, lines),
+        inner.width as usize,
+        text,
+      );
+    }
+  }
+
+  fn render_queued_inputs(&self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let background = cx.editor.theme.get("ui.background");
+    let text = cx.editor.theme.get("ui.text");
+    surface.clear_with(area, background);
+
+    let queued = &cx.session.queued_inputs;
+    let title = match queued.len() > MAX_QUEUED_INPUTS_SHOWN {
+      true => format!("queued ({} more)", queued.len() - MAX_QUEUED_INPUTS_SHOWN),
+      false => "queued".to_string(),
+    };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+
+    for (row, input) in queued.iter().enumerate().take(inner.height as usize) {
+      let first_line = input.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+      surface.set_stringn(
+        inner.x,
+        inner.y + row as u16,
+        format!("{} {}", row + 1, first_line.trim()),
+        inner.width as usize,
+        text,
+      );
+    }
+  }
+
+  fn render_snippet_chips(&self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let background = cx.editor.theme.get("ui.background");
+    let chip = cx.editor.theme.get("ui.menu.selected");
+    surface.clear_with(area, background);
+
+    let mut x = area.x;
+    for (idx, snippet) in cx.session.snippets.iter().enumerate() {
+      let label = format!(" {} ", snippet_chip(idx + 1, snippet));
+      let width = area.right().saturating_sub(x) as usize;
+      if width == 0 {
+        break;
+      }
+      surface.set_stringn(x, area.y, &label, width, chip);
+      x = x.saturating_add(label.width() as u16 + 1);
+    }
+  }
+
   fn render_preview(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
     // -- Render the frame:
     // clear area
@@ -1161,6 +1761,26 @@ impl<T: MarkdownItem + 'static + Send + Sync> Component for SessionView<T> {
     // |         | |         |
     // +---------+ +---------+
 
+    // the tab bar of the other open sessions is drawn above the session
+    let area = area.clip_top(self.tab_bar_height);
+
+    // a file opened beside the session takes the right half, the input draws it
+    let area = match self.split_view.filter(|view_id| cx.editor.tree.contains(*view_id)) {
+      Some(_) => split_columns(area).0,
+      None => area,
+    };
+
+    // pinned files are listed in a panel to the right of the session
+    let area = if !cx.session.config.pinned_files.is_empty()
+      && area.width > MIN_AREA_WIDTH_FOR_PINNED_FILES
+    {
+      let panel_width = (area.width / 4).min(MAX_PINNED_FILES_WIDTH);
+      self.render_pinned_files(area.clip_left(area.width - panel_width), surface, cx);
+      area.clip_right(panel_width)
+    } else {
+      area
+    };
+
     let render_preview =
       self.show_preview && self.file_fn.is_some() && area.width > MIN_AREA_WIDTH_FOR_PREVIEW;
 
@@ -1199,45 +1819,57 @@ impl<T: MarkdownItem + 'static + Send + Sync> Component for SessionView<T> {
     let event_result = match event {
       Event::IdleTimeout => self.handle_idle_timeout(ctx),
       Event::Resize(..) => {
-        self.update_messages_plaintext();
+        self.rebuild_messages_plaintext();
         EventResult::Consumed(None)
       },
       Event::Mouse(event) => {
         match event.kind {
           MouseEventKind::Down(MouseButton::Left) => {
-            if let Some(char_idx) = crate::movement::translate_pos_to_char_index(
-              &self.get_messages_plaintext(),
-              self.chat_viewport,
-              self.state.vertical_scroll,
-              Position { row: event.row as usize, col: event.column as usize },
-            ) {
-              self.selection = Selection::point(char_idx);
+            let pos = Position { row: event.row as usize, col: event.column as usize };
+            if let Some(char_idx) = self.char_idx_at(pos) {
+              // double and triple clicks select the word or line under them
+              let click = Click::after(self.last_click, pos, std::time::Instant::now());
+              let unit = SelectUnit::from_clicks(click.count);
+              let text = self.get_messages_plaintext();
+              let origin = unit.range_at(text, char_idx);
+              self.selection = match unit {
+                SelectUnit::Char => Selection::point(char_idx),
+                _ => Selection::single(origin.from(), origin.to()),
+              };
+              self.last_click = Some(click);
+              self.drag_origin = Some((origin, unit));
               helix_event::request_redraw();
               EventResult::Consumed(None)
             } else {
               EventResult::Ignored(None)
             }
-            // start select
           },
           MouseEventKind::Up(MouseButton::Left) => {
+            self.drag_origin = None;
             EventResult::Ignored(None)
-            // stop select
-            // log::info!("mouse up event: {:?}", event);
           },
-          MouseEventKind::Drag(MouseButton::Left) => {
-            if let Some(char_idx) = crate::movement::translate_pos_to_char_index(
-              &self.get_messages_plaintext(),
-              self.chat_viewport,
-              self.state.vertical_scroll,
-              Position { row: event.row as usize, col: event.column as usize },
-            ) {
-              let range = self.selection.primary();
-              self.selection = Selection::single(range.anchor, char_idx);
+          MouseEventKind::Drag(MouseButton::Left) => match self.drag_origin {
+            Some((origin, unit)) => {
+              // dragging past the top or bottom of the messages scrolls them, by as many lines as
+              // the pointer is past the edge
+              let area = self.chat_viewport;
+              if event.row < area.top() {
+                self.state.scroll_by(area.top() - event.row, Direction::Backward);
+              } else if event.row >= area.bottom() {
+                self.state.scroll_by(event.row + 1 - area.bottom(), Direction::Forward);
+              }
+              let pos = Position {
+                row: event.row.clamp(area.top(), area.bottom().saturating_sub(1)) as usize,
+                col: event.column.clamp(area.left(), area.right().saturating_sub(1)) as usize,
+              };
+              if let Some(char_idx) = self.char_idx_at(pos) {
+                self.selection =
+                  drag_selection(self.get_messages_plaintext(), origin, unit, char_idx);
+              }
               helix_event::request_redraw();
-            }
-            // update select
-            log::info!("mouse drag event: {:?}", event);
-            EventResult::Consumed(None)
+              EventResult::Consumed(None)
+            },
+            None => EventResult::Ignored(None),
           },
           MouseEventKind::ScrollUp => {
             // log::info!("scroll up");
@@ -1409,80 +2041,84 @@ impl<T: MarkdownItem + Send + Sync + 'static> Component for DynamicSession<T> {
   }
 }
 
-pub fn session_picker(root: PathBuf, config: &helix_view::editor::Config) -> Picker<PathBuf> {
-  use ignore::{types::TypesBuilder, WalkBuilder};
-  use std::time::Instant;
-
-  let now = Instant::now();
-
-  let dedup_symlinks = config.file_picker.deduplicate_links;
-  let absolute_root = root.canonicalize().unwrap_or_else(|_| root.clone());
-
-  let mut walk_builder = WalkBuilder::new(&root);
-  walk_builder
-    .hidden(config.file_picker.hidden)
-    .parents(config.file_picker.parents)
-    .ignore(config.file_picker.ignore)
-    .follow_links(config.file_picker.follow_symlinks)
-    .git_ignore(config.file_picker.git_ignore)
-    .git_global(config.file_picker.git_global)
-    .git_exclude(config.file_picker.git_exclude)
-    .sort_by_file_name(|name1, name2| name2.cmp(name1))
-    .max_depth(config.file_picker.max_depth)
-    .filter_entry(move |entry| filter_picker_entry(entry, &absolute_root, dedup_symlinks));
-
-  walk_builder.add_custom_ignore_filename(helix_loader::config_dir().join("ignore"));
-  walk_builder.add_custom_ignore_filename(".helix/ignore");
-
-  // We want to exclude files that the editor can't handle yet
-  let mut type_builder = TypesBuilder::new();
-  type_builder
-    .add("compressed", "*.{zip,gz,bz2,zst,lzo,sz,tgz,tbz2,lz,lz4,lzma,lzo,z,Z,xz,7z,rar,cab}")
-    .expect("Invalid type definition");
-  type_builder.negate("all");
-  let excluded_types = type_builder.build().expect("failed to build excluded_types");
-  walk_builder.types(excluded_types);
-  let mut files = walk_builder.build().filter_map(|entry| {
-    let entry = entry.ok()?;
-    if !entry.file_type()?.is_file() {
-      return None;
-    }
-    Some(entry.into_path())
+// very long sessions are loaded partially, older messages stay in the session store
+pub const SESSION_LOAD_MESSAGE_LIMIT: usize = 1000;
+
+impl super::menu::Item for SessionSummary {
+  type Data = ();
+
+  fn format(&self, _data: &Self::Data) -> super::menu::Row {
+    let updated_at = chrono::DateTime::from_timestamp(self.updated_at, 0)
+      .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+      .unwrap_or_default();
+    let tags = self.tags.iter().map(|tag| format!(" #{}", tag)).collect::<String>();
+    format!("{}  {}{}  ({} messages)", updated_at, self.title, tags, self.message_count).into()
+  }
+}
+
+impl super::menu::Item for AuditEntry {
+  type Data = ();
+
+  fn format(&self, _data: &Self::Data) -> super::menu::Row {
+    let started_at = chrono::DateTime::parse_from_rfc3339(&self.started_at)
+      .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+      .unwrap_or_default();
+    let files = self.files.iter().map(|f| format!(" {}", f.display())).collect::<String>();
+    format!(
+      "{}  {}  {}ms  {}b{}  {}",
+      started_at, self.tool, self.duration_ms, self.result_bytes, files, self.arguments
+    )
+    .into()
+  }
+}
+
+impl super::menu::Item for MessageSearchResult {
+  type Data = ();
+
+  fn format(&self, _data: &Self::Data) -> super::menu::Row {
+    let content = self.content.lines().find(|l| !l.trim().is_empty()).unwrap_or_default();
+    format!("{}  {}: {}", self.session_title, self.role, content).into()
+  }
+}
+
+pub fn load_stored_session(cx: &mut Context, session_id: &str) {
+  let result = open_session_store().and_then(|store| {
+    cx.session.load_session(&store, session_id, Some(SESSION_LOAD_MESSAGE_LIMIT))
   });
-  log::debug!("file_picker init {:?}", Instant::now().duration_since(now));
-
-  let picker = Picker::new(Vec::new(), root, move |cx, path: &PathBuf, _action| {
-    if let Err(e) = cx.session.load_session(path) {
-      // let err = if let Some(err) = e.source() {
-      //   format!("{}", err)
-      // } else {
-      let err = format!("unable to open \"{}\" {}", path.display(), e);
-      // };
-      cx.editor.set_error(err);
-    }
-  })
-  .with_preview(|_editor, path| Some((path.clone().into(), None)));
-  let injector = picker.injector();
-  let timeout = std::time::Instant::now() + std::time::Duration::from_millis(30);
-
-  let mut hit_timeout = false;
-  for file in &mut files {
-    if injector.push(file).is_err() {
-      break;
-    }
-    if std::time::Instant::now() >= timeout {
-      hit_timeout = true;
-      break;
-    }
+  match result {
+    Ok(()) if cx.session.unloaded_message_count > 0 => cx.editor.set_status(format!(
+      "loaded the last {} messages of {}",
+      cx.session.messages.len(),
+      cx.session.config.title
+    )),
+    Ok(()) => cx.editor.set_status(format!("loaded {}", cx.session.config.title)),
+    Err(e) => cx.editor.set_error(format!("unable to load session {}: {}", session_id, e)),
   }
-  if hit_timeout {
-    std::thread::spawn(move || {
-      for file in files {
-        if injector.push(file).is_err() {
-          break;
+}
+
+pub fn session_picker(sessions: Vec<SessionSummary>) -> Picker<SessionSummary> {
+  Picker::new(sessions, (), move |cx, session: &SessionSummary, _action| {
+    load_stored_session(cx, &session.id)
+  })
+}
+
+pub fn audit_picker(entries: Vec<AuditEntry>) -> Picker<AuditEntry> {
+  Picker::new(entries, (), move |cx, entry: &AuditEntry, _action| {
+    let message_id = entry.message_id;
+    cx.jobs.callback(async move {
+      let callback = move |editor: &mut Editor, compositor: &mut Compositor| {
+        let session = compositor.find::<SessionView<ChatMessageItem>>().unwrap();
+        if !session.goto_message(message_id) {
+          editor.set_status("the message of the tool call is not loaded");
         }
-      }
+      };
+      Ok(Callback::EditorCompositor(Box::new(callback)))
     });
-  }
-  picker
+  })
+}
+
+pub fn message_search_picker(results: Vec<MessageSearchResult>) -> Picker<MessageSearchResult> {
+  Picker::new(results, (), move |cx, result: &MessageSearchResult, _action| {
+    load_stored_session(cx, &result.session_id)
+  })
 }
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use helix_view::{graphics::Rect, Theme};
+use sazid::app::usage::format_tokens;
+use sazid::components::session::Session;
+use tui::buffer::Buffer as Surface;
+use tui::text::{Span, Spans};
+
+use crate::compositor::{Component, Context};
+use crate::ui::{ProgressSpinners, Spinner};
+
+// the share of the context window the last request may take up before it is shown as a warning
+const CONTEXT_WARNING_RATIO: f64 = 0.8;
+
+/// the line between the session and the input, showing the model, what the session is doing,
+/// how much of the context window the last request took up, what the session has cost and the
+/// progress of the language servers
+pub struct SessionStatusline {
+  // the rows of the input below the line, not counting the command line
+  input_height: u16,
+  activity: Option<String>,
+  activity_spinner: Spinner,
+  spinners: ProgressSpinners,
+  // the name of each language server with work in progress and its latest progress, by id
+  lsp_progress: BTreeMap<usize, (String, String)>,
+}
+
+impl SessionStatusline {
+  pub const ID: &'static str = "session-statusline";
+
+  pub fn new(input_height: u16) -> Self {
+    SessionStatusline {
+      input_height,
+      activity: None,
+      activity_spinner: Spinner::default(),
+      spinners: ProgressSpinners::default(),
+      lsp_progress: BTreeMap::new(),
+    }
+  }
+
+  /// what the session is doing while it makes a request, none once the request is done
+  pub fn set_activity(&mut self, activity: Option<String>) {
+    match activity {
+      Some(_) if self.activity_spinner.is_stopped() => self.activity_spinner.start(),
+      Some(_) => {},
+      None => self.activity_spinner.stop(),
+    }
+    self.activity = activity;
+  }
+
+  /// the latest progress of a language server, none once it has no work in progress
+  pub fn set_lsp_progress(&mut self, server_id: usize, name: &str, progress: Option<String>) {
+    let spinner = self.spinners.get_or_create(server_id);
+    match progress {
+      Some(progress) => {
+        if spinner.is_stopped() {
+          spinner.start();
+        }
+        self.lsp_progress.insert(server_id, (name.to_string(), progress));
+      },
+      None => {
+        spinner.stop();
+        self.lsp_progress.remove(&server_id);
+      },
+    }
+  }
+
+  /// the row above the input and its command line
+  pub fn area(&self, area: Rect) -> Rect {
+    area.clip_top(area.height.saturating_sub(self.input_height + 2)).with_height(1)
+  }
+
+  /// draws the line, it needs no editor so it can be rendered on its own
+  pub(crate) fn render_statusline(
+    &self,
+    area: Rect,
+    surface: &mut Surface,
+    theme: &Theme,
+    session: &Session,
+  ) {
+    let base_style = theme.get("ui.statusline");
+    surface.set_style(area, base_style);
+
+    let activity = match &self.activity {
+      _ if session.is_receiving() => "streaming",
+      Some(activity) => activity.as_str(),
+      None => "ready",
+    };
+    let spinner = self.activity_spinner.frame().unwrap_or(" ");
+    let left = Spans::from(Span::styled(format!(" {} {}", spinner, activity), base_style));
+    surface.set_spans(area.x, area.y, &left, area.width);
+
+    let mut right = vec![];
+    for (server_id, (name, progress)) in self.lsp_progress.iter() {
+      let spinner = self.spinners.get(*server_id).and_then(Spinner::frame).unwrap_or(" ");
+      right.push(Span::styled(format!("{} {} {} │ ", spinner, name, progress), base_style));
+    }
+    right.push(Span::styled(format!("{} │ ", session.config.model_settings()), base_style));
+    let limit = session.config.model.token_limit as usize;
+    let context_style = if session.context_tokens as f64 > limit as f64 * CONTEXT_WARNING_RATIO {
+      base_style.patch(theme.get("warning"))
+    } else {
+      base_style
+    };
+    right.push(Span::styled(
+      format!(
+        "ctx {}/{}",
+        format_tokens(session.context_tokens as u64),
+        format_tokens(limit as u64)
+      ),
+      context_style,
+    ));
+    right.push(Span::styled(format!(" │ ${:.4} ", session.usage.total().cost), base_style));
+    let right = Spans::from(right);
+    let width = (right.width() as u16).min(area.width);
+    surface.set_spans(area.right().saturating_sub(width), area.y, &right, width);
+  }
+}
+
+impl Component for SessionStatusline {
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    self.render_statusline(self.area(area), surface, &cx.editor.theme, cx.session);
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
@@ -0,0 +1,300 @@
+use helix_view::graphics::{Margin, Rect};
+use sazid::action::{ChatToolAction, SessionAction};
+use sazid::app::mentions::SymbolIndex;
+use sazid::app::prompt_profiles::DEFAULT_PROMPT_PROFILE;
+use sazid::app::session_config::SessionConfig;
+use sazid::components::session::Session;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tui::buffer::Buffer as Surface;
+use tui::widgets::{Block, Borders, Widget};
+
+use crate::commands::ChatMessageItem;
+use crate::compositor::{Component, Compositor, Context};
+use crate::ui::{SessionStatusline, SessionView};
+
+// titles longer than this many columns are cut off in the tab bar
+const MAX_TAB_TITLE_WIDTH: usize = 24;
+
+/// an open session. the session of the active tab is the one in the compositor context, the
+/// others keep theirs here and go on receiving responses in the background
+pub struct SessionTab {
+  pub id: i64,
+  pub session: Option<Session>,
+  /// set when a response finished in the background, until the tab is shown
+  pub unread: bool,
+  /// the session was opened to work on an agent task, its progress is listed below the tabs
+  pub agent: bool,
+}
+
+/// the sessions open at once, drawn as a bar of tabs above the session while more than one is
+/// open
+pub struct SessionTabs {
+  tabs: Vec<SessionTab>,
+  active: usize,
+  // the actions of the sessions opened since the application last took them
+  opened: Vec<(i64, UnboundedReceiver<SessionAction>)>,
+  // the ids of the sessions closed since the application last took them
+  closed: Vec<i64>,
+  // shared with the sessions opened, which resolve mentions with it
+  symbol_index: SymbolIndex,
+}
+
+impl SessionTabs {
+  pub const ID: &'static str = "session-tabs";
+
+  pub fn new(active_id: i64, symbol_index: SymbolIndex) -> Self {
+    SessionTabs {
+      tabs: vec![SessionTab { id: active_id, session: None, unread: false, agent: false }],
+      active: 0,
+      opened: vec![],
+      closed: vec![],
+      symbol_index,
+    }
+  }
+
+  /// the rows of the tab bar, which is only drawn while more than one session is open, and of
+  /// the agent tasks listed below it
+  pub fn height(&self) -> u16 {
+    u16::from(self.tabs.len() > 1) + self.agent_panel_height()
+  }
+
+  fn agent_panel_height(&self) -> u16 {
+    match self.tabs.iter().filter(|tab| tab.agent).count() as u16 {
+      0 => 0,
+      agents => agents + 2,
+    }
+  }
+
+  /// the tab of a session in the background
+  pub fn background_tab(&mut self, id: i64) -> Option<&mut SessionTab> {
+    self.tabs.iter_mut().find(|tab| tab.id == id && tab.session.is_some())
+  }
+
+  /// the sessions of the tabs in the background
+  pub fn background_sessions(&self) -> impl Iterator<Item = &Session> {
+    self.tabs.iter().filter_map(|tab| tab.session.as_ref())
+  }
+
+  pub fn background_sessions_mut(&mut self) -> impl Iterator<Item = &mut Session> {
+    self.tabs.iter_mut().filter_map(|tab| tab.session.as_mut())
+  }
+
+  /// opens a new session with `config` in a tab after the active one and shows it
+  pub fn open(&mut self, current: &mut Session, config: SessionConfig) {
+    let session = self.new_session(config);
+    let index = self.active + 1;
+    let tab = SessionTab { id: session.id, session: Some(session), unread: false, agent: false };
+    self.tabs.insert(index, tab);
+    self.switch(current, index);
+  }
+
+  /// opens a new session with `config` in a background tab at the end, working towards `goal`
+  /// on its own
+  pub fn open_agent(&mut self, config: SessionConfig, goal: &str) {
+    let mut session = self.new_session(config);
+    session.start_agent_task(goal);
+    let tab = SessionTab { id: session.id, session: Some(session), unread: false, agent: true };
+    self.tabs.push(tab);
+  }
+
+  // a session with its own channel of actions, which the application takes from `opened`
+  fn new_session(&mut self, config: SessionConfig) -> Session {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut session = Session::new(tx.clone(), Some(config));
+    session.symbol_index = self.symbol_index.clone();
+    tx.send(SessionAction::ChatToolAction(ChatToolAction::UpdateConfig(
+      session.id,
+      Box::new(session.config.clone()),
+    )))
+    .unwrap();
+    let prompt_profile =
+      session.config.prompt_profile.clone().unwrap_or(DEFAULT_PROMPT_PROFILE.to_string());
+    if let Err(e) = session.select_prompt_profile(&prompt_profile) {
+      log::error!("unable to set the prompt profile of a new session: {}", e);
+    }
+    self.opened.push((session.id, rx));
+    session
+  }
+
+  /// the actions of the sessions opened since this was last called, by session id
+  pub fn take_opened(&mut self) -> Vec<(i64, UnboundedReceiver<SessionAction>)> {
+    std::mem::take(&mut self.opened)
+  }
+
+  /// the ids of the sessions closed since this was last called, their actions are no longer read
+  pub fn take_closed(&mut self) -> Vec<i64> {
+    std::mem::take(&mut self.closed)
+  }
+
+  /// shows the tab at `index`, putting the session of the active tab in the background. the
+  /// input history goes along to the session shown
+  pub fn switch(&mut self, current: &mut Session, index: usize) {
+    if index == self.active || index >= self.tabs.len() {
+      return;
+    }
+    let Some(mut shown) = self.tabs[index].session.take() else {
+      return;
+    };
+    shown.input_history = std::mem::take(&mut current.input_history);
+    self.tabs[self.active].session = Some(std::mem::replace(current, shown));
+    self.tabs[index].unread = false;
+    self.active = index;
+  }
+
+  /// shows the tab `offset` tabs after the active one, wrapping around the ends
+  pub fn cycle(&mut self, current: &mut Session, offset: isize) {
+    let index = (self.active as isize + offset).rem_euclid(self.tabs.len() as isize);
+    self.switch(current, index as usize);
+  }
+
+  /// closes the active tab and shows the one before it. the session closed is returned so it
+  /// can be saved, the last tab is not closed
+  pub fn close(&mut self, current: &mut Session) -> Option<Session> {
+    if self.tabs.len() < 2 {
+      return None;
+    }
+    let closed = self.active;
+    self.switch(current, if closed == 0 { 1 } else { closed - 1 });
+    if self.active > closed {
+      self.active -= 1;
+    }
+    let closed = self.tabs.remove(closed);
+    self.closed.push(closed.id);
+    closed.session
+  }
+
+  fn title(session: &Session) -> String {
+    let title = match &session.agent_task {
+      Some(task) if !session.config.title_set => &task.goal,
+      _ => &session.config.title,
+    };
+    let title = title.lines().next().unwrap_or_default();
+    if title.chars().count() > MAX_TAB_TITLE_WIDTH {
+      format!("{}…", title.chars().take(MAX_TAB_TITLE_WIDTH - 1).collect::<String>())
+    } else {
+      title.to_string()
+    }
+  }
+}
+
+/// sizes the session below the tab bar, after tabs were opened or closed
+pub fn sync_tab_bar(compositor: &mut Compositor) {
+  let height = compositor.find::<SessionTabs>().map_or(0, |tabs| tabs.height());
+  if let Some(session) = compositor.find::<SessionView<ChatMessageItem>>() {
+    session.tab_bar_height = height;
+  }
+}
+
+/// shows the messages of the session that became active, and what it is doing
+pub fn show_active_session(compositor: &mut Compositor, session: &Session) {
+  let messages = session.messages.iter().map(ChatMessageItem::from_container).collect();
+  if let Some(session_view) = compositor.find::<SessionView<ChatMessageItem>>() {
+    session_view.reload_messages(messages);
+  }
+  if let Some(statusline) = compositor.find::<SessionStatusline>() {
+    statusline.set_activity(None);
+  }
+}
+
+impl SessionTabs {
+  // the progress of each agent task, by the number of its tab
+  fn render_agent_panel(&self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    let text = cx.editor.theme.get("ui.text");
+    surface.clear_with(area, cx.editor.theme.get("ui.background"));
+    let block = Block::default().title("agent tasks").borders(Borders::ALL);
+    let inner = block.inner(area).inner(&Margin::horizontal(1));
+    block.render(area, surface);
+
+    let agents = self.tabs.iter().enumerate().filter(|(_, tab)| tab.agent);
+    for (row, (index, tab)) in agents.enumerate().take(inner.height as usize) {
+      let session = tab.session.as_ref().unwrap_or(cx.session);
+      let Some(task) = &session.agent_task else {
+        continue;
+      };
+      let goal = task.goal.lines().next().unwrap_or_default();
+      surface.set_stringn(
+        inner.x,
+        inner.y + row as u16,
+        format!("{} {} — {}", index + 1, goal, task.progress()),
+        inner.width as usize,
+        text,
+      );
+    }
+  }
+}
+
+impl Component for SessionTabs {
+  fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+    if self.height() == 0 {
+      return;
+    }
+    let theme = &cx.editor.theme;
+    let (area, below) = (area.with_height(1), area.clip_top(1));
+    surface.clear_with(
+      area,
+      theme.try_get("ui.bufferline.background").unwrap_or_else(|| theme.get("ui.statusline")),
+    );
+    let active_style =
+      theme.try_get("ui.bufferline.active").unwrap_or_else(|| theme.get("ui.statusline.active"));
+    let inactive_style =
+      theme.try_get("ui.bufferline").unwrap_or_else(|| theme.get("ui.statusline.inactive"));
+    let unread_style = inactive_style.patch(theme.get("info"));
+
+    let mut x = area.x;
+    for (index, tab) in self.tabs.iter().enumerate() {
+      let session = tab.session.as_ref().unwrap_or(cx.session);
+      let style = if index == self.active { active_style } else { inactive_style };
+      let text = format!(" {} {} ", index + 1, Self::title(session));
+      x = surface.set_stringn(x, area.y, text, area.right().saturating_sub(x) as usize, style).0;
+      if tab.unread {
+        x = surface
+          .set_stringn(x, area.y, "● ", area.right().saturating_sub(x) as usize, unread_style)
+          .0;
+      }
+      if x >= area.right() {
+        break;
+      }
+    }
+
+    let panel_height = self.agent_panel_height();
+    if panel_height > 0 {
+      self.render_agent_panel(below.with_height(panel_height), surface, cx);
+    }
+  }
+
+  fn id(&self) -> Option<&'static str> {
+    Some(Self::ID)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_switching_tabs_swaps_sessions() {
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let mut current = Session { action_tx: Some(tx), ..Default::default() };
+    let first = current.id;
+    let mut tabs = SessionTabs::new(first, SymbolIndex::default());
+    assert_eq!(tabs.height(), 0);
+
+    tabs.open(&mut current, SessionConfig::default());
+    let second = current.id;
+    assert_ne!(first, second);
+    assert_eq!((tabs.tabs.len(), tabs.height()), (2, 1));
+    assert_eq!(tabs.take_opened().len(), 1);
+
+    // the first session went on in the background and is marked once it has news
+    tabs.background_tab(first).unwrap().unread = true;
+    assert!(tabs.background_tab(second).is_none());
+    tabs.cycle(&mut current, 1);
+    assert_eq!(current.id, first);
+    assert!(!tabs.tabs[0].unread);
+
+    let closed = tabs.close(&mut current).unwrap();
+    assert_eq!((closed.id, current.id, tabs.tabs.len()), (first, second, 1));
+    assert_eq!(tabs.take_closed(), vec![first]);
+    assert!(tabs.close(&mut current).is_none());
+  }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use helix_view::{editor::Action, graphics::Rect, Editor, ViewId};
+
+use crate::commands::ChatMessageItem;
+use crate::compositor::Compositor;
+use crate::ui::{EditorView, SessionView};
+
+/// the messages on the left half of `area` and the file opened beside them on the right
+pub fn split_columns(area: Rect) -> (Rect, Rect) {
+  let messages_width = area.width / 2;
+  (area.with_width(messages_width), area.clip_left(messages_width))
+}
+
+/// the view of the file beside the session, if it is open
+pub fn split_view(compositor: &mut Compositor) -> Option<ViewId> {
+  compositor.find::<EditorView>().and_then(|input| input.split_view)
+}
+
+/// the session is drawn beside the view and the input lays it out, so both are told about it
+pub fn set_split_view(compositor: &mut Compositor, view_id: Option<ViewId>) {
+  if let Some(input) = compositor.find::<EditorView>() {
+    input.split_view = view_id;
+  }
+  if let Some(session) = compositor.find::<SessionView<ChatMessageItem>>() {
+    session.split_view = view_id;
+  }
+}
+
+/// shows `path` in the view beside the session, opening the view if there is none. the focus
+/// stays where it was
+pub fn show_in_split_view(
+  editor: &mut Editor,
+  view_id: Option<ViewId>,
+  path: &Path,
+) -> anyhow::Result<ViewId> {
+  let focus = editor.tree.focus;
+  let doc_id = editor.open(path, Action::Load)?;
+  let view_id = match view_id.filter(|view_id| editor.tree.contains(*view_id)) {
+    Some(view_id) => {
+      editor.focus(view_id);
+      editor.switch(doc_id, Action::Replace);
+      view_id
+    },
+    None => {
+      editor.switch(doc_id, Action::VerticalSplit);
+      editor.tree.focus
+    },
+  };
+  if editor.tree.contains(focus) {
+    editor.focus(focus);
+  }
+  Ok(view_id)
+}
+
+/// reloads an open file a tool changed, so the views of it show the change. a file with changes
+/// that were not written is left as it is
+pub fn reload_changed_file(editor: &mut Editor, path: &Path) {
+  let Some(doc) = editor.document_by_path(path) else {
+    return;
+  };
+  if doc.is_modified() {
+    editor.set_error(format!("{} was changed by a tool but has unsaved changes", path.display()));
+    return;
+  }
+  let doc_id = doc.id();
+  let scrolloff = editor.config().scrolloff;
+  let Some((view, _)) = editor.tree.views_mut().find(|(view, _)| view.doc == doc_id) else {
+    return;
+  };
+  let doc = editor.documents.get_mut(&doc_id).unwrap();
+  view.sync_changes(doc);
+  match doc.reload(view, &editor.diff_providers) {
+    Ok(_) => {
+      view.ensure_cursor_in_view(doc, scrolloff);
+      editor.language_servers.file_event_handler.file_changed(path.to_path_buf());
+    },
+    Err(e) => log::error!("error reloading {}: {}", path.display(), e),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_split_columns() {
+    let (messages, file) = split_columns(Rect::new(2, 1, 81, 20));
+    assert_eq!(messages, Rect::new(2, 1, 40, 20));
+    assert_eq!(file, Rect::new(42, 1, 41, 20));
+  }
+}
@@ -0,0 +1,64 @@
+use helix_view::{
+  graphics::{Color, Modifier, Style},
+  Theme,
+};
+use sazid::app::file_patch::{DiffLine, FileEdit};
+use tui::text::{Span, Spans};
+
+/// What the user decided to do with a file edit shown as a diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+  Pending,
+  Accepted,
+  Reverted,
+}
+
+/// A colored unified diff of a change a tool made to a file, shown in the chat in place of the
+/// arguments the tool was called with
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+  pub edit: FileEdit,
+  pub status: DiffStatus,
+}
+
+impl FileDiff {
+  pub fn new(edit: FileEdit) -> Self {
+    FileDiff { edit, status: DiffStatus::Pending }
+  }
+
+  /// A header naming the file and its status, followed by the diff until the change is
+  /// accepted or reverted
+  pub fn lines(&self, theme: Option<&Theme>) -> Vec<Spans<'static>> {
+    let style = |scope: &str, fallback: Color| {
+      theme.and_then(|theme| theme.try_get(scope)).unwrap_or_else(|| Style::default().fg(fallback))
+    };
+    let added = style("diff.plus", Color::Green);
+    let removed = style("diff.minus", Color::Red);
+    let muted = Style::default().fg(Color::Gray);
+
+    let action = if self.edit.original.is_some() { "modified" } else { "created" };
+    let status = match self.status {
+      DiffStatus::Pending => "space e a to accept, space e r to revert",
+      DiffStatus::Accepted => "accepted",
+      DiffStatus::Reverted => "reverted",
+    };
+    let mut lines = vec![Spans::from(vec![
+      Span::styled(
+        format!("   {} {} ", action, self.edit.path.display()),
+        Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+      ),
+      Span::styled(format!("({})", status), muted),
+    ])];
+    if self.status != DiffStatus::Pending {
+      return lines;
+    }
+
+    lines.extend(self.edit.diff_lines().into_iter().map(|line| match line {
+      DiffLine::Unchanged(line) => Spans::from(format!("     {}", line)),
+      DiffLine::Removed(line) => Spans::from(Span::styled(format!("   - {}", line), removed)),
+      DiffLine::Added(line) => Spans::from(Span::styled(format!("   + {}", line), added)),
+      DiffLine::Skipped => Spans::from(Span::styled("     ...", muted)),
+    }));
+    lines
+  }
+}
@@ -1,3 +1,4 @@
+pub mod diff;
 pub mod paragraph;
 pub mod plaintext_reflow;
 pub mod reflow;
@@ -51,6 +51,8 @@ use super::paragraph::Wrap;
 #[derive(Debug, Clone, PartialEq)]
 pub enum MessageType<'a> {
   Chat(&'a ChatMessageItem),
+  /// the cached, already wrapped plain text of a message, drawn without styling
+  Plain(&'a ChatMessageItem),
   Text(String),
 }
 #[derive(Debug, Clone, PartialEq)]
@@ -76,7 +78,9 @@ pub struct MessageCell<'a> {
 impl<'a> MessageCell<'a> {
   pub fn get_height(&self, width: u16) -> u16 {
     match &self.message {
-      MessageType::Chat(message) => message.get_wrapped_height(width) as u16,
+      MessageType::Chat(message) | MessageType::Plain(message) => {
+        message.get_wrapped_height(width) as u16
+      },
       MessageType::Text(s) => s.lines().count() as u16,
     }
   }
@@ -135,9 +139,22 @@ impl<'a> MessageCell<'a> {
   ) {
     let text = match &self.message {
       MessageType::Chat(message) => message.format_to_text(Some(theme), config_loader.clone()),
+      MessageType::Plain(message) => Text::from(message.plain_text.to_string()),
       MessageType::Text(text) => Text::from(text.clone()),
     };
-    let style = Style::default();
+    let wrap = match self.message {
+      MessageType::Plain(_) => None,
+      _ => self.wrap_trim.map(|trim| Wrap { trim }),
+    };
+    let area = match &self.block {
+      Some(block) => {
+        let inner = block.inner(area);
+        block.clone().render(area, buf);
+        inner
+      },
+      None => area,
+    };
+    let style = self.style;
     let _scroll = (0, 0);
     Self::format_text(
       buf,
@@ -145,7 +162,7 @@ impl<'a> MessageCell<'a> {
       true,
       &text,
       style,
-      self.wrap_trim.map(|trim| Wrap { trim }),
+      wrap,
       area,
       self.alignment,
       self.char_idx,
@@ -328,7 +345,7 @@ impl<'a> Row<'a> {
   /// Returns the contents of cells as plain text, without styles and colors.
   pub fn cell_text(&self) -> impl Iterator<Item = String> + '_ {
     self.cells.iter().map(|cell| match &cell.message {
-      MessageType::Chat(message) => message.plain_text.to_string(),
+      MessageType::Chat(message) | MessageType::Plain(message) => message.plain_text.to_string(),
       MessageType::Text(s) => s.to_string(),
     })
   }
@@ -421,6 +438,9 @@ pub struct Table<'a> {
   header: Option<Row<'a>>,
   /// Data to display in each row
   rows: Vec<Row<'a>>,
+  /// Index of the first row and the heights of all rows, when the rows are a window of a longer
+  /// list
+  row_window: Option<(usize, Vec<u16>)>,
   cursor_position: Option<Position>,
   cursor_style: Option<Style>,
 }
@@ -442,6 +462,7 @@ impl<'a> Table<'a> {
       highlight_symbol: None,
       header: None,
       rows: rows.into_iter().collect(),
+      row_window: None,
     }
   }
 
@@ -501,6 +522,13 @@ impl<'a> Table<'a> {
     self
   }
 
+  /// The rows start at `first_row` of a longer list, whose heights including row spacing are
+  /// `row_heights`. Rows outside of the viewport are placed by their height without being built
+  pub fn row_window(mut self, first_row: usize, row_heights: Vec<u16>) -> Self {
+    self.row_window = Some((first_row, row_heights));
+    self
+  }
+
   pub fn get_rows(&self) -> Vec<&Row<'a>> {
     self.header.as_ref().into_iter().chain(self.rows.iter()).collect()
   }
@@ -577,7 +605,10 @@ impl<'a> Table<'a> {
     // 17                3 E -----------------------
     //                   5
     //
-    let mut row_index = 0;
+    let (first_row, mut row_index) = match &self.row_window {
+      Some((first_row, heights)) => (*first_row, heights.iter().take(*first_row).sum()),
+      None => (0, 0),
+    };
 
     self
     .rows
@@ -585,6 +616,7 @@ impl<'a> Table<'a> {
       // .skip(offset)
       .enumerate()
       .map(|(i, row_text)| {
+          let i = first_row + i;
           let row_start_index = row_index;
           let row_end_index = (row_start_index + row_text.height).saturating_sub(1);
 
@@ -602,7 +634,7 @@ impl<'a> Table<'a> {
           let row_y =  row_start_index.saturating_sub(table_start_index).saturating_sub(row_skip_lines);
 
           let row_visible_lines =
-              (row_end_index + 1).min(table_end_index).saturating_sub(row_start_index).saturating_sub(row_skip_lines);
+              (row_end_index + 1).min(table_end_index + 1).saturating_sub(row_start_index).saturating_sub(row_skip_lines);
 
         // if i< 3{
         //   log::info!("
@@ -703,6 +735,49 @@ impl TableState {
     }
   }
 
+  /// The rows intersecting the viewport, widened by `overscan` rows on either side
+  pub fn visible_rows(&self, overscan: usize) -> std::ops::Range<usize> {
+    let rows = self.row_heights.len();
+    let viewport_end = self.vertical_scroll as usize + self.viewport_height as usize;
+    let (mut start, mut end) = (rows, rows);
+    let mut row_top = 0;
+    for (i, height) in self.row_heights.iter().enumerate() {
+      if row_top >= viewport_end {
+        end = i;
+        break;
+      }
+      row_top += *height as usize;
+      if start == rows && row_top > self.vertical_scroll as usize {
+        start = i;
+      }
+    }
+    start.saturating_sub(overscan)..end.saturating_add(overscan).min(rows)
+  }
+
+  /// The row drawn `line` lines from the top of the table
+  pub fn row_at(&self, line: u16) -> Option<usize> {
+    let mut row_bottom = 0;
+    self.row_heights.iter().position(|height| {
+      row_bottom += *height as usize;
+      row_bottom > line as usize
+    })
+  }
+
+  /// The first line and height of a scrollbar thumb as tall as the viewport, none when every row
+  /// fits in it
+  pub fn scrollbar_thumb(&self) -> Option<(u16, u16)> {
+    // summed as usize, the rows of a long session are taller than u16::MAX together
+    let total = self.row_heights.iter().map(|height| *height as usize).sum::<usize>();
+    let height = self.viewport_height as usize;
+    if height == 0 || total <= height {
+      return None;
+    }
+    let thumb_height = (height * height).div_ceil(total).min(height);
+    let thumb_line = (height - thumb_height) * self.vertical_scroll as usize
+      / std::cmp::max(1, self.scroll_max as usize);
+    Some((thumb_line as u16, thumb_height as u16))
+  }
+
   pub fn selected(&self) -> Option<usize> {
     self.selected
   }
@@ -754,7 +829,16 @@ impl<'a> Table<'a> {
     let column_widths = column_areas.iter().map(|a| a.width).collect::<Vec<_>>();
     self.rows.iter_mut().for_each(|row| row.update_wrapped_heights(column_widths.clone()));
 
-    state.row_heights = self.row_heights();
+    state.row_heights = match &self.row_window {
+      Some((first_row, heights)) => {
+        let mut heights = heights.clone();
+        for (height, row_height) in heights.iter_mut().skip(*first_row).zip(self.row_heights()) {
+          *height = row_height;
+        }
+        heights
+      },
+      None => self.row_heights(),
+    };
 
     let highlight_symbol = self.highlight_symbol.unwrap_or("");
     let blank_symbol = " ".repeat(highlight_symbol.width());
@@ -879,10 +963,254 @@ fn render_cell(
 #[cfg(test)]
 mod tests {
   use super::*;
+  use quickcheck::quickcheck;
 
   #[test]
   #[should_panic]
   fn table_invalid_percentages() {
     Table::new(vec![]).widths(&[Constraint::Percentage(110)]);
   }
+
+  fn table(heights: &[u16]) -> Table<'static> {
+    Table::new(
+      heights
+        .iter()
+        .map(|h| Row::new(vec![MessageCell::new(MessageType::Text(String::new()))]).height(*h)),
+    )
+  }
+
+  fn extents(
+    heights: &[u16],
+    vertical_scroll: u16,
+    row_spacing: u16,
+    max_height: u16,
+  ) -> Vec<Option<(usize, u16, u16, u16)>> {
+    table(heights).get_row_extents(vertical_scroll, row_spacing, max_height)
+  }
+
+  #[test]
+  fn row_extents_unscrolled() {
+    assert_eq!(extents(&[3, 3, 3], 0, 0, 5), vec![Some((0, 0, 0, 3)), Some((1, 3, 0, 2)), None]);
+  }
+
+  #[test]
+  fn row_extents_skip_lines_of_partially_scrolled_row() {
+    assert_eq!(extents(&[3, 3, 3], 4, 0, 5), vec![None, Some((1, 0, 1, 2)), Some((2, 2, 0, 3))]);
+  }
+
+  #[test]
+  fn row_extents_with_row_spacing() {
+    assert_eq!(extents(&[2, 2, 2], 0, 1, 4), vec![Some((0, 0, 0, 2)), Some((1, 3, 0, 1)), None]);
+    assert_eq!(extents(&[2, 2, 2], 2, 1, 4), vec![None, Some((1, 1, 0, 2)), None]);
+  }
+
+  #[test]
+  fn row_extents_row_taller_than_viewport() {
+    assert_eq!(extents(&[10], 3, 0, 4), vec![Some((0, 0, 3, 4))]);
+    assert_eq!(extents(&[10], 6, 0, 4), vec![Some((0, 0, 6, 4))]);
+  }
+
+  #[test]
+  fn row_heights_include_spacing() {
+    assert_eq!(table(&[1, 4, 2]).row_spacing(1).row_heights(), vec![2, 5, 3]);
+  }
+
+  #[test]
+  fn wrapped_height_is_tallest_cell() {
+    let mut row = Row::new(vec![
+      MessageCell::new(MessageType::Text("one".to_string())),
+      MessageCell::new(MessageType::Text("one\ntwo\nthree".to_string())),
+    ]);
+    row.update_wrapped_heights(vec![10, 10]);
+    assert_eq!(row.total_height(), 3);
+  }
+
+  #[test]
+  fn format_text_wraps_at_word_boundaries() {
+    let area = Rect::new(0, 0, 10, 5);
+    let mut buf = Buffer::empty(area);
+    let wrapped = MessageCell::format_text(
+      &mut buf,
+      true,
+      false,
+      &Text::from("abcd efghij klmnop"),
+      Style::default(),
+      Some(Wrap { trim: true }),
+      area,
+      Alignment::Left,
+      None,
+      0,
+      None,
+      None,
+    )
+    .unwrap();
+    assert_eq!(wrapped.to_string().trim_end(), "abcd\nefghij\nklmnop");
+  }
+
+  #[test]
+  fn sticky_scroll_follows_incoming_text() {
+    let mut state = TableState {
+      row_heights: vec![5, 5, 5],
+      viewport_height: 10,
+      sticky_scroll: true,
+      ..Default::default()
+    };
+    state.update_sticky_scroll();
+    assert_eq!(state.scroll_max, 5);
+    assert_eq!(state.vertical_scroll, 5);
+
+    state.row_heights.push(4);
+    state.update_sticky_scroll();
+    assert_eq!(state.vertical_scroll, 9);
+
+    state.scroll_by(3, Direction::Backward);
+    assert!(!state.sticky_scroll);
+    state.row_heights.push(4);
+    state.update_sticky_scroll();
+    assert_eq!(state.vertical_scroll, 6);
+
+    state.scroll_by(100, Direction::Forward);
+    assert!(state.sticky_scroll);
+    assert_eq!(state.vertical_scroll, 13);
+  }
+
+  #[test]
+  fn scrollbar_thumb_tracks_the_scroll() {
+    let mut state =
+      TableState { row_heights: vec![5, 5, 10], viewport_height: 10, ..Default::default() };
+    state.update_sticky_scroll();
+    assert_eq!(state.scrollbar_thumb(), Some((0, 5)));
+    assert_eq!(state.row_at(state.viewport_height - 1), Some(1));
+
+    state.scroll_by(10, Direction::Forward);
+    assert_eq!(state.scrollbar_thumb(), Some((5, 5)));
+    assert_eq!(state.row_at(state.vertical_scroll), Some(2));
+    assert_eq!(state.row_at(20), None);
+
+    state.row_heights = vec![4, 4];
+    state.update_sticky_scroll();
+    assert_eq!(state.scrollbar_thumb(), None);
+
+    state.row_heights = vec![u16::MAX, u16::MAX];
+    state.vertical_scroll = 0;
+    assert_eq!(state.scrollbar_thumb(), Some((0, 1)));
+  }
+
+  #[test]
+  fn scroll_top_disables_sticky_scroll() {
+    let mut state = TableState {
+      row_heights: vec![20],
+      viewport_height: 10,
+      sticky_scroll: true,
+      ..Default::default()
+    };
+    state.update_sticky_scroll();
+    state.scroll_top();
+    state.update_sticky_scroll();
+    assert_eq!(state.vertical_scroll, 0);
+  }
+
+  #[test]
+  fn visible_rows_include_overscan() {
+    let mut state =
+      TableState { row_heights: vec![3, 3, 3, 3, 3, 3], viewport_height: 4, ..Default::default() };
+    assert_eq!(state.visible_rows(0), 0..2);
+    state.vertical_scroll = 7;
+    assert_eq!(state.visible_rows(0), 2..4);
+    assert_eq!(state.visible_rows(1), 1..5);
+    state.vertical_scroll = 14;
+    assert_eq!(state.visible_rows(2), 2..6);
+    assert_eq!(TableState::default().visible_rows(2), 0..0);
+  }
+
+  #[test]
+  fn row_window_places_rows_after_skipped_heights() {
+    let table = table(&[3, 3]).row_window(2, vec![3, 3, 3, 3, 3]);
+    assert_eq!(table.get_row_extents(7, 0, 4), vec![Some((2, 0, 1, 2)), Some((3, 2, 0, 2))]);
+  }
+
+  fn row_heights(heights: &[u8]) -> Vec<u16> {
+    heights.iter().map(|h| (*h % 20) as u16 + 1).collect()
+  }
+
+  quickcheck! {
+    fn prop_row_extents_stay_within_table(
+      heights: Vec<u8>,
+      vertical_scroll: u8,
+      row_spacing: u8,
+      max_height: u8
+    ) -> bool {
+      let heights = row_heights(&heights);
+      let row_spacing = (row_spacing % 3) as u16;
+      let max_height = (max_height % 50) as u16 + 1;
+      let row_extents = extents(&heights, vertical_scroll as u16, row_spacing, max_height);
+
+      let mut previous_bottom = 0;
+      row_extents.len() == heights.len()
+        && row_extents.iter().enumerate().all(|(i, extent)| match extent {
+          None => true,
+          Some((index, row_y, skip_lines, visible_lines)) => {
+            let in_bounds = *index == i
+              && row_y + visible_lines <= max_height
+              && skip_lines + visible_lines <= heights[i]
+              && (*visible_lines == 0 || *row_y >= previous_bottom);
+            previous_bottom = previous_bottom.max(row_y + visible_lines);
+            in_bounds
+          },
+        })
+    }
+
+    fn prop_row_extents_fill_table(heights: Vec<u8>, vertical_scroll: u8, max_height: u8) -> bool {
+      let heights = row_heights(&heights);
+      let vertical_scroll = vertical_scroll as u16;
+      let max_height = (max_height % 50) as u16 + 1;
+      let content_height: u16 = heights.iter().sum();
+      let visible: u16 = extents(&heights, vertical_scroll, 0, max_height)
+        .iter()
+        .flatten()
+        .map(|(_, _, _, visible_lines)| visible_lines)
+        .sum();
+      visible == max_height.min(content_height.saturating_sub(vertical_scroll))
+    }
+
+    fn prop_scroll_stays_in_range(heights: Vec<u8>, viewport_height: u8, scrolls: Vec<(u8, bool)>) -> bool {
+      let mut state = TableState {
+        row_heights: row_heights(&heights),
+        viewport_height: viewport_height as u16,
+        sticky_scroll: true,
+        ..Default::default()
+      };
+      state.update_sticky_scroll();
+      scrolls.iter().all(|(amount, forward)| {
+        let direction = if *forward { Direction::Forward } else { Direction::Backward };
+        state.scroll_by(*amount as u16, direction);
+        state.vertical_scroll <= state.scroll_max
+          && state.sticky_scroll == (state.vertical_scroll == state.scroll_max)
+      })
+    }
+
+    fn prop_scroll_to_selection_shows_selected_row(
+      heights: Vec<u8>,
+      vertical_scroll: u8,
+      selected: usize
+    ) -> bool {
+      let heights = row_heights(&heights);
+      if heights.is_empty() {
+        return true;
+      }
+      let selected = selected % heights.len();
+      let mut state = TableState {
+        row_heights: heights.clone(),
+        viewport_height: 21,
+        vertical_scroll: vertical_scroll as u16,
+        selected: Some(selected),
+        ..Default::default()
+      };
+      state.scroll_to_selection();
+      let selection_top: u16 = heights.iter().take(selected).sum();
+      let selection_bottom = selection_top + heights[selected];
+      selection_top >= state.vertical_scroll
+        && selection_bottom <= state.vertical_scroll + state.viewport_height
+    }
+  }
 }
@@ -272,6 +272,7 @@ mod test {
     let code_snippet = "fn main() {\n  println!(\"Hello, world!\");\n}".to_string();
     app.send_language_server_event(sazid::action::LsiAction::ReplaceSymbolText(
       code_snippet.clone(),
+      Default::default(),
       query.clone(),
     ))?;
     run_event_loop_until_idle(&mut app).await;
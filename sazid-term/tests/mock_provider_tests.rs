@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sazid::app::{
+  headless_session::{HeadlessEvent, HeadlessSession},
+  model_tools::shell_tool::ShellToolConfig,
+  provider_fixtures::ProviderMode,
+  session_config::{SessionConfig, WorkspaceParams},
+  session_store::SessionStore,
+};
+use serde_json::json;
+use tempfile::tempdir;
+
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(300);
+
+// the model creates a crate that does not compile, checks it, patches it and checks it again
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_check_patch_loop() -> anyhow::Result<()> {
+  let workspace = tempdir()?;
+  let root = workspace.path();
+  let patch = concat!(
+    "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,3 +1,3 @@\n",
+    " pub fn answer() -> u32 {\n-  \"42\"\n+  42\n }\n"
+  );
+  let script = json!({
+    "steps": [
+      {
+        "content": "creating the crate",
+        "tool_calls": [
+          {
+            "name": "create_file",
+            "arguments": {
+              "path": root.join("Cargo.toml"),
+              "content": "[package]\nname = \"answer\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+            },
+          },
+          {
+            "name": "create_file",
+            "arguments": {
+              "path": root.join("src/lib.rs"),
+              "content": "pub fn answer() -> u32 {\n  \"42\"\n}\n",
+            },
+          },
+        ],
+      },
+      { "expect": "file created", "tool_calls": [{ "name": "cargo_check" }] },
+      {
+        "expect": "mismatched types",
+        "tool_calls": [{ "name": "apply_patch", "arguments": { "patch": patch } }],
+      },
+      { "expect": "hunk 1 applied at line 1", "tool_calls": [{ "name": "cargo_check" }] },
+      { "expect": "exit code 0", "content": "the crate compiles" },
+    ],
+  });
+  let script_path = root.join("script.json");
+  std::fs::write(&script_path, script.to_string())?;
+
+  let config = SessionConfig {
+    workspace: Some(WorkspaceParams {
+      workspace_path: root.to_path_buf(),
+      language: "rust".to_string(),
+      language_server: "rust-analyzer".to_string(),
+      doc_path: None,
+      name: None,
+    }),
+    shell_tools: vec![ShellToolConfig {
+      name: "cargo_check".to_string(),
+      description: "run cargo check in the workspace".to_string(),
+      parameters: None,
+      command: "cargo check --offline --quiet --message-format short".to_string(),
+      timeout_secs: 240,
+    }],
+    provider: ProviderMode::Mock { path: script_path },
+    ..Default::default()
+  };
+  let store = Arc::new(Mutex::new(SessionStore::open_in_memory()?));
+  let mut headless = HeadlessSession::new(config, store)?;
+  headless.send("write a crate with an answer function");
+
+  let mut tool_calls = vec![];
+  let answer = tokio::time::timeout(ANSWER_TIMEOUT, async {
+    loop {
+      match headless.next_event().await? {
+        HeadlessEvent::ToolCall { name, .. } => tool_calls.push(name),
+        HeadlessEvent::Done(answer) => return anyhow::Ok(answer),
+        _ => {},
+      }
+    }
+  })
+  .await??;
+
+  assert_eq!(answer, "the crate compiles");
+  assert_eq!(
+    tool_calls,
+    vec!["create_file", "create_file", "cargo_check", "apply_patch", "cargo_check"]
+  );
+  assert_eq!(
+    std::fs::read_to_string(root.join("src/lib.rs"))?,
+    "pub fn answer() -> u32 {\n  42\n}\n"
+  );
+  Ok(())
+}
@@ -0,0 +1,3 @@
+ALTER TABLE embedding_pages
+  DROP COLUMN start_line,
+  DROP COLUMN end_line;
@@ -0,0 +1,3 @@
+ALTER TABLE embedding_pages
+  ADD COLUMN start_line INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN end_line INTEGER NOT NULL DEFAULT 0;
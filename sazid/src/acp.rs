@@ -0,0 +1,313 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::app::errors::SazidError;
+use crate::app::headless_session::{HeadlessEvent, HeadlessSession};
+use crate::app::messages::get_chat_message_text;
+use crate::app::session_config::{SessionConfig, WorkspaceParams};
+use crate::app::session_store::SessionStore;
+use crate::server::{
+  error_response, parse_params, write_message, Request, RpcError, INVALID_PARAMS, METHOD_NOT_FOUND,
+  PARSE_ERROR,
+};
+
+// the version of the agent client protocol implemented here
+const PROTOCOL_VERSION: u64 = 1;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewSessionParams {
+  #[serde(default)]
+  cwd: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SessionParams {
+  session_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PromptParams {
+  session_id: String,
+  prompt: Vec<Value>,
+}
+
+/// runs as an agent of the agent client protocol, the json-rpc protocol editors like zed host
+/// coding agents with, reading requests from `input` and writing to `output` until `input` ends
+pub async fn serve_acp(
+  config: SessionConfig,
+  store: SessionStore,
+  input: impl AsyncRead + Unpin + Send + 'static,
+  output: &mut (impl AsyncWrite + Unpin),
+) -> Result<(), SazidError> {
+  // lines are read while a prompt is answered, so it can be cancelled
+  let (line_tx, mut line_rx) = mpsc::unbounded_channel();
+  tokio::spawn(async move {
+    let mut lines = BufReader::new(input).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+      if line_tx.send(line).is_err() {
+        break;
+      }
+    }
+  });
+  let mut agent = Agent {
+    config,
+    store: Arc::new(Mutex::new(store)),
+    sessions: HashMap::new(),
+    pending: VecDeque::new(),
+  };
+  loop {
+    let line = match agent.pending.pop_front() {
+      Some(line) => line,
+      None => match line_rx.recv().await {
+        Some(line) => line,
+        None => return Ok(()),
+      },
+    };
+    if !line.trim().is_empty() {
+      agent.handle(&line, &mut line_rx, output).await?;
+    }
+  }
+}
+
+struct Agent {
+  config: SessionConfig,
+  store: Arc<Mutex<SessionStore>>,
+  sessions: HashMap<String, HeadlessSession>,
+  // lines that arrived while a prompt was answered
+  pending: VecDeque<String>,
+}
+
+impl Agent {
+  // answers one request line, errors are those of writing to the client
+  async fn handle(
+    &mut self,
+    line: &str,
+    lines: &mut UnboundedReceiver<String>,
+    output: &mut (impl AsyncWrite + Unpin),
+  ) -> Result<(), SazidError> {
+    let request = match serde_json::from_str::<Request>(line) {
+      Ok(request) => request,
+      Err(e) => {
+        let error = RpcError { code: PARSE_ERROR, message: e.to_string() };
+        return write_message(output, &error_response(Value::Null, error)).await;
+      },
+    };
+    let result = self.call(&request, lines, output).await;
+    let Some(id) = request.id else {
+      return Ok(());
+    };
+    let response = match result {
+      Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+      Err(error) => error_response(id, error),
+    };
+    write_message(output, &response).await
+  }
+
+  async fn call(
+    &mut self,
+    request: &Request,
+    lines: &mut UnboundedReceiver<String>,
+    output: &mut (impl AsyncWrite + Unpin),
+  ) -> Result<Value, RpcError> {
+    let params = request.params.clone();
+    match request.method.as_str() {
+      "initialize" => Ok(json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "agentCapabilities": {
+          "loadSession": true,
+          "promptCapabilities": { "image": false, "audio": false, "embeddedContext": true },
+        },
+        "authMethods": [],
+      })),
+      // api keys come from the environment, the keyring or the config file
+      "authenticate" => Ok(Value::Null),
+      "session/new" => {
+        let params: NewSessionParams = parse_params(params)?;
+        let mut config = self.config.clone();
+        config.id = SessionConfig::generate_session_id();
+        if let Some(cwd) = params.cwd {
+          config.workspace = Some(workspace_at(&config, cwd));
+        }
+        let session = HeadlessSession::new(config, self.store.clone())?;
+        let session_id = session.id().to_string();
+        self.sessions.insert(session_id.clone(), session);
+        Ok(json!({ "sessionId": session_id }))
+      },
+      "session/load" => {
+        let params: SessionParams = parse_params(params)?;
+        let session =
+          HeadlessSession::load(self.config.clone(), self.store.clone(), &params.session_id)?;
+        // the client shows the conversation so far from these updates
+        for message in &session.session.messages {
+          let kind = match message.message {
+            ChatCompletionRequestMessage::User(_) => "user_message_chunk",
+            ChatCompletionRequestMessage::Assistant(_) => "agent_message_chunk",
+            _ => continue,
+          };
+          let text = get_chat_message_text(&message.message);
+          if !text.is_empty() {
+            let update =
+              json!({ "sessionUpdate": kind, "content": { "type": "text", "text": text } });
+            write_message(output, &session_update(&params.session_id, update)).await?;
+          }
+        }
+        self.sessions.insert(params.session_id, session);
+        Ok(Value::Null)
+      },
+      "session/prompt" => {
+        let params: PromptParams = parse_params(params)?;
+        let Some(session) = self.sessions.get_mut(&params.session_id) else {
+          return Err(RpcError {
+            code: INVALID_PARAMS,
+            message: format!("no session {}", params.session_id),
+          });
+        };
+        session.send(&prompt_text(&params.prompt));
+        loop {
+          let event = tokio::select! {
+            event = session.next_event() => event?,
+            Some(line) = lines.recv() => {
+              if is_cancel(&line, &params.session_id) {
+                session.cancel();
+                return Ok(json!({ "stopReason": "cancelled" }));
+              }
+              self.pending.push_back(line);
+              continue;
+            },
+          };
+          let update = match event {
+            HeadlessEvent::Token(text) => json!({
+              "sessionUpdate": "agent_message_chunk",
+              "content": { "type": "text", "text": text },
+            }),
+            HeadlessEvent::ToolCall { id, name, arguments } => {
+              let raw_input = serde_json::from_str(&arguments).unwrap_or(Value::String(arguments));
+              json!({
+                "sessionUpdate": "tool_call",
+                "toolCallId": id,
+                "title": name,
+                "kind": tool_kind(&name),
+                "status": "in_progress",
+                "rawInput": raw_input,
+              })
+            },
+            HeadlessEvent::ToolResult { id, content } => {
+              let status =
+                if content.starts_with("Tool Call Error") { "failed" } else { "completed" };
+              json!({
+                "sessionUpdate": "tool_call_update",
+                "toolCallId": id,
+                "status": status,
+                "content": [{ "type": "content", "content": { "type": "text", "text": content } }],
+              })
+            },
+            HeadlessEvent::Done(_) => return Ok(json!({ "stopReason": "end_turn" })),
+          };
+          write_message(output, &session_update(&params.session_id, update)).await?;
+        }
+      },
+      // a prompt that is not being answered any more
+      "session/cancel" => Ok(Value::Null),
+      method => {
+        Err(RpcError { code: METHOD_NOT_FOUND, message: format!("unknown method {}", method) })
+      },
+    }
+  }
+}
+
+// the configured workspace settings for the directory the client opened the session in
+fn workspace_at(config: &SessionConfig, cwd: PathBuf) -> WorkspaceParams {
+  let (language, language_server) = match &config.workspace {
+    Some(workspace) => (workspace.language.clone(), workspace.language_server.clone()),
+    None => ("rust".to_string(), "rust-analyzer".to_string()),
+  };
+  WorkspaceParams { workspace_path: cwd, language, language_server, doc_path: None, name: None }
+}
+
+fn session_update(session_id: &str, update: Value) -> Value {
+  json!({
+    "jsonrpc": "2.0",
+    "method": "session/update",
+    "params": { "sessionId": session_id, "update": update },
+  })
+}
+
+fn is_cancel(line: &str, session_id: &str) -> bool {
+  serde_json::from_str::<Request>(line).is_ok_and(|request| {
+    request.method == "session/cancel" && request.params["sessionId"] == session_id
+  })
+}
+
+/// the text sent to the model for the content blocks of a prompt. embedded files are fenced
+/// after their uri, linked ones are named by their uri
+fn prompt_text(blocks: &[Value]) -> String {
+  blocks
+    .iter()
+    .filter_map(|block| match block["type"].as_str()? {
+      "text" => block["text"].as_str().map(String::from),
+      "resource" => {
+        let resource = &block["resource"];
+        let text = resource["text"].as_str()?;
+        Some(format!("{}:\n```\n{}\n```", resource["uri"].as_str().unwrap_or_default(), text))
+      },
+      "resource_link" => block["uri"].as_str().map(String::from),
+      _ => None,
+    })
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+// how clients show a call of one of the tools
+fn tool_kind(name: &str) -> &'static str {
+  match name {
+    "create_file" | "lsp_replace_symbol_text" | "modify_file" | "apply_patch" | "multi_edit" => {
+      "edit"
+    },
+    "grep" | "semantic_search" | "treesitter_query" | "search_symbols_all" | "lsp_query" => {
+      "search"
+    },
+    "read_file"
+    | "lsp_read_symbol_source"
+    | "summarize_file"
+    | "lsp_workspace_files"
+    | "list_directory" => "read",
+    _ => "other",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_prompt_text() {
+    let prompt = vec![
+      json!({ "type": "text", "text": "why does this panic" }),
+      json!({
+        "type": "resource",
+        "resource": { "uri": "file:///work/src/main.rs", "text": "fn main() {}" },
+      }),
+      json!({ "type": "resource_link", "uri": "file:///work/Cargo.toml", "name": "Cargo.toml" }),
+      json!({ "type": "image", "data": "" }),
+    ];
+    assert_eq!(
+      prompt_text(&prompt),
+      "why does this panic\n\nfile:///work/src/main.rs:\n```\nfn main() {}\n```\n\nfile:///work/Cargo.toml"
+    );
+    assert!(is_cancel(
+      r#"{"jsonrpc":"2.0","method":"session/cancel","params":{"sessionId":"1700000000"}}"#,
+      "1700000000"
+    ));
+    assert!(!is_cancel(r#"{"jsonrpc":"2.0","method":"session/cancel","params":{}}"#, "1"));
+  }
+}
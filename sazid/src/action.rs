@@ -3,9 +3,14 @@ use std::path::PathBuf;
 use crate::{
   app::{
     database::types::QueryableSession,
-    lsi::query::LsiQuery,
+    edit_validation::EditValidation,
+    file_patch::FileEdit,
+    file_read::FileRead,
+    lsi::{query::LsiQuery, symbol_sync::SymbolSyncProgress},
     messages::ChatMessage,
+    model_tools::tool_permissions::WriteGuard,
     session_config::{SessionConfig, WorkspaceParams},
+    session_title::SessionTitle,
   },
   components::data_manager::DataManagerAction,
 };
@@ -13,7 +18,7 @@ use async_openai::types::{
   ChatCompletionMessageToolCall, ChatCompletionRequestMessage, ChatCompletionTool,
 };
 use helix_lsp::Call;
-use lsp_types::{DocumentSymbol, TextDocumentIdentifier};
+use lsp_types::{DocumentSymbol, Position, TextDocumentIdentifier};
 use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -31,6 +36,17 @@ pub enum SessionAction {
   SetTestToolResponse(ToolType, String),
   ToolCallComplete(ToolType, String),
   ToolCallError(ToolType, String),
+  /// a tool call that did not finish in time, answered with the message. what it sends later
+  /// is dropped
+  ToolCallTimedOut(ToolType, String),
+  FileEdited(i64, FileEdit),
+  FileEditReverted(String),
+  /// files indexed so far and the number of files being indexed
+  IndexProgress(i64, usize, usize),
+  /// the number of files that were indexed and that could not be
+  IndexComplete(i64, usize, usize),
+  /// indexes the workspace in the background, unless it is indexed or being indexed
+  IndexWorkspace(i64),
 
   CreateLoadSessionResponse(QueryableSession),
   AddMessageEmbedding(i64, i64, ChatCompletionRequestMessage),
@@ -45,8 +61,12 @@ pub enum SessionAction {
   ReloadMessages(Vec<(i64, ChatCompletionRequestMessage)>),
   UpdateStatus(Option<String>),
   UpdateToolList(i64, Vec<ChatCompletionTool>),
+  UpdateContextSummary(i64, Vec<i64>, Option<String>),
+  UpdateTitle(i64, Option<SessionTitle>),
 
   SaveSession,
+  /// the session settings of a config file that changed while the app was running
+  ConfigReloaded(Box<SessionConfig>),
 
   LsiAction(LsiAction),
   DataManagerAction(DataManagerAction),
@@ -62,15 +82,41 @@ pub enum LsiAction {
   ChatToolResponse(Box<ChatToolAction>),
   AddWorkspace(WorkspaceParams),
   QueryWorkspaceSymbols(LsiQuery),
+  QueryAllWorkspaceSymbols(LsiQuery),
   GetWorkspaceFiles(LsiQuery),
-  ReplaceSymbolText(String, LsiQuery),
+  ReplaceSymbolText(String, EditValidation, WriteGuard, LsiQuery),
   ReadSymbolSource(LsiQuery),
   GoToSymbolDefinition(LsiQuery),
   GoToSymbolDeclaration(LsiQuery),
   GoToTypeDefinition(LsiQuery),
+  GoToImplementation(LsiQuery),
+  DocumentHighlight(LsiQuery),
+  /// the signatures of the call at a position in a file
+  SignatureHelp(PathBuf, Position, LsiQuery),
+  /// the inlay hints of the lines of a file in the range of the query
+  InlayHints(PathBuf, LsiQuery),
+  /// the lines of a symbol found by name, read for the `read_file` tool
+  ReadFile(FileRead, LsiQuery),
   GetDiagnostics(LsiQuery),
+  SummarizeFile(PathBuf, LsiQuery),
   UpdateWorkspaceFileSymbols(PathBuf, TextDocumentIdentifier, Vec<DocumentSymbol>),
   RequestWorkspaceFileSymbols(PathBuf, TextDocumentIdentifier, usize),
+  /// a file the symbol sync is done with, shown on the status line
+  SymbolSyncProgress(SymbolSyncProgress),
+  /// a symbol query from the `:symbols` command, answered with a picker of the matches
+  SearchSymbols(String),
+  /// a file from the `:outline` command, answered with its symbol tree
+  ShowOutline(PathBuf),
+  /// restarts the language server with the id, and reopens the workspaces it served
+  RestartLanguageServer(usize),
+  /// stops the language server with the id, and closes the workspaces it served
+  StopLanguageServer(usize),
+  /// starts a crashed language server again for the workspaces it served, by path and language
+  RespawnLanguageServer(String, Vec<(PathBuf, String)>),
+  /// a language server did not start or initialize for the workspaces, with the error
+  LanguageServerStartFailed(String, Vec<(PathBuf, String)>, String),
+  /// sends the changed files to the language servers, once a server started is initialized
+  SynchronizeWorkspaceFiles,
   Error(String),
 }
 
@@ -79,6 +125,12 @@ pub enum ChatToolAction {
   UpdateConfig(i64, Box<SessionConfig>),
   CallTool(ChatCompletionMessageToolCall, i64),
   CompleteToolCall(String, ChatCompletionMessageToolCall, i64),
+  /// a call to a tool with the `confirm` permission, waiting for the user to allow it
+  ConfirmToolCall(ChatCompletionMessageToolCall, i64),
+  /// the user's answer to a `ConfirmToolCall`, true to run the call
+  ToolCallConfirmed(ChatCompletionMessageToolCall, i64, bool),
+  /// stops the tool calls of a session that are still running, after it was interrupted
+  AbortToolCalls(i64),
   #[serde(serialize_with = "serialize_boxed_session_action")]
   SessionAction(Box<SessionAction>),
   LsiRequest(Box<LsiAction>),
@@ -88,6 +140,56 @@ pub enum ChatToolAction {
   Error(String),
 }
 
+impl SessionAction {
+  /// the session an action is for, when it names one
+  pub fn session_id(&self) -> Option<i64> {
+    match self {
+      SessionAction::AddMessage(id, _)
+      | SessionAction::FileEdited(id, _)
+      | SessionAction::IndexProgress(id, ..)
+      | SessionAction::IndexComplete(id, ..)
+      | SessionAction::IndexWorkspace(id)
+      | SessionAction::AddMessageEmbedding(id, ..)
+      | SessionAction::UpdateToolList(id, _)
+      | SessionAction::UpdateContextSummary(id, ..)
+      | SessionAction::UpdateTitle(id, _) => Some(*id),
+      SessionAction::ToolCallComplete(tool_type, _)
+      | SessionAction::ToolCallError(tool_type, _)
+      | SessionAction::ToolCallTimedOut(tool_type, _)
+      | SessionAction::SetTestToolResponse(tool_type, _) => match tool_type {
+        ToolType::LsiQuery(query) => Some(query.session_id),
+        ToolType::Generic(id, _) => Some(*id),
+      },
+      SessionAction::LspSymbolQuery(query) => Some(query.session_id),
+      _ => None,
+    }
+  }
+}
+
+impl LsiAction {
+  /// the query of a request a tool call made
+  pub fn query(&self) -> Option<&LsiQuery> {
+    match self {
+      LsiAction::QueryWorkspaceSymbols(query)
+      | LsiAction::QueryAllWorkspaceSymbols(query)
+      | LsiAction::GetWorkspaceFiles(query)
+      | LsiAction::ReplaceSymbolText(_, _, _, query)
+      | LsiAction::ReadSymbolSource(query)
+      | LsiAction::GoToSymbolDefinition(query)
+      | LsiAction::GoToSymbolDeclaration(query)
+      | LsiAction::GoToTypeDefinition(query)
+      | LsiAction::GoToImplementation(query)
+      | LsiAction::DocumentHighlight(query)
+      | LsiAction::GetDiagnostics(query)
+      | LsiAction::SignatureHelp(_, _, query)
+      | LsiAction::InlayHints(_, query)
+      | LsiAction::ReadFile(_, query)
+      | LsiAction::SummarizeFile(_, query) => Some(query),
+      _ => None,
+    }
+  }
+}
+
 pub fn serialize_boxed_session_action<S>(
   action: &SessionAction,
   serializer: S,
@@ -1,20 +1,55 @@
 use serde::{Deserialize, Serialize};
 
+pub mod agent_task;
+pub mod audit_log;
+pub mod changelog;
 pub mod color_math;
+pub mod commit_message;
 pub mod consts;
+pub mod context_manager;
+pub mod continuation;
+pub mod credentials;
 pub mod database;
+pub mod edit_validation;
 pub mod errors;
+pub mod export;
+pub mod file_patch;
+pub mod file_read;
+pub mod file_summary;
 pub mod gpt_interface;
+pub mod headless_session;
 pub mod helpers;
+pub mod hybrid_search;
+pub mod import;
+pub mod input_history;
 pub mod lsi;
 pub mod markdown;
+pub mod mentions;
 pub mod messages;
+pub mod mock_provider;
 pub mod model_tools;
+pub mod openai_config;
+pub mod pinned_files;
+pub mod plan;
+pub mod prompt_profiles;
+pub mod prompt_templates;
+pub mod provider_fixtures;
+pub mod rate_limit;
 pub mod request_validation;
+pub mod retrieval;
+pub mod semantic_search;
 pub mod session_config;
+pub mod session_store;
+pub mod session_title;
+pub mod snippets;
+pub mod structured_output;
+pub mod tool_call_shim;
+pub mod tool_output;
 pub mod tools;
 pub mod treesitter;
 pub mod types;
+pub mod usage;
+pub mod workspace_walk;
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Mode {
@@ -0,0 +1,115 @@
+// a reply ends with this line once the agent considers its goal reached
+const TASK_COMPLETE_MARKER: &str = "TASK COMPLETE";
+
+/// where an agent task is in its loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentTaskState {
+  Running,
+  Complete,
+  /// stopped by the user or after too many steps, with the reason
+  Stopped(String),
+}
+
+/// a goal handed to a session that works towards it on its own. each reply that calls no more
+/// tools is evaluated: the task is complete once the reply says so, otherwise the session is
+/// asked to go on until it runs out of steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentTask {
+  pub goal: String,
+  pub state: AgentTaskState,
+  /// the replies asked for so far, counting the first
+  pub step: usize,
+  pub max_steps: usize,
+  /// what the agent did last, shown with its progress
+  pub last_action: Option<String>,
+}
+
+impl AgentTask {
+  pub fn new(goal: &str, max_steps: usize) -> Self {
+    AgentTask {
+      goal: goal.trim().to_string(),
+      state: AgentTaskState::Running,
+      step: 1,
+      max_steps,
+      last_action: None,
+    }
+  }
+
+  /// the message that starts the task
+  pub fn prompt(&self) -> String {
+    format!(
+      "Work on this goal on your own, without waiting for the user:\n\n{}\n\nPlan the steps, \
+       carry them out with the tools available and check the results. When the goal is reached, \
+       summarize what was done and end the reply with a line saying {}.",
+      self.goal, TASK_COMPLETE_MARKER
+    )
+  }
+
+  pub fn is_running(&self) -> bool {
+    self.state == AgentTaskState::Running
+  }
+
+  /// evaluates a reply that calls no more tools. returns the message asking for the next step,
+  /// none once the task is complete or out of steps
+  pub fn evaluate(&mut self, reply: &str) -> Option<String> {
+    if !self.is_running() {
+      return None;
+    }
+    if reply.lines().any(|line| line.trim().trim_matches(['*', '.']) == TASK_COMPLETE_MARKER) {
+      self.state = AgentTaskState::Complete;
+      return None;
+    }
+    if self.step >= self.max_steps {
+      self.stop(format!("stopped after {} steps", self.max_steps));
+      return None;
+    }
+    self.step += 1;
+    Some(format!(
+      "Continue with the next step towards the goal. This is step {} of at most {}. End the \
+       reply with a line saying {} once the goal is reached.",
+      self.step, self.max_steps, TASK_COMPLETE_MARKER
+    ))
+  }
+
+  pub fn stop(&mut self, reason: impl Into<String>) {
+    if self.is_running() {
+      self.state = AgentTaskState::Stopped(reason.into());
+    }
+  }
+
+  /// one line on the progress of the task
+  pub fn progress(&self) -> String {
+    let state = match &self.state {
+      AgentTaskState::Running => format!("step {}/{}", self.step, self.max_steps),
+      AgentTaskState::Complete => format!("complete after {} steps", self.step),
+      AgentTaskState::Stopped(reason) => reason.clone(),
+    };
+    match &self.last_action {
+      Some(action) if self.is_running() => format!("{}, {}", state, action),
+      _ => state,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_agent_task_loop() {
+    let mut task = AgentTask::new("fix the failing test", 2);
+    assert!(task.prompt().contains("fix the failing test"));
+
+    let next = task.evaluate("I ran the tests, one still fails.").unwrap();
+    assert!(next.contains("step 2 of at most 2"));
+    task.last_action = Some("calling shell".to_string());
+    assert_eq!(task.progress(), "step 2/2, calling shell");
+
+    assert_eq!(task.evaluate("still failing"), None);
+    assert_eq!(task.state, AgentTaskState::Stopped("stopped after 2 steps".to_string()));
+
+    let mut task = AgentTask::new("rename the module", 5);
+    assert_eq!(task.evaluate("Renamed it.\n\n**TASK COMPLETE**"), None);
+    assert_eq!(task.progress(), "complete after 1 steps");
+  }
+}
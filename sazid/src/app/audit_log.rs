@@ -0,0 +1,132 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use async_openai::types::ChatCompletionMessageToolCall;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::errors::SazidError;
+
+/// a tool call in the audit log of a session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+  /// when the call started, rfc 3339
+  pub started_at: String,
+  /// the reply that made the call
+  pub message_id: i64,
+  pub tool_call_id: String,
+  pub tool: String,
+  /// the arguments of the call, a string if they are not json
+  pub arguments: Value,
+  pub duration_ms: u64,
+  pub result_bytes: usize,
+  /// the files the call edited
+  pub files: Vec<PathBuf>,
+}
+
+/// a tool call that is running, timed until its result arrives
+#[derive(Debug, Clone)]
+pub struct StartedToolCall {
+  pub message_id: i64,
+  pub tool_call: ChatCompletionMessageToolCall,
+  started_at: String,
+  started: Instant,
+}
+
+impl StartedToolCall {
+  pub fn new(message_id: i64, tool_call: ChatCompletionMessageToolCall) -> Self {
+    StartedToolCall {
+      message_id,
+      tool_call,
+      started_at: chrono::Utc::now().to_rfc3339(),
+      started: Instant::now(),
+    }
+  }
+
+  /// the entry of the call once it returned `result` after editing `files`
+  pub fn finish(self, result: &str, files: Vec<PathBuf>) -> AuditEntry {
+    let arguments = serde_json::from_str(&self.tool_call.function.arguments)
+      .unwrap_or(Value::String(self.tool_call.function.arguments));
+    AuditEntry {
+      started_at: self.started_at,
+      message_id: self.message_id,
+      tool_call_id: self.tool_call.id,
+      tool: self.tool_call.function.name,
+      arguments,
+      duration_ms: self.started.elapsed().as_millis() as u64,
+      result_bytes: result.len(),
+      files,
+    }
+  }
+}
+
+/// the audit log of a session in `dir`
+pub fn audit_log_path(dir: &Path, session_id: &str) -> PathBuf {
+  dir.join(format!("{}.jsonl", session_id))
+}
+
+/// adds an entry to the end of an audit log, entries are never rewritten
+pub fn append(path: &Path, entry: &AuditEntry) -> Result<(), SazidError> {
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+  let line = serde_json::to_string(entry).map_err(|e| SazidError::Other(e.to_string()))?;
+  writeln!(file, "{}", line)?;
+  Ok(())
+}
+
+/// the entries of an audit log, oldest first. empty if the session has not called a tool
+pub fn load(path: &Path) -> Result<Vec<AuditEntry>, SazidError> {
+  let text = match std::fs::read_to_string(path) {
+    Ok(text) => text,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+    Err(e) => return Err(e.into()),
+  };
+  let entries = text
+    .lines()
+    .filter_map(|line| match serde_json::from_str::<AuditEntry>(line) {
+      Ok(entry) => Some(entry),
+      Err(e) => {
+        log::warn!("skipping audit log line in {}: {}", path.display(), e);
+        None
+      },
+    })
+    .collect();
+  Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{ChatCompletionToolType, FunctionCall};
+  use serde_json::json;
+  use tempfile::tempdir;
+
+  #[test]
+  fn test_tool_calls_are_appended_to_the_audit_log() {
+    let dir = tempdir().unwrap();
+    let path = audit_log_path(dir.path(), "session");
+    let tool_call = |id: &str, arguments: &str| ChatCompletionMessageToolCall {
+      id: id.to_string(),
+      r#type: ChatCompletionToolType::Function,
+      function: FunctionCall { name: "create_file".to_string(), arguments: arguments.to_string() },
+    };
+
+    let entry = StartedToolCall::new(4, tool_call("call_1", r#"{"path": "a.rs"}"#))
+      .finish("created a.rs", vec![PathBuf::from("a.rs")]);
+    append(&path, &entry).unwrap();
+    std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\n").unwrap();
+    append(&path, &StartedToolCall::new(6, tool_call("call_2", "not json")).finish("", vec![]))
+      .unwrap();
+
+    let entries = load(&path).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], entry);
+    assert_eq!(entries[0].arguments, json!({ "path": "a.rs" }));
+    assert_eq!(entries[0].result_bytes, 12);
+    assert_eq!(entries[1].arguments, json!("not json"));
+    assert!(load(&dir.path().join("missing.jsonl")).unwrap().is_empty());
+  }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use async_openai::types::ChatCompletionMessageToolCall;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// tools that modify files in the workspace
+pub const EDIT_TOOL_NAMES: &[&str] =
+  &["create_file", "lsp_replace_symbol_text", "modify_file", "apply_patch", "multi_edit"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangelogConfig {
+  pub enabled: bool,
+  pub changes_dir: PathBuf,
+}
+
+impl Default for ChangelogConfig {
+  fn default() -> Self {
+    ChangelogConfig { enabled: false, changes_dir: PathBuf::from(".changes") }
+  }
+}
+
+impl ChangelogConfig {
+  /// the directory fragments are written to, relative to the workspace when one is set
+  pub fn fragment_dir(&self, workspace_path: Option<&Path>) -> PathBuf {
+    match workspace_path {
+      Some(workspace_path) if self.changes_dir.is_relative() => {
+        workspace_path.join(&self.changes_dir)
+      },
+      _ => self.changes_dir.clone(),
+    }
+  }
+
+  /// returns true if the tool call modifies a file outside of the changes directory
+  pub fn is_tracked_edit(
+    &self,
+    tool_call: &ChatCompletionMessageToolCall,
+    workspace_path: Option<&Path>,
+  ) -> bool {
+    if !EDIT_TOOL_NAMES.contains(&tool_call.function.name.as_str()) {
+      return false;
+    }
+    // modify_file without content only reads the file
+    if tool_call.function.name == "modify_file" && !has_content_argument(tool_call) {
+      return false;
+    }
+    match tool_call_path_argument(tool_call) {
+      Some(path) => {
+        !path.starts_with(self.fragment_dir(workspace_path)) && !path.starts_with(&self.changes_dir)
+      },
+      None => true,
+    }
+  }
+
+  /// builds the request asking the model to write a keep-a-changelog fragment for the edits
+  pub fn fragment_prompt(
+    &self,
+    edits: &[ChatCompletionMessageToolCall],
+    workspace_path: Option<&Path>,
+  ) -> String {
+    let fragment_path = self
+      .fragment_dir(workspace_path)
+      .join(format!("{}.md", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+    let edit_list = edits
+      .iter()
+      .map(|tc| format!("- {}: {}", tc.function.name, summarize_arguments(&tc.function.arguments)))
+      .collect::<Vec<String>>()
+      .join("\n");
+    format!(
+      "The following edits were applied during this turn:\n{}\n\nWrite a changelog fragment in keep-a-changelog style describing these changes. Group entries under `### Added`, `### Changed`, `### Fixed` or `### Removed` headings as appropriate, one short line per entry. Use the create_file tool to write the fragment to {}. Do not make any other changes.",
+      edit_list,
+      fragment_path.display()
+    )
+  }
+}
+
+fn tool_call_path_argument(tool_call: &ChatCompletionMessageToolCall) -> Option<PathBuf> {
+  serde_json::from_str::<Value>(&tool_call.function.arguments)
+    .ok()
+    .and_then(|args| args.get("path").and_then(|p| p.as_str()).map(PathBuf::from))
+}
+
+fn has_content_argument(tool_call: &ChatCompletionMessageToolCall) -> bool {
+  serde_json::from_str::<Value>(&tool_call.function.arguments)
+    .ok()
+    .is_some_and(|args| args.get("content").is_some())
+}
+
+// file contents are omitted, the model already has them in context
+fn summarize_arguments(arguments: &str) -> String {
+  match serde_json::from_str::<Value>(arguments) {
+    Ok(Value::Object(args)) => args
+      .iter()
+      .filter(|(key, _)| !matches!(key.as_str(), "content" | "replacement_text"))
+      .map(|(key, value)| format!("{}={}", key, value))
+      .collect::<Vec<String>>()
+      .join(", "),
+    _ => arguments.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{ChatCompletionToolType, FunctionCall};
+
+  fn tool_call(name: &str, arguments: &str) -> ChatCompletionMessageToolCall {
+    ChatCompletionMessageToolCall {
+      id: "call_1".to_string(),
+      r#type: ChatCompletionToolType::Function,
+      function: FunctionCall { name: name.to_string(), arguments: arguments.to_string() },
+    }
+  }
+
+  #[test]
+  fn test_is_tracked_edit() {
+    let config = ChangelogConfig { enabled: true, ..Default::default() };
+    let workspace = Path::new("/tmp/workspace");
+    assert!(config.is_tracked_edit(
+      &tool_call("create_file", r#"{"path": "/tmp/workspace/src/lib.rs", "content": ""}"#),
+      Some(workspace)
+    ));
+    assert!(!config.is_tracked_edit(
+      &tool_call("create_file", r#"{"path": "/tmp/workspace/.changes/1.md", "content": ""}"#),
+      Some(workspace)
+    ));
+    assert!(config.is_tracked_edit(
+      &tool_call("lsp_replace_symbol_text", r#"{"symbol_id": "abc", "replacement_text": ""}"#),
+      Some(workspace)
+    ));
+    assert!(config.is_tracked_edit(
+      &tool_call("modify_file", r#"{"path": "src/lib.rs", "anchor": "a", "content": "b"}"#),
+      Some(workspace)
+    ));
+    assert!(!config.is_tracked_edit(
+      &tool_call("modify_file", r#"{"path": "src/lib.rs", "start_line": 1}"#),
+      Some(workspace)
+    ));
+    assert!(!config.is_tracked_edit(&tool_call("lsp_query_symbols", "{}"), Some(workspace)));
+  }
+
+  #[test]
+  fn test_fragment_prompt_omits_file_content() {
+    let config = ChangelogConfig::default();
+    let prompt = config.fragment_prompt(
+      &[tool_call("create_file", r#"{"path": "src/new.rs", "content": "fn secret() {}"}"#)],
+      None,
+    );
+    assert!(prompt.contains("path=\"src/new.rs\""));
+    assert!(!prompt.contains("fn secret"));
+    assert!(prompt.contains(".changes"));
+  }
+}
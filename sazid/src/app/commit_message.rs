@@ -0,0 +1,95 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// the end of a large diff is cut off, the files changed first are enough to describe most commits
+const MAX_DIFF_CHARS: usize = 12000;
+
+/// the file git reads commit messages from, the proposed message is written to it for editing
+pub const COMMIT_MESSAGE_FILE: &str = "COMMIT_EDITMSG";
+
+pub const COMMIT_MESSAGE_PROMPT: &str = "Write a git commit message for the following staged diff. Start with a summary line of at most 72 characters in the imperative mood. If the change needs explaining, follow it with a blank line and a short body saying what changed and why. Respond with only the commit message.";
+
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+  let output = Command::new("git").args(args).current_dir(dir).output()?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "git {} failed: {}",
+      args.join(" "),
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// the staged changes of the repository at `dir`
+pub fn staged_diff(dir: &Path) -> anyhow::Result<String> {
+  let diff = git(dir, &["diff", "--cached"])?;
+  if diff.trim().is_empty() {
+    anyhow::bail!("there are no staged changes");
+  }
+  Ok(diff)
+}
+
+/// the path of the commit message file in the repository at `dir`
+pub fn commit_message_path(dir: &Path) -> anyhow::Result<PathBuf> {
+  let git_dir = PathBuf::from(git(dir, &["rev-parse", "--git-dir"])?.trim());
+  Ok(dir.join(git_dir).join(COMMIT_MESSAGE_FILE))
+}
+
+/// commits the staged changes with the message in `message_path`. lines starting with # are
+/// left out, as they are when git opens an editor for the message
+pub fn commit(dir: &Path, message_path: &Path) -> anyhow::Result<String> {
+  let message_path = message_path.to_string_lossy();
+  git(dir, &["commit", "--cleanup=strip", "-F", &message_path])
+}
+
+pub fn commit_message_request_messages(diff: &str) -> Vec<ChatCompletionRequestMessage> {
+  let diff = match diff.char_indices().nth(MAX_DIFF_CHARS) {
+    Some((end, _)) => format!("{}\n[diff truncated]", &diff[..end]),
+    None => diff.to_string(),
+  };
+  vec![
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: COMMIT_MESSAGE_PROMPT.to_string(),
+      role: Role::System,
+      name: None,
+    }),
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: diff,
+      role: Role::System,
+      name: None,
+    }),
+  ]
+}
+
+/// the commit message in a response, without the code fence models sometimes wrap it in
+pub fn parse_commit_message_response(response: &str) -> String {
+  let response = response.trim();
+  let message = match response.strip_prefix("```") {
+    // the rest of the opening fence line is a language name, if anything
+    Some(fenced) => {
+      let fenced = fenced.split_once('\n').map(|(_, rest)| rest).unwrap_or_default();
+      fenced.trim_end().strip_suffix("```").unwrap_or(fenced)
+    },
+    None => response,
+  };
+  format!("{}\n", message.trim())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::messages::get_chat_message_text;
+
+  #[test]
+  fn test_parse_commit_message_response() {
+    let message = "Add :commit-msg\n\nProposes a message for the staged diff.\n";
+    assert_eq!(parse_commit_message_response(message), message);
+    assert_eq!(parse_commit_message_response(&format!("```text\n{}```", message)), message);
+    assert_eq!(parse_commit_message_response("  Fix typo  "), "Fix typo\n");
+
+    let messages = commit_message_request_messages(&"+".repeat(MAX_DIFF_CHARS + 1));
+    assert!(get_chat_message_text(&messages[1]).ends_with("+\n[diff truncated]"));
+  }
+}
@@ -14,31 +14,49 @@ lazy_static! {
         name: "gpt-4o".to_string(),
         endpoint: "https://api.openai.com/v1/completions".to_string(),
         token_limit: 32768,
+        prompt_cost_per_million: 5.0,
+        completion_cost_per_million: 15.0,
+        text_tool_calls: false,
     };
     pub static ref GPT4_TURBO: Model = Model {
         name: "gpt-4-turbo-preview".to_string(),
         endpoint: "https://api.openai.com/v1/completions".to_string(),
         token_limit: 16384,
+        prompt_cost_per_million: 10.0,
+        completion_cost_per_million: 30.0,
+        text_tool_calls: false,
     };
     pub static ref GPT3_TURBO_16K: Model = Model {
         name: "gpt-3.5-turbo-16k".to_string(),
         endpoint: "https://api.openai.com/v1/completions".to_string(),
         token_limit: 16384,
+        prompt_cost_per_million: 3.0,
+        completion_cost_per_million: 4.0,
+        text_tool_calls: false,
     };
     pub static ref GPT3_TURBO: Model = Model {
         name: "gpt-3.5-turbo".to_string(),
         endpoint: "https://api.openai.com/v1/completions".to_string(),
         token_limit: 4097,
+        prompt_cost_per_million: 0.5,
+        completion_cost_per_million: 1.5,
+        text_tool_calls: false,
     };
     pub static ref WIZARDLM: Model = Model {
         name: "wizardlm".to_string(),
         endpoint: "https://localhost:1234/v1/completions".to_string(),
         token_limit: 2047,
+        prompt_cost_per_million: 0.0,
+        completion_cost_per_million: 0.0,
+        text_tool_calls: true,
     };
     pub static ref GPT4: Model = Model {
         name: "gpt-4".to_string(),
         endpoint: "https://api.openai.com/v1/completions".to_string(),
         token_limit: 8192,
+        prompt_cost_per_million: 30.0,
+        completion_cost_per_million: 60.0,
+        text_tool_calls: false,
     };
     // logging constants
     pub static ref PROJECT_NAME: String = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
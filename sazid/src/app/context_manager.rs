@@ -0,0 +1,224 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+use tiktoken_rs::CoreBPE;
+
+use super::messages::{get_chat_message_text, MessageContainer};
+
+// every message carries a few tokens of formatting overhead in the chat format
+const TOKENS_PER_MESSAGE: usize = 4;
+
+pub const SUMMARY_PROMPT: &str = "Summarize the following conversation between a user and an assistant so that it can replace the original messages in the context window. Keep file paths, symbol names, decisions that were made and any unresolved tasks. Be concise.";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextWindow {
+  pub messages: Vec<ChatCompletionRequestMessage>,
+  pub evicted_message_ids: Vec<i64>,
+  pub token_count: usize,
+}
+
+pub struct ContextManager {
+  bpe: CoreBPE,
+  token_budget: usize,
+}
+
+impl ContextManager {
+  pub fn new(model_name: &str, token_budget: usize) -> Self {
+    let bpe = tiktoken_rs::get_bpe_from_model(model_name)
+      .unwrap_or_else(|_| tiktoken_rs::cl100k_base().unwrap());
+    ContextManager { bpe, token_budget }
+  }
+
+  pub fn count_message_tokens(&self, message: &ChatCompletionRequestMessage) -> usize {
+    TOKENS_PER_MESSAGE + self.bpe.encode_with_special_tokens(&get_chat_message_text(message)).len()
+  }
+
+  /// selects the messages to send, evicting the oldest turns first until the request fits.
+  /// system messages and pinned messages are always included, as is the most recent turn.
+  /// the pinned files message and the summary, if provided, are inserted after the leading
+  /// system messages.
+  pub fn build_context_window(
+    &self,
+    messages: &[MessageContainer],
+    summary: Option<&str>,
+    pinned_files: Option<ChatCompletionRequestMessage>,
+  ) -> ContextWindow {
+    let is_pinned = |m: &MessageContainer| {
+      m.pinned || matches!(m.message, ChatCompletionRequestMessage::System(_))
+    };
+    let mut leading_messages =
+      pinned_files.into_iter().chain(summary.map(summary_message)).collect::<Vec<_>>();
+
+    let mut token_count =
+      leading_messages.iter().map(|m| self.count_message_tokens(m)).sum::<usize>();
+
+    // group unpinned messages into turns, each starting with a user message, so tool calls
+    // are never separated from their results
+    let mut turns: Vec<(Vec<i64>, usize)> = Vec::new();
+    for m in messages {
+      let tokens = self.count_message_tokens(&m.message);
+      token_count += tokens;
+      if is_pinned(m) {
+        continue;
+      }
+      match turns.last_mut() {
+        Some((ids, turn_tokens)) if !matches!(m.message, ChatCompletionRequestMessage::User(_)) => {
+          ids.push(m.message_id);
+          *turn_tokens += tokens;
+        },
+        _ => turns.push((vec![m.message_id], tokens)),
+      }
+    }
+
+    let mut evicted_message_ids = Vec::new();
+    let mut turns = turns.into_iter();
+    while token_count > self.token_budget && turns.len() > 1 {
+      let (ids, turn_tokens) = turns.next().unwrap();
+      token_count -= turn_tokens;
+      evicted_message_ids.extend(ids);
+    }
+
+    let mut window = Vec::new();
+    for m in messages {
+      if !matches!(m.message, ChatCompletionRequestMessage::System(_)) {
+        window.append(&mut leading_messages);
+      }
+      if !evicted_message_ids.contains(&m.message_id) {
+        window.push(m.message.clone());
+      }
+    }
+    window.append(&mut leading_messages);
+
+    ContextWindow { messages: window, evicted_message_ids, token_count }
+  }
+}
+
+pub fn summary_message(summary: &str) -> ChatCompletionRequestMessage {
+  ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+    content: format!("Summary of earlier conversation:\n{}", summary),
+    role: Role::System,
+    name: None,
+  })
+}
+
+/// builds the messages for a request that folds evicted messages into the rolling summary
+pub fn summary_request_messages(
+  previous_summary: Option<&str>,
+  evicted: &[ChatCompletionRequestMessage],
+) -> Vec<ChatCompletionRequestMessage> {
+  let mut transcript = String::new();
+  if let Some(previous_summary) = previous_summary {
+    transcript.push_str(&format!("Existing summary:\n{}\n\n", previous_summary));
+  }
+  evicted.iter().for_each(|m| {
+    let role = match m {
+      ChatCompletionRequestMessage::System(_) => "system",
+      ChatCompletionRequestMessage::User(_) => "user",
+      ChatCompletionRequestMessage::Assistant(_) => "assistant",
+      ChatCompletionRequestMessage::Tool(_) => "tool",
+      ChatCompletionRequestMessage::Function(_) => "function",
+    };
+    transcript.push_str(&format!("{}: {}\n", role, get_chat_message_text(m)));
+  });
+  vec![
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: SUMMARY_PROMPT.to_string(),
+      role: Role::System,
+      name: None,
+    }),
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: transcript,
+      role: Role::System,
+      name: None,
+    }),
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent,
+  };
+
+  fn user(text: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::User(
+      ChatCompletionRequestUserMessage {
+        content: ChatCompletionRequestUserMessageContent::Text(text.to_string()),
+        role: Role::User,
+        name: None,
+      },
+    ))
+  }
+
+  fn assistant(text: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::Assistant(
+      ChatCompletionRequestAssistantMessage {
+        name: None,
+        role: Role::Assistant,
+        content: Some(text.to_string()),
+        function_call: None,
+        tool_calls: None,
+      },
+    ))
+  }
+
+  fn system(text: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::System(
+      ChatCompletionRequestSystemMessage {
+        content: text.to_string(),
+        role: Role::System,
+        name: None,
+      },
+    ))
+  }
+
+  #[test]
+  fn test_context_window_fits_without_eviction() {
+    let manager = ContextManager::new("gpt-4", 10_000);
+    let messages = vec![system("prompt"), user("hello"), assistant("hi")];
+    let window = manager.build_context_window(&messages, None, None);
+    assert_eq!(window.messages.len(), 3);
+    assert!(window.evicted_message_ids.is_empty());
+  }
+
+  #[test]
+  fn test_context_window_evicts_oldest_turns_and_keeps_pinned() {
+    let long_text = "lorem ipsum ".repeat(200);
+    let mut pinned = user(&long_text);
+    pinned.pinned = true;
+    let messages = vec![
+      system("prompt"),
+      pinned,
+      user(&long_text),
+      assistant(&long_text),
+      user("latest question"),
+      assistant("latest answer"),
+    ];
+    let budget = 2 * manager_tokens(&long_text) + 100;
+    let manager = ContextManager::new("gpt-4", budget);
+    let window = manager.build_context_window(&messages, Some("earlier summary"), None);
+
+    assert_eq!(window.evicted_message_ids, vec![messages[2].message_id, messages[3].message_id]);
+    // system prompt, summary, pinned message and the latest turn
+    assert_eq!(window.messages.len(), 5);
+    assert_eq!(window.messages[0], messages[0].message);
+    assert!(get_chat_message_text(&window.messages[1]).contains("earlier summary"));
+    assert_eq!(window.messages[2], messages[1].message);
+  }
+
+  #[test]
+  fn test_context_window_inserts_pinned_files_after_system_prompt() {
+    let manager = ContextManager::new("gpt-4", 10_000);
+    let messages = vec![system("prompt"), user("hello")];
+    let pinned_files = system("pinned files").message;
+    let window =
+      manager.build_context_window(&messages, Some("summary"), Some(pinned_files.clone()));
+    assert_eq!(window.messages.len(), 4);
+    assert_eq!(window.messages[1], pinned_files);
+    assert!(get_chat_message_text(&window.messages[2]).contains("summary"));
+  }
+
+  fn manager_tokens(text: &str) -> usize {
+    ContextManager::new("gpt-4", 0).count_message_tokens(&user(text).message)
+  }
+}
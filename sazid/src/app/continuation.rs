@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+// sent for the next part of a reply cut off at the token limit
+pub const CONTINUE_PROMPT: &str =
+  "Your reply was cut off at the token limit. Continue it exactly where it stopped, without \
+   repeating anything or adding an introduction.";
+
+// the shortest text taken to be repeated from the end of the previous part, shorter matches are
+// more likely to be chance than overlap
+const MIN_OVERLAP: usize = 8;
+// how far back from the end of the previous part repeated text is looked for
+const MAX_OVERLAP: usize = 400;
+
+/// whether replies cut off at `response_max_tokens` are continued on their own
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ContinueConfig {
+  pub enabled: bool,
+  /// the most continue requests sent for one reply
+  pub max_continuations: usize,
+}
+
+impl Default for ContinueConfig {
+  fn default() -> Self {
+    ContinueConfig { enabled: false, max_continuations: 3 }
+  }
+}
+
+/// a reply being continued, by the message the parts are stitched into
+#[derive(Debug, Clone, PartialEq)]
+pub struct Continuation {
+  pub message_id: i64,
+  /// the continue requests sent so far
+  pub count: usize,
+}
+
+/// joins the next part of a reply onto the part before it. text the next part repeats from the
+/// end of the previous one is dropped, as is a line started again with different indentation
+pub fn stitch(previous: &str, next: &str) -> String {
+  let overlap = overlap(previous, next);
+  if overlap > 0 {
+    return format!("{}{}", previous, &next[overlap..]);
+  }
+  let partial_line = previous.rsplit('\n').next().unwrap_or_default().trim_start();
+  let dedented = next.trim_start_matches([' ', '\t']);
+  if !partial_line.trim().is_empty() && dedented.starts_with(partial_line) {
+    return format!("{}{}", previous, &dedented[partial_line.len()..]);
+  }
+  format!("{}{}", previous, next)
+}
+
+// the length of the longest start of `next` that `previous` ends with
+fn overlap(previous: &str, next: &str) -> usize {
+  let longest = previous.len().min(next.len()).min(MAX_OVERLAP);
+  (MIN_OVERLAP..=longest)
+    .rev()
+    .find(|&len| next.is_char_boundary(len) && previous.ends_with(&next[..len]))
+    .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_stitch_drops_repeated_text() {
+    // the next part repeats the end of the previous one
+    let previous = "fn main() {\n    println!(\"hello";
+    assert_eq!(
+      stitch(previous, "println!(\"hello, world\");\n}"),
+      "fn main() {\n    println!(\"hello, world\");\n}"
+    );
+    // the cut off line is started again, indented differently
+    assert_eq!(
+      stitch("1. read\n  2. wri", "2. write the file\n3. test"),
+      "1. read\n  2. write the file\n3. test"
+    );
+    // nothing repeated
+    assert_eq!(stitch("The quick brown", " fox jumps"), "The quick brown fox jumps");
+    assert_eq!(stitch("a short", " tail"), "a short tail");
+  }
+}
@@ -0,0 +1,73 @@
+use super::errors::SazidError;
+
+// the service api keys are stored under in the os keyring, with the provider as the user
+const KEYRING_SERVICE: &str = "sazid";
+
+/// the providers api keys can be stored for
+pub const PROVIDERS: &[&str] = &["openai"];
+
+/// the environment variable holding a provider's api key
+pub fn provider_env_var(provider: &str) -> Result<&'static str, SazidError> {
+  match provider {
+    "openai" => Ok("OPENAI_API_KEY"),
+    _ => Err(SazidError::Other(format!(
+      "unknown provider {}, the providers are: {}",
+      provider,
+      PROVIDERS.join(", ")
+    ))),
+  }
+}
+
+fn keyring_entry(provider: &str) -> Result<keyring::Entry, SazidError> {
+  provider_env_var(provider)?;
+  Ok(keyring::Entry::new(KEYRING_SERVICE, provider)?)
+}
+
+/// the api key of a provider from its environment variable, then the os keyring, then the config
+/// file. a keyring that can not be read is skipped
+pub fn api_key(provider: &str, configured: Option<&str>) -> Option<String> {
+  let env_key = provider_env_var(provider)
+    .ok()
+    .and_then(|var| std::env::var(var).ok())
+    .filter(|key| !key.is_empty());
+  env_key
+    .or_else(|| match keyring_entry(provider).and_then(|entry| Ok(entry.get_password()?)) {
+      Ok(key) => Some(key),
+      Err(e) => {
+        log::debug!("no {} api key in the keyring: {}", provider, e);
+        None
+      },
+    })
+    .or_else(|| configured.map(String::from))
+}
+
+/// asks for a provider's api key without echoing it and stores it in the os keyring
+pub fn login(provider: &str) -> Result<(), SazidError> {
+  keyring_entry(provider)?;
+  let key = dialoguer::Password::new().with_prompt(format!("{} api key", provider)).interact()?;
+  store(provider, &key)
+}
+
+/// stores a provider's api key in the os keyring
+pub fn store(provider: &str, key: &str) -> Result<(), SazidError> {
+  keyring_entry(provider)?.set_password(key.trim())?;
+  Ok(())
+}
+
+/// removes a provider's api key from the os keyring
+pub fn logout(provider: &str) -> Result<(), SazidError> {
+  keyring_entry(provider)?.delete_password()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_provider_env_var() {
+    assert_eq!(provider_env_var("openai").unwrap(), "OPENAI_API_KEY");
+    assert!(provider_env_var("acme").is_err());
+    assert!(login("acme").is_err());
+  }
+}
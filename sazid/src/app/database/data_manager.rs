@@ -1,14 +1,19 @@
 use super::data_models::EmbeddingModel;
 use super::types::*;
+use super::vector_store::{open_vector_store, StoredChunk, VectorStore, VectorStoreConfig};
 use crate::app::errors::SazidError;
+use crate::app::hybrid_search::HybridSearchConfig;
+use crate::app::semantic_search;
 use crate::app::session_config::SessionConfig;
 use crate::cli::Cli;
 use crate::components::data_manager::DataManagerAction;
 use async_openai::types::ChatCompletionRequestMessage;
+use async_trait::async_trait;
 use dialoguer;
 use diesel::{prelude::*, sql_query};
 use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use pgvector::{Vector, VectorExpressionMethods};
+use std::path::Path;
 use tokio::sync::mpsc::UnboundedSender;
 
 #[derive(Default, Debug)]
@@ -16,6 +21,7 @@ pub struct DataManager {
   pub action_tx: Option<UnboundedSender<DataManagerAction>>,
   pub model: EmbeddingModel,
   pub db_url: String,
+  pub vector_store: VectorStoreConfig,
 }
 
 impl DataManager {
@@ -24,15 +30,15 @@ impl DataManager {
     Ok(match args {
       Cli { list_embeddings: true, .. } => {
         // let categories = self.list_embeddings_categories().await?;
-        let embeddings = get_all_embeddings(db_url).await?;
+        let files = open_vector_store(&self.vector_store, db_url)?.files().await?;
 
-        if embeddings.is_empty() {
+        if files.is_empty() {
           Some("No embeddings found".to_string())
         } else {
           Some(
-            embeddings
+            files
               .into_iter()
-              .map(|(fe, vec_ep)| format!("{} -- {} pages", fe.filepath, vec_ep.len()))
+              .map(|(filepath, chunks)| format!("{} -- {} chunks", filepath, chunks))
               .collect::<Vec<String>>()
               .join("\n"),
           )
@@ -54,11 +60,30 @@ impl DataManager {
         }
       },
       Cli { search_embeddings: Some(text), .. } => {
-        let embeddings = search_all_embeddings(db_url, &self.model, &text).await?;
-        if embeddings.is_empty() {
+        let store = open_vector_store(&self.vector_store, db_url)?;
+        let config = HybridSearchConfig::default();
+        let results =
+          semantic_search::search(store.as_ref(), &self.model, &text, 10, None, &config).await?;
+        if results.is_empty() {
           Some("No embeddings found".to_string())
         } else {
-          Some(embeddings.into_iter().map(|e| format!("{}", e)).collect::<Vec<String>>().join("\n"))
+          Some(
+            results
+              .into_iter()
+              .map(|result| {
+                let chunk = result.chunk;
+                let first_line = chunk.content.lines().next().unwrap_or_default();
+                format!(
+                  "{}:{}-{}\t{}",
+                  result.path.display(),
+                  chunk.start_line,
+                  chunk.end_line,
+                  first_line
+                )
+              })
+              .collect::<Vec<String>>()
+              .join("\n"),
+          )
         }
       },
       Cli { parse_source_embeddings: Some(_), .. } => {
@@ -68,7 +93,8 @@ impl DataManager {
       },
       Cli { add_text_file_embeddings: Some(filepath), .. } => {
         // read the file at filepath
-        match add_textfile_embedding(db_url, &self.model, &filepath).await {
+        let store = open_vector_store(&self.vector_store, db_url)?;
+        match semantic_search::index_file(store.as_ref(), &self.model, Path::new(&filepath)).await {
           Ok(_) => Some(format!("Added embedding for file at {}", filepath)),
           Err(e) => Some(format!("Error adding embedding for file at {}: {}", filepath, e)),
         }
@@ -79,7 +105,12 @@ impl DataManager {
   }
 
   pub async fn new(model: EmbeddingModel, db_url: &str) -> Result<Self, SazidError> {
-    Ok(DataManager { action_tx: None, model, db_url: db_url.to_string() })
+    Ok(DataManager {
+      action_tx: None,
+      model,
+      db_url: db_url.to_string(),
+      vector_store: VectorStoreConfig::default(),
+    })
   }
 }
 
@@ -220,6 +251,8 @@ pub async fn add_embedding(
         embedding_pages::checksum.eq(p.checksum.clone()),
         embedding_pages::file_embedding_id.eq(embedding_id),
         embedding_pages::embedding.eq(p.embedding.clone()),
+        embedding_pages::start_line.eq(p.start_line),
+        embedding_pages::end_line.eq(p.end_line),
       ))
       .execute(conn)
       .await?;
@@ -279,9 +312,153 @@ pub async fn add_textfile_embedding(
   let embedding = model.create_embedding_vector(&vector_content).await?;
   let new_embedding =
     InsertableFileEmbedding { filepath: filepath.to_string(), checksum: checksum.clone() };
-  let new_page = InsertablePage { content, page_number: 0, checksum, embedding };
+  let end_line = content.lines().count() as i32;
+  let new_page =
+    InsertablePage { content, page_number: 0, checksum, embedding, start_line: 1, end_line };
   add_embedding(db_url, &new_embedding, vec![&new_page]).await
 }
+
+/// file chunk embeddings in the postgres database, searched with pgvector
+pub struct PgVectorStore {
+  pub db_url: String,
+}
+
+// a like pattern matching the strings that start with `prefix`, which may hold wildcards
+fn like_prefix(prefix: &str) -> String {
+  let escaped = prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+  format!("{}%", escaped)
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+  async fn contains_file(&self, checksum: &str) -> Result<bool, SazidError> {
+    use super::schema::file_embeddings;
+    let conn = &mut establish_connection(&self.db_url).await;
+    let file = file_embeddings::table
+      .filter(file_embeddings::checksum.eq(checksum))
+      .select(file_embeddings::id)
+      .first::<i64>(conn)
+      .await
+      .optional()?;
+    Ok(file.is_some())
+  }
+
+  async fn replace_file(
+    &self,
+    filepath: &str,
+    checksum: &str,
+    chunks: Vec<StoredChunk>,
+  ) -> Result<(), SazidError> {
+    use super::schema::embedding_pages;
+    use super::schema::file_embeddings;
+    let conn = &mut establish_connection(&self.db_url).await;
+    let stale = file_embeddings::table
+      .filter(file_embeddings::filepath.eq(filepath))
+      .select(file_embeddings::id)
+      .load::<i64>(conn)
+      .await?;
+    diesel::delete(
+      embedding_pages::table.filter(embedding_pages::file_embedding_id.eq_any(&stale)),
+    )
+    .execute(conn)
+    .await?;
+    diesel::delete(file_embeddings::table.filter(file_embeddings::id.eq_any(&stale)))
+      .execute(conn)
+      .await?;
+
+    let pages = chunks
+      .into_iter()
+      .enumerate()
+      .map(|(page_number, chunk)| InsertablePage {
+        content: chunk.content,
+        page_number: page_number as i32,
+        // page checksums are unique as well
+        checksum: blake3::hash(format!("{}\n{}", checksum, page_number).as_bytes())
+          .to_hex()
+          .to_string(),
+        embedding: Vector::from(chunk.embedding),
+        start_line: chunk.start_line as i32,
+        end_line: chunk.end_line as i32,
+      })
+      .collect::<Vec<_>>();
+    let new_embedding =
+      InsertableFileEmbedding { filepath: filepath.to_string(), checksum: checksum.to_string() };
+    add_embedding(&self.db_url, &new_embedding, pages.iter().collect()).await?;
+    Ok(())
+  }
+
+  async fn nearest_chunks(
+    &self,
+    vector: Vec<f32>,
+    prefix: &str,
+    limit: usize,
+  ) -> Result<Vec<(String, StoredChunk)>, SazidError> {
+    use super::schema::embedding_pages;
+    use super::schema::file_embeddings;
+    let vector = Vector::from(vector);
+    let conn = &mut establish_connection(&self.db_url).await;
+    let chunks = embedding_pages::table
+      .inner_join(file_embeddings::table)
+      .filter(file_embeddings::filepath.like(like_prefix(prefix)))
+      .select((file_embeddings::filepath, EmbeddingPage::as_select()))
+      .order(embedding_pages::embedding.cosine_distance(&vector))
+      .limit(limit as i64)
+      .load::<(String, EmbeddingPage)>(conn)
+      .await?
+      .into_iter()
+      .map(|(filepath, page)| {
+        let chunk = StoredChunk {
+          start_line: page.start_line as usize,
+          end_line: page.end_line as usize,
+          embedding: page.embedding.to_vec(),
+          content: page.content,
+        };
+        (filepath, chunk)
+      })
+      .collect();
+    Ok(chunks)
+  }
+
+  async fn text_chunks(&self, prefix: &str) -> Result<Vec<(String, StoredChunk)>, SazidError> {
+    use super::schema::embedding_pages;
+    use super::schema::file_embeddings;
+    let conn = &mut establish_connection(&self.db_url).await;
+    let chunks = embedding_pages::table
+      .inner_join(file_embeddings::table)
+      .filter(file_embeddings::filepath.like(like_prefix(prefix)))
+      .select((
+        file_embeddings::filepath,
+        embedding_pages::start_line,
+        embedding_pages::end_line,
+        embedding_pages::content,
+      ))
+      .load::<(String, i32, i32, String)>(conn)
+      .await?
+      .into_iter()
+      .map(|(filepath, start_line, end_line, content)| {
+        let chunk = StoredChunk {
+          start_line: start_line as usize,
+          end_line: end_line as usize,
+          content,
+          embedding: vec![],
+        };
+        (filepath, chunk)
+      })
+      .collect();
+    Ok(chunks)
+  }
+
+  async fn files(&self) -> Result<Vec<(String, usize)>, SazidError> {
+    Ok(
+      get_all_embeddings(&self.db_url)
+        .await?
+        .into_iter()
+        .map(|(file, pages)| (file.filepath, pages.len()))
+        .collect(),
+    )
+  }
+}
+
 // Method to retrieve indexing progress information
 pub async fn get_indexing_progress(db_url: &str) -> Result<Vec<PgVectorIndexInfo>, SazidError> {
   let conn = &mut establish_connection(db_url).await;
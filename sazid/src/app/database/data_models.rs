@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use async_openai::{config::OpenAIConfig, types::CreateEmbeddingRequestArgs};
 use pgvector::Vector;
 
@@ -5,18 +7,20 @@ use crate::{
   app::{
     errors::{ParseError, SazidError},
     model_tools::argument_validation::count_tokens,
+    provider_fixtures::ProviderFixtures,
   },
   components::session::create_openai_client,
 };
 
+/// an embedding model, with the fixtures its requests are recorded to or replayed from
 #[derive(Debug, Clone)]
 pub enum EmbeddingModel {
-  Ada002(OpenAIConfig),
+  Ada002(OpenAIConfig, Option<Arc<ProviderFixtures>>),
 }
 
 impl Default for EmbeddingModel {
   fn default() -> Self {
-    Self::Ada002(OpenAIConfig::default())
+    Self::Ada002(OpenAIConfig::default(), None)
   }
 }
 #[derive(Clone)]
@@ -30,7 +34,7 @@ pub struct EmbeddingModelConfig {
 impl EmbeddingModel {
   pub fn config(&self) -> EmbeddingModelConfig {
     match self {
-      Self::Ada002(_) => EmbeddingModelConfig {
+      Self::Ada002(..) => EmbeddingModelConfig {
         model_string: "text-embedding-ada-002".to_string(),
         embedding_suffix: "ada-002".to_string(),
         token_limit: 8192,
@@ -70,24 +74,26 @@ impl EmbeddingModel {
     }
 
     let vector = match self {
-      Self::Ada002(openai_config) => {
-        let client = create_openai_client(openai_config);
-        let request = CreateEmbeddingRequestArgs::default()
-          .model(self.model_string())
-          .input(text)
-          .build()
-          .unwrap();
-
-        // embedding_response.data.iter().map(|e| e.embedding.clone()).collect::<Vec<Vec<f32>>>();
-        //let embedding = embedding_response.data.first().unwrap().embedding.clone();
-        client.embeddings().create(request).await.unwrap()
+      Self::Ada002(openai_config, fixtures) => {
+        let created = create_ada002_embedding(openai_config, self.model_string(), text);
+        match fixtures {
+          Some(fixtures) => fixtures.embedding(&self.model_string(), text, created).await?,
+          None => created.await?,
+        }
       },
-    }
-    .data
-    .iter()
-    .flat_map(|e| e.embedding.clone())
-    .collect::<Vec<f32>>();
+    };
 
     Ok(vector.into())
   }
 }
+
+async fn create_ada002_embedding(
+  openai_config: &OpenAIConfig,
+  model: String,
+  text: &str,
+) -> Result<Vec<f32>, SazidError> {
+  let client = create_openai_client(openai_config);
+  let request = CreateEmbeddingRequestArgs::default().model(model).input(text).build()?;
+  let response = client.embeddings().create(request).await?;
+  Ok(response.data.iter().flat_map(|e| e.embedding.clone()).collect())
+}
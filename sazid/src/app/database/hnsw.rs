@@ -0,0 +1,254 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::vector_store::cosine_distance;
+
+// the neighbors a node keeps on the layers above the bottom one, the bottom one keeps twice as
+// many
+const MAX_NEIGHBORS: usize = 16;
+// the candidates kept while the neighbors of a new node are searched for
+const EF_CONSTRUCTION: usize = 100;
+// the fewest candidates kept while searching, more find the nearest more reliably
+const EF_SEARCH: usize = 64;
+
+/// an approximate nearest neighbor index of embeddings by cosine distance, as a hierarchical
+/// navigable small world graph. removed embeddings stay in the graph to route searches through,
+/// and are left out of the results
+pub struct Hnsw {
+  nodes: Vec<Node>,
+  // the node of each embedding that was not removed, by id
+  ids: HashMap<i64, usize>,
+  entry: Option<usize>,
+  removed: usize,
+  rng: StdRng,
+}
+
+struct Node {
+  id: i64,
+  vector: Vec<f32>,
+  // the neighbors on each layer the node is on, from the bottom one up
+  layers: Vec<Vec<usize>>,
+  removed: bool,
+}
+
+// a node with its distance from the vector searched for, ordered by the distance
+#[derive(Debug, Clone, Copy)]
+struct Scored(f32, usize);
+
+impl PartialEq for Scored {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Scored {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+  }
+}
+
+impl Hnsw {
+  /// an empty index, `seed` picks the layers of the nodes
+  pub fn new(seed: u64) -> Self {
+    Hnsw {
+      nodes: vec![],
+      ids: HashMap::new(),
+      entry: None,
+      removed: 0,
+      rng: StdRng::seed_from_u64(seed),
+    }
+  }
+
+  /// the number of embeddings that were not removed
+  pub fn len(&self) -> usize {
+    self.ids.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.ids.is_empty()
+  }
+
+  /// the number of removed embeddings still in the graph, an index mostly made of them is
+  /// better built again
+  pub fn removed(&self) -> usize {
+    self.removed
+  }
+
+  /// adds an embedding, an id that is in the index already is left as it is
+  pub fn insert(&mut self, id: i64, vector: Vec<f32>) {
+    if self.ids.contains_key(&id) {
+      return;
+    }
+    let level = self.random_level();
+    let node = self.nodes.len();
+    self.nodes.push(Node { id, vector, layers: vec![vec![]; level + 1], removed: false });
+    self.ids.insert(id, node);
+    let Some(entry) = self.entry else {
+      self.entry = Some(node);
+      return;
+    };
+
+    let vector = self.nodes[node].vector.clone();
+    let top = self.nodes[entry].layers.len() - 1;
+    let mut nearest = vec![self.score(&vector, entry)];
+    for layer in (level + 1..=top).rev() {
+      nearest = self.search_layer(&vector, &nearest, 1, layer);
+    }
+    for layer in (0..=level.min(top)).rev() {
+      nearest = self.search_layer(&vector, &nearest, EF_CONSTRUCTION, layer);
+      let max = max_neighbors(layer);
+      let neighbors = nearest.iter().take(max).map(|scored| scored.1).collect::<Vec<_>>();
+      for &neighbor in &neighbors {
+        self.nodes[neighbor].layers[layer].push(node);
+        if self.nodes[neighbor].layers[layer].len() > max {
+          self.prune(neighbor, layer, max);
+        }
+      }
+      self.nodes[node].layers[layer] = neighbors;
+    }
+    if level > top {
+      self.entry = Some(node);
+    }
+  }
+
+  /// removes the embedding with the id, returns false if there is none
+  pub fn remove(&mut self, id: i64) -> bool {
+    let Some(node) = self.ids.remove(&id) else {
+      return false;
+    };
+    self.nodes[node].removed = true;
+    self.removed += 1;
+    true
+  }
+
+  /// the ids of about the `k` embeddings nearest to `vector` with their distances, nearest first
+  pub fn search(&self, vector: &[f32], k: usize) -> Vec<(i64, f32)> {
+    let Some(entry) = self.entry else {
+      return vec![];
+    };
+    let mut nearest = vec![self.score(vector, entry)];
+    for layer in (1..self.nodes[entry].layers.len()).rev() {
+      nearest = self.search_layer(vector, &nearest, 1, layer);
+    }
+    self
+      .search_layer(vector, &nearest, k.max(EF_SEARCH), 0)
+      .into_iter()
+      .filter(|scored| !self.nodes[scored.1].removed)
+      .take(k)
+      .map(|Scored(distance, node)| (self.nodes[node].id, distance))
+      .collect()
+  }
+
+  // the `ef` nodes nearest to `vector` found on `layer` from the `entry` nodes, nearest first
+  fn search_layer(&self, vector: &[f32], entry: &[Scored], ef: usize, layer: usize) -> Vec<Scored> {
+    let mut visited = entry.iter().map(|scored| scored.1).collect::<HashSet<_>>();
+    let mut candidates = entry.iter().map(|&scored| Reverse(scored)).collect::<BinaryHeap<_>>();
+    // the farthest of the nearest nodes so far is on top, and is dropped for a nearer one
+    let mut found = entry.iter().copied().collect::<BinaryHeap<_>>();
+    while found.len() > ef {
+      found.pop();
+    }
+    while let Some(Reverse(candidate)) = candidates.pop() {
+      if found.len() >= ef && found.peek().is_some_and(|farthest| candidate > *farthest) {
+        break;
+      }
+      for &neighbor in &self.nodes[candidate.1].layers[layer] {
+        if !visited.insert(neighbor) {
+          continue;
+        }
+        let scored = self.score(vector, neighbor);
+        if found.len() < ef || found.peek().is_some_and(|farthest| scored < *farthest) {
+          candidates.push(Reverse(scored));
+          found.push(scored);
+          if found.len() > ef {
+            found.pop();
+          }
+        }
+      }
+    }
+    found.into_sorted_vec()
+  }
+
+  // keeps the `max` neighbors of the node on the layer that are nearest to it
+  fn prune(&mut self, node: usize, layer: usize, max: usize) {
+    let vector = &self.nodes[node].vector;
+    let mut neighbors = self.nodes[node].layers[layer]
+      .iter()
+      .map(|&neighbor| Scored(cosine_distance(vector, &self.nodes[neighbor].vector), neighbor))
+      .collect::<Vec<_>>();
+    neighbors.sort();
+    self.nodes[node].layers[layer] = neighbors.into_iter().take(max).map(|s| s.1).collect();
+  }
+
+  // each layer up holds about one in MAX_NEIGHBORS of the nodes of the one below
+  fn random_level(&mut self) -> usize {
+    let uniform = 1.0 - self.rng.gen::<f64>();
+    (-uniform.ln() / (MAX_NEIGHBORS as f64).ln()) as usize
+  }
+
+  fn score(&self, vector: &[f32], node: usize) -> Scored {
+    Scored(cosine_distance(vector, &self.nodes[node].vector), node)
+  }
+}
+
+fn max_neighbors(layer: usize) -> usize {
+  match layer {
+    0 => 2 * MAX_NEIGHBORS,
+    _ => MAX_NEIGHBORS,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hnsw_finds_the_nearest_embeddings() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut random_vector = || (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect::<Vec<f32>>();
+    let vectors = (0..500).map(|_| random_vector()).collect::<Vec<_>>();
+    let queries = (0..20).map(|_| random_vector()).collect::<Vec<_>>();
+    let mut index = Hnsw::new(0);
+    for (id, vector) in vectors.iter().enumerate() {
+      index.insert(id as i64, vector.clone());
+    }
+    assert_eq!(index.len(), 500);
+
+    let exact = |query: &[f32]| {
+      let mut ids = (0..vectors.len() as i64).collect::<Vec<_>>();
+      ids.sort_by(|a, b| {
+        let distance = |id: &i64| cosine_distance(query, &vectors[*id as usize]);
+        distance(a).total_cmp(&distance(b))
+      });
+      ids.truncate(10);
+      ids
+    };
+    let found = queries
+      .iter()
+      .map(|query| {
+        let nearest = index.search(query, 10).into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        exact(query).iter().filter(|id| nearest.contains(id)).count()
+      })
+      .sum::<usize>();
+    assert!(found >= 190, "found {} of the 200 nearest", found);
+
+    // removed embeddings are not found, however near they are
+    let nearest = index.search(&vectors[3], 1)[0].0;
+    assert_eq!(nearest, 3);
+    assert!(index.remove(3));
+    assert!(!index.remove(3));
+    assert_ne!(index.search(&vectors[3], 1)[0].0, 3);
+    assert_eq!((index.len(), index.removed()), (499, 1));
+  }
+}
@@ -1,4 +1,6 @@
 pub mod data_manager;
 pub mod data_models;
+pub mod hnsw;
 pub mod schema;
 pub mod types;
+pub mod vector_store;
@@ -12,6 +12,8 @@ diesel::table! {
         page_number -> Int4,
         updated_at -> Timestamptz,
         file_embedding_id -> Int8,
+        start_line -> Int4,
+        end_line -> Int4,
     }
 }
 
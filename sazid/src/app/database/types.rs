@@ -71,12 +71,14 @@ pub struct InsertableSession {
 #[diesel(table_name = embedding_pages)]
 pub struct EmbeddingPage {
   id: i64,
-  content: String,
+  pub content: String,
   checksum: String,
   page_number: i32,
   #[serde(skip)]
   pub embedding: Vector,
   file_embedding_id: i64,
+  pub start_line: i32,
+  pub end_line: i32,
 }
 
 #[derive(Insertable, Debug, Clone, PartialEq, AsChangeset)]
@@ -86,6 +88,8 @@ pub struct InsertablePage {
   pub page_number: i32,
   pub checksum: String,
   pub embedding: Vector,
+  pub start_line: i32,
+  pub end_line: i32,
 }
 
 #[derive(
@@ -0,0 +1,444 @@
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::data_manager::PgVectorStore;
+use super::hnsw::Hnsw;
+use crate::app::errors::SazidError;
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (
+  id INTEGER PRIMARY KEY,
+  filepath TEXT NOT NULL,
+  checksum TEXT UNIQUE NOT NULL
+);
+CREATE TABLE IF NOT EXISTS chunks (
+  file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  start_line INTEGER NOT NULL,
+  end_line INTEGER NOT NULL,
+  content TEXT NOT NULL,
+  embedding BLOB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS files_filepath ON files (filepath);
+";
+
+/// where the embeddings of file chunks are kept
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum VectorStoreConfig {
+  /// the database at database_url, which needs the pgvector extension
+  #[default]
+  Postgres,
+  /// a local file that needs no database server, embeddings.sqlite in the data directory if
+  /// no path is set. its vector index is built in memory by the first search, postgres suits
+  /// stores of more than a few workspaces
+  Sqlite { path: Option<PathBuf> },
+}
+
+/// a chunk of a file with the embedding it is found by
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredChunk {
+  pub start_line: usize,
+  pub end_line: usize,
+  pub content: String,
+  pub embedding: Vec<f32>,
+}
+
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+  /// true if chunks are stored for a file with this checksum
+  async fn contains_file(&self, checksum: &str) -> Result<bool, SazidError>;
+
+  /// replaces the chunks stored for the file at `filepath`
+  async fn replace_file(
+    &self,
+    filepath: &str,
+    checksum: &str,
+    chunks: Vec<StoredChunk>,
+  ) -> Result<(), SazidError>;
+
+  /// the `limit` chunks nearest to `vector` by cosine distance, with the paths of their files.
+  /// only files whose path starts with `prefix` are searched
+  async fn nearest_chunks(
+    &self,
+    vector: Vec<f32>,
+    prefix: &str,
+    limit: usize,
+  ) -> Result<Vec<(String, StoredChunk)>, SazidError>;
+
+  /// every stored chunk of the files whose path starts with `prefix`, with the path of its file
+  /// and without its embedding, for keyword search. the chunks are all loaded and scored in
+  /// memory on each search
+  async fn text_chunks(&self, prefix: &str) -> Result<Vec<(String, StoredChunk)>, SazidError>;
+
+  /// the paths of the stored files with the number of chunks of each
+  async fn files(&self) -> Result<Vec<(String, usize)>, SazidError>;
+}
+
+pub fn open_vector_store(
+  config: &VectorStoreConfig,
+  database_url: &str,
+) -> Result<Arc<dyn VectorStore>, SazidError> {
+  match config {
+    VectorStoreConfig::Postgres if database_url.is_empty() => {
+      Err(SazidError::Other("the postgres vector store needs a database_url in the config".into()))
+    },
+    VectorStoreConfig::Postgres => Ok(Arc::new(PgVectorStore { db_url: database_url.to_string() })),
+    VectorStoreConfig::Sqlite { path } => {
+      let path = path.clone().unwrap_or_else(|| helix_loader::data_dir().join("embeddings.sqlite"));
+      Ok(Arc::new(SqliteVectorStore::open(&path)?))
+    },
+  }
+}
+
+pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+  let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+  let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+  let norms = norm(a) * norm(b);
+  if norms == 0.0 {
+    return 1.0;
+  }
+  1.0 - dot / norms
+}
+
+// each search asks the index for this many candidates per result, as chunks outside of the
+// prefix searched are among them
+const CANDIDATES_PER_RESULT: usize = 8;
+
+/// embeddings in a sqlite file, for setups without a database server. the sqlite-vec extension
+/// is left out since it has to be loaded into sqlite at runtime, the embeddings are searched
+/// with an hnsw index kept in memory instead. a search whose prefix leaves out most of the
+/// candidates the index finds compares the query with every chunk under the prefix
+pub struct SqliteVectorStore {
+  path: PathBuf,
+  // the index of the stored chunks by rowid, built by the first search and kept up to date as
+  // files are replaced
+  index: Arc<Mutex<Option<Hnsw>>>,
+}
+
+impl SqliteVectorStore {
+  pub fn open(path: &Path) -> Result<Self, SazidError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+      std::fs::create_dir_all(parent)?;
+    }
+    let store = SqliteVectorStore { path: path.to_path_buf(), index: Arc::new(Mutex::new(None)) };
+    store.connect()?.execute_batch(SQLITE_SCHEMA)?;
+    Ok(store)
+  }
+
+  fn connect(&self) -> Result<Connection, SazidError> {
+    let connection = Connection::open(&self.path)?;
+    connection.pragma_update(None, "foreign_keys", "ON")?;
+    Ok(connection)
+  }
+
+  /// runs `f` with a new connection on the blocking thread pool
+  async fn with_connection<T, F>(&self, f: F) -> Result<T, SazidError>
+  where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> Result<T, SazidError> + Send + 'static,
+  {
+    let mut connection = self.connect()?;
+    tokio::task::spawn_blocking(move || f(&mut connection))
+      .await
+      .map_err(|e| SazidError::Other(format!("vector store task failed: {}", e)))?
+  }
+}
+
+// a chunk found by a search, ordered by its distance from the query
+struct Nearest {
+  distance: f32,
+  filepath: String,
+  chunk: StoredChunk,
+}
+
+impl PartialEq for Nearest {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Nearest {}
+
+impl PartialOrd for Nearest {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Nearest {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.distance.total_cmp(&other.distance)
+  }
+}
+
+// an index of every stored chunk
+fn build_index(connection: &Connection) -> Result<Hnsw, SazidError> {
+  let mut index = Hnsw::new(0);
+  let mut statement = connection.prepare("SELECT rowid, embedding FROM chunks")?;
+  let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+  for row in rows {
+    let (id, blob) = row?;
+    index.insert(id, blob_to_embedding(&blob));
+  }
+  Ok(index)
+}
+
+// the `limit` chunks under the prefix nearest to the vector, comparing it with each of them
+fn scan_nearest(
+  connection: &Connection,
+  vector: &[f32],
+  prefix: &str,
+  limit: usize,
+) -> Result<Vec<(String, StoredChunk)>, SazidError> {
+  let mut statement = connection.prepare(
+    "SELECT files.filepath, chunks.start_line, chunks.end_line, chunks.content,
+       chunks.embedding
+     FROM chunks JOIN files ON files.id = chunks.file_id
+     WHERE substr(files.filepath, 1, length(?1)) = ?1",
+  )?;
+  let rows = statement.query_map(params![prefix], |row| {
+    let chunk = StoredChunk {
+      start_line: row.get(1)?,
+      end_line: row.get(2)?,
+      content: row.get(3)?,
+      embedding: blob_to_embedding(&row.get::<_, Vec<u8>>(4)?),
+    };
+    Ok((row.get::<_, String>(0)?, chunk))
+  })?;
+  // the farthest of the nearest chunks so far is on top, and is dropped for a nearer one
+  let mut nearest = BinaryHeap::with_capacity(limit + 1);
+  for row in rows {
+    let (filepath, chunk) = row?;
+    let distance = cosine_distance(vector, &chunk.embedding);
+    nearest.push(Nearest { distance, filepath, chunk });
+    if nearest.len() > limit {
+      nearest.pop();
+    }
+  }
+  Ok(nearest.into_sorted_vec().into_iter().map(|n| (n.filepath, n.chunk)).collect())
+}
+
+fn embedding_to_blob(embedding: &[f32]) -> Vec<u8> {
+  embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn blob_to_embedding(blob: &[u8]) -> Vec<f32> {
+  blob.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+}
+
+#[async_trait]
+impl VectorStore for SqliteVectorStore {
+  async fn contains_file(&self, checksum: &str) -> Result<bool, SazidError> {
+    let checksum = checksum.to_string();
+    self
+      .with_connection(move |connection| {
+        Ok(
+          connection
+            .query_row("SELECT 1 FROM files WHERE checksum = ?1", params![checksum], |_| Ok(()))
+            .optional()?
+            .is_some(),
+        )
+      })
+      .await
+  }
+
+  async fn replace_file(
+    &self,
+    filepath: &str,
+    checksum: &str,
+    chunks: Vec<StoredChunk>,
+  ) -> Result<(), SazidError> {
+    let (filepath, checksum) = (filepath.to_string(), checksum.to_string());
+    let index = self.index.clone();
+    self
+      .with_connection(move |connection| {
+        let transaction = connection.transaction()?;
+        let replaced = transaction
+          .prepare(
+            "SELECT chunks.rowid FROM chunks JOIN files ON files.id = chunks.file_id
+             WHERE files.filepath = ?1",
+          )?
+          .query_map(params![filepath], |row| row.get::<_, i64>(0))?
+          .collect::<Result<Vec<_>, _>>()?;
+        transaction.execute("DELETE FROM files WHERE filepath = ?1", params![filepath])?;
+        transaction.execute(
+          "INSERT INTO files (filepath, checksum) VALUES (?1, ?2)",
+          params![filepath, checksum],
+        )?;
+        let file_id = transaction.last_insert_rowid();
+        let mut added = vec![];
+        for chunk in chunks {
+          transaction.execute(
+            "INSERT INTO chunks (file_id, start_line, end_line, content, embedding)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+              file_id,
+              chunk.start_line,
+              chunk.end_line,
+              chunk.content,
+              embedding_to_blob(&chunk.embedding)
+            ],
+          )?;
+          added.push((transaction.last_insert_rowid(), chunk.embedding));
+        }
+        transaction.commit()?;
+
+        let mut index = index.lock().unwrap();
+        if let Some(hnsw) = index.as_mut() {
+          for id in replaced {
+            hnsw.remove(id);
+          }
+          for (id, embedding) in added {
+            hnsw.insert(id, embedding);
+          }
+          // an index mostly made of removed chunks is built again by the next search
+          if hnsw.removed() > hnsw.len() {
+            *index = None;
+          }
+        }
+        Ok(())
+      })
+      .await
+  }
+
+  async fn nearest_chunks(
+    &self,
+    vector: Vec<f32>,
+    prefix: &str,
+    limit: usize,
+  ) -> Result<Vec<(String, StoredChunk)>, SazidError> {
+    if limit == 0 {
+      return Ok(vec![]);
+    }
+    let prefix = prefix.to_string();
+    let index = self.index.clone();
+    self
+      .with_connection(move |connection| {
+        let mut index = index.lock().unwrap();
+        let hnsw = match index.take() {
+          Some(hnsw) => hnsw,
+          None => build_index(connection)?,
+        };
+        let hnsw = index.insert(hnsw);
+        let candidates = hnsw.search(&vector, limit * CANDIDATES_PER_RESULT);
+        let mut statement = connection.prepare(
+          "SELECT files.filepath, chunks.start_line, chunks.end_line, chunks.content,
+             chunks.embedding
+           FROM chunks JOIN files ON files.id = chunks.file_id
+           WHERE chunks.rowid = ?1 AND substr(files.filepath, 1, length(?2)) = ?2",
+        )?;
+        let mut nearest = vec![];
+        for (id, _) in &candidates {
+          let row = statement
+            .query_row(params![id, prefix], |row| {
+              let chunk = StoredChunk {
+                start_line: row.get(1)?,
+                end_line: row.get(2)?,
+                content: row.get(3)?,
+                embedding: blob_to_embedding(&row.get::<_, Vec<u8>>(4)?),
+              };
+              Ok((row.get::<_, String>(0)?, chunk))
+            })
+            .optional()?;
+          nearest.extend(row);
+          if nearest.len() == limit {
+            return Ok(nearest);
+          }
+        }
+        // the index was searched through without finding enough chunks under the prefix
+        if candidates.len() < hnsw.len() {
+          return scan_nearest(connection, &vector, &prefix, limit);
+        }
+        Ok(nearest)
+      })
+      .await
+  }
+
+  async fn text_chunks(&self, prefix: &str) -> Result<Vec<(String, StoredChunk)>, SazidError> {
+    let prefix = prefix.to_string();
+    self
+      .with_connection(move |connection| {
+        let mut statement = connection.prepare(
+          "SELECT files.filepath, chunks.start_line, chunks.end_line, chunks.content
+           FROM chunks JOIN files ON files.id = chunks.file_id
+           WHERE substr(files.filepath, 1, length(?1)) = ?1",
+        )?;
+        let chunks = statement
+          .query_map(params![prefix], |row| {
+            let chunk = StoredChunk {
+              start_line: row.get(1)?,
+              end_line: row.get(2)?,
+              content: row.get(3)?,
+              embedding: vec![],
+            };
+            Ok((row.get::<_, String>(0)?, chunk))
+          })?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(chunks)
+      })
+      .await
+  }
+
+  async fn files(&self) -> Result<Vec<(String, usize)>, SazidError> {
+    self
+      .with_connection(|connection| {
+        let mut statement = connection.prepare(
+          "SELECT files.filepath, COUNT(chunks.file_id) FROM files
+           LEFT JOIN chunks ON chunks.file_id = files.id GROUP BY files.id ORDER BY files.filepath",
+        )?;
+        let files = statement
+          .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(files)
+      })
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn chunk(start_line: usize, embedding: Vec<f32>) -> StoredChunk {
+    StoredChunk { start_line, end_line: start_line + 1, content: "fn a() {}".into(), embedding }
+  }
+
+  #[tokio::test]
+  async fn test_sqlite_vector_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SqliteVectorStore::open(&dir.path().join("embeddings.sqlite")).unwrap();
+    store
+      .replace_file("src/a.rs", "a1", vec![chunk(1, vec![1.0, 0.0]), chunk(5, vec![0.0, 1.0])])
+      .await
+      .unwrap();
+    store.replace_file("src/b.rs", "b1", vec![chunk(1, vec![0.6, 0.8])]).await.unwrap();
+    assert!(store.contains_file("a1").await.unwrap());
+    assert_eq!(store.text_chunks("").await.unwrap().len(), 3);
+    assert_eq!(store.text_chunks("src/b").await.unwrap().len(), 1);
+
+    let nearest = store.nearest_chunks(vec![0.0, 2.0], "", 2).await.unwrap();
+    let nearest = nearest.iter().map(|(path, c)| (path.as_str(), c.start_line)).collect::<Vec<_>>();
+    assert_eq!(nearest, vec![("src/a.rs", 5), ("src/b.rs", 1)]);
+    assert_eq!(store.nearest_chunks(vec![1.0, 0.0], "", 10).await.unwrap().len(), 3);
+    // chunks of files outside of the prefix are left out, however near they are
+    let nearest = store.nearest_chunks(vec![0.0, 2.0], "src/b", 2).await.unwrap();
+    assert_eq!(nearest.iter().map(|(path, _)| path.as_str()).collect::<Vec<_>>(), ["src/b.rs"]);
+    assert!(store.nearest_chunks(vec![0.0, 2.0], "lib/", 2).await.unwrap().is_empty());
+
+    // a file indexed again replaces its old chunks, in the index too
+    store.replace_file("src/a.rs", "a2", vec![chunk(1, vec![1.0, 0.0])]).await.unwrap();
+    assert!(!store.contains_file("a1").await.unwrap());
+    let nearest = store.nearest_chunks(vec![0.0, 2.0], "", 2).await.unwrap();
+    let nearest = nearest.iter().map(|(path, c)| (path.as_str(), c.start_line)).collect::<Vec<_>>();
+    assert_eq!(nearest, vec![("src/b.rs", 1), ("src/a.rs", 1)]);
+    assert_eq!(
+      store.files().await.unwrap(),
+      vec![("src/a.rs".to_string(), 1), ("src/b.rs".to_string(), 1)]
+    );
+  }
+}
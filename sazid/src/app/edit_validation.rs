@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Parser};
+
+use super::treesitter::language_for_path;
+
+// syntax errors listed before the rest are counted instead
+const MAX_SYNTAX_ERRORS: usize = 10;
+const CARGO_CHECK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// the checks a file edit has to pass to be kept
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EditValidation {
+  /// the edit must not add syntax errors to the file, for languages with a grammar
+  pub syntax: bool,
+  /// `cargo check` has to pass in the workspace, for rust files
+  pub cargo_check: bool,
+}
+
+impl Default for EditValidation {
+  fn default() -> Self {
+    EditValidation { syntax: true, cargo_check: false }
+  }
+}
+
+/// the syntax errors in `source`, parsed with the grammar for `path`. none when there is no
+/// grammar for the file
+pub fn syntax_errors(path: &Path, source: &str) -> Option<Vec<String>> {
+  let language = language_for_path(path)?;
+  let mut parser = Parser::new();
+  parser.set_language(language).ok()?;
+  let tree = parser.parse(source, None)?;
+  let mut errors = vec![];
+  collect_errors(tree.root_node(), source, &mut errors);
+  Some(errors)
+}
+
+/// the syntax errors in `modified` that were not in `original`, so an edit is not refused for
+/// errors the file already had. errors are told apart by what is wrong, not where, since an edit
+/// moves the lines after it
+pub fn new_syntax_errors(path: &Path, original: &str, modified: &str) -> Option<Vec<String>> {
+  let mut existing: HashMap<String, usize> = HashMap::new();
+  for error in syntax_errors(path, original).unwrap_or_default() {
+    *existing.entry(error_kind(&error).to_string()).or_default() += 1;
+  }
+  let errors = syntax_errors(path, modified)?;
+  Some(
+    errors
+      .into_iter()
+      .filter(|error| match existing.get_mut(error_kind(error)) {
+        Some(count) if *count > 0 => {
+          *count -= 1;
+          false
+        },
+        _ => true,
+      })
+      .collect(),
+  )
+}
+
+// an error without its position, as in "missing ;"
+fn error_kind(error: &str) -> &str {
+  error.split_once(": ").map_or(error, |(_, kind)| kind)
+}
+
+fn collect_errors(node: Node, source: &str, errors: &mut Vec<String>) {
+  if !node.has_error() {
+    return;
+  }
+  let position = node.start_position();
+  if node.is_missing() {
+    errors.push(format!(
+      "line {} column {}: missing {}",
+      position.row + 1,
+      position.column + 1,
+      node.kind()
+    ));
+    return;
+  }
+  if node.is_error() {
+    let text = source[node.byte_range()].lines().next().unwrap_or_default();
+    errors.push(format!(
+      "line {} column {}: unexpected `{}`",
+      position.row + 1,
+      position.column + 1,
+      text.chars().take(40).collect::<String>()
+    ));
+    return;
+  }
+  let mut cursor = node.walk();
+  for child in node.children(&mut cursor) {
+    collect_errors(child, source, errors);
+  }
+}
+
+/// why an edit was refused, when the edited file has syntax errors
+pub fn describe_syntax_errors(path: &Path, errors: &[String]) -> String {
+  let mut text = format!("{} does not parse after the edit:\n", path.display());
+  for error in errors.iter().take(MAX_SYNTAX_ERRORS) {
+    text.push_str(&format!("  {}\n", error));
+  }
+  if errors.len() > MAX_SYNTAX_ERRORS {
+    text.push_str(&format!("  and {} more\n", errors.len() - MAX_SYNTAX_ERRORS));
+  }
+  text
+}
+
+/// runs `cargo check` in `workspace_root`, returning its errors when it fails
+pub async fn cargo_check(workspace_root: &Path) -> anyhow::Result<Option<String>> {
+  let mut command = tokio::process::Command::new("cargo");
+  command
+    .args(["check", "--quiet", "--message-format", "short"])
+    .current_dir(workspace_root)
+    .stdin(Stdio::null())
+    .kill_on_drop(true);
+  let output = tokio::time::timeout(CARGO_CHECK_TIMEOUT, command.output())
+    .await
+    .map_err(|_| anyhow::anyhow!("cargo check did not finish within 5 minutes"))??;
+  match output.status.success() {
+    true => Ok(None),
+    false => Ok(Some(String::from_utf8_lossy(&output.stderr).to_string())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_syntax_errors() {
+    let path = Path::new("src/lib.rs");
+    assert_eq!(syntax_errors(path, "fn a() -> u8 {\n  1\n}\n"), Some(vec![]));
+    let errors = syntax_errors(path, "fn a() -> u8 {\n  let x = ;\n}\n").unwrap();
+    assert!(!errors.is_empty());
+    assert!(errors[0].starts_with("line 2"));
+    assert!(!syntax_errors(path, "fn a() {\n").unwrap().is_empty());
+    assert_eq!(syntax_errors(Path::new("notes.txt"), "fn a() {"), None);
+  }
+
+  #[test]
+  fn test_new_syntax_errors() {
+    let path = Path::new("src/lib.rs");
+    let original = "fn a() -> u8 {\n  1\n}\nfn b() {\n  let x = ;\n}\n";
+    // the error already in b is moved down by the edit, but is not new
+    let modified = "fn a() -> u8 {\n  let y = 1;\n  y\n}\nfn b() {\n  let x = ;\n}\n";
+    assert_eq!(new_syntax_errors(path, original, modified), Some(vec![]));
+    let broken = "fn a() -> u8 {\n  let y = ;\n  y\n}\nfn b() {\n  let x = ;\n}\n";
+    assert_eq!(new_syntax_errors(path, original, broken).map(|errors| errors.len()), Some(1));
+  }
+}
@@ -17,6 +17,7 @@ pub enum SazidError {
   Other(String),
   ChunkifierError(ChunkifierError),
   TokioPosgresError(tokio_postgres::Error),
+  SqliteError(rusqlite::Error),
 }
 
 impl fmt::Display for SazidError {
@@ -31,6 +32,7 @@ impl fmt::Display for SazidError {
       SazidError::TokioPosgresError(err) => {
         write!(f, "TokioPosgresError: {}", err)
       },
+      SazidError::SqliteError(err) => write!(f, "SqliteError: {}", err),
       SazidError::ChunkifierError(err) => write!(f, "ChunkifierError: {}", err),
       SazidError::ParseError(err) => write!(f, "ParseError: {}", err),
       SazidError::ConfigError(err) => write!(f, "ConfigError: {}", err),
@@ -60,6 +62,12 @@ impl From<dialoguer::Error> for SazidError {
   }
 }
 
+impl From<keyring::Error> for SazidError {
+  fn from(err: keyring::Error) -> SazidError {
+    SazidError::Other(format!("keyring: {}", err))
+  }
+}
+
 impl From<std::io::Error> for SazidError {
   fn from(err: std::io::Error) -> SazidError {
     SazidError::IoError(err)
@@ -72,6 +80,12 @@ impl From<tokio_postgres::Error> for SazidError {
   }
 }
 
+impl From<rusqlite::Error> for SazidError {
+  fn from(err: rusqlite::Error) -> SazidError {
+    SazidError::SqliteError(err)
+  }
+}
+
 impl From<ParseError> for SazidError {
   fn from(err: ParseError) -> SazidError {
     SazidError::ParseError(err)
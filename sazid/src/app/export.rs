@@ -0,0 +1,181 @@
+use std::{fs, path::Path, str::FromStr};
+
+use async_openai::types::ChatCompletionRequestMessage;
+
+use super::{
+  errors::SazidError,
+  markdown::HtmlRenderer,
+  messages::{
+    chat_completion_request_message_content_as_str,
+    chat_completion_request_message_tool_calls_as_str, MessageContainer,
+  },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+  Markdown,
+  Html,
+}
+
+impl FromStr for ExportFormat {
+  type Err = SazidError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "markdown" | "md" => Ok(ExportFormat::Markdown),
+      "html" => Ok(ExportFormat::Html),
+      _ => Err(SazidError::Other(format!("unknown export format: {}", s))),
+    }
+  }
+}
+
+fn role_name(message: &ChatCompletionRequestMessage) -> &'static str {
+  match message {
+    ChatCompletionRequestMessage::System(_) => "System",
+    ChatCompletionRequestMessage::User(_) => "User",
+    ChatCompletionRequestMessage::Assistant(_) => "Assistant",
+    ChatCompletionRequestMessage::Tool(_) => "Tool",
+    ChatCompletionRequestMessage::Function(_) => "Function",
+  }
+}
+
+/// a backtick fence longer than any run of backticks in text
+fn fence_for(text: &str) -> String {
+  let longest = text.split(|c| c != '`').map(|run| run.len()).max().unwrap_or(0);
+  "`".repeat(longest.max(2) + 1)
+}
+
+pub fn message_to_markdown(message: &ChatCompletionRequestMessage) -> String {
+  let mut markdown = format!("## {}\n\n", role_name(message));
+  let content = chat_completion_request_message_content_as_str(message);
+  match message {
+    // tool output is rarely markdown, keep it verbatim
+    ChatCompletionRequestMessage::Tool(_) | ChatCompletionRequestMessage::Function(_) => {
+      let fence = fence_for(content);
+      markdown.push_str(&format!("{}\n{}\n{}\n\n", fence, content, fence));
+    },
+    _ if !content.is_empty() => markdown.push_str(&format!("{}\n\n", content)),
+    _ => {},
+  }
+  if let Some(tool_calls) = chat_completion_request_message_tool_calls_as_str(message) {
+    tool_calls.iter().for_each(|(name, arguments)| {
+      let fence = fence_for(arguments);
+      markdown.push_str(&format!(
+        "**Tool Call:** `{}`\n\n{}json\n{}\n{}\n\n",
+        name, fence, arguments, fence
+      ));
+    });
+  }
+  markdown
+}
+
+pub fn session_to_markdown(title: &str, messages: &[MessageContainer]) -> String {
+  let mut markdown = format!("# {}\n\n", title);
+  messages.iter().for_each(|m| markdown.push_str(&message_to_markdown(&m.message)));
+  markdown
+}
+
+pub fn session_to_html(title: &str, messages: &[MessageContainer]) -> String {
+  let renderer = HtmlRenderer::default();
+  let title = html_escape::encode_text(title);
+  let mut body = String::new();
+  messages.iter().for_each(|m| {
+    let role = role_name(&m.message);
+    let content = chat_completion_request_message_content_as_str(&m.message);
+    body.push_str(&format!(
+      "<section class=\"message {}\">\n<h2>{}</h2>\n",
+      role.to_lowercase(),
+      role
+    ));
+    match &m.message {
+      // tool calls and results are collapsed, they tend to be long
+      ChatCompletionRequestMessage::Tool(_) | ChatCompletionRequestMessage::Function(_) => {
+        body.push_str(&format!(
+          "<details>\n<summary>tool result</summary>\n<pre>{}</pre>\n</details>\n",
+          html_escape::encode_text(content)
+        ));
+      },
+      _ => body.push_str(&renderer.render(content)),
+    }
+    if let Some(tool_calls) = chat_completion_request_message_tool_calls_as_str(&m.message) {
+      tool_calls.iter().for_each(|(name, arguments)| {
+        body.push_str(&format!(
+          "<details>\n<summary>tool call: {}</summary>\n<pre>{}</pre>\n</details>\n",
+          html_escape::encode_text(name),
+          html_escape::encode_text(arguments)
+        ));
+      });
+    }
+    body.push_str("</section>\n");
+  });
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\nbody {{ font-family: sans-serif; max-width: 60em; margin: auto; }}\nsection.message {{ border-bottom: 1px solid #ddd; padding: 0.5em 0; }}\nsection.tool, section.function {{ color: #555; }}\npre {{ overflow-x: auto; padding: 0.5em; }}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n",
+    title, title, body
+  )
+}
+
+pub fn export_session(
+  title: &str,
+  messages: &[MessageContainer],
+  format: ExportFormat,
+  path: &Path,
+) -> Result<(), SazidError> {
+  let transcript = match format {
+    ExportFormat::Markdown => session_to_markdown(title, messages),
+    ExportFormat::Html => session_to_html(title, messages),
+  };
+  if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+    fs::create_dir_all(parent)?;
+  }
+  fs::write(path, transcript)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestToolMessage, Role,
+  };
+
+  fn assistant(content: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::Assistant(
+      ChatCompletionRequestAssistantMessage {
+        name: None,
+        role: Role::Assistant,
+        content: Some(content.to_string()),
+        function_call: None,
+        tool_calls: None,
+      },
+    ))
+  }
+
+  fn tool(content: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::Tool(
+      ChatCompletionRequestToolMessage {
+        role: Role::Tool,
+        content: content.to_string(),
+        tool_call_id: "call_1".to_string(),
+      },
+    ))
+  }
+
+  #[test]
+  fn test_markdown_export_fences_tool_output() {
+    let messages = vec![assistant("see below"), tool("line one\n```\nline two")];
+    let markdown = session_to_markdown("test session", &messages);
+    assert!(markdown.starts_with("# test session\n"));
+    assert!(markdown.contains("## Assistant\n\nsee below"));
+    assert!(markdown.contains("## Tool\n\n````\nline one\n```\nline two\n````"));
+  }
+
+  #[test]
+  fn test_html_export_highlights_code_and_collapses_tools() {
+    let messages = vec![assistant("```rust\nfn main() {}\n```"), tool("<result>")];
+    let html = session_to_html("test session", &messages);
+    assert!(html.contains("<title>test session</title>"));
+    assert!(!html.contains("<code class=\"language-rust\">"));
+    assert!(html.contains("<pre style="));
+    assert!(html.contains("<details>\n<summary>tool result</summary>\n<pre>&lt;result&gt;</pre>"));
+  }
+}
@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// unchanged lines kept around each change in a preview
+const PREVIEW_CONTEXT_LINES: usize = 3;
+
+/// a change a tool made to a file, kept so it can be shown as a diff and reverted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEdit {
+  pub tool_call_id: String,
+  pub path: PathBuf,
+  /// the contents before the change, none if the tool created the file
+  pub original: Option<String>,
+  pub modified: String,
+}
+
+impl FileEdit {
+  /// restores the file to its contents before the change, removing it if it was created
+  pub fn revert(&self) -> std::io::Result<()> {
+    match &self.original {
+      Some(original) => std::fs::write(&self.path, original),
+      None => std::fs::remove_file(&self.path),
+    }
+  }
+
+  /// whether the file still holds the text of the change, so reverting it loses nothing
+  pub fn is_current(&self) -> bool {
+    std::fs::read_to_string(&self.path).ok().as_deref() == Some(self.modified.as_str())
+  }
+
+  pub fn diff_lines(&self) -> Vec<DiffLine> {
+    diff_lines(self.original.as_deref().unwrap_or_default(), &self.modified)
+  }
+}
+
+/// a line of a diff between two texts
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+  Unchanged(String),
+  Removed(String),
+  Added(String),
+  /// unchanged lines away from any change that were left out
+  Skipped,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Hunk {
+  /// 1 based line the hunk starts at in the original file, per its header
+  old_start: usize,
+  old_lines: Vec<String>,
+  new_lines: Vec<String>,
+  /// the hunk as it is in the diff, header included
+  text: String,
+}
+
+impl Hunk {
+  // the lines the old and new side share at their start and at their end, the hunk's context
+  fn context_lines(&self) -> (usize, usize) {
+    let shared = |a: &[String], b: &[String]| a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let leading = shared(&self.old_lines, &self.new_lines);
+    let max_trailing = self.old_lines.len().min(self.new_lines.len()) - leading;
+    let trailing = shared(
+      &self.old_lines.iter().rev().cloned().collect::<Vec<_>>(),
+      &self.new_lines.iter().rev().cloned().collect::<Vec<_>>(),
+    );
+    (leading, trailing.min(max_trailing))
+  }
+}
+
+/// how a hunk of a patch went
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunkResult {
+  /// 1 based line of the original the hunk was applied at, none if it did not match
+  pub applied_at: Option<usize>,
+  /// the context lines left out at either end for the hunk to match
+  pub fuzz: usize,
+}
+
+/// the result of applying a diff, with the hunks that did not match kept to be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchOutcome {
+  pub patched: String,
+  pub hunks: Vec<HunkResult>,
+  /// the hunks that did not match, as they were in the diff
+  pub rejects: String,
+}
+
+/// true for code blocks holding a unified diff
+pub fn is_diff(language: &str, code: &str) -> bool {
+  matches!(language, "diff" | "patch")
+    || code.starts_with("diff --git")
+    || code.starts_with("--- ") && code.contains("\n+++ ")
+}
+
+/// the file a diff applies to, from its +++ header, without the b/ prefix git adds
+pub fn patch_target(diff: &str) -> Option<PathBuf> {
+  let path = diff.lines().find_map(|line| line.strip_prefix("+++ "))?;
+  // a tab separates the path from a timestamp in diffs made by diff -u
+  let path = path.split('\t').next().unwrap_or_default().trim();
+  match path {
+    "" | "/dev/null" => None,
+    path => Some(PathBuf::from(path.strip_prefix("b/").unwrap_or(path))),
+  }
+}
+
+fn parse_hunks(diff: &str) -> anyhow::Result<Vec<Hunk>> {
+  let mut hunks: Vec<Hunk> = vec![];
+  for line in diff.lines() {
+    if let Some(header) = line.strip_prefix("@@") {
+      let old_start = header
+        .split_whitespace()
+        .find_map(|range| range.strip_prefix('-'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse::<usize>().ok())
+        .ok_or_else(|| anyhow::anyhow!("invalid hunk header: {}", line))?;
+      hunks.push(Hunk { old_start, text: format!("{}\n", line), ..Default::default() });
+      continue;
+    }
+    let Some(hunk) = hunks.last_mut() else {
+      continue;
+    };
+    if line.starts_with("diff ") {
+      anyhow::bail!("diffs of several files are not supported");
+    }
+    hunk.text.push_str(line);
+    hunk.text.push('\n');
+    // hunk line counts are not trusted, models rarely get them right. blank lines are taken
+    // as blank context lines whose leading space was trimmed
+    match line.chars().next() {
+      Some('-') => hunk.old_lines.push(line[1..].to_string()),
+      Some('+') => hunk.new_lines.push(line[1..].to_string()),
+      Some(' ') => {
+        hunk.old_lines.push(line[1..].to_string());
+        hunk.new_lines.push(line[1..].to_string());
+      },
+      None => {
+        hunk.old_lines.push(String::new());
+        hunk.new_lines.push(String::new());
+      },
+      _ => {},
+    }
+  }
+  match hunks.is_empty() {
+    true => Err(anyhow::anyhow!("the diff has no hunks")),
+    false => Ok(hunks),
+  }
+}
+
+/// applies a unified diff to `original`. hunks are located by their content, preferring the
+/// match closest to the line in their header, so diffs with stale line numbers still apply
+pub fn apply_patch(original: &str, diff: &str) -> anyhow::Result<String> {
+  let outcome = apply_hunks(original, diff, 0)?;
+  match outcome.hunks.iter().position(|hunk| hunk.applied_at.is_none()) {
+    Some(i) => Err(anyhow::anyhow!("hunk {} does not match the file", i + 1)),
+    None => Ok(outcome.patched),
+  }
+}
+
+/// applies the hunks of a unified diff that match `original`, located like `apply_patch` does.
+/// a hunk that does not match is tried again with up to `max_fuzz` of its context lines left
+/// out at either end, and is rejected if it still does not
+pub fn apply_hunks(original: &str, diff: &str, max_fuzz: usize) -> anyhow::Result<PatchOutcome> {
+  let lines = original.lines().collect::<Vec<_>>();
+  let mut patched: Vec<&str> = vec![];
+  let mut next_line = 0;
+  let mut results = vec![];
+  let mut rejects = String::new();
+  let hunks = parse_hunks(diff)?;
+  for hunk in hunks.iter() {
+    let (leading, trailing) = hunk.context_lines();
+    let located = (0..=max_fuzz).find_map(|fuzz| {
+      let (skip_start, skip_end) = (fuzz.min(leading), fuzz.min(trailing));
+      let old_lines = &hunk.old_lines[skip_start..hunk.old_lines.len() - skip_end];
+      let new_lines = &hunk.new_lines[skip_start..hunk.new_lines.len() - skip_end];
+      let expected = match hunk.old_lines.is_empty() {
+        true => hunk.old_start,
+        false => hunk.old_start.saturating_sub(1) + skip_start,
+      };
+      let matches_at = |start: usize| {
+        lines[start..start + old_lines.len()]
+          .iter()
+          .zip(old_lines.iter())
+          .all(|(line, old)| line.trim_end() == old.trim_end())
+      };
+      (next_line..=lines.len().saturating_sub(old_lines.len()))
+        .filter(|start| start + old_lines.len() <= lines.len() && matches_at(*start))
+        .min_by_key(|start| start.abs_diff(expected))
+        .map(|start| (start, fuzz, old_lines.len(), new_lines))
+    });
+    match located {
+      Some((start, fuzz, old_len, new_lines)) => {
+        patched.extend(&lines[next_line..start]);
+        patched.extend(new_lines.iter().map(String::as_str));
+        next_line = start + old_len;
+        results.push(HunkResult { applied_at: Some(start + 1), fuzz });
+      },
+      None => {
+        rejects.push_str(&hunk.text);
+        results.push(HunkResult { applied_at: None, fuzz: 0 });
+      },
+    }
+  }
+  patched.extend(&lines[next_line..]);
+
+  let mut patched = patched.join("\n");
+  if original.ends_with('\n') || original.is_empty() {
+    patched.push('\n');
+  }
+  Ok(PatchOutcome { patched, hunks: results, rejects })
+}
+
+/// the diffs of each file in a diff of several, each with its headers
+pub fn file_diffs(diff: &str) -> Vec<String> {
+  let lines = diff.lines().collect::<Vec<_>>();
+  let mut diffs: Vec<String> = vec![];
+  let mut in_hunks = false;
+  for (i, line) in lines.iter().enumerate() {
+    let starts_file = line.starts_with("diff ")
+      || line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "));
+    // a --- header right after a diff line belongs to the same file
+    let after_diff_line = i > 0 && lines[i - 1].starts_with("diff ");
+    if diffs.is_empty() || starts_file && in_hunks && !after_diff_line {
+      diffs.push(String::new());
+      in_hunks = false;
+    }
+    in_hunks |= line.starts_with("@@");
+    let diff = diffs.last_mut().unwrap();
+    diff.push_str(line);
+    diff.push('\n');
+  }
+  diffs
+}
+
+/// the lines of a diff of `old` and `new`. unchanged lines away from any change are left out
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+  // diff::lines splits on every newline, which would leave an empty last line
+  let lines =
+    diff::lines(old.strip_suffix('\n').unwrap_or(old), new.strip_suffix('\n').unwrap_or(new));
+  let is_change = |line: &diff::Result<&str>| !matches!(line, diff::Result::Both(..));
+  let near_change = |idx: usize| {
+    let from = idx.saturating_sub(PREVIEW_CONTEXT_LINES);
+    let to = (idx + PREVIEW_CONTEXT_LINES + 1).min(lines.len());
+    lines[from..to].iter().any(is_change)
+  };
+
+  let mut diff: Vec<DiffLine> = vec![];
+  for (idx, line) in lines.iter().enumerate() {
+    match line {
+      diff::Result::Left(l) => diff.push(DiffLine::Removed(l.to_string())),
+      diff::Result::Right(r) => diff.push(DiffLine::Added(r.to_string())),
+      diff::Result::Both(b, _) if near_change(idx) => diff.push(DiffLine::Unchanged(b.to_string())),
+      diff::Result::Both(..) => {
+        if diff.last() != Some(&DiffLine::Skipped) {
+          diff.push(DiffLine::Skipped);
+        }
+      },
+    }
+  }
+  diff
+}
+
+/// a line diff of `old` and `new` for confirming a change
+pub fn preview_diff(old: &str, new: &str) -> String {
+  diff_lines(old, new)
+    .into_iter()
+    .map(|line| match line {
+      DiffLine::Unchanged(line) => format!("  {}\n", line),
+      DiffLine::Removed(line) => format!("- {}\n", line),
+      DiffLine::Added(line) => format!("+ {}\n", line),
+      DiffLine::Skipped => "  ...\n".to_string(),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_apply_patch_with_stale_line_numbers() {
+    let original = "fn a() {}\n\nfn b() {\n  1\n}\n\nfn c() {}\n";
+    let diff = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -10,3 +10,3 @@\n fn b() {\n-  1\n+  2\n }\n@@ -7,0 +7,1 @@\n fn c() {}\n+fn d() {}\n";
+    assert!(is_diff("", diff));
+    assert_eq!(patch_target(diff), Some(PathBuf::from("src/lib.rs")));
+    assert_eq!(
+      apply_patch(original, diff).unwrap(),
+      "fn a() {}\n\nfn b() {\n  2\n}\n\nfn c() {}\nfn d() {}\n"
+    );
+    assert!(apply_patch(original, "@@ -1,1 +1,1 @@\n-fn z() {}\n+fn y() {}\n").is_err());
+  }
+
+  #[test]
+  fn test_apply_hunks_with_fuzz() {
+    let original = "fn a() {}\n\nfn b() {\n  1\n}\n";
+    // the first context line was changed since the diff was made
+    let diff =
+      "@@ -3,3 +3,3 @@\n fn b(x: u8) {\n-  1\n+  2\n }\n@@ -9,1 +9,1 @@\n-fn z() {}\n+fn y() {}\n";
+    let outcome = apply_hunks(original, diff, 0).unwrap();
+    assert!(outcome.hunks.iter().all(|hunk| hunk.applied_at.is_none()));
+    assert_eq!(outcome.patched, original);
+
+    let outcome = apply_hunks(original, diff, 1).unwrap();
+    assert_eq!(outcome.patched, "fn a() {}\n\nfn b() {\n  2\n}\n");
+    assert_eq!(
+      outcome.hunks,
+      vec![HunkResult { applied_at: Some(4), fuzz: 1 }, HunkResult { applied_at: None, fuzz: 0 }]
+    );
+    assert_eq!(outcome.rejects, "@@ -9,1 +9,1 @@\n-fn z() {}\n+fn y() {}\n");
+
+    let diffs = file_diffs(
+      "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1 +1 @@\n-a\n+b\n--- b.rs\n+++ b.rs\n@@ -1 +1 @@\n-c\n+d\n",
+    );
+    assert_eq!(diffs.len(), 2);
+    assert_eq!(patch_target(&diffs[0]), Some(PathBuf::from("a.rs")));
+    assert_eq!(patch_target(&diffs[1]), Some(PathBuf::from("b.rs")));
+  }
+
+  #[test]
+  fn test_preview_diff_skips_distant_lines() {
+    let old = (1..=10).map(|i| format!("{}\n", i)).collect::<String>();
+    let new = old.replace("9\n", "nine\n");
+    assert_eq!(preview_diff(&old, &new), "  ...\n  6\n  7\n  8\n- 9\n+ nine\n  10\n");
+  }
+
+  #[test]
+  fn test_revert_file_edit() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.rs");
+    std::fs::write(&path, "fn b() {}\n").unwrap();
+    let edit = FileEdit {
+      tool_call_id: "call_1".to_string(),
+      path: path.clone(),
+      original: Some("fn a() {}\n".to_string()),
+      modified: "fn b() {}\n".to_string(),
+    };
+    assert_eq!(
+      edit.diff_lines(),
+      vec![DiffLine::Removed("fn a() {}".to_string()), DiffLine::Added("fn b() {}".to_string())]
+    );
+    edit.revert().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn a() {}\n");
+
+    FileEdit { original: None, ..edit }.revert().unwrap();
+    assert!(!path.exists());
+  }
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::lsi::symbol_types::SourceSymbol;
+
+/// the most lines a read returns, a longer read is truncated with a notice of where to continue
+pub const MAX_READ_LINES: usize = 400;
+
+/// a read of a file for the model, of 1 based inclusive lines or of the lines of a symbol
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRead {
+  pub file_path: Option<PathBuf>,
+  /// a symbol name, qualified by the names of the symbols it is declared in, as in `App::run`
+  pub symbol: Option<String>,
+  pub start_line: Option<usize>,
+  pub end_line: Option<usize>,
+  /// the most bytes returned, a longer read is truncated
+  pub max_bytes: usize,
+  /// the inlay hints of the language server are written into the lines as comments
+  pub inlay_hints: bool,
+}
+
+/// the lines from `start_line` to `end_line` of the text, each prefixed by its number, under a
+/// header naming the file. a read over `MAX_READ_LINES` or `max_bytes` is cut off with a notice
+/// of the start_line to continue from
+pub fn read_lines(
+  name: &str,
+  text: &str,
+  start_line: Option<usize>,
+  end_line: Option<usize>,
+  max_bytes: usize,
+) -> Result<String, String> {
+  let lines = text.lines().collect::<Vec<_>>();
+  let total = lines.len();
+  let start = start_line.unwrap_or(1).max(1);
+  if start > total.max(1) {
+    return Err(format!(
+      "start_line {} is past the end of {}, which has {} lines",
+      start, name, total
+    ));
+  }
+  if end_line.is_some_and(|end| end < start) {
+    return Err("end_line is before start_line".to_string());
+  }
+  let end = end_line.unwrap_or(total).min(total);
+
+  let width = end.to_string().len();
+  let mut numbered = String::new();
+  let mut last = start - 1;
+  for (number, line) in (start..=end).zip(&lines[start - 1..end]) {
+    let line = format!("{:>width$} | {}\n", number, line, width = width);
+    let full = number - start >= MAX_READ_LINES || numbered.len() + line.len() > max_bytes;
+    // a line longer than max_bytes is still read, so a read always moves forward
+    if full && last >= start {
+      break;
+    }
+    numbered.push_str(&line);
+    last = number;
+  }
+
+  let mut read = format!("{} (lines {}-{} of {})\n{}", name, start, last, total, numbered);
+  if last < end {
+    read.push_str(&format!(
+      "[truncated after line {}, read on with start_line={}]\n",
+      last,
+      last + 1
+    ));
+  }
+  Ok(read)
+}
+
+/// the symbol with the qualified name, such as `App::run` or `App.run`. each name before the
+/// last is a symbol the one after it is declared in, where a name also matches the last word of
+/// a symbol, so `App` matches the `impl App` rust-analyzer reports
+pub fn find_symbol(
+  symbols: impl IntoIterator<Item = Arc<SourceSymbol>>,
+  qualified_name: &str,
+  file_path: Option<&Path>,
+) -> Result<Arc<SourceSymbol>, String> {
+  let path = qualified_name
+    .split("::")
+    .flat_map(|name| name.split('.'))
+    .filter(|name| !name.is_empty())
+    .collect::<Vec<_>>();
+  let Some((name, parents)) = path.split_last() else {
+    return Err("the symbol name is empty".to_string());
+  };
+
+  let mut matches = symbols
+    .into_iter()
+    .filter(|symbol| symbol.name == *name)
+    .filter(|symbol| file_path.map_or(true, |file_path| symbol.file_path == file_path))
+    .filter(|symbol| declared_in(symbol, parents))
+    .collect::<Vec<_>>();
+  match matches.len() {
+    0 => {
+      Err(format!("no symbol named {} was found, use lsp_query to search for it", qualified_name))
+    },
+    1 => Ok(matches.remove(0)),
+    count => {
+      let candidates = matches
+        .iter()
+        .map(|symbol| {
+          let line = symbol.range.lock().unwrap().start.line + 1;
+          format!("{} ({}:{})", qualified(symbol), symbol.file_path.display(), line)
+        })
+        .collect::<Vec<_>>();
+      Err(format!(
+        "{} symbols are named {}, qualify the name with the symbols it is declared in or give \
+         the file_path: {}",
+        count,
+        qualified_name,
+        candidates.join(", ")
+      ))
+    },
+  }
+}
+
+fn names(symbol: &SourceSymbol, name: &str) -> bool {
+  symbol.name == name || symbol.name.split_whitespace().last() == Some(name)
+}
+
+// whether the symbol is declared in the symbols of `parents`, outermost first
+fn declared_in(symbol: &SourceSymbol, parents: &[&str]) -> bool {
+  let mut parent = symbol.parent.lock().unwrap().upgrade();
+  for name in parents.iter().rev() {
+    match parent {
+      Some(symbol) if names(&symbol, name) => parent = symbol.parent.lock().unwrap().upgrade(),
+      _ => return false,
+    }
+  }
+  true
+}
+
+// the name of the symbol qualified by the symbols it is declared in
+fn qualified(symbol: &SourceSymbol) -> String {
+  let mut names = vec![symbol.name.clone()];
+  let mut parent = symbol.parent.lock().unwrap().upgrade();
+  while let Some(symbol) = parent {
+    names.push(symbol.name.clone());
+    parent = symbol.parent.lock().unwrap().upgrade();
+  }
+  names.reverse();
+  names.join("::")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_read_lines() {
+    let text = (1..=12).map(|n| format!("line {}", n)).collect::<Vec<_>>().join("\n");
+    assert_eq!(
+      read_lines("a.txt", &text, Some(9), Some(10), 1024).unwrap(),
+      "a.txt (lines 9-10 of 12)\n 9 | line 9\n10 | line 10\n"
+    );
+    let truncated = read_lines("a.txt", &text, None, None, 40).unwrap();
+    assert!(truncated.starts_with("a.txt (lines 1-3 of 12)\n 1 | line 1\n"));
+    assert!(truncated.ends_with("[truncated after line 3, read on with start_line=4]\n"));
+    assert!(read_lines("a.txt", &text, Some(13), None, 1024).is_err());
+    assert!(read_lines("a.txt", &text, Some(5), Some(4), 1024).is_err());
+  }
+
+  #[test]
+  fn test_find_symbol() {
+    let symbol = |name: &str, file: &str| {
+      Arc::new(SourceSymbol {
+        name: name.to_string(),
+        file_path: PathBuf::from(file),
+        ..Default::default()
+      })
+    };
+    let app = symbol("impl App", "/work/src/app.rs");
+    let app_run = symbol("run", "/work/src/app.rs");
+    *app_run.parent.lock().unwrap() = Arc::downgrade(&app);
+    let symbols = vec![app, app_run.clone(), symbol("run", "/work/src/main.rs")];
+
+    assert!(Arc::ptr_eq(&find_symbol(symbols.clone(), "App::run", None).unwrap(), &app_run));
+    let in_main = find_symbol(symbols.clone(), "run", Some(Path::new("/work/src/main.rs")));
+    assert_eq!(in_main.unwrap().file_path, PathBuf::from("/work/src/main.rs"));
+    let ambiguous = find_symbol(symbols.clone(), "run", None).unwrap_err();
+    assert!(ambiguous.contains("impl App::run (/work/src/app.rs:1)"));
+    assert!(find_symbol(symbols, "Other.run", None).is_err());
+  }
+}
@@ -0,0 +1,345 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Parser};
+
+/// files smaller than this are cheap enough to read in full, summarize_file returns their text
+pub const SUMMARY_THRESHOLD_BYTES: u64 = 16 * 1024;
+
+const MAX_IMPORTS: usize = 100;
+const MAX_SYMBOLS: usize = 200;
+// signatures spanning more lines than this are cut off
+const MAX_SIGNATURE_LINES: usize = 4;
+
+const DOCUMENT_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "rst", "txt", "adoc", "org"];
+
+const IMPORT_PREFIXES: &[&str] =
+  &["use ", "pub use ", "import ", "from ", "#include", "extern crate ", "require "];
+
+// line prefixes, at the start of a line, that begin a declaration in common languages
+const DECLARATION_KEYWORDS: &[&str] = &[
+  "export default function",
+  "export function",
+  "export class",
+  "export interface",
+  "export type",
+  "export const",
+  "async function",
+  "async def",
+  "function",
+  "class",
+  "interface",
+  "type",
+  "def",
+  "func",
+  "module",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolSummary {
+  pub kind: String,
+  pub name: String,
+  pub signature: String,
+  /// 1 based line number of the symbol name
+  pub line: usize,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub children: Vec<SymbolSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeadingSummary {
+  pub level: usize,
+  pub title: String,
+  pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileSummary {
+  pub file_path: PathBuf,
+  pub line_count: usize,
+  pub byte_count: usize,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub imports: Vec<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub symbols: Vec<SymbolSummary>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub headings: Vec<HeadingSummary>,
+  /// how the symbols were found, language server, tree-sitter or declaration keywords
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub symbol_source: Option<String>,
+}
+
+pub fn is_document(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| DOCUMENT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// summarizes the structure of a file. `symbols` are the symbols reported by a language
+/// server, when they are not available symbols are found with tree-sitter for rust, and by
+/// matching declaration keywords for other languages
+pub fn summarize(path: &Path, text: &str, symbols: Option<Vec<SymbolSummary>>) -> FileSummary {
+  let mut summary = FileSummary {
+    file_path: path.to_path_buf(),
+    line_count: text.lines().count(),
+    byte_count: text.len(),
+    ..Default::default()
+  };
+  if is_document(path) {
+    summary.headings = headings(text);
+    return summary;
+  }
+
+  summary.imports = imports(text);
+  let (symbols, source) = match symbols {
+    Some(symbols) if !symbols.is_empty() => (symbols, "language server"),
+    _ => match path.extension().and_then(|ext| ext.to_str()) {
+      Some("rs") => (rust_symbols(text), "tree-sitter"),
+      _ => (declaration_symbols(text), "declaration keywords"),
+    },
+  };
+  summary.symbols =
+    symbols.into_iter().filter(|s| is_public(path, &s.signature)).take(MAX_SYMBOLS).collect();
+  summary.symbol_source = Some(source.to_string());
+  summary
+}
+
+/// the declaration starting at the 0 based `line`, without its body
+pub fn signature_at(text: &str, line: usize) -> String {
+  let mut signature = String::new();
+  for l in text.lines().skip(line).take(MAX_SIGNATURE_LINES) {
+    let l = l.trim();
+    if !signature.is_empty() {
+      signature.push(' ');
+    }
+    match l.find('{') {
+      Some(i) => {
+        signature.push_str(l[..i].trim_end());
+        break;
+      },
+      None => signature.push_str(l),
+    }
+    if l.ends_with(';') || l.ends_with(':') {
+      break;
+    }
+  }
+  signature.trim_end_matches(';').trim_end().to_string()
+}
+
+// private items are left out where the language marks them, in rust by a missing pub, in
+// python by a leading underscore
+fn is_public(path: &Path, signature: &str) -> bool {
+  match path.extension().and_then(|ext| ext.to_str()) {
+    Some("rs") => {
+      let signature = signature.trim_start_matches("unsafe ");
+      signature.starts_with("pub") || signature.starts_with("impl")
+    },
+    Some("py") => signature
+      .split_whitespace()
+      .find(|word| !matches!(*word, "def" | "class" | "async"))
+      .is_some_and(|name| !name.starts_with('_')),
+    _ => true,
+  }
+}
+
+fn imports(text: &str) -> Vec<String> {
+  let mut imports = vec![];
+  let mut lines = text.lines();
+  while let Some(line) = lines.next() {
+    if imports.len() == MAX_IMPORTS {
+      break;
+    }
+    let is_import =
+      IMPORT_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) || line.contains("= require(");
+    if !is_import {
+      continue;
+    }
+    // join the lines of a multi line import list
+    let mut import = line.trim().to_string();
+    let closing = if import.contains('{') { Some('}') } else { None };
+    let closing = closing.or(if import.ends_with('(') { Some(')') } else { None });
+    if let Some(closing) = closing {
+      while !import.contains(closing) {
+        match lines.next() {
+          Some(line) => {
+            import.push(' ');
+            import.push_str(line.trim());
+          },
+          None => break,
+        }
+      }
+    }
+    imports.push(import);
+  }
+  imports
+}
+
+fn headings(text: &str) -> Vec<HeadingSummary> {
+  let mut headings = vec![];
+  let mut in_code_block = false;
+  let lines = text.lines().collect::<Vec<_>>();
+  for (i, line) in lines.iter().enumerate() {
+    if line.starts_with("```") || line.starts_with("~~~") {
+      in_code_block = !in_code_block;
+      continue;
+    }
+    if in_code_block {
+      continue;
+    }
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+      let title = line[level..].trim().trim_end_matches('#').trim().to_string();
+      headings.push(HeadingSummary { level, title, line: i + 1 });
+      continue;
+    }
+    // setext style headings, a line of text underlined with = or -
+    let underline = lines.get(i + 1).map(|l| l.trim_end()).unwrap_or_default();
+    let level = match underline.chars().next() {
+      Some('=') => 1,
+      Some('-') => 2,
+      _ => continue,
+    };
+    let is_underline = underline.len() >= 3 && underline.chars().all(|c| c == '=' || c == '-');
+    if !line.trim().is_empty() && is_underline {
+      headings.push(HeadingSummary { level, title: line.trim().to_string(), line: i + 1 });
+    }
+  }
+  headings
+}
+
+fn rust_symbols(text: &str) -> Vec<SymbolSummary> {
+  let mut parser = Parser::new();
+  if parser.set_language(tree_sitter_rust::language()).is_err() {
+    return vec![];
+  }
+  let tree = match parser.parse(text, None) {
+    Some(tree) => tree,
+    None => return vec![],
+  };
+  let root = tree.root_node();
+  let mut cursor = root.walk();
+  let symbols =
+    root.named_children(&mut cursor).filter_map(|node| rust_symbol(node, text)).collect();
+  symbols
+}
+
+fn rust_symbol(node: Node, text: &str) -> Option<SymbolSummary> {
+  let kind = match node.kind() {
+    "function_item" | "function_signature_item" => "function",
+    "struct_item" => "struct",
+    "enum_item" => "enum",
+    "union_item" => "union",
+    "trait_item" => "trait",
+    "impl_item" => "impl",
+    "mod_item" => "module",
+    "const_item" => "constant",
+    "static_item" => "static",
+    "type_item" => "type",
+    "macro_definition" => "macro",
+    _ => return None,
+  };
+  let name_node = match node.kind() {
+    "impl_item" => node.child_by_field_name("type"),
+    _ => node.child_by_field_name("name"),
+  };
+  let name =
+    name_node.and_then(|n| n.utf8_text(text.as_bytes()).ok()).unwrap_or_default().to_string();
+  let line = name_node.unwrap_or(node).start_position().row;
+
+  let children = match (kind, node.child_by_field_name("body")) {
+    ("impl" | "trait", Some(body)) => {
+      let is_trait = kind == "trait" || node.child_by_field_name("trait").is_some();
+      let mut cursor = body.walk();
+      let children = body
+        .named_children(&mut cursor)
+        .filter_map(|child| rust_symbol(child, text))
+        .filter(|child| is_trait || child.signature.starts_with("pub"))
+        .collect();
+      children
+    },
+    _ => vec![],
+  };
+  Some(SymbolSummary {
+    kind: kind.to_string(),
+    name,
+    signature: signature_at(text, node.start_position().row),
+    line: line + 1,
+    children,
+  })
+}
+
+fn declaration_symbols(text: &str) -> Vec<SymbolSummary> {
+  text
+    .lines()
+    .enumerate()
+    .filter(|(_, line)| !line.starts_with(char::is_whitespace))
+    .filter_map(|(i, line)| {
+      let keyword = DECLARATION_KEYWORDS
+        .iter()
+        .find(|keyword| line.strip_prefix(**keyword).is_some_and(|rest| rest.starts_with(' ')))?;
+      let name = line[keyword.len()..]
+        .trim_start()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .next()
+        .unwrap_or_default()
+        .to_string();
+      Some(SymbolSummary {
+        kind: keyword.rsplit(' ').next().unwrap_or(keyword).to_string(),
+        name,
+        signature: signature_at(text, i),
+        line: i + 1,
+        children: vec![],
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_summarize_rust_source() {
+    let text = "use std::collections::{\n  HashMap,\n  HashSet,\n};\nuse serde::Serialize;\n\n/// a widget\n#[derive(Debug)]\npub struct Widget {\n  size: usize,\n}\n\nfn helper() {}\n\nimpl Widget {\n  pub fn new(\n    size: usize,\n  ) -> Self {\n    Widget { size }\n  }\n\n  fn private(&self) {}\n}\n";
+    let summary = summarize(Path::new("src/widget.rs"), text, None);
+    assert_eq!(
+      summary.imports,
+      vec!["use std::collections::{ HashMap, HashSet, };", "use serde::Serialize;"]
+    );
+    assert_eq!(summary.symbol_source.as_deref(), Some("tree-sitter"));
+    let names = summary.symbols.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Widget", "Widget"]);
+    assert_eq!(summary.symbols[0].signature, "pub struct Widget");
+    assert_eq!(summary.symbols[0].line, 9);
+    let methods = &summary.symbols[1].children;
+    assert_eq!(methods.len(), 1);
+    assert_eq!(methods[0].signature, "pub fn new( size: usize, ) -> Self");
+  }
+
+  #[test]
+  fn test_summarize_document_headings() {
+    let text =
+      "Title\n=====\n\nintro\n\n## Install\n\n```sh\n# not a heading\n```\n\n### From source ###\n";
+    let summary = summarize(Path::new("README.md"), text, None);
+    assert_eq!(
+      summary.headings,
+      vec![
+        HeadingSummary { level: 1, title: "Title".to_string(), line: 1 },
+        HeadingSummary { level: 2, title: "Install".to_string(), line: 6 },
+        HeadingSummary { level: 3, title: "From source".to_string(), line: 12 },
+      ]
+    );
+    assert!(summary.symbols.is_empty());
+  }
+
+  #[test]
+  fn test_summarize_by_declaration_keywords() {
+    let text = "import os\nfrom typing import (\n  List,\n)\n\nclass Loader:\n  def load(self):\n    pass\n\ndef _private():\n  pass\n\ndef main(args):\n  pass\n";
+    let summary = summarize(Path::new("loader.py"), text, None);
+    assert_eq!(summary.imports, vec!["import os", "from typing import ( List, )"]);
+    let names = summary.symbols.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Loader", "main"]);
+    assert_eq!(summary.symbols[1].signature, "def main(args):");
+  }
+}
@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use super::{
+  errors::SazidError,
+  messages::{get_chat_message_text, ChatMessage},
+  model_tools::tool_call::ChatTools,
+  prompt_profiles::DEFAULT_PROMPT_PROFILE,
+  session_config::SessionConfig,
+  session_store::SessionStore,
+};
+use crate::action::{ChatToolAction, LsiAction, SessionAction, ToolType};
+use crate::components::session::Session;
+
+/// what a headless session reports while it answers a message
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadlessEvent {
+  /// text of the answer as it streams in
+  Token(String),
+  /// a tool the model called, with its json arguments
+  ToolCall { id: String, name: String, arguments: String },
+  /// what a tool call returned to the model
+  ToolResult { id: String, content: String },
+  /// the answer is complete, with its text
+  Done(String),
+}
+
+/// a session and its tools driven by the same actions as in the terminal interface, without
+/// one. tools that query the language server answer with an error, none is started
+pub struct HeadlessSession {
+  pub session: Session,
+  session_tx: UnboundedSender<SessionAction>,
+  session_rx: UnboundedReceiver<SessionAction>,
+  chat_tools: ChatTools,
+  tool_rx: UnboundedReceiver<ChatToolAction>,
+  store: Arc<Mutex<SessionStore>>,
+  events: VecDeque<HeadlessEvent>,
+  answering: bool,
+}
+
+impl HeadlessSession {
+  /// a new session with the system prompt of its configured prompt profile
+  pub fn new(config: SessionConfig, store: Arc<Mutex<SessionStore>>) -> Result<Self, SazidError> {
+    let mut headless = Self::with_config(config, store);
+    let prompt_profile =
+      headless.session.config.prompt_profile.clone().unwrap_or(DEFAULT_PROMPT_PROFILE.to_string());
+    headless.session.select_prompt_profile(&prompt_profile)?;
+    Ok(headless)
+  }
+
+  /// a saved session, with the config it was saved with
+  pub fn load(
+    config: SessionConfig,
+    store: Arc<Mutex<SessionStore>>,
+    session_id: &str,
+  ) -> Result<Self, SazidError> {
+    let mut headless = Self::with_config(config, store);
+    headless.session.load_session(&headless.store.lock().unwrap(), session_id, None)?;
+    headless.chat_tools.upsert_configs(headless.session.id, headless.session.config.clone());
+    Ok(headless)
+  }
+
+  fn with_config(config: SessionConfig, store: Arc<Mutex<SessionStore>>) -> Self {
+    let (session_tx, session_rx) = mpsc::unbounded_channel();
+    let session = Session::new(session_tx.clone(), Some(config));
+    let (tool_tx, tool_rx) = mpsc::unbounded_channel();
+    let chat_tools = ChatTools::new(tool_tx, session.id, session.config.clone());
+    HeadlessSession {
+      session,
+      session_tx,
+      session_rx,
+      chat_tools,
+      tool_rx,
+      store,
+      events: VecDeque::new(),
+      answering: false,
+    }
+  }
+
+  /// the id the session is saved and listed with
+  pub fn id(&self) -> &str {
+    &self.session.config.id
+  }
+
+  /// sends a user message, the answer is read with `next_event` until it is done
+  pub fn send(&mut self, text: &str) {
+    self.events.clear();
+    self.answering = true;
+    self.session.submit_chat_completion_request(text.to_string());
+  }
+
+  /// stops waiting for the answer to the last message sent. a response still arriving is added
+  /// to the session, but no more events are reported for it
+  pub fn cancel(&mut self) {
+    self.answering = false;
+    self.events.clear();
+  }
+
+  /// the next event of the answer to the last message sent
+  pub async fn next_event(&mut self) -> Result<HeadlessEvent, SazidError> {
+    loop {
+      if let Some(event) = self.events.pop_front() {
+        return Ok(event);
+      }
+      if !self.answering {
+        return Err(SazidError::Other("no message is being answered".to_string()));
+      }
+      tokio::select! {
+        Some(action) = self.session_rx.recv() => self.handle_session_action(action)?,
+        Some(action) = self.tool_rx.recv() => self.handle_tool_action(action),
+        else => return Err(SazidError::Other("the session stopped".to_string())),
+      }
+      if self.answering && self.session.turn_complete() {
+        self.answering = false;
+        self.save();
+        let answer = self.session.messages.last().map(|m| get_chat_message_text(&m.message));
+        self.events.push_back(HeadlessEvent::Done(answer.unwrap_or_default()));
+      }
+    }
+  }
+
+  fn handle_session_action(&mut self, action: SessionAction) -> Result<(), SazidError> {
+    match action {
+      SessionAction::SaveSession => self.save(),
+      SessionAction::ChatToolAction(action) => self.chat_tools.tx.send(action).unwrap(),
+      SessionAction::LsiAction(action) => self.answer_without_language_server(action),
+      SessionAction::Error(error) if self.answering => {
+        self.answering = false;
+        return Err(SazidError::Other(error));
+      },
+      SessionAction::Error(error) => log::error!("headless session error: {}", error),
+      // only the terminal interface shows these
+      SessionAction::UpdateStatus(_)
+      | SessionAction::UpdateMessage(_, _)
+      | SessionAction::ReloadMessages(_)
+      | SessionAction::IndexProgress(_, _, _)
+      | SessionAction::FileEditReverted(_) => {},
+      action => {
+        match &action {
+          SessionAction::AddMessage(_, ChatMessage::Tool(message)) => {
+            self.events.push_back(HeadlessEvent::ToolResult {
+              id: message.tool_call_id.clone(),
+              content: message.content.clone(),
+            })
+          },
+          SessionAction::AddMessage(_, message) => {
+            self.events.extend(answer_text(message).map(HeadlessEvent::Token))
+          },
+          SessionAction::ToolCallComplete(ToolType::LsiQuery(query), content) => {
+            self.events.push_back(HeadlessEvent::ToolResult {
+              id: query.tool_call_id.clone(),
+              content: content.clone(),
+            })
+          },
+          SessionAction::ToolCallComplete(ToolType::Generic(_, id), content) => self
+            .events
+            .push_back(HeadlessEvent::ToolResult { id: id.clone(), content: content.clone() }),
+          _ => {},
+        }
+        match self.session.update(action) {
+          Ok(Some(action)) => self.session_tx.send(action).unwrap(),
+          Ok(None) => {},
+          Err(err) => log::debug!("session update error: {:#?}", err),
+        }
+      },
+    }
+    Ok(())
+  }
+
+  fn handle_tool_action(&mut self, action: ChatToolAction) {
+    match action {
+      ChatToolAction::SessionAction(action) => self.session_tx.send(*action).unwrap(),
+      ChatToolAction::LsiRequest(action) => self.answer_without_language_server(*action),
+      // nobody is there to allow the call
+      ChatToolAction::ConfirmToolCall(tool_call, session_id) => {
+        self.handle_tool_action(ChatToolAction::ToolCallConfirmed(tool_call, session_id, false))
+      },
+      action => {
+        if let ChatToolAction::CallTool(tool_call, _) = &action {
+          self.events.push_back(HeadlessEvent::ToolCall {
+            id: tool_call.id.clone(),
+            name: tool_call.function.name.clone(),
+            arguments: tool_call.function.arguments.clone(),
+          });
+        }
+        match self.chat_tools.handle_action(action) {
+          Ok(Some(action)) => self.chat_tools.tx.send(action).unwrap(),
+          Ok(None) => {},
+          Err(e) => self.chat_tools.tx.send(ChatToolAction::Error(e.to_string())).unwrap(),
+        }
+      },
+    }
+  }
+
+  // completes the tool calls meant for the language server interface with an error
+  fn answer_without_language_server(&mut self, action: LsiAction) {
+    let query = match action {
+      LsiAction::SessionAction(action) => {
+        self.session_tx.send(*action).unwrap();
+        return;
+      },
+      LsiAction::ChatToolResponse(action) => {
+        self.chat_tools.tx.send(*action).unwrap();
+        return;
+      },
+      LsiAction::QueryWorkspaceSymbols(query)
+      | LsiAction::QueryAllWorkspaceSymbols(query)
+      | LsiAction::GetWorkspaceFiles(query)
+      | LsiAction::ReplaceSymbolText(_, _, _, query)
+      | LsiAction::ReadSymbolSource(query)
+      | LsiAction::GoToSymbolDefinition(query)
+      | LsiAction::GoToSymbolDeclaration(query)
+      | LsiAction::GoToTypeDefinition(query)
+      | LsiAction::GoToImplementation(query)
+      | LsiAction::DocumentHighlight(query)
+      | LsiAction::GetDiagnostics(query)
+      | LsiAction::SignatureHelp(_, _, query)
+      | LsiAction::InlayHints(_, query)
+      | LsiAction::ReadFile(_, query)
+      | LsiAction::SummarizeFile(_, query) => query,
+      _ => return,
+    };
+    self
+      .session_tx
+      .send(SessionAction::ToolCallComplete(
+        ToolType::LsiQuery(query),
+        "Tool Call Error: this tool needs a language server, which is only started by the terminal interface".to_string(),
+      ))
+      .unwrap();
+  }
+
+  fn save(&mut self) {
+    if let Err(e) = self.session.save_session(&mut self.store.lock().unwrap()) {
+      log::error!("error saving session: {}", e);
+    }
+  }
+}
+
+// the text a response adds to the answer
+fn answer_text(message: &ChatMessage) -> Option<String> {
+  let text = match message {
+    ChatMessage::StreamResponse(responses) => responses
+      .iter()
+      .flat_map(|response| response.choices.iter().filter(|choice| choice.index == 0))
+      .filter_map(|choice| choice.delta.content.clone())
+      .collect::<String>(),
+    ChatMessage::Response(response) => {
+      response.choices.first().and_then(|choice| choice.message.content.clone()).unwrap_or_default()
+    },
+    _ => String::new(),
+  };
+  (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::lsi::query::LsiQuery;
+
+  #[test]
+  fn test_language_server_tools_answer_with_an_error() {
+    let store = Arc::new(Mutex::new(SessionStore::open_in_memory().unwrap()));
+    let mut headless = HeadlessSession::new(SessionConfig::default(), store).unwrap();
+    let query = LsiQuery {
+      session_id: headless.session.id,
+      tool_call_id: "call_1".to_string(),
+      ..LsiQuery::default()
+    };
+    headless.answer_without_language_server(LsiAction::GetDiagnostics(query.clone()));
+    let mut answer = None;
+    while let Ok(action) = headless.session_rx.try_recv() {
+      if let SessionAction::ToolCallComplete(ToolType::LsiQuery(answered), content) = action {
+        answer = Some((answered, content));
+      }
+    }
+    let (answered, content) = answer.unwrap();
+    assert_eq!(answered, query);
+    assert!(content.starts_with("Tool Call Error"));
+  }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// the usual bm25 parameters, k1 limits how much repeating a term counts and b how much long
+// documents are penalized
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// how keyword and vector search results are combined. each ranking adds weight / (rrf_k + rank)
+/// to the score of a result, so results found by both rank highest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HybridSearchConfig {
+  pub vector_weight: f32,
+  /// 0 leaves keyword search out
+  pub keyword_weight: f32,
+  /// larger values make the lower ranks count for more against the first ones
+  pub rrf_k: f32,
+}
+
+impl Default for HybridSearchConfig {
+  fn default() -> Self {
+    HybridSearchConfig { vector_weight: 1.0, keyword_weight: 1.0, rrf_k: 60.0 }
+  }
+}
+
+/// lowercased words and identifiers, so `retry_backoff` is matched whole
+pub fn tokenize(text: &str) -> Vec<String> {
+  text
+    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+    .filter(|token| !token.is_empty())
+    .map(|token| token.to_lowercase())
+    .collect()
+}
+
+/// the bm25 score of each document for the query, 0 for documents without any of its terms
+pub fn bm25_scores(query: &str, documents: &[&str]) -> Vec<f32> {
+  let documents = documents.iter().map(|document| tokenize(document)).collect::<Vec<_>>();
+  let average_len =
+    documents.iter().map(|terms| terms.len()).sum::<usize>() as f32 / documents.len().max(1) as f32;
+  let mut query_terms = tokenize(query);
+  query_terms.sort();
+  query_terms.dedup();
+
+  let idf = query_terms
+    .iter()
+    .map(|term| {
+      let containing = documents.iter().filter(|terms| terms.contains(term)).count() as f32;
+      let total = documents.len() as f32;
+      ((total - containing + 0.5) / (containing + 0.5) + 1.0).ln()
+    })
+    .collect::<Vec<_>>();
+
+  documents
+    .iter()
+    .map(|terms| {
+      let len_norm = 1.0 - BM25_B + BM25_B * terms.len() as f32 / average_len.max(1.0);
+      query_terms
+        .iter()
+        .zip(&idf)
+        .map(|(term, idf)| {
+          let frequency = terms.iter().filter(|t| *t == term).count() as f32;
+          idf * frequency * (BM25_K1 + 1.0) / (frequency + BM25_K1 * len_norm)
+        })
+        .sum()
+    })
+    .collect()
+}
+
+/// merges rankings, best first, into one ordered by reciprocal rank fusion score
+pub fn reciprocal_rank_fusion<K: Clone + Eq + Hash>(
+  vector_ranking: &[K],
+  keyword_ranking: &[K],
+  config: &HybridSearchConfig,
+) -> Vec<K> {
+  let mut scores: HashMap<K, f32> = HashMap::new();
+  let mut order = vec![];
+  for (ranking, weight) in
+    [(vector_ranking, config.vector_weight), (keyword_ranking, config.keyword_weight)]
+  {
+    for (rank, key) in ranking.iter().enumerate() {
+      let score = scores.entry(key.clone()).or_insert_with(|| {
+        order.push(key.clone());
+        0.0
+      });
+      *score += weight / (config.rrf_k + rank as f32 + 1.0);
+    }
+  }
+  // the sort is stable, ties keep the vector ranking's order
+  order.sort_by(|a, b| scores[b].total_cmp(&scores[a]));
+  order
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hybrid_ranking() {
+    let documents = [
+      "fn retry_backoff(attempt: u32) -> Duration",
+      "the backoff grows with every retry",
+      "fn parse_config() {}",
+    ];
+    let scores = bm25_scores("retry_backoff", &documents);
+    assert!(scores[0] > 0.0);
+    assert_eq!(scores[1..], [0.0, 0.0]);
+    let scores = bm25_scores("retry backoff", &documents);
+    assert!(scores[1] > scores[0] && scores[2] == 0.0);
+
+    let config = HybridSearchConfig::default();
+    let fused = reciprocal_rank_fusion(&["a", "b", "c"], &["c", "d"], &config);
+    assert_eq!(fused, vec!["c", "a", "b", "d"]);
+    let vector_only = HybridSearchConfig { keyword_weight: 0.0, ..config };
+    assert_eq!(reciprocal_rank_fusion(&["a", "b"], &["b"], &vector_only), vec!["a", "b"]);
+  }
+}
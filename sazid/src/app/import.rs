@@ -0,0 +1,162 @@
+use std::{fs, path::Path};
+
+use async_openai::types::{
+  ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+  ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+  ChatCompletionRequestUserMessageContent, Role,
+};
+use serde_json::Value;
+
+use super::errors::SazidError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedConversation {
+  pub title: String,
+  pub messages: Vec<ChatCompletionRequestMessage>,
+}
+
+/// reads a chatgpt data export (`conversations.json`, or a single conversation from it) or a
+/// jsonl file of `{"role": .., "content": ..}` lines
+pub fn import_conversations(path: &Path) -> Result<Vec<ImportedConversation>, SazidError> {
+  let contents = fs::read_to_string(path)?;
+  let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+  match serde_json::from_str::<Value>(&contents) {
+    Ok(Value::Array(conversations)) => conversations.iter().map(chatgpt_conversation).collect(),
+    Ok(conversation) if conversation.get("mapping").is_some() => {
+      Ok(vec![chatgpt_conversation(&conversation)?])
+    },
+    _ => Ok(vec![ImportedConversation { title, messages: jsonl_messages(&contents)? }]),
+  }
+}
+
+/// chatgpt stores a conversation as a tree of message nodes, since edited prompts and
+/// regenerated responses create branches. the branch that was last shown is recovered by
+/// walking from `current_node` back to the root
+fn chatgpt_conversation(conversation: &Value) -> Result<ImportedConversation, SazidError> {
+  let title = conversation["title"].as_str().unwrap_or("imported conversation").to_string();
+  let mapping = conversation["mapping"]
+    .as_object()
+    .ok_or_else(|| SazidError::Other(format!("conversation {} has no message mapping", title)))?;
+
+  let mut node_id = conversation["current_node"].as_str();
+  let mut branch = Vec::new();
+  while let Some(node) = node_id.and_then(|id| mapping.get(id)) {
+    branch.push(node);
+    node_id = node["parent"].as_str();
+  }
+
+  let messages = branch
+    .iter()
+    .rev()
+    .filter_map(|node| {
+      let message = &node["message"];
+      let role = message["author"]["role"].as_str()?;
+      let content = message["content"]["parts"]
+        .as_array()?
+        .iter()
+        .filter_map(|part| part.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+      chat_message(role, content)
+    })
+    .collect();
+  Ok(ImportedConversation { title, messages })
+}
+
+fn jsonl_messages(contents: &str) -> Result<Vec<ChatCompletionRequestMessage>, SazidError> {
+  let mut messages = Vec::new();
+  for (line_number, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+    let value = serde_json::from_str::<Value>(line)
+      .map_err(|e| SazidError::Other(format!("line {}: {}", line_number + 1, e)))?;
+    let role = value["role"]
+      .as_str()
+      .ok_or_else(|| SazidError::Other(format!("line {}: missing role", line_number + 1)))?;
+    let content = match &value["content"] {
+      Value::String(content) => content.clone(),
+      Value::Null => String::new(),
+      content => content.to_string(),
+    };
+    messages.extend(chat_message(role, content));
+  }
+  Ok(messages)
+}
+
+// tool messages are dropped, the api rejects tool results without a matching tool call
+fn chat_message(role: &str, content: String) -> Option<ChatCompletionRequestMessage> {
+  if content.trim().is_empty() {
+    return None;
+  }
+  match role {
+    "system" => Some(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content,
+      role: Role::System,
+      name: None,
+    })),
+    "user" => Some(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+      content: ChatCompletionRequestUserMessageContent::Text(content),
+      role: Role::User,
+      name: None,
+    })),
+    "assistant" => {
+      Some(ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+        content: Some(content),
+        role: Role::Assistant,
+        name: None,
+        tool_calls: None,
+        function_call: None,
+      }))
+    },
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::messages::get_chat_message_text;
+  use tempfile::tempdir;
+
+  #[test]
+  fn test_import_chatgpt_export_follows_current_branch() {
+    let export = r#"[{
+      "title": "rust question",
+      "current_node": "c",
+      "mapping": {
+        "root": { "id": "root", "message": null, "parent": null, "children": ["a"] },
+        "a": { "id": "a", "parent": "root", "children": ["b", "x"],
+          "message": { "author": { "role": "user" }, "content": { "content_type": "text", "parts": ["what is a trait?"] } } },
+        "x": { "id": "x", "parent": "a", "children": [],
+          "message": { "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["discarded answer"] } } },
+        "b": { "id": "b", "parent": "a", "children": ["c"],
+          "message": { "author": { "role": "tool" }, "content": { "content_type": "text", "parts": ["search results"] } } },
+        "c": { "id": "c", "parent": "b", "children": [],
+          "message": { "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["a trait is an interface"] } } }
+      }
+    }]"#;
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("conversations.json");
+    fs::write(&path, export).unwrap();
+    let conversations = import_conversations(&path).unwrap();
+
+    assert_eq!(conversations.len(), 1);
+    assert_eq!(conversations[0].title, "rust question");
+    let texts = conversations[0].messages.iter().map(get_chat_message_text).collect::<Vec<_>>();
+    assert_eq!(texts, vec!["what is a trait?", "a trait is an interface"]);
+    assert!(matches!(conversations[0].messages[1], ChatCompletionRequestMessage::Assistant(_)));
+  }
+
+  #[test]
+  fn test_import_jsonl() {
+    let jsonl = "{\"role\": \"system\", \"content\": \"be brief\"}\n\n{\"role\": \"user\", \"content\": \"hello\"}\n{\"role\": \"assistant\", \"content\": \"hi\"}\n";
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("chat.jsonl");
+    fs::write(&path, jsonl).unwrap();
+    let conversations = import_conversations(&path).unwrap();
+
+    assert_eq!(conversations.len(), 1);
+    assert_eq!(conversations[0].title, "chat");
+    assert_eq!(conversations[0].messages.len(), 3);
+    assert!(matches!(conversations[0].messages[0], ChatCompletionRequestMessage::System(_)));
+    assert_eq!(get_chat_message_text(&conversations[0].messages[1]), "hello");
+  }
+}
@@ -0,0 +1,152 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::errors::SazidError;
+
+/// the most inputs kept in the history file, older ones are dropped
+pub const MAX_INPUT_HISTORY: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+  pub session_id: String,
+  pub text: String,
+}
+
+/// the inputs sent to sessions, saved to a file as json lines. they are recalled newest first,
+/// those sent to the current session before those sent to others
+#[derive(Debug, Default)]
+pub struct InputHistory {
+  path: Option<PathBuf>,
+  // oldest first, without duplicates
+  entries: Vec<HistoryEntry>,
+  recall: Option<Recall>,
+}
+
+// the inputs being stepped through and the input that was in the box before recalling started
+#[derive(Debug)]
+struct Recall {
+  texts: Vec<String>,
+  position: usize,
+  draft: String,
+}
+
+impl InputHistory {
+  /// the history saved in `path`, empty if the file does not exist yet
+  pub fn load(path: &Path) -> Result<Self, SazidError> {
+    let entries = match std::fs::read_to_string(path) {
+      Ok(text) => text
+        .lines()
+        .filter_map(|line| match serde_json::from_str::<HistoryEntry>(line) {
+          Ok(entry) => Some(entry),
+          Err(e) => {
+            log::warn!("skipping input history line in {}: {}", path.display(), e);
+            None
+          },
+        })
+        .collect(),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+      Err(e) => return Err(e.into()),
+    };
+    Ok(InputHistory { path: Some(path.to_path_buf()), entries, recall: None })
+  }
+
+  pub fn entries(&self) -> &[HistoryEntry] {
+    &self.entries
+  }
+
+  /// adds an input sent to a session as the newest entry, replacing an earlier entry with the
+  /// same text, and saves the history
+  pub fn add(&mut self, session_id: &str, text: &str) -> Result<(), SazidError> {
+    self.recall = None;
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+      return Ok(());
+    }
+    self.entries.retain(|entry| entry.text != text);
+    self.entries.push(HistoryEntry { session_id: session_id.to_string(), text: text.to_string() });
+    let excess = self.entries.len().saturating_sub(MAX_INPUT_HISTORY);
+    self.entries.drain(..excess);
+    self.save()
+  }
+
+  fn save(&self) -> Result<(), SazidError> {
+    let Some(path) = &self.path else {
+      return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let mut text = String::new();
+    for entry in self.entries.iter() {
+      text.push_str(&serde_json::to_string(entry)?);
+      text.push('\n');
+    }
+    std::fs::write(path, text)?;
+    Ok(())
+  }
+
+  /// the input before the one recalled last, starting with the newest. `input` is what is in
+  /// the input box, it is given back once recalling steps past the newest input again
+  pub fn recall_previous(&mut self, session_id: &str, input: &str) -> Option<String> {
+    if self.entries.is_empty() {
+      return None;
+    }
+    let recall = self.recall.get_or_insert_with(|| {
+      let newest_first = self.entries.iter().rev();
+      let (mut texts, others): (Vec<_>, Vec<_>) =
+        newest_first.partition(|entry| entry.session_id == session_id);
+      texts.extend(others);
+      Recall {
+        texts: texts.into_iter().map(|entry| entry.text.clone()).collect(),
+        position: 0,
+        draft: input.to_string(),
+      }
+    });
+    let text = recall.texts.get(recall.position)?.clone();
+    recall.position += 1;
+    Some(text)
+  }
+
+  /// the input after the one recalled last, or the input that was in the box before recalling
+  /// started. none when nothing is being recalled
+  pub fn recall_next(&mut self) -> Option<String> {
+    let recall = self.recall.as_mut()?;
+    recall.position = recall.position.saturating_sub(1);
+    match recall.position {
+      0 => self.recall.take().map(|recall| recall.draft),
+      position => Some(recall.texts[position - 1].clone()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_input_history_recall() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("input_history.jsonl");
+    let mut history = InputHistory::load(&path).unwrap();
+    history.add("a", "first").unwrap();
+    history.add("b", "other session").unwrap();
+    history.add("a", "second\n").unwrap();
+    history.add("a", "first").unwrap();
+    history.add("a", "  ").unwrap();
+
+    let mut history = InputHistory::load(&path).unwrap();
+    let texts = history.entries().iter().map(|entry| entry.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, vec!["other session", "second", "first"]);
+
+    assert_eq!(history.recall_next(), None);
+    assert_eq!(history.recall_previous("a", "draft").as_deref(), Some("first"));
+    assert_eq!(history.recall_previous("a", "first").as_deref(), Some("second"));
+    assert_eq!(history.recall_previous("a", "second").as_deref(), Some("other session"));
+    assert_eq!(history.recall_previous("a", "other session"), None);
+    assert_eq!(history.recall_next().as_deref(), Some("second"));
+    assert_eq!(history.recall_next().as_deref(), Some("first"));
+    assert_eq!(history.recall_next().as_deref(), Some("draft"));
+    assert_eq!(history.recall_next(), None);
+  }
+}
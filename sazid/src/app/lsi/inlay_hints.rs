@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use helix_core::line_ending::line_end_char_index;
+use helix_core::syntax::LanguageConfiguration;
+use helix_core::Rope;
+use helix_lsp::util::lsp_pos_to_pos;
+use helix_lsp::{lsp, OffsetEncoding};
+use serde_json::json;
+
+/// how hints are written into source, as a block comment where the hint is shown or, in
+/// languages without block comments, as a line comment after the line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HintComment {
+  Block(String, String),
+  Line(String),
+}
+
+impl HintComment {
+  pub fn for_language(config: &LanguageConfiguration) -> Self {
+    if let Some(token) = config.block_comment_tokens.as_ref().and_then(|tokens| tokens.first()) {
+      return HintComment::Block(token.start.clone(), token.end.clone());
+    }
+    let token = config.comment_tokens.as_ref().and_then(|tokens| tokens.first());
+    HintComment::Line(token.cloned().unwrap_or_else(|| "//".to_string()))
+  }
+}
+
+pub fn hint_label(hint: &lsp::InlayHint) -> String {
+  match &hint.label {
+    lsp::InlayHintLabel::String(label) => label.clone(),
+    lsp::InlayHintLabel::LabelParts(parts) => {
+      parts.iter().map(|part| part.value.as_str()).collect()
+    },
+  }
+}
+
+/// the hints as a list of positions, kinds and labels
+pub fn describe_hints(mut hints: Vec<lsp::InlayHint>) -> serde_json::Value {
+  hints.sort_by_key(|hint| hint.position);
+  let hints = hints
+    .iter()
+    .map(|hint| {
+      // rust-analyzer does not set the kind of its hints
+      let kind = match hint.kind {
+        Some(lsp::InlayHintKind::TYPE) => "type",
+        Some(lsp::InlayHintKind::PARAMETER) => "parameter",
+        _ => "other",
+      };
+      json!({ "position": hint.position, "kind": kind, "label": hint_label(hint) })
+    })
+    .collect::<Vec<_>>();
+  json!(hints)
+}
+
+/// the text of the range with each hint in it written as a comment
+pub fn annotate_hints(
+  text: &Rope,
+  range: lsp::Range,
+  mut hints: Vec<lsp::InlayHint>,
+  offset_encoding: OffsetEncoding,
+  comment: &HintComment,
+) -> String {
+  hints.sort_by_key(|hint| hint.position);
+  let (Some(start), Some(end)) = (
+    lsp_pos_to_pos(text, range.start, offset_encoding),
+    lsp_pos_to_pos(text, range.end, offset_encoding),
+  ) else {
+    return String::new();
+  };
+  let hints = hints
+    .iter()
+    .filter_map(|hint| Some((lsp_pos_to_pos(text, hint.position, offset_encoding)?, hint)))
+    .filter(|(idx, _)| (start..=end).contains(idx));
+
+  let mut insertions = match comment {
+    HintComment::Block(open, close) => hints
+      .map(|(idx, hint)| (idx, format!("{}{}{}", open, hint_label(hint).trim(), close)))
+      .collect::<Vec<_>>(),
+    HintComment::Line(token) => {
+      let mut lines = BTreeMap::<usize, Vec<String>>::new();
+      for (idx, hint) in hints {
+        lines.entry(text.char_to_line(idx)).or_default().push(hint_label(hint).trim().to_string());
+      }
+      lines
+        .into_iter()
+        .map(|(line, labels)| {
+          let line_end = line_end_char_index(&text.slice(..), line);
+          (line_end, format!(" {} {}", token, labels.join(", ")))
+        })
+        .collect()
+    },
+  };
+
+  // inserted from the end, so the positions before each insertion stay where they were
+  insertions.sort_by_key(|(idx, _)| *idx);
+  let mut text = text.clone();
+  let mut added = 0;
+  for (idx, insertion) in insertions.iter().rev() {
+    text.insert(*idx, insertion);
+    added += insertion.chars().count();
+  }
+  text.slice(start..end + added).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_annotate_hints() {
+    let hint = |line: u32, character: u32, label: &str| lsp::InlayHint {
+      position: lsp::Position::new(line, character),
+      label: lsp::InlayHintLabel::String(label.to_string()),
+      kind: None,
+      text_edits: None,
+      tooltip: None,
+      padding_left: None,
+      padding_right: None,
+      data: None,
+    };
+    let text = Rope::from_str("fn main() {\n    let size = area(4, 2);\n}\n");
+    let hints = vec![hint(1, 23, "height:"), hint(1, 12, ": u32"), hint(1, 20, "width:")];
+    let range = lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(2, 0));
+    let block = HintComment::Block("/*".to_string(), "*/".to_string());
+    assert_eq!(
+      annotate_hints(&text, range, hints.clone(), OffsetEncoding::Utf16, &block),
+      "    let size/*: u32*/ = area(/*width:*/4, /*height:*/2);\n"
+    );
+    let line = HintComment::Line("#".to_string());
+    assert_eq!(
+      annotate_hints(&text, range, hints.clone(), OffsetEncoding::Utf16, &line),
+      "    let size = area(4, 2); # : u32, width:, height:\n"
+    );
+
+    let described = describe_hints(hints);
+    assert_eq!(described[0]["label"], ": u32");
+    assert_eq!(described[2]["kind"], "other");
+  }
+}
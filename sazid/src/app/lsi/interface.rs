@@ -1,13 +1,13 @@
 use arc_swap::ArcSwap;
-use futures_util::FutureExt;
-use helix_core::diff::compare_ropes;
 use helix_core::syntax;
 use helix_lsp::Registry;
 use lsp::TextDocumentIdentifier;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::UnboundedSender;
+use tracing::Instrument;
 
 use helix_core::syntax::LanguageConfiguration;
 use helix_core::syntax::Loader;
@@ -21,11 +21,21 @@ use url::Url;
 use crate::action::LsiAction;
 use crate::action::SessionAction;
 use crate::action::ToolType;
-use crate::app::lsi::symbol_types::DocumentChange;
+use crate::app::lsi::query_deadline::QueryDeadlines;
+use crate::app::lsi::server_status::{
+  child_pid, progress_tokens, wait_for_initialization, LanguageServerStatus, RespawnBackoff,
+};
+use crate::app::lsi::symbol_sync::{
+  document_symbols, nest_flat_symbols, spawn_symbol_sync, WorkspaceFileChange,
+};
 use crate::app::lsi::workspace::Workspace;
+use crate::app::mentions::{IndexedSymbol, SymbolIndex};
 
 use super::query::LsiQuery;
 
+// a language server that does not initialize in this long is handled like one that crashed
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub struct LanguageServerInterface {
   pub workspaces: Vec<Workspace>,
@@ -33,6 +43,13 @@ pub struct LanguageServerInterface {
   pub language_servers: Registry,
   loader: Arc<ArcSwap<Loader>>,
   pub tx: UnboundedSender<LsiAction>,
+  pub deadlines: QueryDeadlines,
+  // the crashes of each language server by name, to back off restarting it
+  respawns: HashMap<String, RespawnBackoff>,
+  // the process of each client by id, looked up once when it is started
+  pids: HashMap<usize, Option<u32>>,
+  /// the symbols of every workspace as of the last sync, see `refresh_symbol_index`
+  pub symbol_index: SymbolIndex,
 }
 
 impl LanguageServerInterface {
@@ -40,7 +57,17 @@ impl LanguageServerInterface {
     let loader = syn_loader.clone();
     // let language_servers = Arc::new(Mutex::new(Registry::new(loader.clone())))
     let language_servers = Registry::new(syn_loader.clone());
-    Self { lsp_progress: LspProgressMap::new(), loader, language_servers, workspaces: vec![], tx }
+    Self {
+      lsp_progress: LspProgressMap::new(),
+      loader,
+      language_servers,
+      workspaces: vec![],
+      deadlines: QueryDeadlines::new(tx.clone()),
+      tx,
+      respawns: HashMap::new(),
+      pids: HashMap::new(),
+      symbol_index: SymbolIndex::default(),
+    }
   }
 
   pub fn handle_action(&mut self, action: LsiAction) {
@@ -65,9 +92,11 @@ impl LanguageServerInterface {
         let lsi_query_result = self.lsi_read_symbol_source(&lsi_query);
         Self::handle_lsi_query_result(lsi_query, lsi_query_result)
       },
-      LsiAction::ReplaceSymbolText(replacement_text, lsi_query) => {
-        let lsi_query_result = self.lsi_replace_symbol_text(replacement_text, &lsi_query);
-        Self::handle_lsi_query_result(lsi_query, lsi_query_result)
+      LsiAction::ReplaceSymbolText(replacement_text, validation, write_guard, lsi_query) => {
+        match self.lsi_replace_symbol_text(replacement_text, validation, &write_guard, &lsi_query) {
+          Ok(()) => Ok(None),
+          Err(e) => Self::handle_lsi_query_result(lsi_query, Err(e)),
+        }
       },
       LsiAction::GetWorkspaceFiles(lsi_query) => {
         log::info!("get_workspace_files: {:#?}", lsi_query);
@@ -75,49 +104,105 @@ impl LanguageServerInterface {
         Self::handle_lsi_query_result(lsi_query, lsi_query_result)
       },
       LsiAction::AddWorkspace(ws) => {
-        match self.create_workspace(
-          ws.workspace_path,
-          &ws.language,
-          &ws.language_server,
-          ws.doc_path.as_ref(),
-        ) {
-          Ok(()) => match self.synchronize_workspace_file_changes() {
-            Ok(_) => Ok(None),
-            Err(e) => {
-              Ok(Some(LsiAction::Error(format!("error updating workspace symbols: {}", e))))
-            },
-          },
-          Err(e) => Ok(Some(LsiAction::Error(format!("error creating workspace: {}", e)))),
-        }
+        let workspaces = vec![(ws.workspace_path, ws.language)];
+        self.open_workspaces(&ws.language_server, workspaces, ws.doc_path.as_ref());
+        Ok(None)
+      },
+      LsiAction::SynchronizeWorkspaceFiles => {
+        self.synchronize_workspace_file_changes().map(|_| None)
+      },
+      LsiAction::LanguageServerStartFailed(name, workspaces, error) => {
+        log::error!("{} did not start: {}", name, error);
+        self.language_server_start_failed(&name, workspaces);
+        Ok(None)
       },
       LsiAction::QueryWorkspaceSymbols(lsi_query) => {
         log::info!("query_workspace_symbols: {:#?}", lsi_query);
         let lsi_query_result = self.lsi_query_workspace_symbols(&lsi_query);
         Self::handle_lsi_query_result(lsi_query, lsi_query_result)
       },
+      LsiAction::QueryAllWorkspaceSymbols(lsi_query) => {
+        log::info!("query_all_workspace_symbols: {:#?}", lsi_query);
+        let lsi_query_result = self.query_all_workspace_symbols(&lsi_query);
+        Self::handle_lsi_query_result(lsi_query, lsi_query_result)
+      },
       LsiAction::SessionAction(_) => Ok(None),
+      LsiAction::SymbolSyncProgress(_) => Ok(None),
+      LsiAction::SearchSymbols(_) => Ok(None),
+      LsiAction::ShowOutline(_) => Ok(None),
       LsiAction::ChatToolResponse(_) => Ok(None),
       LsiAction::GoToSymbolDefinition(lsi_query) => {
         log::info!("goto_symbol_definition: {:#?}", lsi_query);
-        self.goto_symbol_definition(&lsi_query).expect("goto_symbol_definition failed");
+        if let Err(e) = self.goto_symbol_definition(&lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
         Ok(None)
       },
       LsiAction::GoToSymbolDeclaration(lsi_query) => {
         log::info!("goto_symbol_declaration: {:#?}", lsi_query);
-        self.goto_symbol_declaration(&lsi_query).unwrap();
+        if let Err(e) = self.goto_symbol_declaration(&lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
         Ok(None)
         // self.handle_lsi_query_response(lsi_query, lsi_query_result)
       },
       LsiAction::GoToTypeDefinition(lsi_query) => {
         log::info!("goto_type_definition: {:#?}", lsi_query);
-        self.goto_type_definition(&lsi_query).expect("goto_type_definition failed");
+        if let Err(e) = self.goto_type_definition(&lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
+        Ok(None)
+      },
+      LsiAction::GoToImplementation(lsi_query) => {
+        log::info!("goto_implementation: {:#?}", lsi_query);
+        if let Err(e) = self.goto_implementation(&lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
+        Ok(None)
+      },
+      LsiAction::DocumentHighlight(lsi_query) => {
+        log::info!("document_highlight: {:#?}", lsi_query);
+        if let Err(e) = self.document_highlight(&lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
+        Ok(None)
+      },
+      LsiAction::SignatureHelp(file_path, position, lsi_query) => {
+        log::info!("signature_help: {:?} {:?} {:#?}", file_path, position, lsi_query);
+        if let Err(e) = self.signature_help(&file_path, position, &lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
+        Ok(None)
+      },
+      LsiAction::InlayHints(file_path, lsi_query) => {
+        log::info!("inlay_hints: {:?} {:#?}", file_path, lsi_query);
+        if let Err(e) = self.inlay_hints(&file_path, &lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
+        Ok(None)
+      },
+      LsiAction::ReadFile(read, lsi_query) if read.inlay_hints => {
+        log::info!("read_file with inlay hints: {:?} {:#?}", read, lsi_query);
+        if let Err(e) = self.read_file_with_hints(&read, &lsi_query) {
+          return Self::handle_lsi_query_result(lsi_query, Err(e));
+        }
         Ok(None)
       },
+      LsiAction::ReadFile(read, lsi_query) => {
+        log::info!("read_file: {:?} {:#?}", read, lsi_query);
+        let lsi_query_result = self.read_file_symbol(&read, &lsi_query);
+        Self::handle_lsi_query_result(lsi_query, lsi_query_result)
+      },
       LsiAction::GetDiagnostics(lsi_query) => {
         log::info!("get_diagnostics: {:#?}", lsi_query);
         let lsi_query_result = self.get_diagnostics(&lsi_query);
         Self::handle_lsi_query_result(lsi_query, lsi_query_result)
       },
+      LsiAction::SummarizeFile(file_path, lsi_query) => {
+        log::info!("summarize_file: {:?} {:#?}", file_path, lsi_query);
+        let lsi_query_result = self.summarize_file(&file_path, &lsi_query);
+        Self::handle_lsi_query_result(lsi_query, lsi_query_result)
+      },
       LsiAction::UpdateWorkspaceFileSymbols(workspace_path, doc_id, doc_symbols) => {
         log::info!(
           "update {} workspace file symbols for doc id: {:#?}, ",
@@ -150,6 +235,17 @@ impl LanguageServerInterface {
           },
         }
       },
+      LsiAction::RestartLanguageServer(language_server_id) => {
+        self.restart_language_server(language_server_id).map(|()| None)
+      },
+      LsiAction::StopLanguageServer(language_server_id) => {
+        self.stop_language_server(language_server_id).map(|()| None)
+      },
+      LsiAction::RespawnLanguageServer(name, workspaces) => {
+        log::info!("restarting {} for {} workspaces", name, workspaces.len());
+        self.open_workspaces(&name, workspaces, None);
+        Ok(None)
+      },
     };
 
     match action_result {
@@ -237,43 +333,74 @@ impl LanguageServerInterface {
     )
   }
 
+  /// registers a workspace and starts its language server, without waiting for the server to
+  /// initialize. the client is returned, none when the root was registered already
   pub fn create_workspace(
     &mut self,
     workspace_path: PathBuf,
     language_name: &str,
     languge_server_name: &str,
     doc_path: Option<&PathBuf>,
-  ) -> anyhow::Result<()> {
+  ) -> anyhow::Result<Option<Arc<Client>>> {
     log::info!("create_workspace: {:#?}", workspace_path);
+    // each root is registered once, tool calls find their workspace by its root
+    if self.workspaces.iter().any(|workspace| workspace.workspace_path == workspace_path) {
+      return Ok(None);
+    }
 
     let root_dirs = &[workspace_path.clone()];
     let enable_snippets = false;
 
-    let language_server = self
-      .initialize_client(language_name, languge_server_name, doc_path, root_dirs, enable_snippets)
-      .unwrap()
-      .expect("unable to initialize language server");
-
-    tokio::time::interval(Duration::from_millis(250));
-    while !language_server.is_initialized() {
-      // log::info!("waiting for language server to initialize");
-    }
-
     let language_config = self
       .language_configuration_by_name(language_name)
-      .expect("can't find language configuration");
+      .ok_or_else(|| anyhow::anyhow!("no language configuration for {}", language_name))?;
+    let language_server = self
+      .initialize_client(language_name, languge_server_name, doc_path, root_dirs, enable_snippets)?
+      .ok_or_else(|| anyhow::anyhow!("unable to start {}", languge_server_name))?;
+    self.record_pid(&language_server);
+
     self.workspaces.push(Workspace::new(
       &workspace_path,
       language_name.into(),
-      language_server,
+      language_server.clone(),
       language_config,
     ));
-    Ok(())
+    Ok(Some(language_server))
   }
 
-  pub fn get_workspace_file_changes(
+  /// opens workspaces, by path and language, in a language server without blocking on it. the
+  /// files are synchronized once the server is initialized. a server that does not start, or
+  /// does not initialize within INITIALIZE_TIMEOUT, is reported with `LanguageServerStartFailed`
+  pub fn open_workspaces(
     &mut self,
-  ) -> Option<Vec<(PathBuf, DocumentChange, TextDocumentIdentifier, i32, Arc<Client>, String)>> {
+    name: &str,
+    workspaces: Vec<(PathBuf, String)>,
+    doc_path: Option<&PathBuf>,
+  ) {
+    let mut clients = vec![];
+    for (path, language) in &workspaces {
+      match self.create_workspace(path.clone(), language, name, doc_path) {
+        Ok(client) => clients.extend(client),
+        Err(e) => {
+          let failed =
+            LsiAction::LanguageServerStartFailed(name.to_string(), workspaces, e.to_string());
+          self.tx.send(failed).unwrap();
+          return;
+        },
+      }
+    }
+    let tx = self.tx.clone();
+    let name = name.to_string();
+    tokio::spawn(async move {
+      let action = match wait_for_initialization(&clients, INITIALIZE_TIMEOUT).await {
+        Ok(()) => LsiAction::SynchronizeWorkspaceFiles,
+        Err(e) => LsiAction::LanguageServerStartFailed(name, workspaces, e.to_string()),
+      };
+      tx.send(action).ok();
+    });
+  }
+
+  pub fn get_workspace_file_changes(&mut self) -> Option<Vec<WorkspaceFileChange>> {
     let changes = self
       .workspaces
       .iter_mut()
@@ -306,130 +433,55 @@ impl LanguageServerInterface {
     }
   }
 
+  /// sends the files that changed since the last sync to their language servers and requests
+  /// their symbols, see `spawn_symbol_sync`. returns true if a sync was started
   pub fn synchronize_workspace_file_changes(&mut self) -> anyhow::Result<bool> {
     log::debug!("synchronize_workspace_file_changes");
     self.workspaces.iter_mut().for_each(|workspace| workspace.scan_workspace_files().unwrap());
     match self.get_workspace_file_changes() {
       Some(changes) => {
-        for (workspace_path, doc_change, doc_id, version, language_server, language_id) in changes {
-          if let DocumentChange {
-            original_contents: Some(original_contents),
-            new_contents,
-            versioned_doc_id,
-          } = doc_change
-          {
-            log::info!("updating document with language server {:#?}", doc_id);
-            let changes = compare_ropes(&original_contents, &new_contents);
-            let tx = self.tx.clone();
-            tokio::spawn(async move {
-              language_server
-                .text_document_did_change(
-                  versioned_doc_id,
-                  &original_contents,
-                  &new_contents,
-                  changes.changes(),
-                )
-                .unwrap()
-                .then(|res| async move {
-                  log::info!("updated document with language server");
-                  match res {
-                    Err(e) => {
-                      log::error!("failed to update document with language server: {}", e);
-                    },
-                    Ok(()) => {
-                      tx.send(LsiAction::RequestWorkspaceFileSymbols(
-                        workspace_path,
-                        doc_id,
-                        language_server.id(),
-                      ))
-                      .unwrap();
-                    },
-                  }
-                })
-                .await
-            });
-          } else {
-            let tx = self.tx.clone();
-            tokio::spawn(async move {
-              language_server
-                .text_document_did_open(
-                  doc_change.versioned_doc_id.uri,
-                  version,
-                  &doc_change.new_contents,
-                  language_id,
-                )
-                .then(|res| async move {
-                  // log::info!("updated document with language server");
-                  match res {
-                    Err(e) => {
-                      log::error!("failed to open document with language server: {}", e);
-                    },
-                    Ok(()) => {
-                      tx.send(LsiAction::RequestWorkspaceFileSymbols(
-                        workspace_path,
-                        doc_id,
-                        language_server.id(),
-                      ))
-                      .unwrap();
-                    },
-                  }
-                })
-                .await
-            });
-          }
-        }
+        log::info!("synchronizing {} workspace files", changes.len());
+        spawn_symbol_sync(changes, self.tx.clone());
         Ok(true)
       },
       None => Ok(false),
     }
   }
 
+  /// replaces the symbol index with the symbols of every workspace, once a sync is complete
+  pub fn refresh_symbol_index(&self) {
+    let symbols = self
+      .workspaces
+      .iter()
+      .flat_map(|workspace| workspace.all_symbols_weak())
+      .filter_map(|symbol| symbol.upgrade())
+      .map(|symbol| {
+        let range = *symbol.range.lock().unwrap();
+        IndexedSymbol {
+          name: symbol.name.clone(),
+          file_path: symbol.file_path.clone(),
+          start_line: range.start.line as usize,
+          end_line: range.end.line as usize,
+        }
+      })
+      .collect();
+    self.symbol_index.store(Arc::new(symbols));
+  }
+
   pub fn get_workspace_file_symbols(
     workspace_path: PathBuf,
     doc_id: TextDocumentIdentifier,
     language_server: Arc<Client>,
     tx: UnboundedSender<LsiAction>,
   ) -> anyhow::Result<()> {
-    // log::info!("get_workspace_file_symbols {:?}", doc_id);
-    if let Some(request_fut) = language_server.document_symbols(doc_id.clone()) {
-      tokio::spawn(async move {
-        request_fut
-          .then(|response_json_result| async move {
-            let response_json = response_json_result.unwrap();
-            let response_parsed: Option<lsp::DocumentSymbolResponse> =
-              serde_json::from_value(response_json)?;
-
-            match response_parsed {
-              Some(lsp::DocumentSymbolResponse::Nested(symbols)) => {
-                // log::info!("nested symbols: {:#?}", symbols);
-                // workspace_file.update_symbols(doc_symbols).unwrap();
-                // log::debug!(
-                //   "workspace_file symbols: {:#?}",
-                //   workspace_file.file_tree
-                // );
-                tx.send(LsiAction::UpdateWorkspaceFileSymbols(workspace_path, doc_id, symbols))
-                  .unwrap();
-                // let mut flat_symbols = Vec::new();
-                // for symbol in symbols {
-                //   nested_to_flat(&mut flat_symbols, &doc_id, symbol, offset_encoding)
-                // }
-                // flat_symbols
-                Ok(())
-              },
-              Some(lsp::DocumentSymbolResponse::Flat(_symbols)) => {
-                // log::info!("flat symbols: {:#?}", _symbols);
-                // symbols.into_iter().map(|symbol| SymbolInformationItem { symbol, offset_encoding }).collect()
-                Err(anyhow::anyhow!("nested document symbol support is required"))
-              },
-              None => {
-                log::info!("document symbol response is None");
-                Err(anyhow::anyhow!("document symbol response is None"))
-              },
-            }
-          })
-          .await
-      });
-    };
+    tokio::spawn(async move {
+      match document_symbols(&language_server, doc_id.clone()).await {
+        Ok(symbols) => {
+          tx.send(LsiAction::UpdateWorkspaceFileSymbols(workspace_path, doc_id, symbols)).unwrap()
+        },
+        Err(e) => log::error!("error getting symbols of {}: {}", doc_id.uri, e),
+      }
+    });
     Ok(())
   }
 
@@ -447,7 +499,8 @@ impl LanguageServerInterface {
 
             let _offset_encoding = language_server.offset_encoding();
             if let Some(s) = language_server.document_symbols(doc_id.clone()) {
-              let symbols = s.await.unwrap();
+              let span = tracing::info_span!("lsp_request", method = "textDocument/documentSymbol");
+              let symbols = s.instrument(span).await?;
               let response: Option<lsp::DocumentSymbolResponse> = serde_json::from_value(symbols)?;
 
               let symbols = match response {
@@ -463,10 +516,7 @@ impl LanguageServerInterface {
                   // }
                   // flat_symbols
                 },
-                lsp::DocumentSymbolResponse::Flat(_symbols) => {
-                  // symbols.into_iter().map(|symbol| SymbolInformationItem { symbol, offset_encoding }).collect()
-                  return Err(anyhow::anyhow!("document symbol support is required"));
-                },
+                lsp::DocumentSymbolResponse::Flat(symbols) => nest_flat_symbols(symbols),
               };
               results.extend(symbols);
             }
@@ -533,7 +583,7 @@ impl LanguageServerInterface {
             enable_snippets,
           )
           .find(|(name, _client)| name == languge_server_name)
-          .expect("language server not found")
+          .ok_or_else(|| anyhow::anyhow!("language server {} not found", languge_server_name))?
           .1
           .map_err(|e| anyhow::anyhow!(e))?;
         Ok(Some(client))
@@ -542,6 +592,140 @@ impl LanguageServerInterface {
     }
   }
 
+  /// the clients in the registry, for the language server panel
+  pub fn language_server_statuses(&self, progress: &LspProgressMap) -> Vec<LanguageServerStatus> {
+    let mut statuses = self
+      .language_servers
+      .iter_clients()
+      .map(|client| LanguageServerStatus {
+        id: client.id(),
+        name: client.name().to_string(),
+        pid: self.pids.get(&client.id()).copied().flatten(),
+        roots: self
+          .workspaces
+          .iter()
+          .filter(|workspace| workspace.language_server.id() == client.id())
+          .map(|workspace| workspace.workspace_path.clone())
+          .collect(),
+        initialized: client.is_initialized(),
+        progress_tokens: progress_tokens(progress, client.id()),
+      })
+      .collect::<Vec<_>>();
+    statuses.sort_by_key(|status| status.id);
+    statuses
+  }
+
+  // looks up the process of a client that was just started. clients running the same command
+  // are told apart by the pids the others were given
+  fn record_pid(&mut self, client: &Client) {
+    if self.pids.contains_key(&client.id()) {
+      return;
+    }
+    let loader = self.loader.load();
+    let claimed = self.pids.values().flatten().copied().collect::<Vec<_>>();
+    let pid = loader
+      .language_server_configs()
+      .get(client.name())
+      .and_then(|config| child_pid(&config.command, &claimed));
+    self.pids.insert(client.id(), pid);
+  }
+
+  /// stops a language server, the workspaces it served are closed. other clients of the same
+  /// server, started for other workspaces, keep running
+  pub fn stop_language_server(&mut self, language_server_id: usize) -> anyhow::Result<()> {
+    let client = self
+      .language_server_by_id(language_server_id)
+      .ok_or_else(|| anyhow::anyhow!("no language server with id {}", language_server_id))?;
+    self.language_servers.remove_by_id(language_server_id);
+    self.pids.remove(&language_server_id);
+    tokio::spawn(async move {
+      if let Err(e) = client.force_shutdown().await {
+        log::error!("error stopping {}: {}", client.name(), e);
+      }
+    });
+    self.workspaces.retain(|workspace| workspace.language_server.id() != language_server_id);
+    Ok(())
+  }
+
+  /// handles the exit of a language server. one that exited without being stopped crashed, it
+  /// is removed from the registry and started again for its workspaces after a backoff, which
+  /// is returned. none when it was stopped, served no workspace or crashed too often
+  pub fn language_server_exited(&mut self, language_server_id: usize) -> Option<Duration> {
+    // stopped servers are no longer in the registry when they exit
+    let client = self.language_server_by_id(language_server_id)?;
+    let name = client.name().to_string();
+    let served = self
+      .workspaces
+      .iter()
+      .filter(|workspace| workspace.language_server.id() == language_server_id)
+      .map(|workspace| (workspace.workspace_path.clone(), workspace.language_id.clone()))
+      .collect::<Vec<_>>();
+    self.language_servers.remove_by_id(language_server_id);
+    self.pids.remove(&language_server_id);
+    self.workspaces.retain(|workspace| workspace.language_server.id() != language_server_id);
+    self.schedule_respawn(name, served)
+  }
+
+  /// handles a language server that did not start or initialize for `served`. the clients
+  /// started for them are stopped, and it is started again after a backoff, which counts this
+  /// like a crash. the backoff is returned, none once it failed too often
+  pub fn language_server_start_failed(
+    &mut self,
+    name: &str,
+    served: Vec<(PathBuf, String)>,
+  ) -> Option<Duration> {
+    let started = self
+      .workspaces
+      .iter()
+      .filter(|workspace| served.iter().any(|(path, _)| path == &workspace.workspace_path))
+      .map(|workspace| workspace.language_server.id())
+      .collect::<Vec<_>>();
+    for language_server_id in started {
+      // a client started for several of the workspaces is stopped with the first
+      self.stop_language_server(language_server_id).ok();
+    }
+    self
+      .workspaces
+      .retain(|workspace| !served.iter().any(|(path, _)| path == &workspace.workspace_path));
+    self.schedule_respawn(name.to_string(), served)
+  }
+
+  // starts a language server again for `served` after the backoff of its name
+  fn schedule_respawn(&mut self, name: String, served: Vec<(PathBuf, String)>) -> Option<Duration> {
+    if served.is_empty() {
+      return None;
+    }
+    let Some(delay) = self.respawns.entry(name.clone()).or_default().next_delay(Instant::now())
+    else {
+      log::error!("{} crashed too often and is not restarted", name);
+      return None;
+    };
+    log::warn!("{} crashed, restarting it in {:?}", name, delay);
+    let tx = self.tx.clone();
+    tokio::spawn(async move {
+      tokio::time::sleep(delay).await;
+      tx.send(LsiAction::RespawnLanguageServer(name, served)).ok();
+    });
+    Some(delay)
+  }
+
+  /// stops a language server and starts it again for the workspaces it served, which are
+  /// opened in it again
+  pub fn restart_language_server(&mut self, language_server_id: usize) -> anyhow::Result<()> {
+    let client = self
+      .language_server_by_id(language_server_id)
+      .ok_or_else(|| anyhow::anyhow!("no language server with id {}", language_server_id))?;
+    let served = self
+      .workspaces
+      .iter()
+      .filter(|workspace| workspace.language_server.id() == language_server_id)
+      .map(|workspace| (workspace.workspace_path.clone(), workspace.language_id.clone()))
+      .collect::<Vec<_>>();
+    self.stop_language_server(language_server_id)?;
+    self.open_workspaces(client.name(), served, None);
+    Ok(())
+  }
+
   pub fn language_configuration_by_name(&self, name: &str) -> Option<Arc<LanguageConfiguration>> {
     self.loader.load().language_config_for_name(name)
   }
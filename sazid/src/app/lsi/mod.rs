@@ -1,8 +1,13 @@
 use std::path::Path;
 
+pub mod inlay_hints;
 pub mod interface;
 pub mod query;
+pub mod query_deadline;
+pub mod server_status;
 pub mod status_message;
+pub mod symbol_query;
+pub mod symbol_sync;
 pub mod symbol_types;
 pub mod tool_impl;
 pub mod workspace;
@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use helix_lsp::lsp;
 use serde::{Deserialize, Serialize};
@@ -26,4 +27,16 @@ pub struct LsiQuery {
   pub tool_call_id: String,
   pub include_source: bool,
   pub test_query: bool,
+  /// a symbol query, such as `kind:function path:src/** parent:App handle`, see `SymbolQuery`
+  #[serde(default)]
+  pub query: Option<String>,
+  /// maximum number of results, applied by queries that span workspaces and symbol queries
+  #[serde(default)]
+  pub limit: Option<usize>,
+  /// the number of results a symbol query skips, to page through them
+  #[serde(default)]
+  pub offset: Option<usize>,
+  /// how long the query may wait on the language server, none waits for as long as it takes
+  #[serde(default)]
+  pub timeout: Option<Duration>,
 }
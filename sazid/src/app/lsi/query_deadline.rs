@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+use crate::action::{LsiAction, SessionAction, ToolType};
+
+use super::query::LsiQuery;
+
+/// the queries with a timeout that have not been answered, by tool call id. when the deadline
+/// of one passes its token is cancelled, which stops the requests made for it, and the model is
+/// told the call timed out
+#[derive(Debug, Clone)]
+pub struct QueryDeadlines {
+  pending: Arc<Mutex<HashMap<String, CancellationToken>>>,
+  tx: UnboundedSender<LsiAction>,
+}
+
+impl QueryDeadlines {
+  pub fn new(tx: UnboundedSender<LsiAction>) -> Self {
+    QueryDeadlines { pending: Arc::new(Mutex::new(HashMap::new())), tx }
+  }
+
+  /// starts the deadline of a query as it is received, a query without a timeout has none
+  pub fn start(&self, lsi_query: &LsiQuery) {
+    let Some(timeout) = lsi_query.timeout else {
+      return;
+    };
+    let token = CancellationToken::new();
+    self.pending.lock().unwrap().insert(lsi_query.tool_call_id.clone(), token.clone());
+
+    let deadlines = self.clone();
+    let lsi_query = lsi_query.clone();
+    tokio::spawn(async move {
+      tokio::select! {
+        _ = tokio::time::sleep(timeout) => {},
+        _ = token.cancelled() => return,
+      }
+      if deadlines.pending.lock().unwrap().remove(&lsi_query.tool_call_id).is_none() {
+        return;
+      }
+      token.cancel();
+      log::warn!("{} timed out after {:?}", lsi_query.tool_call_id, timeout);
+      let message = format!(
+        "the language server did not answer within {} seconds, the request was cancelled. it \
+         may still be indexing the workspace, try again later or narrow the query",
+        timeout.as_secs()
+      );
+      let action = SessionAction::ToolCallTimedOut(ToolType::LsiQuery(lsi_query), message);
+      let _ = deadlines.tx.send(LsiAction::SessionAction(Box::new(action)));
+    });
+  }
+
+  /// the token cancelled when the deadline of a query passes. a query without a deadline gets
+  /// one that is never cancelled
+  pub fn token(&self, lsi_query: &LsiQuery) -> CancellationToken {
+    self.pending.lock().unwrap().get(&lsi_query.tool_call_id).cloned().unwrap_or_default()
+  }
+
+  /// whether a query with a timeout is no longer waited on, as its deadline passed or it was
+  /// answered. a request for it is not run
+  pub fn timed_out(&self, lsi_query: &LsiQuery) -> bool {
+    lsi_query.timeout.is_some()
+      && !self.pending.lock().unwrap().contains_key(&lsi_query.tool_call_id)
+  }
+
+  /// ends the deadline of a query that was answered
+  pub fn finish(&self, tool_call_id: &str) {
+    if let Some(token) = self.pending.lock().unwrap().remove(tool_call_id) {
+      token.cancel();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  #[tokio::test]
+  async fn test_query_times_out() {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let deadlines = QueryDeadlines::new(tx);
+    let query = |id: &str| LsiQuery {
+      tool_call_id: id.to_string(),
+      timeout: Some(Duration::from_millis(50)),
+      ..Default::default()
+    };
+    deadlines.start(&query("slow"));
+    deadlines.start(&query("fast"));
+    let token = deadlines.token(&query("slow"));
+
+    deadlines.finish("fast");
+    assert!(!deadlines.timed_out(&query("slow")));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(token.is_cancelled());
+    assert!(deadlines.timed_out(&query("slow")));
+
+    // only the query that was not answered is reported
+    let Some(LsiAction::SessionAction(action)) = rx.recv().await else {
+      panic!("expected a timed out tool call");
+    };
+    assert!(matches!(
+      *action,
+      SessionAction::ToolCallTimedOut(ToolType::LsiQuery(query), _) if query.tool_call_id == "slow"
+    ));
+    assert!(rx.try_recv().is_err());
+    assert!(!deadlines.token(&LsiQuery::default()).is_cancelled());
+  }
+}
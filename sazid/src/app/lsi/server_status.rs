@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use helix_lsp::{lsp, Client, LspProgressMap};
+
+// a server that crashes more often than this in a row is left stopped
+const MAX_RESPAWNS: u32 = 5;
+const RESPAWN_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESPAWN_MAX_DELAY: Duration = Duration::from_secs(60);
+// a server that ran this long since its last crash starts its backoff over
+const RESPAWN_RESET_AFTER: Duration = Duration::from_secs(300);
+
+/// what the language server panel shows of a client in the registry
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageServerStatus {
+  pub id: usize,
+  pub name: String,
+  /// none when the process is not found, or off linux
+  pub pid: Option<u32>,
+  /// the workspaces the server was started for
+  pub roots: Vec<PathBuf>,
+  pub initialized: bool,
+  /// the work done progress tokens the server has not ended
+  pub progress_tokens: Vec<String>,
+}
+
+/// the work done progress tokens of a server that have not ended, in order
+pub fn progress_tokens(progress: &LspProgressMap, id: usize) -> Vec<String> {
+  let mut tokens = progress
+    .progress_map(id)
+    .map(|tokens| {
+      tokens
+        .keys()
+        .map(|token| match token {
+          lsp::NumberOrString::Number(n) => n.to_string(),
+          lsp::NumberOrString::String(s) => s.clone(),
+        })
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  tokens.sort();
+  tokens
+}
+
+/// the delays before a crashed language server is started again, doubling with each crash
+#[derive(Debug, Clone, Default)]
+pub struct RespawnBackoff {
+  attempts: u32,
+  last_crash: Option<Instant>,
+}
+
+impl RespawnBackoff {
+  /// records a crash at `now`, returns the delay before the server is started again. none once
+  /// it crashed too often
+  pub fn next_delay(&mut self, now: Instant) -> Option<Duration> {
+    if self.last_crash.is_some_and(|last| now.duration_since(last) > RESPAWN_RESET_AFTER) {
+      self.attempts = 0;
+    }
+    self.last_crash = Some(now);
+    if self.attempts >= MAX_RESPAWNS {
+      return None;
+    }
+    let delay = RESPAWN_BASE_DELAY * 2u32.pow(self.attempts);
+    self.attempts += 1;
+    Some(delay.min(RESPAWN_MAX_DELAY))
+  }
+}
+
+/// waits for the clients to initialize, polled as the registry does not signal it. a client
+/// still initializing after `timeout` is an error
+pub async fn wait_for_initialization(
+  clients: &[Arc<Client>],
+  timeout: Duration,
+) -> anyhow::Result<()> {
+  let wait = async {
+    let mut interval = tokio::time::interval(Duration::from_millis(250));
+    while !clients.iter().all(|client| client.is_initialized()) {
+      interval.tick().await;
+    }
+  };
+  tokio::time::timeout(timeout, wait)
+    .await
+    .map_err(|_| anyhow::anyhow!("the language server did not initialize within {:?}", timeout))
+}
+
+/// the pid of a process this one started running `command`, other than the `claimed` ones. the
+/// registry does not hand out the processes of its clients, so they are looked up by parent and
+/// name in /proc
+pub fn child_pid(command: &str, claimed: &[u32]) -> Option<u32> {
+  let name = Path::new(command).file_name()?.to_str()?;
+  // the kernel keeps the first 15 bytes of the name
+  let comm = name.get(..name.len().min(15)).unwrap_or(name);
+  let parent = std::process::id().to_string();
+  std::fs::read_dir("/proc").ok()?.filter_map(Result::ok).find_map(|entry| {
+    let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+    let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+    // the name in stat is in parentheses and can hold spaces, the fields after it are plain
+    let (name, fields) = stat.split_once(" (")?.1.rsplit_once(") ")?;
+    let ppid = fields.split_whitespace().nth(1)?;
+    (name == comm && ppid == parent && !claimed.contains(&pid)).then_some(pid)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_respawn_backoff() {
+    let mut backoff = RespawnBackoff::default();
+    let start = Instant::now();
+    let delays = (0..MAX_RESPAWNS).map(|_| backoff.next_delay(start).unwrap()).collect::<Vec<_>>();
+    assert_eq!(delays.iter().map(Duration::as_secs).collect::<Vec<_>>(), [1, 2, 4, 8, 16]);
+    assert_eq!(backoff.next_delay(start), None);
+    let later = start + RESPAWN_RESET_AFTER + Duration::from_secs(1);
+    assert_eq!(backoff.next_delay(later), Some(RESPAWN_BASE_DELAY));
+  }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn test_child_pid() {
+    let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+    assert_eq!(child_pid("/bin/sleep", &[]), Some(child.id()));
+    assert_eq!(child_pid("/bin/sleep", &[child.id()]), None);
+    assert_eq!(child_pid("rust-analyzer", &[]), None);
+    child.kill().unwrap();
+    child.wait().unwrap();
+  }
+}
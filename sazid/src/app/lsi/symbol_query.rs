@@ -0,0 +1,133 @@
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use globset::{Glob, GlobMatcher};
+use helix_core::fuzzy::MATCHER;
+use helix_lsp::lsp;
+use nucleo::pattern::{Atom, AtomKind, CaseMatching};
+use nucleo::{Config, Utf32Str};
+use regex::Regex;
+
+use super::symbol_types::SourceSymbol;
+
+/// a symbol query such as `kind:function,method path:src/**/*.rs parent:App name:^handle_ key`.
+/// the terms with a prefix filter the symbols, the remaining words are matched fuzzily against
+/// their names and rank the results
+#[derive(Debug, Default)]
+pub struct SymbolQuery {
+  /// the symbol must be one of these kinds, any kind when empty
+  pub kinds: Vec<lsp::SymbolKind>,
+  /// a glob on the path of the symbol's file, relative to its workspace
+  pub path: Option<GlobMatcher>,
+  /// a regex on the symbol's name
+  pub name: Option<Regex>,
+  /// the name of the symbol the symbol is declared in
+  pub parent: Option<String>,
+  pub fuzzy: Vec<String>,
+}
+
+impl SymbolQuery {
+  pub fn parse(query: &str) -> anyhow::Result<Self> {
+    let mut symbol_query = SymbolQuery::default();
+    for term in query.split_whitespace() {
+      match term.split_once(':') {
+        Some(("kind", kinds)) => {
+          for kind in kinds.split(',').filter(|kind| !kind.is_empty()) {
+            symbol_query.kinds.push(parse_kind(kind)?);
+          }
+        },
+        Some(("path", glob)) => symbol_query.path = Some(Glob::new(glob)?.compile_matcher()),
+        Some(("name", regex)) => symbol_query.name = Some(Regex::new(regex)?),
+        Some(("parent", parent)) => symbol_query.parent = Some(parent.to_string()),
+        _ => symbol_query.fuzzy.push(term.to_string()),
+      }
+    }
+    Ok(symbol_query)
+  }
+
+  /// the symbols that match the query. with fuzzy words they are ranked by how well their names
+  /// match every word, otherwise they keep their order
+  pub fn filter(&self, symbols: Vec<Arc<SourceSymbol>>) -> Vec<Arc<SourceSymbol>> {
+    let symbols = symbols.into_iter().filter(|symbol| self.is_match(symbol));
+    if self.fuzzy.is_empty() {
+      return symbols.collect();
+    }
+
+    let mut matcher = MATCHER.lock();
+    matcher.config = Config::DEFAULT;
+    let atoms = self
+      .fuzzy
+      .iter()
+      .map(|word| Atom::new(word, CaseMatching::Smart, AtomKind::Fuzzy, false))
+      .collect::<Vec<_>>();
+    let mut buf = Vec::new();
+    let mut scored = symbols
+      .filter_map(|symbol| {
+        let mut score = 0;
+        for atom in &atoms {
+          score += atom.score(Utf32Str::new(&symbol.name, &mut buf), &mut matcher)? as u32;
+        }
+        Some((score, symbol))
+      })
+      .collect::<Vec<_>>();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, symbol)| symbol).collect()
+  }
+
+  fn is_match(&self, symbol: &SourceSymbol) -> bool {
+    let relative_path =
+      symbol.file_path.strip_prefix(&symbol.workspace_path).unwrap_or(&symbol.file_path);
+    (self.kinds.is_empty() || self.kinds.contains(&symbol.kind))
+      && self.path.as_ref().map_or(true, |glob| glob.is_match(relative_path))
+      && self.name.as_ref().map_or(true, |regex| regex.is_match(&symbol.name))
+      && self.parent.as_ref().map_or(true, |name| {
+        symbol.parent.lock().unwrap().upgrade().is_some_and(|parent| &parent.name == name)
+      })
+  }
+}
+
+// a kind as the model writes it, such as `function`, `enum_member` or `TYPE_PARAMETER`
+fn parse_kind(kind: &str) -> anyhow::Result<lsp::SymbolKind> {
+  let pascal_case = change_case::pascal_case(kind);
+  lsp::SymbolKind::try_from(pascal_case.as_str())
+    .map_err(|_| anyhow::anyhow!("unknown symbol kind: {}", kind))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::PathBuf;
+
+  #[test]
+  fn test_symbol_query() {
+    let symbol = |name: &str, kind: lsp::SymbolKind, file: &str| {
+      Arc::new(SourceSymbol {
+        name: name.to_string(),
+        kind,
+        workspace_path: PathBuf::from("/work"),
+        file_path: PathBuf::from("/work").join(file),
+        ..Default::default()
+      })
+    };
+    let app = symbol("App", lsp::SymbolKind::STRUCT, "src/app.rs");
+    let handle_key = symbol("handle_key", lsp::SymbolKind::METHOD, "src/app.rs");
+    *handle_key.parent.lock().unwrap() = Arc::downgrade(&app);
+    let symbols = vec![
+      app.clone(),
+      handle_key.clone(),
+      symbol("key_handler", lsp::SymbolKind::FUNCTION, "src/input.rs"),
+      symbol("handle_key", lsp::SymbolKind::FUNCTION, "tests/input.rs"),
+    ];
+    let names = |query: &str| {
+      let query = SymbolQuery::parse(query).unwrap();
+      query.filter(symbols.clone()).iter().map(|s| s.name.clone()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(names("kind:function,method path:src/**"), ["handle_key", "key_handler"]);
+    assert_eq!(names("parent:App"), ["handle_key"]);
+    assert_eq!(names("name:^handle kind:function"), ["handle_key"]);
+    assert_eq!(names("hkey path:src/*.rs"), ["handle_key"]);
+    assert!(names("path:*.toml").is_empty());
+    assert!(SymbolQuery::parse("kind:widget").is_err());
+  }
+}
@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures_util::{stream, StreamExt};
+use helix_core::diff::compare_ropes;
+use helix_lsp::{lsp, Client};
+use lsp::{DocumentSymbol, TextDocumentIdentifier};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::Instrument;
+
+use crate::action::LsiAction;
+
+use super::symbol_types::DocumentChange;
+
+// documents synchronized with their language servers at once. a server answers the requests of
+// one document while the next are sent, without being flooded by a large workspace
+const MAX_CONCURRENT_FILE_SYNCS: usize = 16;
+
+/// a workspace file that changed since its language server last saw it: its workspace, the
+/// change, its document, its version, the server and the language id
+pub type WorkspaceFileChange =
+  (PathBuf, DocumentChange, TextDocumentIdentifier, i32, Arc<Client>, String);
+
+/// the progress of a symbol sync, sent as each file is done
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolSyncProgress {
+  pub file: TextDocumentIdentifier,
+  /// the files done so far, including this one, and the files being synchronized
+  pub done: usize,
+  pub total: usize,
+  /// the files done so far that could not be synchronized
+  pub failed: usize,
+  /// why this file could not be synchronized
+  pub error: Option<String>,
+}
+
+impl SymbolSyncProgress {
+  pub fn is_complete(&self) -> bool {
+    self.done == self.total
+  }
+
+  /// the status line shown while the symbols are synchronized
+  pub fn message(&self) -> String {
+    match (self.is_complete(), self.failed) {
+      (false, _) => format!("synchronizing workspace symbols {}/{}", self.done, self.total),
+      (true, 0) => format!("synchronized the symbols of {} files", self.total),
+      (true, failed) => format!(
+        "synchronized the symbols of {} files, {} could not be synchronized",
+        self.total - failed,
+        failed
+      ),
+    }
+  }
+}
+
+/// synchronizes the changed files with their language servers and requests their symbols, a
+/// bounded number at a time. the symbols of each file are sent as an
+/// `UpdateWorkspaceFileSymbols`, followed by the progress of the sync
+pub fn spawn_symbol_sync(changes: Vec<WorkspaceFileChange>, tx: UnboundedSender<LsiAction>) {
+  let total = changes.len();
+  let span = tracing::info_span!("symbol_sync", files = total);
+  let sync = async move {
+    let mut synced =
+      stream::iter(changes).map(sync_workspace_file).buffer_unordered(MAX_CONCURRENT_FILE_SYNCS);
+    let mut done = 0;
+    let mut failed = 0;
+    while let Some((workspace_path, doc_id, result)) = synced.next().await {
+      done += 1;
+      let error = match result {
+        Ok(symbols) => {
+          let update =
+            LsiAction::UpdateWorkspaceFileSymbols(workspace_path, doc_id.clone(), symbols);
+          let _ = tx.send(update);
+          None
+        },
+        Err(e) => {
+          log::warn!("could not synchronize {}: {}", doc_id.uri, e);
+          failed += 1;
+          Some(e.to_string())
+        },
+      };
+      let progress = SymbolSyncProgress { file: doc_id, done, total, failed, error };
+      let _ = tx.send(LsiAction::SymbolSyncProgress(progress));
+    }
+  };
+  tokio::spawn(sync.instrument(span));
+}
+
+// synchronizes one file, returning its workspace and document with the result
+async fn sync_workspace_file(
+  change: WorkspaceFileChange,
+) -> (PathBuf, TextDocumentIdentifier, anyhow::Result<Vec<DocumentSymbol>>) {
+  let (workspace_path, doc_change, doc_id, version, language_server, language_id) = change;
+  let result = sync_document(&language_server, doc_change, version, language_id, &doc_id).await;
+  (workspace_path, doc_id, result)
+}
+
+// opens the document with its language server or sends it the change, then requests its symbols
+async fn sync_document(
+  language_server: &Client,
+  doc_change: DocumentChange,
+  version: i32,
+  language_id: String,
+  doc_id: &TextDocumentIdentifier,
+) -> anyhow::Result<Vec<DocumentSymbol>> {
+  match doc_change {
+    DocumentChange {
+      original_contents: Some(original_contents),
+      new_contents,
+      versioned_doc_id,
+    } => {
+      let changes = compare_ropes(&original_contents, &new_contents);
+      language_server
+        .text_document_did_change(
+          versioned_doc_id,
+          &original_contents,
+          &new_contents,
+          changes.changes(),
+        )
+        .ok_or_else(|| anyhow::anyhow!("the language server does not take document changes"))?
+        .await?
+    },
+    DocumentChange { new_contents, versioned_doc_id, .. } => {
+      language_server
+        .text_document_did_open(versioned_doc_id.uri, version, &new_contents, language_id)
+        .await?
+    },
+  }
+  document_symbols(language_server, doc_id.clone()).await
+}
+
+/// requests the symbols of a document, nested as the workspace keeps them
+pub async fn document_symbols(
+  language_server: &Client,
+  doc_id: TextDocumentIdentifier,
+) -> anyhow::Result<Vec<DocumentSymbol>> {
+  let Some(request) = language_server.document_symbols(doc_id) else {
+    return Err(anyhow::anyhow!("the language server does not provide document symbols"));
+  };
+  let span = tracing::info_span!("lsp_request", method = "textDocument/documentSymbol");
+  let response = request.instrument(span).await?;
+  match serde_json::from_value::<Option<lsp::DocumentSymbolResponse>>(response)? {
+    Some(lsp::DocumentSymbolResponse::Nested(symbols)) => Ok(symbols),
+    Some(lsp::DocumentSymbolResponse::Flat(symbols)) => Ok(nest_flat_symbols(symbols)),
+    None => Err(anyhow::anyhow!("document symbol response is None")),
+  }
+}
+
+/// nests the symbols of a flat response, as servers other than rust-analyzer send them. a symbol
+/// is the child of the innermost symbol whose range holds its own, or when the ranges only cover
+/// the names, of the last symbol named as its container
+pub fn nest_flat_symbols(mut symbols: Vec<lsp::SymbolInformation>) -> Vec<DocumentSymbol> {
+  // outer symbols come before the symbols they hold
+  symbols.sort_by(|a, b| {
+    let (a, b) = (a.location.range, b.location.range);
+    a.start.cmp(&b.start).then(b.end.cmp(&a.end))
+  });
+  let holds = |outer: lsp::Range, inner: lsp::Range| {
+    outer != inner && outer.start <= inner.start && inner.end <= outer.end
+  };
+
+  let mut parents = Vec::with_capacity(symbols.len());
+  // the symbols holding the current one, innermost last
+  let mut enclosing: Vec<usize> = vec![];
+  for (idx, symbol) in symbols.iter().enumerate() {
+    let range = symbol.location.range;
+    while enclosing.last().is_some_and(|&outer| !holds(symbols[outer].location.range, range)) {
+      enclosing.pop();
+    }
+    let parent = enclosing.last().copied().or_else(|| {
+      let container = symbol.container_name.as_ref()?;
+      (0..idx).rev().find(|&other| &symbols[other].name == container)
+    });
+    parents.push(parent);
+    enclosing.push(idx);
+  }
+
+  let mut nodes = symbols.into_iter().map(|symbol| Some(nested_symbol(symbol))).collect::<Vec<_>>();
+  // a parent comes before its children, so each child is complete once it is moved
+  for idx in (0..nodes.len()).rev() {
+    if let Some(parent) = parents[idx] {
+      let child = nodes[idx].take().unwrap();
+      nodes[parent].as_mut().unwrap().children.get_or_insert_with(Vec::new).insert(0, child);
+    }
+  }
+  nodes.into_iter().flatten().collect()
+}
+
+#[allow(deprecated)]
+fn nested_symbol(symbol: lsp::SymbolInformation) -> DocumentSymbol {
+  DocumentSymbol {
+    name: symbol.name,
+    detail: None,
+    kind: symbol.kind,
+    tags: symbol.tags,
+    deprecated: symbol.deprecated,
+    range: symbol.location.range,
+    selection_range: symbol.location.range,
+    children: None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_progress_message() {
+    let file = TextDocumentIdentifier::new("file:///work/src/lib.rs".parse().unwrap());
+    let mut progress = SymbolSyncProgress { file, done: 3, total: 10, failed: 0, error: None };
+    assert_eq!(progress.message(), "synchronizing workspace symbols 3/10");
+    progress.done = 10;
+    assert_eq!(progress.message(), "synchronized the symbols of 10 files");
+    progress.failed = 2;
+    assert!(progress.is_complete());
+    assert_eq!(
+      progress.message(),
+      "synchronized the symbols of 8 files, 2 could not be synchronized"
+    );
+  }
+
+  #[allow(deprecated)]
+  #[test]
+  fn test_flat_symbols_are_nested() {
+    let symbol = |name: &str, container: Option<&str>, lines: (u32, u32)| lsp::SymbolInformation {
+      name: name.to_string(),
+      kind: lsp::SymbolKind::FUNCTION,
+      tags: None,
+      deprecated: None,
+      location: lsp::Location::new(
+        "file:///work/app.py".parse().unwrap(),
+        lsp::Range::new(lsp::Position::new(lines.0, 0), lsp::Position::new(lines.1, 0)),
+      ),
+      container_name: container.map(str::to_string),
+    };
+    let nested = nest_flat_symbols(vec![
+      symbol("helper", None, (20, 22)),
+      symbol("method", Some("App"), (3, 5)),
+      symbol("App", None, (1, 10)),
+      symbol("run", Some("App"), (6, 9)),
+      // a server that only gives the range of the name
+      symbol("Other", None, (12, 12)),
+      symbol("field", Some("Other"), (13, 13)),
+    ]);
+    let names =
+      |symbols: &[DocumentSymbol]| symbols.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&nested), ["App", "Other", "helper"]);
+    assert_eq!(names(nested[0].children.as_ref().unwrap()), ["method", "run"]);
+    assert_eq!(names(nested[1].children.as_ref().unwrap()), ["field"]);
+    assert!(nested[2].children.is_none());
+  }
+}
@@ -1,105 +1,263 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
 
 use serde_json::json;
+use tracing::Instrument;
 
-use super::workspace::Workspace;
+use super::inlay_hints::{self, HintComment};
+use super::symbol_query::SymbolQuery;
+use super::symbol_types::SourceSymbol;
+use super::workspace::{query_file_symbols, Workspace};
+use super::workspace_file::WorkspaceFile;
 use super::{
   interface::LanguageServerInterface, query::LsiQuery, symbol_types::SerializableSourceSymbol,
 };
+use crate::action::{LsiAction, SessionAction};
+use crate::app::edit_validation::{self, EditValidation};
+use crate::app::file_patch::FileEdit;
+use crate::app::file_read::{self, FileRead};
+use crate::app::file_summary::{self, SymbolSummary};
+use crate::app::model_tools::tool_permissions::WriteGuard;
+use crate::app::semantic_search::indexable_files;
+use helix_core::Rope;
 use helix_lsp::lsp::{self};
+use helix_lsp::util::pos_to_lsp_pos;
 
-use lsp::{Diagnostic, DiagnosticSeverity, NumberOrString};
+use lsp::{Diagnostic, DiagnosticSeverity, DocumentHighlight, NumberOrString};
 use url::Url;
 
+const DEFAULT_ALL_WORKSPACE_SYMBOL_LIMIT: usize = 100;
+const DEFAULT_SYMBOL_PAGE_SIZE: usize = 50;
+
 impl LanguageServerInterface {
   pub fn goto_type_definition(&self, lsi_query: &LsiQuery) -> anyhow::Result<()> {
-    let workspace = self.get_workspace(lsi_query).unwrap();
-    let symbol_id =
-      TryInto::<[u8; 32]>::try_into(lsi_query.symbol_id.clone().expect("symbol_id not set"))
-        .expect("symbol id has the incorrect number of bytes");
-    let symbol = workspace
-      .query_symbol_by_id(&symbol_id)
-      .unwrap_or_else(|| panic!("could not find symbol with id {:#?}", symbol_id));
-    let text_document =
-      lsp::TextDocumentIdentifier { uri: Url::from_file_path(symbol.file_path.clone()).unwrap() };
-    let position = symbol.selection_range.lock().unwrap().start;
+    let (workspace, _, text_document, position) = self.symbol_position(lsi_query)?;
     let work_done_token = Some(NumberOrString::String("goto type definition".to_string()));
     let response = workspace
       .language_server
       .goto_type_definition(text_document, position, work_done_token)
-      .expect("could not obtain goto definition response");
+      .ok_or_else(|| {
+        anyhow::anyhow!("the language server does not support goto type definition")
+      })?;
 
     let lsi_query = lsi_query.clone();
     let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/typeDefinition");
+    let cancelled = self.deadlines.token(&lsi_query);
     tokio::spawn(async move {
-      let result = response.await;
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
       let result = result
-        .map(|value: serde_json::Value| serde_json::to_string_pretty(&value))
-        .unwrap()
-        .unwrap();
-      Self::send_query_response(&tx, lsi_query, Ok(result));
+        .and_then(|value| Ok(serde_json::to_string_pretty(&value)?))
+        .map_err(|e| anyhow::anyhow!("error getting the type definition: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
+    });
+
+    Ok(())
+  }
+
+  /// requests the locations that implement the symbol, such as the impls of a trait
+  pub fn goto_implementation(&self, lsi_query: &LsiQuery) -> anyhow::Result<()> {
+    let (workspace, _, text_document, position) = self.symbol_position(lsi_query)?;
+    let work_done_token = Some(NumberOrString::String("goto implementation".to_string()));
+    let response = workspace
+      .language_server
+      .goto_implementation(text_document, position, work_done_token)
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support goto implementation"))?;
+
+    let lsi_query = lsi_query.clone();
+    let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/implementation");
+    let cancelled = self.deadlines.token(&lsi_query);
+    tokio::spawn(async move {
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
+      let result = result
+        .and_then(|value| Ok(serde_json::to_string_pretty(&value)?))
+        .map_err(|e| anyhow::anyhow!("error getting the implementations: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
+    });
+
+    Ok(())
+  }
+
+  /// requests the occurrences of the symbol in its own file, classified as reads or writes
+  pub fn document_highlight(&self, lsi_query: &LsiQuery) -> anyhow::Result<()> {
+    let (workspace, symbol, text_document, position) = self.symbol_position(lsi_query)?;
+    let work_done_token = Some(NumberOrString::String("document highlight".to_string()));
+    let response = workspace
+      .language_server
+      .text_document_document_highlight(text_document, position, work_done_token)
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support document highlights"))?;
+
+    let file_path = symbol.file_path.clone();
+    let lsi_query = lsi_query.clone();
+    let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/documentHighlight");
+    let cancelled = self.deadlines.token(&lsi_query);
+    tokio::spawn(async move {
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
+      let result = result
+        .and_then(|value| Ok(serde_json::from_value::<Option<Vec<DocumentHighlight>>>(value)?))
+        .map(|highlights| {
+          let contents = std::fs::read_to_string(&file_path).unwrap_or_default();
+          describe_highlights(&file_path, &contents, highlights.unwrap_or_default()).to_string()
+        })
+        .map_err(|e| anyhow::anyhow!("error getting document highlights: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
+    });
+
+    Ok(())
+  }
+
+  /// requests the signatures of the call at a position in a file
+  pub fn signature_help(
+    &self,
+    file_path: &Path,
+    position: lsp::Position,
+    lsi_query: &LsiQuery,
+  ) -> anyhow::Result<()> {
+    let workspace = self.get_workspace(lsi_query)?;
+    let file_path = file_path.canonicalize()?;
+    let uri = Url::from_file_path(&file_path)
+      .map_err(|_| anyhow::anyhow!("invalid file path: {}", file_path.display()))?;
+    let work_done_token = Some(NumberOrString::String("signature help".to_string()));
+    let response = workspace
+      .language_server
+      .text_document_signature_help(lsp::TextDocumentIdentifier { uri }, position, work_done_token)
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support signature help"))?;
+
+    let lsi_query = lsi_query.clone();
+    let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/signatureHelp");
+    let cancelled = self.deadlines.token(&lsi_query);
+    tokio::spawn(async move {
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
+      let result = result
+        .and_then(|value| Ok(serde_json::from_value::<Option<lsp::SignatureHelp>>(value)?))
+        .map(|help| match help {
+          Some(help) => describe_signature_help(help).to_string(),
+          None => "there is no call at this position".to_string(),
+        })
+        .map_err(|e| anyhow::anyhow!("error getting signature help: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
+    });
+
+    Ok(())
+  }
+
+  /// requests the inlay hints of the lines of a file in the range of the query. they are
+  /// returned as a list, or written into the lines as comments when `include_source` is set
+  pub fn inlay_hints(&self, file_path: &Path, lsi_query: &LsiQuery) -> anyhow::Result<()> {
+    let workspace = self.get_workspace(lsi_query)?;
+    let file_path = file_path.canonicalize()?;
+    let uri = Url::from_file_path(&file_path)
+      .map_err(|_| anyhow::anyhow!("invalid file path: {}", file_path.display()))?;
+    let text = Rope::from_str(&std::fs::read_to_string(&file_path)?);
+    let offset_encoding = workspace.language_server.offset_encoding();
+    let mut range = lsi_query.range.unwrap_or_default();
+    if range.end.line as usize >= text.len_lines() {
+      range.end = pos_to_lsp_pos(&text, text.len_chars(), offset_encoding);
+    }
+    if range.start > range.end {
+      return Err(anyhow::anyhow!("the range starts past the end of {}", file_path.display()));
+    }
+    let comment = HintComment::for_language(&workspace.language_config);
+    let work_done_token = Some(NumberOrString::String("inlay hints".to_string()));
+    let response = workspace
+      .language_server
+      .text_document_range_inlay_hints(lsp::TextDocumentIdentifier { uri }, range, work_done_token)
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support inlay hints"))?;
+
+    let lsi_query = lsi_query.clone();
+    let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/inlayHint");
+    let cancelled = self.deadlines.token(&lsi_query);
+    tokio::spawn(async move {
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
+      let result = result
+        .and_then(|value| Ok(serde_json::from_value::<Option<Vec<lsp::InlayHint>>>(value)?))
+        .map(|hints| {
+          let hints = hints.unwrap_or_default();
+          match lsi_query.include_source {
+            true => inlay_hints::annotate_hints(&text, range, hints, offset_encoding, &comment),
+            false => inlay_hints::describe_hints(hints).to_string(),
+          }
+        })
+        .map_err(|e| anyhow::anyhow!("error getting inlay hints: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
     });
 
     Ok(())
   }
 
   pub fn goto_symbol_definition(&self, lsi_query: &LsiQuery) -> anyhow::Result<()> {
-    let workspace = self.get_workspace(lsi_query).unwrap();
-    let symbol_id =
-      TryInto::<[u8; 32]>::try_into(lsi_query.symbol_id.clone().expect("symbol_id not set"))
-        .expect("symbol id has the incorrect number of bytes");
-    let symbol = workspace
-      .query_symbol_by_id(&symbol_id)
-      .unwrap_or_else(|| panic!("could not find symbol with id {:#?}", symbol_id));
-    let text_document =
-      lsp::TextDocumentIdentifier { uri: Url::from_file_path(symbol.file_path.clone()).unwrap() };
-    let position = symbol.selection_range.lock().unwrap().start;
+    let (workspace, _, text_document, position) = self.symbol_position(lsi_query)?;
     let work_done_token = Some(NumberOrString::String("goto definition".to_string()));
     let response = workspace
       .language_server
       .goto_definition(text_document, position, work_done_token)
-      .expect("could not obtain goto definition response");
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support goto definition"))?;
 
     let lsi_query = lsi_query.clone();
     let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/definition");
+    let cancelled = self.deadlines.token(&lsi_query);
     tokio::spawn(async move {
-      let result = response.await;
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
       let result = result
-        .map(|value: serde_json::Value| serde_json::to_string_pretty(&value))
-        .unwrap()
-        .unwrap();
-      Self::send_query_response(&tx, lsi_query, Ok(result));
+        .and_then(|value| Ok(serde_json::to_string_pretty(&value)?))
+        .map_err(|e| anyhow::anyhow!("error getting the definition: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
     });
 
     Ok(())
   }
 
   pub fn goto_symbol_declaration(&self, lsi_query: &LsiQuery) -> anyhow::Result<()> {
-    let workspace = self.get_workspace(lsi_query).unwrap();
-    let symbol_id =
-      TryInto::<[u8; 32]>::try_into(lsi_query.symbol_id.clone().expect("symbol_id not set"))
-        .expect("symbol id has the incorrect number of bytes");
-    let symbol = workspace
-      .query_symbol_by_id(&symbol_id)
-      .unwrap_or_else(|| panic!("could not find symbol with id {:#?}", symbol_id));
-    let text_document =
-      lsp::TextDocumentIdentifier { uri: Url::from_file_path(symbol.file_path.clone()).unwrap() };
-    let position = symbol.selection_range.lock().unwrap().start;
+    let (workspace, _, text_document, position) = self.symbol_position(lsi_query)?;
     let work_done_token = Some(NumberOrString::String("goto declaration".to_string()));
     let response = workspace
       .language_server
       .goto_declaration(text_document, position, work_done_token)
-      .expect("could not obtain goto declaration response");
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support goto declaration"))?;
 
     let lsi_query = lsi_query.clone();
     let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/declaration");
+    let cancelled = self.deadlines.token(&lsi_query);
     tokio::spawn(async move {
-      let result = response.await;
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
       let result = result
-        .map(|value: serde_json::Value| serde_json::to_string_pretty(&value))
-        .unwrap()
-        .unwrap();
-      Self::send_query_response(&tx, lsi_query, Ok(result));
+        .and_then(|value| Ok(serde_json::to_string_pretty(&value)?))
+        .map_err(|e| anyhow::anyhow!("error getting the declaration: {}", e));
+      Self::send_query_response(&tx, lsi_query, result);
     });
 
     Ok(())
@@ -182,7 +340,7 @@ impl LanguageServerInterface {
   }
 
   pub fn lsi_read_symbol_source(&mut self, lsi_query: &LsiQuery) -> anyhow::Result<String> {
-    match self.get_workspace(lsi_query)?.query_symbols(lsi_query) {
+    match self.query_symbols(lsi_query) {
       Ok(symbols) => match symbols.len() {
         0 => Ok("lsp_read_symbol_source: no symbols found".to_string()),
         _ => {
@@ -194,34 +352,114 @@ impl LanguageServerInterface {
     }
   }
 
+  /// replaces the text of a symbol and sends the result once the edit has passed `validation`.
+  /// an edit that does not pass is reverted, and the errors are sent instead. a symbol in a file
+  /// `write_guard` refuses is not replaced
   pub fn lsi_replace_symbol_text(
     &mut self,
     replacement_text: String,
+    validation: EditValidation,
+    write_guard: &WriteGuard,
     lsi_query: &LsiQuery,
-  ) -> anyhow::Result<String> {
+  ) -> anyhow::Result<()> {
     log::info!("lsi_replace_symbol_text: {:?}", lsi_query);
 
-    match &lsi_query.symbol_id {
-      Some(symbol_id) => {
-        let symbol_id: [u8; 32] = TryInto::<[u8; 32]>::try_into(symbol_id.as_slice())?;
-        match self.get_workspace(lsi_query)?.query_symbol_by_id(&symbol_id) {
-          Some(symbol) => {
-            let _new_content = symbol.replace_text(&replacement_text)?;
-            Ok(format!(
-              "symbol text replaced on symbol id {:?} in file {:?}\naffected symbol_ids will be regenerated",
-              symbol.symbol_id,
-              symbol.file_path.display()
-            ))
-          },
-          None => Err(anyhow::anyhow!("no symbol found with id")),
+    let Some(symbol_id) = &lsi_query.symbol_id else {
+      return Err(anyhow::anyhow!("symbol_id not set"));
+    };
+    let symbol_id: [u8; 32] = TryInto::<[u8; 32]>::try_into(symbol_id.as_slice())?;
+    let Some(symbol) = self.get_workspace(lsi_query)?.query_symbol_by_id(&symbol_id) else {
+      return Err(anyhow::anyhow!("no symbol found with id"));
+    };
+    let file_path = symbol.file_path.clone();
+    write_guard
+      .check(&file_path)
+      .map_err(|reason| anyhow::anyhow!("the symbol was not replaced: {}", reason))?;
+    let original = std::fs::read_to_string(&file_path)?;
+    let modified = symbol.replace_text(&replacement_text)?;
+
+    if validation.syntax {
+      if let Some(errors) = edit_validation::new_syntax_errors(&file_path, &original, &modified) {
+        if !errors.is_empty() {
+          std::fs::write(&file_path, &original)?;
+          return Err(anyhow::anyhow!(
+            "{}the replacement was reverted",
+            edit_validation::describe_syntax_errors(&file_path, &errors)
+          ));
         }
-      },
-      None => Err(anyhow::anyhow!("symbol_id not set")),
+      }
+    }
+
+    let edit = FileEdit {
+      tool_call_id: lsi_query.tool_call_id.clone(),
+      path: file_path.clone(),
+      original: Some(original),
+      modified,
+    };
+    let response = format!(
+      "symbol text replaced on symbol id {:?} in file {:?}\naffected symbol_ids will be regenerated",
+      symbol.symbol_id,
+      file_path.display()
+    );
+    let is_rust = file_path.extension().is_some_and(|extension| extension == "rs");
+    let tx = self.tx.clone();
+    let lsi_query = lsi_query.clone();
+    if !(validation.cargo_check && is_rust) {
+      let _ = tx.send(LsiAction::SessionAction(Box::new(SessionAction::FileEdited(
+        lsi_query.session_id,
+        edit,
+      ))));
+      Self::send_query_response(&tx, lsi_query, Ok(response));
+      return Ok(());
     }
+
+    // cargo check takes a while, the edit is kept or reverted once it is done. the edit is
+    // written, so it is not cancelled by the deadline of the call, cargo check has its own
+    self.deadlines.finish(&lsi_query.tool_call_id);
+    tokio::spawn(async move {
+      let result = match edit_validation::cargo_check(&lsi_query.workspace_root).await {
+        Ok(None) => {
+          let _ = tx.send(LsiAction::SessionAction(Box::new(SessionAction::FileEdited(
+            lsi_query.session_id,
+            edit,
+          ))));
+          Ok(response)
+        },
+        // the file may have been changed again while cargo check ran, that change is kept
+        Ok(Some(errors)) if !edit.is_current() => Err(anyhow::anyhow!(
+          "cargo check failed after the replacement, which was kept since {} changed again:\n{}",
+          edit.path.display(),
+          errors
+        )),
+        Ok(Some(errors)) => match edit.revert() {
+          Ok(()) => Err(anyhow::anyhow!(
+            "cargo check failed after the replacement, which was reverted:\n{}",
+            errors
+          )),
+          Err(e) => Err(anyhow::anyhow!(
+            "cargo check failed after the replacement, and reverting it failed: {}\n{}",
+            e,
+            errors
+          )),
+        },
+        Err(e) => {
+          let _ = tx.send(LsiAction::SessionAction(Box::new(SessionAction::FileEdited(
+            lsi_query.session_id,
+            edit,
+          ))));
+          Err(anyhow::anyhow!("could not run cargo check, the replacement was kept: {}", e))
+        },
+      };
+      Self::send_query_response(&tx, lsi_query, result);
+    });
+    Ok(())
   }
 
   pub fn lsi_query_workspace_symbols(&mut self, lsi_query: &LsiQuery) -> anyhow::Result<String> {
-    match self.get_workspace(lsi_query)?.query_symbols(lsi_query) {
+    if lsi_query.query.is_some() {
+      return self.page_workspace_symbols(lsi_query);
+    }
+    match self.query_symbols(lsi_query) {
       Ok(symbols) => match symbols.len() {
         0 => Ok("no symbols found".to_string()),
         _ => match serde_json::to_string(
@@ -245,6 +483,324 @@ impl LanguageServerInterface {
     }
   }
 
+  /// answers a symbol query with a page of the matching symbols and the number of matches, so
+  /// the model can ask for the next page at `next_offset`
+  fn page_workspace_symbols(&self, lsi_query: &LsiQuery) -> anyhow::Result<String> {
+    let symbols = self
+      .query_symbols(lsi_query)
+      .map_err(|e| anyhow::anyhow!("error querying workspace symbols: {}", e))?;
+    let total_matches = symbols.len();
+    let offset = lsi_query.offset.unwrap_or(0);
+    let limit = lsi_query.limit.unwrap_or(DEFAULT_SYMBOL_PAGE_SIZE);
+    let page = symbols
+      .into_iter()
+      .skip(offset)
+      .take(limit)
+      .map(|s| {
+        let mut ser = SerializableSourceSymbol::from(s);
+        if !lsi_query.include_source {
+          ser.source_code = None;
+        }
+        ser
+      })
+      .collect::<Vec<_>>();
+
+    match serde_json::to_string(&json!({
+      "total_matches": total_matches,
+      "offset": offset,
+      "next_offset": (offset + limit < total_matches).then_some(offset + limit),
+      "symbols": page,
+    })) {
+      Ok(content) => Ok(content),
+      Err(e) => Err(anyhow::anyhow!("error serializing symbols: {}", e)),
+    }
+  }
+
+  /// the symbols of every workspace that match a symbol query, for the `:symbols` command
+  pub fn search_symbols(&self, query: &str) -> anyhow::Result<Vec<Arc<SourceSymbol>>> {
+    let symbol_query = SymbolQuery::parse(query)?;
+    let symbols = self
+      .workspaces
+      .iter()
+      .flat_map(|workspace| workspace.all_symbols_weak())
+      .filter_map(|symbol| symbol.upgrade())
+      .collect::<Vec<_>>();
+    Ok(symbol_query.filter(symbols))
+  }
+
+  /// the symbols at the top of the tree of a file, for the `:outline` command
+  pub fn file_outline(&self, file_path: &Path) -> anyhow::Result<Vec<Arc<SourceSymbol>>> {
+    let file_path = file_path.canonicalize()?;
+    self
+      .workspaces
+      .iter()
+      .flat_map(|workspace| &workspace.files)
+      .find(|file| file.file_path == file_path)
+      .map(|file| file.file_tree.children.lock().unwrap().clone())
+      .ok_or_else(|| anyhow::anyhow!("{} is not in a workspace", file_path.display()))
+  }
+
+  /// queries every workspace, grouping the matching symbols by workspace. at most
+  /// `lsi_query.limit` symbols are returned in total, shared evenly between workspaces
+  pub fn query_all_workspace_symbols(&mut self, lsi_query: &LsiQuery) -> anyhow::Result<String> {
+    if self.workspaces.is_empty() {
+      return Err(anyhow::anyhow!("no workspaces have been added"));
+    }
+    let limit = lsi_query.limit.unwrap_or(DEFAULT_ALL_WORKSPACE_SYMBOL_LIMIT);
+
+    let mut matches = Vec::new();
+    for workspace in self.workspaces.iter() {
+      match workspace.query_symbols(lsi_query) {
+        Ok(symbols) => matches.push((workspace, symbols)),
+        Err(e) => log::warn!(
+          "error querying symbols in workspace {}: {}",
+          workspace.workspace_path.display(),
+          e
+        ),
+      }
+    }
+
+    // hand out the limit one symbol per workspace at a time, so a large workspace can not
+    // crowd the others out of the results
+    let mut taken = vec![0; matches.len()];
+    let mut remaining = limit;
+    while remaining > 0 {
+      let mut took_any = false;
+      for (taken, (_, symbols)) in taken.iter_mut().zip(matches.iter()) {
+        if remaining > 0 && *taken < symbols.len() {
+          *taken += 1;
+          remaining -= 1;
+          took_any = true;
+        }
+      }
+      if !took_any {
+        break;
+      }
+    }
+
+    let total_matches = matches.iter().map(|(_, symbols)| symbols.len()).sum::<usize>();
+    let workspaces = matches
+      .into_iter()
+      .zip(taken)
+      .map(|((workspace, symbols), taken)| {
+        let match_count = symbols.len();
+        let symbols = symbols
+          .into_iter()
+          .take(taken)
+          .map(|s| {
+            let mut ser = SerializableSourceSymbol::from(s);
+            if !lsi_query.include_source {
+              ser.source_code = None;
+            }
+            ser
+          })
+          .collect::<Vec<_>>();
+        json!({
+          "workspace": workspace.workspace_path,
+          "language": workspace.language_id,
+          "total_matches": match_count,
+          "symbols": symbols,
+        })
+      })
+      .collect::<Vec<_>>();
+
+    match serde_json::to_string(&json!({
+      "total_matches": total_matches,
+      "truncated": total_matches > limit,
+      "workspaces": workspaces,
+    })) {
+      Ok(content) => Ok(content),
+      Err(e) => Err(anyhow::anyhow!("error serializing symbols: {}", e)),
+    }
+  }
+
+  /// summarizes a file using the symbols the language server reported for it. tree-sitter or
+  /// declaration keywords are used instead while the file has not been indexed yet
+  pub fn summarize_file(&self, file_path: &Path, lsi_query: &LsiQuery) -> anyhow::Result<String> {
+    let text = std::fs::read_to_string(file_path)?;
+    let file_path = file_path.canonicalize()?;
+    let symbols =
+      self.get_workspace(lsi_query)?.files.iter().find(|f| f.file_path == file_path).map(|file| {
+        file
+          .file_tree
+          .children
+          .lock()
+          .unwrap()
+          .iter()
+          .map(|symbol| Self::symbol_summary(symbol, &text, true))
+          .collect::<Vec<_>>()
+      });
+    let summary = file_summary::summarize(&file_path, &text, symbols);
+    Ok(serde_json::to_string(&summary)?)
+  }
+
+  /// the numbered lines of the symbol a read names. the start_line and end_line of the read
+  /// narrow the lines of the symbol, so a truncated read can be continued
+  pub fn read_file_symbol(&self, read: &FileRead, lsi_query: &LsiQuery) -> anyhow::Result<String> {
+    let workspace = self.get_workspace(lsi_query)?;
+    let (file_path, start, end) = Self::symbol_lines(workspace, read)?;
+    let text = std::fs::read_to_string(&file_path)?;
+    let name = file_path.strip_prefix(&workspace.workspace_path).unwrap_or(&file_path);
+    file_read::read_lines(
+      &name.display().to_string(),
+      &text,
+      Some(start),
+      Some(end),
+      read.max_bytes,
+    )
+    .map_err(|e| anyhow::anyhow!(e))
+  }
+
+  /// reads the lines of a file or symbol with the inlay hints of the language server written
+  /// into them as comments, see `inlay_hints::annotate_hints`
+  pub fn read_file_with_hints(&self, read: &FileRead, lsi_query: &LsiQuery) -> anyhow::Result<()> {
+    let workspace = self.get_workspace(lsi_query)?;
+    let (file_path, start_line, end_line) = match read.symbol {
+      Some(_) => {
+        let (file_path, start, end) = Self::symbol_lines(workspace, read)?;
+        (file_path, Some(start), Some(end))
+      },
+      None => {
+        let file_path = read
+          .file_path
+          .as_ref()
+          .ok_or_else(|| anyhow::anyhow!("file_path is required unless a symbol is given"))?;
+        (file_path.canonicalize()?, read.start_line, read.end_line)
+      },
+    };
+    let uri = Url::from_file_path(&file_path)
+      .map_err(|_| anyhow::anyhow!("invalid file path: {}", file_path.display()))?;
+    let text = Rope::from_str(&std::fs::read_to_string(&file_path)?);
+    let offset_encoding = workspace.language_server.offset_encoding();
+    let end_of_file = pos_to_lsp_pos(&text, text.len_chars(), offset_encoding);
+    // the hints of the lines read, the lines are 1 based
+    let start = lsp::Position::new(start_line.unwrap_or(1).saturating_sub(1) as u32, 0);
+    let end = match end_line {
+      Some(end_line) if end_line < text.len_lines() => lsp::Position::new(end_line as u32, 0),
+      _ => end_of_file,
+    };
+    if start > end {
+      return Err(anyhow::anyhow!("the read starts past the end of {}", file_path.display()));
+    }
+    let comment = HintComment::for_language(&workspace.language_config);
+    let work_done_token = Some(NumberOrString::String("inlay hints".to_string()));
+    let response = workspace
+      .language_server
+      .text_document_range_inlay_hints(
+        lsp::TextDocumentIdentifier { uri },
+        lsp::Range::new(start, end),
+        work_done_token,
+      )
+      .ok_or_else(|| anyhow::anyhow!("the language server does not support inlay hints"))?;
+    let name = file_path.strip_prefix(&workspace.workspace_path).unwrap_or(&file_path);
+    let name = name.display().to_string();
+    let max_bytes = read.max_bytes;
+
+    let lsi_query = lsi_query.clone();
+    let tx = self.tx.clone();
+    let span = tracing::info_span!("lsp_request", method = "textDocument/inlayHint");
+    let cancelled = self.deadlines.token(&lsi_query);
+    tokio::spawn(async move {
+      let result = tokio::select! {
+        result = response.instrument(span) => result,
+        // the model was told the request timed out
+        _ = cancelled.cancelled() => return,
+      };
+      let result = result
+        .and_then(|value| Ok(serde_json::from_value::<Option<Vec<lsp::InlayHint>>>(value)?))
+        .map_err(|e| anyhow::anyhow!("error getting inlay hints: {}", e))
+        .and_then(|hints| {
+          // the hints only go into the lines they are on, so the lines keep their numbers
+          let whole_file = lsp::Range::new(lsp::Position::new(0, 0), end_of_file);
+          let hints = hints.unwrap_or_default();
+          let annotated =
+            inlay_hints::annotate_hints(&text, whole_file, hints, offset_encoding, &comment);
+          file_read::read_lines(&name, &annotated, start_line, end_line, max_bytes)
+            .map_err(|e| anyhow::anyhow!(e))
+        });
+      Self::send_query_response(&tx, lsi_query, result);
+    });
+
+    Ok(())
+  }
+
+  // the file of the symbol a read names and its 1 based lines, narrowed to the lines the read
+  // names
+  fn symbol_lines(
+    workspace: &Workspace,
+    read: &FileRead,
+  ) -> anyhow::Result<(PathBuf, usize, usize)> {
+    let file_path =
+      read.file_path.as_ref().map(|file_path| file_path.canonicalize()).transpose()?;
+    let symbol = file_read::find_symbol(
+      workspace.all_symbols_weak().iter().filter_map(Weak::upgrade),
+      read.symbol.as_deref().unwrap_or_default(),
+      file_path.as_deref(),
+    )
+    .map_err(|e| anyhow::anyhow!(e))?;
+
+    let range = *symbol.range.lock().unwrap();
+    let (first, last) = (range.start.line as usize + 1, range.end.line as usize + 1);
+    let start = read.start_line.map_or(first, |line| line.clamp(first, last));
+    let end = read.end_line.map_or(last, |line| line.clamp(start, last));
+    Ok((symbol.file_path.clone(), start, end))
+  }
+
+  fn symbol_summary(symbol: &SourceSymbol, text: &str, include_children: bool) -> SymbolSummary {
+    let line = symbol.selection_range.lock().unwrap().start.line as usize;
+    let children = match include_children {
+      true => symbol
+        .children
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|child| Self::symbol_summary(child, text, false))
+        .collect(),
+      false => vec![],
+    };
+    SymbolSummary {
+      kind: format!("{:?}", symbol.kind),
+      name: symbol.name.clone(),
+      signature: file_summary::signature_at(text, line),
+      line: line + 1,
+      children,
+    }
+  }
+
+  /// the symbols matching the query in its workspace. a workspace without a language server gets
+  /// its symbols from tree-sitter tags queries instead, in the same form
+  fn query_symbols(&self, lsi_query: &LsiQuery) -> anyhow::Result<Vec<Arc<SourceSymbol>>> {
+    if let Ok(workspace) = self.get_workspace(lsi_query) {
+      return workspace.query_symbols(lsi_query);
+    }
+    let files = indexable_files(&lsi_query.workspace_root)
+      .iter()
+      .filter_map(|path| WorkspaceFile::from_treesitter(path, &lsi_query.workspace_root))
+      .collect::<Vec<_>>();
+    query_file_symbols(&files, lsi_query)
+  }
+
+  // the workspace of a query, and the symbol it names by id with the document and position that
+  // requests about the symbol are made at
+  fn symbol_position(
+    &self,
+    lsi_query: &LsiQuery,
+  ) -> anyhow::Result<(&Workspace, Arc<SourceSymbol>, lsp::TextDocumentIdentifier, lsp::Position)>
+  {
+    let workspace = self.get_workspace(lsi_query)?;
+    let symbol_id =
+      lsi_query.symbol_id.clone().ok_or_else(|| anyhow::anyhow!("symbol_id not set"))?;
+    let symbol_id = <[u8; 32]>::try_from(symbol_id)
+      .map_err(|_| anyhow::anyhow!("symbol_id has the incorrect number of bytes"))?;
+    let symbol = workspace
+      .query_symbol_by_id(&symbol_id)
+      .ok_or_else(|| anyhow::anyhow!("no symbol found with symbol_id {:?}", symbol_id))?;
+    let uri = Url::from_file_path(&symbol.file_path)
+      .map_err(|_| anyhow::anyhow!("invalid file path: {}", symbol.file_path.display()))?;
+    let position = symbol.selection_range.lock().unwrap().start;
+    Ok((workspace, symbol, lsp::TextDocumentIdentifier { uri }, position))
+  }
+
   fn get_workspace(&self, lsi_query: &LsiQuery) -> anyhow::Result<&Workspace> {
     match self.workspaces.iter().find(|w| w.workspace_path == lsi_query.workspace_root) {
       Some(workspace) => Ok(workspace),
@@ -252,3 +808,131 @@ impl LanguageServerInterface {
     }
   }
 }
+
+// the occurrences of a symbol in its file in the order they appear, each with the line it is on
+// and whether it reads or writes the symbol
+fn describe_highlights(
+  file_path: &Path,
+  contents: &str,
+  mut highlights: Vec<DocumentHighlight>,
+) -> serde_json::Value {
+  highlights.sort_by_key(|highlight| highlight.range.start);
+  let lines = contents.lines().collect::<Vec<_>>();
+  let occurrences = highlights
+    .into_iter()
+    .map(|highlight| {
+      let kind = match highlight.kind {
+        Some(lsp::DocumentHighlightKind::READ) => "read",
+        Some(lsp::DocumentHighlightKind::WRITE) => "write",
+        _ => "text",
+      };
+      let line = lines.get(highlight.range.start.line as usize).map(|line| line.trim());
+      json!({ "range": highlight.range, "kind": kind, "line": line })
+    })
+    .collect::<Vec<_>>();
+  json!({ "file": file_path, "count": occurrences.len(), "occurrences": occurrences })
+}
+
+// the signatures of a call with the label and documentation of each parameter, and the signature
+// and parameter the position is at
+fn describe_signature_help(help: lsp::SignatureHelp) -> serde_json::Value {
+  let documentation = |documentation: Option<lsp::Documentation>| match documentation {
+    Some(lsp::Documentation::String(text)) => Some(text),
+    Some(lsp::Documentation::MarkupContent(content)) => Some(content.value),
+    None => None,
+  };
+  let signatures = help
+    .signatures
+    .into_iter()
+    .map(|signature| {
+      let parameters = signature
+        .parameters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|parameter| {
+          let label = match parameter.label {
+            lsp::ParameterLabel::Simple(label) => label,
+            // offsets into the label of the signature, in utf-16 code units
+            lsp::ParameterLabel::LabelOffsets([start, end]) => {
+              let units = signature.label.encode_utf16().collect::<Vec<_>>();
+              units
+                .get(start as usize..end as usize)
+                .map(String::from_utf16_lossy)
+                .unwrap_or_default()
+            },
+          };
+          json!({ "label": label, "documentation": documentation(parameter.documentation) })
+        })
+        .collect::<Vec<_>>();
+      json!({
+        "label": signature.label,
+        "documentation": documentation(signature.documentation),
+        "parameters": parameters,
+        "active_parameter": signature.active_parameter,
+      })
+    })
+    .collect::<Vec<_>>();
+  json!({
+    "active_signature": help.active_signature.unwrap_or(0),
+    "active_parameter": help.active_parameter,
+    "signatures": signatures,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_describe_highlights() {
+    let highlight = |line: u32, kind: Option<lsp::DocumentHighlightKind>| DocumentHighlight {
+      range: lsp::Range::new(lsp::Position::new(line, 4), lsp::Position::new(line, 9)),
+      kind,
+    };
+    let contents = "fn main() {\n    let count = 0;\n    count += 1;\n    print(count);\n}";
+    let described = describe_highlights(
+      Path::new("/work/src/main.rs"),
+      contents,
+      vec![
+        highlight(3, Some(lsp::DocumentHighlightKind::READ)),
+        highlight(1, Some(lsp::DocumentHighlightKind::WRITE)),
+        highlight(2, None),
+      ],
+    );
+    assert_eq!(described["count"], 3);
+    let occurrences = described["occurrences"].as_array().unwrap();
+    let kinds = occurrences.iter().map(|o| o["kind"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(kinds, ["write", "text", "read"]);
+    assert_eq!(occurrences[0]["line"], "let count = 0;");
+  }
+
+  #[test]
+  fn test_describe_signature_help() {
+    let help = lsp::SignatureHelp {
+      signatures: vec![lsp::SignatureInformation {
+        label: "fn resize(width: u16, height: u16)".to_string(),
+        documentation: Some(lsp::Documentation::String("resizes the view".to_string())),
+        parameters: Some(vec![
+          lsp::ParameterInformation {
+            label: lsp::ParameterLabel::LabelOffsets([10, 20]),
+            documentation: None,
+          },
+          lsp::ParameterInformation {
+            label: lsp::ParameterLabel::Simple("height: u16".to_string()),
+            documentation: None,
+          },
+        ]),
+        active_parameter: None,
+      }],
+      active_signature: None,
+      active_parameter: Some(1),
+    };
+    let described = describe_signature_help(help);
+    assert_eq!(described["active_signature"], 0);
+    assert_eq!(described["active_parameter"], 1);
+    let signature = &described["signatures"][0];
+    assert_eq!(signature["documentation"], "resizes the view");
+    assert_eq!(signature["parameters"][0]["label"], "width: u16");
+    assert_eq!(signature["parameters"][1]["label"], "height: u16");
+  }
+}
@@ -1,4 +1,5 @@
 use super::query::LsiQuery;
+use super::symbol_query::SymbolQuery;
 use super::symbol_types::SourceSymbol;
 use super::workspace_file::WorkspaceFile;
 use helix_core::syntax::{FileType, LanguageConfiguration};
@@ -89,62 +90,7 @@ impl Workspace {
   }
 
   pub fn query_symbols(&self, query: &LsiQuery) -> anyhow::Result<Vec<Arc<SourceSymbol>>> {
-    log::info!(
-      "query_symbols: {:?}\nsymbolcount: {}\nupgradeable symbols: {}",
-      query,
-      self.all_symbols_weak().len(),
-      self.all_symbols_weak().iter().flat_map(|s| s.upgrade()).count()
-    );
-
-    if let Some(regex) = &query.file_path_regex {
-      let regex = regex::Regex::new(regex).unwrap();
-
-      if !self.files.iter().any(|f| {
-        let file_path = f.file_path.to_str().unwrap();
-        log::warn!("\nfile_path: {:?}\nregex: {:?}", file_path, regex);
-        regex.is_match(file_path)
-      }) {
-        return Err(anyhow::anyhow!("no files match the provided regex\nregex: {:?}", regex));
-      }
-    }
-
-    let symbols =
-      self
-        .all_symbols_weak()
-        .iter()
-        .flat_map(|s| s.upgrade())
-        .filter(|s| {
-          if let Some(symbol_id) = &query.symbol_id {
-            s.symbol_id == symbol_id.as_slice()
-          } else {
-            true
-          }
-        })
-        .filter(|s| {
-          if let Some(file_name) = &query.file_path_regex {
-            s.file_path.file_name().unwrap().to_str().unwrap() == file_name
-              || &s.file_path.display().to_string() == file_name
-          } else {
-            true
-          }
-        })
-        .filter(|s| {
-          if let Some(name) = query.name_regex.clone() {
-            s.name.contains(&name)
-          } else {
-            true
-          }
-        })
-        .filter(|s| if let Some(kind) = query.kind { s.kind == kind } else { true })
-        .filter(|s| {
-          if let Some(range) = query.range {
-            *s.range.lock().unwrap() == range
-          } else {
-            true
-          }
-        })
-        .collect::<Vec<_>>();
-    Ok(symbols)
+    query_file_symbols(&self.files, query)
   }
 
   pub fn all_symbols_weak(&self) -> Vec<Weak<SourceSymbol>> {
@@ -163,3 +109,74 @@ impl Workspace {
     self.all_symbols_weak().len()
   }
 }
+
+/// the symbols of `files` that match the query, whether they came from a language server or from
+/// tree-sitter
+pub fn query_file_symbols(
+  files: &[WorkspaceFile],
+  query: &LsiQuery,
+) -> anyhow::Result<Vec<Arc<SourceSymbol>>> {
+  let all_symbols_weak =
+    files.iter().flat_map(|file| file.symbol_list.iter().cloned()).collect::<Vec<_>>();
+  log::info!(
+    "query_symbols: {:?}\nsymbolcount: {}\nupgradeable symbols: {}",
+    query,
+    all_symbols_weak.len(),
+    all_symbols_weak.iter().flat_map(|s| s.upgrade()).count()
+  );
+
+  if let Some(regex) = &query.file_path_regex {
+    let regex = regex::Regex::new(regex).unwrap();
+
+    if !files.iter().any(|f| {
+      let file_path = f.file_path.to_str().unwrap();
+      log::warn!("\nfile_path: {:?}\nregex: {:?}", file_path, regex);
+      regex.is_match(file_path)
+    }) {
+      return Err(anyhow::anyhow!("no files match the provided regex\nregex: {:?}", regex));
+    }
+  }
+
+  let symbols = all_symbols_weak
+    .iter()
+    .flat_map(|s| s.upgrade())
+    .filter(|s| {
+      if let Some(symbol_id) = &query.symbol_id {
+        s.symbol_id == symbol_id.as_slice()
+      } else {
+        true
+      }
+    })
+    .filter(|s| {
+      if let Some(file_name) = &query.file_path_regex {
+        s.file_path.file_name().unwrap().to_str().unwrap() == file_name
+          || &s.file_path.display().to_string() == file_name
+      } else {
+        true
+      }
+    })
+    .filter(
+      |s| {
+        if let Some(name) = query.name_regex.clone() {
+          s.name.contains(&name)
+        } else {
+          true
+        }
+      },
+    )
+    .filter(|s| if let Some(kind) = query.kind { s.kind == kind } else { true })
+    .filter(
+      |s| {
+        if let Some(range) = query.range {
+          *s.range.lock().unwrap() == range
+        } else {
+          true
+        }
+      },
+    )
+    .collect::<Vec<_>>();
+  match &query.query {
+    Some(symbol_query) => Ok(SymbolQuery::parse(symbol_query)?.filter(symbols)),
+    None => Ok(symbols),
+  }
+}
@@ -1,4 +1,5 @@
 use super::symbol_types::{DocumentChange, SourceSymbol};
+use crate::app::treesitter::treesitter_symbols;
 use helix_lsp::OffsetEncoding;
 use lsp_types as lsp;
 use ropey::Rope;
@@ -37,6 +38,17 @@ impl WorkspaceFile {
     }
   }
 
+  /// a file with symbols from its grammar's tags query, for workspaces without a language
+  /// server. none for files without a tags query
+  pub fn from_treesitter(file_path: &Path, workspace_path: &Path) -> Option<Self> {
+    let file_path = file_path.canonicalize().ok()?;
+    let text = std::fs::read_to_string(&file_path).ok()?;
+    let doc_symbols = treesitter_symbols::document_symbols(&file_path, &text)?;
+    let mut file = WorkspaceFile::new(&file_path, workspace_path, &OffsetEncoding::Utf8);
+    file.update_symbols(doc_symbols).ok()?;
+    Some(file)
+  }
+
   pub fn get_current_contents(&self) -> Rope {
     self.contents.get(&self.version).cloned().expect("No contents found for current version")
   }
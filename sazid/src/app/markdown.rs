@@ -1,3 +1,70 @@
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag};
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+const HTML_CODE_THEME: &str = "InspiredGitHub";
+
+/// parses markdown with the extensions replies use, such as tables, strikethrough and task lists
+pub fn parse(markdown: &str) -> Parser<'_, '_> {
+  Parser::new_ext(markdown, Options::all())
+}
+
+/// renders markdown to html, with fenced code blocks syntax highlighted by their language. the
+/// syntaxes and theme are loaded once, keep a renderer for all the messages of a session
+pub struct HtmlRenderer {
+  syntax_set: SyntaxSet,
+  theme: Theme,
+}
+
+impl Default for HtmlRenderer {
+  fn default() -> Self {
+    let mut theme_set = ThemeSet::load_defaults();
+    HtmlRenderer {
+      syntax_set: SyntaxSet::load_defaults_newlines(),
+      theme: theme_set.themes.remove(HTML_CODE_THEME).unwrap_or_default(),
+    }
+  }
+}
+
+impl HtmlRenderer {
+  pub fn render(&self, markdown: &str) -> String {
+    let mut code_block: Option<(String, String)> = None;
+    let mut events = Vec::new();
+    for event in parse(markdown) {
+      match event {
+        Event::Start(Tag::CodeBlock(kind)) => {
+          let language = match kind {
+            CodeBlockKind::Fenced(language) => language.to_string(),
+            CodeBlockKind::Indented => String::new(),
+          };
+          code_block = Some((language, String::new()));
+        },
+        Event::Text(text) if code_block.is_some() => {
+          code_block.as_mut().unwrap().1.push_str(&text);
+        },
+        Event::End(Tag::CodeBlock(_)) => {
+          let (language, code) = code_block.take().unwrap_or_default();
+          events.push(Event::Html(self.highlight(&code, &language).into()));
+        },
+        event => events.push(event),
+      }
+    }
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events.into_iter());
+    rendered
+  }
+
+  // code of an unknown language is left plain
+  fn highlight(&self, code: &str, language: &str) -> String {
+    let syntax = self
+      .syntax_set
+      .find_syntax_by_token(language)
+      .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+    syntect::html::highlighted_html_for_string(code, &self.syntax_set, syntax, &self.theme)
+      .unwrap_or_else(|_| format!("<pre>{}</pre>", html_escape::encode_text(code)))
+  }
+}
+
 // use helix_term::compositor::{Component, Context};
 // use pulldown_cmark::{
 //   CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag,
@@ -535,3 +602,22 @@
 //   let (content, properties) = unfill(raw);
 //   return format!("{}{}", properties.initial_indent, content);
 // }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_html_renderer_highlights_fenced_code() {
+    let renderer = HtmlRenderer::default();
+    let html =
+      renderer.render("some **bold** text\n\n```rust\nfn main() {}\n```\n\n| a |\n|---|\n| 1 |\n");
+    assert!(html.contains("<strong>bold</strong>"));
+    assert!(!html.contains("<code class=\"language-rust\">"));
+    assert!(html.contains("<pre style="));
+    assert!(html.contains("<table>"));
+
+    let html = renderer.render("```nonexistent\n<tag>\n```");
+    assert!(html.contains("&lt;tag&gt;"));
+  }
+}
@@ -0,0 +1,282 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use super::semantic_search::{format_result, CodeChunk, SearchResult};
+
+const MENTIONS_PROMPT: &str = "The user mentioned these files and symbols in their next message:";
+
+// the most completions offered for a mention being typed
+const MAX_MENTION_COMPLETIONS: usize = 200;
+
+/// a symbol of the workspaces and the lines it is defined on, counted from 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedSymbol {
+  pub name: String,
+  pub file_path: PathBuf,
+  pub start_line: usize,
+  pub end_line: usize,
+}
+
+/// the symbols the language server interface synchronized, shared with the sessions and the
+/// completion of mentions so neither parses the workspace to find a symbol
+pub type SymbolIndex = Arc<ArcSwap<Vec<IndexedSymbol>>>;
+
+/// what the completion of mentions needs of the session shown, kept by the application as the
+/// session or its workspace changes
+#[derive(Debug, Clone, Default)]
+pub struct MentionScope {
+  pub workspace_root: Arc<ArcSwap<PathBuf>>,
+  pub symbols: SymbolIndex,
+}
+
+/// a file or symbol named in a message with `@path/to/file` or `@symbol:Name`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mention {
+  /// relative to the workspace root
+  File(PathBuf),
+  Symbol(String),
+}
+
+/// the mentions in a message, in order and without repeats. a mention starts with an `@` at the
+/// start of a word, so email addresses are not mentions
+pub fn parse_mentions(input: &str) -> Vec<Mention> {
+  let mut mentions = vec![];
+  for mention in input.split_whitespace().filter_map(|word| parse_mention(word.strip_prefix('@')?))
+  {
+    if !mentions.contains(&mention) {
+      mentions.push(mention);
+    }
+  }
+  mentions
+}
+
+fn parse_mention(text: &str) -> Option<Mention> {
+  // punctuation ending a sentence or a clause is not part of the mention
+  let text = text.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '"', '\'', '`']);
+  match text.split_once(':') {
+    Some(("symbol", name)) if !name.is_empty() => Some(Mention::Symbol(name.to_string())),
+    // other prefixes, like @workspace:, are not mentions
+    Some(_) => None,
+    None if is_relative_within(Path::new(text)) => Some(Mention::File(PathBuf::from(text))),
+    None => None,
+  }
+}
+
+// a path that can not leave the directory it is joined to
+fn is_relative_within(path: &Path) -> bool {
+  path.components().next().is_some()
+    && path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// the contents of the files and the definitions of the symbols mentioned in `input`, symbols
+/// are looked up in `symbols`. mentions naming nothing in the workspace are left as text
+pub fn resolve_mentions(
+  input: &str,
+  workspace_root: &Path,
+  symbols: &[IndexedSymbol],
+) -> Vec<SearchResult> {
+  let workspace_symbols = workspace_symbols(symbols, workspace_root).collect::<Vec<_>>();
+  parse_mentions(input)
+    .iter()
+    .filter_map(|mention| match mention {
+      Mention::File(path) => {
+        let path = workspace_root.join(path);
+        let content = std::fs::read_to_string(&path).ok()?;
+        let end_line = content.lines().count();
+        Some(SearchResult { path, chunk: CodeChunk { start_line: 1, end_line, content } })
+      },
+      Mention::Symbol(name) => {
+        let symbol = workspace_symbols.iter().find(|symbol| symbol.name == *name)?;
+        let text = std::fs::read_to_string(&symbol.file_path).ok()?;
+        Some(SearchResult { path: symbol.file_path.clone(), chunk: symbol_chunk(&text, symbol) })
+      },
+    })
+    .collect()
+}
+
+// the indexed symbols defined in the workspace, whose files have canonical paths
+fn workspace_symbols<'a>(
+  symbols: &'a [IndexedSymbol],
+  workspace_root: &Path,
+) -> impl Iterator<Item = &'a IndexedSymbol> {
+  let root = workspace_root.canonicalize().unwrap_or_else(|_| workspace_root.to_path_buf());
+  symbols.iter().filter(move |symbol| symbol.file_path.starts_with(&root))
+}
+
+fn symbol_chunk(text: &str, symbol: &IndexedSymbol) -> CodeChunk {
+  let (start, end) = (symbol.start_line, symbol.end_line.max(symbol.start_line));
+  let content = text.lines().skip(start).take(end + 1 - start).collect::<Vec<_>>().join("\n");
+  CodeChunk { start_line: start + 1, end_line: end + 1, content }
+}
+
+/// the text sent before a message holding what it mentions, none if it mentions nothing that
+/// was found
+pub fn mentions_context(results: &[SearchResult], workspace_root: &Path) -> Option<String> {
+  if results.is_empty() {
+    return None;
+  }
+  let mut context = MENTIONS_PROMPT.to_string();
+  for result in results {
+    context.push_str("\n\n");
+    context.push_str(&format_result(result, workspace_root));
+  }
+  Some(context)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MentionCompletionKind {
+  File,
+  Directory,
+  Symbol,
+}
+
+/// a completion of a mention being typed. `text` replaces the last path segment or the symbol
+/// name typed so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct MentionCompletion {
+  pub text: String,
+  pub kind: MentionCompletionKind,
+  /// the file a symbol is defined in
+  pub detail: Option<String>,
+}
+
+/// the mention being typed at the end of `text`, without its `@`
+pub fn partial_mention(text: &str) -> Option<&str> {
+  let partial = text.rsplit(char::is_whitespace).next()?.strip_prefix('@')?;
+  match partial.split_once(':') {
+    Some(("symbol", _)) | None => Some(partial),
+    Some(_) => None,
+  }
+}
+
+/// the part of a partial mention its completions replace
+pub fn completed_segment(partial: &str) -> &str {
+  match partial.strip_prefix("symbol:") {
+    Some(name) => name,
+    None => partial.rsplit('/').next().unwrap_or(partial),
+  }
+}
+
+/// the entries of the directory of a partial path mention, or the indexed symbols in the
+/// workspace starting with a partial symbol name
+pub fn complete_mention(
+  partial: &str,
+  workspace_root: &Path,
+  symbols: &[IndexedSymbol],
+) -> Vec<MentionCompletion> {
+  let segment = completed_segment(partial);
+  if partial.starts_with("symbol:") {
+    return complete_symbol(segment, workspace_root, symbols);
+  }
+  let dir = &partial[..partial.len() - segment.len()];
+  if !dir.is_empty() && !is_relative_within(Path::new(dir)) {
+    return vec![];
+  }
+  let Ok(entries) = std::fs::read_dir(workspace_root.join(dir)) else {
+    return vec![];
+  };
+  let mut completions = entries
+    .flatten()
+    .filter_map(|entry| {
+      let name = entry.file_name().into_string().ok()?;
+      // hidden entries are offered once their leading dot is typed
+      if !name.starts_with(segment) || (name.starts_with('.') && !segment.starts_with('.')) {
+        return None;
+      }
+      let (text, kind) = match entry.file_type().ok()?.is_dir() {
+        true => (format!("{}/", name), MentionCompletionKind::Directory),
+        false => (name, MentionCompletionKind::File),
+      };
+      Some(MentionCompletion { text, kind, detail: None })
+    })
+    .collect::<Vec<_>>();
+  completions.sort_by(|a, b| a.text.cmp(&b.text));
+  completions.truncate(MAX_MENTION_COMPLETIONS);
+  completions
+}
+
+fn complete_symbol(
+  prefix: &str,
+  workspace_root: &Path,
+  symbols: &[IndexedSymbol],
+) -> Vec<MentionCompletion> {
+  let root = workspace_root.canonicalize().unwrap_or_else(|_| workspace_root.to_path_buf());
+  let mut completions: Vec<MentionCompletion> = vec![];
+  for symbol in workspace_symbols(symbols, workspace_root) {
+    // impls are named by their signature, which is not a name that can be mentioned
+    if symbol.name.starts_with(prefix)
+      && !symbol.name.contains(char::is_whitespace)
+      && !completions.iter().any(|completion| completion.text == symbol.name)
+    {
+      let path = symbol.file_path.strip_prefix(&root).unwrap_or(&symbol.file_path);
+      completions.push(MentionCompletion {
+        text: symbol.name.clone(),
+        kind: MentionCompletionKind::Symbol,
+        detail: Some(path.display().to_string()),
+      });
+      if completions.len() == MAX_MENTION_COMPLETIONS {
+        break;
+      }
+    }
+  }
+  completions
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_mentions() {
+    assert_eq!(
+      parse_mentions(
+        "see @src/lib.rs, @symbol:answer and a@b.c @../secret @workspace:x @src/lib.rs"
+      ),
+      vec![Mention::File(PathBuf::from("src/lib.rs")), Mention::Symbol("answer".to_string())]
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(root.join("src/lib.rs"), "// the answer\npub fn answer() -> u32 {\n  42\n}\n")
+      .unwrap();
+    let outside = IndexedSymbol {
+      name: "answer".to_string(),
+      file_path: PathBuf::from("/elsewhere/lib.rs"),
+      start_line: 0,
+      end_line: 0,
+    };
+    let symbols = [
+      outside,
+      IndexedSymbol {
+        name: "answer".to_string(),
+        file_path: root.canonicalize().unwrap().join("src/lib.rs"),
+        start_line: 1,
+        end_line: 3,
+      },
+    ];
+
+    let input = "why is @symbol:answer in @src/lib.rs? @missing.rs";
+    let results = resolve_mentions(input, root, &symbols);
+    let context = mentions_context(&results, root).unwrap();
+    assert!(context.contains("src/lib.rs:2-4\n```rs\npub fn answer() -> u32 {\n  42\n}\n```"));
+    assert!(context.contains("src/lib.rs:1-4\n```rs\n// the answer\n"));
+    assert_eq!(results.len(), 2);
+
+    assert_eq!(partial_mention("look at @src/li"), Some("src/li"));
+    assert_eq!(partial_mention("look at @src/lib.rs "), None);
+    let texts = |partial: &str| {
+      complete_mention(partial, root, &symbols).into_iter().map(|c| c.text).collect::<Vec<_>>()
+    };
+    assert_eq!(texts(""), vec!["src/"]);
+    assert_eq!(texts("src/l"), vec!["lib.rs"]);
+    assert_eq!(texts("symbol:ans"), vec!["answer"]);
+    assert_eq!(
+      complete_mention("symbol:ans", root, &symbols)[0].detail.as_deref(),
+      Some("src/lib.rs")
+    );
+    assert_eq!(texts("../"), Vec::<String>::new());
+  }
+}
@@ -18,7 +18,7 @@ use async_openai::{
     ChatCompletionRequestMessageContentPart, ChatCompletionRequestSystemMessage,
     ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
     ChatCompletionRequestUserMessageContent, CreateChatCompletionResponse,
-    CreateChatCompletionStreamResponse, FunctionCall, FunctionCallStream, Role,
+    CreateChatCompletionStreamResponse, FinishReason, FunctionCall, FunctionCallStream, Role,
   },
 };
 
@@ -124,6 +124,8 @@ pub struct MessageContainer {
   #[serde(skip)]
   pub rendered_line_count: usize,
   pub message_state: MessageState,
+  #[serde(default)]
+  pub pinned: bool,
 }
 
 fn serialize_message<S>(
@@ -238,6 +240,7 @@ impl From<ReceiveBuffer> for MessageContainer {
       current_transaction_flag: false,
       message_state,
       rendered_line_count: 0,
+      pinned: false,
     }
   }
 }
@@ -391,6 +394,31 @@ impl MessageContainer {
       token_usage: 0,
       message_state: MessageState::empty(),
       rendered_line_count: 0,
+      pinned: false,
+    }
+  }
+
+  /// the model the provider says wrote the message, none for messages it did not write
+  pub fn model(&self) -> Option<&str> {
+    match &self.receive_buffer {
+      Some(ReceiveBuffer::Response(response)) => Some(&response.model),
+      Some(ReceiveBuffer::StreamResponse(srvec)) => srvec.first().map(|r| r.model.as_str()),
+      None => None,
+    }
+  }
+
+  /// why the provider stopped writing the selected choice, none until it has
+  pub fn finish_reason(&self) -> Option<FinishReason> {
+    let index = self.selected_choice as u32;
+    match &self.receive_buffer {
+      Some(ReceiveBuffer::Response(response)) => {
+        response.choices.iter().find(|c| c.index == index).and_then(|c| c.finish_reason)
+      },
+      Some(ReceiveBuffer::StreamResponse(srvec)) => srvec
+        .iter()
+        .flat_map(|response| response.choices.iter())
+        .find_map(|c| if c.index == index { c.finish_reason } else { None }),
+      None => None,
     }
   }
 
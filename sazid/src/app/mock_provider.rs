@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use async_openai::{
+  error::OpenAIError,
+  types::{
+    CreateChatCompletionRequest, CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
+  },
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::errors::SazidError;
+use super::messages::get_chat_message_text;
+
+const MOCK_MODEL: &str = "mock";
+
+/// the answers a mock provider gives, read from a yaml or json script
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct MockScript {
+  /// the answers to the session's requests, in order
+  pub steps: Vec<MockStep>,
+  /// the answer to requests made without tools, like those for titles and summaries
+  #[serde(default = "default_reply")]
+  pub reply: String,
+}
+
+fn default_reply() -> String {
+  "mock reply".to_string()
+}
+
+/// one assistant message of a script
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct MockStep {
+  /// text the last message of the request has to contain, the request fails otherwise. it is
+  /// how a script checks what the tools it called returned
+  #[serde(default)]
+  pub expect: Option<String>,
+  #[serde(default)]
+  pub content: Option<String>,
+  #[serde(default)]
+  pub tool_calls: Vec<MockToolCall>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct MockToolCall {
+  pub name: String,
+  /// the arguments, written in the script as yaml or json and sent to the tool as json
+  #[serde(default)]
+  pub arguments: Value,
+}
+
+/// a provider that answers chat requests with the steps of a script, so the tools a session
+/// runs can be tested without the network
+#[derive(Debug)]
+pub struct MockProvider {
+  steps: Mutex<VecDeque<MockStep>>,
+  reply: String,
+  // numbers the responses and tool calls
+  count: AtomicUsize,
+}
+
+impl MockProvider {
+  pub fn new(script: MockScript) -> Self {
+    MockProvider {
+      steps: Mutex::new(script.steps.into()),
+      reply: script.reply,
+      count: AtomicUsize::new(0),
+    }
+  }
+
+  /// the provider of the script in `path`, json when it ends in .json and yaml otherwise
+  pub fn load(path: &Path) -> Result<Self, SazidError> {
+    let text = std::fs::read_to_string(path)?;
+    let script = match path.extension().and_then(|extension| extension.to_str()) {
+      Some("json") => serde_json::from_str(&text)?,
+      _ => serde_yaml::from_str(&text).map_err(|e| {
+        SazidError::Other(format!("error reading mock script {}: {}", path.display(), e))
+      })?,
+    };
+    Ok(Self::new(script))
+  }
+
+  // the next step of the script, or the reply for requests without tools
+  fn next_step(&self, request: &CreateChatCompletionRequest) -> Result<MockStep, OpenAIError> {
+    if request.tools.is_none() {
+      return Ok(MockStep { content: Some(self.reply.clone()), ..MockStep::default() });
+    }
+    let step = self.steps.lock().unwrap().pop_front().ok_or_else(|| {
+      OpenAIError::InvalidArgument("the mock script has no steps left".to_string())
+    })?;
+    if let Some(expected) = &step.expect {
+      let last = request.messages.last().map(get_chat_message_text).unwrap_or_default();
+      if !last.contains(expected.as_str()) {
+        return Err(OpenAIError::InvalidArgument(format!(
+          "the mock script expected the last message to contain {:?}, it was {:?}",
+          expected, last
+        )));
+      }
+    }
+    Ok(step)
+  }
+
+  // the tool calls of a step with ids and their arguments as json text
+  fn tool_calls(&self, step: &MockStep) -> Vec<Value> {
+    step
+      .tool_calls
+      .iter()
+      .map(|tool_call| {
+        let arguments = match &tool_call.arguments {
+          Value::Null => "{}".to_string(),
+          arguments => arguments.to_string(),
+        };
+        json!({
+          "id": format!("call_{}", self.count.fetch_add(1, Ordering::SeqCst)),
+          "type": "function",
+          "function": { "name": tool_call.name, "arguments": arguments },
+        })
+      })
+      .collect()
+  }
+
+  pub fn create(
+    &self,
+    request: &CreateChatCompletionRequest,
+  ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+    let step = self.next_step(request)?;
+    let tool_calls = self.tool_calls(&step);
+    let finish_reason = if tool_calls.is_empty() { "stop" } else { "tool_calls" };
+    let response = json!({
+      "id": format!("mock-{}", self.count.fetch_add(1, Ordering::SeqCst)),
+      "object": "chat.completion",
+      "created": 0,
+      "model": MOCK_MODEL,
+      "choices": [{
+        "index": 0,
+        "message": {
+          "role": "assistant",
+          "content": step.content,
+          "tool_calls": (!tool_calls.is_empty()).then_some(tool_calls),
+        },
+        "finish_reason": finish_reason,
+      }],
+    });
+    serde_json::from_value(response).map_err(OpenAIError::JSONDeserialize)
+  }
+
+  /// the chunks a streamed answer arrives in: the text, each tool call, then the finish reason
+  pub fn create_stream(
+    &self,
+    request: &CreateChatCompletionRequest,
+  ) -> Result<Vec<CreateChatCompletionStreamResponse>, OpenAIError> {
+    let step = self.next_step(request)?;
+    let tool_calls = self.tool_calls(&step);
+    let id = format!("mock-{}", self.count.fetch_add(1, Ordering::SeqCst));
+    let chunk = |delta: Value, finish_reason: Option<&str>| {
+      serde_json::from_value::<CreateChatCompletionStreamResponse>(json!({
+        "id": id,
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": MOCK_MODEL,
+        "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+      }))
+      .map_err(OpenAIError::JSONDeserialize)
+    };
+    let finish_reason = if tool_calls.is_empty() { "stop" } else { "tool_calls" };
+    let mut chunks = vec![chunk(json!({ "role": "assistant", "content": step.content }), None)?];
+    for (index, mut tool_call) in tool_calls.into_iter().enumerate() {
+      tool_call["index"] = json!(index);
+      chunks.push(chunk(json!({ "tool_calls": [tool_call] }), None)?);
+    }
+    chunks.push(chunk(json!({}), Some(finish_reason))?);
+    Ok(chunks)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_mock_script() {
+    let script: MockScript = serde_yaml::from_str(
+      r#"
+steps:
+  - content: creating it
+    tool_calls:
+      - name: create_file
+        arguments: { path: src/lib.rs, content: "fn a() {}" }
+  - expect: file created
+    content: done
+"#,
+    )
+    .unwrap();
+    let provider = MockProvider::new(script);
+    let request = CreateChatCompletionRequest { tools: Some(vec![]), ..Default::default() };
+
+    let chunks = provider.create_stream(&request).unwrap();
+    assert_eq!(chunks.len(), 3);
+    let tool_call = chunks[1].choices[0].delta.tool_calls.as_ref().unwrap()[0].clone();
+    let function = tool_call.function.unwrap();
+    assert_eq!(function.name.as_deref(), Some("create_file"));
+    let arguments = serde_json::from_str::<Value>(&function.arguments.unwrap()).unwrap();
+    assert_eq!(arguments, json!({ "path": "src/lib.rs", "content": "fn a() {}" }));
+
+    assert!(provider.create(&request).is_err(), "the last message does not say file created");
+    assert!(provider.create(&request).is_err(), "no steps are left");
+    let untooled = CreateChatCompletionRequest::default();
+    let response = provider.create(&untooled).unwrap();
+    assert_eq!(response.choices[0].message.content.as_deref(), Some("mock reply"));
+  }
+}
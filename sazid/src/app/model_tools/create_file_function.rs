@@ -9,6 +9,9 @@ use std::{
 use futures_util::Future;
 use serde::{Deserialize, Serialize};
 
+use crate::action::{ChatToolAction, SessionAction};
+use crate::app::file_patch::FileEdit;
+
 use super::{
   errors::ToolCallError,
   tool_call::{ToolCallParams, ToolCallTrait},
@@ -73,10 +76,19 @@ impl ToolCallTrait for CreateFileFunction {
 
     let path = get_validated_argument::<PathBuf>(&validated_arguments, "path");
     let text = get_validated_argument::<String>(&validated_arguments, "content");
+    let (tx, tool_call_id, session_id) = (params.tx, params.tool_call_id, params.session_id);
     Box::pin(async move {
       if let Some(path) = path {
         if let Some(text) = text {
-          create_file(&path, text.as_str(), false)
+          let existed = path.exists();
+          let result = create_file(&path, text.as_str(), false);
+          if !existed && path.exists() {
+            let edit = FileEdit { tool_call_id, path, original: None, modified: text };
+            let _ = tx.send(ChatToolAction::SessionAction(Box::new(SessionAction::FileEdited(
+              session_id, edit,
+            ))));
+          }
+          result
         } else {
           Err(ToolCallError::new("text argument is required"))
         }
@@ -1,286 +1,343 @@
 use futures_util::Future;
-use grep::{
-  printer::StandardBuilder,
-  regex::RegexMatcher,
-  searcher::{BinaryDetection, SearcherBuilder},
+use globset::GlobSet;
+use grep::matcher::Matcher;
+use grep::regex::{RegexMatcher, RegexMatcherBuilder};
+use grep::searcher::{
+  BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind, SinkMatch,
 };
+use helix_view::editor::FilePickerConfig;
+use ignore::overrides::OverrideBuilder;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 
-use std::{io::BufWriter, path::PathBuf};
-use std::{io::Write, pin::Pin};
-use walkdir::WalkDir;
+use crate::app::workspace_walk::workspace_walk_builder;
 
-use super::{
-  argument_validation::{
-    validate_and_extract_paths_from_argument,
-    validate_and_extract_string_argument,
-  },
-  errors::ToolCallError,
-  tool_call::{ToolCallParams, ToolCallTrait},
-  types::{FunctionProperty, PropertyType},
-};
+use super::errors::ToolCallError;
+use super::path_guard::is_denied;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+const DEFAULT_MAX_RESULTS: usize = 100;
+// longer lines, such as those of minified files, are cut off in the preview
+const MAX_PREVIEW_CHARS: usize = 240;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize)]
 pub struct GrepFunction {
   pub name: String,
   pub description: String,
-  pub properties: Vec<FunctionProperty>,
+  pub parameters: FunctionProperty,
 }
 
-impl Default for GrepFunction {
-  fn default() -> Self {
+/// a search of the files of a workspace for a regular expression
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GrepOptions {
+  pub pattern: String,
+  /// globs of the files to search, a glob starting with `!` leaves files out
+  pub globs: Vec<String>,
+  pub case_insensitive: bool,
+  /// the lines before and after each match that are returned with it
+  pub context_lines: usize,
+  pub max_results: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GrepMatch {
+  pub path: PathBuf,
+  /// 1 based line and column of the start of the match
+  pub line: u64,
+  pub column: usize,
+  pub preview: String,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub before: Vec<String>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GrepResult {
+  pub matches: Vec<GrepMatch>,
+  /// whether the search stopped at `max_results`, with more matches left
+  pub truncated: bool,
+}
+
+impl ToolCallTrait for GrepFunction {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
     GrepFunction {
       name: "grep".to_string(),
-      description: "an implementation of grep".to_string(),
-      properties: vec![
-        FunctionProperty {
-          name: "pattern".to_string(),
-          required: true,
-          property_type: PropertyType::String,
-          description: Some("a regular expression pattern to match against file contents".to_string()),
-          enum_values: None,
-        },
-        FunctionProperty {
-          name: "paths".to_string(),
-          required: true,
-          property_type: PropertyType::String,
-          description: Some(
-            "a list of comma separated paths to walk for files which the pattern will be matched against".to_string(),
+      description: "search the text of the files of the workspace for a regular expression, leaving out the files ignored by git. returns the path, line, column and text of each matching line, with the lines around it when context_lines is set. use it to find text that is not a symbol, such as strings, comments and config keys".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "pattern".to_string(),
+            FunctionProperty::Pattern {
+              required: true,
+              description: Some("a regular expression to match against each line".to_string()),
+            },
           ),
-          enum_values: None,
-        },
-        // todo: implement multi_line
-
-      // CommandProperty {
-      //   name: "multi_line".to_string(),
-      //   required: false,
-      //   property_type: "bool".to_string(),
-      //   description: Some("match pattern over multiple lines. default is false".to_string()),
-      //   enum_values: None,
-      // }
-      ],
+          (
+            "path".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some("the file or directory to search, relative to the workspace root, the root if not set".to_string()),
+            },
+          ),
+          (
+            "globs".to_string(),
+            FunctionProperty::Array {
+              required: false,
+              description: Some("globs of the files to search, such as *.rs. a glob starting with ! leaves the files it matches out".to_string()),
+              items: Box::new(FunctionProperty::String { required: true, description: None }),
+              min_items: None,
+              max_items: None,
+            },
+          ),
+          (
+            "case_insensitive".to_string(),
+            FunctionProperty::Bool {
+              required: false,
+              description: Some("match regardless of case. this defaults to false".to_string()),
+            },
+          ),
+          (
+            "context_lines".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(0),
+              maximum: Some(10),
+              description: Some("the number of lines before and after each match to return with it, 0 if not set".to_string()),
+            },
+          ),
+          (
+            "max_results".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(1),
+              maximum: Some(1000),
+              description: Some(format!("the most matches to return, {} if not set", DEFAULT_MAX_RESULTS)),
+            },
+          ),
+        ]),
+      },
     }
   }
-}
 
-impl ToolCallTrait for GrepFunction {
-  fn init() -> Self {
-    GrepFunction::default()
-  }
   fn name(&self) -> &str {
     &self.name
   }
 
-  fn call(
-    &self,
-    params: ToolCallParams,
-  ) -> Pin<
-    Box<
-      dyn Future<Output = Result<Option<String>, ToolCallError>>
-        + Send
-        + 'static,
-    >,
-  > {
-    let paths = validate_and_extract_paths_from_argument(
-      &params.function_args,
-      params.session_config,
-      true,
-      None,
-    )
-    .unwrap()
-    .unwrap();
-    let pattern = validate_and_extract_string_argument(
-      &params.function_args,
-      "pattern",
-      true,
-    )
-    .unwrap()
-    .unwrap();
-    Box::pin(async move { grep(pattern.as_str(), paths) })
-  }
-
-  fn parameters(&self) -> Vec<FunctionProperty> {
-    self.properties.clone()
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
   }
 
   fn description(&self) -> String {
     self.description.clone()
   }
-}
 
-pub fn grep(
-  pattern: &str,
-  paths: Vec<PathBuf>,
-) -> Result<Option<String>, ToolCallError> {
-  //let mut buffer = Cursor::new(Vec::new());
-  let buffer: BufWriter<Vec<u8>> = BufWriter::new(Vec::new());
-  let mut error_buffer: BufWriter<Vec<u8>> = BufWriter::new(Vec::new());
-  match RegexMatcher::new(pattern) {
-    Ok(matcher) => {
-      let mut searcher = SearcherBuilder::new()
-        .binary_detection(BinaryDetection::quit(b'\x00'))
-        .line_number(false)
-        .build();
-      let mut printer = StandardBuilder::new().build_no_color(buffer);
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .map_err(|e| ToolCallError::new(&e));
+    let denied =
+      params.session_config.tools.path_guard.denied_set().map_err(|e| ToolCallError::new(&e));
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
 
-      for path in paths {
-        for result in WalkDir::new(path) {
-          let dent = match result {
-            Ok(dent) => dent,
-            Err(err) => {
-              error_buffer.write_all(format!("{}\n", err).as_bytes()).unwrap();
-              continue;
-            },
-          };
-          if !dent.file_type().is_file() {
-            continue;
-          }
-          let result = searcher.search_path(
-            &matcher,
-            dent.path(),
-            printer.sink_with_path(&matcher, dent.path()),
-          );
-          if let Err(err) = result {
-            error_buffer
-              .write_all(
-                format!("{}: {}", dent.path().display(), err).as_bytes(),
-              )
-              .unwrap();
-          }
-        }
+    Box::pin(async move {
+      let validated_arguments = validated_arguments?;
+      let denied = denied?;
+      let Some(workspace_root) = workspace_root else {
+        return Err(ToolCallError::new("grep needs a workspace"));
+      };
+      let path = get_validated_argument::<String>(&validated_arguments, "path");
+      let options = GrepOptions {
+        pattern: get_validated_argument::<String>(&validated_arguments, "pattern")
+          .unwrap_or_default(),
+        globs: get_validated_argument::<Vec<String>>(&validated_arguments, "globs")
+          .unwrap_or_default(),
+        case_insensitive: get_validated_argument::<bool>(&validated_arguments, "case_insensitive")
+          .unwrap_or_default(),
+        context_lines: get_validated_argument::<usize>(&validated_arguments, "context_lines")
+          .unwrap_or_default(),
+        max_results: get_validated_argument::<usize>(&validated_arguments, "max_results")
+          .unwrap_or(DEFAULT_MAX_RESULTS),
+      };
+      let search_path = workspace_root.join(path.unwrap_or_default());
+      let canonical_root = workspace_root.canonicalize()?;
+      if !search_path.canonicalize()?.starts_with(&canonical_root) {
+        return Err(ToolCallError::new("cannot search outside of the workspace"));
       }
-      match printer.into_inner().into_inner().into_inner() {
-        Ok(matches) => {
-          if matches.is_empty() {
-            error_buffer
-              .write_all(
-                format!("No matches found for pattern: {}", pattern).as_bytes(),
-              )
-              .unwrap();
-          }
-
-          Ok(Some(
-            String::from_utf8(matches)
-              .unwrap_or_else(|_| "Error parsing grep output text".to_string())
-              + String::from_utf8(error_buffer.into_inner().unwrap())
-                .unwrap_or_else(|_| {
-                  "Error parsing grep output text".to_string()
-                })
-                .as_str(),
-          ))
-        },
-        Err(err) => {
-          error_buffer
-            .write_all(
-              format!("Error parsing grep output text: {:?}", err).as_bytes(),
-            )
-            .unwrap();
-          Ok(Some(
-            String::from_utf8(error_buffer.into_inner().unwrap()).unwrap(),
-          ))
-        },
+      // the walk reads every file under the path, which would hold up a runtime worker
+      let result =
+        tokio::task::spawn_blocking(move || grep(&workspace_root, &search_path, &options, &denied))
+          .await
+          .map_err(|e| ToolCallError::new(&format!("grep task failed: {}", e)))?;
+      match result {
+        Ok(result) => Ok(Some(serde_json::to_string(&result).unwrap())),
+        Err(e) => Ok(Some(e)),
       }
-    },
-    Err(err) => Ok(Some(format!("Error parsing grep pattern: {:?}", err))),
+    })
   }
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use std::error::Error;
-  use std::fs::File;
-  use std::io::Write;
-  use std::path::PathBuf;
-  use tempfile::tempdir;
+/// the lines of the files under `search_path` that match the options, with their paths relative
+/// to `root`. files are walked in order of their names, leaving out those ignored by git and the
+/// file picker, binary files and the files `denied` matches
+pub fn grep(
+  root: &Path,
+  search_path: &Path,
+  options: &GrepOptions,
+  denied: &GlobSet,
+) -> Result<GrepResult, String> {
+  let matcher = RegexMatcherBuilder::new()
+    .case_insensitive(options.case_insensitive)
+    .build(&options.pattern)
+    .map_err(|e| format!("invalid pattern {}: {}", options.pattern, e))?;
+  let mut searcher = SearcherBuilder::new()
+    .binary_detection(BinaryDetection::quit(b'\x00'))
+    .line_number(true)
+    .before_context(options.context_lines)
+    .after_context(options.context_lines)
+    .build();
 
-  // Helper function to write to a file
-  fn write_to_file(
-    file_path: &PathBuf,
-    content: &str,
-  ) -> Result<(), std::io::Error> {
-    let mut file = File::create(file_path)?;
-    file.write_all(content.as_bytes())?;
-    Ok(())
+  let mut walk_builder = workspace_walk_builder(search_path, &FilePickerConfig::default());
+  if !options.globs.is_empty() {
+    let mut overrides = OverrideBuilder::new(search_path);
+    for glob in &options.globs {
+      overrides.add(glob).map_err(|e| format!("invalid glob {}: {}", glob, e))?;
+    }
+    walk_builder.overrides(overrides.build().map_err(|e| e.to_string())?);
   }
 
-  #[test]
-  fn test_grep_basic_single_line_match() -> Result<(), Box<dyn Error>> {
-    let dir = tempdir()?;
-    let file_path = dir.path().join("single_line.txt");
-    write_to_file(&file_path, "Hello\nRust\nWorld")?;
-
-    let pattern = "Rust";
-    let paths = vec![file_path];
-    let result = grep(pattern, paths)?;
-
-    assert!(result.is_some());
-    assert!(result.unwrap().contains("Rust"));
-    Ok(())
+  let mut result = GrepResult::default();
+  for entry in walk_builder.build().flatten() {
+    if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+      continue;
+    }
+    let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+    if is_denied(denied, path) {
+      continue;
+    }
+    let mut sink = MatchSink {
+      matcher: &matcher,
+      path,
+      max_results: options.max_results,
+      result: &mut result,
+      before: vec![],
+    };
+    // a file that cannot be read is left out, like an ignored one
+    let _ = searcher.search_path(&matcher, entry.path(), &mut sink);
+    if result.truncated {
+      break;
+    }
   }
+  Ok(result)
+}
 
-  #[test]
-  fn test_grep_basic_multi_line_match() -> Result<(), Box<dyn Error>> {
-    let dir = tempdir()?;
-    let file_path = dir.path().join("multi_line.txt");
-    write_to_file(&file_path, "Hello\nRust Language\nWorld\nRust Programming")?;
+// collects the matches of a file, each with the context lines around it
+struct MatchSink<'a> {
+  matcher: &'a RegexMatcher,
+  path: &'a Path,
+  max_results: usize,
+  result: &'a mut GrepResult,
+  before: Vec<String>,
+}
 
-    let pattern = "Rust.*";
-    let paths = vec![file_path];
-    let result = grep(pattern, paths)?;
+impl Sink for MatchSink<'_> {
+  type Error = std::io::Error;
 
-    assert!(result.is_some());
-    let content = result.unwrap();
-    assert!(content.contains("Rust Language"));
-    assert!(content.contains("Rust Programming"));
-    Ok(())
+  fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch) -> Result<bool, Self::Error> {
+    // the search goes on past the last match, for the context lines after it
+    if self.result.matches.len() >= self.max_results {
+      self.result.truncated = true;
+      return Ok(false);
+    }
+    let bytes = mat.bytes();
+    let start = self.matcher.find(bytes).ok().flatten().map_or(0, |found| found.start());
+    self.result.matches.push(GrepMatch {
+      path: self.path.to_path_buf(),
+      line: mat.line_number().unwrap_or_default(),
+      column: String::from_utf8_lossy(&bytes[..start]).chars().count() + 1,
+      preview: preview(bytes),
+      before: std::mem::take(&mut self.before),
+      after: vec![],
+    });
+    Ok(true)
   }
 
-  #[test]
-  fn test_grep_pattern_not_found() -> Result<(), Box<dyn Error>> {
-    let dir = tempdir()?;
-    let file_path = dir.path().join("not_found.txt");
-    write_to_file(&file_path, "Hello\nRust\nWorld")?;
+  fn context(&mut self, _searcher: &Searcher, context: &SinkContext) -> Result<bool, Self::Error> {
+    let line = preview(context.bytes());
+    match (context.kind(), self.result.matches.last_mut()) {
+      (SinkContextKind::After, Some(last)) => last.after.push(line),
+      _ => self.before.push(line),
+    }
+    Ok(true)
+  }
+}
 
-    let pattern = "Nonexistent";
-    let paths = vec![file_path];
-    let result = grep(pattern, paths)?;
+fn preview(bytes: &[u8]) -> String {
+  let line = String::from_utf8_lossy(bytes);
+  line.trim_end_matches(['\r', '\n']).chars().take(MAX_PREVIEW_CHARS).collect()
+}
 
-    assert!(result.is_some());
-    assert!(result
-      .unwrap()
-      .contains("No matches found for pattern: Nonexistent"));
-    Ok(())
-  }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::model_tools::path_guard::PathGuardConfig;
 
   #[test]
-  fn test_grep_invalid_pattern() -> Result<(), Box<dyn Error>> {
-    let dir = tempdir()?;
-    let file_path = dir.path().join("invalid_pattern.txt");
-    write_to_file(&file_path, "Hello\nRust\nWorld")?;
+  fn test_grep() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), "fn main() {\n  let rust = 1;\n}\n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "Rust\nRust Language\n").unwrap();
+    std::fs::write(dir.path().join("binary.bin"), [0, 82, 117, 115, 116]).unwrap();
+    std::fs::write(dir.path().join("src/secret.pem"), "Rust private key\n").unwrap();
+    let denied = PathGuardConfig::default().denied_set().unwrap();
+    let options = |pattern: &str| GrepOptions {
+      pattern: pattern.to_string(),
+      max_results: DEFAULT_MAX_RESULTS,
+      ..Default::default()
+    };
+    let search = |options: &GrepOptions| grep(dir.path(), dir.path(), options, &denied);
 
-    let pattern = "[Unclosed bracket";
-    let paths = vec![file_path];
-    let result = grep(pattern, paths)?;
+    // the denied secret.pem is left out
+    let result = search(&options("Rust")).unwrap();
+    let found = result.matches.iter().map(|m| (m.path.clone(), m.line)).collect::<Vec<_>>();
+    assert_eq!(found, vec![(PathBuf::from("notes.txt"), 1), (PathBuf::from("notes.txt"), 2)]);
 
-    assert!(result.is_some());
-    assert!(result.unwrap().contains("Error parsing grep pattern:"));
-    Ok(())
-  }
-
-  #[test]
-  fn test_grep_with_binary_file() -> Result<(), Box<dyn Error>> {
-    let dir = tempdir()?;
-    let file_path = dir.path().join("binary_file.bin");
-    let mut file = File::create(&file_path)?;
-    file.write_all(&[0, 159, 146, 150])?; // Some arbitrary non-text bytes
+    let result = search(&GrepOptions {
+      globs: vec!["*.rs".to_string()],
+      case_insensitive: true,
+      context_lines: 1,
+      ..options("rust")
+    })
+    .unwrap();
+    assert_eq!(
+      result.matches,
+      vec![GrepMatch {
+        path: PathBuf::from("src/main.rs"),
+        line: 2,
+        column: 7,
+        preview: "  let rust = 1;".to_string(),
+        before: vec!["fn main() {".to_string()],
+        after: vec!["}".to_string()],
+      }]
+    );
 
-    let pattern = ".*";
-    let paths = vec![file_path];
-    let result = grep(pattern, paths)?;
+    let result = search(&GrepOptions { max_results: 1, ..options("Rust") });
+    assert_eq!(result.as_ref().map(|r| (r.matches.len(), r.truncated)), Ok((1, true)));
+    assert!(search(&options("Nonexistent")).unwrap().matches.is_empty());
+    assert!(search(&options("[Unclosed")).is_err());
 
-    assert!(result.is_some());
-    assert!(result.unwrap().contains("No matches found for pattern: .*"));
-    Ok(())
+    let unguarded = PathGuardConfig { enabled: false, ..Default::default() };
+    let result =
+      grep(dir.path(), dir.path(), &options("private"), &unguarded.denied_set().unwrap()).unwrap();
+    assert_eq!(result.matches[0].path, PathBuf::from("src/secret.pem"));
   }
 }
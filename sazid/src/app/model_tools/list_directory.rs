@@ -0,0 +1,191 @@
+use futures_util::Future;
+use helix_view::editor::FilePickerConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use crate::app::workspace_walk::workspace_walk_builder;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+const DEFAULT_MAX_DEPTH: usize = 3;
+const DEFAULT_MAX_ENTRIES: usize = 400;
+
+#[derive(Serialize, Deserialize)]
+pub struct ListDirectory {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+struct DirectoryStats {
+  files: usize,
+  bytes: u64,
+}
+
+impl ToolCallTrait for ListDirectory {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    ListDirectory {
+      name: "list_directory".to_string(),
+      description: "list a directory of the workspace as a tree, leaving out the files ignored by git and the file picker. files are shown with their size, directories with the number of files and bytes below them, also below the depth listed. use it to find your way around a workspace before searching it".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "path".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some(
+                "the directory to list, relative to the workspace root, the root if not set"
+                  .to_string(),
+              ),
+            },
+          ),
+          (
+            "max_depth".to_string(),
+            FunctionProperty::Integer {
+              minimum: Some(1),
+              maximum: Some(16),
+              required: false,
+              description: Some(format!(
+                "how many levels of directories to list, {} if not set",
+                DEFAULT_MAX_DEPTH
+              )),
+            },
+          ),
+        ]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .map_err(|e| ToolCallError::new(&e));
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
+
+    Box::pin(async move {
+      let validated_arguments = validated_arguments?;
+      let path = get_validated_argument::<String>(&validated_arguments, "path");
+      let max_depth = get_validated_argument::<usize>(&validated_arguments, "max_depth")
+        .unwrap_or(DEFAULT_MAX_DEPTH);
+      let Some(workspace_root) = workspace_root else {
+        return Err(ToolCallError::new("list_directory needs a workspace"));
+      };
+      let dir = workspace_root.join(path.unwrap_or_default());
+      let canonical_root = workspace_root.canonicalize()?;
+      if !dir.canonicalize()?.starts_with(&canonical_root) {
+        return Err(ToolCallError::new("cannot list directories outside of the workspace"));
+      }
+      Ok(Some(directory_tree(&dir, max_depth, DEFAULT_MAX_ENTRIES)))
+    })
+  }
+}
+
+/// the files and directories under `dir` to `max_depth` as an indented tree of at most
+/// `max_entries` lines, with the size of each file and the number of files and bytes under each
+/// directory
+pub fn directory_tree(dir: &Path, max_depth: usize, max_entries: usize) -> String {
+  let mut entries: Vec<(PathBuf, usize, Option<u64>)> = vec![];
+  let mut stats: HashMap<PathBuf, DirectoryStats> = HashMap::new();
+  for entry in workspace_walk_builder(dir, &FilePickerConfig::default()).build().flatten() {
+    let is_file = entry.file_type().is_some_and(|file_type| file_type.is_file());
+    let size = is_file.then(|| entry.metadata().map(|m| m.len()).unwrap_or(0));
+    if let Some(size) = size {
+      // the file counts towards every directory above it
+      for ancestor in entry.path().ancestors().skip(1) {
+        let dir_stats = stats.entry(ancestor.to_path_buf()).or_default();
+        dir_stats.files += 1;
+        dir_stats.bytes += size;
+        if ancestor == dir {
+          break;
+        }
+      }
+    }
+    if entry.depth() <= max_depth {
+      entries.push((entry.path().to_path_buf(), entry.depth(), size));
+    }
+  }
+
+  let mut lines = vec![];
+  for (path, depth, size) in entries.iter().take(max_entries) {
+    let name = match depth {
+      0 => path.display().to_string(),
+      _ => path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+    };
+    let indent = "  ".repeat(*depth);
+    lines.push(match size {
+      Some(size) => format!("{}{} ({})", indent, name, format_size(*size)),
+      None => {
+        let dir_stats = stats.get(path).copied().unwrap_or_default();
+        format!("{}{}/ ({} files, {})", indent, name, dir_stats.files, format_size(dir_stats.bytes))
+      },
+    });
+  }
+  if entries.len() > max_entries {
+    lines.push(format!(
+      "{} more entries not listed, list a subdirectory or fewer levels",
+      entries.len() - max_entries
+    ));
+  }
+  lines.join("\n")
+}
+
+fn format_size(bytes: u64) -> String {
+  match bytes {
+    0..=1023 => format!("{} B", bytes),
+    1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+    _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_directory_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src/app")).unwrap();
+    std::fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(dir.path().join("src/app/session.rs"), vec![b'a'; 2048]).unwrap();
+    std::fs::write(dir.path().join("src/archive.zip"), "zip").unwrap();
+
+    let tree = directory_tree(dir.path(), 2, 100);
+    let lines = tree.lines().skip(1).collect::<Vec<_>>();
+    assert_eq!(
+      lines,
+      vec![
+        "  Cargo.toml (9 B)",
+        "  src/ (2 files, 2.0 KB)",
+        "    app/ (1 files, 2.0 KB)",
+        "    main.rs (12 B)",
+      ]
+    );
+    assert!(tree.lines().next().unwrap().ends_with("/ (3 files, 2.0 KB)"));
+
+    let tree = directory_tree(dir.path(), 2, 2);
+    assert!(tree.ends_with("3 more entries not listed, list a subdirectory or fewer levels"));
+  }
+}
@@ -79,6 +79,7 @@ impl ToolCallTrait for LspGetDiagnostics {
     &self,
     params: ToolCallParams,
   ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
     let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
       .expect("error validating arguments");
 
@@ -106,6 +107,7 @@ impl ToolCallTrait for LspGetDiagnostics {
         file_path_regex,
         tool_call_id: params.tool_call_id,
         session_id: params.session_id,
+        timeout,
         diagnostic_severity: Some(DiagnosticIncludeFlags {
           include_errors,
           include_warnings,
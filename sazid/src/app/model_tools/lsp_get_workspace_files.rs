@@ -55,6 +55,7 @@ impl ToolCallTrait for LspGetWorkspaceFiles {
     &self,
     params: ToolCallParams,
   ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
     log::info!("LspGetWorkspaceFiles::call");
 
     let pattern =
@@ -67,6 +68,7 @@ impl ToolCallTrait for LspGetWorkspaceFiles {
     let lsi_query = LsiQuery {
       workspace_root,
       session_id: params.session_id,
+      timeout,
       tool_call_id: params.tool_call_id,
       file_path_regex: pattern.map(|p| p.to_string()),
       ..Default::default()
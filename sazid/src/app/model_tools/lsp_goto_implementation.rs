@@ -0,0 +1,93 @@
+use futures_util::Future;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+
+use crate::action::{ChatToolAction, LsiAction};
+use crate::app::lsi::query::LsiQuery;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+#[derive(Serialize, Deserialize)]
+pub struct LspGotoImplementation {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for LspGotoImplementation {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    LspGotoImplementation {
+      name: "lsp_goto_implementation".to_string(),
+      description: "get the locations of the implementations of a symbol, such as the impl blocks of a trait or the classes implementing an interface".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([(
+          "symbol_id".to_string(),
+          FunctionProperty::Array {
+            required: true,
+            description: Some(
+              "the 32 byte symbol_id of the trait, interface or method to find the implementations of".to_string(),
+            ),
+            items: Box::new(FunctionProperty::Integer {
+              description: None,
+              required: true,
+              minimum: Some(0),
+              maximum: Some(255),
+            }),
+            min_items: Some(32),
+            max_items: Some(32),
+          },
+        )]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+
+    let symbol_id = get_validated_argument::<Vec<u8>>(&validated_arguments, "symbol_id");
+
+    let workspace_root =
+      params.session_config.workspace.expect("workspace not set").workspace_path.clone();
+
+    Box::pin(async move {
+      let query = LsiQuery {
+        symbol_id,
+        workspace_root,
+
+        tool_call_id: params.tool_call_id,
+        session_id: params.session_id,
+        timeout,
+        ..Default::default()
+      };
+
+      params
+        .tx
+        .send(ChatToolAction::LsiRequest(Box::new(LsiAction::GoToImplementation(query))))
+        .unwrap();
+      // return none, so the tool completes when it receieves a response from the language server
+      Ok(None)
+    })
+  }
+}
@@ -62,6 +62,7 @@ impl ToolCallTrait for LspGotoTypeDefinition {
     &self,
     params: ToolCallParams,
   ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
     let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
       .expect("error validating arguments");
 
@@ -77,6 +78,7 @@ impl ToolCallTrait for LspGotoTypeDefinition {
 
         tool_call_id: params.tool_call_id,
         session_id: params.session_id,
+        timeout,
         ..Default::default()
       };
 
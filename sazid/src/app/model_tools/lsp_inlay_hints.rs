@@ -0,0 +1,125 @@
+use futures_util::Future;
+use lsp_types::{Position, Range};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use crate::action::{ChatToolAction, LsiAction};
+use crate::app::lsi::query::LsiQuery;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+#[derive(Serialize, Deserialize)]
+pub struct LspInlayHints {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for LspInlayHints {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    LspInlayHints {
+      name: "lsp_inlay_hints".to_string(),
+      description: "get the inlay hints of the language server for lines of a file: the inferred types of variables and the names of the parameters arguments are passed to. with include_source, the lines are returned with each hint written into them as a comment, so the inferred types can be read along with the code".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "file_path".to_string(),
+            FunctionProperty::PathBuf {
+              required: true,
+              description: Some("path of the file, relative to the workspace root".to_string()),
+            },
+          ),
+          (
+            "start_line".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(0),
+              maximum: None,
+              description: Some("zero based first line to get the hints of. omit to start at the beginning of the file".to_string()),
+            },
+          ),
+          (
+            "end_line".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(0),
+              maximum: None,
+              description: Some("zero based last line to get the hints of. omit to end at the end of the file".to_string()),
+            },
+          ),
+          (
+            "include_source".to_string(),
+            FunctionProperty::Bool {
+              required: false,
+              description: Some("return the lines with the hints written into them as comments, instead of a list of hints. this defaults to false".to_string()),
+            },
+          ),
+        ]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+
+    let file_path = get_validated_argument::<PathBuf>(&validated_arguments, "file_path");
+    let start_line = get_validated_argument::<u32>(&validated_arguments, "start_line");
+    let end_line = get_validated_argument::<u32>(&validated_arguments, "end_line");
+    let include_source =
+      get_validated_argument::<bool>(&validated_arguments, "include_source").unwrap_or_default();
+
+    let workspace_root =
+      params.session_config.workspace.expect("workspace not set").workspace_path.clone();
+
+    Box::pin(async move {
+      let Some(file_path) = file_path else {
+        return Err(ToolCallError::new("file_path argument is required"));
+      };
+      let file_path =
+        if file_path.is_relative() { workspace_root.join(file_path) } else { file_path };
+      // the range ends at the start of the line after the last one, the whole file when omitted
+      let range = Range::new(
+        Position::new(start_line.unwrap_or(0), 0),
+        Position::new(end_line.map_or(u32::MAX, |line| line.saturating_add(1)), 0),
+      );
+      let query = LsiQuery {
+        range: Some(range),
+        include_source,
+        workspace_root,
+        tool_call_id: params.tool_call_id,
+        session_id: params.session_id,
+        timeout,
+        ..Default::default()
+      };
+
+      let action = LsiAction::InlayHints(file_path, query);
+      params.tx.send(ChatToolAction::LsiRequest(Box::new(action))).unwrap();
+      // return none, so the tool completes when it receieves a response from the language server
+      Ok(None)
+    })
+  }
+}
@@ -25,7 +25,7 @@ impl ToolCallTrait for LspQuerySymbol {
   {
     LspQuerySymbol {
             name: "lsp_query".to_string(),
-            description: "query symbols in project source code using a language server. each property will filter the results. Omit a property to query for unfiltered results".to_string(),
+            description: "query symbols in project source code using a language server, or tree-sitter for workspaces without one. each property will filter the results. Omit a property to query for unfiltered results".to_string(),
               parameters: FunctionProperty::Parameters {
           properties: HashMap::from([
                     ("name_regex".to_string(),
@@ -47,6 +47,25 @@ impl ToolCallTrait for LspQuerySymbol {
               FunctionProperty::Pattern {
                     required: false,
                 description: Some("filter results by file path. Omit to get symbols from all files".to_string()),
+                }),
+                    ("query".to_string(),
+              FunctionProperty::String {
+                    required: false,
+                description: Some("a symbol query of space separated terms. kind:function,method filters by kind, path:src/**/*.rs by a glob on the file path, name:^handle_ by a regex on the name, parent:App by the name of the enclosing symbol. other words are fuzzy matched against the name and rank the results. the response holds a page of symbols, total_matches and next_offset".to_string()),
+                }),
+                    ("offset".to_string(),
+              FunctionProperty::Integer {
+                    required: false,
+                    minimum: Some(0),
+                    maximum: None,
+                description: Some("the number of query results to skip, pass the next_offset of the previous page to get the next one".to_string()),
+                }),
+                    ("limit".to_string(),
+              FunctionProperty::Integer {
+                    required: false,
+                    minimum: Some(1),
+                    maximum: Some(1000),
+                description: Some("the number of query results in a page. defaults to 50".to_string()),
                 }),
                     ("include_source_code".to_string(),
               FunctionProperty::Bool {
@@ -72,6 +91,7 @@ impl ToolCallTrait for LspQuerySymbol {
     &self,
     params: ToolCallParams,
   ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
     let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
       .expect("error validating arguments");
 
@@ -91,6 +111,9 @@ impl ToolCallTrait for LspQuerySymbol {
         .unwrap_or_default();
 
     let file_path_regex = get_validated_argument::<String>(&validated_arguments, "file_path_regex");
+    let symbol_query = get_validated_argument::<String>(&validated_arguments, "query");
+    let offset = get_validated_argument::<usize>(&validated_arguments, "offset");
+    let limit = get_validated_argument::<usize>(&validated_arguments, "limit");
 
     Box::pin(async move {
       params.session_config.workspace.expect("workspace must be initialized before query");
@@ -107,9 +130,13 @@ impl ToolCallTrait for LspQuerySymbol {
         workspace_root,
         tool_call_id: params.tool_call_id,
         session_id: params.session_id,
+        timeout,
         file_path_regex,
         diagnostic_severity: None,
         include_source,
+        query: symbol_query,
+        offset,
+        limit,
         ..Default::default()
       };
 
@@ -4,10 +4,12 @@ use std::collections::HashMap;
 use std::pin::Pin;
 
 use crate::action::{ChatToolAction, LsiAction};
+use crate::app::edit_validation::EditValidation;
 use crate::app::lsi::query::LsiQuery;
 
 use super::errors::ToolCallError;
 use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::tool_permissions::WriteGuard;
 use super::types::*;
 
 #[derive(Serialize, Deserialize)]
@@ -24,7 +26,7 @@ impl ToolCallTrait for LspReplaceSymbolText {
   {
     LspReplaceSymbolText {
       name: "lsp_replace_symbol_text".to_string(),
-      description: "replace the text for a given symbol_id".to_string(),
+      description: "replace the text for a given symbol_id. the edited file is checked to still parse, and with cargo_check set, that cargo check still passes for rust files. an edit that fails a check is reverted and the errors are returned".to_string(),
       parameters: FunctionProperty::Parameters {
         properties: HashMap::from([
           (
@@ -49,6 +51,25 @@ impl ToolCallTrait for LspReplaceSymbolText {
               max_items: Some(32),
             },
           ),
+          (
+            "validate_syntax".to_string(),
+            FunctionProperty::Bool {
+              required: false,
+              description: Some(
+                "revert the edit if the file no longer parses, true if not set".to_string(),
+              ),
+            },
+          ),
+          (
+            "cargo_check".to_string(),
+            FunctionProperty::Bool {
+              required: false,
+              description: Some(
+                "revert the edit if cargo check fails after it, for rust files. false if not set"
+                  .to_string(),
+              ),
+            },
+          ),
         ]),
       },
     }
@@ -69,13 +90,22 @@ impl ToolCallTrait for LspReplaceSymbolText {
     &self,
     params: ToolCallParams,
   ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
     let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
       .expect("error validating arguments");
 
     let symbol_id = get_validated_argument(&validated_arguments, "symbol_id");
     let replacement_text: Option<String> =
       get_validated_argument(&validated_arguments, "replacement_text");
+    let default_validation = EditValidation::default();
+    let validation = EditValidation {
+      syntax: get_validated_argument(&validated_arguments, "validate_syntax")
+        .unwrap_or(default_validation.syntax),
+      cargo_check: get_validated_argument(&validated_arguments, "cargo_check")
+        .unwrap_or(default_validation.cargo_check),
+    };
 
+    let write_guard = WriteGuard::new(&params.session_config);
     let workspace_root =
       params.session_config.workspace.expect("workspace not set").workspace_path.clone();
 
@@ -86,6 +116,7 @@ impl ToolCallTrait for LspReplaceSymbolText {
 
         tool_call_id: params.tool_call_id,
         session_id: params.session_id,
+        timeout,
         ..Default::default()
       };
 
@@ -93,6 +124,8 @@ impl ToolCallTrait for LspReplaceSymbolText {
         .tx
         .send(ChatToolAction::LsiRequest(Box::new(LsiAction::ReplaceSymbolText(
           replacement_text.expect("replacement text required for ReplaceSymbolText"),
+          validation,
+          write_guard,
           query,
         ))))
         .unwrap();
@@ -0,0 +1,135 @@
+use futures_util::Future;
+use lsp_types::SymbolKind;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+
+use crate::action::{ChatToolAction, LsiAction};
+use crate::app::lsi::query::LsiQuery;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+#[derive(Serialize, Deserialize)]
+pub struct LspSearchSymbolsAll {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for LspSearchSymbolsAll {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    LspSearchSymbolsAll {
+      name: "search_symbols_all".to_string(),
+      description: "query symbols across every workspace in the session using their language servers. results are grouped by workspace, with the number of matches in each workspace. use this when a project spans several roots, such as a frontend and a backend".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "name_regex".to_string(),
+            FunctionProperty::Pattern {
+              required: false,
+              description: Some("filter symbol results by name. omit to get symbols unfiltered by name".to_string()),
+            },
+          ),
+          (
+            "kind".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some("filter results by kind. omit to get all symbol kinds. valid kinds include: MODULE NAMESPACE PACKAGE CLASS METHOD PROPERTY FIELD CONSTRUCTOR ENUM INTERFACE FUNCTION VARIABLE CONSTANT STRING NUMBER BOOLEAN ARRAY OBJECT KEY NULL ENUM_MEMBER STRUCT EVENT OPERATOR TYPE_PARAMETER".to_string()),
+            },
+          ),
+          (
+            "file_path_regex".to_string(),
+            FunctionProperty::Pattern {
+              required: false,
+              description: Some("filter results by file path. Omit to get symbols from all files".to_string()),
+            },
+          ),
+          (
+            "query".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some("a symbol query of space separated terms, as taken by lsp_query. kind:function,method, path:src/**/*.rs, name:^handle_ and parent:App filter the symbols, other words are fuzzy matched against the name and rank the results".to_string()),
+            },
+          ),
+          (
+            "limit".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(1),
+              maximum: Some(1000),
+              description: Some("maximum number of symbols to return across all workspaces, shared evenly between them. defaults to 100".to_string()),
+            },
+          ),
+          (
+            "include_source_code".to_string(),
+            FunctionProperty::Bool {
+              required: false,
+              description: Some("include symbol source code in the response. this defaults to false".to_string()),
+            },
+          ),
+        ]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+
+    let name_regex = get_validated_argument::<String>(&validated_arguments, "name_regex");
+    let kind = get_validated_argument::<String>(&validated_arguments, "kind");
+    let file_path_regex = get_validated_argument::<String>(&validated_arguments, "file_path_regex");
+    let symbol_query = get_validated_argument::<String>(&validated_arguments, "query");
+    let limit = get_validated_argument::<usize>(&validated_arguments, "limit");
+    let include_source =
+      get_validated_argument::<bool>(&validated_arguments, "include_source_code")
+        .unwrap_or_default();
+
+    Box::pin(async move {
+      let kind: Option<SymbolKind> = kind.and_then(|kind| {
+        let kind = change_case::pascal_case(&kind);
+        SymbolKind::try_from(kind.as_str()).ok()
+      });
+
+      let query = LsiQuery {
+        name_regex,
+        kind,
+        file_path_regex,
+        query: symbol_query,
+        limit,
+        tool_call_id: params.tool_call_id,
+        session_id: params.session_id,
+        timeout,
+        include_source,
+        ..Default::default()
+      };
+
+      params
+        .tx
+        .send(ChatToolAction::LsiRequest(Box::new(LsiAction::QueryAllWorkspaceSymbols(query))))
+        .unwrap();
+      Ok(None)
+    })
+  }
+}
@@ -0,0 +1,109 @@
+use futures_util::Future;
+use lsp_types::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use crate::action::{ChatToolAction, LsiAction};
+use crate::app::lsi::query::LsiQuery;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+#[derive(Serialize, Deserialize)]
+pub struct LspSignatureHelp {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for LspSignatureHelp {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    LspSignatureHelp {
+      name: "lsp_signature_help".to_string(),
+      description: "get the signatures of the function or method called at a position in a file, with their parameters and documentation, and which signature and parameter the position is at. use this to check the order and types of the arguments before writing a call".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "file_path".to_string(),
+            FunctionProperty::PathBuf {
+              required: true,
+              description: Some("path of the file with the call, relative to the workspace root".to_string()),
+            },
+          ),
+          (
+            "line".to_string(),
+            FunctionProperty::Integer {
+              required: true,
+              minimum: Some(0),
+              maximum: None,
+              description: Some("zero based line of the position, inside the parentheses of the call".to_string()),
+            },
+          ),
+          (
+            "character".to_string(),
+            FunctionProperty::Integer {
+              required: true,
+              minimum: Some(0),
+              maximum: None,
+              description: Some("zero based character of the position in the line".to_string()),
+            },
+          ),
+        ]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+
+    let file_path = get_validated_argument::<PathBuf>(&validated_arguments, "file_path");
+    let line = get_validated_argument::<u32>(&validated_arguments, "line");
+    let character = get_validated_argument::<u32>(&validated_arguments, "character");
+
+    let workspace_root =
+      params.session_config.workspace.expect("workspace not set").workspace_path.clone();
+
+    Box::pin(async move {
+      let (Some(file_path), Some(line), Some(character)) = (file_path, line, character) else {
+        return Err(ToolCallError::new("file_path, line and character arguments are required"));
+      };
+      let file_path =
+        if file_path.is_relative() { workspace_root.join(file_path) } else { file_path };
+      let query = LsiQuery {
+        workspace_root,
+        tool_call_id: params.tool_call_id,
+        session_id: params.session_id,
+        timeout,
+        ..Default::default()
+      };
+
+      let action = LsiAction::SignatureHelp(file_path, Position::new(line, character), query);
+      params.tx.send(ChatToolAction::LsiRequest(Box::new(action))).unwrap();
+      // return none, so the tool completes when it receieves a response from the language server
+      Ok(None)
+    })
+  }
+}
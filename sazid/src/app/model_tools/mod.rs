@@ -1,24 +1,38 @@
 // pub mod cargo_check_function;
 // pub mod create_file_function;
 // pub mod file_search_function;
-// pub mod grep_function;
-// pub mod pcre2grep_function;
 // pub mod read_file_lines_function;
-// pub mod treesitter_function;
 
 pub mod create_file_function;
+pub mod grep_function;
+pub mod list_directory;
+pub mod lsp_document_highlight;
 pub mod lsp_get_diagnostics;
 pub mod lsp_get_workspace_files;
+pub mod lsp_goto_implementation;
 pub mod lsp_goto_symbol_declaration;
 pub mod lsp_goto_symbol_definition;
 pub mod lsp_goto_type_definition;
+pub mod lsp_inlay_hints;
 pub mod lsp_query_symbols;
 pub mod lsp_read_symbol_source;
 pub mod lsp_replace_symbol_text;
+pub mod lsp_search_symbols_all;
+pub mod lsp_signature_help;
+pub mod modify_file_function;
+pub mod multi_edit_function;
+pub mod patch_file_function;
 pub mod read_file_text;
+pub mod semantic_search;
+pub mod shell_tool;
+pub mod summarize_file;
+pub mod treesitter_function;
 
 pub mod argument_validation;
 pub mod errors;
+pub mod path_guard;
 pub mod tool_call;
 pub mod tool_call_template;
+pub mod tool_permissions;
+pub mod tool_registry;
 pub mod types;
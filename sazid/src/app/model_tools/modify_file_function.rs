@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures_util::Future;
+
+use crate::action::{ChatToolAction, SessionAction};
+use crate::app::file_patch::{preview_diff, FileEdit};
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+/// where in a file an edit goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditLocation {
+  /// lines `start` to `end`, counted from 1 and inclusive. `end` one before `start` inserts
+  /// before `start` without replacing anything
+  Lines { start: usize, end: usize },
+  /// the one place the snippet occurs in the file
+  Anchor(String),
+}
+
+pub struct ModifyFileFunction {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+  // the hash of each file as the model last saw it through this tool, by session
+  last_read: Arc<Mutex<HashMap<(i64, PathBuf), String>>>,
+}
+
+impl ToolCallTrait for ModifyFileFunction {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    ModifyFileFunction {
+      name: "modify_file".to_string(),
+      description: "read or change part of an existing file. locate the part with start_line and end_line, or with an anchor snippet that occurs once in the file. with content, the part is replaced by it and a diff of the change is returned; without, the numbered lines are returned. every result ends with the hash of the file, pass it as file_hash to the next edit so it is refused if the file changed since".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "path".to_string(),
+            FunctionProperty::String {
+              required: true,
+              description: Some("the file, relative to the workspace root".to_string()),
+            },
+          ),
+          (
+            "start_line".to_string(),
+            FunctionProperty::Integer {
+              minimum: Some(1),
+              maximum: None,
+              required: false,
+              description: Some("the first line of the part, counted from 1".to_string()),
+            },
+          ),
+          (
+            "end_line".to_string(),
+            FunctionProperty::Integer {
+              minimum: Some(0),
+              maximum: None,
+              required: false,
+              description: Some(
+                "the last line of the part. one less than start_line inserts the content before \
+                 start_line"
+                  .to_string(),
+              ),
+            },
+          ),
+          (
+            "anchor".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some(
+                "text that occurs exactly once in the file, the part to read or replace"
+                  .to_string(),
+              ),
+            },
+          ),
+          (
+            "content".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some("the text to replace the part with".to_string()),
+            },
+          ),
+          (
+            "file_hash".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some("the hash of the file as you last read it".to_string()),
+            },
+          ),
+        ]),
+      },
+      last_read: Arc::new(Mutex::new(HashMap::new())),
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .map_err(|e| ToolCallError::new(&e));
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
+    let (tx, tool_call_id, session_id) = (params.tx, params.tool_call_id, params.session_id);
+    let last_read = self.last_read.clone();
+
+    Box::pin(async move {
+      let validated_arguments = validated_arguments?;
+      let Some(path) = get_validated_argument::<String>(&validated_arguments, "path") else {
+        return Err(ToolCallError::new("path argument is required"));
+      };
+      let location = edit_location(
+        get_validated_argument::<usize>(&validated_arguments, "start_line"),
+        get_validated_argument::<usize>(&validated_arguments, "end_line"),
+        get_validated_argument::<String>(&validated_arguments, "anchor"),
+      )?;
+      let content = get_validated_argument::<String>(&validated_arguments, "content");
+      let file_hash = get_validated_argument::<String>(&validated_arguments, "file_hash");
+
+      let path = match &workspace_root {
+        Some(root) => {
+          let path = root.join(path);
+          if !path.canonicalize()?.starts_with(root.canonicalize()?) {
+            return Err(ToolCallError::new("cannot modify files outside of the workspace"));
+          }
+          path
+        },
+        None => PathBuf::from(path),
+      };
+      let original = std::fs::read_to_string(&path)?;
+      let hash = content_hash(&original);
+      let key = (session_id, path.clone());
+      let expected_hash = file_hash.or_else(|| last_read.lock().unwrap().get(&key).cloned());
+      if let Some(expected_hash) = expected_hash {
+        if expected_hash != hash {
+          return Err(ToolCallError::new(&format!(
+            "{} changed since it was last read, its hash is now {}. read the part again before \
+             changing it",
+            path.display(),
+            hash
+          )));
+        }
+      }
+
+      let Some(content) = content else {
+        let lines = read_location(&original, location.as_ref())?;
+        last_read.lock().unwrap().insert(key, hash.clone());
+        return Ok(Some(format!("{}\nfile hash: {}", lines, hash)));
+      };
+      let Some(location) = location else {
+        return Err(ToolCallError::new("start_line or anchor is needed to change a file"));
+      };
+      let modified = apply_edit(&original, &location, &content)?;
+      std::fs::write(&path, &modified)?;
+      let modified_hash = content_hash(&modified);
+      last_read.lock().unwrap().insert(key, modified_hash.clone());
+      let diff = preview_diff(&original, &modified);
+      let edit = FileEdit { tool_call_id, path, original: Some(original), modified };
+      let _ = tx
+        .send(ChatToolAction::SessionAction(Box::new(SessionAction::FileEdited(session_id, edit))));
+      Ok(Some(format!("{}file hash: {}", diff, modified_hash)))
+    })
+  }
+}
+
+/// where an edit goes, from the start_line, end_line and anchor arguments. none without any
+pub(super) fn edit_location(
+  start: Option<usize>,
+  end: Option<usize>,
+  anchor: Option<String>,
+) -> Result<Option<EditLocation>, ToolCallError> {
+  match (start, end, anchor) {
+    (None, None, None) => Ok(None),
+    (None, None, Some(anchor)) => Ok(Some(EditLocation::Anchor(anchor))),
+    (Some(start), end, None) => Ok(Some(EditLocation::Lines { start, end: end.unwrap_or(start) })),
+    (None, Some(_), _) => Err(ToolCallError::new("end_line needs a start_line")),
+    (Some(_), _, Some(_)) => Err(ToolCallError::new("give either start_line or anchor, not both")),
+  }
+}
+
+/// the blake3 hash of a file's content, shortened to what is needed to tell versions apart
+pub fn content_hash(content: &str) -> String {
+  blake3::hash(content.as_bytes()).to_hex().as_str()[..16].to_string()
+}
+
+// the byte range of a location in the file
+fn locate(
+  original: &str,
+  location: &EditLocation,
+) -> Result<std::ops::Range<usize>, ToolCallError> {
+  match location {
+    EditLocation::Lines { start, end } => {
+      let line_starts = std::iter::once(0)
+        .chain(original.match_indices('\n').map(|(idx, _)| idx + 1))
+        .filter(|idx| *idx < original.len() || *idx == 0)
+        .collect::<Vec<_>>();
+      let line_count = if original.is_empty() { 0 } else { line_starts.len() };
+      if *start == 0 || *start > line_count + 1 || *end + 1 < *start || *end > line_count {
+        return Err(ToolCallError::new(&format!(
+          "lines {} to {} are not in the file, which has {} lines",
+          start, end, line_count
+        )));
+      }
+      let byte_at = |line: usize| line_starts.get(line - 1).copied().unwrap_or(original.len());
+      Ok(byte_at(*start)..byte_at(*end + 1))
+    },
+    EditLocation::Anchor(anchor) if anchor.is_empty() => {
+      Err(ToolCallError::new("the anchor is empty"))
+    },
+    EditLocation::Anchor(anchor) => {
+      let found = original.match_indices(anchor.as_str()).map(|(idx, _)| idx).collect::<Vec<_>>();
+      let line_of = |idx: usize| original[..idx].matches('\n').count() + 1;
+      match found.as_slice() {
+        [idx] => Ok(*idx..idx + anchor.len()),
+        [] => Err(ToolCallError::new("the anchor does not occur in the file")),
+        found => Err(ToolCallError::new(&format!(
+          "the anchor occurs {} times, on lines {}. make it longer so it occurs once",
+          found.len(),
+          found.iter().map(|idx| line_of(*idx).to_string()).collect::<Vec<_>>().join(", ")
+        ))),
+      }
+    },
+  }
+}
+
+/// `original` with the part at `location` replaced by `content`. replaced lines are replaced by
+/// whole lines, so the content gets a line break at the end when it has none
+pub fn apply_edit(
+  original: &str,
+  location: &EditLocation,
+  content: &str,
+) -> Result<String, ToolCallError> {
+  let range = locate(original, location)?;
+  let mut modified = String::with_capacity(original.len() + content.len());
+  modified.push_str(&original[..range.start]);
+  modified.push_str(content);
+  if matches!(location, EditLocation::Lines { .. })
+    && !content.is_empty()
+    && !content.ends_with('\n')
+    && range.end < original.len()
+  {
+    modified.push('\n');
+  }
+  modified.push_str(&original[range.end..]);
+  Ok(modified)
+}
+
+// the lines at a location with their numbers, the whole file without one
+fn read_location(original: &str, location: Option<&EditLocation>) -> Result<String, ToolCallError> {
+  let range = match location {
+    Some(location) => locate(original, location)?,
+    None => 0..original.len(),
+  };
+  // widen an anchor to the lines it is on
+  let start = original[..range.start].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+  let end = original[range.end..].find('\n').map(|idx| range.end + idx).unwrap_or(original.len());
+  let first_line = original[..start].matches('\n').count() + 1;
+  Ok(
+    original[start..end]
+      .lines()
+      .enumerate()
+      .map(|(idx, line)| format!("{:>5}| {}\n", first_line + idx, line))
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_apply_edit() {
+    let original = "fn a() {}\n\nfn b() {\n  1\n}\n";
+    let lines = |start, end| EditLocation::Lines { start, end };
+    assert_eq!(
+      apply_edit(original, &lines(4, 4), "  2").unwrap(),
+      "fn a() {}\n\nfn b() {\n  2\n}\n"
+    );
+    assert_eq!(
+      apply_edit(original, &lines(2, 1), "// b\n").unwrap(),
+      "fn a() {}\n// b\n\nfn b() {\n  1\n}\n"
+    );
+    assert_eq!(
+      apply_edit(original, &lines(6, 5), "fn c() {}\n").unwrap(),
+      format!("{}fn c() {{}}\n", original)
+    );
+    assert_eq!(apply_edit(original, &lines(3, 5), "").unwrap(), "fn a() {}\n\n");
+    assert!(apply_edit(original, &lines(5, 6), "").is_err());
+
+    let anchor = EditLocation::Anchor("fn b() {\n  1".to_string());
+    assert_eq!(
+      apply_edit(original, &anchor, "fn b() {\n  3").unwrap(),
+      "fn a() {}\n\nfn b() {\n  3\n}\n"
+    );
+    let error = apply_edit(original, &EditLocation::Anchor("() {".to_string()), "").unwrap_err();
+    assert!(error.to_string().contains("on lines 1, 3"));
+
+    assert_eq!(read_location(original, Some(&anchor)).unwrap(), "    3| fn b() {\n    4|   1\n");
+    assert_ne!(content_hash(original), content_hash(&original.replace('1', "2")));
+  }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use futures_util::Future;
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ChatToolAction, SessionAction};
+use crate::app::file_patch::{preview_diff, FileEdit};
+
+use super::errors::ToolCallError;
+use super::modify_file_function::{apply_edit, edit_location};
+use super::patch_file_function::workspace_file;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+#[derive(Serialize, Deserialize)]
+pub struct MultiEditFunction {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+/// one edit of a `multi_edit` call
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct EditOperation {
+  pub path: PathBuf,
+  pub start_line: Option<usize>,
+  pub end_line: Option<usize>,
+  pub anchor: Option<String>,
+  pub content: String,
+}
+
+impl ToolCallTrait for MultiEditFunction {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    MultiEditFunction {
+      name: "multi_edit".to_string(),
+      description: "apply several edits across files as one change: either all of them are applied or none are. each edit replaces lines start_line to end_line, or an anchor snippet that occurs once in the file, with content. edits to the same file are applied in order, so line numbers count in the file as the edits before left it. returns a diff of every file changed".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([(
+          "edits".to_string(),
+          FunctionProperty::Array {
+            required: true,
+            description: Some("the edits, in the order they are applied".to_string()),
+            items: Box::new(FunctionProperty::Parameters {
+              properties: HashMap::from([
+                (
+                  "path".to_string(),
+                  FunctionProperty::String {
+                    required: true,
+                    description: Some("the file, relative to the workspace root".to_string()),
+                  },
+                ),
+                (
+                  "start_line".to_string(),
+                  FunctionProperty::Integer {
+                    minimum: Some(1),
+                    maximum: None,
+                    required: false,
+                    description: Some("the first line to replace, counted from 1".to_string()),
+                  },
+                ),
+                (
+                  "end_line".to_string(),
+                  FunctionProperty::Integer {
+                    minimum: Some(0),
+                    maximum: None,
+                    required: false,
+                    description: Some(
+                      "the last line to replace, one less than start_line to insert before it"
+                        .to_string(),
+                    ),
+                  },
+                ),
+                (
+                  "anchor".to_string(),
+                  FunctionProperty::String {
+                    required: false,
+                    description: Some(
+                      "text that occurs once in the file, replaced instead of lines".to_string(),
+                    ),
+                  },
+                ),
+                (
+                  "content".to_string(),
+                  FunctionProperty::String {
+                    required: true,
+                    description: Some("the replacement text".to_string()),
+                  },
+                ),
+              ]),
+            }),
+            min_items: Some(1),
+            max_items: None,
+          },
+        )]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .map_err(|e| ToolCallError::new(&e));
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
+    let (tx, tool_call_id, session_id) = (params.tx, params.tool_call_id, params.session_id);
+
+    Box::pin(async move {
+      let validated_arguments = validated_arguments?;
+      let Some(edits) = get_validated_argument::<Vec<EditOperation>>(&validated_arguments, "edits")
+      else {
+        return Err(ToolCallError::new("edits argument is required"));
+      };
+      let changes = apply_edits(workspace_root.as_deref(), &edits)?;
+      write_changes(&changes)?;
+
+      let mut report = String::new();
+      for (path, original, modified) in changes {
+        report.push_str(&format!("{}:\n{}", path.display(), preview_diff(&original, &modified)));
+        let edit =
+          FileEdit { tool_call_id: tool_call_id.clone(), path, original: Some(original), modified };
+        let _ = tx.send(ChatToolAction::SessionAction(Box::new(SessionAction::FileEdited(
+          session_id, edit,
+        ))));
+      }
+      Ok(Some(report))
+    })
+  }
+}
+
+/// each file the edits change, with its contents before and after them. nothing is written, an
+/// edit that can not be applied fails them all
+pub fn apply_edits(
+  workspace_root: Option<&Path>,
+  edits: &[EditOperation],
+) -> Result<Vec<(PathBuf, String, String)>, ToolCallError> {
+  let mut changes: Vec<(PathBuf, String, String)> = vec![];
+  for (i, edit) in edits.iter().enumerate() {
+    let failed = |e: ToolCallError| {
+      ToolCallError::new(&format!(
+        "edit {} to {} failed, no edits were applied: {}",
+        i + 1,
+        edit.path.display(),
+        e
+      ))
+    };
+    let path = workspace_file(workspace_root, &edit.path).map_err(failed)?;
+    let location = edit_location(edit.start_line, edit.end_line, edit.anchor.clone())
+      .map_err(failed)?
+      .ok_or_else(|| failed(ToolCallError::new("start_line or anchor is needed")))?;
+    let change = match changes.iter().position(|(changed, ..)| *changed == path) {
+      Some(index) => &mut changes[index],
+      None => {
+        let original = std::fs::read_to_string(&path).map_err(|e| failed(e.into()))?;
+        changes.push((path, original.clone(), original));
+        changes.last_mut().unwrap()
+      },
+    };
+    change.2 = apply_edit(&change.2, &location, &edit.content).map_err(failed)?;
+  }
+  Ok(changes)
+}
+
+// writes the changed files, putting back the ones already written when a write fails
+fn write_changes(changes: &[(PathBuf, String, String)]) -> Result<(), ToolCallError> {
+  for (i, (path, _, modified)) in changes.iter().enumerate() {
+    if let Err(e) = std::fs::write(path, modified) {
+      for (written, original, _) in &changes[..i] {
+        if let Err(e) = std::fs::write(written, original) {
+          log::error!("error restoring {}: {}", written.display(), e);
+        }
+      }
+      return Err(ToolCallError::new(&format!(
+        "writing {} failed, no edits were applied: {}",
+        path.display(),
+        e
+      )));
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_apply_edits() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    std::fs::write(dir.path().join("b.rs"), "use a::a;\n").unwrap();
+    let edit = |path: &str, start_line, anchor: Option<&str>, content: &str| EditOperation {
+      path: PathBuf::from(path),
+      start_line,
+      end_line: None,
+      anchor: anchor.map(str::to_string),
+      content: content.to_string(),
+    };
+
+    let edits = vec![
+      edit("a.rs", Some(1), None, "fn c() {}"),
+      edit("b.rs", None, Some("a::a"), "a::c"),
+      edit("a.rs", None, Some("fn b"), "fn d"),
+    ];
+    let changes = apply_edits(Some(dir.path()), &edits).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].2, "fn c() {}\nfn d() {}\n");
+    assert_eq!(changes[1].2, "use a::c;\n");
+
+    let edits = vec![edit("a.rs", Some(1), None, "fn c() {}"), edit("b.rs", None, Some("x"), "")];
+    let error = apply_edits(Some(dir.path()), &edits).unwrap_err();
+    assert!(error.to_string().starts_with("edit 2 to b.rs failed"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("a.rs")).unwrap(), "fn a() {}\nfn b() {}\n");
+  }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+
+use futures_util::Future;
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ChatToolAction, SessionAction};
+use crate::app::file_patch::{apply_hunks, file_diffs, patch_target, FileEdit};
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+const DEFAULT_FUZZ: usize = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct PatchFileFunction {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for PatchFileFunction {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    PatchFileFunction {
+      name: "apply_patch".to_string(),
+      description: "change files by applying a unified diff, which may cover several files. hunks are found by their lines rather than their line numbers, and may match with some of their context lines left out. hunks that do not match are written to a .rej file next to the file, the result lists each hunk and where it was applied".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "patch".to_string(),
+            FunctionProperty::String {
+              required: true,
+              description: Some(
+                "the unified diff, with --- and +++ headers naming the files relative to the \
+                 workspace root. use /dev/null as the old file to create a file"
+                  .to_string(),
+              ),
+            },
+          ),
+          (
+            "fuzz".to_string(),
+            FunctionProperty::Integer {
+              minimum: Some(0),
+              maximum: Some(3),
+              required: false,
+              description: Some(format!(
+                "how many context lines a hunk may leave out at either end to match, {} if not \
+                 set",
+                DEFAULT_FUZZ
+              )),
+            },
+          ),
+        ]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .map_err(|e| ToolCallError::new(&e));
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
+    let (tx, tool_call_id, session_id) = (params.tx, params.tool_call_id, params.session_id);
+
+    Box::pin(async move {
+      let validated_arguments = validated_arguments?;
+      let Some(patch) = get_validated_argument::<String>(&validated_arguments, "patch") else {
+        return Err(ToolCallError::new("patch argument is required"));
+      };
+      let fuzz =
+        get_validated_argument::<usize>(&validated_arguments, "fuzz").unwrap_or(DEFAULT_FUZZ);
+
+      let mut report = String::new();
+      for diff in file_diffs(&patch) {
+        let Some(target) = patch_target(&diff) else {
+          let _ =
+            writeln!(report, "a diff names no file to patch, deleting files is not supported");
+          continue;
+        };
+        let path = match workspace_file(workspace_root.as_deref(), &target) {
+          Ok(path) => path,
+          Err(e) => {
+            let _ = writeln!(report, "{}: {}", target.display(), e);
+            continue;
+          },
+        };
+        let original = match path.exists() {
+          true => Some(std::fs::read_to_string(&path)?),
+          false if diff.lines().any(|line| line.starts_with("--- /dev/null")) => None,
+          false => {
+            let _ = writeln!(report, "{}: the file does not exist", target.display());
+            continue;
+          },
+        };
+        let outcome = match apply_hunks(original.as_deref().unwrap_or_default(), &diff, fuzz) {
+          Ok(outcome) => outcome,
+          Err(e) => {
+            let _ = writeln!(report, "{}: {}", target.display(), e);
+            continue;
+          },
+        };
+
+        let _ = writeln!(report, "{}:", target.display());
+        let reject_path = PathBuf::from(format!("{}.rej", path.display()));
+        for (i, hunk) in outcome.hunks.iter().enumerate() {
+          let _ = match (hunk.applied_at, hunk.fuzz) {
+            (Some(line), 0) => writeln!(report, "  hunk {} applied at line {}", i + 1, line),
+            (Some(line), fuzz) => {
+              writeln!(report, "  hunk {} applied at line {} with fuzz {}", i + 1, line, fuzz)
+            },
+            (None, _) => {
+              writeln!(report, "  hunk {} failed, written to {}", i + 1, reject_path.display())
+            },
+          };
+        }
+        if !outcome.rejects.is_empty() {
+          let header = format!("--- a/{0}\n+++ b/{0}\n", target.display());
+          std::fs::write(&reject_path, header + &outcome.rejects)?;
+        }
+        if outcome.hunks.iter().any(|hunk| hunk.applied_at.is_some()) {
+          if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+          }
+          std::fs::write(&path, &outcome.patched)?;
+          let edit = FileEdit {
+            tool_call_id: tool_call_id.clone(),
+            path,
+            original,
+            modified: outcome.patched,
+          };
+          let _ = tx.send(ChatToolAction::SessionAction(Box::new(SessionAction::FileEdited(
+            session_id, edit,
+          ))));
+        }
+      }
+      match report.is_empty() {
+        true => Err(ToolCallError::new("the patch has no diffs")),
+        false => Ok(Some(report)),
+      }
+    })
+  }
+}
+
+/// the path of a file named in a tool call, which has to be within the workspace
+pub(super) fn workspace_file(
+  workspace_root: Option<&Path>,
+  path: &Path,
+) -> Result<PathBuf, ToolCallError> {
+  if path.components().any(|component| component == Component::ParentDir) {
+    return Err(ToolCallError::new("paths may not contain .."));
+  }
+  match workspace_root {
+    Some(root) if path.is_absolute() && !path.starts_with(root) => {
+      Err(ToolCallError::new("cannot edit files outside of the workspace"))
+    },
+    Some(root) => Ok(root.join(path)),
+    None => Ok(path.to_path_buf()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_workspace_file() {
+    let root = Path::new("/tmp/workspace");
+    assert_eq!(
+      workspace_file(Some(root), Path::new("src/lib.rs")).unwrap(),
+      PathBuf::from("/tmp/workspace/src/lib.rs")
+    );
+    assert_eq!(
+      workspace_file(Some(root), Path::new("/tmp/workspace/src/lib.rs")).unwrap(),
+      PathBuf::from("/tmp/workspace/src/lib.rs")
+    );
+    assert!(workspace_file(Some(root), Path::new("../etc/passwd")).is_err());
+    assert!(workspace_file(Some(root), Path::new("/etc/passwd")).is_err());
+  }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app::file_patch::{file_diffs, patch_target};
+
+/// keeps the file tools inside the registered workspaces. every path a call names is resolved,
+/// following symlinks and `..`, and the call is refused if it leaves the workspaces or matches
+/// `denied`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PathGuardConfig {
+  pub enabled: bool,
+  /// globs of files no tool may read or write, matched against the path in its workspace and
+  /// the file name
+  pub denied: Vec<String>,
+}
+
+impl Default for PathGuardConfig {
+  fn default() -> Self {
+    PathGuardConfig {
+      enabled: true,
+      denied: [".env", ".env.*", "*.pem", "*.key", "id_rsa*", "id_ed25519*"]
+        .iter()
+        .map(|glob| glob.to_string())
+        .collect(),
+    }
+  }
+}
+
+impl PathGuardConfig {
+  /// refuses a call naming a path outside of `roots` or a denied one, relative paths are in the
+  /// first root. without roots only the denied paths are refused
+  pub fn check(&self, arguments: &HashMap<String, Value>, roots: &[&Path]) -> Result<(), String> {
+    if !self.enabled {
+      return Ok(());
+    }
+    let denied = self.denied_set()?;
+    let roots = roots.iter().map(|root| canonicalize(root)).collect::<Vec<_>>();
+    for path in path_arguments(arguments) {
+      let resolved = match roots.first() {
+        Some(root) if path.is_relative() => canonicalize(&root.join(&path)),
+        _ => canonicalize(&path),
+      };
+      let relative = match roots.iter().find(|root| resolved.starts_with(root)) {
+        Some(root) => resolved.strip_prefix(root).unwrap_or(&resolved),
+        None if roots.is_empty() => resolved.as_path(),
+        None => return Err(format!("{} is outside of the workspace", path.display())),
+      };
+      if is_denied(&denied, relative) {
+        return Err(format!("{} is a denied path", path.display()));
+      }
+    }
+    Ok(())
+  }
+
+  /// the globs of `denied` as one set, empty when the guard is disabled. tools that walk
+  /// directories check each file they find with `is_denied`
+  pub fn denied_set(&self) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in self.denied.iter().filter(|_| self.enabled) {
+      builder.add(Glob::new(glob).map_err(|e| format!("invalid denied path {}: {}", glob, e))?);
+    }
+    builder.build().map_err(|e| e.to_string())
+  }
+}
+
+/// whether a path, relative to its workspace, or its file name matches the denied globs
+pub fn is_denied(denied: &GlobSet, relative: &Path) -> bool {
+  let file_name = relative.file_name().map(Path::new).unwrap_or(relative);
+  denied.is_match(relative) || denied.is_match(file_name)
+}
+
+/// the paths the arguments of a call name: `path` and `file_path`, the paths of a multi edit
+/// and the files a patch changes
+pub fn path_arguments(arguments: &HashMap<String, Value>) -> Vec<PathBuf> {
+  let path = |value: &Value| value.get("path").and_then(Value::as_str).map(PathBuf::from);
+  let mut paths = ["path", "file_path"]
+    .iter()
+    .filter_map(|name| arguments.get(*name)?.as_str().map(PathBuf::from))
+    .collect::<Vec<_>>();
+  if let Some(Value::Array(edits)) = arguments.get("edits") {
+    paths.extend(edits.iter().filter_map(path));
+  }
+  if let Some(patch) = arguments.get("patch").and_then(Value::as_str) {
+    paths.extend(file_diffs(patch).iter().filter_map(|diff| patch_target(diff)));
+  }
+  paths
+}
+
+// resolves symlinks and `..`. a path that does not exist yet, like a file about to be created,
+// is resolved through its nearest existing ancestor
+fn canonicalize(path: &Path) -> PathBuf {
+  let path = helix_stdx::path::normalize(path);
+  let mut existing = path.as_path();
+  let mut missing = vec![];
+  while !existing.exists() {
+    match (existing.parent(), existing.file_name()) {
+      (Some(parent), Some(name)) => {
+        missing.push(name);
+        existing = parent;
+      },
+      _ => return path.clone(),
+    }
+  }
+  let mut resolved = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+  resolved.extend(missing.iter().rev());
+  resolved
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use tempdir::TempDir;
+
+  fn arguments(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn test_paths_are_kept_in_the_workspace() {
+    let workspace = TempDir::new("test_path_guard").unwrap();
+    std::fs::create_dir(workspace.path().join("src")).unwrap();
+    let roots = [workspace.path()];
+    let guard = PathGuardConfig::default();
+    let check = |value: Value| guard.check(&arguments(value), &roots);
+
+    assert!(check(json!({ "path": "src/new.rs" })).is_ok());
+    let inside = workspace.path().join("src/../Cargo.toml");
+    assert!(check(json!({ "file_path": inside.to_str().unwrap() })).is_ok());
+    assert!(check(json!({ "path": "src/../../outside.rs" })).unwrap_err().contains("outside"));
+    assert!(check(json!({ "file_path": "/etc/passwd" })).is_err());
+    assert!(check(json!({ "path": "config/.env" })).unwrap_err().contains("denied"));
+    let edits = json!({ "edits": [{ "path": "src/a.rs" }, { "path": "certs/site.pem" }] });
+    assert!(check(edits).is_err());
+
+    #[cfg(unix)]
+    {
+      std::os::unix::fs::symlink("/etc", workspace.path().join("etc")).unwrap();
+      assert!(check(json!({ "path": "etc/passwd" })).is_err());
+    }
+    let disabled = PathGuardConfig { enabled: false, ..Default::default() };
+    assert!(disabled.check(&arguments(json!({ "path": "/etc/passwd" })), &roots).is_ok());
+  }
+}
@@ -1,18 +1,20 @@
 use futures_util::Future;
-use lsp_types::{Range, SymbolKind};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::pin::Pin;
 
 use crate::action::{ChatToolAction, LsiAction};
-use crate::app::lsi::get_file_range_contents;
+use crate::app::file_read::{self, FileRead, MAX_READ_LINES};
 use crate::app::lsi::query::LsiQuery;
 
 use super::errors::ToolCallError;
 use super::tool_call::{ToolCallParams, ToolCallTrait};
 use super::types::*;
 
+// a rough number of bytes per token, to keep a read within function_result_max_tokens
+const BYTES_PER_TOKEN: usize = 4;
+
 #[derive(Serialize, Deserialize)]
 pub struct ReadFileText {
   pub name: String,
@@ -26,27 +28,58 @@ impl ToolCallTrait for ReadFileText {
     Self: Sized,
   {
     ReadFileText {
-            name: "read_file".to_string(),
-            description: "read text from a file".to_string(),
-            parameters: FunctionProperty::Parameters {
-            properties: HashMap::from([
-                    ("file_path".to_string(),
-              FunctionProperty::PathBuf{
-                    required: true,
-                    description: Some("path of file to read text from".to_string()),
-                }),
-                    ("byte_range".to_string(),
-              FunctionProperty::String {
-                    required: false,
-                    description: Some("range of bytes to read from file, in the format of start_line,start_char,end_line,end_char. omit to read entire file".to_string()),
-                }),
-            ]),
-      }
-  }
+      name: "read_file".to_string(),
+      description: format!("read lines of a file, each prefixed by its line number. read a range of lines, or the lines of a symbol by its name. reads of more than {} lines, or too long for a tool result, are truncated with a notice of the start_line to read on from", MAX_READ_LINES),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "file_path".to_string(),
+            FunctionProperty::PathBuf {
+              required: false,
+              description: Some("path of the file to read, relative to the workspace root. required unless a symbol is given".to_string()),
+            },
+          ),
+          (
+            "symbol".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some("name of a symbol to read the lines of, qualified by the symbols it is declared in when the name is ambiguous, as in App::run. give file_path too to pick the symbol in that file".to_string()),
+            },
+          ),
+          (
+            "start_line".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(1),
+              maximum: None,
+              description: Some("1 based first line to read. omit to read from the start of the file or symbol".to_string()),
+            },
+          ),
+          (
+            "end_line".to_string(),
+            FunctionProperty::Integer {
+              required: false,
+              minimum: Some(1),
+              maximum: None,
+              description: Some("1 based last line to read. omit to read to the end of the file or symbol".to_string()),
+            },
+          ),
+          (
+            "inlay_hints".to_string(),
+            FunctionProperty::Bool {
+              required: false,
+              description: Some("write the inlay hints of the language server into the lines as comments, like the inferred types of variables. this defaults to false".to_string()),
+            },
+          ),
+        ]),
+      },
+    }
   }
+
   fn name(&self) -> &str {
     &self.name
   }
+
   fn parameters(&self) -> FunctionProperty {
     self.parameters.clone()
   }
@@ -59,15 +92,66 @@ impl ToolCallTrait for ReadFileText {
     &self,
     params: ToolCallParams,
   ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
     let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
       .expect("error validating arguments");
 
-    let file_path = get_validated_argument::<PathBuf>(&validated_arguments, "file_path")
-      .expect("file_path is required");
-    let range = get_validated_argument::<Range>(&validated_arguments, "range");
+    let workspace_root =
+      params.session_config.workspace.as_ref().map(|workspace| workspace.workspace_path.clone());
+    let file_path = get_validated_argument::<PathBuf>(&validated_arguments, "file_path");
+    let read = FileRead {
+      file_path: match (file_path, &workspace_root) {
+        (Some(file_path), Some(root)) if file_path.is_relative() => Some(root.join(file_path)),
+        (file_path, _) => file_path,
+      },
+      symbol: get_validated_argument::<String>(&validated_arguments, "symbol"),
+      start_line: get_validated_argument::<usize>(&validated_arguments, "start_line"),
+      end_line: get_validated_argument::<usize>(&validated_arguments, "end_line"),
+      max_bytes: params.session_config.function_result_max_tokens * BYTES_PER_TOKEN,
+      inlay_hints: get_validated_argument::<bool>(&validated_arguments, "inlay_hints")
+        .unwrap_or_default(),
+    };
 
     Box::pin(async move {
-      Ok(Some(get_file_range_contents(&file_path, range).expect("unable to read file contents")))
-    }) // End example call function code
+      match workspace_root {
+        // symbols are found in the symbol tree of the workspace, hints come from its language
+        // server
+        Some(workspace_root) if read.symbol.is_some() || read.inlay_hints => {
+          let query = LsiQuery {
+            workspace_root,
+            tool_call_id: params.tool_call_id,
+            session_id: params.session_id,
+            timeout,
+            ..Default::default()
+          };
+          params
+            .tx
+            .send(ChatToolAction::LsiRequest(Box::new(LsiAction::ReadFile(read, query))))
+            .unwrap();
+          Ok(None)
+        },
+        None if read.symbol.is_some() => {
+          Err(ToolCallError::new("reading a symbol needs a workspace"))
+        },
+        None if read.inlay_hints => {
+          Err(ToolCallError::new("reading inlay hints needs a workspace"))
+        },
+        _ => {
+          let Some(file_path) = read.file_path else {
+            return Err(ToolCallError::new("file_path is required unless a symbol is given"));
+          };
+          let text = match std::fs::read_to_string(&file_path) {
+            Ok(text) => text,
+            Err(e) => return Ok(Some(format!("error reading {}: {}", file_path.display(), e))),
+          };
+          let name = file_path.display().to_string();
+          match file_read::read_lines(&name, &text, read.start_line, read.end_line, read.max_bytes)
+          {
+            Ok(lines) => Ok(Some(lines)),
+            Err(e) => Ok(Some(e)),
+          }
+        },
+      }
+    })
   }
 }
@@ -0,0 +1,125 @@
+use futures_util::Future;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+
+use crate::action::{ChatToolAction, SessionAction};
+use crate::app::database::{data_models::EmbeddingModel, vector_store::open_vector_store};
+use crate::app::provider_fixtures::ProviderFixtures;
+use crate::app::semantic_search;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+const DEFAULT_RESULT_COUNT: usize = 8;
+
+#[derive(Serialize, Deserialize)]
+pub struct SemanticSearch {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for SemanticSearch {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    SemanticSearch {
+      name: "semantic_search".to_string(),
+      description: "search the workspace for code by meaning rather than by exact text, for example \"where is retry backoff implemented\". returns the closest matching chunks of files with their paths and line ranges. use it to find where something is done when the names involved are not known".to_string(),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([
+          (
+            "query".to_string(),
+            FunctionProperty::String {
+              required: true,
+              description: Some("a description of the code to find".to_string()),
+            },
+          ),
+          (
+            "count".to_string(),
+            FunctionProperty::Integer {
+              minimum: Some(1),
+              maximum: Some(32),
+              required: false,
+              description: Some(format!(
+                "the number of chunks to return, {} if not set",
+                DEFAULT_RESULT_COUNT
+              )),
+            },
+          ),
+        ]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+    let query = get_validated_argument::<String>(&validated_arguments, "query");
+    let count = get_validated_argument::<usize>(&validated_arguments, "count")
+      .unwrap_or(DEFAULT_RESULT_COUNT);
+
+    let (tx, session_id) = (params.tx, params.session_id);
+    let config = params.session_config;
+    let workspace_root = config.workspace.map(|workspace| workspace.workspace_path);
+    let model = ProviderFixtures::open(&config.provider)
+      .map(|fixtures| EmbeddingModel::Ada002(config.openai.client_config().config, fixtures))
+      .map_err(|e| e.to_string());
+    let hybrid = config.hybrid_search.clone();
+    let store =
+      open_vector_store(&config.vector_store, &config.database_url).map_err(|e| e.to_string());
+
+    Box::pin(async move {
+      let Some(query) = query else {
+        return Err(ToolCallError::new("query argument is required"));
+      };
+      let Some(workspace_root) = workspace_root else {
+        return Err(ToolCallError::new("semantic search needs a workspace"));
+      };
+      let store = store.map_err(|e| ToolCallError::new(&e))?;
+      let model = model.map_err(|e| ToolCallError::new(&e))?;
+
+      // the session indexes the workspace in the background the first time, and files again as
+      // they change, so the search does not wait for files to be embedded
+      let index = SessionAction::IndexWorkspace(session_id);
+      tx.send(ChatToolAction::SessionAction(Box::new(index))).unwrap();
+
+      let prefix = workspace_root.join("").to_string_lossy().to_string();
+      let files = store.files().await.map_err(|e| ToolCallError::new(&e.to_string()))?;
+      if !files.iter().any(|(filepath, _)| filepath.starts_with(&prefix)) {
+        return Ok(Some(
+          "the workspace is being indexed for the first time, search again in a minute".into(),
+        ));
+      }
+      let results = semantic_search::search(
+        store.as_ref(),
+        &model,
+        &query,
+        count,
+        Some(workspace_root.as_path()),
+        &hybrid,
+      )
+      .await
+      .map_err(|e| ToolCallError::new(&format!("error searching workspace: {}", e)))?;
+      Ok(Some(semantic_search::format_results(&results, &workspace_root)))
+    })
+  }
+}
@@ -0,0 +1,205 @@
+use futures_util::Future;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::time::Duration;
+
+use async_openai::types::{ChatCompletionTool, ChatCompletionToolType, FunctionObject};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+lazy_static! {
+  static ref PLACEHOLDER: Regex = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+}
+
+fn default_timeout_secs() -> u64 {
+  60
+}
+
+/// a tool declared in the config that runs a command in the workspace
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShellToolConfig {
+  pub name: String,
+  pub description: String,
+  /// the json schema of the arguments, none if not set
+  #[serde(default)]
+  pub parameters: Option<Value>,
+  /// the command, with `{argument}` where an argument goes. it is split into words like a shell
+  /// would, but not run by one, so arguments are never read as shell syntax
+  pub command: String,
+  #[serde(default = "default_timeout_secs")]
+  pub timeout_secs: u64,
+}
+
+pub struct ShellTool {
+  pub config: ShellToolConfig,
+}
+
+impl ShellTool {
+  pub fn new(config: ShellToolConfig) -> Self {
+    ShellTool { config }
+  }
+}
+
+impl ToolCallTrait for ShellTool {
+  // shell tools are made from their config with `new`, this one runs nothing
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    ShellTool::new(ShellToolConfig {
+      name: "shell".to_string(),
+      description: String::new(),
+      parameters: None,
+      command: String::new(),
+      timeout_secs: default_timeout_secs(),
+    })
+  }
+
+  fn name(&self) -> &str {
+    &self.config.name
+  }
+
+  // the declared schema is sent as it is, see to_chat_completion_tool
+  fn parameters(&self) -> FunctionProperty {
+    FunctionProperty::Parameters { properties: HashMap::new() }
+  }
+
+  fn description(&self) -> String {
+    self.config.description.clone()
+  }
+
+  fn to_chat_completion_tool(&self) -> Result<ChatCompletionTool, ToolCallError> {
+    Ok(ChatCompletionTool {
+      r#type: ChatCompletionToolType::Function,
+      function: FunctionObject {
+        name: self.config.name.clone(),
+        description: Some(self.config.description.clone()),
+        parameters: Some(
+          self.config.parameters.clone().unwrap_or(json!({ "type": "object", "properties": {} })),
+        ),
+      },
+    })
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let words = command_words(&self.config.command, &params.function_args);
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
+    let timeout = Duration::from_secs(self.config.timeout_secs);
+    let name = self.config.name.clone();
+
+    Box::pin(async move {
+      let words = words?;
+      let Some((program, args)) = words.split_first() else {
+        return Err(ToolCallError::new(&format!("{} has no command", name)));
+      };
+      let mut command = tokio::process::Command::new(program);
+      command.args(args).stdin(Stdio::null()).kill_on_drop(true);
+      if let Some(workspace_root) = workspace_root {
+        command.current_dir(workspace_root);
+      }
+      let output = match tokio::time::timeout(timeout, command.output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+          return Err(ToolCallError::new(&format!(
+            "{} did not finish within {} seconds",
+            name,
+            timeout.as_secs()
+          )))
+        },
+      };
+      Ok(Some(format_output(&output)))
+    })
+  }
+}
+
+/// the words of a command template with the arguments put in. a word that is only a
+/// placeholder is left out when its argument is not given, and is one word for each item of an
+/// array
+pub fn command_words(
+  template: &str,
+  arguments: &HashMap<String, Value>,
+) -> Result<Vec<String>, ToolCallError> {
+  let words = shell_words::split(template)
+    .map_err(|e| ToolCallError::new(&format!("invalid command {}: {}", template, e)))?;
+  let mut command = vec![];
+  for word in words {
+    if let Some(captures) = PLACEHOLDER.captures(&word).filter(|c| c[0].len() == word.len()) {
+      match arguments.get(&captures[1]) {
+        Some(Value::Array(items)) => command.extend(items.iter().map(argument_text)),
+        Some(value) => command.push(argument_text(value)),
+        None => {},
+      }
+      continue;
+    }
+    let mut missing = None;
+    let word = PLACEHOLDER.replace_all(&word, |captures: &regex::Captures| {
+      match arguments.get(&captures[1]) {
+        Some(value) => argument_text(value),
+        None => {
+          missing = Some(captures[1].to_string());
+          String::new()
+        },
+      }
+    });
+    if let Some(missing) = missing {
+      return Err(ToolCallError::new(&format!("the command needs the argument {}", missing)));
+    }
+    command.push(word.into_owned());
+  }
+  Ok(command)
+}
+
+fn argument_text(value: &Value) -> String {
+  match value {
+    Value::String(text) => text.clone(),
+    Value::Null => String::new(),
+    value => value.to_string(),
+  }
+}
+
+fn format_output(output: &std::process::Output) -> String {
+  let status = match output.status.code() {
+    Some(code) => format!("exit code {}", code),
+    None => "terminated by a signal".to_string(),
+  };
+  let mut text = format!("{}\n{}", status, String::from_utf8_lossy(&output.stdout));
+  if !output.stderr.is_empty() {
+    text.push_str(&format!("\nstderr:\n{}", String::from_utf8_lossy(&output.stderr)));
+  }
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_command_words() {
+    let arguments = HashMap::from([
+      ("test".to_string(), json!("session; rm -rf /")),
+      ("features".to_string(), json!(["a", "b"])),
+      ("jobs".to_string(), json!(4)),
+    ]);
+    assert_eq!(
+      command_words("cargo test {test} --features {features} -j{jobs} {release}", &arguments)
+        .unwrap(),
+      vec!["cargo", "test", "session; rm -rf /", "--features", "a", "b", "-j4"]
+    );
+    assert_eq!(
+      command_words("sh -c 'echo \"$0\"' {test}", &arguments).unwrap(),
+      vec!["sh", "-c", "echo \"$0\"", "session; rm -rf /"]
+    );
+    assert!(command_words("make --target={target}", &arguments).is_err());
+    assert!(command_words("echo 'unclosed", &arguments).is_err());
+  }
+}
@@ -0,0 +1,111 @@
+use futures_util::Future;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use crate::action::{ChatToolAction, LsiAction};
+use crate::app::file_summary::{self, SUMMARY_THRESHOLD_BYTES};
+use crate::app::lsi::query::LsiQuery;
+
+use super::errors::ToolCallError;
+use super::tool_call::{ToolCallParams, ToolCallTrait};
+use super::types::*;
+
+#[derive(Serialize, Deserialize)]
+pub struct SummarizeFile {
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+impl ToolCallTrait for SummarizeFile {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
+    SummarizeFile {
+      name: "summarize_file".to_string(),
+      description: format!("summarize the structure of a file instead of reading all of it. returns the imports and public symbols with their signatures and line numbers, or the section headings of documents. files smaller than {} bytes are returned in full. use this to decide which parts of a large file to read", SUMMARY_THRESHOLD_BYTES),
+      parameters: FunctionProperty::Parameters {
+        properties: HashMap::from([(
+          "file_path".to_string(),
+          FunctionProperty::PathBuf {
+            required: true,
+            description: Some("path of the file to summarize, relative to the workspace root".to_string()),
+          },
+        )]),
+      },
+    }
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn call(
+    &self,
+    params: ToolCallParams,
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let timeout = Some(params.session_config.tools.timeout(self.name()));
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+
+    let file_path = get_validated_argument::<PathBuf>(&validated_arguments, "file_path");
+    let workspace_root =
+      params.session_config.workspace.as_ref().map(|workspace| workspace.workspace_path.clone());
+
+    Box::pin(async move {
+      let file_path = match (file_path, &workspace_root) {
+        (Some(file_path), Some(root)) if file_path.is_relative() => root.join(file_path),
+        (Some(file_path), _) => file_path,
+        (None, _) => return Err(ToolCallError::new("file_path argument is required")),
+      };
+      let size = match std::fs::metadata(&file_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return Ok(Some(format!("error reading {}: {}", file_path.display(), e))),
+      };
+      if size < SUMMARY_THRESHOLD_BYTES {
+        return match std::fs::read_to_string(&file_path) {
+          Ok(text) => Ok(Some(text)),
+          Err(e) => Ok(Some(format!("error reading {}: {}", file_path.display(), e))),
+        };
+      }
+
+      match workspace_root {
+        // source files in a workspace are summarized from their language server symbols
+        Some(workspace_root) if !file_summary::is_document(&file_path) => {
+          let query = LsiQuery {
+            workspace_root,
+            tool_call_id: params.tool_call_id,
+            session_id: params.session_id,
+            timeout,
+            ..Default::default()
+          };
+          params
+            .tx
+            .send(ChatToolAction::LsiRequest(Box::new(LsiAction::SummarizeFile(file_path, query))))
+            .unwrap();
+          Ok(None)
+        },
+        _ => match std::fs::read_to_string(&file_path) {
+          Ok(text) => {
+            let summary = file_summary::summarize(&file_path, &text, None);
+            serde_json::to_string(&summary)
+              .map(Some)
+              .map_err(|e| ToolCallError::new(&format!("error serializing summary: {}", e)))
+          },
+          Err(e) => Ok(Some(format!("error reading {}: {}", file_path.display(), e))),
+        },
+      }
+    })
+  }
+}
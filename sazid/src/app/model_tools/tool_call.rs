@@ -9,23 +9,18 @@ use async_openai::types::{
 use lsp_types::CreateFile;
 use serde_json::Value;
 use std::{any::Any, collections::HashMap, pin::Pin, sync::Arc};
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::{sync::mpsc::UnboundedSender, task::AbortHandle};
+use tracing::Instrument;
 
 use futures_util::Future;
 
 use crate::app::session_config::SessionConfig;
 
 use super::{
-  create_file_function::CreateFileFunction,
   errors::ToolCallError,
-  lsp_get_diagnostics::LspGetDiagnostics,
-  lsp_get_workspace_files::LspGetWorkspaceFiles,
-  lsp_goto_symbol_declaration::LspGotoSymbolDeclaration,
-  lsp_goto_symbol_definition::LspGotoSymbolDefinition,
-  lsp_goto_type_definition::LspGotoTypeDefinition,
-  lsp_query_symbols::LspQuerySymbol,
-  lsp_read_symbol_source::LspReadSymbolSource,
-  lsp_replace_symbol_text::LspReplaceSymbolText,
+  shell_tool::ShellTool,
+  tool_permissions::ToolDecision,
+  tool_registry::{ToolCallRejection, ToolRegistry},
   types::{FunctionProperty, ToolCall},
 };
 
@@ -85,7 +80,10 @@ pub struct ToolCallParams {
 pub struct ChatTools {
   pub tx: UnboundedSender<ChatToolAction>,
   config: HashMap<i64, SessionConfig>,
-  tools: Vec<Arc<dyn ToolCallTrait + 'static>>,
+  // the tools of each session, the built in tools and the shell tools its config declares
+  registries: HashMap<i64, ToolRegistry>,
+  // the tasks of the tool calls running, by tool call id, with the session that made them
+  tasks: HashMap<String, (i64, AbortHandle)>,
 }
 
 impl ChatTools {
@@ -94,34 +92,31 @@ impl ChatTools {
     session_id: i64,
     session_config: SessionConfig,
   ) -> Self {
-    let tools = Self::all_tools().unwrap();
-    let mut config: HashMap<i64, SessionConfig> = HashMap::new();
-    config.insert(session_id, session_config);
-
-    Self { tx, config, tools }
-  }
-
-  pub fn all_tools() -> Result<Vec<Arc<dyn ToolCallTrait + 'static>>, ToolCallError> {
-    Ok(vec![
-      // Arc::new(CargoCheckFunction::init()),
-      // Arc::new(Pcre2GrepFunction::init()),
-      // Arc::new(FileSearchFunction::init()),
-      Arc::new(LspGetWorkspaceFiles::init()),
-      Arc::new(LspQuerySymbol::init()),
-      Arc::new(CreateFileFunction::init()),
-      Arc::new(LspReplaceSymbolText::init()),
-      Arc::new(LspGotoSymbolDefinition::init()),
-      Arc::new(LspGotoSymbolDeclaration::init()),
-      Arc::new(LspGotoTypeDefinition::init()),
-      Arc::new(LspGetDiagnostics::init()),
-      // Arc::new(ReadFileLinesFunction::init()),
-    ])
+    let mut chat_tools =
+      Self { tx, config: HashMap::new(), registries: HashMap::new(), tasks: HashMap::new() };
+    chat_tools.upsert_configs(session_id, session_config);
+    chat_tools
   }
 
+  /// keeps the config of a session, its tools are registered again so the shell tools it no
+  /// longer declares are gone
   pub fn upsert_configs(&mut self, session_id: i64, config: SessionConfig) {
+    self.registries.insert(session_id, self.session_registry(&config));
     self.config.insert(session_id, config);
   }
 
+  // the built in tools and the shell tools declared in a config. one named like a built in tool
+  // is not registered
+  fn session_registry(&self, config: &SessionConfig) -> ToolRegistry {
+    let mut registry = ToolRegistry::builtin().unwrap();
+    for tool_config in &config.shell_tools {
+      if let Err(e) = registry.register(Arc::new(ShellTool::new(tool_config.clone()))) {
+        Self::send_chat_tool_error(self.tx.clone(), &e, None);
+      }
+    }
+    registry
+  }
+
   pub fn handle_action(
     &mut self,
     action: ChatToolAction,
@@ -141,10 +136,30 @@ impl ChatTools {
         self.handle_tool_call(&tool_call, session_id);
         Ok(None)
       },
+      ChatToolAction::ToolCallConfirmed(tool_call, session_id, true) => {
+        self.run_tool_call(&tool_call, session_id);
+        Ok(None)
+      },
+      ChatToolAction::ToolCallConfirmed(tool_call, session_id, false) => {
+        let declined = format!("the user did not allow the {} call", tool_call.function.name);
+        self.complete_tool_call(declined, false, tool_call.id, session_id);
+        Ok(None)
+      },
+      ChatToolAction::AbortToolCalls(session_id) => {
+        self.tasks.retain(|tool_call_id, (id, task)| {
+          if *id == session_id {
+            log::debug!("aborting tool call {}", tool_call_id);
+            task.abort();
+          }
+          *id != session_id
+        });
+        Ok(None)
+      },
       ChatToolAction::ToolListRequest(session_id) => {
-        let tools = self
-          .tools
-          .iter()
+        let (config, registry) = self.validate_session_tool_config(session_id)?;
+        let tools = registry
+          .tools()
+          .filter(|tool| config.tool_enabled(tool.name()))
           .map(|tool| tool.to_chat_completion_tool())
           .collect::<Result<Vec<ChatCompletionTool>, ToolCallError>>()?;
         // log::debug!("tools request: {:#?}", tools);
@@ -178,11 +193,9 @@ impl ChatTools {
     session_id: i64,
   ) -> Result<Option<Vec<ChatCompletionTool>>, ToolCallError> {
     let tools: Vec<_> = match self.validate_session_tool_config(session_id) {
-      Ok(config) => self
-        .tools
-        .iter()
-        .filter(|tool| !config.disabled_tools.contains(&tool.name().to_string()))
-        .collect(),
+      Ok((config, registry)) => {
+        registry.tools().filter(|tool| config.tool_enabled(tool.name())).collect()
+      },
       Err(e) => {
         Self::send_chat_tool_error(self.tx.clone(), &e, None);
         return Err(e);
@@ -197,26 +210,28 @@ impl ChatTools {
     }
   }
 
-  fn validate_session_tool_config(&self, session_id: i64) -> Result<&SessionConfig, ToolCallError> {
-    let config = match self.config.get(&session_id) {
-      Some(config) => config,
-      None => {
-        return Err(ToolCallError::new(
-          format!(
-            "session config not found.\nrequested id: {}\nconfig: {:#?}",
-            session_id, self.config
-          )
-          .as_str(),
-        ));
-      },
+  fn validate_session_tool_config(
+    &self,
+    session_id: i64,
+  ) -> Result<(&SessionConfig, &ToolRegistry), ToolCallError> {
+    let (Some(config), Some(registry)) =
+      (self.config.get(&session_id), self.registries.get(&session_id))
+    else {
+      return Err(ToolCallError::new(
+        format!(
+          "session config not found.\nrequested id: {}\nconfig: {:#?}",
+          session_id, self.config
+        )
+        .as_str(),
+      ));
     };
 
     for tool in config.disabled_tools.clone() {
-      if !self.tools.iter().any(|tool_func| *tool_func.name().to_string() == tool) {
+      if registry.get(&tool).is_none() {
         return Err(ToolCallError::new(&format!("disabled tool not found: {}", tool)));
       }
     }
-    Ok(config)
+    Ok((config, registry))
   }
 
   pub fn get_tool_by_name(
@@ -225,20 +240,15 @@ impl ChatTools {
     session_id: i64,
   ) -> Result<Option<Arc<dyn ToolCallTrait + 'static>>, ToolCallError> {
     match self.validate_session_tool_config(session_id) {
-      Ok(config) => Ok(
-        self
-          .tools
-          .iter()
-          .filter(|tool| !config.disabled_tools.contains(&tool.name().to_string()))
-          .find(|tool| tool.name() == tool_name)
-          .cloned(),
-      ),
+      Ok((config, registry)) => {
+        Ok(registry.get(tool_name).filter(|tool| config.tool_enabled(tool.name())).cloned())
+      },
       Err(e) => Err(e),
     }
   }
 
   pub fn call_tool(
-    &self,
+    &mut self,
     tool_name: String,
     tool_args: HashMap<String, Value>,
     tool_call_id: String,
@@ -264,12 +274,14 @@ impl ChatTools {
     };
 
     let tx = self.tx.clone();
+    let span = tracing::info_span!("tool_call", tool = %tool_name, %tool_call_id, session_id);
 
     match self.get_tool_by_name(tool_name.as_str(), session_id) {
       Ok(Some(tool)) => {
-        let tool_call_id = tool_call_id.clone();
         let tool = tool.clone();
-        tokio::spawn(async move {
+        let call_id = tool_call_id.clone();
+        let run_tool = async move {
+          let tool_call_id = call_id;
           let tool_call_result = tool
             .call(ToolCallParams {
               tx: tx.clone(),
@@ -296,7 +308,10 @@ impl ChatTools {
               Self::send_chat_tool_error(tx.clone(), &e, Some((session_id, tool_call_id)));
             },
           }
-        });
+        };
+        let task = tokio::spawn(run_tool.instrument(span));
+        self.tasks.retain(|_, (_, task)| !task.is_finished());
+        self.tasks.insert(tool_call_id, (session_id, task.abort_handle()));
       },
       Ok(None) => {
         Self::send_chat_tool_error(
@@ -361,38 +376,93 @@ impl ChatTools {
       .unwrap()
   }
 
-  pub fn handle_tool_call(&self, tool_call: &ChatCompletionMessageToolCall, session_id: i64) {
-    let function_args_result: Result<HashMap<String, serde_json::Value>, serde_json::Error> =
-      serde_json::from_str(tool_call.function.arguments.as_str());
+  /// runs a call the model made, unless the `[tools]` config refuses it or asks the user first
+  pub fn handle_tool_call(&mut self, tool_call: &ChatCompletionMessageToolCall, session_id: i64) {
+    // a call the registry rejects is refused before the user is asked to confirm it
+    let Some((config, function_args)) = self.validate_tool_call(tool_call, session_id) else {
+      return;
+    };
+    let workspace = config.workspace.as_ref().map(|workspace| workspace.workspace_path.as_path());
+    match config.tools.decide(&tool_call.function.name, &function_args, workspace) {
+      ToolDecision::Run => self.run_tool_call(tool_call, session_id),
+      ToolDecision::Confirm => {
+        self.tx.send(ChatToolAction::ConfirmToolCall(tool_call.clone(), session_id)).unwrap()
+      },
+      ToolDecision::Refuse(reason) => {
+        log::warn!("refused tool call {}: {}", tool_call.id, reason);
+        self.complete_tool_call(reason, false, tool_call.id.clone(), session_id);
+      },
+    }
+  }
 
-    match function_args_result {
-      Ok(function_args) => {
-        log::debug!(
-          "handle tool call: call id: {} session id: {}",
-          tool_call.id.clone(),
-          session_id
-        );
+  fn run_tool_call(&mut self, tool_call: &ChatCompletionMessageToolCall, session_id: i64) {
+    let Some((_, function_args)) = self.validate_tool_call(tool_call, session_id) else {
+      return;
+    };
+    log::debug!("handle tool call: call id: {} session id: {}", tool_call.id.clone(), session_id);
+    self.call_tool(
+      tool_call.function.name.clone(),
+      function_args,
+      tool_call.id.clone(),
+      session_id,
+    );
+  }
 
-        self.call_tool(
-          tool_call.function.name.clone(),
-          function_args,
-          tool_call.id.clone(),
-          session_id,
-        );
-      },
-      Err(e) => {
-        Self::send_chat_tool_error(
-          self.tx.clone(),
-          &ToolCallError::new(
-            format!(
-              "Failed to parse function arguments:\nfunction:{:?}\nargs:{:?}\nerror:{:?}",
-              tool_call.function.name, tool_call.function.arguments, e
-            )
-            .as_str(),
-          ),
-          Some((session_id, tool_call.id.clone())),
-        );
+  // the config of the session and the checked arguments of a call. a call that is rejected is
+  // completed with the reason, so the model can make it again
+  fn validate_tool_call(
+    &self,
+    tool_call: &ChatCompletionMessageToolCall,
+    session_id: i64,
+  ) -> Option<(&SessionConfig, HashMap<String, Value>)> {
+    let (Some(config), Some(registry)) =
+      (self.config.get(&session_id), self.registries.get(&session_id))
+    else {
+      let error = format!("session config not found, session id: {}", session_id);
+      self.complete_tool_call(error, true, tool_call.id.clone(), session_id);
+      return None;
+    };
+    let function = &tool_call.function;
+    match registry.validate_call(&function.name, &function.arguments, config) {
+      Ok((_, function_args)) => Some((config, function_args)),
+      Err(rejection) => {
+        log::warn!("rejected tool call {}: {}", tool_call.id, rejection);
+        // a path the guard refuses is shown to the user too
+        if let ToolCallRejection::RefusedPath { reason, .. } = &rejection {
+          let error = format!("refused {} call: {}", function.name, reason);
+          Self::send_chat_tool_error(self.tx.clone(), &ToolCallError::new(&error), None);
+        }
+        self.complete_tool_call(rejection.to_string(), false, tool_call.id.clone(), session_id);
+        None
       },
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::model_tools::shell_tool::ShellToolConfig;
+
+  #[test]
+  fn test_shell_tools_are_offered_to_the_sessions_declaring_them() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let deploy = ShellToolConfig {
+      name: "deploy".to_string(),
+      description: "deploys the workspace".to_string(),
+      parameters: None,
+      command: "true".to_string(),
+      timeout_secs: 5,
+    };
+    let config = SessionConfig { shell_tools: vec![deploy], ..Default::default() };
+    let mut chat_tools = ChatTools::new(tx, 1, config.clone());
+    chat_tools.upsert_configs(2, SessionConfig::default());
+    assert!(chat_tools.get_tool_by_name("deploy", 1).unwrap().is_some());
+    assert!(chat_tools.get_tool_by_name("deploy", 2).unwrap().is_none());
+
+    // a tool the config no longer declares is gone
+    chat_tools.upsert_configs(1, SessionConfig { shell_tools: vec![], ..config });
+    assert!(chat_tools.get_tool_by_name("deploy", 1).unwrap().is_none());
+    assert!(chat_tools.get_tool_by_name("read_file", 1).unwrap().is_some());
+  }
+}
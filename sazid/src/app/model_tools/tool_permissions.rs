@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app::changelog::EDIT_TOOL_NAMES;
+use crate::app::session_config::SessionConfig;
+
+use super::path_guard::{path_arguments, PathGuardConfig};
+
+// how long a tool waiting on a language server runs when `timeouts` does not name it
+const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 60;
+
+/// whether a tool call runs, waits for the user to allow it, or is refused
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+  #[default]
+  Auto,
+  Confirm,
+  /// the tool is not offered to the model, and calls to it are refused
+  Deny,
+}
+
+/// the `[tools]` section of the config: which tools the model is offered, what each may do
+/// without asking, and where edits may write
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ToolsConfig {
+  /// tools that are not offered to the model, as with `disabled_tools`
+  pub disabled: Vec<String>,
+  /// the permission of the tools not named in `permissions`
+  pub default_permission: ToolPermission,
+  pub permissions: BTreeMap<String, ToolPermission>,
+  /// edits to files outside of the workspace are refused
+  pub deny_writes_outside_workspace: bool,
+  /// edits to files under these paths are refused, relative paths are in the workspace
+  pub deny_write_paths: Vec<PathBuf>,
+  /// keeps every path a call names in the workspaces, see `PathGuardConfig`
+  pub path_guard: PathGuardConfig,
+  /// seconds a call waiting on a language server may take, by tool name. a call that takes
+  /// longer is cancelled, and the model is told it timed out
+  pub timeouts: BTreeMap<String, u64>,
+}
+
+/// what the tool executor does with a call, see `ToolsConfig::decide`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolDecision {
+  Run,
+  Confirm,
+  /// refused, the reason is sent to the model as the result of the call
+  Refuse(String),
+}
+
+impl ToolsConfig {
+  pub fn permission(&self, tool_name: &str) -> ToolPermission {
+    self.permissions.get(tool_name).copied().unwrap_or(self.default_permission)
+  }
+
+  /// how long a call to `tool_name` may wait on a language server
+  pub fn timeout(&self, tool_name: &str) -> Duration {
+    Duration::from_secs(self.timeouts.get(tool_name).copied().unwrap_or(DEFAULT_TOOL_TIMEOUT_SECS))
+  }
+
+  /// whether the tool is offered to the model
+  pub fn is_offered(&self, tool_name: &str) -> bool {
+    !self.disabled.iter().any(|name| name == tool_name)
+      && self.permission(tool_name) != ToolPermission::Deny
+  }
+
+  /// decides on a call to `tool_name` with `arguments`, refusing edits to paths it may not write
+  pub fn decide(
+    &self,
+    tool_name: &str,
+    arguments: &HashMap<String, Value>,
+    workspace: Option<&Path>,
+  ) -> ToolDecision {
+    if !self.is_offered(tool_name) {
+      return ToolDecision::Refuse(format!("the {} tool is not permitted", tool_name));
+    }
+    let refused_write = written_paths(tool_name, arguments)
+      .into_iter()
+      .find_map(|path| self.write_refusal(&path, workspace));
+    if let Some(reason) = refused_write {
+      return ToolDecision::Refuse(reason);
+    }
+    match self.permission(tool_name) {
+      ToolPermission::Auto => ToolDecision::Run,
+      ToolPermission::Confirm => ToolDecision::Confirm,
+      ToolPermission::Deny => {
+        ToolDecision::Refuse(format!("the {} tool is not permitted", tool_name))
+      },
+    }
+  }
+
+  fn write_refusal(&self, path: &Path, workspace: Option<&Path>) -> Option<String> {
+    let resolve = |path: &Path| match workspace {
+      Some(workspace) if path.is_relative() => helix_stdx::path::normalize(workspace.join(path)),
+      _ => helix_stdx::path::normalize(path),
+    };
+    let resolved = resolve(path);
+    if let Some(workspace) = workspace.filter(|_| self.deny_writes_outside_workspace) {
+      if !resolved.starts_with(helix_stdx::path::normalize(workspace)) {
+        return Some(format!(
+          "writing {} outside of the workspace is not permitted",
+          path.display()
+        ));
+      }
+    }
+    self
+      .deny_write_paths
+      .iter()
+      .find(|denied| resolved.starts_with(resolve(denied)))
+      .map(|denied| format!("writing under {} is not permitted", denied.display()))
+  }
+}
+
+/// the write restrictions of a session, for an edit whose file is only known once its target is
+/// found, like the file of the symbol `lsp_replace_symbol_text` replaces
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WriteGuard {
+  tools: ToolsConfig,
+  // the workspaces of the session, relative paths are in the first
+  roots: Vec<PathBuf>,
+}
+
+impl WriteGuard {
+  pub fn new(config: &SessionConfig) -> Self {
+    let roots = config.all_workspaces().iter().map(|w| w.workspace_path.clone()).collect();
+    WriteGuard { tools: config.tools.clone(), roots }
+  }
+
+  /// refuses writing to `path` where a call naming it would be refused
+  pub fn check(&self, path: &Path) -> Result<(), String> {
+    if let Some(reason) = self.tools.write_refusal(path, self.roots.first().map(PathBuf::as_path)) {
+      return Err(reason);
+    }
+    let roots = self.roots.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+    let arguments = HashMap::from([("path".to_string(), Value::from(path.to_string_lossy()))]);
+    self.tools.path_guard.check(&arguments, &roots)
+  }
+}
+
+// the files a call to an edit tool writes to, an edit of a symbol names none
+fn written_paths(tool_name: &str, arguments: &HashMap<String, Value>) -> Vec<PathBuf> {
+  match tool_name {
+    // modify_file without content only reads the file
+    "modify_file" if !arguments.contains_key("content") => vec![],
+    _ if EDIT_TOOL_NAMES.contains(&tool_name) => path_arguments(arguments),
+    _ => vec![],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::session_config::WorkspaceParams;
+  use serde_json::json;
+
+  fn arguments(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn test_tool_calls_are_decided_by_the_config() {
+    let config: ToolsConfig = toml::from_str(
+      r#"
+        disabled = ["grep"]
+        deny_writes_outside_workspace = true
+        deny_write_paths = ["secrets"]
+
+        [permissions]
+        create_file = "confirm"
+        shell = "deny"
+
+        [timeouts]
+        lsp_get_diagnostics = 5
+      "#,
+    )
+    .unwrap();
+    let workspace = Some(Path::new("/work"));
+    let file = |path: &str| arguments(json!({ "path": path, "content": "" }));
+
+    assert!(!config.is_offered("grep") && !config.is_offered("shell"));
+    assert_eq!(config.decide("read_file", &file("/etc/passwd"), workspace), ToolDecision::Run);
+    assert_eq!(config.decide("create_file", &file("src/lib.rs"), workspace), ToolDecision::Confirm);
+    assert!(matches!(
+      config.decide("create_file", &file("../outside.rs"), workspace),
+      ToolDecision::Refuse(reason) if reason.contains("outside of the workspace")
+    ));
+    let edits = arguments(json!({ "edits": [{ "path": "src/a.rs" }, { "path": "secrets/key" }] }));
+    assert!(matches!(config.decide("multi_edit", &edits, workspace), ToolDecision::Refuse(_)));
+    assert!(matches!(config.decide("shell", &HashMap::new(), workspace), ToolDecision::Refuse(_)));
+    assert_eq!(config.timeout("lsp_get_diagnostics"), Duration::from_secs(5));
+    assert_eq!(config.timeout("lsp_query_symbols"), Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS));
+  }
+
+  #[test]
+  fn test_symbol_edits_are_checked_against_the_file_of_the_symbol() {
+    let workspace = tempdir::TempDir::new("test_write_guard").unwrap();
+    let mut config = SessionConfig {
+      workspace: Some(WorkspaceParams {
+        workspace_path: workspace.path().to_path_buf(),
+        language: "rust".to_string(),
+        language_server: "rust-analyzer".to_string(),
+        doc_path: None,
+        name: None,
+      }),
+      ..Default::default()
+    };
+    config.tools.deny_writes_outside_workspace = true;
+    config.tools.deny_write_paths = vec![PathBuf::from("vendor")];
+
+    // the call names no file, so it is decided on the file of the symbol once it is found
+    let call = arguments(json!({ "symbol_id": [0; 32], "replacement_text": "" }));
+    let decision = config.tools.decide("lsp_replace_symbol_text", &call, Some(workspace.path()));
+    assert_eq!(decision, ToolDecision::Run);
+    let guard = WriteGuard::new(&config);
+    assert!(guard.check(&workspace.path().join("src/lib.rs")).is_ok());
+    assert!(guard.check(Path::new("/etc/hosts")).unwrap_err().contains("outside"));
+    assert!(guard.check(&workspace.path().join("vendor/lib.rs")).is_err());
+    assert!(guard.check(&workspace.path().join(".env")).unwrap_err().contains("denied"));
+  }
+}
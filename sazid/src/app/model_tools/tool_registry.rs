@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use jsonschema::{Draft, JSONSchema};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::app::session_config::SessionConfig;
+
+use super::{
+  create_file_function::CreateFileFunction, errors::ToolCallError, grep_function::GrepFunction,
+  list_directory::ListDirectory, lsp_document_highlight::LspDocumentHighlight,
+  lsp_get_diagnostics::LspGetDiagnostics, lsp_get_workspace_files::LspGetWorkspaceFiles,
+  lsp_goto_implementation::LspGotoImplementation,
+  lsp_goto_symbol_declaration::LspGotoSymbolDeclaration,
+  lsp_goto_symbol_definition::LspGotoSymbolDefinition,
+  lsp_goto_type_definition::LspGotoTypeDefinition, lsp_inlay_hints::LspInlayHints,
+  lsp_query_symbols::LspQuerySymbol, lsp_replace_symbol_text::LspReplaceSymbolText,
+  lsp_search_symbols_all::LspSearchSymbolsAll, lsp_signature_help::LspSignatureHelp,
+  modify_file_function::ModifyFileFunction, multi_edit_function::MultiEditFunction,
+  patch_file_function::PatchFileFunction, read_file_text::ReadFileText,
+  semantic_search::SemanticSearch, summarize_file::SummarizeFile, tool_call::ToolCallTrait,
+  treesitter_function::TreesitterFunction,
+};
+
+struct RegisteredTool {
+  tool: Arc<dyn ToolCallTrait>,
+  schema: Value,
+  validator: JSONSchema,
+}
+
+/// the tools the model can call by name, each with the json schema of its arguments. calls
+/// are checked against the schema before the tool runs
+#[derive(Default)]
+pub struct ToolRegistry {
+  tools: BTreeMap<String, RegisteredTool>,
+}
+
+/// where the arguments of a call did not match the schema of the tool, `path` is a json
+/// pointer to the argument, empty for the arguments as a whole
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ArgumentError {
+  pub path: String,
+  pub message: String,
+}
+
+/// why a tool call was not run. it is returned to the model as json, so it can correct the call.
+/// a call naming a path outside of the workspaces, or one the path guard denies, is refused
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum ToolCallRejection {
+  UnknownTool { tool: String, tools: Vec<String> },
+  InvalidArguments { tool: String, errors: Vec<ArgumentError>, schema: Value },
+  RefusedPath { tool: String, reason: String },
+}
+
+impl fmt::Display for ToolCallRejection {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Tool Call Error: {}", serde_json::to_string(self).map_err(|_| fmt::Error)?)
+  }
+}
+
+impl ToolRegistry {
+  /// the tools built into sazid
+  pub fn builtin() -> Result<Self, ToolCallError> {
+    let mut registry = ToolRegistry::default();
+    let tools: Vec<Arc<dyn ToolCallTrait>> = vec![
+      Arc::new(LspGetWorkspaceFiles::init()),
+      Arc::new(LspQuerySymbol::init()),
+      Arc::new(LspSearchSymbolsAll::init()),
+      Arc::new(SummarizeFile::init()),
+      Arc::new(ReadFileText::init()),
+      Arc::new(GrepFunction::init()),
+      Arc::new(CreateFileFunction::init()),
+      Arc::new(ModifyFileFunction::init()),
+      Arc::new(PatchFileFunction::init()),
+      Arc::new(MultiEditFunction::init()),
+      Arc::new(LspReplaceSymbolText::init()),
+      Arc::new(LspGotoSymbolDefinition::init()),
+      Arc::new(LspGotoSymbolDeclaration::init()),
+      Arc::new(LspGotoTypeDefinition::init()),
+      Arc::new(LspGotoImplementation::init()),
+      Arc::new(LspDocumentHighlight::init()),
+      Arc::new(LspSignatureHelp::init()),
+      Arc::new(LspInlayHints::init()),
+      Arc::new(LspGetDiagnostics::init()),
+      Arc::new(SemanticSearch::init()),
+      Arc::new(TreesitterFunction::init()),
+      Arc::new(ListDirectory::init()),
+    ];
+    for tool in tools {
+      registry.register(tool)?;
+    }
+    Ok(registry)
+  }
+
+  /// adds a tool, there can only be one tool of a name
+  pub fn register(&mut self, tool: Arc<dyn ToolCallTrait>) -> Result<(), ToolCallError> {
+    let name = tool.name().to_string();
+    if self.tools.contains_key(&name) {
+      return Err(ToolCallError::new(&format!("a tool named {} is already registered", name)));
+    }
+    let schema = tool
+      .to_chat_completion_tool()?
+      .function
+      .parameters
+      .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+    let validator = JSONSchema::options()
+      .with_draft(Draft::Draft7)
+      .compile(&schema)
+      .map_err(|e| ToolCallError::new(&format!("invalid schema for tool {}: {}", name, e)))?;
+    self.tools.insert(name, RegisteredTool { tool, schema, validator });
+    Ok(())
+  }
+
+  pub fn remove(&mut self, name: &str) -> Option<Arc<dyn ToolCallTrait>> {
+    self.tools.remove(name).map(|registered| registered.tool)
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Arc<dyn ToolCallTrait>> {
+    self.tools.get(name).map(|registered| &registered.tool)
+  }
+
+  pub fn schema(&self, name: &str) -> Option<&Value> {
+    self.tools.get(name).map(|registered| &registered.schema)
+  }
+
+  pub fn names(&self) -> Vec<&str> {
+    self.tools.keys().map(String::as_str).collect()
+  }
+
+  pub fn tools(&self) -> impl Iterator<Item = &Arc<dyn ToolCallTrait>> {
+    self.tools.values().map(|registered| &registered.tool)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.tools.is_empty()
+  }
+
+  /// the tool a call is for and its arguments, once they are checked against the tool's schema
+  /// and the path guard of `config`. a call without arguments is taken as a call with none. every
+  /// way a tool is run goes through here, so none of them can get past the guard
+  pub fn validate_call(
+    &self,
+    name: &str,
+    arguments: &str,
+    config: &SessionConfig,
+  ) -> Result<(Arc<dyn ToolCallTrait>, HashMap<String, Value>), ToolCallRejection> {
+    let Some(registered) = self.tools.get(name) else {
+      return Err(ToolCallRejection::UnknownTool {
+        tool: name.to_string(),
+        tools: self.tools.keys().cloned().collect(),
+      });
+    };
+    let invalid = |errors| ToolCallRejection::InvalidArguments {
+      tool: name.to_string(),
+      errors,
+      schema: registered.schema.clone(),
+    };
+    let arguments = if arguments.trim().is_empty() {
+      json!({})
+    } else {
+      serde_json::from_str::<Value>(arguments).map_err(|e| {
+        invalid(vec![ArgumentError {
+          path: String::new(),
+          message: format!("the arguments are not valid json: {}", e),
+        }])
+      })?
+    };
+    if let Err(errors) = registered.validator.validate(&arguments) {
+      return Err(invalid(
+        errors
+          .map(|e| ArgumentError { path: e.instance_path.to_string(), message: e.to_string() })
+          .collect(),
+      ));
+    }
+    let Value::Object(arguments) = arguments else {
+      return Err(invalid(vec![ArgumentError {
+        path: String::new(),
+        message: "the arguments must be a json object".to_string(),
+      }]));
+    };
+    let arguments = arguments.into_iter().collect();
+    let roots = config
+      .all_workspaces()
+      .into_iter()
+      .map(|workspace| workspace.workspace_path.as_path())
+      .collect::<Vec<_>>();
+    config
+      .tools
+      .path_guard
+      .check(&arguments, &roots)
+      .map_err(|reason| ToolCallRejection::RefusedPath { tool: name.to_string(), reason })?;
+    Ok((registered.tool.clone(), arguments))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::session_config::WorkspaceParams;
+
+  #[test]
+  fn test_validate_call() {
+    let mut registry = ToolRegistry::builtin().unwrap();
+    let config = SessionConfig::default();
+    assert!(registry.register(Arc::new(SemanticSearch::init())).is_err());
+
+    let (tool, arguments) = registry
+      .validate_call("semantic_search", r#"{"query":"retry backoff","count":4}"#, &config)
+      .unwrap();
+    assert_eq!(tool.name(), "semantic_search");
+    assert_eq!(arguments["count"], json!(4));
+
+    let Err(ToolCallRejection::InvalidArguments { errors, .. }) =
+      registry.validate_call("semantic_search", r#"{"count":"four"}"#, &config)
+    else {
+      panic!("expected invalid arguments");
+    };
+    let mut paths = errors.iter().map(|e| e.path.as_str()).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, vec!["", "/count"]);
+    assert!(registry
+      .validate_call("semantic_search", r#"{"query":"x","count":64}"#, &config)
+      .is_err());
+    assert!(registry.validate_call("semantic_search", "not json", &config).is_err());
+
+    let rejection = registry.validate_call("pcre2grep", "{}", &config).unwrap_err();
+    assert!(
+      matches!(&rejection, ToolCallRejection::UnknownTool { tools, .. } if tools.len() == 22)
+    );
+    let output = rejection.to_string();
+    let json = output.strip_prefix("Tool Call Error: ").unwrap();
+    assert_eq!(serde_json::from_str::<Value>(json).unwrap()["error"], "unknown_tool");
+
+    let workspace = tempfile::tempdir().unwrap();
+    let config = SessionConfig {
+      workspace: Some(WorkspaceParams {
+        workspace_path: workspace.path().to_path_buf(),
+        language: "rust".to_string(),
+        language_server: "rust-analyzer".to_string(),
+        doc_path: None,
+        name: None,
+      }),
+      ..Default::default()
+    };
+    assert!(registry.validate_call("read_file", r#"{"file_path":"src/main.rs"}"#, &config).is_ok());
+    for path in ["/etc/passwd", "certs/site.pem"] {
+      let arguments = json!({ "file_path": path }).to_string();
+      let rejection = registry.validate_call("read_file", &arguments, &config).unwrap_err();
+      assert!(matches!(rejection, ToolCallRejection::RefusedPath { .. }));
+    }
+  }
+}
@@ -1,43 +1,80 @@
 use futures_util::Future;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use globset::Glob;
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use tree_sitter::{Parser, Query};
+use tree_sitter::{Parser, Query, QueryCursor};
+
+use crate::app::semantic_search::indexable_files;
+use crate::app::treesitter::language_for_path;
 
-use super::argument_validation::*;
 use super::errors::ToolCallError;
 use super::tool_call::{ToolCallParams, ToolCallTrait};
 use super::types::*;
 
-use serde::{Deserialize, Serialize};
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+const DEFAULT_MAX_CAPTURES: usize = 100;
+
+#[derive(Serialize, Deserialize)]
 pub struct TreesitterFunction {
-  name: String,
-  description: String,
-  properties: Vec<FunctionProperty>,
+  pub name: String,
+  pub description: String,
+  pub parameters: FunctionProperty,
+}
+
+/// a node captured by a query, with one based lines and columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCapture {
+  pub path: PathBuf,
+  pub name: String,
+  pub start: (usize, usize),
+  pub end: (usize, usize),
+  pub text: String,
 }
 
 impl ToolCallTrait for TreesitterFunction {
-  fn init() -> Self {
+  fn init() -> Self
+  where
+    Self: Sized,
+  {
     TreesitterFunction {
-      name: "treesitter".to_string(),
-      description: "parse source code using tree-sitter".to_string(),
-    properties: FunctionProperty::Parameters {
+      name: "treesitter_query".to_string(),
+      description: "run a tree-sitter query, an s-expression pattern over syntax trees, on the workspace source files matching a glob. returns each capture with its path, range and text. use it for structural searches text search can not do, for example `(impl_item trait: (type_identifier) @t (#eq? @t \"Drop\") body: (declaration_list (function_item body: (block (expression_statement (call_expression function: (field_expression field: (field_identifier) @m (#eq? @m \"unwrap\"))))))))` finds unwrap() calls directly inside impl Drop. only rust files have a grammar".to_string(),
+      parameters: FunctionProperty::Parameters {
         properties: HashMap::from([
-                        ( "path_globs".to_string(),
-          FunctionProperty::String {
-        required: true,
-        description: Some(
-          "a comma separated list of glob patterns that represent source files to be parsed".to_string(),
-        ),
-      }),
-      ( "query".to_string(),
-      FunctionProperty::String {
-        required: false,
-        description: Some("tree sitter query to execute upon source file".into()),
-      }),
-       ])
-    },
+          (
+            "query".to_string(),
+            FunctionProperty::String {
+              required: true,
+              description: Some(
+                "the tree-sitter query, with @names on the nodes to return".to_string(),
+              ),
+            },
+          ),
+          (
+            "glob".to_string(),
+            FunctionProperty::String {
+              required: false,
+              description: Some(
+                "the files to query, relative to the workspace root, all source files if not set"
+                  .to_string(),
+              ),
+            },
+          ),
+          (
+            "max_captures".to_string(),
+            FunctionProperty::Integer {
+              minimum: Some(1),
+              maximum: Some(1000),
+              required: false,
+              description: Some(format!(
+                "the most captures to return, {} if not set",
+                DEFAULT_MAX_CAPTURES
+              )),
+            },
+          ),
+        ]),
+      },
     }
   }
 
@@ -45,8 +82,8 @@ impl ToolCallTrait for TreesitterFunction {
     &self.name
   }
 
-  fn parameters(&self) -> Vec<FunctionProperty> {
-    self.properties.clone()
+  fn parameters(&self) -> FunctionProperty {
+    self.parameters.clone()
   }
 
   fn description(&self) -> String {
@@ -56,67 +93,144 @@ impl ToolCallTrait for TreesitterFunction {
   fn call(
     &self,
     params: ToolCallParams,
-  ) -> Pin<
-    Box<
-      dyn Future<Output = Result<Option<String>, ToolCallError>>
-        + Send
-        + 'static,
-    >,
-  > {
+  ) -> Pin<Box<dyn Future<Output = Result<Option<String>, ToolCallError>> + Send + 'static>> {
+    let validated_arguments = validate_arguments(params.function_args, &self.parameters, None)
+      .expect("error validating arguments");
+    let query = get_validated_argument::<String>(&validated_arguments, "query");
+    let glob = get_validated_argument::<String>(&validated_arguments, "glob");
+    let max_captures = get_validated_argument::<usize>(&validated_arguments, "max_captures")
+      .unwrap_or(DEFAULT_MAX_CAPTURES);
+    let workspace_root = params.session_config.workspace.map(|workspace| workspace.workspace_path);
+
     Box::pin(async move {
-      let paths = validate_and_extract_paths_from_argument(
-        &params.function_args,
-        params.session_config,
-        true,
-        Some(PathBuf::from("./")),
-      )?
-      .unwrap();
-      let query = validate_and_extract_string_argument(
-        &params.function_args,
-        "query",
-        true,
-      )?
-      .unwrap();
-      treesitter_query(paths, &query)
+      let Some(query) = query else {
+        return Err(ToolCallError::new("query argument is required"));
+      };
+      let Some(workspace_root) = workspace_root else {
+        return Err(ToolCallError::new("treesitter_query needs a workspace"));
+      };
+      let matcher = glob.map(|glob| Glob::new(&glob)).transpose()?.map(|g| g.compile_matcher());
+      let paths = indexable_files(&workspace_root)
+        .into_iter()
+        .filter(|path| match &matcher {
+          Some(matcher) => matcher.is_match(path.strip_prefix(&workspace_root).unwrap_or(path)),
+          None => true,
+        })
+        .collect::<Vec<_>>();
+      let (captures, total) = query_files(&paths, &query, max_captures)?;
+      Ok(Some(format_captures(&captures, total, &workspace_root)))
     })
   }
 }
 
-pub fn treesitter_query(
-  paths: Vec<PathBuf>,
+/// the first `max_captures` captures of `query` in the source files among `paths`, with the
+/// number found in all of them
+pub fn query_files(
+  paths: &[PathBuf],
   query: &str,
-) -> Result<Option<String>, ToolCallError> {
+  max_captures: usize,
+) -> Result<(Vec<QueryCapture>, usize), ToolCallError> {
+  // a query is compiled once for each language it is run on
+  let mut queries: HashMap<String, Query> = HashMap::new();
   let mut parser = Parser::new();
-  let language = tree_sitter_rust::language();
-  parser.set_language(language).expect("Error loading Rust grammar");
-  let query = &Query::new(language, query).unwrap();
-  let mut results = vec![];
+  let mut captures = vec![];
+  let mut total = 0;
   for path in paths {
-    let source_code = std::fs::read_to_string(&path).map_err(|e| {
-      ToolCallError::new(
-        format!("Error: {}  Path: {}", &e, &path.to_str().unwrap()).as_str(),
-      )
-    })?;
-    let parsed =
-      parser.parse(&source_code, None).expect("Error parsing source code");
-    let root_node = parsed.root_node();
-    let mut query_cursor = tree_sitter::QueryCursor::new();
-
-    let query_matches =
-      query_cursor.matches(query, root_node, source_code.as_bytes());
-    for match_ in query_matches {
-      for capture in match_.captures {
-        let capture_text = &source_code
-          [capture.node.range().start_byte..capture.node.range().end_byte]
-          .to_string();
-        results.push(capture_text.clone());
+    let Some(language) = language_for_path(path) else {
+      continue;
+    };
+    let Ok(source) = std::fs::read_to_string(path) else {
+      continue;
+    };
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_string();
+    let query = match queries.entry(extension) {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => entry.insert(
+        Query::new(language, query)
+          .map_err(|e| ToolCallError::new(&format!("invalid query: {}", e)))?,
+      ),
+    };
+    parser.set_language(language).map_err(|e| ToolCallError::new(&e.to_string()))?;
+    let Some(tree) = parser.parse(&source, None) else {
+      continue;
+    };
+
+    let mut cursor = QueryCursor::new();
+    for query_match in cursor.matches(query, tree.root_node(), source.as_bytes()) {
+      for capture in query_match.captures {
+        total += 1;
+        if captures.len() >= max_captures {
+          continue;
+        }
+        let node = capture.node;
+        captures.push(QueryCapture {
+          path: path.clone(),
+          name: query.capture_names()[capture.index as usize].clone(),
+          start: (node.start_position().row + 1, node.start_position().column + 1),
+          end: (node.end_position().row + 1, node.end_position().column + 1),
+          text: source[node.byte_range()].to_string(),
+        });
       }
     }
   }
+  Ok((captures, total))
+}
+
+/// one line for each capture, with the first line of its text
+pub fn format_captures(captures: &[QueryCapture], total: usize, workspace_root: &Path) -> String {
+  if captures.is_empty() {
+    return "no captures found".to_string();
+  }
+  let mut lines = captures
+    .iter()
+    .map(|capture| {
+      let path = capture.path.strip_prefix(workspace_root).unwrap_or(&capture.path);
+      let mut text = capture.text.lines().next().unwrap_or_default().to_string();
+      if capture.text.lines().nth(1).is_some() {
+        text.push_str(" ...");
+      }
+      format!(
+        "{}:{}:{}-{}:{} @{}: {}",
+        path.display(),
+        capture.start.0,
+        capture.start.1,
+        capture.end.0,
+        capture.end.1,
+        capture.name,
+        text
+      )
+    })
+    .collect::<Vec<_>>();
+  if total > captures.len() {
+    lines.push(format!(
+      "{} more captures not shown, narrow the query or glob to see them",
+      total - captures.len()
+    ));
+  }
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_query_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = "struct Guard;\n\nimpl Drop for Guard {\n  fn drop(&mut self) {\n    lock().unwrap();\n  }\n}\n\nfn main() {\n  run().unwrap();\n}\n";
+    let path = dir.path().join("guard.rs");
+    std::fs::write(&path, source).unwrap();
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "unwrap").unwrap();
+
+    let query = "(impl_item trait: (type_identifier) @trait (#eq? @trait \"Drop\") body: (declaration_list (function_item body: (block (expression_statement (call_expression function: (field_expression field: (field_identifier) @method (#eq? @method \"unwrap\"))))))))";
+    let (captures, total) = query_files(&[path, notes], query, 1).unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(
+      format_captures(&captures, total, dir.path()),
+      "guard.rs:3:6-3:10 @trait: Drop\n1 more captures not shown, narrow the query or glob to see them"
+    );
 
-  if results.is_empty() {
-    Ok(Some("no matches found".into()))
-  } else {
-    Ok(Some(results.join("\n")))
+    assert!(query_files(&[dir.path().join("guard.rs")], "(no_such_node)", 1).is_err());
   }
 }
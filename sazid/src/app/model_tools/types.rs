@@ -92,20 +92,20 @@ pub enum FunctionProperty {
   Array {
     #[serde(rename = "items")]
     items: Box<FunctionProperty>,
-    #[serde(rename = "minItems")]
+    #[serde(rename = "minItems", skip_serializing_if = "Option::is_none")]
     min_items: Option<usize>,
-    #[serde(rename = "maxItems")]
+    #[serde(rename = "maxItems", skip_serializing_if = "Option::is_none")]
     max_items: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip)]
     required: bool,
   },
-  #[serde(rename = "number")]
+  #[serde(rename = "integer")]
   Integer {
-    #[serde(rename = "minimum")]
+    #[serde(rename = "minimum", skip_serializing_if = "Option::is_none")]
     minimum: Option<i64>,
-    #[serde(rename = "maximum")]
+    #[serde(rename = "maximum", skip_serializing_if = "Option::is_none")]
     maximum: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
@@ -349,6 +349,7 @@ fn is_valid_type(value: &Value, expected_type: &FunctionProperty) -> bool {
       | (Value::Number(_), FunctionProperty::Integer { .. })
       | (Value::String(_), FunctionProperty::Pattern { .. })
       | (Value::String(_), FunctionProperty::PathBuf { .. })
+      | (Value::Object(_), FunctionProperty::Parameters { .. })
   )
 }
 
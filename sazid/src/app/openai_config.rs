@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use async_openai::config::{Config, OpenAIConfig, OPENAI_API_BASE};
+use reqwest::header::{HeaderMap, HeaderValue};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use super::credentials;
+
+pub const OPENAI_PROJECT_HEADER: &str = "OpenAI-Project";
+
+/// connection parameters for the openai api. values left unset fall back to the
+/// OPENAI_API_BASE, OPENAI_ORG_ID and OPENAI_PROJECT_ID environment variables
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct OpenAIParams {
+  /// used when neither OPENAI_API_KEY nor the os keyring has a key. never saved with a session
+  #[serde(default, skip_serializing)]
+  pub api_key: Option<String>,
+  pub api_base: Option<String>,
+  pub organization_id: Option<String>,
+  pub project_id: Option<String>,
+  pub profile: Option<String>,
+  #[serde(default)]
+  pub profiles: HashMap<String, OpenAIParams>,
+}
+
+impl OpenAIParams {
+  /// the parameters of the selected profile layered over the top level parameters
+  pub fn resolve(&self) -> OpenAIParams {
+    let profile = self.profile.as_ref().and_then(|name| {
+      let profile = self.profiles.get(name);
+      if profile.is_none() {
+        log::warn!("openai profile {} not found, using default parameters", name);
+      }
+      profile
+    });
+    let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+    OpenAIParams {
+      api_key: profile.and_then(|p| p.api_key.clone()).or(self.api_key.clone()),
+      api_base: profile
+        .and_then(|p| p.api_base.clone())
+        .or(self.api_base.clone())
+        .or_else(|| env("OPENAI_API_BASE")),
+      organization_id: profile
+        .and_then(|p| p.organization_id.clone())
+        .or(self.organization_id.clone())
+        .or_else(|| env("OPENAI_ORG_ID")),
+      project_id: profile
+        .and_then(|p| p.project_id.clone())
+        .or(self.project_id.clone())
+        .or_else(|| env("OPENAI_PROJECT_ID")),
+      profile: self.profile.clone(),
+      profiles: HashMap::new(),
+    }
+  }
+
+  pub fn client_config(&self) -> OpenAIClientConfig {
+    let params = self.resolve();
+    let mut config = OpenAIConfig::default();
+    if let Some(api_key) = credentials::api_key("openai", params.api_key.as_deref()) {
+      config = config.with_api_key(api_key);
+    }
+    if let Some(api_base) = params.api_base {
+      config = config.with_api_base(api_base);
+    }
+    if let Some(organization_id) = params.organization_id {
+      config = config.with_org_id(organization_id);
+    }
+    OpenAIClientConfig { config, project_id: params.project_id }
+  }
+}
+
+/// wraps OpenAIConfig to add the project header, which async-openai does not send itself
+#[derive(Debug, Clone, Default)]
+pub struct OpenAIClientConfig {
+  pub config: OpenAIConfig,
+  pub project_id: Option<String>,
+}
+
+impl OpenAIClientConfig {
+  /// whether requests can be sent: an api key was found, or the api is served from elsewhere,
+  /// like a local server that may not need one
+  pub fn is_configured(&self) -> bool {
+    !self.config.api_key().expose_secret().is_empty() || self.config.api_base() != OPENAI_API_BASE
+  }
+}
+
+impl Config for OpenAIClientConfig {
+  fn headers(&self) -> HeaderMap {
+    let mut headers = self.config.headers();
+    if let Some(project_id) = &self.project_id {
+      match HeaderValue::from_str(project_id) {
+        Ok(value) => {
+          headers.insert(OPENAI_PROJECT_HEADER, value);
+        },
+        Err(e) => log::error!("invalid openai project id {}: {}", project_id, e),
+      }
+    }
+    headers
+  }
+
+  fn url(&self, path: &str) -> String {
+    self.config.url(path)
+  }
+
+  fn query(&self) -> Vec<(&str, &str)> {
+    self.config.query()
+  }
+
+  fn api_base(&self) -> &str {
+    self.config.api_base()
+  }
+
+  fn api_key(&self) -> &Secret<String> {
+    self.config.api_key()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_profile_overrides_top_level_params() {
+    let params = OpenAIParams {
+      organization_id: Some("org-default".to_string()),
+      project_id: Some("proj-default".to_string()),
+      profile: Some("work".to_string()),
+      profiles: HashMap::from([(
+        "work".to_string(),
+        OpenAIParams { project_id: Some("proj-work".to_string()), ..Default::default() },
+      )]),
+      ..Default::default()
+    };
+    let resolved = params.resolve();
+    assert_eq!(resolved.organization_id, Some("org-default".to_string()));
+    assert_eq!(resolved.project_id, Some("proj-work".to_string()));
+  }
+
+  #[test]
+  fn test_is_configured() {
+    let config = |openai: OpenAIConfig| OpenAIClientConfig { config: openai, project_id: None };
+    assert!(!config(OpenAIConfig::new().with_api_key("")).is_configured());
+    assert!(config(OpenAIConfig::new().with_api_key("sk-test")).is_configured());
+    let local = OpenAIConfig::new().with_api_key("").with_api_base("http://localhost:11434/v1");
+    assert!(config(local).is_configured());
+  }
+
+  #[test]
+  fn test_project_header() {
+    let config = OpenAIClientConfig {
+      config: OpenAIConfig::new().with_org_id("org-test"),
+      project_id: Some("proj-test".to_string()),
+    };
+    let headers = config.headers();
+    assert_eq!(headers.get(OPENAI_PROJECT_HEADER).unwrap(), "proj-test");
+    assert_eq!(headers.get("OpenAI-Organization").unwrap(), "org-test");
+  }
+}
@@ -0,0 +1,75 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// a file attached to the session whose current contents are sent with every request
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinnedFile {
+  pub path: PathBuf,
+  pub content: String,
+  pub modified: Option<SystemTime>,
+}
+
+impl PinnedFile {
+  pub fn read(path: &Path) -> std::io::Result<Self> {
+    let modified = std::fs::metadata(path)?.modified().ok();
+    let content = std::fs::read_to_string(path)?;
+    Ok(PinnedFile { path: path.to_path_buf(), content, modified })
+  }
+
+  /// re-reads the file if it was modified since it was last read. returns true if it was
+  pub fn refresh(&mut self) -> std::io::Result<bool> {
+    let modified = std::fs::metadata(&self.path)?.modified().ok();
+    if modified.is_some() && modified == self.modified {
+      return Ok(false);
+    }
+    *self = PinnedFile::read(&self.path)?;
+    Ok(true)
+  }
+}
+
+/// a system message holding the contents of the pinned files, none if nothing is pinned
+pub fn pinned_files_message(files: &[PinnedFile]) -> Option<ChatCompletionRequestMessage> {
+  if files.is_empty() {
+    return None;
+  }
+  let mut content = String::from("The user pinned these files, their contents are current:\n");
+  for file in files {
+    let language = file.path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    content.push_str(&format!("\n{}\n```{}\n{}", file.path.display(), language, file.content));
+    if !file.content.ends_with('\n') {
+      content.push('\n');
+    }
+    content.push_str("```\n");
+  }
+  Some(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+    content,
+    role: Role::System,
+    name: None,
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::messages::get_chat_message_text;
+
+  #[test]
+  fn test_pinned_file_refreshes_when_modified() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    std::fs::write(&path, "first").unwrap();
+    let mut file = PinnedFile::read(&path).unwrap();
+    assert!(!file.refresh().unwrap());
+
+    std::fs::write(&path, "second\n").unwrap();
+    // the modification time may not change within the filesystem's timestamp resolution
+    file.modified = None;
+    assert!(file.refresh().unwrap());
+    assert_eq!(file.content, "second\n");
+
+    let message = pinned_files_message(&[file]).unwrap();
+    assert!(get_chat_message_text(&message).ends_with("notes.md\n```md\nsecond\n```\n"));
+    assert_eq!(pinned_files_message(&[]), None);
+  }
+}
@@ -0,0 +1,205 @@
+use regex::Regex;
+
+/// where a plan is, from the model drafting it to the last of its steps being carried out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanState {
+  Drafting,
+  /// the steps wait for the user to edit and approve them
+  Review,
+  Executing,
+  Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepState {
+  Pending,
+  Running,
+  Done,
+}
+
+/// a step of a plan and the messages of the reply that carried it out
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep {
+  pub text: String,
+  /// steps that are not checked are skipped when the plan runs
+  pub checked: bool,
+  pub state: StepState,
+  /// ids of the messages, tool calls and their outputs included, from carrying out the step
+  pub message_ids: Vec<i64>,
+}
+
+impl PlanStep {
+  pub fn new(text: &str) -> Self {
+    PlanStep {
+      text: text.trim().to_string(),
+      checked: true,
+      state: StepState::Pending,
+      message_ids: vec![],
+    }
+  }
+}
+
+/// a plan the model drafts for a goal as a checklist. the user edits and approves the steps,
+/// then they are carried out one reply at a time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+  pub goal: String,
+  pub state: PlanState,
+  pub steps: Vec<PlanStep>,
+  // the step being carried out and the number of messages before its reply
+  running: Option<(usize, usize)>,
+}
+
+impl Plan {
+  pub fn new(goal: &str) -> Self {
+    Plan { goal: goal.trim().to_string(), state: PlanState::Drafting, steps: vec![], running: None }
+  }
+
+  /// the message asking the model to draft the plan
+  pub fn planning_prompt(&self) -> String {
+    format!(
+      "Make a plan for this goal, without carrying any of it out yet:\n\n{}\n\nReply with the \
+       plan as a numbered list, one short step per line, and nothing else.",
+      self.goal
+    )
+  }
+
+  /// takes the steps of the draft the model replied with and waits for the user to review
+  /// them. returns false if the reply holds no list
+  pub fn draft_from_reply(&mut self, reply: &str) -> bool {
+    self.steps = parse_plan_steps(reply).iter().map(|step| PlanStep::new(step)).collect();
+    if self.steps.is_empty() {
+      return false;
+    }
+    self.state = PlanState::Review;
+    true
+  }
+
+  /// moves the step at `idx` by `offset` places, returning where it ended up
+  pub fn move_step(&mut self, idx: usize, offset: isize) -> usize {
+    if idx >= self.steps.len() || self.steps[idx].state != StepState::Pending {
+      return idx;
+    }
+    let target = (idx as isize + offset).clamp(0, self.steps.len() as isize - 1) as usize;
+    let step = self.steps.remove(idx);
+    self.steps.insert(target, step);
+    target
+  }
+
+  /// starts carrying out the checked steps
+  pub fn approve(&mut self) {
+    if self.state == PlanState::Review {
+      self.state = PlanState::Executing;
+    }
+  }
+
+  /// the index of the first message of the reply carrying out the running step
+  pub fn running_from(&self) -> Option<usize> {
+    self.running.map(|(_, from)| from)
+  }
+
+  /// marks the running step done with the messages of its reply
+  pub fn finish_step(&mut self, message_ids: Vec<i64>) {
+    if let Some((idx, _)) = self.running.take() {
+      self.steps[idx].state = StepState::Done;
+      self.steps[idx].message_ids = message_ids;
+    }
+  }
+
+  /// starts the next checked step, returning the message asking for it. `message_count` is the
+  /// number of messages before it. none once every checked step is done
+  pub fn next_step(&mut self, message_count: usize) -> Option<String> {
+    let Some(idx) =
+      self.steps.iter().position(|step| step.checked && step.state == StepState::Pending)
+    else {
+      self.state = PlanState::Done;
+      return None;
+    };
+    self.steps[idx].state = StepState::Running;
+    self.running = Some((idx, message_count));
+    let plan = self
+      .steps
+      .iter()
+      .filter(|step| step.checked)
+      .enumerate()
+      .map(|(n, step)| format!("{}. {}", n + 1, step.text))
+      .collect::<Vec<_>>()
+      .join("\n");
+    Some(format!(
+      "The plan for the goal \"{}\" is:\n\n{}\n\nCarry out only this step now: {}\nSummarize \
+       the result when it is done.",
+      self.goal, plan, self.steps[idx].text
+    ))
+  }
+
+  /// stops carrying out the plan, the running step waits to be run again after another review
+  pub fn pause(&mut self) {
+    if let Some((idx, _)) = self.running.take() {
+      self.steps[idx].state = StepState::Pending;
+    }
+    if self.state == PlanState::Executing {
+      self.state = PlanState::Review;
+    }
+  }
+}
+
+/// the items of the first list in a reply, numbered or bulleted, checkboxes and emphasis removed
+pub fn parse_plan_steps(reply: &str) -> Vec<String> {
+  let item = Regex::new(r"^\s*(?:\d+[.)]|[-*+])\s+(?:\[[ xX]\]\s+)?(.+)$").unwrap();
+  let mut steps = vec![];
+  for line in reply.lines() {
+    match item.captures(line) {
+      Some(captures) => {
+        let text = captures[1].trim().trim_matches('*').trim();
+        if !text.is_empty() {
+          steps.push(text.to_string());
+        }
+      },
+      // a list ends at the first line of prose after it
+      None if !steps.is_empty() && !line.trim().is_empty() => break,
+      None => {},
+    }
+  }
+  steps
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_plan_steps_run_in_order() {
+    let reply = "Here is the plan:\n\n1. Read the config loader\n2) **Add the option**\n- [ ] \
+                 Write a test\n\nThat should do it.\n3. not part of the plan";
+    assert_eq!(
+      parse_plan_steps(reply),
+      vec!["Read the config loader", "Add the option", "Write a test"]
+    );
+
+    let mut plan = Plan::new("add an option");
+    assert!(!plan.draft_from_reply("I can not make a plan."));
+    assert!(plan.draft_from_reply(reply));
+    assert_eq!(plan.state, PlanState::Review);
+
+    // the test is written first and reading the loader is skipped
+    assert_eq!(plan.move_step(2, -2), 0);
+    plan.steps[1].checked = false;
+    plan.approve();
+
+    let prompt = plan.next_step(4).unwrap();
+    assert!(prompt.contains("1. Write a test\n2. Add the option"));
+    assert!(prompt.contains("only this step now: Write a test\n"));
+    assert_eq!(plan.running_from(), Some(4));
+    plan.finish_step(vec![7, 8]);
+    assert_eq!(plan.steps[0].message_ids, vec![7, 8]);
+
+    assert!(plan.next_step(9).is_some());
+    plan.pause();
+    assert_eq!((plan.state, plan.steps[2].state), (PlanState::Review, StepState::Pending));
+    plan.approve();
+    assert!(plan.next_step(9).is_some());
+    plan.finish_step(vec![]);
+    assert_eq!(plan.next_step(11), None);
+    assert_eq!(plan.state, PlanState::Done);
+  }
+}
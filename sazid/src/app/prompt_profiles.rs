@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+/// the profile sessions start with when the config does not name one
+pub const DEFAULT_PROMPT_PROFILE: &str = "rust-assistant";
+
+const RUST_ASSISTANT_PROMPT: &str = "you are an expert rust programming assistant. you work in the user's workspace through the tools you are given: read the code involved before changing it, keep changes in the style of the surrounding code, and check them with the language server diagnostics. explain what you changed and why in a few sentences";
+
+const CODE_REVIEWER_PROMPT: &str = "you are a careful code reviewer. read the code or changes the user points you to and report bugs, unhandled errors, unclear names and missing tests, most serious first, each with the file and line it concerns. suggest fixes but do not edit files unless asked to";
+
+const TERSE_PROMPT: &str = "you are an expert programming assistant. answer as briefly as possible: code without commentary where code answers the question, otherwise a sentence or two";
+
+/// the profiles that are available without any configured, a configured profile with the same
+/// name replaces one of these
+pub fn builtin_prompt_profiles() -> BTreeMap<String, String> {
+  BTreeMap::from([
+    (DEFAULT_PROMPT_PROFILE.to_string(), RUST_ASSISTANT_PROMPT.to_string()),
+    ("code-reviewer".to_string(), CODE_REVIEWER_PROMPT.to_string()),
+    ("terse".to_string(), TERSE_PROMPT.to_string()),
+  ])
+}
+
+/// the built in profiles with the configured ones added, by name
+pub fn prompt_profiles(configured: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+  let mut profiles = builtin_prompt_profiles();
+  profiles.extend(configured.iter().map(|(name, prompt)| (name.clone(), prompt.clone())));
+  profiles
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_prompt_profiles() {
+    let configured = BTreeMap::from([
+      ("terse".to_string(), "one word answers".to_string()),
+      ("sql".to_string(), "you write postgres queries".to_string()),
+    ]);
+    let profiles = prompt_profiles(&configured);
+    assert_eq!(
+      profiles.keys().collect::<Vec<_>>(),
+      vec!["code-reviewer", "rust-assistant", "sql", "terse"]
+    );
+    assert_eq!(profiles["terse"], "one word answers");
+    assert_eq!(profiles[DEFAULT_PROMPT_PROFILE], RUST_ASSISTANT_PROMPT);
+  }
+}
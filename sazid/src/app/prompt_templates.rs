@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::errors::SazidError;
+
+/// the command an input starts with to be expanded from a template, as in `/template review`
+pub const TEMPLATE_COMMAND: &str = "/template";
+
+/// the values of the variables a template can use, none when there is nothing to fill one with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateVariables {
+  /// the text selected in the session
+  pub selection: Option<String>,
+  /// the path and contents of the file named after the template
+  pub file: Option<String>,
+  /// the language server diagnostics of the workspace
+  pub diagnostics: Option<String>,
+}
+
+impl TemplateVariables {
+  fn get(&self, name: &str) -> Option<Option<&str>> {
+    match name {
+      "selection" => Some(self.selection.as_deref()),
+      "file" => Some(self.file.as_deref()),
+      "diagnostics" => Some(self.diagnostics.as_deref()),
+      _ => None,
+    }
+  }
+}
+
+/// the templates in `dir` by name, the file stem: `review.md` is the template `review`. empty if
+/// the directory does not exist
+pub fn load_templates(dir: &Path) -> Result<BTreeMap<String, String>, SazidError> {
+  let entries = match std::fs::read_dir(dir) {
+    Ok(entries) => entries,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+    Err(e) => return Err(e.into()),
+  };
+  let mut templates = BTreeMap::new();
+  for entry in entries {
+    let path = entry?.path();
+    let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+      continue;
+    };
+    if path.is_file() && !name.starts_with('.') {
+      templates.insert(name.to_string(), std::fs::read_to_string(&path)?);
+    }
+  }
+  Ok(templates)
+}
+
+/// the template name and the rest of an input starting with `/template <name>`
+pub fn parse_template_command(input: &str) -> Option<(&str, &str)> {
+  let command = input.trim().strip_prefix(TEMPLATE_COMMAND)?;
+  if !command.starts_with(char::is_whitespace) {
+    return None;
+  }
+  let command = command.trim_start();
+  let (name, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+  Some((name, rest.trim()))
+}
+
+/// the template with each `{variable}` replaced by its value. braces around anything else are
+/// left as they are, so templates can hold code. using a variable without a value is an error,
+/// a prompt missing what it is about is not sent
+pub fn expand_template(
+  template: &str,
+  variables: &TemplateVariables,
+) -> Result<String, SazidError> {
+  let mut prompt = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(start) = rest.find('{') {
+    prompt.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let name = rest[1..].split_once('}').map(|(name, _)| name).unwrap_or_default();
+    match variables.get(name) {
+      Some(Some(value)) => prompt.push_str(value),
+      Some(None) => {
+        return Err(SazidError::Other(format!(
+          "the template uses {{{}}}, which has no value",
+          name
+        )))
+      },
+      None => {
+        prompt.push('{');
+        rest = &rest[1..];
+        continue;
+      },
+    }
+    rest = &rest[name.len() + 2..];
+  }
+  prompt.push_str(rest);
+  Ok(prompt)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_prompt_templates() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("review.md"), "review {file}\nfix {diagnostics}\n").unwrap();
+    std::fs::write(dir.path().join("explain.txt"), "explain {selection} in fn a() {}").unwrap();
+    let templates = load_templates(dir.path()).unwrap();
+    assert_eq!(templates.keys().collect::<Vec<_>>(), vec!["explain", "review"]);
+    assert!(load_templates(&dir.path().join("missing")).unwrap().is_empty());
+
+    assert_eq!(
+      parse_template_command(" /template review src/lib.rs\n"),
+      Some(("review", "src/lib.rs"))
+    );
+    assert_eq!(parse_template_command("/template explain"), Some(("explain", "")));
+    assert_eq!(parse_template_command("/templates"), None);
+    assert_eq!(parse_template_command("use /template review"), None);
+
+    let variables =
+      TemplateVariables { selection: Some("x + 1".to_string()), ..TemplateVariables::default() };
+    assert_eq!(
+      expand_template(&templates["explain"], &variables).unwrap(),
+      "explain x + 1 in fn a() {}"
+    );
+    let error = expand_template(&templates["review"], &variables).unwrap_err();
+    assert!(error.to_string().contains("{file}"));
+  }
+}
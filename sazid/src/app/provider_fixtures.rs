@@ -0,0 +1,421 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_openai::{
+  config::Config,
+  error::{ApiError, OpenAIError},
+  types::{
+    ChatCompletionResponseStream, CreateChatCompletionRequest, CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse,
+  },
+  Client,
+};
+use futures_util::StreamExt;
+use lazy_static::lazy_static;
+use reqwest::StatusCode;
+use reqwest_eventsource::{Event, RequestBuilderExt};
+use serde::{Deserialize, Serialize};
+
+use super::errors::SazidError;
+use super::mock_provider::MockProvider;
+use super::rate_limit::retry_after_header;
+
+lazy_static! {
+  // fixtures are opened once per process, so every request records to or replays from the same
+  static ref OPEN_FIXTURES: Mutex<HashMap<ProviderMode, Arc<ProviderFixtures>>> =
+    Mutex::new(HashMap::new());
+}
+
+/// where requests to the provider are answered from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ProviderMode {
+  /// the provider's api
+  #[default]
+  Live,
+  /// the provider's api, with each request and its response written to a fixture file. the
+  /// file is started over when it is first opened
+  Record { path: PathBuf },
+  /// the responses of a fixture file, without the network or an api key
+  Replay { path: PathBuf },
+  /// the answers of a mock script, see `MockScript`
+  Mock { path: PathBuf },
+}
+
+/// a request to the provider and its response, one line of a fixture file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Exchange {
+  /// a chat completion, with the tools offered in the request and the tool calls of the response
+  Chat {
+    request: CreateChatCompletionRequest,
+    response: ChatResponse,
+  },
+  Embedding {
+    model: String,
+    input: String,
+    embedding: Vec<f32>,
+  },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatResponse {
+  Stream(Vec<CreateChatCompletionStreamResponse>),
+  Complete(CreateChatCompletionResponse),
+}
+
+#[derive(Debug)]
+enum Fixtures {
+  Record(Mutex<File>),
+  Replay {
+    // served in the order they were recorded
+    chats: Mutex<VecDeque<(CreateChatCompletionRequest, ChatResponse)>>,
+    embeddings: HashMap<(String, String), Vec<f32>>,
+  },
+  Mock(MockProvider),
+}
+
+/// the provider traffic of a fixture file, recorded or replayed, or of a mock script
+#[derive(Debug)]
+pub struct ProviderFixtures {
+  path: PathBuf,
+  fixtures: Fixtures,
+}
+
+impl ProviderFixtures {
+  /// the fixtures of `mode`, shared with every request made in it. none when requests go to the
+  /// provider without being recorded
+  pub fn open(mode: &ProviderMode) -> Result<Option<Arc<Self>>, SazidError> {
+    if *mode == ProviderMode::Live {
+      return Ok(None);
+    }
+    let mut open_fixtures = OPEN_FIXTURES.lock().unwrap();
+    if let Some(fixtures) = open_fixtures.get(mode) {
+      return Ok(Some(fixtures.clone()));
+    }
+    let fixtures = Arc::new(match mode {
+      ProviderMode::Record { path } => Self::record(path)?,
+      ProviderMode::Replay { path } => Self::replay(path)?,
+      ProviderMode::Mock { path } => {
+        ProviderFixtures { path: path.clone(), fixtures: Fixtures::Mock(MockProvider::load(path)?) }
+      },
+      ProviderMode::Live => unreachable!(),
+    });
+    open_fixtures.insert(mode.clone(), fixtures.clone());
+    Ok(Some(fixtures))
+  }
+
+  /// fixtures that record to `path`, replacing what it held
+  pub fn record(path: &Path) -> Result<Self, SazidError> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(path)?;
+    Ok(ProviderFixtures { path: path.to_path_buf(), fixtures: Fixtures::Record(Mutex::new(file)) })
+  }
+
+  /// fixtures that replay the exchanges recorded in `path`
+  pub fn replay(path: &Path) -> Result<Self, SazidError> {
+    let (mut chats, mut embeddings) = (VecDeque::new(), HashMap::new());
+    for line in BufReader::new(File::open(path)?).lines() {
+      let line = line?;
+      if line.trim().is_empty() {
+        continue;
+      }
+      match serde_json::from_str::<Exchange>(&line)? {
+        Exchange::Chat { request, response } => chats.push_back((request, response)),
+        Exchange::Embedding { model, input, embedding } => {
+          embeddings.insert((model, input), embedding);
+        },
+      }
+    }
+    let fixtures = Fixtures::Replay { chats: Mutex::new(chats), embeddings };
+    Ok(ProviderFixtures { path: path.to_path_buf(), fixtures })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  fn write(&self, exchange: &Exchange) {
+    let Fixtures::Record(file) = &self.fixtures else {
+      return;
+    };
+    let written = serde_json::to_string(exchange)
+      .map_err(|e| e.to_string())
+      .and_then(|line| writeln!(file.lock().unwrap(), "{}", line).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+      log::error!("error recording to {}: {}", self.path.display(), e);
+    }
+  }
+
+  // the recorded chat that answers `request`: the first recorded for the same messages, else
+  // the first of the same kind. titles and summaries are requested without tools alongside the
+  // session's requests, so they may not be made in the order they were recorded
+  fn next_chat(
+    &self,
+    request: &CreateChatCompletionRequest,
+    chats: &Mutex<VecDeque<(CreateChatCompletionRequest, ChatResponse)>>,
+  ) -> Result<ChatResponse, OpenAIError> {
+    let mut chats = chats.lock().unwrap();
+    let same_kind =
+      |recorded: &CreateChatCompletionRequest| recorded.tools.is_some() == request.tools.is_some();
+    let index = match chats.iter().position(|(recorded, _)| recorded.messages == request.messages) {
+      Some(index) => index,
+      None => {
+        let index =
+          chats.iter().position(|(recorded, _)| same_kind(recorded)).ok_or_else(|| {
+            OpenAIError::InvalidArgument(format!(
+              "{} has no recorded chat response left",
+              self.path.display()
+            ))
+          })?;
+        log::warn!(
+          "replaying a chat response from {} that was recorded for other messages",
+          self.path.display()
+        );
+        index
+      },
+    };
+    Ok(chats.remove(index).unwrap().1)
+  }
+
+  /// the embedding of `input`, created by `create` when recording
+  pub async fn embedding(
+    &self,
+    model: &str,
+    input: &str,
+    create: impl Future<Output = Result<Vec<f32>, SazidError>>,
+  ) -> Result<Vec<f32>, SazidError> {
+    match &self.fixtures {
+      Fixtures::Record(_) => {
+        let embedding = create.await?;
+        self.write(&Exchange::Embedding {
+          model: model.to_string(),
+          input: input.to_string(),
+          embedding: embedding.clone(),
+        });
+        Ok(embedding)
+      },
+      Fixtures::Replay { embeddings, .. } => {
+        embeddings.get(&(model.to_string(), input.to_string())).cloned().ok_or_else(|| {
+          SazidError::Other(format!(
+            "{} has no recorded {} embedding of {:?}",
+            self.path.display(),
+            model,
+            input.chars().take(60).collect::<String>()
+          ))
+        })
+      },
+      Fixtures::Mock(_) => Err(SazidError::Other("the mock provider creates no embeddings".into())),
+    }
+  }
+}
+
+/// sends chat requests to the provider, or answers them from fixtures
+#[derive(Clone)]
+pub struct ChatProvider<C: Config> {
+  client: Client<C>,
+  // requests are sent with the config of `client` but not through it, since its errors leave out
+  // the headers of the response
+  http: reqwest::Client,
+  fixtures: Option<Arc<ProviderFixtures>>,
+  // the wait the provider asked for in the headers of the last response that failed
+  retry_after: Arc<Mutex<Option<Duration>>>,
+}
+
+// the body of a response that failed
+#[derive(Deserialize)]
+struct ErrorBody {
+  error: ApiError,
+}
+
+impl<C: Config> ChatProvider<C> {
+  pub fn new(client: Client<C>, fixtures: Option<Arc<ProviderFixtures>>) -> Self {
+    ChatProvider { client, http: reqwest::Client::new(), fixtures, retry_after: Default::default() }
+  }
+
+  /// the wait the provider asked for in the headers of the last response that failed, for
+  /// `rate_limit::wait_to_retry`
+  pub fn retry_after(&self) -> Option<Duration> {
+    *self.retry_after.lock().unwrap()
+  }
+
+  pub async fn create(
+    &self,
+    request: CreateChatCompletionRequest,
+  ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+    let Some(fixtures) = &self.fixtures else {
+      return self.send(&request).await;
+    };
+    match &fixtures.fixtures {
+      Fixtures::Record(_) => {
+        let response = self.send(&request).await?;
+        fixtures
+          .write(&Exchange::Chat { request, response: ChatResponse::Complete(response.clone()) });
+        Ok(response)
+      },
+      Fixtures::Replay { chats, .. } => match fixtures.next_chat(&request, chats)? {
+        ChatResponse::Complete(response) => Ok(response),
+        ChatResponse::Stream(_) => Err(not_recorded_as(fixtures, "a complete response")),
+      },
+      Fixtures::Mock(mock) => mock.create(&request),
+    }
+  }
+
+  pub async fn create_stream(
+    &self,
+    request: CreateChatCompletionRequest,
+  ) -> Result<ChatCompletionResponseStream, OpenAIError> {
+    let Some(fixtures) = &self.fixtures else {
+      return self.send_stream(&request);
+    };
+    match &fixtures.fixtures {
+      Fixtures::Record(_) => {
+        let mut stream = self.send_stream(&request)?;
+        let fixtures = fixtures.clone();
+        // the response is recorded once it has streamed in whole, a failed one is not
+        Ok(Box::pin(async_stream::stream! {
+          let (mut chunks, mut failed) = (vec![], false);
+          while let Some(chunk) = stream.next().await {
+            match &chunk {
+              Ok(chunk) => chunks.push(chunk.clone()),
+              Err(_) => failed = true,
+            }
+            yield chunk;
+          }
+          if !failed {
+            fixtures.write(&Exchange::Chat { request, response: ChatResponse::Stream(chunks) });
+          }
+        }))
+      },
+      Fixtures::Replay { chats, .. } => match fixtures.next_chat(&request, chats)? {
+        ChatResponse::Stream(chunks) => {
+          Ok(Box::pin(futures_util::stream::iter(chunks.into_iter().map(Ok))))
+        },
+        ChatResponse::Complete(_) => Err(not_recorded_as(fixtures, "a stream")),
+      },
+      Fixtures::Mock(mock) => {
+        let chunks = mock.create_stream(&request)?;
+        Ok(Box::pin(futures_util::stream::iter(chunks.into_iter().map(Ok))))
+      },
+    }
+  }
+
+  // a request to the chat completions endpoint of the provider
+  fn post(&self, request: &CreateChatCompletionRequest) -> reqwest::RequestBuilder {
+    *self.retry_after.lock().unwrap() = None;
+    let config = self.client.config();
+    self
+      .http
+      .post(config.url("/chat/completions"))
+      .query(&config.query())
+      .headers(config.headers())
+      .json(request)
+  }
+
+  async fn send(
+    &self,
+    request: &CreateChatCompletionRequest,
+  ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+    let response = self.post(request).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+      *self.retry_after.lock().unwrap() = retry_after_header(response.headers());
+      return Err(response_error(status, &response.bytes().await?));
+    }
+    serde_json::from_slice(&response.bytes().await?).map_err(OpenAIError::JSONDeserialize)
+  }
+
+  // the events of a streamed response, as async-openai reads them
+  fn send_stream(
+    &self,
+    request: &CreateChatCompletionRequest,
+  ) -> Result<ChatCompletionResponseStream, OpenAIError> {
+    let mut source =
+      self.post(request).eventsource().map_err(|e| OpenAIError::StreamError(e.to_string()))?;
+    let retry_after = self.retry_after.clone();
+    Ok(Box::pin(async_stream::stream! {
+      while let Some(event) = source.next().await {
+        match event {
+          Ok(Event::Open) => continue,
+          Ok(Event::Message(message)) if message.data == "[DONE]" => break,
+          Ok(Event::Message(message)) => {
+            yield serde_json::from_str::<CreateChatCompletionStreamResponse>(&message.data)
+              .map_err(OpenAIError::JSONDeserialize);
+          },
+          Err(reqwest_eventsource::Error::StreamEnded) => break,
+          Err(e) => {
+            if let reqwest_eventsource::Error::InvalidStatusCode(_, response) = &e {
+              *retry_after.lock().unwrap() = retry_after_header(response.headers());
+            }
+            yield Err(OpenAIError::StreamError(e.to_string()));
+            break;
+          },
+        }
+      }
+      source.close();
+    }))
+  }
+}
+
+// the error the provider described in the body of a response that failed, or its status when
+// the body describes none
+fn response_error(status: StatusCode, body: &[u8]) -> OpenAIError {
+  match serde_json::from_slice::<ErrorBody>(body) {
+    Ok(body) => OpenAIError::ApiError(body.error),
+    Err(_) => OpenAIError::StreamError(format!("Invalid status code: {}", status)),
+  }
+}
+
+fn not_recorded_as(fixtures: &ProviderFixtures, kind: &str) -> OpenAIError {
+  OpenAIError::InvalidArgument(format!(
+    "the next chat response in {} was not recorded as {}",
+    fixtures.path.display(),
+    kind
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_record_and_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fixtures/session.jsonl");
+    let request = CreateChatCompletionRequest { model: "gpt-4o".to_string(), ..Default::default() };
+    let chunk: CreateChatCompletionStreamResponse = serde_json::from_value(serde_json::json!({
+      "id": "chatcmpl-1",
+      "object": "chat.completion.chunk",
+      "created": 1,
+      "model": "gpt-4o",
+      "choices": [{ "index": 0, "delta": { "content": "hello" }, "finish_reason": null }]
+    }))
+    .unwrap();
+
+    let recording = ProviderFixtures::record(&path).unwrap();
+    recording.write(&Exchange::Chat {
+      request: request.clone(),
+      response: ChatResponse::Stream(vec![chunk.clone()]),
+    });
+    let embedding = recording.embedding("ada", "fn main", async { Ok(vec![0.5, 1.0]) });
+    assert_eq!(embedding.await.unwrap(), vec![0.5, 1.0]);
+
+    let replay =
+      ChatProvider::new(Client::new(), Some(Arc::new(ProviderFixtures::replay(&path).unwrap())));
+    let fixtures = replay.fixtures.as_ref().unwrap();
+    let not_created = async { Err(SazidError::Other("not recorded".into())) };
+    assert_eq!(fixtures.embedding("ada", "fn main", not_created).await.unwrap(), vec![0.5, 1.0]);
+    assert!(fixtures.embedding("ada", "fn other", async { Ok(vec![]) }).await.is_err());
+    let replayed = replay.create_stream(request.clone()).await.unwrap().collect::<Vec<_>>().await;
+    assert_eq!(replayed.into_iter().map(Result::unwrap).collect::<Vec<_>>(), vec![chunk]);
+    assert!(replay.create(request).await.is_err());
+  }
+}
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use async_openai::error::OpenAIError;
+use backoff::{exponential::ExponentialBackoffBuilder, ExponentialBackoff};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+  // "Please try again in 20s.", "in 1m30s", "in 334ms" or "Please retry after 6 seconds"
+  static ref RETRY_AFTER: Regex =
+    Regex::new(r"(?i)(?:try again in|retry after)\s+(?:(\d+)m(?:in)?\s*)?(\d+(?:\.\d+)?)\s*(ms|s)")
+      .unwrap();
+  static ref STATUS_CODE: Regex = Regex::new(r"(?i)status code:?\s+(\d{3})").unwrap();
+}
+
+/// how chat requests are retried when the provider rate limits them or fails
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+  pub max_retries: u32,
+  /// the delay before the first retry, doubled for each retry after it
+  pub base_delay_ms: u64,
+  /// the longest delay between retries, unless the provider asks to wait longer
+  pub max_delay_secs: u64,
+}
+
+impl Default for RetryConfig {
+  fn default() -> Self {
+    RetryConfig { max_retries: 5, base_delay_ms: 1000, max_delay_secs: 60 }
+  }
+}
+
+/// a failed request that can be sent again
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryableError {
+  /// the http status of the response, when the error has one
+  pub status: Option<u16>,
+  /// how long the provider asked to wait before the next request
+  pub retry_after: Option<Duration>,
+}
+
+impl RetryConfig {
+  /// the delay before retry `attempt`, counted from 0. it doubles with every attempt up to the
+  /// longest delay, and `jitter`, between 0 and 1, takes up to half of it off so clients that
+  /// failed together do not retry together. a delay the provider asked for is waited at least
+  pub fn delay(&self, attempt: u32, retry_after: Option<Duration>, jitter: f64) -> Duration {
+    let backoff = Duration::from_millis(self.base_delay_ms)
+      .saturating_mul(2u32.saturating_pow(attempt))
+      .min(Duration::from_secs(self.max_delay_secs));
+    let jittered = backoff.mul_f64(1.0 - jitter.clamp(0.0, 1.0) / 2.0);
+    match retry_after {
+      Some(retry_after) => retry_after.max(jittered),
+      None => jittered,
+    }
+  }
+}
+
+/// the client for chat requests leaves retrying to `wait_to_retry`, which tells the user
+pub fn without_retries() -> ExponentialBackoff {
+  ExponentialBackoffBuilder::new().with_max_elapsed_time(Some(Duration::ZERO)).build()
+}
+
+/// whether a request that failed with `error` can be sent again: when it was rate limited, the
+/// provider failed, or the connection did
+pub fn retryable(error: &OpenAIError) -> Option<RetryableError> {
+  let is_retried_status = |status: u16| status == 429 || (500..600).contains(&status);
+  match error {
+    OpenAIError::ApiError(api_error) => {
+      let kind = api_error.r#type.as_deref().unwrap_or_default();
+      let rate_limited = matches!(kind, "requests" | "tokens" | "rate_limit_exceeded")
+        || api_error.message.to_lowercase().contains("rate limit");
+      let server_error = matches!(kind, "server_error" | "service_unavailable");
+      (rate_limited || server_error).then(|| RetryableError {
+        status: Some(if rate_limited { 429 } else { 500 }),
+        retry_after: retry_after(&api_error.message),
+      })
+    },
+    OpenAIError::StreamError(message) => {
+      let status = STATUS_CODE.captures(message).and_then(|c| c[1].parse::<u16>().ok());
+      let retried = match status {
+        Some(status) => is_retried_status(status),
+        None => message.starts_with("Transport error"),
+      };
+      retried.then(|| RetryableError { status, retry_after: retry_after(message) })
+    },
+    OpenAIError::Reqwest(e) => {
+      let status = e.status().map(|status| status.as_u16());
+      let retried = status.map_or(e.is_timeout() || e.is_connect(), is_retried_status);
+      retried.then_some(RetryableError { status, retry_after: None })
+    },
+    _ => None,
+  }
+}
+
+/// the wait the provider asked for in an error message, as OpenAI and Azure word it
+pub fn retry_after(message: &str) -> Option<Duration> {
+  let captures = RETRY_AFTER.captures(message)?;
+  let minutes = captures.get(1).and_then(|m| m.as_str().parse::<u64>().ok()).unwrap_or(0);
+  let amount = captures[2].parse::<f64>().ok()?;
+  let amount = match &captures[3] {
+    "ms" => Duration::from_secs_f64(amount / 1000.0),
+    _ => Duration::from_secs_f64(amount),
+  };
+  Some(Duration::from_secs(minutes * 60) + amount)
+}
+
+/// the wait the provider asked for in the headers of a failed response. openai sends
+/// `retry-after-ms` beside `retry-after`, which holds seconds or an http date
+pub fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+  let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+  let millis = header("retry-after-ms").and_then(|ms| ms.parse::<f64>().ok());
+  if let Some(millis) = millis.filter(|ms| ms.is_finite() && *ms >= 0.0) {
+    return Some(Duration::from_secs_f64(millis / 1000.0));
+  }
+  let value = header("retry-after")?;
+  match value.parse::<u64>() {
+    Ok(secs) => Some(Duration::from_secs(secs)),
+    Err(_) => {
+      let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+      (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+    },
+  }
+}
+
+/// the status line shown while waiting to retry
+pub fn retry_status(error: &RetryableError, remaining: Duration, retry: u32, max: u32) -> String {
+  let reason = match error.status {
+    Some(429) => "rate limited".to_string(),
+    Some(status) => format!("provider error {}", status),
+    None => "connection failed".to_string(),
+  };
+  format!(
+    "{}, retrying in {}s (retry {} of {})",
+    reason,
+    remaining.as_secs_f64().ceil(),
+    retry,
+    max
+  )
+}
+
+/// when `error` can be retried and attempts are left, waits before retry `attempt`, counting
+/// down the wait on the status line through `status`. a wait the provider asked for in the
+/// headers of the response, `retry_after`, is taken over one in the error message. false when
+/// the request is not to be sent again
+pub async fn wait_to_retry(
+  config: &RetryConfig,
+  attempt: u32,
+  error: &OpenAIError,
+  retry_after: Option<Duration>,
+  mut status: impl FnMut(String),
+) -> bool {
+  let Some(mut retryable) = retryable(error).filter(|_| attempt < config.max_retries) else {
+    return false;
+  };
+  retryable.retry_after = retry_after.or(retryable.retry_after);
+  log::warn!("retrying request, attempt {} failed: {}", attempt + 1, error);
+  let mut remaining = config.delay(attempt, retryable.retry_after, rand::random::<f64>());
+  while !remaining.is_zero() {
+    status(retry_status(&retryable, remaining, attempt + 1, config.max_retries));
+    let step = remaining.min(Duration::from_secs(1));
+    tokio::time::sleep(step).await;
+    remaining -= step;
+  }
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_retry_delay() {
+    assert_eq!(retry_after("Please try again in 20s. Visit"), Some(Duration::from_secs(20)));
+    assert_eq!(retry_after("please try again in 334ms."), Some(Duration::from_millis(334)));
+    assert_eq!(retry_after("Please try again in 1m30s."), Some(Duration::from_secs(90)));
+    assert_eq!(retry_after("Please retry after 6 seconds."), Some(Duration::from_secs(6)));
+    assert_eq!(retry_after("Invalid status code: 429 Too Many Requests"), None);
+
+    let mut headers = HeaderMap::new();
+    assert_eq!(retry_after_header(&headers), None);
+    headers.insert("retry-after", "7".parse().unwrap());
+    assert_eq!(retry_after_header(&headers), Some(Duration::from_secs(7)));
+    headers.insert("retry-after-ms", "1500".parse().unwrap());
+    assert_eq!(retry_after_header(&headers), Some(Duration::from_millis(1500)));
+    headers.remove("retry-after-ms");
+    headers.insert("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+    assert_eq!(retry_after_header(&headers), None);
+    let later = chrono::Utc::now() + chrono::Duration::seconds(120);
+    headers.insert("retry-after", later.to_rfc2822().parse().unwrap());
+    assert!(retry_after_header(&headers).is_some_and(|wait| wait > Duration::from_secs(100)));
+
+    let config = RetryConfig::default();
+    assert_eq!(config.delay(0, None, 0.0), Duration::from_secs(1));
+    assert_eq!(config.delay(3, None, 0.0), Duration::from_secs(8));
+    assert_eq!(config.delay(3, None, 1.0), Duration::from_secs(4));
+    assert_eq!(config.delay(20, None, 0.0), Duration::from_secs(60));
+    assert_eq!(config.delay(0, Some(Duration::from_secs(20)), 0.5), Duration::from_secs(20));
+
+    let error = retryable(&OpenAIError::StreamError(
+      "Invalid status code: 429 Too Many Requests".to_string(),
+    ))
+    .unwrap();
+    assert_eq!(error.status, Some(429));
+    assert_eq!(
+      retry_status(&error, Duration::from_millis(11_400), 1, 5),
+      "rate limited, retrying in 12s (retry 1 of 5)"
+    );
+    assert!(retryable(&OpenAIError::StreamError("Invalid status code: 401".to_string())).is_none());
+    assert!(retryable(&OpenAIError::InvalidArgument("model".to_string())).is_none());
+  }
+}
@@ -0,0 +1,78 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::model_tools::argument_validation::count_tokens;
+use super::semantic_search::{format_result, SearchResult};
+
+const RETRIEVAL_PROMPT: &str = "Code from the workspace that may be relevant to the user's next message. It was found by similarity and can be unrelated. When you use it, cite it by its number and path:";
+
+/// retrieval of indexed workspace code for each user message, sent along with the request
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetrievalConfig {
+  pub enabled: bool,
+  /// the most tokens the retrieved code may take up, taken from the context window
+  pub token_budget: usize,
+  pub max_chunks: usize,
+}
+
+impl Default for RetrievalConfig {
+  fn default() -> Self {
+    RetrievalConfig { enabled: false, token_budget: 2000, max_chunks: 8 }
+  }
+}
+
+/// a system message holding numbered results, the best first, until the token budget is spent.
+/// none if not even the first fits
+pub fn retrieval_context_message(
+  results: &[SearchResult],
+  workspace_root: &Path,
+  token_budget: usize,
+) -> Option<ChatCompletionRequestMessage> {
+  let mut content = RETRIEVAL_PROMPT.to_string();
+  let mut tokens = count_tokens(&content);
+  let mut included = 0;
+  for result in results {
+    let citation = format!("\n\n[{}] {}", included + 1, format_result(result, workspace_root));
+    let citation_tokens = count_tokens(&citation);
+    if tokens + citation_tokens > token_budget {
+      break;
+    }
+    tokens += citation_tokens;
+    content.push_str(&citation);
+    included += 1;
+  }
+  if included == 0 {
+    return None;
+  }
+  Some(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+    content,
+    role: Role::System,
+    name: None,
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::messages::get_chat_message_text;
+  use crate::app::semantic_search::CodeChunk;
+  use std::path::PathBuf;
+
+  #[test]
+  fn test_retrieval_context_message_fits_budget() {
+    let result = |path: &str, content: &str| SearchResult {
+      path: PathBuf::from(path),
+      chunk: CodeChunk { start_line: 1, end_line: 1, content: content.to_string() },
+    };
+    let results = vec![
+      result("/work/src/retry.rs", "fn backoff() {}"),
+      result("/work/src/data.rs", &"let data = 0;\n".repeat(200)),
+    ];
+    let message = retrieval_context_message(&results, Path::new("/work"), 200).unwrap();
+    let text = get_chat_message_text(&message);
+    assert!(text.ends_with("\n\n[1] src/retry.rs:1-1\n```rs\nfn backoff() {}\n```"));
+    assert!(!text.contains("src/data.rs"));
+    assert_eq!(retrieval_context_message(&results, Path::new("/work"), 10), None);
+  }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::database::{
+  data_models::EmbeddingModel,
+  vector_store::{StoredChunk, VectorStore},
+};
+use super::errors::SazidError;
+use super::hybrid_search::{bm25_scores, reciprocal_rank_fusion, HybridSearchConfig};
+use super::treesitter::treesitter_chunks::chunk_source;
+
+/// the most lines in a chunk, apart from the headers of source chunks
+pub const CHUNK_LINES: usize = 40;
+// each chunk repeats the last lines of the one before, so code near a boundary is found whole
+const CHUNK_OVERLAP_LINES: usize = 8;
+
+// each search contributes this many candidates per result asked for to the fused ranking
+const CANDIDATES_PER_RESULT: usize = 4;
+
+/// the number of files embedded at the same time when a workspace is indexed in the background
+pub const INDEX_CONCURRENCY: usize = 4;
+
+// larger files are usually generated or data, not code worth searching
+const MAX_INDEXED_FILE_BYTES: u64 = 256 * 1024;
+
+// part of the checksum of indexed files, raised when chunking changes so files are indexed again
+const CHUNKING_VERSION: u32 = 2;
+
+/// a range of lines of a file that is embedded and returned by searches as one unit
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeChunk {
+  /// one based, inclusive
+  pub start_line: usize,
+  pub end_line: usize,
+  pub content: String,
+}
+
+/// a chunk found by a search, with the file it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+  pub path: PathBuf,
+  pub chunk: CodeChunk,
+}
+
+pub fn chunk_lines(text: &str) -> Vec<CodeChunk> {
+  let lines = text.lines().collect::<Vec<_>>();
+  let mut chunks = vec![];
+  let mut start = 0;
+  while start < lines.len() {
+    let end = (start + CHUNK_LINES).min(lines.len());
+    let content = lines[start..end].join("\n");
+    if !content.trim().is_empty() {
+      chunks.push(CodeChunk { start_line: start + 1, end_line: end, content });
+    }
+    if end == lines.len() {
+      break;
+    }
+    start = end - CHUNK_OVERLAP_LINES;
+  }
+  chunks
+}
+
+/// source files are chunked at item boundaries where there is a grammar for them, other files by
+/// lines
+pub fn chunk_file(path: &Path, text: &str) -> Vec<CodeChunk> {
+  chunk_source(path, text).unwrap_or_else(|| chunk_lines(text))
+}
+
+/// the text files in the workspace that are indexed, leaving out hidden files and build output
+pub fn indexable_files(workspace_root: &Path) -> Vec<PathBuf> {
+  walkdir::WalkDir::new(workspace_root)
+    .into_iter()
+    .filter_entry(|entry| {
+      let name = entry.file_name().to_string_lossy();
+      entry.depth() == 0 || !(name.starts_with('.') || name == "target" || name == "node_modules")
+    })
+    .filter_map(|entry| entry.ok())
+    .filter(|entry| entry.file_type().is_file())
+    .map(|entry| entry.into_path())
+    .collect()
+}
+
+/// embeds the chunks of a file unless it is unchanged since it was last indexed. returns true if
+/// it was indexed, binary and very large files are not
+pub async fn index_file(
+  store: &dyn VectorStore,
+  model: &EmbeddingModel,
+  path: &Path,
+) -> Result<bool, SazidError> {
+  if std::fs::metadata(path)?.len() > MAX_INDEXED_FILE_BYTES {
+    return Ok(false);
+  }
+  let Ok(content) = std::fs::read_to_string(path) else {
+    return Ok(false);
+  };
+  let filepath = path.to_string_lossy();
+  // the path is part of the checksum so files with the same content are kept apart
+  let checksummed = format!("{}\n{}\n{}", CHUNKING_VERSION, filepath, content);
+  let checksum = blake3::hash(checksummed.as_bytes()).to_hex().to_string();
+  if store.contains_file(&checksum).await? {
+    return Ok(false);
+  }
+
+  // the embeddings are created before the old chunks are replaced, a failed request keeps them
+  let mut chunks = vec![];
+  for chunk in chunk_file(path, &content) {
+    let vector_content = format!("{}:{}\n{}", filepath, chunk.start_line, chunk.content);
+    chunks.push(StoredChunk {
+      embedding: model.create_embedding_vector(&vector_content).await?.to_vec(),
+      start_line: chunk.start_line,
+      end_line: chunk.end_line,
+      content: chunk.content,
+    });
+  }
+  store.replace_file(&filepath, &checksum, chunks).await?;
+  Ok(true)
+}
+
+/// the `count` chunks that best match `query`, found by meaning and by keyword and ranked by
+/// reciprocal rank fusion, so exact identifiers are found as well. only the files in `scope` are
+/// searched, every indexed file if it is not set. files deleted since they were indexed are
+/// left out
+pub async fn search(
+  store: &dyn VectorStore,
+  model: &EmbeddingModel,
+  query: &str,
+  count: usize,
+  scope: Option<&Path>,
+  config: &HybridSearchConfig,
+) -> Result<Vec<SearchResult>, SazidError> {
+  let candidates = count * CANDIDATES_PER_RESULT;
+  // the trailing separator keeps a sibling directory sharing the name's start out
+  let prefix = scope.map(|scope| scope.join("").to_string_lossy().to_string()).unwrap_or_default();
+  let mut chunks: HashMap<(String, usize), StoredChunk> = HashMap::new();
+  let mut rank = |ranked: Vec<(String, StoredChunk)>| {
+    ranked
+      .into_iter()
+      .map(|(filepath, chunk)| {
+        let key = (filepath, chunk.start_line);
+        chunks.entry(key.clone()).or_insert(chunk);
+        key
+      })
+      .collect::<Vec<_>>()
+  };
+
+  let vector_ranking = if config.vector_weight > 0.0 {
+    let vector = model.create_embedding_vector(query).await?.to_vec();
+    rank(store.nearest_chunks(vector, &prefix, candidates).await?)
+  } else {
+    vec![]
+  };
+  let keyword_ranking = if config.keyword_weight > 0.0 {
+    let text_chunks = store.text_chunks(&prefix).await?;
+    let contents = text_chunks.iter().map(|(_, chunk)| chunk.content.as_str()).collect::<Vec<_>>();
+    let scores = bm25_scores(query, &contents);
+    let mut ranked =
+      text_chunks.into_iter().zip(scores).filter(|(_, score)| *score > 0.0).collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    rank(ranked.into_iter().take(candidates).map(|(chunk, _)| chunk).collect())
+  } else {
+    vec![]
+  };
+
+  Ok(
+    reciprocal_rank_fusion(&vector_ranking, &keyword_ranking, config)
+      .into_iter()
+      .filter_map(|key| {
+        let chunk = chunks.remove(&key)?;
+        Some(SearchResult {
+          path: PathBuf::from(key.0),
+          chunk: CodeChunk {
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            content: chunk.content,
+          },
+        })
+      })
+      .filter(|result| result.path.exists())
+      .take(count)
+      .collect(),
+  )
+}
+
+/// a result as a fenced code block headed by its path, relative to the workspace root, and line
+/// range
+pub fn format_result(result: &SearchResult, workspace_root: &Path) -> String {
+  let path = result.path.strip_prefix(workspace_root).unwrap_or(&result.path);
+  let language = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+  format!(
+    "{}:{}-{}\n```{}\n{}\n```",
+    path.display(),
+    result.chunk.start_line,
+    result.chunk.end_line,
+    language,
+    result.chunk.content
+  )
+}
+
+pub fn format_results(results: &[SearchResult], workspace_root: &Path) -> String {
+  if results.is_empty() {
+    return "no matching code found".to_string();
+  }
+  results
+    .iter()
+    .map(|result| format_result(result, workspace_root))
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_chunk_lines() {
+    let text = (1..=90).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+    let ranges = chunk_lines(&text).iter().map(|c| (c.start_line, c.end_line)).collect::<Vec<_>>();
+    assert_eq!(ranges, vec![(1, 40), (33, 72), (65, 90)]);
+    assert!(chunk_lines(&text)[1].content.starts_with("line 33\n"));
+    assert_eq!(chunk_lines("\n\n"), vec![]);
+
+    let results = vec![SearchResult {
+      path: PathBuf::from("/work/src/retry.rs"),
+      chunk: CodeChunk { start_line: 3, end_line: 4, content: "fn backoff() {}".to_string() },
+    }];
+    assert_eq!(
+      format_results(&results, Path::new("/work")),
+      "src/retry.rs:3-4\n```rs\nfn backoff() {}\n```"
+    );
+  }
+}
@@ -1,4 +1,5 @@
 use std::{
+  collections::BTreeMap,
   path::PathBuf,
   time::{SystemTime, UNIX_EPOCH},
 };
@@ -6,7 +7,20 @@ use std::{
 use async_openai::types::{ChatCompletionRequestSystemMessage, Role};
 use serde::{Deserialize, Serialize};
 
-use super::{consts::*, types::Model};
+use super::model_tools::tool_permissions::ToolsConfig;
+use super::{
+  changelog::ChangelogConfig, consts::*, continuation::ContinueConfig,
+  database::vector_store::VectorStoreConfig, errors::SazidError, hybrid_search::HybridSearchConfig,
+  model_tools::shell_tool::ShellToolConfig, openai_config::OpenAIParams, prompt_profiles,
+  provider_fixtures::ProviderMode, rate_limit::RetryConfig, retrieval::RetrievalConfig,
+  types::Model,
+};
+
+// a message starting with this and a workspace name is sent to that workspace
+const WORKSPACE_TARGET_PREFIX: &str = "@workspace:";
+
+/// the settings `:set` changes for the session rather than the editor
+pub const SESSION_OPTIONS: &[&str] = &["model", "temperature", "max-tokens"];
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct WorkspaceParams {
@@ -14,46 +28,222 @@ pub struct WorkspaceParams {
   pub language: String,
   pub language_server: String,
   pub doc_path: Option<PathBuf>,
+  /// how messages refer to the workspace, the name of its directory if not set
+  #[serde(default)]
+  pub name: Option<String>,
 }
+
+impl WorkspaceParams {
+  pub fn name(&self) -> String {
+    match &self.name {
+      Some(name) => name.clone(),
+      None => self
+        .workspace_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| self.workspace_path.display().to_string()),
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SessionConfig {
   pub prompt: String,
+  /// the name of the prompt profile the system prompt was taken from
+  #[serde(default)]
+  pub prompt_profile: Option<String>,
+  /// system prompts by name, in addition to the built in ones
+  #[serde(default)]
+  pub prompt_profiles: BTreeMap<String, String>,
   pub id: String,
   pub title: String,
+  /// set once the title has been generated or chosen by the user, no title is generated after
+  #[serde(default)]
+  pub title_set: bool,
+  #[serde(default)]
+  pub tags: Vec<String>,
   pub session_dir: PathBuf,
   pub disabled_tools: Vec<String>,
   pub tools_enabled: bool,
   pub accessible_paths: Vec<PathBuf>,
+  /// files whose contents are sent with every request
+  #[serde(default)]
+  pub pinned_files: Vec<PathBuf>,
+  /// the workspace tool calls are scoped to
   pub workspace: Option<WorkspaceParams>,
+  /// other workspaces registered alongside it, which messages can switch to
+  #[serde(default)]
+  pub workspaces: Vec<WorkspaceParams>,
   pub model: Model,
+  /// the model that titles the session. if not set, a cheap model when `model` is served by
+  /// openai, `model` itself otherwise
+  #[serde(default)]
+  pub title_model: Option<Model>,
   pub retrieval_augmentation_message_count: Option<i64>,
   pub user: String,
   pub include_functions: bool,
   pub stream_response: bool,
   pub function_result_max_tokens: usize,
   pub response_max_tokens: usize,
+  #[serde(default)]
+  pub temperature: Option<f32>,
   pub database_url: String,
+  /// where workspace file embeddings are kept for semantic search
+  #[serde(default)]
+  pub vector_store: VectorStoreConfig,
+  /// how keyword and vector matches are weighed in semantic search
+  #[serde(default)]
+  pub hybrid_search: HybridSearchConfig,
+  #[serde(default)]
+  pub openai: OpenAIParams,
+  #[serde(default)]
+  pub changelog: ChangelogConfig,
+  #[serde(default)]
+  pub retrieval: RetrievalConfig,
+  /// show the session picker at launch when sessions have been saved and none is opened
+  #[serde(default)]
+  pub startup_session_picker: bool,
+  /// tools that run a command, declared in the config and offered to the model with the
+  /// built in ones
+  #[serde(default)]
+  pub shell_tools: Vec<ShellToolConfig>,
+  /// how chat requests are retried when they are rate limited or the provider fails
+  #[serde(default)]
+  pub retry: RetryConfig,
+  /// whether requests go to the provider, are recorded to a fixture file, replayed from one or
+  /// answered by a mock script
+  #[serde(default)]
+  pub provider: ProviderMode,
+  /// what the column beside each message shows
+  #[serde(default)]
+  pub gutter: GutterConfig,
+  /// pastes with more lines than this are attached to the next message as a snippet instead of
+  /// going into the input, 0 to always paste into the input
+  #[serde(default = "default_snippet_paste_lines")]
+  pub snippet_paste_lines: usize,
+  /// how mermaid and graphviz blocks in replies are drawn as images
+  #[serde(default)]
+  pub diagrams: DiagramConfig,
+  /// the most replies an agent task is asked for before it is stopped, see `app::agent_task`
+  #[serde(default = "default_agent_max_steps")]
+  pub agent_max_steps: usize,
+  /// whether replies cut off at `response_max_tokens` are continued, see `app::continuation`
+  #[serde(default)]
+  pub auto_continue: ContinueConfig,
+  /// which tools are offered to the model, which need the user to allow each call, and where
+  /// edits may write, see `model_tools::tool_permissions`
+  #[serde(default)]
+  pub tools: ToolsConfig,
+  /// where the tool calls of each session are logged, as `<session id>.jsonl`. not logged when
+  /// unset, the terminal ui sets it to `audit` in the data directory
+  #[serde(default)]
+  pub audit_dir: Option<PathBuf>,
+}
+
+fn default_snippet_paste_lines() -> usize {
+  40
+}
+
+fn default_agent_max_steps() -> usize {
+  20
+}
+
+/// how the gutter shows the role of a message
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GutterRole {
+  /// only the index of the message
+  None,
+  /// an icon before the index of the message
+  #[default]
+  Icon,
+  /// the name of the role in place of the index
+  Name,
+}
+
+/// the lines of the column beside each message, one below the other
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct GutterConfig {
+  pub role: GutterRole,
+  /// the time the message was sent
+  pub timestamp: bool,
+  /// the model that wrote an assistant message
+  pub model: bool,
+}
+
+impl Default for GutterConfig {
+  fn default() -> Self {
+    GutterConfig { role: GutterRole::Icon, timestamp: true, model: true }
+  }
+}
+
+/// renders the mermaid and graphviz blocks of replies to images shown in place of the code, in
+/// terminals supporting the kitty or iTerm2 graphics protocol
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct DiagramConfig {
+  pub enabled: bool,
+  /// the command writing the png of a mermaid diagram, with `{input}` and `{output}` where the
+  /// paths of the diagram source and the png go
+  pub mermaid_command: String,
+  /// the command writing the png of a graphviz diagram
+  pub graphviz_command: String,
+}
+
+impl Default for DiagramConfig {
+  fn default() -> Self {
+    DiagramConfig {
+      enabled: false,
+      mermaid_command: "mmdc --input {input} --output {output}".to_string(),
+      graphviz_command: "dot -Tpng {input} -o {output}".to_string(),
+    }
+  }
 }
 
 impl Default for SessionConfig {
   fn default() -> Self {
     SessionConfig {
       prompt: String::new(),
+      prompt_profile: None,
+      prompt_profiles: BTreeMap::new(),
       id: Self::generate_session_id(),
       title: chrono::Utc::now().to_rfc3339(),
+      title_set: false,
+      tags: vec![],
       session_dir: PathBuf::new(),
       disabled_tools: vec![],
       workspace: None,
+      workspaces: vec![],
       tools_enabled: true,
       accessible_paths: vec![],
+      pinned_files: vec![],
       model: GPT4_O.clone(),
+      title_model: None,
       retrieval_augmentation_message_count: Some(10),
       user: "sazid_user_1234".to_string(),
       function_result_max_tokens: 8192,
       response_max_tokens: 4095,
+      temperature: None,
       include_functions: true,
       stream_response: true,
       database_url: String::new(),
+      vector_store: VectorStoreConfig::default(),
+      hybrid_search: HybridSearchConfig::default(),
+      openai: OpenAIParams::default(),
+      changelog: ChangelogConfig::default(),
+      retrieval: RetrievalConfig::default(),
+      startup_session_picker: false,
+      shell_tools: vec![],
+      retry: RetryConfig::default(),
+      provider: ProviderMode::default(),
+      gutter: GutterConfig::default(),
+      snippet_paste_lines: default_snippet_paste_lines(),
+      diagrams: DiagramConfig::default(),
+      agent_max_steps: default_agent_max_steps(),
+      auto_continue: ContinueConfig::default(),
+      tools: ToolsConfig::default(),
+      audit_dir: None,
     }
   }
 }
@@ -67,6 +257,118 @@ impl SessionConfig {
     }
   }
 
+  /// whether a tool is offered to the model, by `disabled_tools` and the `[tools]` section
+  pub fn tool_enabled(&self, tool_name: &str) -> bool {
+    !self.disabled_tools.iter().any(|name| name == tool_name) && self.tools.is_offered(tool_name)
+  }
+
+  /// the active workspace and the other registered ones, each once
+  pub fn all_workspaces(&self) -> Vec<&WorkspaceParams> {
+    let mut workspaces: Vec<&WorkspaceParams> = self.workspace.iter().collect();
+    for workspace in &self.workspaces {
+      if !workspaces.iter().any(|w| w.workspace_path == workspace.workspace_path) {
+        workspaces.push(workspace);
+      }
+    }
+    workspaces
+  }
+
+  pub fn workspace_by_name(&self, name: &str) -> Option<&WorkspaceParams> {
+    self.all_workspaces().into_iter().find(|workspace| workspace.name() == name)
+  }
+
+  /// the built in and configured prompt profiles by name
+  pub fn all_prompt_profiles(&self) -> BTreeMap<String, String> {
+    prompt_profiles::prompt_profiles(&self.prompt_profiles)
+  }
+
+  /// sets one of the `SESSION_OPTIONS` from its text. `default` unsets the temperature
+  pub fn set_option(&mut self, key: &str, value: &str) -> Result<(), SazidError> {
+    let invalid = || SazidError::Other(format!("invalid {}: {}", key, value));
+    match key {
+      "model" => {
+        self.model = Model::from_name(value)
+          .ok_or_else(|| SazidError::Other(format!("unknown model: {}", value)))?;
+      },
+      "temperature" if value == "default" => self.temperature = None,
+      "temperature" => {
+        let temperature = value.parse::<f32>().map_err(|_| invalid())?;
+        if !(0.0..=2.0).contains(&temperature) {
+          return Err(SazidError::Other("temperature must be between 0 and 2".to_string()));
+        }
+        self.temperature = Some(temperature);
+      },
+      "max-tokens" => {
+        self.response_max_tokens =
+          value.parse::<usize>().ok().filter(|tokens| *tokens > 0).ok_or_else(invalid)?;
+      },
+      _ => return Err(SazidError::Other(format!("unknown session option: {}", key))),
+    }
+    Ok(())
+  }
+
+  /// the model the session is titled with
+  pub fn title_model(&self) -> Model {
+    match &self.title_model {
+      Some(model) => model.clone(),
+      None if self.model.endpoint == GPT3_TURBO.endpoint => GPT3_TURBO.clone(),
+      None => self.model.clone(),
+    }
+  }
+
+  /// the model and sampling settings requests are made with, as shown in the statusline
+  pub fn model_settings(&self) -> String {
+    let mut settings = vec![self.model.name.clone()];
+    if let Some(temperature) = self.temperature {
+      settings.push(format!("temp {}", temperature));
+    }
+    settings.push(format!("max {}", self.response_max_tokens));
+    settings.join(" ")
+  }
+
+  /// takes the settings of a reloaded config file that can change while a session runs. what
+  /// belongs to the session, like its id, title, workspaces and pinned files, and the openai
+  /// settings that command line arguments may have overridden are kept
+  pub fn apply_reloaded(&mut self, reloaded: &SessionConfig) {
+    self.model = reloaded.model.clone();
+    self.title_model = reloaded.title_model.clone();
+    self.temperature = reloaded.temperature;
+    self.response_max_tokens = reloaded.response_max_tokens;
+    self.function_result_max_tokens = reloaded.function_result_max_tokens;
+    self.retrieval_augmentation_message_count = reloaded.retrieval_augmentation_message_count;
+    self.disabled_tools = reloaded.disabled_tools.clone();
+    self.tools_enabled = reloaded.tools_enabled;
+    self.include_functions = reloaded.include_functions;
+    self.stream_response = reloaded.stream_response;
+    self.hybrid_search = reloaded.hybrid_search.clone();
+    self.changelog = reloaded.changelog.clone();
+    self.retrieval = reloaded.retrieval.clone();
+    self.prompt_profiles = reloaded.prompt_profiles.clone();
+    self.startup_session_picker = reloaded.startup_session_picker;
+    self.shell_tools = reloaded.shell_tools.clone();
+    self.retry = reloaded.retry.clone();
+    self.provider = reloaded.provider.clone();
+    self.gutter = reloaded.gutter;
+    self.snippet_paste_lines = reloaded.snippet_paste_lines;
+    self.diagrams = reloaded.diagrams.clone();
+    self.agent_max_steps = reloaded.agent_max_steps;
+    self.auto_continue = reloaded.auto_continue.clone();
+    self.tools = reloaded.tools.clone();
+  }
+
+  /// the settings of a new session started beside this one. it has its own id and title and
+  /// starts with nothing pinned or tagged
+  pub fn for_new_session(&self) -> SessionConfig {
+    SessionConfig {
+      id: Self::generate_session_id(),
+      title: chrono::Utc::now().to_rfc3339(),
+      title_set: false,
+      tags: vec![],
+      pinned_files: vec![],
+      ..self.clone()
+    }
+  }
+
   pub fn generate_session_id() -> String {
     // Get the current time since UNIX_EPOCH in seconds.
     let start = SystemTime::now();
@@ -79,3 +381,92 @@ impl SessionConfig {
     since_the_epoch.to_string()
   }
 }
+
+/// the workspace name and the rest of a message starting with `@workspace:<name>`
+pub fn parse_workspace_target(input: &str) -> Option<(&str, &str)> {
+  let target = input.trim_start().strip_prefix(WORKSPACE_TARGET_PREFIX)?;
+  let (name, rest) = target.split_once(char::is_whitespace).unwrap_or((target, ""));
+  if name.is_empty() {
+    return None;
+  }
+  Some((name, rest.trim_start()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_workspace_targets() {
+    let workspace = |path: &str, name: Option<&str>| WorkspaceParams {
+      workspace_path: PathBuf::from(path),
+      language: "rust".to_string(),
+      language_server: "rust-analyzer".to_string(),
+      doc_path: None,
+      name: name.map(String::from),
+    };
+    let config = SessionConfig {
+      workspace: Some(workspace("/work/backend", None)),
+      workspaces: vec![workspace("/work/backend", None), workspace("/work/web", Some("frontend"))],
+      ..SessionConfig::default()
+    };
+    assert_eq!(config.all_workspaces().len(), 2);
+    assert_eq!(
+      config.workspace_by_name("frontend").map(|w| &w.workspace_path),
+      Some(&PathBuf::from("/work/web"))
+    );
+    assert_eq!(config.workspace_by_name("web"), None);
+
+    assert_eq!(
+      parse_workspace_target("@workspace:backend find symbol Foo"),
+      Some(("backend", "find symbol Foo"))
+    );
+    assert_eq!(parse_workspace_target("@workspace:backend"), Some(("backend", "")));
+    assert_eq!(parse_workspace_target("@workspace: find"), None);
+    assert_eq!(parse_workspace_target("find @workspace:backend"), None);
+  }
+
+  #[test]
+  fn test_apply_reloaded() {
+    let mut config = SessionConfig {
+      title: "refactor".to_string(),
+      pinned_files: vec![PathBuf::from("src/main.rs")],
+      ..SessionConfig::default()
+    };
+    let reloaded = SessionConfig {
+      title: "from the file".to_string(),
+      temperature: Some(0.2),
+      disabled_tools: vec!["create_file".to_string()],
+      ..SessionConfig::default()
+    };
+    config.apply_reloaded(&reloaded);
+    assert_eq!(config.temperature, Some(0.2));
+    assert_eq!(config.disabled_tools, vec!["create_file".to_string()]);
+    assert_eq!(config.title, "refactor");
+    assert_eq!(config.pinned_files, vec![PathBuf::from("src/main.rs")]);
+  }
+
+  #[test]
+  fn test_title_model() {
+    assert_eq!(SessionConfig::default().title_model(), *GPT3_TURBO);
+    let local = SessionConfig { model: WIZARDLM.clone(), ..SessionConfig::default() };
+    assert_eq!(local.title_model(), *WIZARDLM);
+    let config = SessionConfig { title_model: Some(GPT4.clone()), ..SessionConfig::default() };
+    assert_eq!(config.title_model(), *GPT4);
+  }
+
+  #[test]
+  fn test_set_option() {
+    let mut config = SessionConfig::default();
+    config.set_option("model", "gpt-4-turbo-preview").unwrap();
+    config.set_option("temperature", "0.2").unwrap();
+    config.set_option("max-tokens", "2048").unwrap();
+    assert_eq!(config.model_settings(), "gpt-4-turbo-preview temp 0.2 max 2048");
+
+    assert!(config.set_option("temperature", "3").is_err());
+    assert!(config.set_option("max-tokens", "0").is_err());
+    assert!(config.set_option("model", "gpt-5").is_err());
+    config.set_option("temperature", "default").unwrap();
+    assert_eq!(config.temperature, None);
+  }
+}
@@ -0,0 +1,512 @@
+use std::{
+  collections::HashSet,
+  fs,
+  path::{Path, PathBuf},
+};
+
+use async_openai::types::ChatCompletionRequestMessage;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{
+  errors::SazidError,
+  messages::{get_chat_message_text, MessageContainer},
+};
+use crate::components::session::Session;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+  id TEXT PRIMARY KEY,
+  title TEXT NOT NULL,
+  config TEXT NOT NULL,
+  usage TEXT NOT NULL,
+  enabled_tools TEXT NOT NULL,
+  context_summary TEXT,
+  summarized_message_ids TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+  session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+  message_id INTEGER NOT NULL,
+  position INTEGER NOT NULL,
+  role TEXT NOT NULL,
+  content TEXT NOT NULL,
+  container TEXT NOT NULL,
+  PRIMARY KEY (session_id, message_id)
+);
+CREATE INDEX IF NOT EXISTS messages_session_position ON messages (session_id, position);
+CREATE TABLE IF NOT EXISTS tool_calls (
+  session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+  message_id INTEGER NOT NULL,
+  tool_call_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  arguments TEXT NOT NULL,
+  PRIMARY KEY (session_id, tool_call_id)
+);
+CREATE TABLE IF NOT EXISTS tool_outputs (
+  session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+  tool_call_id TEXT NOT NULL,
+  content TEXT NOT NULL,
+  PRIMARY KEY (session_id, tool_call_id)
+);
+CREATE TABLE IF NOT EXISTS file_edits (
+  session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  edit TEXT NOT NULL,
+  PRIMARY KEY (session_id, position)
+);
+";
+
+// sessions saved as json before the sqlite store are renamed with this extension once imported
+const MIGRATED_EXTENSION: &str = "szd.migrated";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+  pub id: String,
+  pub title: String,
+  pub tags: Vec<String>,
+  pub updated_at: i64,
+  pub message_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSearchResult {
+  pub session_id: String,
+  pub session_title: String,
+  pub message_id: i64,
+  pub role: String,
+  pub content: String,
+}
+
+/// sqlite backed session storage. saving a session only writes the rows that changed, so
+/// long sessions are not rewritten on every save
+pub struct SessionStore {
+  connection: Connection,
+}
+
+impl SessionStore {
+  pub fn open(path: &Path) -> Result<Self, SazidError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+      fs::create_dir_all(parent)?;
+    }
+    Self::init(Connection::open(path)?)
+  }
+
+  pub fn open_in_memory() -> Result<Self, SazidError> {
+    Self::init(Connection::open_in_memory()?)
+  }
+
+  fn init(connection: Connection) -> Result<Self, SazidError> {
+    connection.pragma_update(None, "foreign_keys", "ON")?;
+    connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(SessionStore { connection })
+  }
+
+  pub fn save_session(&mut self, session: &Session) -> Result<(), SazidError> {
+    let now = chrono::Utc::now().timestamp();
+    let session_id = session.config.id.as_str();
+    let transaction = self.connection.transaction()?;
+    transaction.execute(
+      "INSERT INTO sessions (id, title, config, usage, enabled_tools, context_summary,
+         summarized_message_ids, created_at, updated_at)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+       ON CONFLICT (id) DO UPDATE SET title = excluded.title, config = excluded.config,
+         usage = excluded.usage, enabled_tools = excluded.enabled_tools,
+         context_summary = excluded.context_summary,
+         summarized_message_ids = excluded.summarized_message_ids,
+         updated_at = excluded.updated_at",
+      params![
+        session_id,
+        session.config.title,
+        serde_json::to_string(&session.config)?,
+        serde_json::to_string(&session.usage)?,
+        serde_json::to_string(&session.enabled_tools)?,
+        session.context_summary,
+        serde_json::to_string(&session.summarized_message_ids)?,
+        now,
+      ],
+    )?;
+
+    // messages before the loaded tail of a partially loaded session are left as they are
+    let offset = session.unloaded_message_count;
+    let current_ids = session.messages.iter().map(|m| m.message_id).collect::<HashSet<_>>();
+    let removed_ids = {
+      let mut statement = transaction
+        .prepare("SELECT message_id FROM messages WHERE session_id = ?1 AND position >= ?2")?;
+      let stored_ids = statement
+        .query_map(params![session_id, offset], |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+      stored_ids.into_iter().filter(|id| !current_ids.contains(id)).collect::<Vec<_>>()
+    };
+    for message_id in removed_ids {
+      transaction.execute(
+        "DELETE FROM messages WHERE session_id = ?1 AND message_id = ?2",
+        params![session_id, message_id],
+      )?;
+      transaction.execute(
+        "DELETE FROM tool_calls WHERE session_id = ?1 AND message_id = ?2",
+        params![session_id, message_id],
+      )?;
+    }
+
+    {
+      let mut upsert_message = transaction.prepare(
+        "INSERT INTO messages (session_id, message_id, position, role, content, container)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (session_id, message_id) DO UPDATE SET position = excluded.position,
+           role = excluded.role, content = excluded.content, container = excluded.container
+         WHERE position != excluded.position OR container != excluded.container",
+      )?;
+      let mut insert_tool_call = transaction.prepare(
+        "INSERT OR IGNORE INTO tool_calls (session_id, message_id, tool_call_id, name, arguments)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+      )?;
+      for (index, m) in session.messages.iter().enumerate() {
+        upsert_message.execute(params![
+          session_id,
+          m.message_id,
+          offset + index,
+          role_name(&m.message),
+          get_chat_message_text(&m.message),
+          serde_json::to_string(m)?,
+        ])?;
+        if let ChatCompletionRequestMessage::Assistant(message) = &m.message {
+          for tool_call in message.tool_calls.iter().flatten() {
+            insert_tool_call.execute(params![
+              session_id,
+              m.message_id,
+              tool_call.id,
+              tool_call.function.name,
+              tool_call.function.arguments,
+            ])?;
+          }
+        }
+      }
+      // outputs of finished tool calls do not change, so each is written once
+      let mut insert_tool_output = transaction.prepare(
+        "INSERT OR IGNORE INTO tool_outputs (session_id, tool_call_id, content)
+         VALUES (?1, ?2, ?3)",
+      )?;
+      for (tool_call_id, content) in &session.tool_outputs {
+        insert_tool_output.execute(params![session_id, tool_call_id, content])?;
+      }
+
+      // the file edit journal, so edits can be reverted after the session is reloaded
+      let mut upsert_file_edit = transaction.prepare(
+        "INSERT INTO file_edits (session_id, position, edit) VALUES (?1, ?2, ?3)
+         ON CONFLICT (session_id, position) DO UPDATE SET edit = excluded.edit
+         WHERE edit != excluded.edit",
+      )?;
+      for (position, edit) in session.file_edits.iter().enumerate() {
+        upsert_file_edit.execute(params![session_id, position, serde_json::to_string(edit)?])?;
+      }
+      transaction.execute(
+        "DELETE FROM file_edits WHERE session_id = ?1 AND position >= ?2",
+        params![session_id, session.file_edits.len()],
+      )?;
+    }
+    transaction.commit()?;
+    Ok(())
+  }
+
+  /// loads a session, or only its most recent `message_limit` messages
+  pub fn load_session(
+    &self,
+    session_id: &str,
+    message_limit: Option<usize>,
+  ) -> Result<Session, SazidError> {
+    let mut session = self
+      .connection
+      .query_row(
+        "SELECT config, usage, enabled_tools, context_summary, summarized_message_ids
+         FROM sessions WHERE id = ?1",
+        params![session_id],
+        |row| {
+          Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, String>(4)?,
+          ))
+        },
+      )
+      .optional()?
+      .map(|(config, usage, enabled_tools, context_summary, summarized_message_ids)| {
+        Ok::<_, SazidError>(Session {
+          config: serde_json::from_str(&config)?,
+          usage: serde_json::from_str(&usage)?,
+          enabled_tools: serde_json::from_str(&enabled_tools)?,
+          context_summary,
+          summarized_message_ids: serde_json::from_str(&summarized_message_ids)?,
+          ..Default::default()
+        })
+      })
+      .transpose()?
+      .ok_or_else(|| SazidError::Other(format!("session {} not found", session_id)))?;
+
+    let message_count = self.message_count(session_id)?;
+    let offset = message_limit.map_or(0, |limit| message_count.saturating_sub(limit));
+    let mut statement = self.connection.prepare(
+      "SELECT container FROM messages WHERE session_id = ?1 AND position >= ?2 ORDER BY position",
+    )?;
+    let containers = statement
+      .query_map(params![session_id, offset], |row| row.get::<_, String>(0))?
+      .collect::<Result<Vec<_>, _>>()?;
+    session.messages = containers
+      .iter()
+      .map(|container| serde_json::from_str::<MessageContainer>(container))
+      .collect::<Result<Vec<_>, _>>()?;
+    session.unloaded_message_count = offset;
+
+    let mut statement = self
+      .connection
+      .prepare("SELECT tool_call_id, content FROM tool_outputs WHERE session_id = ?1")?;
+    session.tool_outputs = statement
+      .query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+      .collect::<Result<_, _>>()?;
+
+    let mut statement = self
+      .connection
+      .prepare("SELECT edit FROM file_edits WHERE session_id = ?1 ORDER BY position")?;
+    let edits = statement
+      .query_map(params![session_id], |row| row.get::<_, String>(0))?
+      .collect::<Result<Vec<_>, _>>()?;
+    session.file_edits =
+      edits.iter().map(|edit| serde_json::from_str(edit)).collect::<Result<_, _>>()?;
+    Ok(session)
+  }
+
+  pub fn message_count(&self, session_id: &str) -> Result<usize, SazidError> {
+    Ok(self.connection.query_row(
+      "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
+      params![session_id],
+      |row| row.get::<_, usize>(0),
+    )?)
+  }
+
+  /// all sessions, most recently updated first
+  pub fn list_sessions(&self) -> Result<Vec<SessionSummary>, SazidError> {
+    let mut statement = self.connection.prepare(
+      "SELECT sessions.id, sessions.title, json_extract(sessions.config, '$.tags'),
+         sessions.updated_at, COUNT(messages.message_id)
+       FROM sessions LEFT JOIN messages ON messages.session_id = sessions.id
+       GROUP BY sessions.id ORDER BY sessions.updated_at DESC",
+    )?;
+    let sessions = statement
+      .query_map([], |row| {
+        let tags = row.get::<_, Option<String>>(2)?;
+        Ok(SessionSummary {
+          id: row.get(0)?,
+          title: row.get(1)?,
+          tags: tags.and_then(|tags| serde_json::from_str(&tags).ok()).unwrap_or_default(),
+          updated_at: row.get(3)?,
+          message_count: row.get(4)?,
+        })
+      })?
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(sessions)
+  }
+
+  /// case insensitive substring search over the text of every stored message
+  pub fn search_messages(
+    &self,
+    text: &str,
+    limit: usize,
+  ) -> Result<Vec<MessageSearchResult>, SazidError> {
+    let pattern =
+      format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let mut statement = self.connection.prepare(
+      "SELECT messages.session_id, sessions.title, messages.message_id, messages.role,
+         messages.content
+       FROM messages JOIN sessions ON sessions.id = messages.session_id
+       WHERE messages.content LIKE ?1 ESCAPE '\\'
+       ORDER BY sessions.updated_at DESC, messages.position DESC LIMIT ?2",
+    )?;
+    let results = statement
+      .query_map(params![pattern, limit], |row| {
+        Ok(MessageSearchResult {
+          session_id: row.get(0)?,
+          session_title: row.get(1)?,
+          message_id: row.get(2)?,
+          role: row.get(3)?,
+          content: row.get(4)?,
+        })
+      })?
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(results)
+  }
+
+  pub fn delete_session(&mut self, session_id: &str) -> Result<(), SazidError> {
+    self.connection.execute("DELETE FROM sessions WHERE id = ?1", params![session_id])?;
+    Ok(())
+  }
+
+  /// imports the json sessions saved in `dir` by earlier versions. imported files are renamed
+  /// so they are only imported once
+  pub fn migrate_json_sessions(&mut self, dir: &Path) -> Result<usize, SazidError> {
+    let Ok(entries) = fs::read_dir(dir) else {
+      return Ok(0);
+    };
+    let paths = entries
+      .filter_map(|entry| entry.ok().map(|e| e.path()))
+      .filter(|path| path.extension().is_some_and(|ext| ext == "szd"))
+      .collect::<Vec<PathBuf>>();
+    let mut migrated = 0;
+    for path in paths {
+      let session = match fs::read_to_string(&path)
+        .map_err(SazidError::from)
+        .and_then(|json| Ok(serde_json::from_str::<Session>(&json)?))
+      {
+        Ok(session) => session,
+        Err(e) => {
+          log::warn!("skipping session {}: {}", path.display(), e);
+          continue;
+        },
+      };
+      self.save_session(&session)?;
+      fs::rename(&path, path.with_extension(MIGRATED_EXTENSION))?;
+      migrated += 1;
+    }
+    Ok(migrated)
+  }
+}
+
+fn role_name(message: &ChatCompletionRequestMessage) -> &'static str {
+  match message {
+    ChatCompletionRequestMessage::System(_) => "system",
+    ChatCompletionRequestMessage::User(_) => "user",
+    ChatCompletionRequestMessage::Assistant(_) => "assistant",
+    ChatCompletionRequestMessage::Tool(_) => "tool",
+    ChatCompletionRequestMessage::Function(_) => "function",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+    ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent,
+    ChatCompletionToolType, FunctionCall, Role,
+  };
+
+  use crate::app::file_patch::FileEdit;
+
+  fn user(text: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::User(
+      ChatCompletionRequestUserMessage {
+        content: ChatCompletionRequestUserMessageContent::Text(text.to_string()),
+        role: Role::User,
+        name: None,
+      },
+    ))
+  }
+
+  fn assistant_tool_call(tool_call_id: &str) -> MessageContainer {
+    MessageContainer::new_from_completed_message(ChatCompletionRequestMessage::Assistant(
+      ChatCompletionRequestAssistantMessage {
+        name: None,
+        role: Role::Assistant,
+        content: None,
+        function_call: None,
+        tool_calls: Some(vec![ChatCompletionMessageToolCall {
+          id: tool_call_id.to_string(),
+          r#type: ChatCompletionToolType::Function,
+          function: FunctionCall { name: "lsp_query".to_string(), arguments: "{}".to_string() },
+        }]),
+      },
+    ))
+  }
+
+  fn session(title: &str, messages: Vec<MessageContainer>) -> Session {
+    let mut session = Session { messages, ..Default::default() };
+    session.config.id = title.to_string();
+    session.config.title = title.to_string();
+    session
+  }
+
+  #[test]
+  fn test_save_and_load_session() {
+    let mut store = SessionStore::open_in_memory().unwrap();
+    let mut saved = session("first", vec![user("hello"), assistant_tool_call("call_1")]);
+    saved.tool_outputs.insert("call_1".to_string(), "a long output".to_string());
+    store.save_session(&saved).unwrap();
+
+    // removed messages are deleted, new messages are appended
+    saved.messages.remove(0);
+    saved.messages.push(user("again"));
+    store.save_session(&saved).unwrap();
+
+    let loaded = store.load_session("first", None).unwrap();
+    assert_eq!(loaded.config.title, "first");
+    assert_eq!(loaded.messages, saved.messages);
+    assert_eq!(loaded.tool_outputs, saved.tool_outputs);
+    let tool_calls = store
+      .connection
+      .query_row("SELECT COUNT(*) FROM tool_calls WHERE session_id = 'first'", [], |row| {
+        row.get::<_, usize>(0)
+      })
+      .unwrap();
+    assert_eq!(tool_calls, 1);
+  }
+
+  #[test]
+  fn test_file_edits_are_kept_across_loads() {
+    let mut store = SessionStore::open_in_memory().unwrap();
+    let edit = |tool_call_id: &str, path: &str| FileEdit {
+      tool_call_id: tool_call_id.to_string(),
+      path: PathBuf::from(path),
+      original: Some("before".to_string()),
+      modified: "after".to_string(),
+    };
+    let mut saved = session("edited", vec![user("edit a and b")]);
+    saved.file_edits = vec![edit("call_1", "a.rs"), edit("call_2", "b.rs")];
+    store.save_session(&saved).unwrap();
+    assert_eq!(store.load_session("edited", None).unwrap().file_edits, saved.file_edits);
+
+    // a reverted edit is dropped from the journal
+    saved.file_edits.remove(0);
+    store.save_session(&saved).unwrap();
+    assert_eq!(store.load_session("edited", None).unwrap().file_edits, saved.file_edits);
+  }
+
+  #[test]
+  fn test_partial_load_keeps_unloaded_messages() {
+    let mut store = SessionStore::open_in_memory().unwrap();
+    let messages = (0..5).map(|i| user(&format!("message {}", i))).collect::<Vec<_>>();
+    store.save_session(&session("long", messages.clone())).unwrap();
+
+    let mut loaded = store.load_session("long", Some(2)).unwrap();
+    assert_eq!(loaded.messages, messages[3..].to_vec());
+    assert_eq!(loaded.unloaded_message_count, 3);
+
+    loaded.messages.push(user("message 5"));
+    store.save_session(&loaded).unwrap();
+    let reloaded = store.load_session("long", None).unwrap();
+    assert_eq!(reloaded.messages.len(), 6);
+    assert_eq!(reloaded.messages[..5].to_vec(), messages);
+  }
+
+  #[test]
+  fn test_list_and_search_sessions() {
+    let mut store = SessionStore::open_in_memory().unwrap();
+    store.save_session(&session("a", vec![user("where is 100% of the parser?")])).unwrap();
+    let mut b = session("b", vec![user("hello"), user("world")]);
+    b.config.tags = vec!["greeting".to_string()];
+    store.save_session(&b).unwrap();
+
+    let sessions = store.list_sessions().unwrap();
+    assert_eq!(sessions.len(), 2);
+    let b = sessions.iter().find(|s| s.id == "b").unwrap();
+    assert_eq!(b.message_count, 2);
+    assert_eq!(b.tags, vec!["greeting".to_string()]);
+
+    let results = store.search_messages("100% OF", 10).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].session_title, "a");
+    assert_eq!(results[0].role, "user");
+    assert!(store.search_messages("10_%", 10).unwrap().is_empty());
+  }
+}
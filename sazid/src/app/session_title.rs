@@ -0,0 +1,101 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+use serde::{Deserialize, Serialize};
+
+use super::messages::get_chat_message_text;
+
+/// a title is generated once this many user messages have been answered
+pub const TITLE_AFTER_EXCHANGES: usize = 2;
+/// the most title requests sent for a session, a failed one is retried after the next reply
+pub const MAX_TITLE_ATTEMPTS: usize = 2;
+
+const MAX_TAGS: usize = 5;
+// long messages add little to a title, only their beginning is sent
+const MAX_MESSAGE_CHARS: usize = 500;
+
+pub const TITLE_PROMPT: &str = "Write a short title (at most 8 words) and up to 5 lowercase single word tags for the following conversation between a user and a coding assistant. Respond with only a json object in the form {\"title\": \"...\", \"tags\": [\"...\"]}.";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionTitle {
+  pub title: String,
+  #[serde(default)]
+  pub tags: Vec<String>,
+}
+
+/// builds the messages for a request that titles the conversation so far. tool messages are
+/// left out, they are long and rarely describe what the session is about
+pub fn title_request_messages(
+  messages: &[ChatCompletionRequestMessage],
+) -> Vec<ChatCompletionRequestMessage> {
+  let transcript = messages
+    .iter()
+    .filter_map(|m| match m {
+      ChatCompletionRequestMessage::User(_) => Some(("user", m)),
+      ChatCompletionRequestMessage::Assistant(_) => Some(("assistant", m)),
+      _ => None,
+    })
+    .map(|(role, m)| {
+      let text = get_chat_message_text(m);
+      format!("{}: {}\n", role, text.chars().take(MAX_MESSAGE_CHARS).collect::<String>())
+    })
+    .collect::<String>();
+  vec![
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: TITLE_PROMPT.to_string(),
+      role: Role::System,
+      name: None,
+    }),
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: transcript,
+      role: Role::System,
+      name: None,
+    }),
+  ]
+}
+
+/// parses the title response. models sometimes wrap the json in a code fence or ignore the
+/// format entirely, in which case the first line is used as the title
+pub fn parse_title_response(response: &str) -> Option<SessionTitle> {
+  let json = match (response.find('{'), response.rfind('}')) {
+    (Some(start), Some(end)) if start < end => &response[start..=end],
+    _ => "",
+  };
+  let mut title = serde_json::from_str::<SessionTitle>(json).unwrap_or_else(|_| SessionTitle {
+    title: response.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().to_string(),
+    tags: vec![],
+  });
+  title.title = title.title.trim().trim_matches('"').trim().to_string();
+  title.tags = title
+    .tags
+    .iter()
+    .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+    .filter(|tag| !tag.is_empty())
+    .take(MAX_TAGS)
+    .collect();
+  match title.title.is_empty() {
+    true => None,
+    false => Some(title),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_title_response() {
+    let response =
+      "```json\n{\"title\": \"Fix table scrolling\", \"tags\": [\"Rust\", \"#tui\", \"\"]}\n```";
+    assert_eq!(
+      parse_title_response(response),
+      Some(SessionTitle {
+        title: "Fix table scrolling".to_string(),
+        tags: vec!["rust".to_string(), "tui".to_string()],
+      })
+    );
+    assert_eq!(
+      parse_title_response("\n\"Session storage\"\nsome explanation"),
+      Some(SessionTitle { title: "Session storage".to_string(), tags: vec![] })
+    );
+    assert_eq!(parse_title_response("  "), None);
+  }
+}
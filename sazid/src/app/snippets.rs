@@ -0,0 +1,50 @@
+// the line every snippet message starts with, so the session can tell pasted text from typed
+const SNIPPET_HEADER: &str = "The user attached this pasted snippet:";
+// the most characters of its first line a pending snippet's chip shows
+const CHIP_CHARS: usize = 32;
+
+/// true when pasted text has more than `max_lines` lines and is attached to the next message as
+/// a snippet rather than inserted into the input. a `max_lines` of 0 never attaches
+pub fn is_snippet_paste(text: &str, max_lines: usize) -> bool {
+  max_lines > 0 && text.lines().count() > max_lines
+}
+
+/// the message a snippet is sent to the model in
+pub fn snippet_message(snippet: &str) -> String {
+  format!("{}\n```\n{}\n```", SNIPPET_HEADER, snippet.trim_end())
+}
+
+/// the pasted text of a snippet message, none for other messages. a snippet too long for one
+/// message is split over several, only the first starts with the header and is found, without
+/// the text of the others
+pub fn snippet_text(content: &str) -> Option<&str> {
+  let text = content.strip_prefix(SNIPPET_HEADER)?.strip_prefix("\n```\n")?;
+  // the first message of a split snippet has no closing fence
+  Some(text.strip_suffix("\n```").unwrap_or(text))
+}
+
+/// the label of a snippet waiting for the next message, numbered from 1 as `:snippet-drop`
+/// takes it
+pub fn snippet_chip(number: usize, snippet: &str) -> String {
+  let first_line = snippet.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+  let first_line = first_line.trim().chars().take(CHIP_CHARS).collect::<String>();
+  format!("snippet {}: {} — {} lines", number, first_line, snippet.lines().count())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_snippet_message_round_trip() {
+    let log = "error: one\nerror: two\n".repeat(30);
+    assert!(is_snippet_paste(&log, 40));
+    assert!(!is_snippet_paste(&log, 60));
+    assert!(!is_snippet_paste(&log, 0));
+
+    let message = snippet_message(&log);
+    assert_eq!(snippet_text(&message), Some(log.trim_end()));
+    assert_eq!(snippet_text("error: one"), None);
+    assert_eq!(snippet_chip(2, &log), "snippet 2: error: one — 60 lines");
+  }
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use async_openai::types::{
+  ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, ChatCompletionResponseFormat,
+  ChatCompletionResponseFormatType, Role,
+};
+use jsonschema::{Draft, JSONSchema};
+use serde_json::Value;
+
+use super::errors::SazidError;
+
+/// how many times a reply that does not match the schema is asked for again
+pub const MAX_SCHEMA_REASKS: usize = 2;
+
+/// a json schema the replies of a session are held to. requests ask for a json object and carry
+/// the schema in a system message, and each reply is validated against it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseSchema {
+  pub path: PathBuf,
+  pub schema: Value,
+  /// the replies asked for again since the last one that matched
+  pub reasks: usize,
+}
+
+impl ResponseSchema {
+  /// reads a schema from a json or yaml file, making sure it compiles
+  pub fn load(path: &Path) -> Result<Self, SazidError> {
+    let contents = std::fs::read_to_string(path)?;
+    let schema: Value = serde_yaml::from_str(&contents)
+      .map_err(|e| SazidError::Other(format!("{} is not json or yaml: {}", path.display(), e)))?;
+    compile(&schema)?;
+    Ok(ResponseSchema { path: path.to_path_buf(), schema, reasks: 0 })
+  }
+
+  pub fn response_format(&self) -> ChatCompletionResponseFormat {
+    ChatCompletionResponseFormat { r#type: ChatCompletionResponseFormatType::JsonObject }
+  }
+
+  /// the system message sent last in each request, asking for a reply matching the schema
+  pub fn instructions(&self) -> ChatCompletionRequestMessage {
+    let schema = serde_json::to_string_pretty(&self.schema).unwrap_or_default();
+    ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: format!(
+        "Reply with a single JSON object that is valid against this JSON schema, and nothing \
+         else:\n\n{}",
+        schema
+      ),
+      role: Role::System,
+      name: None,
+    })
+  }
+
+  /// the json value of a reply, or what is wrong with it
+  pub fn validate(&self, reply: &str) -> Result<Value, Vec<String>> {
+    let value: Value = serde_json::from_str(strip_code_fence(reply))
+      .map_err(|e| vec![format!("not json: {}", e)])?;
+    let compiled = compile(&self.schema).map_err(|e| vec![e.to_string()])?;
+    let validation = compiled
+      .validate(&value)
+      .map_err(|errors| errors.map(|e| format!("at {}: {}", e.instance_path, e)).collect());
+    validation.map(|()| value)
+  }
+
+  /// the message asking again for a reply that did not match the schema
+  pub fn reask_prompt(errors: &[String]) -> String {
+    format!(
+      "The reply is not valid against the JSON schema:\n\n{}\n\nReply again with only the \
+       corrected JSON object.",
+      errors.iter().map(|e| format!("- {}", e)).collect::<Vec<_>>().join("\n")
+    )
+  }
+}
+
+fn compile(schema: &Value) -> Result<JSONSchema, SazidError> {
+  JSONSchema::options()
+    .with_draft(Draft::Draft7)
+    .compile(schema)
+    .map_err(|e| SazidError::Other(format!("invalid schema: {}", e)))
+}
+
+// models often wrap json in a markdown code block even when asked not to
+fn strip_code_fence(reply: &str) -> &str {
+  let reply = reply.trim();
+  match reply.strip_prefix("```").and_then(|rest| rest.strip_suffix("```")) {
+    Some(fenced) => fenced.split_once('\n').map_or(fenced, |(_, body)| body).trim(),
+    None => reply,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_replies_are_validated_against_the_schema() {
+    let schema = ResponseSchema {
+      path: PathBuf::from("release.json"),
+      schema: json!({
+        "type": "object",
+        "properties": { "version": { "type": "string" }, "breaking": { "type": "boolean" } },
+        "required": ["version"]
+      }),
+      reasks: 0,
+    };
+    let value = schema.validate("```json\n{\"version\": \"1.2.0\", \"breaking\": false}\n```");
+    assert_eq!(value, Ok(json!({ "version": "1.2.0", "breaking": false })));
+
+    let errors = schema.validate("{\"breaking\": \"yes\"}").unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(ResponseSchema::reask_prompt(&errors).contains("- at /breaking"));
+    assert!(schema.validate("version 1.2.0").unwrap_err()[0].starts_with("not json"));
+  }
+}
@@ -0,0 +1,173 @@
+use async_openai::types::{
+  ChatCompletionMessageToolCall, ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+  ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent, ChatCompletionTool,
+  ChatCompletionToolType, CreateChatCompletionRequest, FunctionCall, Role,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{json, Value};
+
+lazy_static! {
+  // a fenced block of a tool call, as the tools message asks for
+  static ref TOOL_CALL_BLOCK: Regex =
+    Regex::new(r"(?s)```[ \t]*tool_call[ \t]*\n(.*?)\n?[ \t]*```\s*").unwrap();
+}
+
+/// describes the tools to a model without native tool calls, and how to call them from its reply
+pub fn tools_message(tools: &[ChatCompletionTool]) -> ChatCompletionRequestMessage {
+  let described = tools
+    .iter()
+    .map(|tool| {
+      json!({
+        "name": tool.function.name,
+        "description": tool.function.description,
+        "parameters": tool.function.parameters,
+      })
+    })
+    .collect::<Vec<_>>();
+  ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+    content: format!(
+      "You can call these tools:\n\n{}\n\nTo call a tool, reply with a fenced block like this, \
+       one block per call, and wait for the results before going on:\n\n{}\n\nThe results are \
+       sent back in the next message.",
+      serde_json::to_string_pretty(&described).unwrap_or_default(),
+      tool_call_block("tool_name", &json!({ "argument": "value" }))
+    ),
+    role: Role::System,
+    name: None,
+  })
+}
+
+fn tool_call_block(name: &str, arguments: &Value) -> String {
+  format!("```tool_call\n{}\n```", json!({ "name": name, "arguments": arguments }))
+}
+
+/// rewrites a request for a model without native tool calls: the tools are described in a
+/// system message, and the tool calls and results of earlier replies become text
+pub fn shim_request(request: &mut CreateChatCompletionRequest) {
+  let tools = request.tools.take().unwrap_or_default();
+  request.tool_choice = None;
+  for message in request.messages.iter_mut() {
+    match message {
+      ChatCompletionRequestMessage::Assistant(assistant) => {
+        let Some(tool_calls) = assistant.tool_calls.take() else {
+          continue;
+        };
+        let blocks = tool_calls.iter().map(|call| {
+          let arguments = serde_json::from_str::<Value>(&call.function.arguments)
+            .unwrap_or_else(|_| Value::String(call.function.arguments.clone()));
+          tool_call_block(&call.function.name, &arguments)
+        });
+        let content = assistant.content.iter().cloned().chain(blocks).collect::<Vec<_>>();
+        assistant.content = Some(content.join("\n\n"));
+      },
+      ChatCompletionRequestMessage::Tool(tool) => {
+        let content = format!("Result of tool call {}:\n\n{}", tool.tool_call_id, tool.content);
+        *message = ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+          content: ChatCompletionRequestUserMessageContent::Text(content),
+          role: Role::User,
+          name: None,
+        });
+      },
+      _ => {},
+    }
+  }
+  if !tools.is_empty() {
+    // after the system prompt, so the tools are described before the conversation
+    let index = request
+      .messages
+      .iter()
+      .take_while(|m| matches!(m, ChatCompletionRequestMessage::System(_)))
+      .count();
+    request.messages.insert(index, tools_message(&tools));
+  }
+}
+
+/// takes the fenced tool calls out of a reply, returning the rest of it and the calls. blocks
+/// that do not hold a call are left in the reply. `id_prefix` makes the call ids unique
+pub fn parse_tool_calls(
+  content: &str,
+  id_prefix: &str,
+) -> (String, Vec<ChatCompletionMessageToolCall>) {
+  let mut tool_calls = vec![];
+  let rest = TOOL_CALL_BLOCK.replace_all(content, |captures: &regex::Captures| {
+    let call = serde_json::from_str::<Value>(&captures[1]).ok().and_then(|call| {
+      let name = call.get("name")?.as_str()?.to_string();
+      let arguments = match call.get("arguments") {
+        Some(Value::String(arguments)) => arguments.clone(),
+        Some(arguments) => arguments.to_string(),
+        None => "{}".to_string(),
+      };
+      Some(FunctionCall { name, arguments })
+    });
+    match call {
+      Some(function) => {
+        let id = format!("{}_{}", id_prefix, tool_calls.len());
+        tool_calls.push(ChatCompletionMessageToolCall {
+          id,
+          r#type: ChatCompletionToolType::Function,
+          function,
+        });
+        String::new()
+      },
+      None => {
+        log::warn!("tool call block without a call: {}", &captures[1]);
+        captures[0].to_string()
+      },
+    }
+  });
+  (rest.trim().to_string(), tool_calls)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use async_openai::types::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestToolMessage, FunctionObject,
+  };
+
+  #[test]
+  fn test_tool_calls_round_trip_through_text() {
+    let reply = "Let me look.\n\n```tool_call\n{\"name\": \"read_file\", \"arguments\": \
+                 {\"path\": \"src/main.rs\"}}\n```\n\n```tool_call\nnot a call\n```";
+    let (rest, calls) = parse_tool_calls(reply, "call_7");
+    assert_eq!(rest, "Let me look.\n\n```tool_call\nnot a call\n```");
+    assert_eq!(calls.len(), 1);
+    assert_eq!((calls[0].id.as_str(), calls[0].function.name.as_str()), ("call_7_0", "read_file"));
+    assert_eq!(calls[0].function.arguments, r#"{"path":"src/main.rs"}"#);
+
+    let mut request = CreateChatCompletionRequest {
+      messages: vec![
+        ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+          content: Some(rest),
+          tool_calls: Some(calls),
+          ..Default::default()
+        }),
+        ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+          role: Role::Tool,
+          content: "fn main() {}".to_string(),
+          tool_call_id: "call_7_0".to_string(),
+        }),
+      ],
+      tools: Some(vec![ChatCompletionTool {
+        r#type: ChatCompletionToolType::Function,
+        function: FunctionObject {
+          name: "read_file".to_string(),
+          description: Some("reads a file".to_string()),
+          parameters: None,
+        },
+      }]),
+      ..Default::default()
+    };
+    shim_request(&mut request);
+    assert!(request.tools.is_none());
+    assert_eq!(request.messages.len(), 3);
+    assert!(matches!(&request.messages[0], ChatCompletionRequestMessage::System(_)));
+    let ChatCompletionRequestMessage::Assistant(assistant) = &request.messages[1] else {
+      panic!("the reply is no longer an assistant message");
+    };
+    assert!(assistant.content.as_ref().unwrap().contains("\"name\":\"read_file\""));
+    assert!(assistant.tool_calls.is_none());
+    assert!(matches!(&request.messages[2], ChatCompletionRequestMessage::User(_)));
+  }
+}
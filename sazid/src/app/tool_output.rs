@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use async_openai::types::{ChatCompletionTool, ChatCompletionToolType, FunctionObject};
+use serde::Deserialize;
+
+use super::model_tools::types::FunctionProperty;
+
+/// the tool the model pages through tool outputs too long to send at once with. the session
+/// answers it from the outputs it kept, it is only offered once an output was truncated
+pub const READ_MORE_TOOL: &str = "read_more";
+
+#[derive(Deserialize, Debug)]
+struct ReadMoreArguments {
+  tool_call_id: String,
+  #[serde(default)]
+  offset: usize,
+}
+
+pub fn read_more_tool() -> ChatCompletionTool {
+  let parameters = FunctionProperty::Parameters {
+    properties: HashMap::from([
+      (
+        "tool_call_id".to_string(),
+        FunctionProperty::String {
+          required: true,
+          description: Some("the id of the tool call whose output was truncated".to_string()),
+        },
+      ),
+      (
+        "offset".to_string(),
+        FunctionProperty::Integer {
+          minimum: Some(0),
+          maximum: None,
+          required: true,
+          description: Some(
+            "where to continue reading, as given in the truncated output".to_string(),
+          ),
+        },
+      ),
+    ]),
+  };
+  ChatCompletionTool {
+    r#type: ChatCompletionToolType::Function,
+    function: FunctionObject {
+      name: READ_MORE_TOOL.to_string(),
+      description: Some(
+        "read more of a tool output that was too long to be returned at once".to_string(),
+      ),
+      parameters: serde_json::to_value(parameters).ok(),
+    },
+  }
+}
+
+/// the page of a tool output starting at the byte `offset`, as many whole lines as fit in
+/// `max_tokens`, and where the next page starts. a line too long for a page is split
+pub fn output_page(output: &str, offset: usize, max_tokens: usize) -> (&str, Option<usize>) {
+  let mut start = offset.min(output.len());
+  while !output.is_char_boundary(start) {
+    start -= 1;
+  }
+  let rest = &output[start..];
+  let bpe = tiktoken_rs::cl100k_base().unwrap();
+  let mut tokens = 0;
+  let mut end = 0;
+  for line in rest.split_inclusive('\n') {
+    let line_tokens = bpe.encode_with_special_tokens(line).len();
+    if tokens + line_tokens > max_tokens {
+      if end == 0 {
+        // about three bytes a token, so a split line stays within the page
+        end = (max_tokens * 3).clamp(1, line.len());
+        while !line.is_char_boundary(end) {
+          end += 1;
+        }
+      }
+      return (&rest[..end], Some(start + end));
+    }
+    tokens += line_tokens;
+    end += line.len();
+  }
+  (rest, None)
+}
+
+/// a tool output as it is sent to the model: the page at `offset`, followed by a note on how
+/// to read the rest with `read_more` when it did not fit
+pub fn paged_output(tool_call_id: &str, output: &str, offset: usize, max_tokens: usize) -> String {
+  match output_page(output, offset, max_tokens) {
+    (page, None) => page.to_string(),
+    (page, Some(next)) => format!(
+      "{}\n[output truncated, showing bytes {} to {} of {}. call {} with tool_call_id \"{}\" and \
+       offset {} to read on]",
+      page,
+      next - page.len(),
+      next,
+      output.len(),
+      READ_MORE_TOOL,
+      tool_call_id,
+      next
+    ),
+  }
+}
+
+/// answers a `read_more` call from the kept outputs of the session
+pub fn read_more(outputs: &HashMap<String, String>, arguments: &str, max_tokens: usize) -> String {
+  let arguments = match serde_json::from_str::<ReadMoreArguments>(arguments) {
+    Ok(arguments) => arguments,
+    Err(e) => return format!("Tool Call Error: invalid arguments: {}", e),
+  };
+  match outputs.get(&arguments.tool_call_id) {
+    Some(output) if arguments.offset < output.len() => {
+      paged_output(&arguments.tool_call_id, output, arguments.offset, max_tokens)
+    },
+    Some(output) => format!(
+      "Tool Call Error: offset {} is past the end of the output, which is {} bytes",
+      arguments.offset,
+      output.len()
+    ),
+    None => {
+      format!("Tool Call Error: no truncated output for tool call {}", arguments.tool_call_id)
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_paging() {
+    let output = (0..200).map(|i| format!("line {}\n", i)).collect::<String>();
+    assert_eq!(output_page(&output, 0, 10_000), (output.as_str(), None));
+
+    let (page, next) = output_page(&output, 0, 50);
+    assert!(page.ends_with('\n'));
+    let next = next.unwrap();
+    assert_eq!(next, page.len());
+    let mut read = page.to_string();
+    let mut offset = Some(next);
+    while let Some(start) = offset {
+      let (page, next) = output_page(&output, start, 50);
+      read.push_str(page);
+      offset = next;
+    }
+    assert_eq!(read, output);
+
+    // one long line is split instead of never fitting
+    let line = "x".repeat(1000);
+    let (part, rest) = output_page(&line, 0, 10);
+    assert!(!part.is_empty() && rest == Some(part.len()));
+
+    let outputs = HashMap::from([("call_1".to_string(), output.clone())]);
+    let first = paged_output("call_1", &output, 0, 50);
+    assert!(first.ends_with(&format!("and offset {} to read on]", next)));
+    let second = read_more(&outputs, r#"{"tool_call_id":"call_1","offset":7}"#, 50);
+    assert!(second.starts_with("line 1\n"));
+    assert!(read_more(&outputs, r#"{"tool_call_id":"call_2","offset":0}"#, 50)
+      .starts_with("Tool Call Error"));
+    assert!(read_more(&outputs, r#"{"tool_call_id":"call_1","offset":100000}"#, 50)
+      .starts_with("Tool Call Error"));
+  }
+}
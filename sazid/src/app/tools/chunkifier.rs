@@ -15,8 +15,8 @@ pub fn parse_input(
   model_max_tokens: usize,
 ) -> Result<Vec<String>, ChunkifierError> {
   let ingest_data = categorize_input(input)?;
-  let chunks = chunkify_parsed_input(ingest_data, tokens_per_chunk).unwrap();
-  check_token_count_model_limit(&chunks, model_max_tokens).unwrap();
+  let chunks = chunkify_parsed_input(ingest_data, tokens_per_chunk)?;
+  check_token_count_model_limit(&chunks, model_max_tokens)?;
   Ok(chunks)
 }
 
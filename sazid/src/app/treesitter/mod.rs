@@ -1,6 +1,28 @@
+pub mod treesitter_chunks;
 pub mod treesitter_extraction;
 pub mod treesitter_parser;
 pub mod treesitter_query;
+pub mod treesitter_symbols;
+
+use std::path::Path;
+use tree_sitter::Language;
+
+/// the grammar for a source file, by its extension. none for languages without one here
+pub fn language_for_path(path: &Path) -> Option<Language> {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some("rs") => Some(tree_sitter_rust::language()),
+    _ => None,
+  }
+}
+
+/// the tags query of a source file's grammar, which captures each definition as
+/// @definition.<kind> with its @name
+pub fn tags_query_for_path(path: &Path) -> Option<&'static str> {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some("rs") => Some(tree_sitter_rust::TAGGING_QUERY),
+    _ => None,
+  }
+}
 
 pub mod ts_proto {
   include!(concat!(env!("OUT_DIR"), "/treesitter.ts_proto.rs"));
@@ -0,0 +1,175 @@
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+use super::language_for_path;
+use crate::app::semantic_search::{chunk_lines, CodeChunk, CHUNK_LINES};
+
+// items whose body is chunked member by member when the whole item does not fit in one chunk
+const CONTAINER_KINDS: &[&str] = &["impl_item", "trait_item", "mod_item"];
+
+// lines that belong to the item after them
+const LEADING_KINDS: &[&str] = &["line_comment", "block_comment", "attribute_item"];
+
+/// an item with the comments and attributes before it, as zero based rows
+struct Segment<'a> {
+  start_row: usize,
+  end_row: usize,
+  node: Option<Node<'a>>,
+}
+
+/// chunks a source file at item boundaries, so functions, impls and structs are embedded whole.
+/// small items next to each other share a chunk, and the members of impls, traits and modules too
+/// large for one chunk are chunked on their own, headed by the lines that open their parents.
+/// none for languages without a grammar here
+pub fn chunk_source(path: &Path, text: &str) -> Option<Vec<CodeChunk>> {
+  let mut parser = Parser::new();
+  parser.set_language(language_for_path(path)?).ok()?;
+  let tree = parser.parse(text, None)?;
+  let lines = text.lines().collect::<Vec<_>>();
+  let mut chunks = vec![];
+  chunk_children(tree.root_node(), &lines, &[], &mut chunks);
+  Some(chunks)
+}
+
+fn chunk_children(parent: Node, lines: &[&str], headers: &[&str], chunks: &mut Vec<CodeChunk>) {
+  let mut group: Option<(usize, usize)> = None;
+  for segment in segments(parent) {
+    let segment_lines = segment.end_row - segment.start_row + 1;
+    let group_lines = group.map_or(0, |(start, _)| segment.end_row - start + 1);
+    if segment_lines > CHUNK_LINES || group_lines > CHUNK_LINES {
+      if let Some((start, end)) = group.take() {
+        push_chunk(lines, headers, start, end, chunks);
+      }
+    }
+    if segment_lines > CHUNK_LINES {
+      chunk_large_segment(&segment, lines, headers, chunks);
+      continue;
+    }
+    group = Some(match group {
+      Some((start, _)) => (start, segment.end_row),
+      None => (segment.start_row, segment.end_row),
+    });
+  }
+  if let Some((start, end)) = group {
+    push_chunk(lines, headers, start, end, chunks);
+  }
+}
+
+fn segments(parent: Node) -> Vec<Segment> {
+  let mut segments = vec![];
+  let mut leading_start = None;
+  let mut cursor = parent.walk();
+  for node in parent.named_children(&mut cursor) {
+    let start_row = leading_start.take().unwrap_or(node.start_position().row);
+    if LEADING_KINDS.contains(&node.kind()) {
+      leading_start = Some(start_row);
+      continue;
+    }
+    segments.push(Segment { start_row, end_row: end_row(node), node: Some(node) });
+  }
+  // comments after the last item
+  if let Some(start_row) = leading_start {
+    let end_row = parent.named_child(parent.named_child_count().saturating_sub(1)).map(end_row);
+    segments.push(Segment { start_row, end_row: end_row.unwrap_or(start_row), node: None });
+  }
+  segments
+}
+
+// a node ending at the start of a line, like a file ending in a newline, ends on the line before
+fn end_row(node: Node) -> usize {
+  let end = node.end_position();
+  if end.column == 0 && end.row > node.start_position().row {
+    end.row - 1
+  } else {
+    end.row
+  }
+}
+
+fn chunk_large_segment(
+  segment: &Segment,
+  lines: &[&str],
+  headers: &[&str],
+  chunks: &mut Vec<CodeChunk>,
+) {
+  let header_row = segment.node.map_or(segment.start_row, |node| node.start_position().row);
+  let mut headers = headers.to_vec();
+  headers.push(lines[header_row]);
+
+  let body = segment
+    .node
+    .filter(|node| CONTAINER_KINDS.contains(&node.kind()))
+    .and_then(|node| node.child_by_field_name("body"));
+  if let Some(body) = body {
+    chunk_children(body, lines, &headers, chunks);
+    return;
+  }
+
+  // a long function or other item is split by lines, the first piece shows its own first line
+  let text = lines[segment.start_row..=segment.end_row].join("\n");
+  for (i, chunk) in chunk_lines(&text).into_iter().enumerate() {
+    let headers = if i == 0 { &headers[..headers.len() - 1] } else { &headers[..] };
+    chunks.push(CodeChunk {
+      start_line: segment.start_row + chunk.start_line,
+      end_line: segment.start_row + chunk.end_line,
+      content: with_headers(headers, chunk.content),
+    });
+  }
+}
+
+fn push_chunk(
+  lines: &[&str],
+  headers: &[&str],
+  start_row: usize,
+  end_row: usize,
+  chunks: &mut Vec<CodeChunk>,
+) {
+  let content = lines[start_row..=end_row].join("\n");
+  if content.trim().is_empty() {
+    return;
+  }
+  chunks.push(CodeChunk {
+    start_line: start_row + 1,
+    end_line: end_row + 1,
+    content: with_headers(headers, content),
+  });
+}
+
+fn with_headers(headers: &[&str], content: String) -> String {
+  if headers.is_empty() {
+    return content;
+  }
+  format!("{}\n{}", headers.join("\n"), content)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_chunk_source_at_item_boundaries() {
+    let method = |i: usize| {
+      format!(
+        "  /// method {}\n  fn method_{}(&self) {{\n{}  }}\n",
+        i,
+        i,
+        "    work();\n".repeat(8)
+      )
+    };
+    let text = format!(
+      "use std::fmt;\n\n#[derive(Debug)]\nstruct Session {{\n  id: i64,\n}}\n\nimpl Session {{\n{}}}\n",
+      (0..4).map(method).collect::<Vec<_>>().join("\n")
+    );
+    let chunks = chunk_source(Path::new("session.rs"), &text).unwrap();
+    let ranges = chunks.iter().map(|c| (c.start_line, c.end_line)).collect::<Vec<_>>();
+    // the impl is 49 lines long, so its methods are chunked apart from the struct
+    assert_eq!(ranges, vec![(1, 6), (9, 43), (45, 55)]);
+    assert!(chunks[0].content.starts_with("use std::fmt;\n\n#[derive(Debug)]\nstruct Session {"));
+    assert!(chunks[1]
+      .content
+      .starts_with("impl Session {\n  /// method 0\n  fn method_0(&self) {"));
+    assert!(chunks[2].content.starts_with("impl Session {\n  /// method 3\n"));
+    assert!(chunks[2].content.ends_with("    work();\n  }"));
+
+    assert_eq!(chunk_source(Path::new("notes.txt"), &text), None);
+  }
+}
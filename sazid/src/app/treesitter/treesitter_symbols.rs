@@ -0,0 +1,177 @@
+use lsp_types::{DocumentSymbol, Position, Range, SymbolKind};
+use std::collections::HashMap;
+use std::path::Path;
+use tree_sitter::{Node, Parser, Query, QueryCursor};
+
+use super::{language_for_path, tags_query_for_path};
+use crate::app::file_summary::signature_at;
+
+/// a definition captured by a tags query
+struct Definition<'a> {
+  node: Node<'a>,
+  name_node: Node<'a>,
+  tag: String,
+  pattern_index: usize,
+}
+
+/// the symbols of a source file from its grammar's tags query, nested by the ranges they span
+/// like the document symbols of a language server. impls are included so their methods have a
+/// parent. none for languages without a tags query here
+pub fn document_symbols(path: &Path, text: &str) -> Option<Vec<DocumentSymbol>> {
+  let language = language_for_path(path)?;
+  let query = Query::new(language, tags_query_for_path(path)?).ok()?;
+  let mut parser = Parser::new();
+  parser.set_language(language).ok()?;
+  let tree = parser.parse(text, None)?;
+
+  // a node matched by several patterns, like a function in an impl, which is both a method and
+  // a function, is kept as the first pattern's kind
+  let mut definitions: HashMap<usize, Definition> = HashMap::new();
+  let mut cursor = QueryCursor::new();
+  for query_match in cursor.matches(&query, tree.root_node(), text.as_bytes()) {
+    let mut name_node = None;
+    let mut tag = None;
+    for capture in query_match.captures {
+      match query.capture_names()[capture.index as usize].as_str() {
+        "name" => name_node = Some(capture.node),
+        name => tag = name.strip_prefix("definition.").or(name.strip_prefix("reference.")),
+      }
+    }
+    let (Some(name_node), Some(tag)) = (name_node, tag) else {
+      continue;
+    };
+    if tag == "call" {
+      continue;
+    }
+    let node = name_node.parent().unwrap_or(name_node);
+    let definition = Definition {
+      node,
+      name_node,
+      tag: tag.to_string(),
+      pattern_index: query_match.pattern_index,
+    };
+    match definitions.get(&node.id()) {
+      Some(existing) if existing.pattern_index < definition.pattern_index => {},
+      _ => {
+        definitions.insert(node.id(), definition);
+      },
+    }
+  }
+
+  let mut definitions = definitions.into_values().collect::<Vec<_>>();
+  definitions.sort_by_key(|d| (d.node.start_byte(), std::cmp::Reverse(d.node.end_byte())));
+
+  // each symbol is open on the stack until a symbol after its end is reached
+  let mut roots = vec![];
+  let mut stack: Vec<(usize, DocumentSymbol)> = vec![];
+  for definition in definitions {
+    while stack.last().is_some_and(|(end, _)| *end <= definition.node.start_byte()) {
+      close_symbol(&mut stack, &mut roots);
+    }
+    stack.push((definition.node.end_byte(), document_symbol(&definition, text)));
+  }
+  while !stack.is_empty() {
+    close_symbol(&mut stack, &mut roots);
+  }
+  Some(roots)
+}
+
+fn close_symbol(stack: &mut Vec<(usize, DocumentSymbol)>, roots: &mut Vec<DocumentSymbol>) {
+  let Some((_, mut symbol)) = stack.pop() else {
+    return;
+  };
+  match stack.last_mut() {
+    Some((_, parent)) => {
+      // tags queries can not tell functions in a module body from methods in an impl body
+      if parent.kind == SymbolKind::MODULE && symbol.kind == SymbolKind::METHOD {
+        symbol.kind = SymbolKind::FUNCTION;
+      }
+      parent.children.get_or_insert_with(Vec::new).push(symbol);
+    },
+    None => roots.push(symbol),
+  }
+}
+
+#[allow(deprecated)]
+fn document_symbol(definition: &Definition, text: &str) -> DocumentSymbol {
+  let row = definition.node.start_position().row;
+  let (name, detail) = match definition.tag.as_str() {
+    "implementation" => (signature_at(text, row), None),
+    _ => (
+      definition.name_node.utf8_text(text.as_bytes()).unwrap_or_default().to_string(),
+      Some(signature_at(text, row)),
+    ),
+  };
+  DocumentSymbol {
+    name,
+    detail,
+    kind: symbol_kind(&definition.tag, definition.node.kind()),
+    tags: None,
+    deprecated: None,
+    range: node_range(definition.node),
+    selection_range: node_range(definition.name_node),
+    children: None,
+  }
+}
+
+fn symbol_kind(tag: &str, node_kind: &str) -> SymbolKind {
+  match tag {
+    "function" | "macro" => SymbolKind::FUNCTION,
+    "method" => SymbolKind::METHOD,
+    "interface" => SymbolKind::INTERFACE,
+    "module" => SymbolKind::MODULE,
+    "constant" => SymbolKind::CONSTANT,
+    "implementation" => SymbolKind::OBJECT,
+    "class" if node_kind.starts_with("enum") => SymbolKind::ENUM,
+    "class" if node_kind.starts_with("struct") => SymbolKind::STRUCT,
+    "class" => SymbolKind::CLASS,
+    _ => SymbolKind::VARIABLE,
+  }
+}
+
+// tree-sitter columns are bytes, as are utf-8 language server positions
+fn node_range(node: Node) -> Range {
+  let (start, end) = (node.start_position(), node.end_position());
+  Range::new(
+    Position::new(start.row as u32, start.column as u32),
+    Position::new(end.row as u32, end.column as u32),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn flatten(symbols: &[DocumentSymbol], depth: usize, out: &mut Vec<(usize, String, SymbolKind)>) {
+    for symbol in symbols {
+      out.push((depth, symbol.name.clone(), symbol.kind));
+      flatten(symbol.children.as_deref().unwrap_or_default(), depth + 1, out);
+    }
+  }
+
+  #[test]
+  fn test_document_symbols() {
+    let text = "mod net {\n  pub fn connect() {}\n}\n\nstruct Session;\n\nenum State {\n  Idle,\n}\n\nimpl Session {\n  fn new() -> Self {\n    Session\n  }\n}\n\ntrait Store {\n  fn load(&self);\n}\n";
+    let symbols = document_symbols(Path::new("lib.rs"), text).unwrap();
+    let mut flat = vec![];
+    flatten(&symbols, 0, &mut flat);
+    let flat = flat.iter().map(|(d, n, k)| (*d, n.as_str(), *k)).collect::<Vec<_>>();
+    assert_eq!(
+      flat,
+      vec![
+        (0, "net", SymbolKind::MODULE),
+        (1, "connect", SymbolKind::FUNCTION),
+        (0, "Session", SymbolKind::STRUCT),
+        (0, "State", SymbolKind::ENUM),
+        (0, "impl Session", SymbolKind::OBJECT),
+        (1, "new", SymbolKind::METHOD),
+        (0, "Store", SymbolKind::INTERFACE),
+      ]
+    );
+    assert_eq!(
+      symbols[3].children.as_ref().unwrap()[0].detail.as_deref(),
+      Some("fn new() -> Self")
+    );
+    assert_eq!(document_symbols(Path::new("notes.txt"), text), None);
+  }
+}
@@ -93,7 +93,26 @@ pub struct Model {
   pub name: String,
   pub endpoint: String,
   pub token_limit: u32,
+  /// usd per million prompt tokens
+  #[serde(default)]
+  pub prompt_cost_per_million: f64,
+  /// usd per million completion tokens
+  #[serde(default)]
+  pub completion_cost_per_million: f64,
+  /// the model has no native tool calls, tools are described in the prompt and called from
+  /// fenced blocks in its replies, see `app::tool_call_shim`
+  #[serde(default)]
+  pub text_tool_calls: bool,
 }
+impl Model {
+  pub fn from_name(name: &str) -> Option<Model> {
+    [&*GPT4_O, &*GPT4_TURBO, &*GPT4, &*GPT3_TURBO_16K, &*GPT3_TURBO, &*WIZARDLM]
+      .into_iter()
+      .find(|model| model.name == name)
+      .cloned()
+  }
+}
+
 impl AsRef<Model> for Model {
   fn as_ref(&self) -> &Model {
     self
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::types::Model;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TokenUsage {
+  pub prompt_tokens: u64,
+  pub completion_tokens: u64,
+  /// usd
+  pub cost: f64,
+}
+
+impl TokenUsage {
+  pub fn total_tokens(&self) -> u64 {
+    self.prompt_tokens + self.completion_tokens
+  }
+}
+
+/// token usage accumulated over the lifetime of a session, keyed by model name
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SessionUsage {
+  pub by_model: BTreeMap<String, TokenUsage>,
+}
+
+impl SessionUsage {
+  pub fn record(&mut self, model: &Model, prompt_tokens: u64, completion_tokens: u64) {
+    let usage = self.by_model.entry(model.name.clone()).or_default();
+    usage.prompt_tokens += prompt_tokens;
+    usage.completion_tokens += completion_tokens;
+    usage.cost += (prompt_tokens as f64 * model.prompt_cost_per_million
+      + completion_tokens as f64 * model.completion_cost_per_million)
+      / 1_000_000.0;
+  }
+
+  pub fn total(&self) -> TokenUsage {
+    self.by_model.values().fold(TokenUsage::default(), |mut total, usage| {
+      total.prompt_tokens += usage.prompt_tokens;
+      total.completion_tokens += usage.completion_tokens;
+      total.cost += usage.cost;
+      total
+    })
+  }
+
+  /// a short readout, e.g. `12.3k tokens $0.0421`
+  pub fn summary(&self) -> String {
+    let total = self.total();
+    format!("{} tokens ${:.4}", format_tokens(total.total_tokens()), total.cost)
+  }
+}
+
+/// a token count shortened to thousands or millions, e.g. `12.3k`
+pub fn format_tokens(tokens: u64) -> String {
+  match tokens {
+    t if t >= 1_000_000 => format!("{:.1}M", t as f64 / 1_000_000.0),
+    t if t >= 1_000 => format!("{:.1}k", t as f64 / 1_000.0),
+    t => t.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::consts::{GPT3_TURBO, GPT4_O};
+
+  #[test]
+  fn test_usage_accumulates_per_model() {
+    let mut usage = SessionUsage::default();
+    usage.record(&GPT4_O, 1_000, 500);
+    usage.record(&GPT4_O, 1_000, 500);
+    usage.record(&GPT3_TURBO, 2_000, 0);
+
+    assert_eq!(usage.by_model["gpt-4o"].prompt_tokens, 2_000);
+    assert_eq!(usage.by_model["gpt-4o"].completion_tokens, 1_000);
+    let total = usage.total();
+    assert_eq!(total.total_tokens(), 5_000);
+    let expected_cost = (2_000.0 * 5.0 + 1_000.0 * 15.0 + 2_000.0 * 0.5) / 1_000_000.0;
+    assert!((total.cost - expected_cost).abs() < 1e-9);
+    assert_eq!(usage.summary(), format!("5.0k tokens ${:.4}", expected_cost));
+  }
+}
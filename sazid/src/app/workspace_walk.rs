@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use helix_view::editor::FilePickerConfig;
+use ignore::{types::TypesBuilder, DirEntry, WalkBuilder};
+
+/// Function used for filtering dir entries in the various file pickers.
+pub fn filter_picker_entry(entry: &DirEntry, root: &Path, dedup_symlinks: bool) -> bool {
+  // We always want to ignore the .git directory, otherwise if
+  // `ignore` is turned off, we end up with a lot of noise
+  // in our picker.
+  if entry.file_name() == ".git" {
+    return false;
+  }
+
+  // We also ignore symlinks that point inside the current directory
+  // if `dedup_links` is enabled.
+  if dedup_symlinks && entry.path_is_symlink() {
+    return entry.path().canonicalize().ok().map_or(false, |path| !path.starts_with(root));
+  }
+
+  true
+}
+
+/// walks the files under `root` the file picker lists, leaving out ignored files and archives
+pub fn workspace_walk_builder(root: &Path, config: &FilePickerConfig) -> WalkBuilder {
+  let dedup_symlinks = config.deduplicate_links;
+  let absolute_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+
+  let mut walk_builder = WalkBuilder::new(root);
+  walk_builder
+    .hidden(config.hidden)
+    .parents(config.parents)
+    .ignore(config.ignore)
+    .follow_links(config.follow_symlinks)
+    .git_ignore(config.git_ignore)
+    .git_global(config.git_global)
+    .git_exclude(config.git_exclude)
+    .sort_by_file_name(|name1, name2| name1.cmp(name2))
+    .max_depth(config.max_depth)
+    .filter_entry(move |entry| filter_picker_entry(entry, &absolute_root, dedup_symlinks));
+
+  walk_builder.add_custom_ignore_filename(helix_loader::config_dir().join("ignore"));
+  walk_builder.add_custom_ignore_filename(".helix/ignore");
+
+  // We want to exclude files that the editor can't handle yet
+  let mut type_builder = TypesBuilder::new();
+  type_builder
+    .add("compressed", "*.{zip,gz,bz2,zst,lzo,sz,tgz,tbz2,lz,lz4,lzma,lzo,z,Z,xz,7z,rar,cab}")
+    .expect("Invalid type definition");
+  type_builder.negate("all");
+  let excluded_types = type_builder.build().expect("failed to build excluded_types");
+  walk_builder.types(excluded_types);
+  walk_builder
+}
@@ -1,7 +1,38 @@
-use clap::Parser;
+use async_openai::types::{
+  ChatCompletionMessageToolCall, ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+  ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
+  ChatCompletionRequestUserMessageContent, Role,
+};
+use clap::{Parser, Subcommand};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+use crate::app::consts::CHUNK_TOKEN_LIMIT;
+use crate::app::errors::SazidError;
+use crate::app::export::{export_session, session_to_markdown, ExportFormat};
+use crate::app::helpers::get_assistant_message_from_create_chat_completion_stream_response;
+use crate::app::model_tools::shell_tool::ShellTool;
+use crate::app::model_tools::tool_call::{ToolCallParams, ToolCallTrait};
+use crate::app::model_tools::tool_registry::ToolRegistry;
+use crate::app::pinned_files::{pinned_files_message, PinnedFile};
+use crate::app::prompt_profiles::DEFAULT_PROMPT_PROFILE;
+use crate::app::provider_fixtures::{ChatProvider, ProviderFixtures};
+use crate::app::rate_limit;
+use crate::app::session_config::SessionConfig;
+use crate::app::session_store::SessionStore;
+use crate::app::tools::chunkifier::parse_input;
+use crate::components::session::{construct_request, create_openai_client};
 use crate::utils::version;
 
+/// the first arguments that run a command without the terminal interface
+pub const HEADLESS_COMMANDS: &[&str] = &["ask", "sessions", "serve", "acp", "doctor"];
+
+// a one shot prompt gives up when the model keeps calling tools
+const MAX_TOOL_ROUNDS: usize = 8;
+
 #[derive(Parser, Debug, Clone)]
 #[command(author, version = version(), about)]
 pub struct Cli {
@@ -75,3 +106,467 @@ pub struct Cli {
   )]
   pub local_api: bool,
 }
+
+/// commands that run without the terminal interface, for scripts and ci
+#[derive(Parser, Debug, Clone)]
+#[command(author, version = version(), about)]
+pub struct HeadlessCli {
+  #[command(subcommand)]
+  pub command: HeadlessCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HeadlessCommand {
+  #[command(about = "send one prompt and print the answer as it streams in")]
+  Ask(AskArgs),
+  #[command(subcommand, about = "list, print, export or delete saved sessions")]
+  Sessions(SessionsCommand),
+  #[command(about = "answer json-rpc requests from editor plugins on a unix socket")]
+  Serve(ServeArgs),
+  #[command(
+    about = "run as an agent client protocol agent on stdin and stdout, for editors like zed"
+  )]
+  Acp,
+  #[command(
+    about = "check the config, api keys, language servers, databases and terminal, and suggest fixes"
+  )]
+  Doctor,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ServeArgs {
+  #[arg(
+    short = 's',
+    long,
+    value_name = "PATH",
+    help = "the socket to listen on, sazid.sock in the data directory if not set"
+  )]
+  pub socket: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SessionsCommand {
+  #[command(about = "list the saved sessions, most recently updated first")]
+  List,
+  #[command(about = "print the transcript of a session as markdown")]
+  Show {
+    #[arg(value_name = "ID", help = "the id of the session, as listed")]
+    id: String,
+  },
+  #[command(about = "write the transcript of a session to a file")]
+  Export {
+    #[arg(value_name = "ID", help = "the id of the session, as listed")]
+    id: String,
+    #[arg(long, value_name = "FORMAT", default_value = "markdown", help = "markdown or html")]
+    format: String,
+    #[arg(
+      short = 'o',
+      long,
+      value_name = "PATH",
+      help = "the file to write, <id>.md or <id>.html if not set"
+    )]
+    output: Option<PathBuf>,
+  },
+  #[command(about = "delete sessions and their messages")]
+  Delete {
+    #[arg(value_name = "ID", required = true, help = "the ids of the sessions, as listed")]
+    ids: Vec<String>,
+  },
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct AskArgs {
+  #[arg(value_name = "PROMPT", help = "the prompt to send")]
+  pub prompt: String,
+
+  #[arg(
+    short = 'f',
+    long = "file",
+    value_name = "PATH",
+    help = "send the contents of a file with the prompt, can be given more than once"
+  )]
+  pub files: Vec<PathBuf>,
+
+  #[arg(
+    short = 't',
+    long = "tool",
+    value_name = "NAME",
+    help = "let the model call a tool, can be given more than once. tools that need a language server are not available"
+  )]
+  pub tools: Vec<String>,
+
+  #[arg(
+    short = 'm',
+    long,
+    value_name = "NAME",
+    help = "the model to use instead of the configured one"
+  )]
+  pub model: Option<String>,
+
+  #[arg(
+    short = 'p',
+    long,
+    value_name = "NAME",
+    help = "the prompt profile to use as the system prompt"
+  )]
+  pub profile: Option<String>,
+
+  #[arg(long, help = "print the answer and the tool calls made as one json object when done")]
+  pub json: bool,
+}
+
+/// what `ask --json` prints
+#[derive(Serialize, Debug, Default)]
+pub struct AskOutput {
+  pub model: String,
+  pub answer: String,
+  pub tool_calls: Vec<AskToolCall>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AskToolCall {
+  pub name: String,
+  pub arguments: String,
+  pub result: String,
+}
+
+/// sends the prompt of `args` and writes the answer to `out` as it streams in. when the model
+/// calls one of the tools named in `args` the answer continues after the tool's result. `piped`
+/// is text read from stdin, which is sent before the prompt in chunks
+pub async fn ask(
+  args: AskArgs,
+  mut config: SessionConfig,
+  piped: Option<String>,
+  out: &mut impl Write,
+) -> Result<(), SazidError> {
+  if let Some(model) = &args.model {
+    config.set_option("model", model)?;
+  }
+  let profile = args
+    .profile
+    .clone()
+    .or(config.prompt_profile.clone())
+    .unwrap_or(DEFAULT_PROMPT_PROFILE.to_string());
+  let system_prompt = config
+    .all_prompt_profiles()
+    .remove(&profile)
+    .ok_or_else(|| SazidError::Other(format!("no prompt profile named {}", profile)))?;
+  let tools = ask_tools(&args.tools, &config)?;
+  let chat_tools = if tools.is_empty() {
+    None
+  } else {
+    Some(
+      tools
+        .tools()
+        .map(|tool| tool.to_chat_completion_tool())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(SazidError::FunctionCallError)?,
+    )
+  };
+
+  let files = args
+    .files
+    .iter()
+    .map(|path| {
+      PinnedFile::read(path)
+        .map_err(|e| SazidError::Other(format!("could not read {}: {}", path.display(), e)))
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  let mut messages =
+    vec![ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+      content: system_prompt,
+      role: Role::System,
+      name: None,
+    })];
+  messages.extend(pinned_files_message(&files));
+  if let Some(piped) = piped.filter(|piped| !piped.trim().is_empty()) {
+    messages.extend(piped_messages(&piped, config.model.token_limit as usize)?);
+  }
+  messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+    content: ChatCompletionRequestUserMessageContent::Text(args.prompt.clone()),
+    role: Role::User,
+    name: None,
+  }));
+
+  let mut output = AskOutput { model: config.model.name.clone(), ..AskOutput::default() };
+  let provider = ChatProvider::new(
+    create_openai_client(&config.openai.client_config()),
+    ProviderFixtures::open(&config.provider)?,
+  );
+  for _ in 0..MAX_TOOL_ROUNDS {
+    let request = construct_request(
+      config.model.name.clone(),
+      messages.clone(),
+      Some(true),
+      Some(config.response_max_tokens as u16),
+      config.temperature,
+      Some(config.user.clone()),
+      chat_tools.clone(),
+    );
+    let (mut responses, mut attempt) = (vec![], 0);
+    loop {
+      let failed = match provider.create_stream(request.clone()).await {
+        Ok(mut stream) => loop {
+          let response = match stream.next().await {
+            Some(Ok(response)) => response,
+            Some(Err(e)) => break Some(e),
+            None => break None,
+          };
+          for choice in response.choices.iter().filter(|choice| choice.index == 0) {
+            if let Some(content) = &choice.delta.content {
+              output.answer.push_str(content);
+              if !args.json {
+                write!(out, "{}", content)?;
+                out.flush()?;
+              }
+            }
+          }
+          responses.push(response);
+        },
+        Err(e) => Some(e),
+      };
+      let Some(e) = failed else {
+        break;
+      };
+      // a response that has begun is not sent again, its text was written already
+      let retry_after = provider.retry_after();
+      let status = |status: String| log::info!("{}", status);
+      if !responses.is_empty()
+        || !rate_limit::wait_to_retry(&config.retry, attempt, &e, retry_after, status).await
+      {
+        return Err(e.into());
+      }
+      attempt += 1;
+    }
+    if responses.is_empty() {
+      return Err(SazidError::Other("the response was empty".to_string()));
+    }
+
+    let message = get_assistant_message_from_create_chat_completion_stream_response(0, &responses)?;
+    let tool_calls = message.tool_calls.clone().unwrap_or_default();
+    messages.push(ChatCompletionRequestMessage::Assistant(message));
+    if tool_calls.is_empty() {
+      if args.json {
+        serde_json::to_writer(&mut *out, &output)?;
+      }
+      writeln!(out)?;
+      return Ok(());
+    }
+    for tool_call in tool_calls {
+      let result = call_tool(&tools, &tool_call, &config).await;
+      output.tool_calls.push(AskToolCall {
+        name: tool_call.function.name.clone(),
+        arguments: tool_call.function.arguments.clone(),
+        result: result.clone(),
+      });
+      messages.push(ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+        role: Role::Tool,
+        content: result,
+        tool_call_id: tool_call.id,
+      }));
+    }
+  }
+  Err(SazidError::Other(format!("no answer after {} rounds of tool calls", MAX_TOOL_ROUNDS)))
+}
+
+/// runs one of the `sessions` commands on `store`, writing what it prints to `out`
+pub fn sessions(
+  command: SessionsCommand,
+  store: &mut SessionStore,
+  out: &mut impl Write,
+) -> Result<(), SazidError> {
+  match command {
+    SessionsCommand::List => {
+      for session in store.list_sessions()? {
+        let updated_at = chrono::DateTime::from_timestamp(session.updated_at, 0)
+          .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+          .unwrap_or_default();
+        let tags = session.tags.iter().map(|tag| format!(" #{}", tag)).collect::<String>();
+        writeln!(
+          out,
+          "{}\t{}\t{} messages\t{}{}",
+          session.id, updated_at, session.message_count, session.title, tags
+        )?;
+      }
+    },
+    SessionsCommand::Show { id } => {
+      let session = store.load_session(&id, None)?;
+      write!(out, "{}", session_to_markdown(&session.config.title, &session.messages))?;
+    },
+    SessionsCommand::Export { id, format, output } => {
+      let format = format.parse::<ExportFormat>()?;
+      let session = store.load_session(&id, None)?;
+      let path = output.unwrap_or_else(|| {
+        PathBuf::from(match format {
+          ExportFormat::Markdown => format!("{}.md", id),
+          ExportFormat::Html => format!("{}.html", id),
+        })
+      });
+      export_session(&session.config.title, &session.messages, format, &path)?;
+      writeln!(out, "exported {} to {}", id, path.display())?;
+    },
+    SessionsCommand::Delete { ids } => {
+      // nothing is deleted when one of the ids is wrong
+      let saved = store.list_sessions()?;
+      if let Some(id) = ids.iter().find(|id| !saved.iter().any(|session| &session.id == *id)) {
+        return Err(SazidError::Other(format!("session {} not found", id)));
+      }
+      for id in ids {
+        store.delete_session(&id)?;
+        writeln!(out, "deleted {}", id)?;
+      }
+    },
+  }
+  Ok(())
+}
+
+// piped text as user messages of at most `CHUNK_TOKEN_LIMIT` tokens each
+fn piped_messages(
+  piped: &str,
+  model_max_tokens: usize,
+) -> Result<Vec<ChatCompletionRequestMessage>, SazidError> {
+  let chunks = parse_input(piped, CHUNK_TOKEN_LIMIT as usize, model_max_tokens)
+    .map_err(SazidError::ChunkifierError)?;
+  let count = chunks.len();
+  Ok(
+    chunks
+      .into_iter()
+      .enumerate()
+      .map(|(i, chunk)| {
+        let content = match count {
+          1 => format!("piped input:\n{}", chunk),
+          _ => format!("piped input, part {} of {}:\n{}", i + 1, count, chunk),
+        };
+        ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+          content: ChatCompletionRequestUserMessageContent::Text(content),
+          role: Role::User,
+          name: Some("stdin".to_string()),
+        })
+      })
+      .collect(),
+  )
+}
+
+// the built in and configured tools of the given names
+pub(crate) fn ask_tools(
+  names: &[String],
+  config: &SessionConfig,
+) -> Result<ToolRegistry, SazidError> {
+  let mut all_tools = ToolRegistry::builtin().map_err(SazidError::FunctionCallError)?;
+  for tool_config in &config.shell_tools {
+    all_tools
+      .register(Arc::new(ShellTool::new(tool_config.clone())))
+      .map_err(SazidError::FunctionCallError)?;
+  }
+  let mut tools = ToolRegistry::default();
+  for name in names {
+    let tool = all_tools.get(name).cloned().ok_or_else(|| {
+      SazidError::Other(format!(
+        "unknown tool {}, the tools are: {}",
+        name,
+        all_tools.names().join(", ")
+      ))
+    })?;
+    tools.register(tool).map_err(SazidError::FunctionCallError)?;
+  }
+  Ok(tools)
+}
+
+// the result of a tool call, or the error the model is told about
+pub(crate) async fn call_tool(
+  tools: &ToolRegistry,
+  tool_call: &ChatCompletionMessageToolCall,
+  config: &SessionConfig,
+) -> String {
+  let name = &tool_call.function.name;
+  let arguments = &tool_call.function.arguments;
+  let (tool, function_args) = match tools.validate_call(name, arguments, config) {
+    Ok(call) => call,
+    Err(rejection) => return rejection.to_string(),
+  };
+  // nothing reads the actions of tools that answer through the language server interface
+  let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+  let params = ToolCallParams {
+    function_args,
+    tool_result: None,
+    tool_call_id: tool_call.id.clone(),
+    session_id: 0,
+    session_config: config.clone(),
+    tx,
+  };
+  match tool.call(params).await {
+    Ok(Some(output)) => output,
+    Ok(None) => {
+      format!("Tool Call Error: {} needs a language server, which ask does not start", name)
+    },
+    Err(e) => format!("Tool Call Error: {}", e),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ask_args() {
+    let cli = HeadlessCli::parse_from([
+      "szd",
+      "ask",
+      "where is the session saved",
+      "--file",
+      "src/session.rs",
+      "--tool",
+      "treesitter_query",
+      "--json",
+    ]);
+    let HeadlessCommand::Ask(args) = cli.command else {
+      panic!("expected the ask command");
+    };
+    assert_eq!(args.files, vec![PathBuf::from("src/session.rs")]);
+    assert!(args.json);
+    let config = SessionConfig::default();
+    assert_eq!(ask_tools(&args.tools, &config).unwrap().names(), vec!["treesitter_query"]);
+    assert!(ask_tools(&["pcre2grep".to_string()], &config).is_err());
+  }
+
+  #[test]
+  fn test_sessions() {
+    let mut store = SessionStore::open_in_memory().unwrap();
+    let mut session = crate::components::session::Session::default();
+    session.config.id = "1700000000".to_string();
+    session.config.title = "fix the parser".to_string();
+    store.save_session(&session).unwrap();
+
+    let mut out = vec![];
+    sessions(SessionsCommand::List, &mut store, &mut out).unwrap();
+    let listed = String::from_utf8(out).unwrap();
+    assert!(listed.starts_with("1700000000\t"));
+    assert!(listed.ends_with("\t0 messages\tfix the parser\n"));
+
+    let mut out = vec![];
+    sessions(SessionsCommand::Show { id: "1700000000".to_string() }, &mut store, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "# fix the parser\n\n");
+
+    let delete =
+      |ids: &[&str]| SessionsCommand::Delete { ids: ids.iter().map(|id| id.to_string()).collect() };
+    assert!(sessions(delete(&["1700000000", "42"]), &mut store, &mut std::io::sink()).is_err());
+    assert_eq!(store.list_sessions().unwrap().len(), 1);
+    sessions(delete(&["1700000000"]), &mut store, &mut std::io::sink()).unwrap();
+    assert!(store.list_sessions().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_piped_messages() {
+    let messages = piped_messages("thread 'main' panicked at src/main.rs:3:5", 8192).unwrap();
+    assert_eq!(messages.len(), 1);
+    let ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+      content: ChatCompletionRequestUserMessageContent::Text(content),
+      ..
+    }) = &messages[0]
+    else {
+      panic!("expected a user message");
+    };
+    assert_eq!(content, "piped input:\nthread 'main' panicked at src/main.rs:3:5");
+    assert!(piped_messages(&"error ".repeat(10000), 100).is_err());
+  }
+}
@@ -1,50 +1,162 @@
 use async_openai::types::{
-  ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
-  ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
+  ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+  ChatCompletionRequestMessage, ChatCompletionRequestToolMessage, ChatCompletionRequestUserMessage,
   ChatCompletionRequestUserMessageContent, ChatCompletionTool, CreateChatCompletionRequest,
-  CreateEmbeddingRequestArgs, CreateEmbeddingResponse, Role,
+  CreateEmbeddingRequestArgs, CreateEmbeddingResponse, FinishReason, Role,
 };
 use futures::StreamExt;
 use futures_util::future::{ready, Ready};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::default::Default;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::result::Result;
+use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
+use tracing::Instrument;
 
-use async_openai::{config::OpenAIConfig, Client};
+use async_openai::{
+  config::{Config, OpenAIConfig},
+  Client,
+};
 use dotenv::dotenv;
 
 use crate::action::{ChatToolAction, LsiAction, SessionAction, ToolType};
+use crate::app::agent_task::AgentTask;
+use crate::app::audit_log::{self, audit_log_path, StartedToolCall};
+use crate::app::commit_message::{commit_message_request_messages, parse_commit_message_response};
+use crate::app::context_manager::{summary_request_messages, ContextManager};
+use crate::app::continuation::{stitch, Continuation, CONTINUE_PROMPT};
 use crate::app::database::data_manager::{
   get_all_embeddings_by_session, search_message_embeddings_by_session,
 };
+use crate::app::database::data_models::EmbeddingModel;
 use crate::app::database::types::QueryableSession;
+use crate::app::database::vector_store::{open_vector_store, VectorStore};
+use crate::app::file_patch::FileEdit;
+use crate::app::import::ImportedConversation;
+use crate::app::input_history::InputHistory;
 use crate::app::lsi::query::LsiQuery;
-use crate::app::messages::{ChatMessage, MessageContainer, MessageState, ReceiveBuffer};
+use crate::app::mentions::{mentions_context, resolve_mentions, SymbolIndex};
+use crate::app::messages::{
+  get_chat_message_text, ChatMessage, MessageContainer, MessageState, ReceiveBuffer,
+};
+use crate::app::openai_config::OpenAIClientConfig;
+use crate::app::pinned_files::{pinned_files_message, PinnedFile};
+use crate::app::plan::{Plan, PlanState};
+use crate::app::provider_fixtures::{ChatProvider, ProviderFixtures, ProviderMode};
+use crate::app::rate_limit;
 use crate::app::request_validation::debug_request_validation;
-use crate::app::session_config::SessionConfig;
+use crate::app::retrieval::retrieval_context_message;
+use crate::app::semantic_search::{self, INDEX_CONCURRENCY};
+use crate::app::session_config::{parse_workspace_target, SessionConfig, WorkspaceParams};
+use crate::app::session_store::SessionStore;
+use crate::app::session_title::{
+  parse_title_response, title_request_messages, MAX_TITLE_ATTEMPTS, TITLE_AFTER_EXCHANGES,
+};
+use crate::app::snippets::snippet_message;
+use crate::app::structured_output::{ResponseSchema, MAX_SCHEMA_REASKS};
+use crate::app::tool_call_shim::{parse_tool_calls, shim_request};
+use crate::app::tool_output::{paged_output, read_more, read_more_tool, READ_MORE_TOOL};
+use crate::app::usage::SessionUsage;
 use crate::app::{consts::*, errors::*, tools::chunkifier::*, types::*};
 use crate::trace_dbg;
 use backoff::exponential::ExponentialBackoffBuilder;
 
 use crate::app::tools::utils::ensure_directory_exists;
 
+// ends the text of a reply that was interrupted before it finished
+const INTERRUPTED_MARKER: &str = "[interrupted by the user]";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Session {
   pub id: i64,
   pub messages: Vec<MessageContainer>,
   pub config: SessionConfig,
   pub enabled_tools: Vec<ChatCompletionTool>,
+  #[serde(default)]
+  pub usage: SessionUsage,
+  #[serde(default)]
+  pub context_summary: Option<String>,
+  #[serde(default)]
+  pub summarized_message_ids: Vec<i64>,
+  #[serde(skip)]
+  pub summary_in_progress: bool,
+  #[serde(skip)]
+  pub title_in_progress: bool,
+  /// the title requests sent since the session was loaded, see `MAX_TITLE_ATTEMPTS`
+  #[serde(skip)]
+  pub title_attempts: usize,
+  #[serde(skip)]
+  pub indexing_in_progress: bool,
+  /// the tokens of the context window sent with the latest request
+  #[serde(skip)]
+  pub context_tokens: usize,
+  /// set once the workspace was indexed, files saved or edited after are indexed again
+  #[serde(skip)]
+  pub workspace_indexed: bool,
+  /// leading messages of a partially loaded session that are only in the session store
+  #[serde(skip)]
+  pub unloaded_message_count: usize,
   #[serde(skip)]
   pub tool_calls_in_progress: Vec<String>,
   #[serde(skip)]
-  pub openai_config: OpenAIConfig,
+  pub applied_edits: Vec<ChatCompletionMessageToolCall>,
+  /// contents of the files in config.pinned_files, read again when they change on disk
+  #[serde(skip)]
+  pub pinned_files: Vec<PinnedFile>,
+  /// messages removed by delete_message with their former positions, most recent last
+  #[serde(skip)]
+  pub deleted_messages: Vec<Vec<(usize, MessageContainer)>>,
+  /// changes tools made to files this session, most recent last, so they can be reverted. kept
+  /// in the session store, so they can still be reverted once the session is reloaded
+  #[serde(skip)]
+  pub file_edits: Vec<FileEdit>,
+  #[serde(skip)]
+  pub openai_config: OpenAIClientConfig,
   #[serde(skip)]
   pub action_tx: Option<UnboundedSender<SessionAction>>,
   #[serde(skip)]
   pub test_tool_call_response: Option<(LsiQuery, String)>,
+  /// complete outputs of the tool calls that were truncated, by tool call id, paged through
+  /// with the read_more tool
+  #[serde(skip)]
+  pub tool_outputs: HashMap<String, String>,
+  /// inputs sent to this and other sessions, recalled in the input box
+  #[serde(skip)]
+  pub input_history: InputHistory,
+  /// large pastes waiting to be sent before the next message, see `app::snippets`
+  #[serde(skip)]
+  pub snippets: Vec<String>,
+  /// the symbols mentions are resolved with, shared with the language server interface
+  #[serde(skip)]
+  pub symbol_index: SymbolIndex,
+  /// the task sending the latest request, aborted to interrupt its response
+  #[serde(skip)]
+  pub request_task: Option<tokio::task::AbortHandle>,
+  /// ids of the streams and tool calls that were interrupted, anything still arriving for them
+  /// is dropped
+  #[serde(skip)]
+  pub interrupted: Vec<String>,
+  /// inputs submitted while a response was being received, sent in order as each turn completes
+  #[serde(skip)]
+  pub queued_inputs: Vec<String>,
+  /// the goal the session works towards on its own, see `app::agent_task`
+  #[serde(skip)]
+  pub agent_task: Option<AgentTask>,
+  /// the plan being drafted, reviewed or carried out, see `app::plan`
+  #[serde(skip)]
+  pub plan: Option<Plan>,
+  /// the reply cut off at the token limit that is being continued, see `app::continuation`
+  #[serde(skip)]
+  pub continuation: Option<Continuation>,
+  /// the schema replies are held to, see `app::structured_output`
+  #[serde(skip)]
+  pub response_schema: Option<ResponseSchema>,
+  /// the tool calls running, by tool call id, until they are added to the audit log
+  #[serde(skip)]
+  pub started_tool_calls: HashMap<String, StartedToolCall>,
 }
 
 impl Default for Session {
@@ -54,10 +166,36 @@ impl Default for Session {
       messages: vec![],
       config: SessionConfig::default(),
       tool_calls_in_progress: Vec::new(),
-      openai_config: OpenAIConfig::default(),
+      applied_edits: Vec::new(),
+      pinned_files: Vec::new(),
+      deleted_messages: Vec::new(),
+      file_edits: Vec::new(),
+      openai_config: OpenAIClientConfig::default(),
       enabled_tools: vec![],
+      usage: SessionUsage::default(),
+      context_summary: None,
+      summarized_message_ids: vec![],
+      summary_in_progress: false,
+      title_in_progress: false,
+      title_attempts: 0,
+      indexing_in_progress: false,
+      context_tokens: 0,
+      workspace_indexed: false,
+      unloaded_message_count: 0,
       action_tx: None,
       test_tool_call_response: None,
+      tool_outputs: HashMap::new(),
+      input_history: InputHistory::default(),
+      snippets: Vec::new(),
+      symbol_index: SymbolIndex::default(),
+      request_task: None,
+      interrupted: Vec::new(),
+      queued_inputs: Vec::new(),
+      agent_task: None,
+      plan: None,
+      continuation: None,
+      response_schema: None,
+      started_tool_calls: HashMap::new(),
     }
   }
 }
@@ -65,29 +203,37 @@ impl Default for Session {
 impl From<QueryableSession> for Session {
   fn from(value: QueryableSession) -> Self {
     dotenv().ok();
-    let api_key = std::env::var("OPENAI_API_KEY").unwrap();
-    let openai_config =
-      OpenAIConfig::new().with_api_key(api_key).with_org_id("org-WagBLu0vLgiuEL12dylmcPFj");
-    // let openai_config = OpenAIConfig::new().with_api_base("http://localhost:1234/v1".to_string());
+    let openai_config = value.config.0.openai.client_config();
     Session { id: value.id, openai_config, config: value.config.0, ..Default::default() }
   }
 }
 
 impl Session {
-  pub fn save_session(&self, path: PathBuf) -> Result<(), SazidError> {
-    let session_json = serde_json::to_string(&self)?;
-    fs::write(path, session_json)?;
-    Ok(())
+  pub fn save_session(&self, store: &mut SessionStore) -> Result<(), SazidError> {
+    store.save_session(self)
   }
 
-  pub fn load_session(&mut self, path: &PathBuf) -> Result<(), SazidError> {
+  /// replaces this session with one from the store, keeping the id used to route actions
+  pub fn load_session(
+    &mut self,
+    store: &SessionStore,
+    session_id: &str,
+    message_limit: Option<usize>,
+  ) -> Result<(), SazidError> {
     let tx = self.action_tx.clone().unwrap();
-    let session_json = fs::read_to_string(path)?;
-    let session: Session = serde_json::from_str(&session_json)?;
-    *self = session;
+    let session = store.load_session(session_id, message_limit)?;
+    // the api key from the config file is not saved with sessions
+    let api_key = self.config.openai.api_key.take();
+    let input_history = std::mem::take(&mut self.input_history);
+    let audit_dir = self.config.audit_dir.take();
+    *self = Session { id: self.id, input_history, ..session };
+    // sessions saved before the audit log was kept have no audit dir
+    self.config.audit_dir = self.config.audit_dir.take().or(audit_dir);
+    self.config.openai.api_key = api_key;
+    self.openai_config = self.config.openai.client_config();
     self.action_tx = Some(tx.clone());
     tx.send(SessionAction::ReloadMessages(
-      self.messages.iter().map(|m| (m.timestamp, m.message.clone())).collect(),
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
     ))
     .unwrap();
     Ok(())
@@ -109,6 +255,307 @@ impl Session {
     self.messages.iter().any(|m| m.is_receiving())
   }
 
+  /// true while a request is being sent, its response received or the tools it called run
+  pub fn is_busy(&self) -> bool {
+    self.request_task.as_ref().is_some_and(|task| !task.is_finished())
+      || self.is_receiving()
+      || !self.tool_calls_in_progress.is_empty()
+  }
+
+  /// the directory of the session's workspace, or the current directory without one
+  pub fn workspace_root(&self) -> PathBuf {
+    match &self.config.workspace {
+      Some(workspace) => workspace.workspace_path.clone(),
+      None => std::env::current_dir().unwrap_or_default(),
+    }
+  }
+
+  /// true once the last reply is received in full and calls no more tools
+  pub fn turn_complete(&self) -> bool {
+    let Some(last) = self.messages.last() else {
+      return false;
+    };
+    let finished_answer = match &last.message {
+      ChatCompletionRequestMessage::Assistant(message) => {
+        message.tool_calls.as_ref().map_or(true, |tool_calls| tool_calls.is_empty())
+      },
+      _ => false,
+    };
+    finished_answer
+      && last.receive_is_complete()
+      && self.tool_calls_in_progress.is_empty()
+      && !self.is_receiving()
+  }
+
+  /// sends `input`, or queues it while a response is being received or other inputs are
+  /// waiting. returns true if it was queued
+  pub fn submit_or_queue(&mut self, input: String) -> bool {
+    if self.is_busy() {
+      self.queued_inputs.push(input);
+      return true;
+    }
+    if self.queued_inputs.is_empty() {
+      self.submit_chat_completion_request(input);
+      return false;
+    }
+    self.queued_inputs.push(input);
+    let next = self.queued_inputs.remove(0);
+    self.submit_chat_completion_request(next);
+    true
+  }
+
+  // sends the first queued input once the turn before it is complete
+  fn send_queued_input(&mut self) {
+    if !self.queued_inputs.is_empty() && self.turn_complete() {
+      let next = self.queued_inputs.remove(0);
+      self.submit_chat_completion_request(next);
+    }
+  }
+
+  // asks for the rest of a reply cut off at the token limit, and stitches each part received
+  // into the message cut off, dropping the continue prompt. returns true while a part is asked
+  // for, so the turn is not taken as complete
+  fn continue_truncated_reply(&mut self) -> bool {
+    let Some(last) = self.messages.last().filter(|_| self.turn_complete()) else {
+      return false;
+    };
+    let truncated = last.finish_reason() == Some(FinishReason::Length);
+    let last_id = last.message_id;
+    let max_continuations = self.config.auto_continue.max_continuations;
+    let Some(continuation) = self.continuation.take() else {
+      if truncated && self.config.auto_continue.enabled && max_continuations > 0 {
+        self.request_continuation(Continuation { message_id: last_id, count: 0 });
+        return true;
+      }
+      return false;
+    };
+    let continued =
+      |m: &MessageContainer| m.message_id == continuation.message_id && m.message_id != last_id;
+    let Some(idx) = self.messages.iter().position(continued) else {
+      return false;
+    };
+    let next = match self.messages.pop().map(|m| m.message) {
+      Some(ChatCompletionRequestMessage::Assistant(reply)) => reply.content.unwrap_or_default(),
+      _ => String::new(),
+    };
+    self.messages.truncate(idx + 1);
+    if let ChatCompletionRequestMessage::Assistant(assistant) = &mut self.messages[idx].message {
+      let previous = assistant.content.take().unwrap_or_default();
+      assistant.content = Some(stitch(&previous, &next));
+    }
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ReloadMessages(
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
+    ))
+    .unwrap();
+    if truncated && continuation.count < max_continuations {
+      self.request_continuation(continuation);
+      return true;
+    }
+    if truncated {
+      log::warn!("reply still cut off after {} continuations", continuation.count);
+    }
+    false
+  }
+
+  fn request_continuation(&mut self, mut continuation: Continuation) {
+    let tx = self.action_tx.clone().unwrap();
+    let (user, model) = (self.config.user.clone(), self.config.model.clone());
+    continuation.count += 1;
+    let added =
+      self.add_chunked_chat_completion_request_messages(CONTINUE_PROMPT, &user, Role::User, &model);
+    match added {
+      Ok(_) => {
+        self.continuation = Some(continuation);
+        tx.send(SessionAction::UpdateStatus(Some("continuing the reply".to_string()))).unwrap();
+        tx.send(SessionAction::RequestChatCompletion()).unwrap();
+      },
+      Err(e) => tx.send(SessionAction::Error(format!("Error: {:?}", e))).unwrap(),
+    }
+  }
+
+  /// holds the replies to come to the json schema in `path`
+  pub fn attach_schema(&mut self, path: &Path) -> Result<(), SazidError> {
+    self.response_schema = Some(ResponseSchema::load(path)?);
+    Ok(())
+  }
+
+  // validates a complete reply against the attached schema and asks again, with what is wrong
+  // with it, until it matches or the re-asks run out. returns true while a reply is asked for
+  fn reask_invalid_reply(&mut self) -> bool {
+    let Some(last) = self.messages.last().filter(|_| self.turn_complete()) else {
+      return false;
+    };
+    let Some(schema) = &mut self.response_schema else {
+      return false;
+    };
+    let errors = match schema.validate(&get_chat_message_text(&last.message)) {
+      Ok(_) => {
+        schema.reasks = 0;
+        return false;
+      },
+      Err(errors) => errors,
+    };
+    if schema.reasks < MAX_SCHEMA_REASKS {
+      schema.reasks += 1;
+      self.submit_chat_completion_request(ResponseSchema::reask_prompt(&errors));
+      return true;
+    }
+    schema.reasks = 0;
+    let error = format!(
+      "the reply does not match {} after {} attempts: {}",
+      schema.path.display(),
+      MAX_SCHEMA_REASKS + 1,
+      errors.join("; ")
+    );
+    self.action_tx.clone().unwrap().send(SessionAction::Error(error)).unwrap();
+    false
+  }
+
+  /// hands the session a goal to work towards on its own, starting with its first step
+  pub fn start_agent_task(&mut self, goal: &str) {
+    let task = AgentTask::new(goal, self.config.agent_max_steps);
+    let prompt = task.prompt();
+    self.agent_task = Some(task);
+    self.submit_chat_completion_request(prompt);
+  }
+
+  // keeps an agent task going: notes the tools each reply calls, and evaluates each reply that
+  // calls none, asking for the next step until the task is over
+  fn advance_agent_task(&mut self) {
+    let Some(last) = self.messages.last() else {
+      return;
+    };
+    let tools = match &last.message {
+      ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+        tool_calls: Some(tool_calls),
+        ..
+      }) if last.receive_is_complete() => {
+        tool_calls.iter().map(|tc| tc.function.name.clone()).collect::<Vec<_>>()
+      },
+      _ => vec![],
+    };
+    let reply = self.turn_complete().then(|| get_chat_message_text(&last.message));
+    let Some(task) = self.agent_task.as_mut().filter(|task| task.is_running()) else {
+      return;
+    };
+    if !tools.is_empty() {
+      task.last_action = Some(format!("calling {}", tools.join(", ")));
+    }
+    if let Some(next) = reply.and_then(|reply| task.evaluate(&reply)) {
+      self.submit_chat_completion_request(next);
+    }
+  }
+
+  /// asks the model to draft a plan for `goal`, which is reviewed before any of it is carried out
+  pub fn start_plan(&mut self, goal: &str) {
+    let plan = Plan::new(goal);
+    let prompt = plan.planning_prompt();
+    self.plan = Some(plan);
+    self.submit_chat_completion_request(prompt);
+  }
+
+  /// carries out the checked steps of the reviewed plan, one reply at a time
+  pub fn execute_plan(&mut self) -> Result<(), SazidError> {
+    if self.is_busy() {
+      return Err(SazidError::Other("cannot run the plan while a response is in progress".into()));
+    }
+    let message_count = self.messages.len();
+    let Some(plan) = self.plan.as_mut().filter(|plan| plan.state == PlanState::Review) else {
+      return Err(SazidError::Other("there is no plan to carry out".into()));
+    };
+    plan.approve();
+    match plan.next_step(message_count) {
+      Some(prompt) => self.submit_chat_completion_request(prompt),
+      None => return Err(SazidError::Other("no step of the plan is checked".into())),
+    }
+    Ok(())
+  }
+
+  // takes the draft of a plan from the reply that completes a turn, or finishes the running step
+  // with the messages of its reply and asks for the next one
+  fn advance_plan(&mut self) {
+    if !self.turn_complete() {
+      return;
+    }
+    let Some(plan) = &self.plan else {
+      return;
+    };
+    let transcript = plan
+      .running_from()
+      .map(|from| self.messages.iter().skip(from).map(|m| m.message_id).collect::<Vec<_>>());
+    let reply = self.messages.last().map(|m| get_chat_message_text(&m.message)).unwrap_or_default();
+    let message_count = self.messages.len();
+    let Some(plan) = self.plan.as_mut() else {
+      return;
+    };
+    match plan.state {
+      PlanState::Drafting => {
+        if !plan.draft_from_reply(&reply) {
+          self.plan = None;
+          let tx = self.action_tx.clone().unwrap();
+          tx.send(SessionAction::Error("the reply did not hold a plan".to_string())).unwrap();
+        }
+      },
+      PlanState::Executing => {
+        plan.finish_step(transcript.unwrap_or_default());
+        if let Some(prompt) = plan.next_step(message_count) {
+          self.submit_chat_completion_request(prompt);
+        }
+      },
+      PlanState::Review | PlanState::Done => {},
+    }
+  }
+
+  /// stops the response being received and the tool calls it made. the partial reply is kept,
+  /// marked as interrupted and without its tool calls, and the tool calls already running are
+  /// answered as interrupted so the next request is valid. returns false if nothing was running
+  pub fn interrupt(&mut self) -> bool {
+    if !self.is_busy() {
+      return false;
+    }
+    if let Some(task) = self.request_task.take() {
+      task.abort();
+    }
+    if let Some(task) = &mut self.agent_task {
+      task.stop("stopped by the user");
+    }
+    if let Some(plan) = &mut self.plan {
+      plan.pause();
+    }
+    self.continuation = None;
+    let mut interrupted_ids = vec![];
+    for message in self.messages.iter_mut().filter(|m| m.is_receiving()) {
+      self.interrupted.extend(message.stream_id.clone());
+      message.set_receive_complete();
+      // the tool calls of an unfinished reply may be cut off, so none of them are run
+      message.tools_called = true;
+      if let ChatCompletionRequestMessage::Assistant(assistant) = &mut message.message {
+        assistant.tool_calls = None;
+        let content = assistant.content.get_or_insert_with(String::new);
+        if !content.is_empty() {
+          content.push_str("\n\n");
+        }
+        content.push_str(INTERRUPTED_MARKER);
+      }
+      interrupted_ids.push(message.message_id);
+    }
+    interrupted_ids.into_iter().for_each(|id| self.update_ui_message(id));
+    for tool_call_id in std::mem::take(&mut self.tool_calls_in_progress) {
+      self.add_message(ChatMessage::Tool(ChatCompletionRequestToolMessage {
+        role: Role::Tool,
+        content: format!("the tool call was {}", INTERRUPTED_MARKER),
+        tool_call_id: tool_call_id.clone(),
+      }));
+      self.interrupted.push(tool_call_id);
+    }
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ChatToolAction(ChatToolAction::AbortToolCalls(self.id))).unwrap();
+    tx.send(SessionAction::UpdateStatus(None)).unwrap();
+    tx.send(SessionAction::SaveSession).unwrap();
+    true
+  }
+
   pub fn message_id_with_unrendered_content(&self) -> Ready<Option<i64>> {
     match self.messages.iter().find(|m| m.has_unrendered_content()) {
       Some(message) => ready(Some(message.message_id)),
@@ -120,11 +567,13 @@ impl Session {
 impl Session {
   pub fn new(tx: UnboundedSender<SessionAction>, config: Option<SessionConfig>) -> Self {
     let config = config.unwrap_or_default();
-    let session = Session { action_tx: Some(tx.clone()), config, ..Default::default() };
+    let openai_config = config.openai.client_config();
+    let session =
+      Session { action_tx: Some(tx.clone()), config, openai_config, ..Default::default() };
     log::info!("Session created: {:?}", session.id);
 
-    if let Some(workspace_params) = session.config.workspace.clone() {
-      tx.send(SessionAction::LsiAction(LsiAction::AddWorkspace(workspace_params))).unwrap();
+    for workspace_params in session.config.all_workspaces() {
+      tx.send(SessionAction::LsiAction(LsiAction::AddWorkspace(workspace_params.clone()))).unwrap();
     }
 
     tx.send(SessionAction::ChatToolAction(ChatToolAction::ToolListRequest(session.id))).unwrap();
@@ -154,6 +603,29 @@ impl Session {
     }
   }
 
+  /// changes the model, temperature or max tokens of this session, saved with it
+  pub fn set_option(&mut self, key: &str, value: &str) -> Result<(), SazidError> {
+    self.config.set_option(key, value)?;
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::SaveSession).unwrap();
+    Ok(())
+  }
+
+  /// makes the named prompt profile the system message, which the next request starts with
+  pub fn select_prompt_profile(&mut self, name: &str) -> Result<(), SazidError> {
+    let mut profiles = self.config.all_prompt_profiles();
+    let Some(prompt) = profiles.remove(name) else {
+      return Err(SazidError::Other(format!(
+        "no prompt profile named {}, the profiles are: {}",
+        name,
+        profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+      )));
+    };
+    self.config.prompt_profile = Some(name.to_string());
+    self.set_system_prompt(&prompt);
+    Ok(())
+  }
+
   pub fn update(&mut self, action: SessionAction) -> Result<Option<SessionAction>, SazidError> {
     let tx = self.action_tx.clone().unwrap();
     match action {
@@ -168,8 +640,18 @@ impl Session {
           return Ok(Some(SessionAction::AddMessage(id, chat_message)));
         }
         self.add_message(chat_message.clone());
+        self.take_text_tool_calls();
+        self.record_response_usage(&chat_message);
         self.execute_tool_calls();
         self.generate_new_message_embeddings();
+        self.request_changelog_fragment();
+        self.request_title();
+        if self.continue_truncated_reply() || self.reask_invalid_reply() {
+          return Ok(None);
+        }
+        self.advance_agent_task();
+        self.advance_plan();
+        self.send_queued_input();
         if let ChatMessage::Tool(_) = chat_message {
           if self.tool_calls_in_progress.is_empty() {
             log::error!("requesting tool chat completion");
@@ -185,6 +667,18 @@ impl Session {
           Ok(None)
         }
       },
+      SessionAction::ConfigReloaded(config) => {
+        self.config.apply_reloaded(&config);
+        self.reload_credentials();
+        // the tool list is requested again so a changed allowlist applies to the next request
+        tx.send(SessionAction::ChatToolAction(ChatToolAction::UpdateConfig(
+          self.id,
+          Box::new(self.config.clone()),
+        )))
+        .unwrap();
+        tx.send(SessionAction::ChatToolAction(ChatToolAction::ToolListRequest(self.id))).unwrap();
+        Ok(None)
+      },
       SessionAction::UpdateToolList(session_id, tool_list) => {
         if session_id == self.id {
           self.enabled_tools = tool_list
@@ -215,44 +709,47 @@ impl Session {
           )));
         }
 
-        let tool_response = ChatMessage::Tool(ChatCompletionRequestToolMessage {
-          role: Role::Tool,
-          content,
-          tool_call_id: lsi_query.tool_call_id.clone(),
-        });
-
-        self.add_message(tool_response);
-        self.generate_new_message_embeddings();
-
-        match self
-          .tool_calls_in_progress
-          .iter()
-          .enumerate()
-          .find(|(_idx, id)| *id == &lsi_query.tool_call_id)
-        {
-          Some((idx, _)) => {
-            self.tool_calls_in_progress.remove(idx);
-            log::error!("removing tool from in progress: {:?}", self.tool_calls_in_progress);
-          },
-          None => {
-            log::error!(
-              "tool call not found in in progress list {} {:?}",
-              &lsi_query.tool_call_id,
-              self.tool_calls_in_progress
-            );
-          },
+        Ok(self.complete_tool_call(lsi_query.tool_call_id, content))
+      },
+      SessionAction::ToolCallComplete(ToolType::Generic(session_id, tool_call_id), content) => {
+        if session_id != self.id {
+          log::warn!("session id did not match, returning ToolCallComplete action to queue");
+          return Ok(Some(SessionAction::ToolCallComplete(
+            ToolType::Generic(session_id, tool_call_id),
+            content,
+          )));
+        }
+        log::info!(
+          "Tool Call Complete\nsession_id: {}, tool_call_id: {}",
+          session_id,
+          tool_call_id
+        );
+        Ok(self.complete_tool_call(tool_call_id, content))
+      },
+      SessionAction::ToolCallTimedOut(tool_type, content) => {
+        let (session_id, tool_call_id) = match &tool_type {
+          ToolType::LsiQuery(lsi_query) => (lsi_query.session_id, lsi_query.tool_call_id.clone()),
+          ToolType::Generic(session_id, tool_call_id) => (*session_id, tool_call_id.clone()),
         };
-
-        if self.tool_calls_in_progress.is_empty() {
-          log::error!("requesting tool chat completion");
-          Ok(Some(SessionAction::RequestChatCompletion()))
-        } else {
-          log::error!(
-            "tool returned, {} tools still in progress",
-            self.tool_calls_in_progress.len()
-          );
-          Ok(None)
+        if session_id != self.id {
+          return Ok(Some(SessionAction::ToolCallTimedOut(tool_type, content)));
+        }
+        // the call was answered just before its deadline passed
+        if !self.tool_calls_in_progress.contains(&tool_call_id) {
+          return Ok(None);
+        }
+        log::warn!(
+          "tool call timed out, session_id: {}, tool_call_id: {}",
+          session_id,
+          tool_call_id
+        );
+        let action = self.complete_tool_call(tool_call_id.clone(), content);
+        // the answer arriving late is dropped like that of an interrupted call. a language server
+        // query is cancelled, and what it answers after is dropped before it gets here
+        if matches!(tool_type, ToolType::Generic(..)) {
+          self.interrupted.push(tool_call_id);
         }
+        Ok(action)
       },
 
       SessionAction::ToolCallError(tool_type, content) => match tool_type {
@@ -265,6 +762,35 @@ impl Session {
           tool_call_id, content
         )))),
       },
+      SessionAction::FileEdited(session_id, edit) => {
+        if session_id != self.id {
+          return Ok(Some(SessionAction::FileEdited(session_id, edit)));
+        }
+        self.reindex_file(&edit.path);
+        self.file_edits.push(edit);
+        Ok(None)
+      },
+      SessionAction::IndexComplete(session_id, _, _) => {
+        if session_id == self.id {
+          self.indexing_in_progress = false;
+          self.workspace_indexed = true;
+        }
+        Ok(None)
+      },
+      SessionAction::IndexWorkspace(session_id) => {
+        let root = self.config.workspace.as_ref().map(|w| w.workspace_path.clone());
+        match root {
+          Some(root)
+            if session_id == self.id && !self.workspace_indexed && !self.indexing_in_progress =>
+          {
+            if let Err(e) = self.index_workspace_files(semantic_search::indexable_files(&root)) {
+              log::warn!("error indexing workspace: {}", e);
+            }
+          },
+          _ => {},
+        }
+        Ok(None)
+      },
       SessionAction::SaveSession => {
         // self.save_session().unwrap();
         Ok(None)
@@ -278,6 +804,29 @@ impl Session {
         self.request_chat_completion(None, tx.clone());
         Ok(None)
       },
+      SessionAction::UpdateContextSummary(session_id, message_ids, summary) => {
+        if session_id == self.id {
+          if let Some(summary) = summary {
+            self.summarized_message_ids.extend(message_ids);
+            self.context_summary = Some(summary);
+          }
+          self.summary_in_progress = false;
+        }
+        Ok(None)
+      },
+      SessionAction::UpdateTitle(session_id, title) => {
+        if session_id == self.id {
+          if let Some(title) = title {
+            log::info!("session titled: {} {:?}", title.title, title.tags);
+            self.config.title = title.title;
+            self.config.tags = title.tags;
+            self.config.title_set = true;
+            tx.send(SessionAction::SaveSession).unwrap();
+          }
+          self.title_in_progress = false;
+        }
+        Ok(None)
+      },
       SessionAction::MessageEmbeddingSuccess(id) => {
         self.messages.iter_mut().find(|m| m.message_id == id).unwrap().embedding_saved = true;
         Ok(None)
@@ -287,6 +836,457 @@ impl Session {
     //self.action_tx.clone().unwrap().send(Action::Render).unwrap();
   }
 
+  /// drops every message after the last user message and requests a new completion,
+  /// optionally switching model and raising the temperature first
+  pub fn regenerate_last_response(
+    &mut self,
+    model: Option<Model>,
+    temperature_bump: Option<f32>,
+  ) -> Result<(), SazidError> {
+    let last_user_idx = self
+      .messages
+      .iter()
+      .rposition(|m| matches!(m.message, ChatCompletionRequestMessage::User(_)))
+      .ok_or_else(|| SazidError::Other("no user message to regenerate a response for".into()))?;
+    if last_user_idx == self.messages.len() - 1 {
+      return Err(SazidError::Other("no assistant response to regenerate".into()));
+    }
+
+    if let Some(model) = model {
+      self.config.model = model;
+    }
+    if let Some(bump) = temperature_bump {
+      // the api default temperature is 1.0, the maximum is 2.0
+      let temperature = self.config.temperature.unwrap_or(1.0) + bump;
+      self.config.temperature = Some(temperature.clamp(0.0, 2.0));
+    }
+    self.truncate_and_request_completion(last_user_idx + 1)
+  }
+
+  /// re-issues the request that produced a message. rerunning a user message keeps it and
+  /// drops everything after it, rerunning any other message drops it as well
+  pub fn rerun_from_message(&mut self, message_id: i64) -> Result<(), SazidError> {
+    let idx = self
+      .messages
+      .iter()
+      .position(|m| m.message_id == message_id)
+      .ok_or_else(|| SazidError::Other("message not found".into()))?;
+    match self.messages[idx].message {
+      ChatCompletionRequestMessage::User(_) => self.truncate_and_request_completion(idx + 1),
+      ChatCompletionRequestMessage::System(_) => {
+        Err(SazidError::Other("cannot rerun the system prompt".into()))
+      },
+      _ => self.truncate_and_request_completion(idx),
+    }
+  }
+
+  fn truncate_and_request_completion(&mut self, len: usize) -> Result<(), SazidError> {
+    if self.is_receiving() || !self.tool_calls_in_progress.is_empty() {
+      return Err(SazidError::Other("cannot regenerate while a response is in progress".into()));
+    }
+    self.messages.truncate(len);
+    self.applied_edits.clear();
+
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ReloadMessages(
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
+    ))
+    .unwrap();
+    tx.send(SessionAction::RequestChatCompletion()).unwrap();
+    Ok(())
+  }
+
+  /// continues the conversation as a new session, keeping messages up to and including
+  /// message_id. the caller is responsible for saving the original session first
+  pub fn fork_from_message(&mut self, message_id: i64) -> Result<(), SazidError> {
+    if self.is_receiving() || !self.tool_calls_in_progress.is_empty() {
+      return Err(SazidError::Other("cannot fork while a response is in progress".into()));
+    }
+    let idx = self
+      .messages
+      .iter()
+      .position(|m| m.message_id == message_id)
+      .ok_or_else(|| SazidError::Other("message not found".into()))?;
+    self.messages.truncate(idx + 1);
+    self.applied_edits.clear();
+    self.config.id = chrono::Utc::now().timestamp().to_string();
+    self.config.title = format!("{} (fork {})", self.config.title, chrono::Utc::now().to_rfc3339());
+
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ReloadMessages(
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
+    ))
+    .unwrap();
+    Ok(())
+  }
+
+  /// removes a message from the conversation so it is no longer sent with requests. an
+  /// assistant tool call and its tool responses are only valid together, deleting any of them
+  /// deletes the whole group. returns the number of messages removed
+  pub fn delete_message(&mut self, message_id: i64) -> Result<usize, SazidError> {
+    if self.is_receiving() || !self.tool_calls_in_progress.is_empty() {
+      return Err(SazidError::Other("cannot delete while a response is in progress".into()));
+    }
+    let idx = self
+      .messages
+      .iter()
+      .position(|m| m.message_id == message_id)
+      .ok_or_else(|| SazidError::Other("message not found".into()))?;
+    if matches!(self.messages[idx].message, ChatCompletionRequestMessage::System(_)) {
+      return Err(SazidError::Other("cannot delete the system prompt".into()));
+    }
+
+    let call_idx = match &self.messages[idx].message {
+      ChatCompletionRequestMessage::Tool(tool) => self.messages.iter().position(|m| {
+        matches!(&m.message, ChatCompletionRequestMessage::Assistant(assistant)
+          if assistant.tool_calls.iter().flatten().any(|call| call.id == tool.tool_call_id))
+      }),
+      _ => Some(idx),
+    };
+    let call_ids = match call_idx.map(|i| &self.messages[i].message) {
+      Some(ChatCompletionRequestMessage::Assistant(assistant)) => {
+        assistant.tool_calls.iter().flatten().map(|call| call.id.clone()).collect::<Vec<_>>()
+      },
+      _ => vec![],
+    };
+    let removed_indices = self
+      .messages
+      .iter()
+      .enumerate()
+      .filter(|(i, m)| {
+        *i == idx
+          || Some(*i) == call_idx
+          || matches!(&m.message, ChatCompletionRequestMessage::Tool(tool)
+            if call_ids.contains(&tool.tool_call_id))
+      })
+      .map(|(i, _)| i)
+      .collect::<Vec<_>>();
+
+    let mut removed = vec![];
+    for i in removed_indices.into_iter().rev() {
+      removed.insert(0, (i, self.messages.remove(i)));
+    }
+    let count = removed.len();
+    self.deleted_messages.push(removed);
+
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ReloadMessages(
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
+    ))
+    .unwrap();
+    Ok(count)
+  }
+
+  /// restores the messages removed by the most recent delete_message. returns the number of
+  /// messages restored
+  pub fn undo_delete_message(&mut self) -> Result<usize, SazidError> {
+    if self.is_receiving() || !self.tool_calls_in_progress.is_empty() {
+      return Err(SazidError::Other("cannot restore while a response is in progress".into()));
+    }
+    let removed =
+      self.deleted_messages.pop().ok_or_else(|| SazidError::Other("nothing to restore".into()))?;
+    let count = removed.len();
+    // positions are restored in ascending order so each one is valid when it is inserted.
+    // messages dropped since the deletion can leave a position past the end
+    for (i, message) in removed {
+      self.messages.insert(i.min(self.messages.len()), message);
+    }
+
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ReloadMessages(
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
+    ))
+    .unwrap();
+    Ok(count)
+  }
+
+  /// restores the files changed by a tool call and drops the changes from the journal. a call
+  /// can change several files, none is reverted unless all of them are unchanged since the call,
+  /// so changes are reverted most recent first
+  pub fn revert_file_edit(&mut self, tool_call_id: &str) -> Result<Vec<FileEdit>, SazidError> {
+    let indices = (0..self.file_edits.len())
+      .rev()
+      .filter(|&idx| self.file_edits[idx].tool_call_id == tool_call_id)
+      .collect::<Vec<_>>();
+    if indices.is_empty() {
+      return Err(SazidError::Other("no file edit found for the tool call".into()));
+    }
+    // every file is checked before any is written, a file the call changed twice is expected to
+    // hold the text of its later change until that one is reverted
+    let mut expected: HashMap<&Path, Option<String>> = HashMap::new();
+    for &idx in &indices {
+      let edit = &self.file_edits[idx];
+      let current =
+        expected.entry(edit.path.as_path()).or_insert_with(|| fs::read_to_string(&edit.path).ok());
+      if current.as_deref() != Some(edit.modified.as_str()) {
+        return Err(SazidError::Other(format!(
+          "{} changed since the edit, revert later changes first",
+          edit.path.display()
+        )));
+      }
+      *current = edit.original.clone();
+    }
+
+    let mut reverted = vec![];
+    for idx in indices {
+      let edit = self.file_edits.remove(idx);
+      if let Err(e) = edit.revert() {
+        let error = format!("error reverting {}: {}", edit.path.display(), e);
+        self.file_edits.insert(idx, edit);
+        return Err(SazidError::Other(error));
+      }
+      reverted.push(edit);
+    }
+
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::FileEditReverted(tool_call_id.to_string())).unwrap();
+    Ok(reverted)
+  }
+
+  /// reverts the most recent tool call that changed files
+  pub fn undo_file_edit(&mut self) -> Result<Vec<FileEdit>, SazidError> {
+    let tool_call_id = self
+      .file_edits
+      .last()
+      .map(|edit| edit.tool_call_id.clone())
+      .ok_or_else(|| SazidError::Other("no file edits to undo".into()))?;
+    self.revert_file_edit(&tool_call_id)
+  }
+
+  /// reverts every change tools made to files this session. returns the number reverted
+  pub fn rollback_file_edits(&mut self) -> Result<usize, SazidError> {
+    let mut count = 0;
+    while !self.file_edits.is_empty() {
+      count += self.undo_file_edit()?.len();
+    }
+    Ok(count)
+  }
+
+  /// embeds the chunks of workspace files in the background for semantic search and retrieval,
+  /// a few files at a time. files unchanged since they were last indexed are skipped
+  pub fn index_workspace_files(&mut self, files: Vec<PathBuf>) -> Result<(), SazidError> {
+    let store = self.vector_store()?;
+    if self.indexing_in_progress {
+      return Err(SazidError::Other("the workspace is already being indexed".into()));
+    }
+    self.indexing_in_progress = true;
+
+    let tx = self.action_tx.clone().unwrap();
+    let session_id = self.id;
+    let model = self.embedding_model()?;
+    tokio::spawn(async move {
+      let total = files.len();
+      let (mut done, mut indexed, mut failed) = (0, 0, 0);
+      let mut results = futures::stream::iter(files)
+        .map(|path| {
+          let (store, model) = (store.as_ref(), &model);
+          async move { (semantic_search::index_file(store, model, &path).await, path) }
+        })
+        .buffer_unordered(INDEX_CONCURRENCY);
+      while let Some((result, path)) = results.next().await {
+        done += 1;
+        match result {
+          Ok(true) => indexed += 1,
+          Ok(false) => {},
+          Err(e) => {
+            log::warn!("error indexing {}: {}", path.display(), e);
+            failed += 1;
+          },
+        }
+        tx.send(SessionAction::IndexProgress(session_id, done, total)).unwrap();
+      }
+      tx.send(SessionAction::IndexComplete(session_id, indexed, failed)).unwrap();
+    });
+    Ok(())
+  }
+
+  /// registers another workspace with the language server interface, which messages and
+  /// :workspace can then switch to
+  pub fn add_workspace(&mut self, workspace_params: WorkspaceParams) -> Result<(), SazidError> {
+    let name = workspace_params.name();
+    if self.config.workspace_by_name(&name).is_some() {
+      return Err(SazidError::Other(format!("a workspace named {} is already registered", name)));
+    }
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::LsiAction(LsiAction::AddWorkspace(workspace_params.clone()))).unwrap();
+    self.config.workspaces.push(workspace_params);
+    Ok(())
+  }
+
+  /// scopes tool calls, retrieval and indexing to the registered workspace named `name`
+  pub fn select_workspace(&mut self, name: &str) -> Result<(), SazidError> {
+    let workspace = match self.config.workspace_by_name(name) {
+      Some(workspace) => workspace.clone(),
+      None => {
+        let names = self.config.all_workspaces().iter().map(|w| w.name()).collect::<Vec<_>>();
+        return Err(SazidError::Other(format!(
+          "no workspace named {}, the workspaces are: {}",
+          name,
+          names.join(", ")
+        )));
+      },
+    };
+    if self.config.workspace.as_ref() == Some(&workspace) {
+      return Ok(());
+    }
+    // the active workspace stays registered so it can be switched back to
+    if let Some(previous) = self.config.workspace.replace(workspace) {
+      if !self.config.workspaces.contains(&previous) {
+        self.config.workspaces.push(previous);
+      }
+    }
+    self.workspace_indexed = false;
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ChatToolAction(ChatToolAction::UpdateConfig(
+      self.id,
+      Box::new(self.config.clone()),
+    )))
+    .unwrap();
+    Ok(())
+  }
+
+  /// indexes a changed file again if the workspace it belongs to was indexed
+  pub fn reindex_file(&self, path: &Path) {
+    let in_workspace = self
+      .config
+      .workspace
+      .as_ref()
+      .map_or(false, |workspace| path.starts_with(&workspace.workspace_path));
+    let indexed = self.workspace_indexed || self.config.retrieval.enabled;
+    if !in_workspace || !indexed {
+      return;
+    }
+    let store = match self.vector_store() {
+      Ok(store) => store,
+      Err(e) => return log::warn!("error indexing {}: {}", path.display(), e),
+    };
+    let path = path.to_path_buf();
+    let model = match self.embedding_model() {
+      Ok(model) => model,
+      Err(e) => return log::warn!("error indexing {}: {}", path.display(), e),
+    };
+    tokio::spawn(async move {
+      if let Err(e) = semantic_search::index_file(store.as_ref(), &model, &path).await {
+        log::warn!("error indexing {}: {}", path.display(), e);
+      }
+    });
+  }
+
+  fn vector_store(&self) -> Result<Arc<dyn VectorStore>, SazidError> {
+    open_vector_store(&self.config.vector_store, &self.config.database_url)
+  }
+
+  // the fixtures requests are recorded to or replayed from, none when they go to the provider
+  fn provider_fixtures(&self) -> Result<Option<Arc<ProviderFixtures>>, SazidError> {
+    ProviderFixtures::open(&self.config.provider)
+  }
+
+  fn embedding_model(&self) -> Result<EmbeddingModel, SazidError> {
+    let fixtures = self.configured_provider_fixtures()?;
+    Ok(EmbeddingModel::Ada002(self.openai_config.config.clone(), fixtures))
+  }
+
+  // the provider chat requests of this session are sent to, through `client`
+  fn chat_provider<C: Config>(&self, client: Client<C>) -> Result<ChatProvider<C>, SazidError> {
+    Ok(ChatProvider::new(client, self.configured_provider_fixtures()?))
+  }
+
+  // the fixtures of the session, refusing requests to a live provider without an api key
+  fn configured_provider_fixtures(&self) -> Result<Option<Arc<ProviderFixtures>>, SazidError> {
+    let fixtures = self.provider_fixtures()?;
+    if fixtures.is_none() && !self.openai_config.is_configured() {
+      return Err(SazidError::Other(
+        "no openai api key is configured, :login stores one in the os keyring".to_string(),
+      ));
+    }
+    Ok(fixtures)
+  }
+
+  /// whether requests can be sent, see `OpenAIClientConfig::is_configured`. sessions answered
+  /// from fixtures or a mock need no api key
+  pub fn provider_configured(&self) -> bool {
+    self.config.provider != ProviderMode::Live || self.openai_config.is_configured()
+  }
+
+  /// looks the api key up again, after one was stored
+  pub fn reload_credentials(&mut self) {
+    self.openai_config = self.config.openai.client_config();
+  }
+
+  /// attaches a file to the session, its contents are sent with every request
+  pub fn pin_file(&mut self, path: PathBuf) -> Result<(), SazidError> {
+    if self.config.pinned_files.contains(&path) {
+      return Err(SazidError::Other(format!("{} is already pinned", path.display())));
+    }
+    let file = PinnedFile::read(&path)
+      .map_err(|e| SazidError::Other(format!("error reading {}: {}", path.display(), e)))?;
+    self.config.pinned_files.push(path);
+    self.pinned_files.push(file);
+    Ok(())
+  }
+
+  pub fn unpin_file(&mut self, path: &Path) -> Result<(), SazidError> {
+    let idx = self
+      .config
+      .pinned_files
+      .iter()
+      .position(|pinned| pinned == path)
+      .ok_or_else(|| SazidError::Other(format!("{} is not pinned", path.display())))?;
+    self.config.pinned_files.remove(idx);
+    self.pinned_files.retain(|file| file.path != path);
+    Ok(())
+  }
+
+  /// brings the pinned file contents up to date before a request. files pinned in a loaded
+  /// session are read for the first time here, files that can no longer be read keep the
+  /// contents they last had
+  fn refresh_pinned_files(&mut self) {
+    self.pinned_files.retain(|file| self.config.pinned_files.contains(&file.path));
+    for path in self.config.pinned_files.iter() {
+      let refreshed = match self.pinned_files.iter_mut().find(|file| &file.path == path) {
+        Some(file) => file.refresh().map(|_| ()),
+        None => PinnedFile::read(path).map(|file| self.pinned_files.push(file)),
+      };
+      if let Err(e) = refreshed {
+        log::warn!("error reading pinned file {}: {}", path.display(), e);
+      }
+    }
+  }
+
+  /// replaces the conversation with an imported one, keeping the session system prompt unless
+  /// the import brings its own. the session gets a new id so it is saved separately
+  pub fn import_conversation(
+    &mut self,
+    conversation: ImportedConversation,
+  ) -> Result<(), SazidError> {
+    if self.is_receiving() || !self.tool_calls_in_progress.is_empty() {
+      return Err(SazidError::Other("cannot import while a response is in progress".into()));
+    }
+    let has_system_prompt =
+      conversation.messages.iter().any(|m| matches!(m, ChatCompletionRequestMessage::System(_)));
+    if has_system_prompt {
+      self.messages.clear();
+    } else {
+      self.messages.retain(|m| matches!(m.message, ChatCompletionRequestMessage::System(_)));
+    }
+    self
+      .messages
+      .extend(conversation.messages.into_iter().map(MessageContainer::new_from_completed_message));
+    self.applied_edits.clear();
+    self.context_summary = None;
+    self.summarized_message_ids.clear();
+    self.config.id = chrono::Utc::now().timestamp().to_string();
+    self.config.title = conversation.title;
+    self.config.title_set = true;
+
+    let tx = self.action_tx.clone().unwrap();
+    tx.send(SessionAction::ReloadMessages(
+      self.messages.iter().map(|m| (m.message_id, m.message.clone())).collect(),
+    ))
+    .unwrap();
+    Ok(())
+  }
+
   pub fn update_ui_message(&self, message_id: i64) {
     let tx = self.action_tx.clone().unwrap();
     let message = self.messages.iter().find(|m| m.message_id == message_id).unwrap();
@@ -304,7 +1304,7 @@ impl Session {
         self.update_ui_message(id);
       },
       ChatMessage::StreamResponse(new_srvec) => {
-        new_srvec.iter().for_each(|sr| {
+        new_srvec.iter().filter(|sr| !self.interrupted.contains(&sr.id)).for_each(|sr| {
           if let Some(message) = self.messages.iter_mut().find(|m| {
             // trace_dbg!("message: {:#?}", m);
             m.stream_id == Some(sr.id.clone())
@@ -340,6 +1340,40 @@ impl Session {
     };
   }
 
+  /// adds the tokens of a received response to the session usage. complete responses report
+  /// usage, streamed responses are counted once they finish receiving
+  pub fn record_response_usage(&mut self, chat_message: &ChatMessage) {
+    match chat_message {
+      ChatMessage::Response(response) => {
+        if let Some(usage) = &response.usage {
+          self.usage.record(
+            &self.config.model,
+            usage.prompt_tokens as u64,
+            usage.completion_tokens as u64,
+          );
+        }
+      },
+      ChatMessage::StreamResponse(responses) => {
+        let mut context_manager = None;
+        self
+          .messages
+          .iter_mut()
+          .filter(|m| {
+            responses.iter().any(|sr| m.stream_id.as_ref() == Some(&sr.id))
+              && m.receive_is_complete()
+              && m.token_usage == 0
+          })
+          .for_each(|m| {
+            let context_manager = context_manager
+              .get_or_insert_with(|| ContextManager::new(&self.config.model.name, 0));
+            m.token_usage = context_manager.count_message_tokens(&m.message);
+            self.usage.record(&self.config.model, 0, m.token_usage as u64);
+          });
+      },
+      _ => {},
+    }
+  }
+
   pub fn generate_new_message_embeddings(&mut self) {
     let tx = self.action_tx.clone().unwrap();
     self
@@ -356,8 +1390,87 @@ impl Session {
       })
   }
 
+  // adds the output of a tool call as its tool message and requests the next completion once
+  // no tool call is in progress. an output over function_result_max_tokens is kept and only its
+  // first page is sent
+  fn complete_tool_call(&mut self, tool_call_id: String, output: String) -> Option<SessionAction> {
+    // a read_more answer is a page already, paging it again would page through the page
+    let is_page = self
+      .started_tool_calls
+      .get(&tool_call_id)
+      .is_some_and(|started| started.tool_call.function.name == READ_MORE_TOOL);
+    self.audit_tool_call(&tool_call_id, &output);
+    if let Some(idx) = self.interrupted.iter().position(|id| id == &tool_call_id) {
+      // the call was answered as interrupted already
+      self.interrupted.remove(idx);
+      return None;
+    }
+    let max_tokens = self.config.function_result_max_tokens;
+    let content = match is_page {
+      true => output,
+      false => {
+        let content = paged_output(&tool_call_id, &output, 0, max_tokens);
+        if content != output {
+          self.tool_outputs.insert(tool_call_id.clone(), output);
+        }
+        content
+      },
+    };
+    let tool_response = ChatMessage::Tool(ChatCompletionRequestToolMessage {
+      role: Role::Tool,
+      content,
+      tool_call_id: tool_call_id.clone(),
+    });
+
+    self.add_message(tool_response);
+    self.generate_new_message_embeddings();
+
+    match self.tool_calls_in_progress.iter().enumerate().find(|(_idx, id)| *id == &tool_call_id) {
+      Some((idx, _)) => {
+        self.tool_calls_in_progress.remove(idx);
+        log::error!("removing tool from in progress: {:?}", self.tool_calls_in_progress);
+      },
+      None => {
+        log::error!(
+          "tool call not found in in progress list {} {:?}",
+          &tool_call_id,
+          self.tool_calls_in_progress
+        );
+      },
+    };
+
+    if self.tool_calls_in_progress.is_empty() {
+      log::error!("requesting tool chat completion");
+      Some(SessionAction::RequestChatCompletion())
+    } else {
+      log::error!("tool returned, {} tools still in progress", self.tool_calls_in_progress.len());
+      None
+    }
+  }
+
+  // adds a finished tool call to the audit log of the session, with the files it edited
+  fn audit_tool_call(&mut self, tool_call_id: &str, output: &str) {
+    let Some(started) = self.started_tool_calls.remove(tool_call_id) else {
+      return;
+    };
+    let Some(dir) = &self.config.audit_dir else {
+      return;
+    };
+    let files = self
+      .file_edits
+      .iter()
+      .filter(|edit| edit.tool_call_id == tool_call_id)
+      .map(|edit| edit.path.clone())
+      .collect();
+    let path = audit_log_path(dir, &self.config.id);
+    if let Err(e) = audit_log::append(&path, &started.finish(output, files)) {
+      log::warn!("unable to write the audit log {}: {}", path.display(), e);
+    }
+  }
+
   pub fn execute_tool_calls(&mut self) {
     let tx = self.action_tx.clone().unwrap();
+    let workspace_path = self.config.workspace.as_ref().map(|w| w.workspace_path.clone());
     self
       .messages
       .iter_mut()
@@ -373,8 +1486,29 @@ impl Session {
         }) = &m.message
         {
           tool_calls.iter().for_each(|tc| {
+            if self.config.changelog.enabled
+              && self.config.changelog.is_tracked_edit(tc, workspace_path.as_deref())
+            {
+              self.applied_edits.push(tc.clone());
+            }
             self.tool_calls_in_progress.push(tc.id.clone());
+            let started = StartedToolCall::new(m.message_id, tc.clone());
+            self.started_tool_calls.insert(tc.id.clone(), started);
             log::warn!("adding tool to in progress: {:?}", self.tool_calls_in_progress);
+            if tc.function.name == READ_MORE_TOOL {
+              // answered from the kept outputs rather than by the chat tools
+              let page = read_more(
+                &self.tool_outputs,
+                &tc.function.arguments,
+                self.config.function_result_max_tokens,
+              );
+              tx.send(SessionAction::ToolCallComplete(
+                ToolType::Generic(self.id, tc.id.clone()),
+                page,
+              ))
+              .unwrap();
+              return;
+            }
             tx.send(SessionAction::ChatToolAction(ChatToolAction::CallTool(tc.clone(), self.id)))
               .unwrap();
           });
@@ -383,6 +1517,62 @@ impl Session {
       })
   }
 
+  // a model without native tool calls calls tools from fenced blocks in its reply, which are
+  // taken out of the reply once it is received and run like any other tool call
+  fn take_text_tool_calls(&mut self) {
+    if !self.config.model.text_tool_calls {
+      return;
+    }
+    let Some(last) = self.messages.last_mut() else {
+      return;
+    };
+    if !last.receive_is_complete() || last.tools_called {
+      return;
+    }
+    let id = last.message_id;
+    let ChatCompletionRequestMessage::Assistant(assistant) = &mut last.message else {
+      return;
+    };
+    if assistant.tool_calls.is_some() {
+      return;
+    }
+    let content = assistant.content.clone().unwrap_or_default();
+    let (rest, tool_calls) = parse_tool_calls(&content, &format!("call_{}", id));
+    if tool_calls.is_empty() {
+      return;
+    }
+    assistant.content = Some(rest);
+    assistant.tool_calls = Some(tool_calls);
+    self.update_ui_message(id);
+  }
+
+  /// once the assistant finishes a turn that applied edits, ask it to write a changelog fragment
+  pub fn request_changelog_fragment(&mut self) {
+    if self.applied_edits.is_empty() || !self.tool_calls_in_progress.is_empty() {
+      return;
+    }
+    let turn_complete = match self.messages.last() {
+      Some(m) => {
+        m.receive_is_complete()
+          && matches!(
+            &m.message,
+            ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+              tool_calls,
+              ..
+            }) if tool_calls.as_ref().map_or(true, |tc| tc.is_empty())
+          )
+      },
+      None => false,
+    };
+    if turn_complete {
+      let edits = std::mem::take(&mut self.applied_edits);
+      let workspace_path = self.config.workspace.as_ref().map(|w| w.workspace_path.clone());
+      let prompt = self.config.changelog.fragment_prompt(&edits, workspace_path.as_deref());
+      log::info!("requesting changelog fragment for {} edits", edits.len());
+      self.submit_chat_completion_request(prompt);
+    }
+  }
+
   pub fn add_chunked_chat_completion_request_messages(
     &mut self,
     content: &str,
@@ -414,20 +1604,45 @@ impl Session {
 
   pub fn submit_chat_completion_request(&mut self, input: String) {
     let tx = self.action_tx.clone().unwrap();
+    let input = match parse_workspace_target(&input) {
+      Some((name, rest)) => match self.select_workspace(name) {
+        Ok(()) => rest.to_string(),
+        Err(e) => {
+          tx.send(SessionAction::Error(e.to_string())).unwrap();
+          return;
+        },
+      },
+      None => input,
+    };
     let config = self.config.clone();
+    // the calls and streams interrupted before were aborted, nothing more arrives for them
+    self.interrupted.clear();
     self
       .messages
       .iter_mut()
       .filter(|m| m.current_transaction_flag)
       .for_each(|m| m.current_transaction_flag = false);
     tx.send(SessionAction::UpdateStatus(Some("submitting input".to_string()))).unwrap();
-    match self.add_chunked_chat_completion_request_messages(
-      Self::filter_non_ascii(&input).as_str(),
-      config.user.as_str(),
-      Role::User,
-      &config.model,
-    ) {
-      Ok(_) => {
+    let workspace_root = self.workspace_root();
+    // attached snippets go first, then the files and symbols the input mentions, each in a
+    // message of its own before the input
+    let snippets = self.snippets.iter().map(|snippet| snippet_message(snippet)).collect::<Vec<_>>();
+    let mentioned = resolve_mentions(&input, &workspace_root, &self.symbol_index.load());
+    let context = mentions_context(&mentioned, &workspace_root);
+    let mut contents = snippets.iter().chain(context.iter()).chain(std::iter::once(&input));
+    let added = contents.try_for_each(|content| {
+      self
+        .add_chunked_chat_completion_request_messages(
+          Self::filter_non_ascii(content).as_str(),
+          config.user.as_str(),
+          Role::User,
+          &config.model,
+        )
+        .map(|_| ())
+    });
+    match added {
+      Ok(()) => {
+        self.snippets.clear();
         tx.send(SessionAction::RequestChatCompletion()).unwrap();
       },
       Err(e) => {
@@ -443,28 +1658,78 @@ impl Session {
   ) {
     tx.send(SessionAction::UpdateStatus(Some("Configuring Client".to_string()))).unwrap();
     let stream_response = self.config.stream_response;
-    let openai_config = self.openai_config.clone();
     let db_url = self.config.database_url.clone();
     let model = self.config.model.clone();
     let user = self.config.user.clone();
     let session_id = self.id;
     let max_tokens = self.config.response_max_tokens;
+    let temperature = self.config.temperature;
     let rag = self.config.retrieval_augmentation_message_count;
     let embedding_model = None;
     let stream = Some(self.config.stream_response);
-    let tools = self.enabled_tools.clone();
+    let mut tools = self.enabled_tools.clone();
+    if !self.tool_outputs.is_empty() {
+      tools.push(read_more_tool());
+    }
+    let retrieval = self.config.retrieval.clone();
+    let hybrid_search = self.config.hybrid_search.clone();
+    let retry = self.config.retry.clone();
+    let response_schema = self.response_schema.clone();
+    let text_tool_calls = self.config.model.text_tool_calls;
+    let retrieval_query = self.retrieval_query().and_then(|query| {
+      let retrieval =
+        self.vector_store().and_then(|store| self.embedding_model().map(|model| (store, model)));
+      match retrieval {
+        Ok((store, embedding_model)) => Some((query, store, embedding_model)),
+        Err(e) => {
+          log::warn!("no workspace context retrieved: {}", e);
+          None
+        },
+      }
+    });
+    let workspace_root = self.config.workspace.as_ref().map(|w| w.workspace_path.clone());
+    let client =
+      Client::with_config(self.openai_config.clone()).with_backoff(rate_limit::without_retries());
+    let provider = match self.chat_provider(client) {
+      Ok(provider) => provider,
+      Err(e) => {
+        tx.send(SessionAction::Error(format!("Error: {}", e))).unwrap();
+        return;
+      },
+    };
 
-    let messages = self
-      .messages
-      .iter_mut()
-      // .filter(|m| m.current_transaction_flag)
-      .map(|m| {
-        // m.current_transaction_flag = false;
-        m.message.clone()
-      })
-      .collect::<Vec<ChatCompletionRequestMessage>>();
+    // the budget for retrieved code is kept free in the context window
+    let retrieval_budget = if retrieval_query.is_some() { retrieval.token_budget } else { 0 };
+    let context_manager = ContextManager::new(
+      &model.name,
+      (model.token_limit as usize)
+        .saturating_sub(self.config.response_max_tokens)
+        .saturating_sub(retrieval_budget),
+    );
+    self.refresh_pinned_files();
+    let context_window = context_manager.build_context_window(
+      &self.messages,
+      self.context_summary.as_deref(),
+      pinned_files_message(&self.pinned_files),
+    );
+    log::info!("request token count: {}", context_window.token_count);
+    self.context_tokens = context_window.token_count;
+    if stream_response {
+      // streamed responses do not report usage, so the prompt is counted locally
+      self.usage.record(&model, context_window.token_count as u64, 0);
+    }
+    self.summarize_evicted_messages(&context_window.evicted_message_ids);
+    let messages = context_window.messages;
     tx.send(SessionAction::UpdateStatus(Some("Assembling request...".to_string()))).unwrap();
-    tokio::spawn(async move {
+    // covers the request from assembling it until the reply is received
+    let span = tracing::info_span!(
+      "chat_request",
+      session_id,
+      model = %model.name,
+      streamed = stream_response,
+      prompt_tokens = context_window.token_count,
+    );
+    let send_request = async move {
       let mut embeddings_and_messages: Vec<ChatCompletionRequestMessage> = Vec::new();
 
       if let Some(embedding_model) = embedding_model {
@@ -484,80 +1749,296 @@ impl Session {
       }
 
       embeddings_and_messages.extend(messages);
+
+      if let (Some((query, store, embedding_model)), Some(workspace_root)) =
+        (retrieval_query, workspace_root)
+      {
+        tx.send(SessionAction::UpdateStatus(Some("Retrieving workspace context...".to_string())))
+          .unwrap();
+        let count = retrieval.max_chunks;
+        let results = semantic_search::search(
+          store.as_ref(),
+          &embedding_model,
+          &query,
+          count,
+          Some(workspace_root.as_path()),
+          &hybrid_search,
+        );
+        match results.instrument(tracing::info_span!("retrieval")).await {
+          Ok(results) => {
+            if let Some(message) =
+              retrieval_context_message(&results, &workspace_root, retrieval.token_budget)
+            {
+              // placed just before the user message it was retrieved for
+              embeddings_and_messages.insert(embeddings_and_messages.len() - 1, message);
+            }
+          },
+          Err(e) => log::warn!("error retrieving workspace context: {}", e),
+        }
+      }
+      if let Some(schema) = &response_schema {
+        embeddings_and_messages.push(schema.instructions());
+      }
       log::info!("embeddings_and_messages: {:#?}", embeddings_and_messages);
-      let request = construct_request(
+      let mut request = construct_request(
         model.name.clone(),
         embeddings_and_messages,
         stream,
         Some(max_tokens as u16),
+        temperature,
         Some(user),
         Some(tools),
       );
+      request.response_format = response_schema.map(|schema| schema.response_format());
+      if text_tool_calls {
+        shim_request(&mut request);
+      }
       let request_clone = request.clone();
       tx.send(SessionAction::UpdateStatus(Some("Establishing Client Connection".to_string())))
         .unwrap();
-      let client = create_openai_client(&openai_config);
       trace_dbg!("client connection established");
+      let status_tx = tx.clone();
+      let status = move |status: String| {
+        let _ = status_tx.send(SessionAction::UpdateStatus(Some(status)));
+      };
       // tx.send(Action::AddMessage(ChatMessage::SazidSystemMessage(format!("Request Token Count: {}", token_count))))
       //   .unwrap();
-      match stream_response {
-        true => {
-          tx.send(SessionAction::UpdateStatus(Some(
-            "Sending Request to OpenAI API...".to_string(),
-          )))
-          .unwrap();
-          trace_dbg!("Sending Request to API");
-          let mut stream = client.chat().create_stream(request).await.unwrap();
-          tx.send(SessionAction::UpdateStatus(Some(
-            "Request submitted. Awaiting Response...".to_string(),
-          )))
-          .unwrap();
-          while let Some(response_result) = stream.next().await {
-            match response_result {
-              Ok(response) => {
-                // log::debug!("Response: {:#?}", response);
-                //tx.send(Action::UpdateStatus(Some(format!("Received responses: {}", count).to_string()))).unwrap();
-                tx.send(SessionAction::AddMessage(
-                  session_id,
-                  ChatMessage::StreamResponse(vec![response]),
-                ))
-                .unwrap();
-              },
+      let mut attempt = 0;
+      'request: loop {
+        match stream_response {
+          true => {
+            tx.send(SessionAction::UpdateStatus(Some(
+              "Sending Request to OpenAI API...".to_string(),
+            )))
+            .unwrap();
+            trace_dbg!("Sending Request to API");
+            let mut stream = match provider.create_stream(request.clone()).await {
+              Ok(stream) => stream,
               Err(e) => {
-                log::error!("Error: {:#?} -- check https://status.openai.com", e);
-                debug_request_validation(&request_clone);
-                // let reqtext = format!("Request: \n{:#?}", request_clone.clone());
-                // trace_dbg!(reqtext);
-                // log::debug!("{:#?}", &request_clone);
-                // let pretty_json = serde_json::to_string_pretty(&request_clone).unwrap().to_string();
-                // log::debug!("{}", pretty_json);
-                // tx.send(Action::AddMessage(ChatMessage::SazidSystemMessage(reqtext))).unwrap();
+                let retry_after = provider.retry_after();
+                if rate_limit::wait_to_retry(&retry, attempt, &e, retry_after, status.clone()).await
+                {
+                  attempt += 1;
+                  continue 'request;
+                }
                 tx.send(SessionAction::Error(format!(
                   "Error: {:?} -- check https://status.openai.com/",
                   e
                 )))
                 .unwrap();
+                return;
               },
-            }
-          }
-        },
-        false => match client.chat().create(request).await {
-          Ok(response) => {
-            tx.send(SessionAction::AddMessage(session_id, ChatMessage::Response(response)))
-              .unwrap();
-          },
-          Err(e) => {
-            trace_dbg!("Error: {}", e);
-            tx.send(SessionAction::Error(format!(
-              "Error: {:#?} -- check https://status.openai.com/",
-              e
+            };
+            tx.send(SessionAction::UpdateStatus(Some(
+              "Request submitted. Awaiting Response...".to_string(),
             )))
             .unwrap();
+            let mut received = false;
+            while let Some(response_result) = stream.next().await {
+              match response_result {
+                Ok(response) => {
+                  if !received {
+                    tracing::info!("first chunk received");
+                  }
+                  received = true;
+                  // log::debug!("Response: {:#?}", response);
+                  //tx.send(Action::UpdateStatus(Some(format!("Received responses: {}", count).to_string()))).unwrap();
+                  tx.send(SessionAction::AddMessage(
+                    session_id,
+                    ChatMessage::StreamResponse(vec![response]),
+                  ))
+                  .unwrap();
+                },
+                Err(e) => {
+                  // a response that has begun is not sent again, it would be added twice
+                  let retry_after = provider.retry_after();
+                  if !received
+                    && rate_limit::wait_to_retry(&retry, attempt, &e, retry_after, status.clone())
+                      .await
+                  {
+                    attempt += 1;
+                    continue 'request;
+                  }
+                  log::error!("Error: {:#?} -- check https://status.openai.com", e);
+                  debug_request_validation(&request_clone);
+                  // let reqtext = format!("Request: \n{:#?}", request_clone.clone());
+                  // trace_dbg!(reqtext);
+                  // log::debug!("{:#?}", &request_clone);
+                  // let pretty_json = serde_json::to_string_pretty(&request_clone).unwrap().to_string();
+                  // log::debug!("{}", pretty_json);
+                  // tx.send(Action::AddMessage(ChatMessage::SazidSystemMessage(reqtext))).unwrap();
+                  tx.send(SessionAction::Error(format!(
+                    "Error: {:?} -- check https://status.openai.com/",
+                    e
+                  )))
+                  .unwrap();
+                },
+              }
+            }
           },
+          false => match provider.create(request.clone()).await {
+            Ok(response) => {
+              tx.send(SessionAction::AddMessage(session_id, ChatMessage::Response(response)))
+                .unwrap();
+            },
+            Err(e) => {
+              let retry_after = provider.retry_after();
+              if rate_limit::wait_to_retry(&retry, attempt, &e, retry_after, status.clone()).await {
+                attempt += 1;
+                continue 'request;
+              }
+              trace_dbg!("Error: {}", e);
+              tx.send(SessionAction::Error(format!(
+                "Error: {:#?} -- check https://status.openai.com/",
+                e
+              )))
+              .unwrap();
+            },
+          },
+        };
+        break;
+      }
+      tx.send(SessionAction::UpdateStatus(None)).unwrap();
+      tx.send(SessionAction::SaveSession).unwrap();
+    };
+    let task = tokio::spawn(send_request.instrument(span));
+    self.request_task = Some(task.abort_handle());
+  }
+
+  /// the text of the last message when it is a user message waiting for an answer and code is
+  /// to be retrieved for it
+  fn retrieval_query(&self) -> Option<String> {
+    let retrieval_enabled = self.config.retrieval.enabled && self.config.workspace.is_some();
+    match self.messages.last().map(|m| &m.message) {
+      Some(message @ ChatCompletionRequestMessage::User(_)) if retrieval_enabled => {
+        Some(get_chat_message_text(message))
+      },
+      _ => None,
+    }
+  }
+
+  /// titles and tags the session in the background once the first exchanges are answered
+  pub fn request_title(&mut self) {
+    if self.config.title_set
+      || self.title_in_progress
+      || self.title_attempts >= MAX_TITLE_ATTEMPTS
+      || self.is_receiving()
+    {
+      return;
+    }
+    let answered_exchanges = self
+      .messages
+      .iter()
+      .filter(|m| matches!(m.message, ChatCompletionRequestMessage::User(_)))
+      .count();
+    let last_is_assistant = matches!(
+      self.messages.last().map(|m| &m.message),
+      Some(ChatCompletionRequestMessage::Assistant(_))
+    );
+    if answered_exchanges < TITLE_AFTER_EXCHANGES || !last_is_assistant {
+      return;
+    }
+    let provider = match self.chat_provider(create_openai_client(&self.openai_config)) {
+      Ok(provider) => provider,
+      Err(e) => return log::error!("error generating session title: {}", e),
+    };
+    self.title_in_progress = true;
+    self.title_attempts += 1;
+
+    let tx = self.action_tx.clone().unwrap();
+    let session_id = self.id;
+    let user = self.config.user.clone();
+    let model = self.config.title_model();
+    let messages =
+      title_request_messages(&self.messages.iter().map(|m| m.message.clone()).collect::<Vec<_>>());
+    tokio::spawn(async move {
+      let request = construct_request(model.name, messages, None, Some(60), None, Some(user), None);
+      let title = match provider.create(request).await {
+        Ok(response) => response
+          .choices
+          .first()
+          .and_then(|choice| choice.message.content.as_deref())
+          .and_then(parse_title_response),
+        Err(e) => {
+          log::error!("error generating session title: {}", e);
+          None
         },
       };
-      tx.send(SessionAction::UpdateStatus(Some("Chat Request Complete".to_string()))).unwrap();
-      tx.send(SessionAction::SaveSession).unwrap();
+      tx.send(SessionAction::UpdateTitle(session_id, title)).unwrap();
+    });
+  }
+
+  /// asks the session's model for a commit message describing a staged diff
+  pub fn request_commit_message(
+    &self,
+    diff: &str,
+  ) -> impl std::future::Future<Output = Result<String, SazidError>> {
+    let provider = self.chat_provider(create_openai_client(&self.openai_config));
+    let request = construct_request(
+      self.config.model.name.clone(),
+      commit_message_request_messages(diff),
+      None,
+      Some(400),
+      None,
+      Some(self.config.user.clone()),
+      None,
+    );
+    async move {
+      let response = provider?.create(request).await?;
+      response
+        .choices
+        .first()
+        .and_then(|choice| choice.message.content.as_deref())
+        .map(parse_commit_message_response)
+        .ok_or_else(|| SazidError::Other("the response had no commit message".into()))
+    }
+  }
+
+  /// folds messages that no longer fit in the context window into the rolling summary
+  fn summarize_evicted_messages(&mut self, evicted_message_ids: &[i64]) {
+    let evicted = self
+      .messages
+      .iter()
+      .filter(|m| {
+        evicted_message_ids.contains(&m.message_id)
+          && !self.summarized_message_ids.contains(&m.message_id)
+      })
+      .map(|m| (m.message_id, m.message.clone()))
+      .collect::<Vec<(i64, ChatCompletionRequestMessage)>>();
+    if evicted.is_empty() || self.summary_in_progress {
+      return;
+    }
+    let provider = match self.chat_provider(create_openai_client(&self.openai_config)) {
+      Ok(provider) => provider,
+      Err(e) => return log::error!("error summarizing evicted messages: {}", e),
+    };
+    self.summary_in_progress = true;
+
+    let tx = self.action_tx.clone().unwrap();
+    let session_id = self.id;
+    let model = self.config.model.clone();
+    let user = self.config.user.clone();
+    let (message_ids, evicted): (Vec<i64>, Vec<ChatCompletionRequestMessage>) =
+      evicted.into_iter().unzip();
+    let messages = summary_request_messages(self.context_summary.as_deref(), &evicted);
+    tokio::spawn(async move {
+      let request = construct_request(model.name, messages, None, None, None, Some(user), None);
+      match provider.create(request).await {
+        Ok(response) => {
+          let summary = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .unwrap_or_default();
+          tx.send(SessionAction::UpdateContextSummary(session_id, message_ids, Some(summary)))
+            .unwrap();
+        },
+        Err(e) => {
+          log::error!("error summarizing evicted messages: {}", e);
+          tx.send(SessionAction::UpdateContextSummary(session_id, message_ids, None)).unwrap();
+          tx.send(SessionAction::Error(format!("Error summarizing context: {:?}", e))).unwrap();
+        },
+      }
     });
   }
 
@@ -614,6 +2095,7 @@ pub fn construct_request(
   messages: Vec<ChatCompletionRequestMessage>,
   stream: Option<bool>,
   max_tokens: Option<u16>,
+  temperature: Option<f32>,
   user: Option<String>,
   tools: Option<Vec<ChatCompletionTool>>,
 ) -> CreateChatCompletionRequest {
@@ -623,12 +2105,13 @@ pub fn construct_request(
     messages,
     stream,
     max_tokens,
+    temperature,
     user,
     tools,
     ..Default::default()
   }
 }
-pub fn create_openai_client(openai_config: &OpenAIConfig) -> async_openai::Client<OpenAIConfig> {
+pub fn create_openai_client<C: Config>(openai_config: &C) -> async_openai::Client<C> {
   let backoff = ExponentialBackoffBuilder::new() // Ensure backoff crate is added to Cargo.toml
     .with_max_elapsed_time(Some(std::time::Duration::from_secs(60)))
     .build();
@@ -644,3 +2127,122 @@ pub async fn create_embedding_request(
   let response = client.embeddings().create(request).await?;
   Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_interrupt_answers_running_tool_calls() {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut session = Session { action_tx: Some(tx), ..Default::default() };
+    assert!(!session.interrupt());
+
+    session.tool_calls_in_progress.push("call_1".to_string());
+    assert!(session.interrupt());
+    assert!(!session.is_busy());
+    let answer = get_chat_message_text(&session.messages.last().unwrap().message);
+    assert!(answer.ends_with(INTERRUPTED_MARKER));
+    let session_id = session.id;
+    assert!(std::iter::from_fn(|| rx.try_recv().ok()).any(|action| matches!(
+      action,
+      SessionAction::ChatToolAction(ChatToolAction::AbortToolCalls(id)) if id == session_id
+    )));
+
+    // the output of the call arriving after it was interrupted is dropped
+    assert!(session.complete_tool_call("call_1".to_string(), "done".to_string()).is_none());
+    assert_eq!(session.messages.len(), 1);
+  }
+  #[test]
+  fn test_read_more_pages_are_not_paged_again() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut session = Session { action_tx: Some(tx), ..Default::default() };
+    session.config.function_result_max_tokens = 8;
+    let output = (1..=40).map(|i| format!("line {}\n", i)).collect::<String>();
+    session.tool_outputs.insert("call_1".to_string(), output);
+    let arguments = r#"{"tool_call_id": "call_1", "offset": 0}"#;
+    let page = read_more(&session.tool_outputs, arguments, 64);
+    let tool_call = ChatCompletionMessageToolCall {
+      id: "call_2".to_string(),
+      r#type: async_openai::types::ChatCompletionToolType::Function,
+      function: async_openai::types::FunctionCall {
+        name: READ_MORE_TOOL.to_string(),
+        arguments: arguments.to_string(),
+      },
+    };
+    session.started_tool_calls.insert("call_2".to_string(), StartedToolCall::new(0, tool_call));
+    session.tool_calls_in_progress.push("call_2".to_string());
+
+    session.complete_tool_call("call_2".to_string(), page.clone());
+    assert_eq!(get_chat_message_text(&session.messages.last().unwrap().message), page);
+    assert!(!session.tool_outputs.contains_key("call_2"));
+  }
+  #[test]
+  fn test_timed_out_tool_call_is_answered_once() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut session = Session { action_tx: Some(tx), ..Default::default() };
+    session.tool_calls_in_progress.push("call_1".to_string());
+    let session_id = session.id;
+    let timed_out = || {
+      SessionAction::ToolCallTimedOut(
+        ToolType::Generic(session_id, "call_1".to_string()),
+        "timed out".to_string(),
+      )
+    };
+    let action = session.update(timed_out()).unwrap();
+    assert!(matches!(action, Some(SessionAction::RequestChatCompletion())));
+    assert_eq!(session.messages.len(), 1);
+
+    // the answer arriving after the deadline, and a second timeout, are dropped
+    assert!(session.complete_tool_call("call_1".to_string(), "done".to_string()).is_none());
+    assert!(session.update(timed_out()).unwrap().is_none());
+    assert_eq!(session.messages.len(), 1);
+  }
+  #[test]
+  fn test_inputs_queue_while_busy() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut session = Session { action_tx: Some(tx), ..Default::default() };
+    session.tool_calls_in_progress.push("call_1".to_string());
+    assert!(!session.turn_complete());
+
+    assert!(session.submit_or_queue("first".to_string()));
+    assert!(session.submit_or_queue("second".to_string()));
+    assert_eq!(session.queued_inputs, vec!["first", "second"]);
+    assert!(session.messages.is_empty());
+
+    // an interrupted turn does not send what was queued behind it
+    session.interrupt();
+    assert!(!session.turn_complete());
+    assert_eq!(session.queued_inputs.len(), 2);
+  }
+  #[test]
+  fn test_revert_reverts_every_file_of_a_call() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut session = Session { action_tx: Some(tx), ..Default::default() };
+    let edit = |name: &str, original: Option<&str>, modified: &str| {
+      let path = dir.path().join(name);
+      fs::write(&path, modified).unwrap();
+      FileEdit {
+        tool_call_id: "call_1".to_string(),
+        path,
+        original: original.map(str::to_string),
+        modified: modified.to_string(),
+      }
+    };
+    session.file_edits.push(edit("a.rs", Some("a"), "a2"));
+    session.file_edits.push(edit("b.rs", None, "b"));
+
+    // a file changed since the call leaves both alone
+    fs::write(dir.path().join("a.rs"), "a3").unwrap();
+    assert!(session.revert_file_edit("call_1").is_err());
+    assert_eq!(fs::read_to_string(dir.path().join("b.rs")).unwrap(), "b");
+
+    fs::write(dir.path().join("a.rs"), "a2").unwrap();
+    assert_eq!(session.revert_file_edit("call_1").unwrap().len(), 2);
+    assert_eq!(fs::read_to_string(dir.path().join("a.rs")).unwrap(), "a");
+    assert!(!dir.path().join("b.rs").exists());
+    assert!(session.file_edits.is_empty());
+    assert!(matches!(rx.try_recv(), Ok(SessionAction::FileEditReverted(id)) if id == "call_1"));
+  }
+}
@@ -1,6 +1,8 @@
+pub mod acp;
 pub mod action;
 pub mod app;
 pub mod cli;
 pub mod components;
 pub mod config;
+pub mod server;
 pub mod utils;
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_openai::types::{ChatCompletionMessageToolCall, ChatCompletionToolType, FunctionCall};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::UnixListener;
+
+use crate::app::errors::SazidError;
+use crate::app::headless_session::{HeadlessEvent, HeadlessSession};
+use crate::app::session_config::SessionConfig;
+use crate::app::session_store::SessionStore;
+use crate::cli::{ask_tools, call_tool};
+
+// json-rpc error codes
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+pub(crate) const SERVER_ERROR: i64 = -32000;
+
+/// the methods `serve` answers
+pub const METHODS: &[&str] =
+  &["session/new", "session/load", "session/send", "sessions/list", "tool/run"];
+
+/// a json-rpc request, a notification when it has no id
+#[derive(Deserialize, Debug)]
+pub(crate) struct Request {
+  #[serde(default)]
+  pub id: Option<Value>,
+  pub method: String,
+  #[serde(default)]
+  pub params: Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct NewParams {
+  #[serde(default)]
+  profile: Option<String>,
+  #[serde(default)]
+  model: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SendParams {
+  session_id: String,
+  message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoadParams {
+  session_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RunToolParams {
+  name: String,
+  #[serde(default)]
+  arguments: Value,
+  #[serde(default)]
+  session_id: Option<String>,
+}
+
+/// a json-rpc error response
+#[derive(Debug)]
+pub(crate) struct RpcError {
+  pub code: i64,
+  pub message: String,
+}
+
+impl RpcError {
+  pub fn invalid_params(e: serde_json::Error) -> Self {
+    RpcError { code: INVALID_PARAMS, message: e.to_string() }
+  }
+}
+
+impl From<SazidError> for RpcError {
+  fn from(e: SazidError) -> Self {
+    RpcError { code: SERVER_ERROR, message: e.to_string() }
+  }
+}
+
+/// answers json-rpc requests, one json object per line, on a unix socket until it is stopped.
+/// each connection has its own sessions, and its requests are answered in the order they arrive
+pub async fn serve(
+  socket: &Path,
+  config: SessionConfig,
+  store: SessionStore,
+) -> Result<(), SazidError> {
+  // a socket left by a server that did not stop cleanly
+  if socket.exists() {
+    std::fs::remove_file(socket)?;
+  }
+  let listener = UnixListener::bind(socket)?;
+  eprintln!("listening on {}", socket.display());
+  let store = Arc::new(Mutex::new(store));
+  loop {
+    let (stream, _) = listener.accept().await?;
+    let config = config.clone();
+    let store = store.clone();
+    tokio::spawn(async move {
+      let (reader, mut writer) = stream.into_split();
+      let mut connection = Connection { config, store, sessions: HashMap::new() };
+      let mut lines = BufReader::new(reader).lines();
+      while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+          continue;
+        }
+        if let Err(e) = connection.handle(&line, &mut writer).await {
+          log::debug!("closing connection: {}", e);
+          break;
+        }
+      }
+    });
+  }
+}
+
+struct Connection {
+  config: SessionConfig,
+  store: Arc<Mutex<SessionStore>>,
+  sessions: HashMap<String, HeadlessSession>,
+}
+
+impl Connection {
+  // answers one request line, errors are those of writing to the client
+  async fn handle(
+    &mut self,
+    line: &str,
+    writer: &mut (impl AsyncWrite + Unpin),
+  ) -> Result<(), SazidError> {
+    let request = match serde_json::from_str::<Request>(line) {
+      Ok(request) => request,
+      Err(e) => {
+        let error = RpcError { code: PARSE_ERROR, message: e.to_string() };
+        return write_message(writer, &error_response(Value::Null, error)).await;
+      },
+    };
+    let result = self.call(&request, writer).await;
+    let Some(id) = request.id else {
+      return Ok(());
+    };
+    let response = match result {
+      Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+      Err(error) => error_response(id, error),
+    };
+    write_message(writer, &response).await
+  }
+
+  async fn call(
+    &mut self,
+    request: &Request,
+    writer: &mut (impl AsyncWrite + Unpin),
+  ) -> Result<Value, RpcError> {
+    let params = request.params.clone();
+    match request.method.as_str() {
+      "session/new" => {
+        let params: NewParams = parse_params(params)?;
+        let mut config = self.config.clone();
+        config.id = SessionConfig::generate_session_id();
+        if let Some(model) = &params.model {
+          config.set_option("model", model)?;
+        }
+        if params.profile.is_some() {
+          config.prompt_profile = params.profile;
+        }
+        let session = HeadlessSession::new(config, self.store.clone())?;
+        Ok(self.add_session(session))
+      },
+      "session/load" => {
+        let params: LoadParams = parse_params(params)?;
+        let session =
+          HeadlessSession::load(self.config.clone(), self.store.clone(), &params.session_id)?;
+        Ok(self.add_session(session))
+      },
+      "session/send" => {
+        let params: SendParams = parse_params(params)?;
+        let Some(session) = self.sessions.get_mut(&params.session_id) else {
+          return Err(RpcError {
+            code: INVALID_PARAMS,
+            message: format!("no session {} on this connection", params.session_id),
+          });
+        };
+        session.send(&params.message);
+        loop {
+          let notification = match session.next_event().await? {
+            HeadlessEvent::Token(text) => json!({
+              "jsonrpc": "2.0",
+              "method": "session/token",
+              "params": { "session_id": params.session_id, "text": text },
+            }),
+            HeadlessEvent::ToolCall { id, name, arguments } => json!({
+              "jsonrpc": "2.0",
+              "method": "session/tool_call",
+              "params": {
+                "session_id": params.session_id,
+                "tool_call_id": id,
+                "name": name,
+                "arguments": arguments,
+              },
+            }),
+            HeadlessEvent::ToolResult { id, content } => json!({
+              "jsonrpc": "2.0",
+              "method": "session/tool_result",
+              "params": { "session_id": params.session_id, "tool_call_id": id, "content": content },
+            }),
+            HeadlessEvent::Done(answer) => return Ok(json!({ "answer": answer })),
+          };
+          write_message(writer, &notification).await?;
+        }
+      },
+      "sessions/list" => {
+        let sessions = self.store.lock().unwrap().list_sessions()?;
+        Ok(Value::Array(
+          sessions
+            .into_iter()
+            .map(|session| {
+              json!({
+                "id": session.id,
+                "title": session.title,
+                "tags": session.tags,
+                "updated_at": session.updated_at,
+                "message_count": session.message_count,
+              })
+            })
+            .collect(),
+        ))
+      },
+      "tool/run" => {
+        let params: RunToolParams = parse_params(params)?;
+        let config = match &params.session_id {
+          Some(id) => match self.sessions.get(id) {
+            Some(session) => session.session.config.clone(),
+            None => {
+              return Err(RpcError {
+                code: INVALID_PARAMS,
+                message: format!("no session {} on this connection", id),
+              })
+            },
+          },
+          None => self.config.clone(),
+        };
+        let tools = ask_tools(&[params.name.clone()], &config)?;
+        let tool_call = ChatCompletionMessageToolCall {
+          id: format!("rpc_{}", request.id.clone().unwrap_or_default()),
+          r#type: ChatCompletionToolType::Function,
+          function: FunctionCall { name: params.name, arguments: params.arguments.to_string() },
+        };
+        Ok(json!({ "result": call_tool(&tools, &tool_call, &config).await }))
+      },
+      method => Err(RpcError {
+        code: METHOD_NOT_FOUND,
+        message: format!("unknown method {}, the methods are: {}", method, METHODS.join(", ")),
+      }),
+    }
+  }
+
+  fn add_session(&mut self, session: HeadlessSession) -> Value {
+    let id = session.id().to_string();
+    let result = json!({ "session_id": id, "title": session.session.config.title });
+    self.sessions.insert(id, session);
+    result
+  }
+}
+
+pub(crate) fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+  // methods without required params can be called without any
+  let params = if params.is_null() { json!({}) } else { params };
+  serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+pub(crate) fn error_response(id: Value, error: RpcError) -> Value {
+  json!({
+    "jsonrpc": "2.0",
+    "id": id,
+    "error": { "code": error.code, "message": error.message },
+  })
+}
+
+pub(crate) async fn write_message(
+  writer: &mut (impl AsyncWrite + Unpin),
+  message: &Value,
+) -> Result<(), SazidError> {
+  let mut line = message.to_string();
+  line.push('\n');
+  writer.write_all(line.as_bytes()).await?;
+  writer.flush().await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_requests() {
+    let mut connection = Connection {
+      config: SessionConfig::default(),
+      store: Arc::new(Mutex::new(SessionStore::open_in_memory().unwrap())),
+      sessions: HashMap::new(),
+    };
+    let mut out = vec![];
+    connection
+      .handle(r#"{"jsonrpc":"2.0","id":1,"method":"sessions/list"}"#, &mut out)
+      .await
+      .unwrap();
+    connection
+      .handle(r#"{"jsonrpc":"2.0","id":2,"method":"session/send","params":{}}"#, &mut out)
+      .await
+      .unwrap();
+    connection
+      .handle(r#"{"jsonrpc":"2.0","id":3,"method":"session/delete"}"#, &mut out)
+      .await
+      .unwrap();
+    connection.handle("not json", &mut out).await.unwrap();
+    let responses = String::from_utf8(out)
+      .unwrap()
+      .lines()
+      .map(|line| serde_json::from_str::<Value>(line).unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(responses[0], json!({ "jsonrpc": "2.0", "id": 1, "result": [] }));
+    assert_eq!(responses[1]["error"]["code"], INVALID_PARAMS);
+    assert_eq!(responses[2]["error"]["code"], METHOD_NOT_FOUND);
+    assert_eq!(responses[3]["error"]["code"], PARSE_ERROR);
+  }
+}